@@ -12,11 +12,23 @@ fn main() -> Result<(), Box<dyn Error + 'static>> {
 		}
 	}
 
-	if ac.probe_expression("std::result::Result::<(), ()>::unwrap_unchecked")
-		&& ac.probe_expression("std::option::Option::<()>::unwrap_unchecked")
-	{
-		emit("has_unwrap_unchecked");
-	}
+	#[cfg(feature = "grpc")]
+	compile_protos()?;
+
+	Ok(())
+}
+
+/// Generates the client, and the service if the `grpc-server` feature is enabled, from the
+/// protocol definition, using a vendored `protoc` so one doesn't need to be installed.
+#[cfg(feature = "grpc")]
+fn compile_protos() -> Result<(), Box<dyn Error + 'static>> {
+	std::env::set_var("PROTOC", protoc_bin_vendored::protoc_bin_path()?);
+
+	tonic_prost_build::configure()
+		.build_server(cfg!(feature = "grpc-server"))
+		// the generated `connect` relies on the 2021 prelude, and the backend makes it's own channel anyways.
+		.build_transport(false)
+		.compile_protos(&["proto/starchart.proto"], &["proto"])?;
 
 	Ok(())
 }
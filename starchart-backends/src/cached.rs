@@ -0,0 +1,808 @@
+//! A read-through cache in front of another backend.
+
+#[cfg(feature = "memory")]
+use std::hash::BuildHasher;
+use std::{
+	collections::{BTreeMap, HashMap},
+	error::Error,
+	fmt::{Debug, Display, Formatter, Result as FmtResult},
+	iter::FromIterator,
+	sync::{Mutex, MutexGuard, PoisonError},
+};
+
+use futures_util::FutureExt;
+use starchart::{
+	backend::{
+		futures::{
+			CreateFuture, CreateMultiFuture, CreateTableFuture, DeleteFuture, DeleteTableFuture,
+			GetFuture, GetKeysFuture, HasFuture, HasTableFuture, HealthCheckFuture, InitFuture,
+			RenameTableFuture, ReplaceFuture, ShutdownFuture, TablesFuture, TruncateTableFuture,
+			UpdateFuture, UpdateMultiFuture,
+		},
+		Backend, BackendLayer,
+	},
+	Entry,
+};
+
+#[cfg(feature = "memory")]
+use crate::memory::{MemoryBackend, Snapshot};
+
+/// An error returned from the [`CachedBackend`].
+#[cfg(feature = "cache")]
+#[derive(Debug)]
+pub struct CachedError {
+	source: Option<Box<dyn Error + Send + Sync>>,
+	kind: CachedErrorType,
+}
+
+impl CachedError {
+	/// Immutable reference to the type of error that occurred.
+	#[must_use = "retrieving the type has no effect if left unused"]
+	pub const fn kind(&self) -> &CachedErrorType {
+		&self.kind
+	}
+
+	/// Consume the error, returning the source error if there is any.
+	#[must_use = "consuming the error and retrieving the source has no effect if left unused"]
+	pub fn into_source(self) -> Option<Box<dyn Error + Send + Sync>> {
+		self.source
+	}
+
+	/// Consume the error, returning the owned error type and the source error.
+	#[must_use = "consuming the error into it's parts has no effect if left unused"]
+	pub fn into_parts(self) -> (CachedErrorType, Option<Box<dyn Error + Send + Sync>>) {
+		(self.kind, self.source)
+	}
+
+	fn primary<E: Error + Send + Sync + 'static>(e: E) -> Self {
+		Self {
+			source: Some(Box::new(e)),
+			kind: CachedErrorType::Primary,
+		}
+	}
+
+	fn cache<E: Error + Send + Sync + 'static>(e: E) -> Self {
+		Self {
+			source: Some(Box::new(e)),
+			kind: CachedErrorType::Cache,
+		}
+	}
+}
+
+impl Display for CachedError {
+	fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+		match &self.kind {
+			CachedErrorType::Primary => f.write_str("an error occurred in the primary backend"),
+			CachedErrorType::Cache => f.write_str("an error occurred invalidating the cache"),
+		}
+	}
+}
+
+impl Error for CachedError {
+	fn source(&self) -> Option<&(dyn Error + 'static)> {
+		self.source
+			.as_ref()
+			.map(|source| &**source as &(dyn Error + 'static))
+	}
+}
+
+impl From<CachedError> for starchart::Error {
+	fn from(e: CachedError) -> Self {
+		Self::backend(Some(Box::new(e)))
+	}
+}
+
+/// The type of [`CachedError`] that occurred.
+#[cfg(feature = "cache")]
+#[allow(missing_copy_implementations)]
+#[derive(Debug)]
+#[non_exhaustive]
+pub enum CachedErrorType {
+	/// The primary backend returned an error.
+	Primary,
+	/// The cache backend returned an error while removing a changed entry.
+	///
+	/// The change was still written to the primary backend.
+	Cache,
+}
+
+/// The entries held by the cache, in the order they were last used.
+#[derive(Debug, Default)]
+struct Lru {
+	tick: u64,
+	entries: HashMap<(String, String), u64>,
+	order: BTreeMap<u64, (String, String)>,
+	// bumped on every invalidation, so reads that raced a write don't fill the cache with the old entry.
+	generation: u64,
+}
+
+impl Lru {
+	fn contains(&self, table: &str, key: &str) -> bool {
+		self.entries
+			.contains_key(&(table.to_owned(), key.to_owned()))
+	}
+
+	/// Marks the entry as the most recently used, returning the entries evicted to stay within capacity.
+	fn touch(&mut self, table: &str, key: &str, capacity: usize) -> Vec<(String, String)> {
+		self.tick += 1;
+
+		let id = (table.to_owned(), key.to_owned());
+		if let Some(tick) = self.entries.insert(id.clone(), self.tick) {
+			self.order.remove(&tick);
+		}
+		self.order.insert(self.tick, id);
+
+		let mut evicted = Vec::new();
+		while self.entries.len() > capacity {
+			let Some((_, id)) = self.order.pop_first() else {
+				break;
+			};
+
+			self.entries.remove(&id);
+			evicted.push(id);
+		}
+
+		evicted
+	}
+
+	fn remove(&mut self, table: &str, key: &str) {
+		self.generation += 1;
+
+		if let Some(tick) = self.entries.remove(&(table.to_owned(), key.to_owned())) {
+			self.order.remove(&tick);
+		}
+	}
+
+	fn remove_table(&mut self, table: &str) {
+		self.generation += 1;
+
+		self.entries
+			.retain(|(entry_table, _), _| entry_table != table);
+		self.order
+			.retain(|_, (entry_table, _)| entry_table != table);
+	}
+}
+
+/// A [`Backend`] that serves reads from a cache [`Backend`], and writes through to a primary [`Backend`].
+///
+/// Entries read from the primary backend are copied to the cache, which holds up to the given capacity of
+/// entries, evicting the least recently used entry once it's full. Writes go to the primary backend,
+/// and remove the entry from the cache, so the next read fetches the new entry. Everything else, such as
+/// listing keys, goes straight to the primary backend.
+///
+/// The cache must only be written to through the [`CachedBackend`]. Entry expiry is always left to the
+/// [`Starchart`], which deletes expired entries through the [`CachedBackend`], so they're never served
+/// from the cache after they expire.
+///
+/// [`Starchart`]: starchart::Starchart
+#[cfg(feature = "cache")]
+pub struct CachedBackend<P, C> {
+	primary: P,
+	cache: C,
+	capacity: usize,
+	lru: Mutex<Lru>,
+}
+
+/// A [`BackendLayer`] that wraps a [`Backend`] in a [`CachedBackend`], as the primary backend.
+#[cfg(feature = "cache")]
+#[derive(Debug, Clone)]
+pub struct CacheLayer<C> {
+	cache: C,
+	capacity: usize,
+}
+
+impl<C> CacheLayer<C> {
+	/// Creates a new [`CacheLayer`], holding up to `capacity` entries in the cache.
+	///
+	/// The cache is cloned into every backend the layer wraps, and like any cache of a [`CachedBackend`],
+	/// each clone must only be written to through the backend it was given to.
+	#[must_use]
+	pub const fn new(cache: C, capacity: usize) -> Self {
+		Self { cache, capacity }
+	}
+}
+
+impl<P: Backend, C: Backend + Clone> BackendLayer<P> for CacheLayer<C> {
+	type Backend = CachedBackend<P, C>;
+
+	fn layer(&self, inner: P) -> Self::Backend {
+		CachedBackend::new(inner, self.cache.clone(), self.capacity)
+	}
+}
+
+impl<P: Backend, C: Backend> CachedBackend<P, C> {
+	/// Creates a new [`CachedBackend`], holding up to `capacity` entries in the cache.
+	#[must_use]
+	pub fn new(primary: P, cache: C, capacity: usize) -> Self {
+		Self {
+			primary,
+			cache,
+			capacity,
+			lru: Mutex::default(),
+		}
+	}
+
+	/// Returns the primary [`Backend`].
+	pub const fn primary(&self) -> &P {
+		&self.primary
+	}
+
+	/// Returns the cache [`Backend`].
+	pub const fn cache(&self) -> &C {
+		&self.cache
+	}
+
+	/// The maximum amount of entries held in the cache.
+	#[must_use]
+	pub const fn capacity(&self) -> usize {
+		self.capacity
+	}
+
+	/// The amount of entries currently held in the cache.
+	#[must_use]
+	pub fn len(&self) -> usize {
+		self.lru().entries.len()
+	}
+
+	/// Whether the cache holds no entries.
+	#[must_use]
+	pub fn is_empty(&self) -> bool {
+		self.len() == 0
+	}
+
+	/// Removes every entry from the cache, so the next reads fetch them from the primary backend.
+	///
+	/// # Errors
+	///
+	/// Returns an error if the cache backend fails to delete an entry, entries that weren't
+	/// deleted are no longer tracked and are never served again.
+	pub async fn clear_cache(&self) -> Result<(), CachedError> {
+		let cached = {
+			let mut lru = self.lru();
+			lru.generation += 1;
+			lru.order.clear();
+			lru.entries.drain().map(|(id, _)| id).collect::<Vec<_>>()
+		};
+
+		for (table, key) in cached {
+			self.cache
+				.delete(&table, &key)
+				.await
+				.map_err(CachedError::cache)?;
+		}
+
+		Ok(())
+	}
+
+	fn lru(&self) -> MutexGuard<'_, Lru> {
+		self.lru.lock().unwrap_or_else(PoisonError::into_inner)
+	}
+
+	/// Copies an entry read from the primary backend into the cache, unless it was changed since it was read.
+	async fn fill<D: Entry>(&self, table: &str, key: &str, entry: &D, generation: u64) {
+		if self.capacity == 0 {
+			return;
+		}
+
+		// failing to fill the cache only costs another read from the primary backend.
+		if self.cache.ensure_table(table).await.is_err()
+			|| self.cache.replace(table, key, entry).await.is_err()
+		{
+			return;
+		}
+
+		let evicted = {
+			let mut lru = self.lru();
+			(lru.generation == generation).then(|| lru.touch(table, key, self.capacity))
+		};
+
+		let Some(evicted) = evicted else {
+			let _removed = self.cache.delete(table, key).await;
+			return;
+		};
+
+		for (table, key) in evicted {
+			let _removed = self.cache.delete(&table, &key).await;
+		}
+	}
+
+	/// Removes an entry from the cache after it's been changed.
+	/// Drops every cached entry of a table that changed as a whole.
+	async fn evict_table(&self, table: &str) -> Result<(), CachedError> {
+		self.lru().remove_table(table);
+
+		if self
+			.cache
+			.has_table(table)
+			.await
+			.map_err(CachedError::cache)?
+		{
+			self.cache
+				.delete_table(table)
+				.await
+				.map_err(CachedError::cache)?;
+		}
+
+		Ok(())
+	}
+
+	async fn invalidate(&self, table: &str, key: &str) -> Result<(), CachedError> {
+		let cached = {
+			let mut lru = self.lru();
+			let cached = lru.contains(table, key);
+			lru.remove(table, key);
+			cached
+		};
+
+		if cached {
+			self.cache
+				.delete(table, key)
+				.await
+				.map_err(CachedError::cache)?;
+		}
+
+		Ok(())
+	}
+
+	async fn invalidate_many<S: Sync>(
+		&self,
+		table: &str,
+		entries: &[(&str, &S)],
+	) -> Result<(), CachedError> {
+		for (key, _) in entries {
+			self.invalidate(table, key).await?;
+		}
+
+		Ok(())
+	}
+}
+
+#[cfg(feature = "memory")]
+impl<S, C> CachedBackend<MemoryBackend<S>, C>
+where
+	S: BuildHasher + Clone + Send + Sync,
+	C: Backend,
+{
+	/// Copies every table and entry of the primary backend into a [`Snapshot`], see [`MemoryBackend::snapshot`].
+	pub fn snapshot(&self) -> Snapshot {
+		self.primary.snapshot()
+	}
+
+	/// Replaces every table and entry of the primary backend with the ones in the [`Snapshot`], and clears
+	/// the cache so entries from before the snapshot was restored are never served.
+	///
+	/// # Errors
+	///
+	/// Returns an error if the cache couldn't be cleared, see [`Self::clear_cache`]. The snapshot is
+	/// restored either way.
+	pub async fn restore(&self, snapshot: Snapshot) -> Result<(), CachedError> {
+		self.primary.restore(snapshot);
+
+		self.clear_cache().await
+	}
+}
+
+impl<P: Debug, C: Debug> Debug for CachedBackend<P, C> {
+	fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+		f.debug_struct("CachedBackend")
+			.field("primary", &self.primary)
+			.field("cache", &self.cache)
+			.field("capacity", &self.capacity)
+			.finish_non_exhaustive()
+	}
+}
+
+impl<P: Backend, C: Backend> Backend for CachedBackend<P, C> {
+	type Error = CachedError;
+
+	fn init(&self) -> InitFuture<'_, Self::Error> {
+		async move {
+			self.primary.init().await.map_err(CachedError::primary)?;
+			self.cache.init().await.map_err(CachedError::cache)
+		}
+		.boxed()
+	}
+
+	unsafe fn shutdown(&self) -> ShutdownFuture<'_> {
+		async move {
+			unsafe {
+				self.primary.shutdown().await;
+				self.cache.shutdown().await;
+			}
+		}
+		.boxed()
+	}
+
+	fn health_check(&self) -> HealthCheckFuture<'_, Self::Error> {
+		async move {
+			self.primary
+				.health_check()
+				.await
+				.map_err(CachedError::primary)?;
+			self.cache.health_check().await.map_err(CachedError::cache)
+		}
+		.boxed()
+	}
+
+	fn is_self_describing(&self) -> bool {
+		self.primary.is_self_describing() && self.cache.is_self_describing()
+	}
+
+	fn entry_size<S: Entry>(&self, table: &str, id: &str, entry: &S) -> Option<usize> {
+		self.primary.entry_size(table, id, entry)
+	}
+
+	fn has_table<'a>(&'a self, table: &'a str) -> HasTableFuture<'a, Self::Error> {
+		self.primary
+			.has_table(table)
+			.map(|result| result.map_err(CachedError::primary))
+			.boxed()
+	}
+
+	fn tables<I>(&self) -> TablesFuture<'_, I, Self::Error>
+	where
+		I: FromIterator<String>,
+	{
+		async move { self.primary.tables().await.map_err(CachedError::primary) }.boxed()
+	}
+
+	fn create_table<'a>(&'a self, table: &'a str) -> CreateTableFuture<'a, Self::Error> {
+		self.primary
+			.create_table(table)
+			.map(|result| result.map_err(CachedError::primary))
+			.boxed()
+	}
+
+	fn delete_table<'a>(&'a self, table: &'a str) -> DeleteTableFuture<'a, Self::Error> {
+		async move {
+			self.primary
+				.delete_table(table)
+				.await
+				.map_err(CachedError::primary)?;
+
+			self.evict_table(table).await
+		}
+		.boxed()
+	}
+
+	fn rename_table<'a>(
+		&'a self,
+		table: &'a str,
+		new: &'a str,
+	) -> RenameTableFuture<'a, Self::Error> {
+		async move {
+			self.primary
+				.rename_table(table, new)
+				.await
+				.map_err(CachedError::primary)?;
+
+			self.evict_table(table).await
+		}
+		.boxed()
+	}
+
+	fn truncate_table<'a>(&'a self, table: &'a str) -> TruncateTableFuture<'a, Self::Error> {
+		async move {
+			self.primary
+				.truncate_table(table)
+				.await
+				.map_err(CachedError::primary)?;
+
+			self.evict_table(table).await
+		}
+		.boxed()
+	}
+
+	fn get_keys<'a, I>(&'a self, table: &'a str) -> GetKeysFuture<'a, I, Self::Error>
+	where
+		I: FromIterator<String>,
+	{
+		async move {
+			self.primary
+				.get_keys(table)
+				.await
+				.map_err(CachedError::primary)
+		}
+		.boxed()
+	}
+
+	fn get<'a, D>(&'a self, table: &'a str, id: &'a str) -> GetFuture<'a, D, Self::Error>
+	where
+		D: Entry,
+	{
+		async move {
+			let cached = self.lru().contains(table, id);
+			if cached {
+				// a failed read from the cache falls back to the primary backend.
+				if let Ok(Some(entry)) = self.cache.get::<D>(table, id).await {
+					self.lru().touch(table, id, self.capacity);
+					return Ok(Some(entry));
+				}
+			}
+
+			let generation = self.lru().generation;
+
+			let entry = self
+				.primary
+				.get::<D>(table, id)
+				.await
+				.map_err(CachedError::primary)?;
+
+			if let Some(entry) = &entry {
+				self.fill(table, id, entry, generation).await;
+			}
+
+			Ok(entry)
+		}
+		.boxed()
+	}
+
+	fn has<'a>(&'a self, table: &'a str, id: &'a str) -> HasFuture<'a, Self::Error> {
+		async move {
+			let cached = self.lru().contains(table, id);
+			if cached {
+				return Ok(true);
+			}
+
+			self.primary
+				.has(table, id)
+				.await
+				.map_err(CachedError::primary)
+		}
+		.boxed()
+	}
+
+	fn create<'a, S>(
+		&'a self,
+		table: &'a str,
+		id: &'a str,
+		value: &'a S,
+	) -> CreateFuture<'a, Self::Error>
+	where
+		S: Entry,
+	{
+		async move {
+			self.primary
+				.create(table, id, value)
+				.await
+				.map_err(CachedError::primary)?;
+
+			self.invalidate(table, id).await
+		}
+		.boxed()
+	}
+
+	fn create_multi<'a, S>(
+		&'a self,
+		table: &'a str,
+		entries: &'a [(&'a str, &'a S)],
+	) -> CreateMultiFuture<'a, Self::Error>
+	where
+		S: Entry,
+	{
+		async move {
+			self.primary
+				.create_multi(table, entries)
+				.await
+				.map_err(CachedError::primary)?;
+
+			self.invalidate_many(table, entries).await
+		}
+		.boxed()
+	}
+
+	fn replace<'a, S>(
+		&'a self,
+		table: &'a str,
+		id: &'a str,
+		value: &'a S,
+	) -> ReplaceFuture<'a, Self::Error>
+	where
+		S: Entry,
+	{
+		async move {
+			self.primary
+				.replace(table, id, value)
+				.await
+				.map_err(CachedError::primary)?;
+
+			self.invalidate(table, id).await
+		}
+		.boxed()
+	}
+
+	fn update<'a, S>(
+		&'a self,
+		table: &'a str,
+		id: &'a str,
+		value: &'a S,
+	) -> UpdateFuture<'a, Self::Error>
+	where
+		S: Entry,
+	{
+		async move {
+			self.primary
+				.update(table, id, value)
+				.await
+				.map_err(CachedError::primary)?;
+
+			self.invalidate(table, id).await
+		}
+		.boxed()
+	}
+
+	fn update_multi<'a, S>(
+		&'a self,
+		table: &'a str,
+		entries: &'a [(&'a str, &'a S)],
+	) -> UpdateMultiFuture<'a, Self::Error>
+	where
+		S: Entry,
+	{
+		async move {
+			self.primary
+				.update_multi(table, entries)
+				.await
+				.map_err(CachedError::primary)?;
+
+			self.invalidate_many(table, entries).await
+		}
+		.boxed()
+	}
+
+	fn set_pinned(&self, table: &str, id: &str, pinned: bool) {
+		// evicting from the cache never loses the entry, so only the primary is told.
+		self.primary.set_pinned(table, id, pinned);
+	}
+
+	fn delete<'a>(&'a self, table: &'a str, id: &'a str) -> DeleteFuture<'a, Self::Error> {
+		async move {
+			self.primary
+				.delete(table, id)
+				.await
+				.map_err(CachedError::primary)?;
+
+			self.invalidate(table, id).await
+		}
+		.boxed()
+	}
+}
+
+#[cfg(all(test, feature = "memory"))]
+mod tests {
+	use std::fmt::Debug;
+
+	use starchart::{
+		action::UpdateEntryAction,
+		backend::{testsuite, Backend, BackendBuilder, TieredLayer},
+		Error, Starchart,
+	};
+	use static_assertions::assert_impl_all;
+
+	use super::{CacheLayer, CachedBackend, CachedError};
+	use crate::{memory::MemoryBackend, testing::TestSettings};
+
+	assert_impl_all!(CachedBackend<MemoryBackend, MemoryBackend>: Backend, Debug, Send, Sync);
+
+	#[tokio::test]
+	async fn testsuite() -> Result<(), CachedError> {
+		testsuite::run(CachedBackend::new(
+			MemoryBackend::new(),
+			MemoryBackend::new(),
+			2,
+		))
+		.await
+	}
+
+	#[tokio::test]
+	async fn read_through() -> Result<(), Error> {
+		let chart = Starchart::new(CachedBackend::new(
+			MemoryBackend::new(),
+			MemoryBackend::new(),
+			2,
+		))
+		.await?;
+		chart.create_table("table").await?;
+
+		let table = chart.table::<TestSettings>("table");
+		for id in 1..=3 {
+			table
+				.insert(&TestSettings {
+					id,
+					..TestSettings::default()
+				})
+				.await?;
+		}
+		assert!(chart.is_empty());
+
+		assert!(table.get(&1_u32).await?.is_some());
+		assert!(chart.cache().has("table", "1").await?);
+		assert!(table.get(&2_u32).await?.is_some());
+		assert!(table.get(&1_u32).await?.is_some());
+		assert!(table.get(&3_u32).await?.is_some());
+
+		// the second entry was the least recently used.
+		assert_eq!(chart.len(), 2);
+		assert!(!chart.cache().has("table", "2").await?);
+		assert!(chart.cache().has("table", "1").await?);
+
+		let updated = TestSettings {
+			value: "updated".to_owned(),
+			..TestSettings::default()
+		};
+		UpdateEntryAction::for_table("table")
+			.with_entry(&updated)
+			.run_update_entry(&chart)
+			.await?;
+		assert!(!chart.cache().has("table", "1").await?);
+		assert_eq!(table.get(&1_u32).await?, Some(updated));
+
+		table.remove(&1_u32).await?;
+		assert!(table.get(&1_u32).await?.is_none());
+		assert!(!chart.has("table", "1").await?);
+
+		chart.delete_table("table").await?;
+		assert!(chart.is_empty());
+		assert!(!chart.cache().has_table("table").await?);
+
+		Ok(())
+	}
+
+	#[tokio::test]
+	async fn snapshots() -> Result<(), Error> {
+		let chart = Starchart::new(CachedBackend::new(
+			MemoryBackend::new(),
+			MemoryBackend::new(),
+			4,
+		))
+		.await?;
+		chart.create_table("table").await?;
+
+		let table = chart.table::<TestSettings>("table");
+		table.insert(&TestSettings::default()).await?;
+		let snapshot = chart.snapshot();
+
+		let updated = TestSettings {
+			value: "updated".to_owned(),
+			..TestSettings::default()
+		};
+		UpdateEntryAction::for_table("table")
+			.with_entry(&updated)
+			.run_update_entry(&chart)
+			.await?;
+		assert_eq!(table.get(&1_u32).await?, Some(updated));
+		assert!(!chart.is_empty());
+
+		// the cached entry is from after the snapshot, so restoring it clears the cache.
+		let backend: &CachedBackend<_, _> = &chart;
+		backend.restore(snapshot).await?;
+		assert!(chart.is_empty());
+		assert!(!chart.cache().has("table", "1").await?);
+		assert_eq!(table.get(&1_u32).await?, Some(TestSettings::default()));
+
+		Ok(())
+	}
+
+	#[tokio::test]
+	async fn layered() -> Result<(), Error> {
+		let backend = BackendBuilder::new()
+			.layer(CacheLayer::new(MemoryBackend::new(), 4))
+			.layer(TieredLayer::new(MemoryBackend::new()))
+			.backend(MemoryBackend::new());
+		let chart = Starchart::new(backend).await?;
+		chart.create_table("table").await?;
+
+		let table = chart.table::<TestSettings>("table");
+		table.insert(&TestSettings::default()).await?;
+
+		// the first layer added is the outermost, so the cache sits in front of both tiers.
+		assert!(chart.primary().replica().has("table", "1").await?);
+		assert!(chart.primary().primary().has("table", "1").await?);
+		assert!(chart.is_empty());
+
+		assert_eq!(table.get(&1_u32).await?, Some(TestSettings::default()));
+		assert!(chart.cache().has("table", "1").await?);
+
+		Ok(())
+	}
+}
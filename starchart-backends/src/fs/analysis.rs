@@ -0,0 +1,348 @@
+use starchart::backend::Backend;
+
+#[cfg(any(feature = "gzip", feature = "zstd"))]
+use super::compressed::Compression;
+use super::{io, Envelope, FsBackend, FsError, Transcoder};
+
+/// Tables averaging less than this many bytes per entry aren't worth compressing.
+const MIN_COMPRESSIBLE_BYTES: u64 = 128;
+
+/// Compression that saves less than this share of the space isn't worth the time spent on it.
+const MIN_SAVINGS: f64 = 0.1;
+
+/// Text entries averaging at least this many bytes would usually be noticeably smaller in a binary format.
+const LARGE_TEXT_BYTES: u64 = 1024;
+
+/// Statistics on how a sample of a table's entries is stored, from [`FsBackend::analyze`].
+///
+/// Sizes are only measured for the sampled entries, so they're estimates for the whole table.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+#[allow(missing_copy_implementations)]
+pub struct TableAnalysis {
+	entries: usize,
+	sampled: usize,
+	stored_bytes: u64,
+	plain_bytes: u64,
+	compressed: usize,
+	opaque: usize,
+	text: usize,
+	#[cfg(any(feature = "gzip", feature = "zstd"))]
+	estimates: Vec<(Compression, u64)>,
+}
+
+impl TableAnalysis {
+	/// The amount of entries in the table.
+	#[must_use]
+	pub const fn entries(&self) -> usize {
+		self.entries
+	}
+
+	/// The amount of entries that were sampled.
+	#[must_use]
+	pub const fn sampled(&self) -> usize {
+		self.sampled
+	}
+
+	/// The bytes the sampled entries take up on disk.
+	#[must_use]
+	pub const fn stored_bytes(&self) -> u64 {
+		self.stored_bytes
+	}
+
+	/// The bytes the sampled entries would take up without compression.
+	///
+	/// Encrypted entries can't be decompressed without their key, so their stored size is used instead.
+	#[must_use]
+	pub const fn plain_bytes(&self) -> u64 {
+		self.plain_bytes
+	}
+
+	/// The average size of a sampled entry without compression.
+	#[must_use]
+	pub const fn average_plain_bytes(&self) -> u64 {
+		match self.sampled {
+			0 => 0,
+			sampled => self.plain_bytes / sampled as u64,
+		}
+	}
+
+	/// The amount of sampled entries that were stored compressed.
+	#[must_use]
+	pub const fn compressed(&self) -> usize {
+		self.compressed
+	}
+
+	/// The amount of sampled entries that were stored encrypted, which can't be analyzed further.
+	#[must_use]
+	pub const fn opaque(&self) -> usize {
+		self.opaque
+	}
+
+	/// The stored size of the sampled entries relative to their size without compression,
+	/// or [`None`] if nothing was sampled.
+	#[must_use]
+	pub fn compression_ratio(&self) -> Option<f64> {
+		ratio(self.stored_bytes, self.plain_bytes)
+	}
+
+	/// The size the sampled entries would be compressed with the [`Compression`] relative to their size
+	/// without compression, or [`None`] if nothing was sampled.
+	#[cfg(any(feature = "gzip", feature = "zstd"))]
+	#[must_use]
+	pub fn estimated_ratio(&self, compression: Compression) -> Option<f64> {
+		self.estimates
+			.iter()
+			.find(|(estimated, _)| *estimated == compression)
+			.and_then(|(_, bytes)| ratio(*bytes, self.plain_bytes))
+	}
+
+	/// Suggests settings that would store the table more efficiently.
+	///
+	/// These are heuristics based on the sampled entries, and don't account for how often entries are
+	/// read or written. An empty list means no changes are suggested.
+	#[must_use]
+	pub fn advice(&self) -> Vec<Advice> {
+		let mut advice = Vec::new();
+
+		if self.sampled == 0 || self.opaque == self.sampled {
+			return advice;
+		}
+
+		let small = self.average_plain_bytes() < MIN_COMPRESSIBLE_BYTES;
+		let current = self.compression_ratio().unwrap_or(1.0);
+
+		#[cfg(any(feature = "gzip", feature = "zstd"))]
+		let best = self
+			.estimates
+			.iter()
+			.filter_map(|(compression, bytes)| {
+				ratio(*bytes, self.plain_bytes).map(|ratio| (*compression, ratio))
+			})
+			.min_by(|(_, a), (_, b)| a.total_cmp(b))
+			.filter(|(_, ratio)| *ratio < current - MIN_SAVINGS);
+
+		if self.compressed > 0 && (small || current > 1.0 - MIN_SAVINGS) {
+			advice.push(Advice::DisableCompression { ratio: current });
+		} else {
+			#[cfg(any(feature = "gzip", feature = "zstd"))]
+			if let Some((compression, ratio)) = best.filter(|_| !small) {
+				advice.push(Advice::Compress { compression, ratio });
+			}
+		}
+
+		if self.text == self.sampled && self.average_plain_bytes() >= LARGE_TEXT_BYTES {
+			advice.push(Advice::UseBinaryFormat);
+		}
+
+		advice
+	}
+}
+
+/// A suggestion from [`TableAnalysis::advice`].
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[non_exhaustive]
+pub enum Advice {
+	/// Compressing entries with a [`CompressedTranscoder`] would save space.
+	///
+	/// [`CompressedTranscoder`]: super::transcoders::CompressedTranscoder
+	#[cfg(any(feature = "gzip", feature = "zstd"))]
+	Compress {
+		/// The codec that compressed the sampled entries best.
+		compression: Compression,
+		/// The estimated size of compressed entries, relative to their size without compression.
+		ratio: f64,
+	},
+	/// Entries are compressed, but are too small or too random for it to save much space.
+	DisableCompression {
+		/// The size of the stored entries, relative to their size without compression.
+		ratio: f64,
+	},
+	/// Entries are large and stored as text, and would usually be smaller with a binary transcoder,
+	/// such as the [`BinaryTranscoder`].
+	///
+	/// [`BinaryTranscoder`]: super::transcoders::BinaryTranscoder
+	UseBinaryFormat,
+}
+
+impl<T: Transcoder> FsBackend<T> {
+	/// Samples up to `sample` entries of the table, measuring how they're stored.
+	///
+	/// Entries are sampled evenly across the table's keys, and are read without being decoded,
+	/// so this works for any entry type.
+	///
+	/// # Errors
+	///
+	/// Returns an error if the table or any of the sampled entries could not be read.
+	pub async fn analyze(&self, table: &str, sample: usize) -> Result<TableAnalysis, FsError> {
+		let mut keys = self.get_keys::<Vec<_>>(table).await?;
+		keys.sort_unstable();
+
+		let mut analysis = TableAnalysis {
+			entries: keys.len(),
+			..TableAnalysis::default()
+		};
+
+		let step = match sample {
+			0 => return Ok(analysis),
+			sample => (keys.len() / sample).max(1),
+		};
+
+		for key in keys.iter().step_by(step).take(sample) {
+			let Some(path) = self.find_entry(table, key).await? else {
+				continue;
+			};
+
+			let data = io::read(&path).await?;
+			analysis.sample(&data)?;
+		}
+
+		Ok(analysis)
+	}
+}
+
+impl TableAnalysis {
+	fn sample(&mut self, data: &[u8]) -> Result<(), FsError> {
+		self.sampled += 1;
+		self.stored_bytes += data.len() as u64;
+
+		let (envelope, payload) = Envelope::decode(data)?;
+		let envelope = envelope.unwrap_or_default();
+
+		if envelope.is_encrypted() {
+			self.opaque += 1;
+			self.plain_bytes += data.len() as u64;
+			return Ok(());
+		}
+
+		let plain = self.decompress(&envelope, payload)?;
+
+		if std::str::from_utf8(&plain).is_ok() {
+			self.text += 1;
+		}
+
+		self.plain_bytes += plain.len() as u64;
+
+		#[cfg(any(feature = "gzip", feature = "zstd"))]
+		self.estimate(&plain)?;
+
+		Ok(())
+	}
+
+	#[cfg(any(feature = "gzip", feature = "zstd"))]
+	fn decompress(&mut self, envelope: &Envelope, payload: &[u8]) -> Result<Vec<u8>, FsError> {
+		if !envelope.is_compressed() {
+			return Ok(payload.to_vec());
+		}
+
+		self.compressed += 1;
+
+		envelope
+			.codecs()
+			.iter()
+			.rev()
+			.filter_map(|codec| Compression::from_codec(*codec))
+			.try_fold(payload.to_vec(), |data, compression| {
+				compression.decompress(&data)
+			})
+	}
+
+	/// Without any codecs enabled compressed payloads can't be decompressed, so they're measured as-is.
+	#[cfg(not(any(feature = "gzip", feature = "zstd")))]
+	#[allow(clippy::unnecessary_wraps)]
+	fn decompress(&mut self, envelope: &Envelope, payload: &[u8]) -> Result<Vec<u8>, FsError> {
+		if envelope.is_compressed() {
+			self.compressed += 1;
+		}
+
+		Ok(payload.to_vec())
+	}
+
+	#[cfg(any(feature = "gzip", feature = "zstd"))]
+	fn estimate(&mut self, plain: &[u8]) -> Result<(), FsError> {
+		for compression in Compression::ENABLED {
+			let bytes = compression.compress(plain, None)?.len() as u64;
+
+			match self
+				.estimates
+				.iter_mut()
+				.find(|(estimated, _)| estimated == compression)
+			{
+				Some((_, total)) => *total += bytes,
+				None => self.estimates.push((*compression, bytes)),
+			}
+		}
+
+		Ok(())
+	}
+}
+
+#[allow(clippy::cast_precision_loss)]
+fn ratio(bytes: u64, plain_bytes: u64) -> Option<f64> {
+	(plain_bytes > 0).then(|| bytes as f64 / plain_bytes as f64)
+}
+
+#[cfg(all(
+	test,
+	feature = "json",
+	any(feature = "gzip", feature = "zstd"),
+	not(miri)
+))]
+mod tests {
+	use starchart::backend::Backend;
+
+	use super::Advice;
+	use crate::{
+		fs::{
+			transcoders::{CompressedTranscoder, JsonTranscoder},
+			FsBackend, FsError,
+		},
+		testing::{TestPath, TestSettings, TEST_GUARD},
+	};
+
+	#[tokio::test]
+	async fn advice() -> Result<(), FsError> {
+		let _lock = TEST_GUARD.lock().await;
+		let path = TestPath::new("advice", "analysis");
+		let backend = FsBackend::new(JsonTranscoder::default(), "json".to_owned(), &path)?;
+
+		backend.init().await?;
+		backend.create_table("large").await?;
+		for id in 0..10 {
+			let settings = TestSettings {
+				id,
+				value: "hello, world! ".repeat(100),
+				..TestSettings::default()
+			};
+			backend.create("large", &id.to_string(), &settings).await?;
+		}
+
+		let analysis = backend.analyze("large", 4).await?;
+		assert_eq!(analysis.entries(), 10);
+		assert_eq!(analysis.sampled(), 4);
+		assert_eq!(analysis.compressed(), 0);
+		assert_eq!(analysis.stored_bytes(), analysis.plain_bytes());
+
+		let advice = analysis.advice();
+		assert!(matches!(advice[0], Advice::Compress { ratio, .. } if ratio < 0.5));
+		assert_eq!(advice[1], Advice::UseBinaryFormat);
+
+		let compressed = FsBackend::new(
+			CompressedTranscoder::new(JsonTranscoder::default()),
+			"json".to_owned(),
+			&path,
+		)?;
+		compressed.create_table("small").await?;
+		compressed
+			.create("small", "1", &TestSettings::default())
+			.await?;
+
+		let analysis = compressed.analyze("small", 4).await?;
+		assert_eq!(analysis.compressed(), 1);
+		assert!(matches!(
+			analysis.advice()[..],
+			[Advice::DisableCompression { .. }]
+		));
+
+		Ok(())
+	}
+}
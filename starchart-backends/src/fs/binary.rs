@@ -82,13 +82,21 @@ impl Transcoder for BinaryTranscoder {
 			BinaryFormat::Cbor => Ok(serde_cbor::from_reader(rdr)?),
 		}
 	}
+
+	// bincode doesn't record field names or types, so entries can only be read as the type they were written as.
+	fn is_self_describing(&self) -> bool {
+		!self.is_bincode()
+	}
 }
 
 #[cfg(all(test, not(miri)))]
 mod tests {
 	use std::{fmt::Debug, fs};
 
-	use starchart::backend::Backend;
+	use starchart::{
+		backend::Backend, error::ErrorType, stats::PayloadStats, version::HistoryLimit, Error,
+		Starchart,
+	};
 	use static_assertions::assert_impl_all;
 
 	use crate::{
@@ -101,6 +109,14 @@ mod tests {
 
 	assert_impl_all!(BinaryTranscoder: Clone, Copy, Debug, Send, Sync);
 
+	#[test]
+	#[cfg(feature = "test-util")]
+	fn arbitrary_entries() {
+		for format in [BinaryFormat::Bincode, BinaryFormat::Cbor] {
+			crate::fs::roundtrip::check(&BinaryTranscoder::new(format)).unwrap();
+		}
+	}
+
 	#[tokio::test]
 	async fn init() -> Result<(), FsError> {
 		let _lock = TEST_GUARD.lock().await;
@@ -176,6 +192,54 @@ mod tests {
 		Ok(())
 	}
 
+	#[tokio::test]
+	async fn untyped() -> Result<(), Error> {
+		let _lock = TEST_GUARD.lock().await;
+		let path = TestPath::new("untyped", "binary");
+		let backend = FsBackend::new(BinaryTranscoder::bincode(), "bin".to_owned(), &path)?;
+		assert!(!backend.is_self_describing());
+
+		let chart = Starchart::builder(backend)
+			.keep_history("table", HistoryLimit::Count(2))
+			.payload_stats(true)
+			.build()
+			.await?;
+		chart.create_table("table").await?;
+
+		let table = chart.table::<TestSettings>("table");
+		let mut settings = TestSettings::default();
+		table.insert(&settings).await?;
+		assert_eq!(
+			chart.payload_stats("table").map(PayloadStats::max_bytes),
+			serde_bincode::serialize(&settings)
+				.ok()
+				.map(|serialized| serialized.len() as u64)
+		);
+		settings.opt = None;
+		table.insert(&settings).await?;
+
+		let history = chart.history::<TestSettings, _>("table", &1_u32).await?;
+		assert_eq!(history.len(), 2);
+		assert_eq!(history[1].entry(), Some(&settings));
+
+		let err = chart.backup(&["table"], Vec::new(), 1).await.unwrap_err();
+		assert!(matches!(err.kind(), ErrorType::Untyped));
+
+		let err = chart
+			.increment::<TestSettings, _>("table", &1_u32, "id", 1)
+			.await
+			.unwrap_err();
+		assert!(matches!(err.kind(), ErrorType::Untyped));
+
+		chart.truncate_table("table").await?;
+		assert_eq!(table.get(&1_u32).await?, None);
+
+		table.insert(&settings).await?;
+		assert_eq!(table.get(&1_u32).await?, Some(settings));
+
+		Ok(())
+	}
+
 	#[tokio::test]
 	async fn get_keys_cbor() -> Result<(), FsError> {
 		let _lock = TEST_GUARD.lock().await;
@@ -0,0 +1,399 @@
+use std::{
+	fmt::{Debug, Formatter, Result as FmtResult},
+	io::Read,
+};
+
+use starchart::Entry;
+
+use super::{CodecId, Envelope, EnvelopeFlags, FsError, FsErrorType, Transcoder};
+
+/// The bytes every gzip stream starts with.
+#[cfg(feature = "gzip")]
+const GZIP_MAGIC: [u8; 2] = [0x1f, 0x8b];
+
+/// The bytes every zstd frame starts with.
+#[cfg(feature = "zstd")]
+const ZSTD_MAGIC: [u8; 4] = [0x28, 0xb5, 0x2f, 0xfd];
+
+/// The compression codecs supported by the [`CompressedTranscoder`], each behind the feature of the same name.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+#[cfg(any(feature = "gzip", feature = "zstd"))]
+#[non_exhaustive]
+pub enum Compression {
+	/// Gzip, which is widely supported by other tools. This is the default when enabled.
+	#[cfg(feature = "gzip")]
+	#[default]
+	Gzip,
+	/// Zstandard, which is faster and usually compresses better than gzip.
+	#[cfg(feature = "zstd")]
+	#[cfg_attr(not(feature = "gzip"), default)]
+	Zstd,
+}
+
+impl Compression {
+	/// Every codec enabled by the crate's features.
+	pub(super) const ENABLED: &'static [Self] = &[
+		#[cfg(feature = "gzip")]
+		Self::Gzip,
+		#[cfg(feature = "zstd")]
+		Self::Zstd,
+	];
+
+	/// The [`CodecId`] recorded in the [`Envelope`] of entries compressed with this codec.
+	#[must_use]
+	pub const fn codec(self) -> CodecId {
+		match self {
+			#[cfg(feature = "gzip")]
+			Self::Gzip => CodecId::GZIP,
+			#[cfg(feature = "zstd")]
+			Self::Zstd => CodecId::ZSTD,
+		}
+	}
+
+	pub(super) const fn from_codec(codec: CodecId) -> Option<Self> {
+		match codec {
+			#[cfg(feature = "gzip")]
+			CodecId::GZIP => Some(Self::Gzip),
+			#[cfg(feature = "zstd")]
+			CodecId::ZSTD => Some(Self::Zstd),
+			_ => None,
+		}
+	}
+
+	/// Detects compressed data without an [`Envelope`], such as files compressed by other tools.
+	fn detect(data: &[u8]) -> Option<Self> {
+		match data {
+			#[cfg(feature = "gzip")]
+			[a, b, ..] if [*a, *b] == GZIP_MAGIC => Some(Self::Gzip),
+			#[cfg(feature = "zstd")]
+			[a, b, c, d, ..] if [*a, *b, *c, *d] == ZSTD_MAGIC => Some(Self::Zstd),
+			_ => None,
+		}
+	}
+
+	pub(super) fn compress(self, data: &[u8], level: Option<u32>) -> Result<Vec<u8>, FsError> {
+		match self {
+			#[cfg(feature = "gzip")]
+			Self::Gzip => {
+				use std::io::Write;
+
+				use flate2::write::GzEncoder;
+
+				let level = level.map_or_else(flate2::Compression::default, |level| {
+					flate2::Compression::new(level.min(9))
+				});
+				let mut encoder = GzEncoder::new(Vec::with_capacity(data.len() / 2), level);
+				encoder.write_all(data).map_err(compression_failed)?;
+				encoder.finish().map_err(compression_failed)
+			}
+			#[cfg(feature = "zstd")]
+			Self::Zstd => {
+				use std::convert::TryFrom;
+
+				// level 0 is zstd's default level.
+				let level =
+					level.map_or(0, |level| i32::try_from(level.min(22)).unwrap_or_default());
+
+				zstd::bulk::compress(data, level).map_err(compression_failed)
+			}
+		}
+	}
+
+	pub(super) fn decompress(self, data: &[u8]) -> Result<Vec<u8>, FsError> {
+		match self {
+			#[cfg(feature = "gzip")]
+			Self::Gzip => {
+				let mut output = Vec::with_capacity(data.len() * 2);
+				flate2::read::GzDecoder::new(data)
+					.read_to_end(&mut output)
+					.map_err(compression_failed)?;
+
+				Ok(output)
+			}
+			#[cfg(feature = "zstd")]
+			Self::Zstd => zstd::stream::decode_all(data).map_err(compression_failed),
+		}
+	}
+}
+
+/// A transcoder that compresses the output of another [`Transcoder`], so large entries take less disk space.
+///
+/// Entries are wrapped in an [`Envelope`] recording the [`Compression`] used. Entries are read
+/// whichever codec they were compressed with, as long as it's feature is enabled. Entries without an
+/// [`Envelope`] are checked for the magic bytes of gzip and zstd, so files compressed by other tools
+/// and uncompressed files, such as those written before compression was enabled, can both be read.
+#[derive(Clone)]
+#[cfg(any(feature = "gzip", feature = "zstd"))]
+#[must_use = "transcoders do nothing by themselves"]
+pub struct CompressedTranscoder<T> {
+	inner: T,
+	compression: Compression,
+	level: Option<u32>,
+}
+
+impl<T: Transcoder> CompressedTranscoder<T> {
+	/// Creates a new [`CompressedTranscoder`] using the default [`Compression`].
+	pub fn new(inner: T) -> Self {
+		Self {
+			inner,
+			compression: Compression::default(),
+			level: None,
+		}
+	}
+
+	/// Sets the [`Compression`] new entries are compressed with.
+	pub const fn with_compression(mut self, compression: Compression) -> Self {
+		self.compression = compression;
+
+		self
+	}
+
+	/// Sets the compression level, instead of the codec's default.
+	///
+	/// Higher levels compress better but slower. Levels are capped to the highest level of the codec,
+	/// which is 9 for gzip and 22 for zstd.
+	pub const fn with_level(mut self, level: u32) -> Self {
+		self.level = Some(level);
+
+		self
+	}
+
+	/// Returns the wrapped [`Transcoder`].
+	pub const fn inner(&self) -> &T {
+		&self.inner
+	}
+
+	/// The [`Compression`] new entries are compressed with.
+	pub const fn compression(&self) -> Compression {
+		self.compression
+	}
+
+	/// The compression level, if one was set.
+	pub const fn level(&self) -> Option<u32> {
+		self.level
+	}
+}
+
+impl<T> Debug for CompressedTranscoder<T>
+where
+	T: Debug,
+{
+	fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+		f.debug_struct("CompressedTranscoder")
+			.field("inner", &self.inner)
+			.field("compression", &self.compression)
+			.field("level", &self.level)
+			.finish()
+	}
+}
+
+impl<T: Transcoder> CompressedTranscoder<T> {
+	/// Compresses the output of the inner transcoder.
+	fn compress(&self, serialized: &[u8]) -> Result<Vec<u8>, FsError> {
+		// inner transcoders that envelope their output share the header, with compression applied last.
+		let (envelope, plain) = match Envelope::decode(serialized)? {
+			(Some(envelope), payload) => (envelope, payload),
+			(None, payload) => (Envelope::new(), payload),
+		};
+
+		envelope
+			.with_codec(self.compression.codec())
+			.encode(&self.compression.compress(plain, self.level)?)
+	}
+
+	/// Decompresses the data, passing the result to `read_inner`.
+	fn decompress<E, R, F>(mut rdr: R, read_inner: F) -> Result<E, FsError>
+	where
+		R: Read,
+		F: FnOnce(&[u8]) -> Result<E, FsError>,
+	{
+		let mut data = Vec::new();
+		rdr.read_to_end(&mut data).map_err(|e| FsError {
+			source: Some(Box::new(e)),
+			kind: FsErrorType::Io,
+		})?;
+
+		let (Some(envelope), payload) = Envelope::decode(&data)? else {
+			return match Compression::detect(&data) {
+				Some(compression) => read_inner(&compression.decompress(&data)?),
+				None => read_inner(&data),
+			};
+		};
+
+		let Some((&last, rest)) = envelope.codecs().split_last() else {
+			return read_inner(payload);
+		};
+
+		let Some(compression) = Compression::from_codec(last) else {
+			if last.flag() == EnvelopeFlags::COMPRESSED {
+				return Err(FsError {
+					source: None,
+					kind: FsErrorType::Compression,
+				});
+			}
+
+			return read_inner(&data);
+		};
+
+		let plain = compression.decompress(payload)?;
+
+		if rest.is_empty() {
+			return read_inner(&plain);
+		}
+
+		let inner = rest.iter().fold(Envelope::new(), |envelope, codec| {
+			envelope.with_codec(*codec)
+		});
+
+		read_inner(&inner.encode(&plain)?)
+	}
+}
+
+impl<T: Transcoder> Transcoder for CompressedTranscoder<T> {
+	fn serialize_value<E: Entry>(&self, value: &E) -> Result<Vec<u8>, FsError> {
+		self.compress(&self.inner.serialize_value(value)?)
+	}
+
+	fn deserialize_data<E: Entry, R: Read>(&self, rdr: R) -> Result<E, FsError> {
+		Self::decompress(rdr, |data| self.inner.deserialize_data(data))
+	}
+
+	fn serialize_entry<E: Entry>(&self, key: &str, value: &E) -> Result<Vec<u8>, FsError> {
+		self.compress(&self.inner.serialize_entry(key, value)?)
+	}
+
+	fn deserialize_entry<E: Entry, R: Read>(&self, key: &str, rdr: R) -> Result<E, FsError> {
+		Self::decompress(rdr, |data| self.inner.deserialize_entry(key, data))
+	}
+
+	fn is_self_describing(&self) -> bool {
+		self.inner.is_self_describing()
+	}
+}
+
+fn compression_failed(e: std::io::Error) -> FsError {
+	FsError {
+		source: Some(Box::new(e)),
+		kind: FsErrorType::Compression,
+	}
+}
+
+#[cfg(all(test, feature = "json", not(miri)))]
+mod tests {
+	use std::fmt::Debug;
+
+	use static_assertions::assert_impl_all;
+
+	use super::{CompressedTranscoder, Compression};
+	use crate::{
+		fs::{transcoders::JsonTranscoder, Envelope, FsBackend, FsError, Transcoder},
+		testing::{TestPath, TestSettings, TEST_GUARD},
+	};
+
+	assert_impl_all!(CompressedTranscoder<JsonTranscoder>: Clone, Debug, Send, Sync, Transcoder);
+
+	fn compressions() -> Vec<Compression> {
+		vec![
+			#[cfg(feature = "gzip")]
+			Compression::Gzip,
+			#[cfg(feature = "zstd")]
+			Compression::Zstd,
+		]
+	}
+
+	#[test]
+	fn round_trip() -> Result<(), FsError> {
+		for compression in compressions() {
+			let transcoder =
+				CompressedTranscoder::new(JsonTranscoder::default()).with_compression(compression);
+
+			let compressed = transcoder.serialize_value(&TestSettings::default())?;
+			let (envelope, _) = Envelope::decode(&compressed)?;
+			assert_eq!(
+				envelope.map(|envelope| envelope.codecs().to_vec()),
+				Some(vec![compression.codec()])
+			);
+
+			let decompressed: TestSettings = transcoder.deserialize_data(compressed.as_slice())?;
+			assert_eq!(decompressed, TestSettings::default());
+
+			// entries can be read whichever codec is used for new entries.
+			let other = CompressedTranscoder::new(JsonTranscoder::default()).with_level(30);
+			let decompressed: TestSettings = other.deserialize_data(compressed.as_slice())?;
+			assert_eq!(decompressed, TestSettings::default());
+		}
+
+		let plain = JsonTranscoder::default().serialize_value(&TestSettings::default())?;
+		let transcoder = CompressedTranscoder::new(JsonTranscoder::default());
+		let read: TestSettings = transcoder.deserialize_data(plain.as_slice())?;
+		assert_eq!(read, TestSettings::default());
+
+		Ok(())
+	}
+
+	#[test]
+	fn magic_bytes() -> Result<(), FsError> {
+		let plain = JsonTranscoder::default().serialize_value(&TestSettings::default())?;
+		let transcoder = CompressedTranscoder::new(JsonTranscoder::default());
+
+		#[cfg(feature = "gzip")]
+		{
+			use std::io::Write;
+
+			let mut encoder =
+				flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::default());
+			encoder.write_all(&plain).unwrap();
+			let gzipped = encoder.finish().unwrap();
+
+			let read: TestSettings = transcoder.deserialize_data(gzipped.as_slice())?;
+			assert_eq!(read, TestSettings::default());
+		}
+
+		#[cfg(feature = "zstd")]
+		{
+			let zstd = zstd::bulk::compress(&plain, 0).unwrap();
+
+			let read: TestSettings = transcoder.deserialize_data(zstd.as_slice())?;
+			assert_eq!(read, TestSettings::default());
+		}
+
+		Ok(())
+	}
+
+	#[tokio::test]
+	async fn compressed_at_rest() -> Result<(), FsError> {
+		use starchart::backend::Backend;
+
+		let _lock = TEST_GUARD.lock().await;
+		let path = TestPath::new("compressed_at_rest", "compressed");
+		let transcoder = CompressedTranscoder::new(JsonTranscoder::default());
+		let backend = FsBackend::new(transcoder, "json".to_owned(), &path)?;
+
+		backend.init().await?;
+		backend.create_table("table").await?;
+		backend
+			.create("table", "1", &TestSettings::default())
+			.await?;
+
+		let envelope = backend.read_envelope("table", "1").await?;
+		assert!(envelope.is_some_and(|envelope| envelope.is_compressed()));
+
+		assert_eq!(
+			backend.get::<TestSettings>("table", "1").await?,
+			Some(TestSettings::default())
+		);
+
+		Ok(())
+	}
+
+	#[test]
+	#[cfg(feature = "test-util")]
+	fn arbitrary_entries() {
+		for compression in compressions() {
+			let transcoder =
+				CompressedTranscoder::new(JsonTranscoder::default()).with_compression(compression);
+
+			crate::fs::roundtrip::check(&transcoder).unwrap();
+		}
+	}
+}
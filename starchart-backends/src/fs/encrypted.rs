@@ -0,0 +1,400 @@
+use std::{
+	fmt::{Debug, Formatter, Result as FmtResult},
+	io::Read,
+};
+
+use aes_gcm::Aes256Gcm;
+use chacha20poly1305::{
+	aead::{generic_array::GenericArray, Aead, AeadCore, KeyInit, OsRng, Payload},
+	ChaCha20Poly1305,
+};
+use starchart::Entry;
+
+use super::{CodecId, Envelope, FsError, FsErrorType, Transcoder};
+
+/// The length of the nonce stored before each encrypted payload.
+const NONCE_LEN: usize = 12;
+
+/// The authenticated ciphers supported by the [`EncryptedTranscoder`].
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+#[cfg(feature = "encryption")]
+#[non_exhaustive]
+pub enum Cipher {
+	/// ChaCha20-Poly1305, which is fast without hardware support. This is the default.
+	#[default]
+	ChaCha20Poly1305,
+	/// AES-256-GCM, which is faster on CPUs with AES instructions.
+	Aes256Gcm,
+}
+
+impl Cipher {
+	/// The [`CodecId`] recorded in the [`Envelope`] of entries encrypted with this cipher.
+	#[must_use]
+	pub const fn codec(self) -> CodecId {
+		match self {
+			Self::ChaCha20Poly1305 => CodecId::CHACHA20_POLY1305,
+			Self::Aes256Gcm => CodecId::AES_GCM,
+		}
+	}
+
+	const fn from_codec(codec: CodecId) -> Option<Self> {
+		match codec {
+			CodecId::CHACHA20_POLY1305 => Some(Self::ChaCha20Poly1305),
+			CodecId::AES_GCM => Some(Self::Aes256Gcm),
+			_ => None,
+		}
+	}
+}
+
+/// A transcoder that encrypts the output of another [`Transcoder`] with a 256-bit key.
+///
+/// Each entry is encrypted with a random nonce, and wrapped in an [`Envelope`] recording the [`Cipher`],
+/// so entries encrypted with either cipher can be read as long as the key is the same.
+///
+/// The [`Envelope`] header is authenticated along with the entry, and entries written by the
+/// [`FsBackend`] are bound to their key, so an encrypted entry copied over another key fails to decrypt.
+/// They aren't bound to their table, as tables are renamed in place. Entries that aren't encrypted are
+/// rejected unless [`Self::allow_plaintext`] is enabled, such as while migrating a table written
+/// before encryption was enabled.
+///
+/// [`FsBackend`]: super::FsBackend
+#[derive(Clone)]
+#[cfg(feature = "encryption")]
+#[must_use = "transcoders do nothing by themselves"]
+pub struct EncryptedTranscoder<T> {
+	inner: T,
+	key: [u8; 32],
+	cipher: Cipher,
+	allow_plaintext: bool,
+}
+
+impl<T: Transcoder> EncryptedTranscoder<T> {
+	/// Creates a new [`EncryptedTranscoder`] using [`Cipher::ChaCha20Poly1305`].
+	pub const fn new(inner: T, key: [u8; 32]) -> Self {
+		Self {
+			inner,
+			key,
+			cipher: Cipher::ChaCha20Poly1305,
+			allow_plaintext: false,
+		}
+	}
+
+	/// Sets the [`Cipher`] new entries are encrypted with.
+	pub const fn with_cipher(mut self, cipher: Cipher) -> Self {
+		self.cipher = cipher;
+
+		self
+	}
+
+	/// Sets whether entries that aren't encrypted are read without decrypting them, instead of failing
+	/// with an [`FsErrorType::Encryption`] error, by default they aren't.
+	pub const fn allow_plaintext(mut self, allow: bool) -> Self {
+		self.allow_plaintext = allow;
+
+		self
+	}
+
+	/// Returns the wrapped [`Transcoder`].
+	pub const fn inner(&self) -> &T {
+		&self.inner
+	}
+
+	/// The [`Cipher`] new entries are encrypted with.
+	pub const fn cipher(&self) -> Cipher {
+		self.cipher
+	}
+
+	fn encrypt(&self, plaintext: &[u8], aad: &[u8]) -> Result<Vec<u8>, FsError> {
+		let payload = Payload {
+			msg: plaintext,
+			aad,
+		};
+
+		let (nonce, ciphertext) = match self.cipher {
+			Cipher::ChaCha20Poly1305 => {
+				let nonce = ChaCha20Poly1305::generate_nonce(&mut OsRng);
+				let ciphertext = ChaCha20Poly1305::new(&self.key.into()).encrypt(&nonce, payload);
+				(nonce, ciphertext)
+			}
+			Cipher::Aes256Gcm => {
+				let nonce = Aes256Gcm::generate_nonce(&mut OsRng);
+				let ciphertext = Aes256Gcm::new(&self.key.into()).encrypt(&nonce, payload);
+				(nonce, ciphertext)
+			}
+		};
+
+		let ciphertext = ciphertext.map_err(|_| encryption_failed())?;
+
+		let mut payload = Vec::with_capacity(NONCE_LEN + ciphertext.len());
+		payload.extend_from_slice(&nonce);
+		payload.extend_from_slice(&ciphertext);
+
+		Ok(payload)
+	}
+
+	fn decrypt(&self, cipher: Cipher, payload: &[u8], aad: &[u8]) -> Result<Vec<u8>, FsError> {
+		if payload.len() < NONCE_LEN {
+			return Err(encryption_failed());
+		}
+
+		let (nonce, ciphertext) = payload.split_at(NONCE_LEN);
+		let nonce = GenericArray::from_slice(nonce);
+		let payload = Payload {
+			msg: ciphertext,
+			aad,
+		};
+
+		match cipher {
+			Cipher::ChaCha20Poly1305 => {
+				ChaCha20Poly1305::new(&self.key.into()).decrypt(nonce, payload)
+			}
+			Cipher::Aes256Gcm => Aes256Gcm::new(&self.key.into()).decrypt(nonce, payload),
+		}
+		.map_err(|_| encryption_failed())
+	}
+
+	/// Encrypts the output of the inner transcoder, authenticating the envelope header along with the key
+	/// the entry is stored under, if there is one.
+	fn seal(&self, key: Option<&str>, serialized: &[u8]) -> Result<Vec<u8>, FsError> {
+		// inner transcoders that envelope their output share the header, with encryption applied last.
+		let (envelope, plaintext) = match Envelope::decode(serialized)? {
+			(Some(envelope), payload) => (envelope, payload),
+			(None, payload) => (Envelope::new(), payload),
+		};
+
+		let envelope = envelope.with_codec(self.cipher.codec());
+		let header = envelope.encode(&[])?;
+
+		envelope.encode(&self.encrypt(plaintext, &associated_data(&header, key))?)
+	}
+
+	/// Decrypts the data, passing the plaintext to `read_inner`.
+	fn open<E, R, F>(&self, key: Option<&str>, mut rdr: R, read_inner: F) -> Result<E, FsError>
+	where
+		R: Read,
+		F: FnOnce(&[u8]) -> Result<E, FsError>,
+	{
+		let mut data = Vec::new();
+		rdr.read_to_end(&mut data).map_err(|e| FsError {
+			source: Some(Box::new(e)),
+			kind: FsErrorType::Io,
+		})?;
+
+		let (envelope, payload) = Envelope::decode(&data)?;
+
+		let encrypted = envelope.as_ref().and_then(|envelope| {
+			let (&last, rest) = envelope.codecs().split_last()?;
+
+			Some((Cipher::from_codec(last)?, rest))
+		});
+
+		let Some((cipher, rest)) = encrypted else {
+			if !self.allow_plaintext {
+				return Err(encryption_failed());
+			}
+
+			return match &envelope {
+				Some(envelope) if envelope.codecs().is_empty() => read_inner(payload),
+				_ => read_inner(&data),
+			};
+		};
+
+		let header = &data[..data.len() - payload.len()];
+		let plaintext = self.decrypt(cipher, payload, &associated_data(header, key))?;
+
+		if rest.is_empty() {
+			return read_inner(&plaintext);
+		}
+
+		let inner = rest.iter().fold(Envelope::new(), |envelope, codec| {
+			envelope.with_codec(*codec)
+		});
+
+		read_inner(&inner.encode(&plaintext)?)
+	}
+}
+
+impl<T> Debug for EncryptedTranscoder<T>
+where
+	T: Debug,
+{
+	fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+		f.debug_struct("EncryptedTranscoder")
+			.field("inner", &self.inner)
+			.field("cipher", &self.cipher)
+			.field("allow_plaintext", &self.allow_plaintext)
+			.finish_non_exhaustive()
+	}
+}
+
+impl<T: Transcoder> Transcoder for EncryptedTranscoder<T> {
+	fn serialize_value<E: Entry>(&self, value: &E) -> Result<Vec<u8>, FsError> {
+		self.seal(None, &self.inner.serialize_value(value)?)
+	}
+
+	fn deserialize_data<E: Entry, R: Read>(&self, rdr: R) -> Result<E, FsError> {
+		self.open(None, rdr, |data| self.inner.deserialize_data(data))
+	}
+
+	fn serialize_entry<E: Entry>(&self, key: &str, value: &E) -> Result<Vec<u8>, FsError> {
+		self.seal(Some(key), &self.inner.serialize_entry(key, value)?)
+	}
+
+	fn deserialize_entry<E: Entry, R: Read>(&self, key: &str, rdr: R) -> Result<E, FsError> {
+		self.open(Some(key), rdr, |data| {
+			self.inner.deserialize_entry(key, data)
+		})
+	}
+
+	fn is_self_describing(&self) -> bool {
+		self.inner.is_self_describing()
+	}
+}
+
+/// The data authenticated along with an entry, the envelope header followed by the key, which is last
+/// as the header records it's own length.
+fn associated_data(header: &[u8], key: Option<&str>) -> Vec<u8> {
+	[header, key.unwrap_or_default().as_bytes()].concat()
+}
+
+const fn encryption_failed() -> FsError {
+	FsError {
+		source: None,
+		kind: FsErrorType::Encryption,
+	}
+}
+
+#[cfg(all(test, feature = "json", not(miri)))]
+mod tests {
+	use std::fmt::Debug;
+
+	use static_assertions::assert_impl_all;
+
+	use super::{Cipher, EncryptedTranscoder};
+	use crate::{
+		fs::{
+			transcoders::JsonTranscoder, CodecId, Envelope, FsBackend, FsError, FsErrorType,
+			Transcoder,
+		},
+		testing::{TestPath, TestSettings, TEST_GUARD},
+	};
+
+	assert_impl_all!(EncryptedTranscoder<JsonTranscoder>: Clone, Debug, Send, Sync, Transcoder);
+
+	const KEY: [u8; 32] = [7; 32];
+
+	#[test]
+	fn round_trip() -> Result<(), FsError> {
+		for cipher in [Cipher::ChaCha20Poly1305, Cipher::Aes256Gcm] {
+			let transcoder =
+				EncryptedTranscoder::new(JsonTranscoder::default(), KEY).with_cipher(cipher);
+
+			let encrypted = transcoder.serialize_value(&TestSettings::default())?;
+			let (envelope, _) = Envelope::decode(&encrypted)?;
+			assert_eq!(
+				envelope.map(|envelope| envelope.codecs().to_vec()),
+				Some(vec![cipher.codec()])
+			);
+			assert!(!String::from_utf8_lossy(&encrypted).contains("hello, world"));
+
+			let decrypted: TestSettings = transcoder.deserialize_data(encrypted.as_slice())?;
+			assert_eq!(decrypted, TestSettings::default());
+
+			// entries can be read whichever cipher is used for new entries.
+			let other = EncryptedTranscoder::new(JsonTranscoder::default(), KEY);
+			let decrypted: TestSettings = other.deserialize_data(encrypted.as_slice())?;
+			assert_eq!(decrypted, TestSettings::default());
+
+			let wrong_key = EncryptedTranscoder::new(JsonTranscoder::default(), [8; 32]);
+			let err = wrong_key
+				.deserialize_data::<TestSettings, _>(encrypted.as_slice())
+				.unwrap_err();
+			assert!(matches!(err.kind(), FsErrorType::Encryption));
+		}
+
+		Ok(())
+	}
+
+	#[test]
+	fn plaintext() -> Result<(), FsError> {
+		let plain = JsonTranscoder::default().serialize_value(&TestSettings::default())?;
+		let transcoder = EncryptedTranscoder::new(JsonTranscoder::default(), KEY);
+
+		let err = transcoder
+			.deserialize_data::<TestSettings, _>(plain.as_slice())
+			.unwrap_err();
+		assert!(matches!(err.kind(), FsErrorType::Encryption));
+
+		let transcoder = transcoder.allow_plaintext(true);
+		let read: TestSettings = transcoder.deserialize_data(plain.as_slice())?;
+		assert_eq!(read, TestSettings::default());
+
+		Ok(())
+	}
+
+	#[test]
+	fn bound_to_key() -> Result<(), FsError> {
+		let transcoder = EncryptedTranscoder::new(JsonTranscoder::default(), KEY);
+		let encrypted = transcoder.serialize_entry("1", &TestSettings::default())?;
+
+		let read: TestSettings = transcoder.deserialize_entry("1", encrypted.as_slice())?;
+		assert_eq!(read, TestSettings::default());
+
+		for key in ["2", "11"] {
+			let err = transcoder
+				.deserialize_entry::<TestSettings, _>(key, encrypted.as_slice())
+				.unwrap_err();
+			assert!(matches!(err.kind(), FsErrorType::Encryption));
+		}
+
+		// the header is authenticated, so codecs can't be added to it.
+		let (_, payload) = Envelope::decode(&encrypted)?;
+		let tampered = Envelope::new()
+			.with_codec(CodecId::CHUNKED)
+			.with_codec(Cipher::ChaCha20Poly1305.codec())
+			.encode(payload)?;
+		let err = transcoder
+			.deserialize_entry::<TestSettings, _>("1", tampered.as_slice())
+			.unwrap_err();
+		assert!(matches!(err.kind(), FsErrorType::Encryption));
+
+		Ok(())
+	}
+
+	#[tokio::test]
+	async fn encrypted_at_rest() -> Result<(), FsError> {
+		use starchart::backend::Backend;
+
+		let _lock = TEST_GUARD.lock().await;
+		let path = TestPath::new("encrypted_at_rest", "encrypted");
+		let transcoder = EncryptedTranscoder::new(JsonTranscoder::default(), KEY);
+		let backend = FsBackend::new(transcoder, "json".to_owned(), &path)?;
+
+		backend.init().await?;
+		backend.create_table("table").await?;
+		backend
+			.create("table", "1", &TestSettings::default())
+			.await?;
+
+		let envelope = backend.read_envelope("table", "1").await?;
+		assert!(envelope.is_some_and(|envelope| envelope.is_encrypted()));
+
+		assert_eq!(
+			backend.get::<TestSettings>("table", "1").await?,
+			Some(TestSettings::default())
+		);
+
+		Ok(())
+	}
+
+	#[test]
+	#[cfg(feature = "test-util")]
+	fn arbitrary_entries() {
+		for cipher in [Cipher::Aes256Gcm, Cipher::ChaCha20Poly1305] {
+			let transcoder =
+				EncryptedTranscoder::new(JsonTranscoder::default(), KEY).with_cipher(cipher);
+
+			crate::fs::roundtrip::check(&transcoder).unwrap();
+		}
+	}
+}
@@ -0,0 +1,299 @@
+use std::{
+	convert::TryFrom,
+	fmt::{Debug, Formatter, Result as FmtResult},
+	ops::BitOr,
+};
+
+use super::{FsError, FsErrorType};
+
+/// The bytes every enveloped entry starts with.
+pub const ENVELOPE_MAGIC: [u8; 4] = *b"SCE\0";
+
+/// The envelope format version written by this crate.
+pub const ENVELOPE_VERSION: u8 = 1;
+
+/// A small header describing how an entry's bytes were transformed before being written.
+///
+/// Transcoders that compress, encrypt, or chunk entries wrap their output in an [`Envelope`], so
+/// entries written with different settings (including plain, unenveloped entries) can coexist in one
+/// table, and tooling can tell how an entry was stored without being able to decode it.
+///
+/// The encoded header is laid out as:
+///
+/// | bytes | contents |
+/// |-------|----------|
+/// | 4 | [`ENVELOPE_MAGIC`] |
+/// | 1 | [`ENVELOPE_VERSION`] |
+/// | 1 | [`EnvelopeFlags`] |
+/// | 1 | the amount of codecs |
+/// | 1 each | the [`CodecId`]s, in the order they were applied |
+///
+/// followed by the payload.
+#[derive(Debug, Default, Clone, PartialEq, Eq, Hash)]
+pub struct Envelope {
+	codecs: Vec<CodecId>,
+}
+
+impl Envelope {
+	/// Creates an empty [`Envelope`], describing a plain entry.
+	#[must_use]
+	pub const fn new() -> Self {
+		Self { codecs: Vec::new() }
+	}
+
+	/// Adds a codec that was applied to the payload, after any codecs already added.
+	#[must_use]
+	pub fn with_codec(mut self, codec: CodecId) -> Self {
+		self.codecs.push(codec);
+
+		self
+	}
+
+	/// The codecs applied to the payload, in the order they were applied.
+	///
+	/// Readers should undo them in reverse order.
+	#[must_use]
+	pub fn codecs(&self) -> &[CodecId] {
+		&self.codecs
+	}
+
+	/// The [`EnvelopeFlags`] summarizing the codecs.
+	#[must_use]
+	pub fn flags(&self) -> EnvelopeFlags {
+		self.codecs
+			.iter()
+			.map(|codec| codec.flag())
+			.fold(EnvelopeFlags::PLAIN, BitOr::bitor)
+	}
+
+	/// Whether the payload was compressed.
+	#[must_use]
+	pub fn is_compressed(&self) -> bool {
+		self.flags().contains(EnvelopeFlags::COMPRESSED)
+	}
+
+	/// Whether the payload was encrypted.
+	#[must_use]
+	pub fn is_encrypted(&self) -> bool {
+		self.flags().contains(EnvelopeFlags::ENCRYPTED)
+	}
+
+	/// Whether the payload was split into chunks.
+	#[must_use]
+	pub fn is_chunked(&self) -> bool {
+		self.flags().contains(EnvelopeFlags::CHUNKED)
+	}
+
+	/// Writes the header followed by the payload.
+	///
+	/// # Errors
+	///
+	/// Returns an [`FsErrorType::InvalidEnvelope`] error if more than 255 codecs were added.
+	pub fn encode(&self, payload: &[u8]) -> Result<Vec<u8>, FsError> {
+		let count = u8::try_from(self.codecs.len()).map_err(|e| FsError {
+			source: Some(Box::new(e)),
+			kind: FsErrorType::InvalidEnvelope,
+		})?;
+
+		let mut output =
+			Vec::with_capacity(ENVELOPE_MAGIC.len() + 3 + self.codecs.len() + payload.len());
+
+		output.extend_from_slice(&ENVELOPE_MAGIC);
+		output.push(ENVELOPE_VERSION);
+		output.push(self.flags().bits());
+		output.push(count);
+		output.extend(self.codecs.iter().map(|codec| codec.0));
+		output.extend_from_slice(payload);
+
+		Ok(output)
+	}
+
+	/// Splits the data into it's [`Envelope`] and payload.
+	///
+	/// Data that doesn't start with [`ENVELOPE_MAGIC`] is a plain entry, and is returned as-is with [`None`].
+	///
+	/// # Errors
+	///
+	/// Returns an [`FsErrorType::InvalidEnvelope`] error if the header is truncated, was written by
+	/// a newer version, or it's flags don't match it's codecs.
+	pub fn decode(data: &[u8]) -> Result<(Option<Self>, &[u8]), FsError> {
+		let Some(header) = data.strip_prefix(&ENVELOPE_MAGIC[..]) else {
+			return Ok((None, data));
+		};
+
+		let (version, flags, count, rest) = match header {
+			[version, flags, count, rest @ ..] => (*version, *flags, usize::from(*count), rest),
+			_ => return Err(invalid()),
+		};
+
+		if version != ENVELOPE_VERSION || rest.len() < count {
+			return Err(invalid());
+		}
+
+		let (codecs, payload) = rest.split_at(count);
+		let envelope = Self {
+			codecs: codecs.iter().copied().map(CodecId).collect(),
+		};
+
+		if envelope.flags().bits() != flags {
+			return Err(invalid());
+		}
+
+		Ok((Some(envelope), payload))
+	}
+}
+
+const fn invalid() -> FsError {
+	FsError {
+		source: None,
+		kind: FsErrorType::InvalidEnvelope,
+	}
+}
+
+/// A summary of the kinds of codecs applied to an enveloped entry.
+#[derive(Default, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct EnvelopeFlags(u8);
+
+impl EnvelopeFlags {
+	/// The payload was split into chunks.
+	pub const CHUNKED: Self = Self(1 << 2);
+	/// The payload was compressed.
+	pub const COMPRESSED: Self = Self(1);
+	/// The payload was encrypted.
+	pub const ENCRYPTED: Self = Self(1 << 1);
+	/// No codecs were applied.
+	pub const PLAIN: Self = Self(0);
+
+	/// The raw bits of the flags.
+	#[must_use]
+	pub const fn bits(self) -> u8 {
+		self.0
+	}
+
+	/// Whether all of the given flags are set.
+	#[must_use]
+	pub const fn contains(self, other: Self) -> bool {
+		self.0 & other.0 == other.0
+	}
+}
+
+impl BitOr for EnvelopeFlags {
+	type Output = Self;
+
+	fn bitor(self, rhs: Self) -> Self::Output {
+		Self(self.0 | rhs.0)
+	}
+}
+
+impl Debug for EnvelopeFlags {
+	fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+		let names = [
+			(Self::COMPRESSED, "COMPRESSED"),
+			(Self::ENCRYPTED, "ENCRYPTED"),
+			(Self::CHUNKED, "CHUNKED"),
+		];
+
+		let mut set = f.debug_set();
+		for (flag, name) in names {
+			if self.contains(flag) {
+				set.entry(&format_args!("{name}"));
+			}
+		}
+
+		set.finish()
+	}
+}
+
+/// The id of a codec applied to an enveloped entry.
+///
+/// Ids are grouped into ranges by what kind of codec they are, so the [`EnvelopeFlags`] can be
+/// derived from ids that aren't otherwise known: compression codecs use `0x01..=0x3F`, encryption
+/// codecs use `0x40..=0x7F`, and chunking codecs use `0x80..=0xBF`. The remaining ids are reserved.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct CodecId(pub u8);
+
+impl CodecId {
+	/// AES-256-GCM encryption.
+	pub const AES_GCM: Self = Self(0x40);
+	/// ChaCha20-Poly1305 encryption.
+	pub const CHACHA20_POLY1305: Self = Self(0x41);
+	/// Fixed-size chunking.
+	pub const CHUNKED: Self = Self(0x80);
+	/// Gzip compression.
+	pub const GZIP: Self = Self(0x01);
+	/// Zstandard compression.
+	pub const ZSTD: Self = Self(0x02);
+
+	/// The [`EnvelopeFlags`] this codec sets.
+	#[must_use]
+	pub const fn flag(self) -> EnvelopeFlags {
+		match self.0 {
+			0x01..=0x3F => EnvelopeFlags::COMPRESSED,
+			0x40..=0x7F => EnvelopeFlags::ENCRYPTED,
+			0x80..=0xBF => EnvelopeFlags::CHUNKED,
+			_ => EnvelopeFlags::PLAIN,
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use std::fmt::Debug;
+
+	use starchart::error::ErrorCategory;
+	use static_assertions::assert_impl_all;
+
+	use super::{CodecId, Envelope, EnvelopeFlags, ENVELOPE_MAGIC};
+	use crate::fs::FsErrorType;
+
+	assert_impl_all!(Envelope: Clone, Debug, Default, Send, Sync);
+	assert_impl_all!(EnvelopeFlags: Clone, Copy, Debug, Default, Send, Sync);
+
+	#[test]
+	fn round_trip() {
+		let envelope = Envelope::new()
+			.with_codec(CodecId::ZSTD)
+			.with_codec(CodecId::AES_GCM);
+
+		assert!(envelope.is_compressed());
+		assert!(envelope.is_encrypted());
+		assert!(!envelope.is_chunked());
+
+		let encoded = envelope.encode(b"payload").unwrap();
+		assert!(encoded.starts_with(&ENVELOPE_MAGIC));
+
+		let (decoded, payload) = Envelope::decode(&encoded).unwrap();
+		assert_eq!(decoded, Some(envelope));
+		assert_eq!(payload, b"payload");
+	}
+
+	#[test]
+	fn plain() {
+		let (decoded, payload) = Envelope::decode(b"{\"id\":1}").unwrap();
+		assert!(decoded.is_none());
+		assert_eq!(payload, b"{\"id\":1}");
+
+		let encoded = Envelope::new().encode(b"plain").unwrap();
+		let (decoded, _) = Envelope::decode(&encoded).unwrap();
+		assert_eq!(
+			decoded.map(|envelope| envelope.flags()),
+			Some(EnvelopeFlags::PLAIN)
+		);
+	}
+
+	#[test]
+	fn invalid() {
+		let mut encoded = Envelope::new()
+			.with_codec(CodecId::GZIP)
+			.encode(b"payload")
+			.unwrap();
+
+		let err = Envelope::decode(&encoded[..6]).unwrap_err();
+		assert!(matches!(err.kind(), FsErrorType::InvalidEnvelope));
+		assert_eq!(err.category(), ErrorCategory::Serialization);
+
+		encoded[5] = EnvelopeFlags::ENCRYPTED.bits();
+		let err = Envelope::decode(&encoded).unwrap_err();
+		assert!(matches!(err.kind(), FsErrorType::InvalidEnvelope));
+	}
+}
@@ -5,6 +5,8 @@ use std::{
 	path::PathBuf,
 };
 
+use starchart::{backend::CorruptEntryError, error::ErrorCategory};
+
 /// An error occurred from the [`FsBackend`] or one of it's [`Transcoders`].
 ///
 /// [`FsBackend`]: super::FsBackend
@@ -28,6 +30,13 @@ impl FsError {
 		}
 	}
 
+	pub(super) fn corrupt(table: &str, key: &str, err: Self) -> Self {
+		Self {
+			source: Some(Box::new(CorruptEntryError::new(table, key, err))),
+			kind: FsErrorType::CorruptEntry,
+		}
+	}
+
 	/// Immutable reference to the type of error that occurred.
 	#[must_use = "retrieving the type has no effect if left unused"]
 	pub const fn kind(&self) -> &FsErrorType {
@@ -45,6 +54,21 @@ impl FsError {
 	pub fn into_parts(self) -> (FsErrorType, Option<Box<dyn Error + Send + Sync>>) {
 		(self.kind, self.source)
 	}
+
+	/// The [`ErrorCategory`] of the error, for branching on it without matching every type.
+	#[must_use]
+	pub const fn category(&self) -> ErrorCategory {
+		match self.kind {
+			FsErrorType::Io | FsErrorType::PathNotDirectory(_) => ErrorCategory::Io,
+			FsErrorType::Serde
+			| FsErrorType::InvalidFile(_)
+			| FsErrorType::InvalidEnvelope
+			| FsErrorType::Encryption
+			| FsErrorType::Compression
+			| FsErrorType::CorruptEntry => ErrorCategory::Serialization,
+			FsErrorType::Locked(_) | FsErrorType::KeyCollision(_) => ErrorCategory::Conflict,
+		}
+	}
 }
 
 impl Display for FsError {
@@ -62,6 +86,22 @@ impl Display for FsError {
 				Display::fmt(&p.display(), f)?;
 				f.write_str(" is invalid")
 			}
+			FsErrorType::InvalidEnvelope => f.write_str("an entry envelope is invalid"),
+			FsErrorType::Encryption => f.write_str("an entry could not be encrypted or decrypted"),
+			FsErrorType::Compression => {
+				f.write_str("an entry could not be compressed or decompressed")
+			}
+			FsErrorType::CorruptEntry => f.write_str("an entry could not be decoded"),
+			FsErrorType::Locked(p) => {
+				f.write_str("lock file ")?;
+				Display::fmt(&p.display(), f)?;
+				f.write_str(" is held by another process")
+			}
+			FsErrorType::KeyCollision(p) => {
+				f.write_str("file ")?;
+				Display::fmt(&p.display(), f)?;
+				f.write_str(" holds an entry for another key with the same hash")
+			}
 		}
 	}
 }
@@ -151,4 +191,24 @@ pub enum FsErrorType {
 	Serde,
 	/// The given file was invalid in some way.
 	InvalidFile(PathBuf),
+	/// The lock file is held by another process and wasn't considered stale, or another process
+	/// has exclusive access to the directory.
+	Locked(PathBuf),
+	/// An entry's [`Envelope`] header was truncated, or written by a newer version.
+	///
+	/// [`Envelope`]: super::Envelope
+	InvalidEnvelope,
+	/// An entry couldn't be encrypted or decrypted, such as when it was encrypted with a different key,
+	/// or wasn't encrypted and plaintext entries aren't allowed.
+	Encryption,
+	/// An entry couldn't be compressed or decompressed, such as when it was compressed with a codec that isn't enabled.
+	Compression,
+	/// An entry was read but couldn't be decoded, the source is a [`CorruptEntryError`]
+	/// caused by the [`Transcoder`]'s error.
+	///
+	/// [`Transcoder`]: super::Transcoder
+	CorruptEntry,
+	/// A key too long to be used as a file name hashed to the same file name as another stored key,
+	/// so the entry couldn't be written without replacing the other one.
+	KeyCollision(PathBuf),
 }
@@ -0,0 +1,322 @@
+//! File I/O for the [`FsBackend`], independent of any async runtime.
+//!
+//! With the `tokio` feature enabled, which it is by default, this uses [`tokio::fs`]. Otherwise every
+//! operation is ran on a small pool of worker threads, so the backend works on any executor, such as
+//! `async-std` or `smol`.
+//!
+//! [`FsBackend`]: super::FsBackend
+
+use std::{
+	ffi::OsString,
+	fs::{FileType, Metadata},
+	io,
+	path::{Path, PathBuf},
+};
+
+pub use self::imp::*;
+
+/// An entry of a directory, read eagerly along with it's file type.
+#[derive(Debug)]
+pub struct DirEntry {
+	pub path: PathBuf,
+	pub file_name: OsString,
+	pub file_type: FileType,
+}
+
+#[cfg(feature = "tokio")]
+mod imp {
+	use std::{io, path::Path};
+
+	use tokio::{fs, task};
+
+	use super::{DirEntry, Metadata};
+
+	/// Runs the blocking operation on tokio's blocking thread pool, completing once it's done.
+	pub async fn unblock<T, F>(f: F) -> io::Result<T>
+	where
+		T: Send + 'static,
+		F: FnOnce() -> io::Result<T> + Send + 'static,
+	{
+		task::spawn_blocking(f)
+			.await
+			.unwrap_or_else(|_| Err(io::Error::other("the file operation panicked")))
+	}
+
+	pub async fn read(path: &Path) -> io::Result<Vec<u8>> {
+		fs::read(path).await
+	}
+
+	pub async fn read_to_string(path: &Path) -> io::Result<String> {
+		fs::read_to_string(path).await
+	}
+
+	pub async fn write(path: &Path, contents: &[u8]) -> io::Result<()> {
+		fs::write(path, contents).await
+	}
+
+	pub async fn metadata(path: &Path) -> io::Result<Metadata> {
+		fs::metadata(path).await
+	}
+
+	pub async fn read_dir(path: &Path) -> io::Result<Vec<DirEntry>> {
+		let mut read_dir = fs::read_dir(path).await?;
+		let mut entries = Vec::new();
+
+		while let Some(entry) = read_dir.next_entry().await? {
+			entries.push(DirEntry {
+				path: entry.path(),
+				file_name: entry.file_name(),
+				file_type: entry.file_type().await?,
+			});
+		}
+
+		Ok(entries)
+	}
+
+	pub async fn create_dir(path: &Path) -> io::Result<()> {
+		fs::create_dir(path).await
+	}
+
+	pub async fn create_dir_all(path: &Path) -> io::Result<()> {
+		fs::create_dir_all(path).await
+	}
+
+	pub async fn remove_dir_all(path: &Path) -> io::Result<()> {
+		fs::remove_dir_all(path).await
+	}
+
+	pub async fn remove_file(path: &Path) -> io::Result<()> {
+		fs::remove_file(path).await
+	}
+
+	pub async fn rename(from: &Path, to: &Path) -> io::Result<()> {
+		fs::rename(from, to).await
+	}
+
+	/// Links a new path to an existing file, failing with [`io::ErrorKind::AlreadyExists`] if the new path exists.
+	pub async fn hard_link(from: &Path, to: &Path) -> io::Result<()> {
+		fs::hard_link(from, to).await
+	}
+
+	/// Flushes a file or directory to disk.
+	pub async fn sync(path: &Path) -> io::Result<()> {
+		fs::File::open(path).await?.sync_all().await
+	}
+}
+
+#[cfg(not(feature = "tokio"))]
+mod imp {
+	use std::{
+		collections::VecDeque,
+		fs, io,
+		panic::{catch_unwind, AssertUnwindSafe},
+		path::Path,
+		sync::{Condvar, Mutex, MutexGuard, OnceLock, PoisonError},
+		thread,
+		time::Duration,
+	};
+
+	use futures_channel::oneshot;
+
+	use super::{DirEntry, Metadata};
+
+	/// The most worker threads ran at once, further operations wait for one to be free.
+	const MAX_WORKERS: usize = 16;
+
+	/// How long an idle worker waits for another operation before exiting.
+	const KEEP_ALIVE: Duration = Duration::from_secs(10);
+
+	type Job = Box<dyn FnOnce() + Send>;
+
+	/// The worker threads shared by every [`FsBackend`], started as operations are queued.
+	///
+	/// [`FsBackend`]: super::super::FsBackend
+	#[derive(Default)]
+	struct Pool {
+		state: Mutex<PoolState>,
+		queued: Condvar,
+	}
+
+	#[derive(Default)]
+	struct PoolState {
+		jobs: VecDeque<Job>,
+		workers: usize,
+		idle: usize,
+	}
+
+	impl Pool {
+		fn get() -> &'static Self {
+			static POOL: OnceLock<Pool> = OnceLock::new();
+
+			POOL.get_or_init(Self::default)
+		}
+
+		fn state(&self) -> MutexGuard<'_, PoolState> {
+			// jobs are ran outside of the lock, so it's never poisoned mid-update.
+			self.state.lock().unwrap_or_else(PoisonError::into_inner)
+		}
+
+		fn execute(&'static self, job: Job) -> io::Result<()> {
+			let mut state = self.state();
+			state.jobs.push_back(job);
+
+			if state.idle > 0 || state.workers >= MAX_WORKERS {
+				drop(state);
+				self.queued.notify_one();
+
+				return Ok(());
+			}
+
+			state.workers += 1;
+			drop(state);
+
+			let spawned = thread::Builder::new()
+				.name("starchart-fs".to_owned())
+				.spawn(move || self.work());
+
+			if let Err(e) = spawned {
+				let mut state = self.state();
+				state.workers -= 1;
+
+				// with no workers left the queued jobs would never run, so they're failed instead.
+				if state.workers == 0 {
+					state.jobs.clear();
+					return Err(e);
+				}
+			}
+
+			Ok(())
+		}
+
+		fn work(&self) {
+			let mut state = self.state();
+
+			loop {
+				if let Some(job) = state.jobs.pop_front() {
+					drop(state);
+
+					// a panic drops the job's sender, which fails the operation rather than the worker.
+					let _ran = catch_unwind(AssertUnwindSafe(job));
+
+					state = self.state();
+					continue;
+				}
+
+				state.idle += 1;
+				let (next, timeout) = self
+					.queued
+					.wait_timeout(state, KEEP_ALIVE)
+					.unwrap_or_else(PoisonError::into_inner);
+				state = next;
+				state.idle -= 1;
+
+				if timeout.timed_out() && state.jobs.is_empty() {
+					state.workers -= 1;
+					return;
+				}
+			}
+		}
+	}
+
+	/// Runs the blocking operation on the worker pool, completing once it's done.
+	pub async fn unblock<T, F>(f: F) -> io::Result<T>
+	where
+		T: Send + 'static,
+		F: FnOnce() -> io::Result<T> + Send + 'static,
+	{
+		let (sender, receiver) = oneshot::channel();
+
+		Pool::get().execute(Box::new(move || {
+			// the future was dropped, so nobody is waiting on the result.
+			let _sent = sender.send(f());
+		}))?;
+
+		receiver
+			.await
+			.unwrap_or_else(|_| Err(io::Error::other("the file operation panicked")))
+	}
+
+	pub async fn read(path: &Path) -> io::Result<Vec<u8>> {
+		let path = path.to_owned();
+		unblock(move || fs::read(path)).await
+	}
+
+	pub async fn read_to_string(path: &Path) -> io::Result<String> {
+		let path = path.to_owned();
+		unblock(move || fs::read_to_string(path)).await
+	}
+
+	pub async fn write(path: &Path, contents: &[u8]) -> io::Result<()> {
+		let (path, contents) = (path.to_owned(), contents.to_vec());
+		unblock(move || fs::write(path, contents)).await
+	}
+
+	pub async fn metadata(path: &Path) -> io::Result<Metadata> {
+		let path = path.to_owned();
+		unblock(move || fs::metadata(path)).await
+	}
+
+	pub async fn read_dir(path: &Path) -> io::Result<Vec<DirEntry>> {
+		let path = path.to_owned();
+		unblock(move || {
+			fs::read_dir(path)?
+				.map(|entry| {
+					let entry = entry?;
+
+					Ok(DirEntry {
+						path: entry.path(),
+						file_name: entry.file_name(),
+						file_type: entry.file_type()?,
+					})
+				})
+				.collect()
+		})
+		.await
+	}
+
+	pub async fn create_dir(path: &Path) -> io::Result<()> {
+		let path = path.to_owned();
+		unblock(move || fs::create_dir(path)).await
+	}
+
+	pub async fn create_dir_all(path: &Path) -> io::Result<()> {
+		let path = path.to_owned();
+		unblock(move || fs::create_dir_all(path)).await
+	}
+
+	pub async fn remove_dir_all(path: &Path) -> io::Result<()> {
+		let path = path.to_owned();
+		unblock(move || fs::remove_dir_all(path)).await
+	}
+
+	pub async fn remove_file(path: &Path) -> io::Result<()> {
+		let path = path.to_owned();
+		unblock(move || fs::remove_file(path)).await
+	}
+
+	pub async fn rename(from: &Path, to: &Path) -> io::Result<()> {
+		let (from, to) = (from.to_owned(), to.to_owned());
+		unblock(move || fs::rename(from, to)).await
+	}
+
+	/// Links a new path to an existing file, failing with [`io::ErrorKind::AlreadyExists`] if the new path exists.
+	pub async fn hard_link(from: &Path, to: &Path) -> io::Result<()> {
+		let (from, to) = (from.to_owned(), to.to_owned());
+		unblock(move || fs::hard_link(from, to)).await
+	}
+
+	/// Flushes a file or directory to disk.
+	pub async fn sync(path: &Path) -> io::Result<()> {
+		let path = path.to_owned();
+		unblock(move || fs::File::open(path)?.sync_all()).await
+	}
+}
+
+/// Checks whether a directory exists.
+pub async fn dir_exists(path: &Path) -> io::Result<bool> {
+	match metadata(path).await {
+		Ok(metadata) => Ok(metadata.is_dir()),
+		Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(false),
+		Err(e) => Err(e),
+	}
+}
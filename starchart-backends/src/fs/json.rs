@@ -55,18 +55,33 @@ impl Transcoder for JsonTranscoder {
 
 #[cfg(all(test, not(miri)))]
 mod tests {
-	use std::{fmt::Debug, fs};
+	use std::{fmt::Debug, fs, path::Path};
 
-	use starchart::backend::Backend;
+	use starchart::{
+		backend::{testsuite, Backend},
+		error::ErrorType,
+		Error, Starchart,
+	};
 	use static_assertions::assert_impl_all;
 
 	use crate::{
-		fs::{transcoders::JsonTranscoder, FsBackend, FsError},
+		fs::{
+			transcoders::JsonTranscoder, util, CodecId, Envelope, FsBackend, FsError, FsErrorType,
+			Layout, StaleLockPolicy,
+		},
 		testing::{TestPath, TestSettings, TEST_GUARD},
 	};
 
 	assert_impl_all!(JsonTranscoder: Clone, Copy, Debug, Send, Sync);
 
+	#[test]
+	#[cfg(feature = "test-util")]
+	fn arbitrary_entries() {
+		for transcoder in [JsonTranscoder::standard(), JsonTranscoder::pretty()] {
+			crate::fs::roundtrip::check(&transcoder).unwrap();
+		}
+	}
+
 	#[tokio::test]
 	async fn init() -> Result<(), FsError> {
 		let _lock = TEST_GUARD.lock().await;
@@ -82,6 +97,30 @@ mod tests {
 		Ok(())
 	}
 
+	// without the `tokio` feature, the backend runs on any executor, not just inside a tokio runtime.
+	#[cfg(not(feature = "tokio"))]
+	#[test]
+	fn runtime_agnostic() -> Result<(), FsError> {
+		futures_executor::block_on(async {
+			let _lock = TEST_GUARD.lock().await;
+			let path = TestPath::new("runtime_agnostic", "json");
+			let backend = FsBackend::new(JsonTranscoder::default(), "json".to_owned(), &path)?;
+
+			backend.init().await?;
+			backend.create_table("table").await?;
+			backend
+				.create("table", "1", &TestSettings::default())
+				.await?;
+
+			assert_eq!(
+				backend.get::<TestSettings>("table", "1").await?,
+				Some(TestSettings::default())
+			);
+
+			Ok(())
+		})
+	}
+
 	#[tokio::test]
 	async fn table_methods() -> Result<(), FsError> {
 		let _lock = TEST_GUARD.lock().await;
@@ -95,10 +134,41 @@ mod tests {
 		backend.create_table("table").await?;
 
 		assert!(backend.has_table("table").await?);
+		assert_eq!(backend.tables::<Vec<_>>().await?, ["table"]);
 
 		backend.delete_table("table").await?;
 
 		assert!(!backend.has_table("table").await?);
+		assert!(backend.tables::<Vec<String>>().await?.is_empty());
+
+		Ok(())
+	}
+
+	#[tokio::test]
+	async fn rename_and_truncate_table() -> Result<(), FsError> {
+		let _lock = TEST_GUARD.lock().await;
+		let path = TestPath::new("rename_and_truncate_table", "json");
+		let backend = FsBackend::new(JsonTranscoder::default(), "json".to_owned(), &path)?;
+
+		backend.init().await?;
+
+		backend.create_table("table").await?;
+		backend
+			.create("table", "1", &TestSettings::default())
+			.await?;
+
+		backend.rename_table("table", "renamed").await?;
+
+		assert!(!backend.has_table("table").await?);
+		assert_eq!(
+			backend.get::<TestSettings>("renamed", "1").await?,
+			Some(TestSettings::default())
+		);
+
+		backend.truncate_table("renamed").await?;
+
+		assert!(backend.has_table("renamed").await?);
+		assert!(backend.get_keys::<Vec<String>>("renamed").await?.is_empty());
 
 		Ok(())
 	}
@@ -276,4 +346,393 @@ mod tests {
 
 		Ok(())
 	}
+
+	#[tokio::test]
+	async fn corrupt_entries() -> Result<(), Error> {
+		let _lock = TEST_GUARD.lock().await;
+		let path = TestPath::new("corrupt_entries", "json");
+
+		// a partially written file is reported the same way as an entry of the wrong type.
+		let backend = FsBackend::new(JsonTranscoder::default(), "json".to_owned(), &path)?;
+		backend.init().await?;
+		backend.create_table("corrupt").await?;
+		let entry = AsRef::<Path>::as_ref(&path).join("corrupt").join("1.json");
+		fs::write(&entry, b"{\"id\": 1, \"val").map_err(|e| Error::backend(Some(Box::new(e))))?;
+
+		let err = backend
+			.get::<TestSettings>("corrupt", "1")
+			.await
+			.unwrap_err();
+		assert!(matches!(err.kind(), FsErrorType::CorruptEntry));
+		assert!(matches!(Error::from(err).kind(), ErrorType::CorruptEntry));
+
+		Ok(())
+	}
+
+	#[tokio::test]
+	async fn testsuite() -> Result<(), FsError> {
+		let _lock = TEST_GUARD.lock().await;
+		let path = TestPath::new("testsuite", "json");
+		let backend = FsBackend::new(JsonTranscoder::default(), "json".to_owned(), &path)?;
+
+		testsuite::run(backend).await
+	}
+
+	#[tokio::test]
+	async fn long_keys() -> Result<(), FsError> {
+		let _lock = TEST_GUARD.lock().await;
+		let path = TestPath::new("long_keys", "json");
+		let backend = FsBackend::new(JsonTranscoder::default(), "json".to_owned(), &path)?;
+
+		backend.init().await?;
+		backend.create_table("table").await?;
+
+		let long_key = format!("https://example.com/{}", "a".repeat(300));
+		let settings = TestSettings::default();
+
+		backend.create("table", &long_key, &settings).await?;
+		backend.create("table", "1", &settings).await?;
+
+		assert!(backend.has("table", &long_key).await?);
+		assert_eq!(
+			backend.get::<TestSettings>("table", &long_key).await?,
+			Some(settings)
+		);
+
+		let mut keys: Vec<String> = backend.get_keys("table").await?;
+		keys.sort();
+		assert_eq!(keys, ["1".to_owned(), long_key.clone()]);
+
+		backend.delete("table", &long_key).await?;
+
+		assert!(!backend.has("table", &long_key).await?);
+		assert_eq!(
+			fs::read_dir(AsRef::<Path>::as_ref(&path).join("table"))?.count(),
+			1
+		);
+
+		Ok(())
+	}
+
+	#[tokio::test]
+	async fn long_key_collision() -> Result<(), FsError> {
+		let _lock = TEST_GUARD.lock().await;
+		let path = TestPath::new("long_key_collision", "json");
+		let backend = FsBackend::new(JsonTranscoder::default(), "json".to_owned(), &path)?;
+
+		backend.init().await?;
+		backend.create_table("table").await?;
+
+		let long_key = "a".repeat(300);
+		let other_key = "b".repeat(300);
+		backend
+			.create("table", &other_key, &TestSettings::default())
+			.await?;
+
+		// stand in for a colliding key by moving the stored entry to the file the long key hashes to.
+		let table = AsRef::<Path>::as_ref(&path).join("table");
+		let file = |key: &str| table.join(util::file_name("json", key));
+		fs::rename(file(&other_key), file(&long_key))?;
+		fs::rename(
+			util::key_path(&file(&other_key)),
+			util::key_path(&file(&long_key)),
+		)?;
+
+		assert!(!backend.has("table", &long_key).await?);
+		assert_eq!(backend.get::<TestSettings>("table", &long_key).await?, None);
+
+		let err = backend
+			.create("table", &long_key, &TestSettings::default())
+			.await
+			.unwrap_err();
+		assert!(matches!(err.kind(), FsErrorType::KeyCollision(_)));
+
+		backend.delete("table", &long_key).await?;
+		let keys: Vec<String> = backend.get_keys("table").await?;
+		assert_eq!(keys, [other_key]);
+
+		Ok(())
+	}
+
+	#[tokio::test]
+	async fn path_strategy() -> Result<(), FsError> {
+		let _lock = TEST_GUARD.lock().await;
+		let path = TestPath::new("path_strategy", "json");
+		let layout = Layout::new()
+			.with_root("tables")
+			.with_nesting('.')
+			.with_extension_alias("jsn");
+		let backend = FsBackend::new(JsonTranscoder::default(), "json".to_owned(), &path)?
+			.with_path_strategy(layout);
+
+		backend.init().await?;
+		backend.create_table("guilds.settings").await?;
+		backend.create_table("users").await?;
+
+		let mut tables: Vec<String> = backend.tables().await?;
+		tables.sort();
+		assert_eq!(tables, ["guilds.settings", "users"]);
+
+		let table = AsRef::<Path>::as_ref(&path).join("tables/guilds/settings");
+		assert!(table.is_dir());
+
+		// an entry written by another tool, with a different extension.
+		fs::write(
+			table.join("1.jsn"),
+			serde_json::to_vec(&TestSettings::default())?,
+		)?;
+
+		assert!(backend.has("guilds.settings", "1").await?);
+		assert_eq!(
+			backend.get_keys::<Vec<_>>("guilds.settings").await?,
+			vec!["1".to_owned()]
+		);
+		assert_eq!(
+			backend.get::<TestSettings>("guilds.settings", "1").await?,
+			Some(TestSettings::default())
+		);
+
+		let updated = TestSettings {
+			value: "updated".to_owned(),
+			..TestSettings::default()
+		};
+		backend.update("guilds.settings", "1", &updated).await?;
+		assert!(table.join("1.json").is_file());
+		assert!(!table.join("1.jsn").exists());
+		assert_eq!(
+			backend.get::<TestSettings>("guilds.settings", "1").await?,
+			Some(updated)
+		);
+
+		backend.delete("guilds.settings", "1").await?;
+		assert!(!backend.has("guilds.settings", "1").await?);
+		backend.delete_table("guilds.settings").await?;
+		assert!(!table.exists());
+
+		Ok(())
+	}
+
+	#[tokio::test]
+	async fn atomic_writes() -> Result<(), FsError> {
+		let _lock = TEST_GUARD.lock().await;
+		let path = TestPath::new("atomic_writes", "json");
+		let backend =
+			FsBackend::new(JsonTranscoder::default(), "json".to_owned(), &path)?.with_fsync(true);
+		assert!(backend.fsync());
+
+		backend.init().await?;
+		backend.create_table("table").await?;
+
+		let mut settings = TestSettings::default();
+		backend.create("table", "1", &settings).await?;
+		settings.value = "updated".to_owned();
+		backend.update("table", "1", &settings).await?;
+
+		let table = AsRef::<Path>::as_ref(&path).join("table");
+		let files = fs::read_dir(&table)?
+			.map(|entry| entry.map(|entry| entry.file_name()))
+			.collect::<Result<Vec<_>, _>>()?;
+		assert_eq!(files, vec!["1.json"]);
+
+		// a temporary file left behind by a crashed write isn't an entry.
+		fs::write(table.join(".__tmp_1_0_2.json"), b"{")?;
+		assert_eq!(
+			backend.get_keys::<Vec<_>>("table").await?,
+			vec!["1".to_owned()]
+		);
+		assert_eq!(
+			backend.get::<TestSettings>("table", "1").await?,
+			Some(settings)
+		);
+
+		Ok(())
+	}
+
+	#[tokio::test]
+	async fn exclusive() -> Result<(), FsError> {
+		let _lock = TEST_GUARD.lock().await;
+		let path = TestPath::new("exclusive", "json");
+		let backend =
+			FsBackend::try_new_exclusive(JsonTranscoder::default(), "json".to_owned(), &path)?;
+		assert!(backend.is_exclusive());
+
+		backend.init().await?;
+		backend.create_table("table").await?;
+
+		let other =
+			FsBackend::try_new_exclusive(JsonTranscoder::default(), "json".to_owned(), &path);
+		assert!(matches!(
+			other.map_err(FsError::into_parts),
+			Err((FsErrorType::Locked(_), None))
+		));
+
+		drop(backend);
+
+		let backend =
+			FsBackend::try_new_exclusive(JsonTranscoder::default(), "json".to_owned(), &path)?;
+		assert!(backend.has_table("table").await?);
+
+		Ok(())
+	}
+
+	#[tokio::test]
+	async fn exclusive_chart() -> Result<(), Error> {
+		let _lock = TEST_GUARD.lock().await;
+		let path = TestPath::new("exclusive_chart", "json");
+		let backend =
+			FsBackend::try_new_exclusive(JsonTranscoder::default(), "json".to_owned(), &path)?;
+		let chart = Starchart::new(backend).await?;
+
+		// dropping a clone of the chart doesn't give up exclusive access.
+		drop(chart.clone());
+		let other =
+			FsBackend::try_new_exclusive(JsonTranscoder::default(), "json".to_owned(), &path);
+		assert!(matches!(
+			other.map_err(FsError::into_parts),
+			Err((FsErrorType::Locked(_), None))
+		));
+
+		drop(chart);
+		FsBackend::try_new_exclusive(JsonTranscoder::default(), "json".to_owned(), &path)?;
+
+		Ok(())
+	}
+
+	#[tokio::test]
+	async fn locked_chart() -> Result<(), Error> {
+		let _lock = TEST_GUARD.lock().await;
+		let path = TestPath::new("locked_chart", "json");
+		let locked = || {
+			FsBackend::new(JsonTranscoder::default(), "json".to_owned(), &path)
+				.map(|backend| backend.with_lock(StaleLockPolicy::Never))
+		};
+		let chart = Starchart::new(locked()?).await?;
+
+		// dropping a clone of the chart doesn't release the lock file.
+		drop(chart.clone());
+		let other = locked()?.init().await;
+		assert!(matches!(
+			other.map_err(FsError::into_parts),
+			Err((FsErrorType::Locked(_), None))
+		));
+
+		drop(chart);
+		locked()?.init().await?;
+
+		Ok(())
+	}
+
+	#[tokio::test]
+	async fn envelopes() -> Result<(), FsError> {
+		let _lock = TEST_GUARD.lock().await;
+		let path = TestPath::new("envelopes", "json");
+		let backend = FsBackend::new(JsonTranscoder::default(), "json".to_owned(), &path)?;
+
+		backend.init().await?;
+		backend.create_table("table").await?;
+		backend
+			.create("table", "1", &TestSettings::default())
+			.await?;
+
+		assert_eq!(backend.read_envelope("table", "1").await?, None);
+
+		let envelope = Envelope::new().with_codec(CodecId::GZIP);
+		fs::write(
+			AsRef::<Path>::as_ref(&path).join("table").join("2.json"),
+			envelope.encode(b"compressed")?,
+		)?;
+
+		let read = backend.read_envelope("table", "2").await?;
+		assert_eq!(read, Some(envelope));
+		assert!(read.is_some_and(|envelope| envelope.is_compressed()));
+
+		Ok(())
+	}
+
+	#[tokio::test]
+	#[cfg(feature = "memory")]
+	async fn tiered() -> Result<(), Error> {
+		use starchart::backend::{ConsistencyPolicy, TieredBackend, TieredErrorType};
+
+		use crate::memory::MemoryBackend;
+
+		let _lock = TEST_GUARD.lock().await;
+		let path = TestPath::new("tiered", "json");
+		let primary = FsBackend::new(JsonTranscoder::default(), "json".to_owned(), &path)?;
+		let chart = Starchart::new(TieredBackend::new(primary, MemoryBackend::new())).await?;
+		chart.create_table("table").await?;
+
+		let table = chart.table::<TestSettings>("table");
+		table.insert(&TestSettings::default()).await?;
+		assert!(chart.replica().has("table", "1").await?);
+
+		let primary = FsBackend::new(JsonTranscoder::default(), "json".to_owned(), &path)?;
+		let backend = TieredBackend::new(primary, MemoryBackend::new())
+			.with_policy(ConsistencyPolicy::Either);
+
+		// the primary can no longer be read or written once it's directory is a file.
+		fs::remove_dir_all(&path).unwrap();
+		fs::write(&path, b"").unwrap();
+
+		assert_eq!(table.get(&1_u32).await?, Some(TestSettings::default()));
+		assert!(chart.stats().fallback_reads() > 0);
+
+		let err = chart
+			.replace("table", "2", &TestSettings::default())
+			.await
+			.unwrap_err();
+		assert!(matches!(err.kind(), TieredErrorType::Primary));
+		assert_eq!(chart.stats().failed_primary_writes(), 1);
+		assert!(chart.replica().has("table", "2").await?);
+
+		backend.replica().create_table("table").await?;
+		backend
+			.replace("table", "1", &TestSettings::default())
+			.await?;
+		assert!(backend.has("table", "1").await?);
+
+		fs::remove_file(&path).unwrap();
+
+		Ok(())
+	}
+
+	#[tokio::test]
+	async fn retry() -> Result<(), Error> {
+		use starchart::{
+			backend::{Backoff, RetryBackend},
+			timer::FnTimer,
+		};
+
+		let _lock = TEST_GUARD.lock().await;
+		let path = TestPath::new("retry", "json");
+		let backend = || FsBackend::new(JsonTranscoder::default(), "json".to_owned(), &path);
+		backend()?.init().await?;
+
+		let failing = RetryBackend::new(backend()?).with_backoff(Backoff::None);
+		let not_retryable = RetryBackend::new(backend()?).with_retryable(|_| false);
+
+		// the directory is back by the time the operation is retried.
+		let directory = AsRef::<Path>::as_ref(&path).to_owned();
+		let recovering = RetryBackend::new(backend()?).with_timer(FnTimer::new(move |_| {
+			fs::remove_file(&directory).unwrap();
+			fs::create_dir(&directory).unwrap();
+			Box::pin(async {})
+		}));
+
+		// the backend can't be read while it's directory is a file.
+		fs::remove_dir_all(&path).unwrap();
+		fs::write(&path, b"").unwrap();
+
+		assert!(failing.has_table("table").await.is_err());
+		assert_eq!(failing.stats().retries(), 2);
+		assert_eq!(failing.stats().exhausted(), 1);
+
+		assert!(not_retryable.has_table("table").await.is_err());
+		assert_eq!(not_retryable.stats().retries(), 0);
+
+		assert!(!recovering.has_table("table").await?);
+		assert_eq!(recovering.stats().retries(), 1);
+
+		Ok(())
+	}
 }
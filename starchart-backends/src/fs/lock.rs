@@ -0,0 +1,439 @@
+use std::{
+	collections::hash_map::RandomState,
+	fs::{self, File},
+	hash::{BuildHasher, Hasher},
+	io::ErrorKind,
+	path::{Path, PathBuf},
+	process,
+	time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use fs2::FileExt;
+
+use super::{io, FsError, FsErrorType};
+
+/// The name of the lock file created in the base directory of a locked [`FsBackend`].
+///
+/// [`FsBackend`]: super::FsBackend
+pub const LOCK_FILE: &str = ".starchart.lock";
+
+/// The name of the file an exclusive [`FsBackend`] holds an advisory lock on, see [`FsBackend::try_new_exclusive`].
+///
+/// The file is never removed, as the lock is tied to the open file rather than it's existence.
+///
+/// [`FsBackend`]: super::FsBackend
+/// [`FsBackend::try_new_exclusive`]: super::FsBackend::try_new_exclusive
+pub const EXCLUSIVE_LOCK_FILE: &str = ".starchart.exclusive";
+
+/// How a locked [`FsBackend`] decides whether an existing lock file was left behind by a crashed process.
+///
+/// [`FsBackend`]: super::FsBackend
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum StaleLockPolicy {
+	/// Never break an existing lock, it must be removed manually.
+	Never,
+	/// Break the lock if the process that created it is no longer running.
+	///
+	/// Process liveness can only be checked on Linux, on other platforms the holder is
+	/// always assumed to be alive. This is the default.
+	#[default]
+	ProcessExited,
+	/// Break the lock if it hasn't been refreshed within the given duration.
+	///
+	/// Long running processes should call [`FsBackend::refresh_lock`] periodically to keep their lease.
+	///
+	/// [`FsBackend::refresh_lock`]: super::FsBackend::refresh_lock
+	LeaseExpired(Duration),
+}
+
+/// A lock file held by an [`FsBackend`], removed once every clone of the backend has been dropped.
+///
+/// [`FsBackend`]: super::FsBackend
+#[derive(Debug)]
+pub struct LockGuard {
+	path: PathBuf,
+	token: String,
+}
+
+impl LockGuard {
+	/// Creates a guard for a lock file already acquired by the holder with the given token.
+	#[must_use]
+	pub fn new(path: PathBuf, token: &str) -> Self {
+		Self {
+			path,
+			token: token.to_owned(),
+		}
+	}
+}
+
+impl Drop for LockGuard {
+	fn drop(&mut self) {
+		release(&self.path, &self.token);
+	}
+}
+
+/// Acquires the lock file for the holder with the given token, breaking it first if it's stale
+/// according to the policy.
+///
+/// The lock file records the holder's process id, when it was last refreshed, the holder's token
+/// (unique to each [`FsBackend`], so two backends in the same process don't share the lock),
+/// and the boot id of the machine on Linux. A lock file that can't be parsed is always treated
+/// as held.
+///
+/// [`FsBackend`]: super::FsBackend
+pub async fn acquire(path: &Path, token: &str, policy: StaleLockPolicy) -> Result<(), FsError> {
+	for _ in 0..2 {
+		match create(path, token).await {
+			Ok(()) => return Ok(()),
+			Err(e) if e.kind() == ErrorKind::AlreadyExists => {
+				// the lock was released in the meantime.
+				let Some(raw) = read(path).await? else {
+					continue;
+				};
+
+				let holder = Holder::parse(&raw);
+
+				if holder
+					.as_ref()
+					.is_some_and(|holder| holder.token.as_deref() == Some(token))
+				{
+					return refresh(path, token).await;
+				}
+
+				if !is_stale(path, holder.as_ref(), policy).await?
+					|| !break_lock(path, token, &raw).await?
+				{
+					break;
+				}
+			}
+			Err(e) => return Err(e.into()),
+		}
+	}
+
+	Err(locked(path))
+}
+
+/// Rewrites the lock file, renewing the lease.
+///
+/// The new contents are renamed over the lock file, so it's never seen half written.
+pub async fn refresh(path: &Path, token: &str) -> Result<(), FsError> {
+	let held = read(path)
+		.await?
+		.and_then(|raw| Holder::parse(&raw))
+		.is_some_and(|holder| holder.token.as_deref() == Some(token));
+
+	if !held {
+		return Err(locked(path));
+	}
+
+	let temp = sibling(path, token, "tmp");
+	io::write(&temp, contents(token).as_bytes()).await?;
+
+	Ok(io::rename(&temp, path).await?)
+}
+
+/// Removes the lock file if it's held by the holder with the given token.
+///
+/// This is synchronous, as it's ran when a [`LockGuard`] is dropped, which may happen outside of a runtime.
+pub fn release(path: &Path, token: &str) {
+	let held = fs::read_to_string(path)
+		.ok()
+		.and_then(|raw| Holder::parse(&raw))
+		.is_some_and(|holder| holder.token.as_deref() == Some(token));
+
+	if held {
+		// nothing can be done if this fails, the next process will see the lock as stale.
+		let _removed = fs::remove_file(path);
+	}
+}
+
+/// Creates a token unique to a lock holder.
+pub fn token() -> String {
+	let random = || RandomState::new().build_hasher().finish();
+
+	format!("{:016x}{:016x}", random(), random())
+}
+
+/// Takes an advisory lock on the file (`flock` on unix, `LockFileEx` on windows), failing if
+/// another process already holds it.
+///
+/// The lock is held until the returned file is closed, which the OS does even if the process crashes.
+pub fn lock_exclusive(path: &Path) -> Result<File, FsError> {
+	let file = fs::OpenOptions::new()
+		.read(true)
+		.write(true)
+		.create(true)
+		.truncate(false)
+		.open(path)?;
+
+	match FileExt::try_lock_exclusive(&file) {
+		Ok(()) => Ok(file),
+		Err(e) if e.raw_os_error() == fs2::lock_contended_error().raw_os_error() => {
+			Err(locked(path))
+		}
+		Err(e) => Err(e.into()),
+	}
+}
+
+/// The holder of a lock, as recorded in the lock file.
+#[derive(Debug)]
+struct Holder {
+	pid: u32,
+	token: Option<String>,
+	boot_id: Option<String>,
+}
+
+impl Holder {
+	/// Parses the lock file, lock files written before tokens were recorded only have the process id.
+	fn parse(raw: &str) -> Option<Self> {
+		let mut lines = raw.lines().map(str::trim);
+
+		let pid = lines.next()?.parse().ok()?;
+		let _timestamp = lines.next();
+		let mut rest = lines.filter(|line| !line.is_empty()).map(ToOwned::to_owned);
+
+		Some(Self {
+			pid,
+			token: rest.next(),
+			boot_id: rest.next(),
+		})
+	}
+}
+
+/// Writes the lock file to a temporary file, then hard links it into place, which fails if the
+/// lock file already exists, so the lock file never exists without it's contents.
+async fn create(path: &Path, token: &str) -> std::io::Result<()> {
+	let temp = sibling(path, token, "tmp");
+	io::write(&temp, contents(token).as_bytes()).await?;
+
+	let linked = io::hard_link(&temp, path).await;
+
+	// the lock file is a link to the same contents, so the temporary file is no longer needed.
+	let _removed = io::remove_file(&temp).await;
+
+	linked
+}
+
+/// Moves a stale lock file out of the way, returning `false` if it was replaced by a live holder
+/// after it was found to be stale, in which case it's put back.
+async fn break_lock(path: &Path, token: &str, stale: &str) -> Result<bool, FsError> {
+	let broken = sibling(path, token, "broken");
+
+	match io::rename(path, &broken).await {
+		Ok(()) => {}
+		// another process broke it first.
+		Err(e) if e.kind() == ErrorKind::NotFound => return Ok(true),
+		Err(e) => return Err(e.into()),
+	}
+
+	let moved = io::read_to_string(&broken).await?;
+	let replaced = moved != stale;
+
+	if replaced {
+		// if yet another lock was taken since, that holder keeps it, and this one will see itself as stale.
+		let _restored = io::hard_link(&broken, path).await;
+	}
+
+	io::remove_file(&broken).await?;
+
+	Ok(!replaced)
+}
+
+async fn read(path: &Path) -> Result<Option<String>, FsError> {
+	match io::read_to_string(path).await {
+		Ok(raw) => Ok(Some(raw)),
+		Err(e) if e.kind() == ErrorKind::NotFound => Ok(None),
+		Err(e) => Err(e.into()),
+	}
+}
+
+async fn is_stale(
+	path: &Path,
+	holder: Option<&Holder>,
+	policy: StaleLockPolicy,
+) -> Result<bool, FsError> {
+	match policy {
+		StaleLockPolicy::Never => Ok(false),
+		StaleLockPolicy::ProcessExited => Ok(holder.is_some_and(|holder| {
+			// a lock taken on another machine, or before a reboot, can't be checked.
+			let same_boot = match (&holder.boot_id, boot_id()) {
+				(Some(theirs), Some(ours)) => *theirs == ours,
+				_ => true,
+			};
+
+			same_boot && !process_alive(holder.pid)
+		})),
+		StaleLockPolicy::LeaseExpired(lease) => {
+			let modified = match io::metadata(path).await {
+				Ok(metadata) => metadata.modified()?,
+				Err(e) if e.kind() == ErrorKind::NotFound => return Ok(true),
+				Err(e) => return Err(e.into()),
+			};
+
+			Ok(modified.elapsed().is_ok_and(|age| age > lease))
+		}
+	}
+}
+
+fn contents(token: &str) -> String {
+	let timestamp = SystemTime::now()
+		.duration_since(UNIX_EPOCH)
+		.unwrap_or_default()
+		.as_secs();
+
+	let mut contents = format!("{}\n{}\n{}\n", process::id(), timestamp, token);
+	if let Some(boot_id) = boot_id() {
+		contents.push_str(&boot_id);
+		contents.push('\n');
+	}
+
+	contents
+}
+
+/// A file next to the lock file, unique to the holder.
+fn sibling(path: &Path, token: &str, suffix: &str) -> PathBuf {
+	let mut name = path.as_os_str().to_owned();
+	name.push(format!(".{token}.{suffix}"));
+
+	name.into()
+}
+
+fn locked(path: &Path) -> FsError {
+	FsError {
+		source: None,
+		kind: FsErrorType::Locked(path.to_path_buf()),
+	}
+}
+
+#[cfg(target_os = "linux")]
+fn boot_id() -> Option<String> {
+	fs::read_to_string("/proc/sys/kernel/random/boot_id")
+		.ok()
+		.map(|id| id.trim().to_owned())
+}
+
+#[cfg(not(target_os = "linux"))]
+const fn boot_id() -> Option<String> {
+	None
+}
+
+#[cfg(target_os = "linux")]
+fn process_alive(pid: u32) -> bool {
+	Path::new("/proc").join(pid.to_string()).exists()
+}
+
+#[cfg(not(target_os = "linux"))]
+const fn process_alive(_: u32) -> bool {
+	true
+}
+
+#[cfg(all(test, not(miri)))]
+mod tests {
+	use std::{fs, path::Path, time::Duration};
+
+	use super::{acquire, lock_exclusive, refresh, release, token, StaleLockPolicy};
+	use crate::{
+		fs::{FsError, FsErrorType},
+		testing::{TestPath, TEST_GUARD},
+	};
+
+	#[tokio::test]
+	async fn acquire_and_release() -> Result<(), FsError> {
+		let _lock = TEST_GUARD.lock().await;
+		let path = TestPath::new("acquire_and_release", "lock");
+		fs::create_dir_all(&path)?;
+		let lock_path = Path::new(&path).join("lock");
+		let (held, other) = (token(), token());
+
+		acquire(&lock_path, &held, StaleLockPolicy::Never).await?;
+		acquire(&lock_path, &held, StaleLockPolicy::Never).await?;
+
+		assert!(lock_path.exists());
+
+		// another holder in the same process doesn't share the lock.
+		let locked = acquire(&lock_path, &other, StaleLockPolicy::ProcessExited).await;
+		assert!(matches!(
+			locked.map_err(FsError::into_parts),
+			Err((FsErrorType::Locked(_), None))
+		));
+		assert!(refresh(&lock_path, &other).await.is_err());
+
+		release(&lock_path, &other);
+		assert!(lock_path.exists());
+
+		release(&lock_path, &held);
+
+		assert!(!lock_path.exists());
+		assert_eq!(fs::read_dir(&path)?.count(), 0);
+
+		Ok(())
+	}
+
+	#[tokio::test]
+	async fn stale_locks() -> Result<(), FsError> {
+		let _lock = TEST_GUARD.lock().await;
+		let path = TestPath::new("stale_locks", "lock");
+		fs::create_dir_all(&path)?;
+		let lock_path = Path::new(&path).join("lock");
+		let held = token();
+
+		fs::write(&lock_path, format!("{}\n0\n", u32::MAX))?;
+
+		let locked = acquire(&lock_path, &held, StaleLockPolicy::Never).await;
+		assert!(matches!(
+			locked.map_err(FsError::into_parts),
+			Err((FsErrorType::Locked(_), None))
+		));
+
+		let locked = acquire(
+			&lock_path,
+			&held,
+			StaleLockPolicy::LeaseExpired(Duration::from_secs(90)),
+		)
+		.await;
+		assert!(locked.is_err());
+
+		acquire(
+			&lock_path,
+			&held,
+			StaleLockPolicy::LeaseExpired(Duration::ZERO),
+		)
+		.await?;
+
+		release(&lock_path, &held);
+
+		// a lock file that can't be parsed, such as one being written by an older version, is held.
+		fs::write(&lock_path, "")?;
+		let locked = acquire(&lock_path, &held, StaleLockPolicy::ProcessExited).await;
+		assert!(locked.is_err());
+
+		fs::write(&lock_path, format!("{}\n0\n", u32::MAX))?;
+
+		#[cfg(target_os = "linux")]
+		acquire(&lock_path, &held, StaleLockPolicy::ProcessExited).await?;
+
+		Ok(())
+	}
+
+	#[test]
+	fn exclusive_locks() -> Result<(), FsError> {
+		let path = TestPath::new("exclusive_locks", "lock");
+		fs::create_dir_all(&path)?;
+		let lock_path = Path::new(&path).join("exclusive");
+
+		let held = lock_exclusive(&lock_path)?;
+
+		let locked = lock_exclusive(&lock_path);
+		assert!(matches!(
+			locked.map_err(FsError::into_parts),
+			Err((FsErrorType::Locked(_), None))
+		));
+
+		drop(held);
+
+		lock_exclusive(&lock_path)?;
+
+		Ok(())
+	}
+}
@@ -1,43 +1,91 @@
 //! The file-system based backends for the starchart crate.
 
+mod analysis;
 #[cfg(feature = "binary")]
 mod binary;
+#[cfg(any(feature = "gzip", feature = "zstd"))]
+mod compressed;
+#[cfg(feature = "encryption")]
+mod encrypted;
+mod envelope;
 mod error;
+mod io;
 #[cfg(feature = "json")]
 mod json;
+mod lock;
+mod path;
+#[cfg(feature = "test-util")]
+pub mod roundtrip;
 #[cfg(feature = "toml")]
 mod toml;
+mod wal;
 #[cfg(feature = "yaml")]
 mod yaml;
 
 use std::{
+	borrow::Cow,
 	io::{ErrorKind, Read},
-	iter::FromIterator,
+	iter::{once, FromIterator},
 	path::{Path, PathBuf},
+	sync::{Arc, OnceLock},
 };
 
+use self::wal::{Operation, Wal};
+pub use self::{
+	analysis::{Advice, TableAnalysis},
+	envelope::{CodecId, Envelope, EnvelopeFlags, ENVELOPE_MAGIC, ENVELOPE_VERSION},
+	error::{FsError, FsErrorType},
+	lock::{LockGuard, StaleLockPolicy, EXCLUSIVE_LOCK_FILE, LOCK_FILE},
+	path::{Casing, Layout, PathStrategy},
+	wal::WAL_FILE,
+};
 use futures_util::future::{err, FutureExt};
 use starchart::{
 	backend::{
 		futures::{
 			CreateFuture, CreateTableFuture, DeleteFuture, DeleteTableFuture, GetFuture,
-			GetKeysFuture, HasFuture, HasTableFuture, InitFuture, UpdateFuture,
+			GetKeysFuture, HasFuture, HasTableFuture, InitFuture, RenameTableFuture, TablesFuture,
+			UpdateFuture,
 		},
 		Backend,
 	},
+	transaction::TransactionalBackend,
 	Entry,
 };
-use tokio::fs;
-
-pub use self::error::{FsError, FsErrorType};
 
 /// An fs-based backend for the starchart crate.
+///
+/// Each entry is stored in it's own file, named after it's key. Keys that would make a file name
+/// longer than most filesystems allow (255 bytes) are stored in a file named after a hash of the key
+/// instead, with the key itself stored in a `.key` file next to it. The `.key` file is checked on every
+/// read and write, so a key that hashes to the same file name as another is never read as the other key,
+/// and fails to be written with [`FsErrorType::KeyCollision`]. This is transparent to the [`Starchart`].
+///
+/// Where tables and entries are stored can be changed with a [`PathStrategy`], see [`Self::with_path_strategy`].
+///
+/// Entries are written to a temporary file in the same directory, which is then renamed over the
+/// entry's file, so a crash mid-write never leaves a partially written entry behind. For stronger
+/// guarantees, mutations can also be journaled, see [`Self::with_write_ahead_log`].
+///
+/// File I/O is ran with `tokio::fs` by default, which requires running inside a tokio runtime.
+/// With the default `tokio` feature disabled, the backend doesn't depend on an async runtime,
+/// and runs file I/O on a small pool of worker threads instead.
+///
+/// [`Starchart`]: starchart::Starchart
 #[derive(Debug, Clone)]
 #[cfg(feature = "fs")]
 pub struct FsBackend<T> {
 	transcoder: T,
 	extension: String,
 	base_directory: PathBuf,
+	lock: Option<StaleLockPolicy>,
+	lock_token: String,
+	// set once the lock file is acquired, and shared with clones so it's released when the last of them drops.
+	held_lock: Arc<OnceLock<LockGuard>>,
+	paths: Arc<dyn PathStrategy>,
+	fsync: bool,
+	wal: Option<Arc<Wal>>,
+	exclusive: Option<Arc<std::fs::File>>,
 }
 
 impl<T: Transcoder> FsBackend<T> {
@@ -63,10 +111,49 @@ impl<T: Transcoder> FsBackend<T> {
 				transcoder,
 				extension,
 				base_directory: path,
+				lock: None,
+				lock_token: lock::token(),
+				held_lock: Arc::default(),
+				paths: Arc::new(Layout::default()),
+				fsync: false,
+				wal: None,
+				exclusive: None,
 			})
 		}
 	}
 
+	/// Creates a new [`FsBackend`] with exclusive access to the base directory, failing fast if
+	/// another process already has it.
+	///
+	/// Exclusivity is enforced with an advisory lock on the [`EXCLUSIVE_LOCK_FILE`] (`flock` on unix,
+	/// `LockFileEx` on windows), which is held until the backend and all of it's clones are dropped.
+	/// Unlike [`Self::with_lock`], the OS releases the lock if the process crashes, so it's never stale. Other processes are only kept out if they also use this method.
+	///
+	/// The base directory is created if it doesn't exist.
+	///
+	/// # Errors
+	///
+	/// Returns an [`FsErrorType::Locked`] error if another process has exclusive access to the
+	/// directory, or any error [`Self::new`] returns.
+	pub fn try_new_exclusive<P: AsRef<Path>>(
+		transcoder: T,
+		extension: String,
+		base_directory: P,
+	) -> Result<Self, FsError> {
+		let mut backend = Self::new(transcoder, extension, base_directory)?;
+
+		std::fs::create_dir_all(&backend.base_directory)?;
+		let file = lock::lock_exclusive(&backend.base_directory.join(EXCLUSIVE_LOCK_FILE))?;
+		backend.exclusive = Some(Arc::new(file));
+
+		Ok(backend)
+	}
+
+	/// Whether the [`FsBackend`] has exclusive access to the base directory, see [`Self::try_new_exclusive`].
+	pub const fn is_exclusive(&self) -> bool {
+		self.exclusive.is_some()
+	}
+
 	/// Returns the base directory for the [`FsBackend`].
 	pub fn base_directory(&self) -> &Path {
 		&self.base_directory
@@ -81,6 +168,255 @@ impl<T: Transcoder> FsBackend<T> {
 	pub fn transcoder(&self) -> &T {
 		&self.transcoder
 	}
+
+	/// Enables cross-process locking, using the given [`StaleLockPolicy`] to recover from
+	/// locks left behind by crashed processes.
+	///
+	/// The lock file is created in the base directory upon [`Backend::init`], and removed once the backend
+	/// and all of it's clones are dropped. Each backend holds the lock separately, a clone of a backend
+	/// shares it's lock, but two backends using the same directory in one process don't.
+	#[must_use]
+	pub const fn with_lock(mut self, policy: StaleLockPolicy) -> Self {
+		self.lock = Some(policy);
+
+		self
+	}
+
+	/// Sets the [`PathStrategy`] deciding where tables and entries are stored, instead of the default [`Layout`].
+	#[must_use]
+	pub fn with_path_strategy<P: PathStrategy + 'static>(mut self, strategy: P) -> Self {
+		self.paths = Arc::new(strategy);
+
+		self
+	}
+
+	/// Returns the [`PathStrategy`] deciding where tables and entries are stored.
+	pub fn path_strategy(&self) -> &dyn PathStrategy {
+		&*self.paths
+	}
+
+	/// Flushes every written entry to disk before it replaces the previous entry, along with the
+	/// directory holding it, so written entries survive a power loss. This is disabled by default.
+	///
+	/// Writes are atomic either way, this only affects whether they're durable once they return.
+	#[must_use]
+	pub const fn with_fsync(mut self, enabled: bool) -> Self {
+		self.fsync = enabled;
+
+		self
+	}
+
+	/// Whether written entries are flushed to disk, see [`Self::with_fsync`].
+	pub const fn fsync(&self) -> bool {
+		self.fsync
+	}
+
+	/// Records every mutation in an append-only log in the base directory before applying it,
+	/// see [`WAL_FILE`]. This is disabled by default.
+	///
+	/// The log is flushed to disk before each mutation is applied, and mutations that were logged
+	/// but never finished, such as those interrupted by a crash, are replayed upon [`Backend::init`].
+	/// Combined with [`Self::with_fsync`], a mutation is durable once it returns, and a crash at any
+	/// point either applies it completely or not at all.
+	///
+	/// The log is shared by clones of the backend, but not by separate backends, so multiple
+	/// processes using the same directory should also enable [`Self::with_lock`].
+	#[must_use]
+	pub fn with_write_ahead_log(mut self, enabled: bool) -> Self {
+		self.wal = enabled.then(|| Arc::new(Wal::new(self.base_directory.join(WAL_FILE))));
+
+		self
+	}
+
+	/// Returns the path of the write-ahead log if it's enabled, see [`Self::with_write_ahead_log`].
+	pub fn write_ahead_log(&self) -> Option<&Path> {
+		self.wal.as_deref().map(Wal::path)
+	}
+
+	/// Returns the [`StaleLockPolicy`] if cross-process locking is enabled.
+	pub const fn lock_policy(&self) -> Option<StaleLockPolicy> {
+		self.lock
+	}
+
+	/// Returns the path of the lock file used for cross-process locking.
+	pub fn lock_path(&self) -> PathBuf {
+		self.base_directory.join(LOCK_FILE)
+	}
+
+	/// Refreshes the lock file, renewing the lease for [`StaleLockPolicy::LeaseExpired`].
+	///
+	/// Does nothing if cross-process locking isn't enabled.
+	///
+	/// # Errors
+	///
+	/// Returns an [`FsErrorType::Locked`] error if the lock is no longer held by this backend, or an error
+	/// if the lock file could not be written.
+	pub async fn refresh_lock(&self) -> Result<(), FsError> {
+		if self.lock.is_some() {
+			lock::refresh(&self.lock_path(), &self.lock_token).await?;
+		}
+
+		Ok(())
+	}
+
+	/// Reads the [`Envelope`] an entry was written with, without decoding the entry.
+	///
+	/// Returns [`None`] for plain entries, which weren't written with an envelope.
+	///
+	/// # Errors
+	///
+	/// Returns an error if the entry could not be read, or if it's envelope is invalid.
+	pub async fn read_envelope(&self, table: &str, id: &str) -> Result<Option<Envelope>, FsError> {
+		let path = self
+			.find_entry(table, id)
+			.await?
+			.unwrap_or_else(|| self.entry_path(table, id, self.extension()));
+		let data = io::read(&path).await?;
+
+		Ok(Envelope::decode(&data)?.0)
+	}
+
+	/// Returns the path of the directory a table is stored in.
+	fn table_path(&self, table: &str) -> PathBuf {
+		self.base_directory()
+			.join(self.paths.table_directory(table))
+	}
+
+	/// The extension entries are written with, followed by the aliases they may have been written with.
+	fn extensions(&self) -> impl Iterator<Item = &str> {
+		once(self.extension()).chain(self.paths.extension_aliases().iter().map(String::as_str))
+	}
+
+	/// Returns the path of the file an entry is stored in with the given extension.
+	///
+	/// Keys too long to be used as a file name are hashed, see [`FsBackend`] for details.
+	fn entry_path(&self, table: &str, id: &str, extension: &str) -> PathBuf {
+		let mut path = self.table_path(table);
+		path.push(util::file_name(extension, &self.paths.file_stem(id)));
+		path
+	}
+
+	/// Finds the file an entry is stored in, whichever of the [`Self::extensions`] it was stored with.
+	async fn find_entry(&self, table: &str, id: &str) -> Result<Option<PathBuf>, FsError> {
+		for extension in self.extensions() {
+			let path = self.entry_path(table, id, extension);
+			match io::metadata(&path).await {
+				Err(e) if e.kind() == ErrorKind::NotFound => {}
+				Err(e) => return Err(e.into()),
+				Ok(_) if self.holds_key(&path, id, extension).await? => return Ok(Some(path)),
+				Ok(_) => {}
+			}
+		}
+
+		Ok(None)
+	}
+
+	/// Checks that the file at the path holds the entry for the key, rather than another key that
+	/// hashed to the same file name.
+	///
+	/// Files for keys that aren't hashed always hold their key, as do hashed files whose `.key` file is missing.
+	async fn holds_key(&self, path: &Path, id: &str, extension: &str) -> Result<bool, FsError> {
+		if !util::is_long_key(extension, &self.paths.file_stem(id)) {
+			return Ok(true);
+		}
+
+		match io::read_to_string(&util::key_path(path)).await {
+			Err(e) if e.kind() == ErrorKind::NotFound => Ok(true),
+			Err(e) => Err(e.into()),
+			Ok(stored) => Ok(stored == id),
+		}
+	}
+
+	async fn write_entry(&self, table: &str, id: &str, serialized: Vec<u8>) -> Result<(), FsError> {
+		let operation = Operation::Write {
+			table: Cow::Borrowed(table),
+			id: Cow::Borrowed(id),
+			data: Cow::Owned(serialized),
+		};
+
+		self.journaled(operation).await
+	}
+
+	/// Logs the operation if the write-ahead log is enabled, then applies it.
+	async fn journaled(&self, operation: Operation<'_>) -> Result<(), FsError> {
+		let Some(wal) = &self.wal else {
+			return self.apply(&operation).await;
+		};
+
+		let seq = wal.append(&operation).await?;
+		let applied = self.apply(&operation).await;
+
+		// failed operations are committed too, they were reported to the caller so they shouldn't be replayed.
+		wal.commit(seq).await?;
+
+		applied
+	}
+
+	async fn apply(&self, operation: &Operation<'_>) -> Result<(), FsError> {
+		match operation {
+			Operation::Write { table, id, data } => self.apply_write(table, id, data).await,
+			Operation::Delete { table, id } => {
+				for extension in self.extensions() {
+					self.remove_entry(table, id, extension).await?;
+				}
+
+				Ok(())
+			}
+		}
+	}
+
+	/// Replays the operations left in the write-ahead log by a crash, then empties it.
+	async fn recover(&self, wal: &Wal) -> Result<(), FsError> {
+		for operation in wal.recover().await? {
+			// the table was deleted after the operation was logged, so there's nothing to replay into.
+			if self.has_table(operation.table()).await? {
+				self.apply(&operation).await?;
+			}
+		}
+
+		wal.reset().await
+	}
+
+	async fn apply_write(&self, table: &str, id: &str, serialized: &[u8]) -> Result<(), FsError> {
+		let path = self.entry_path(table, id, self.extension());
+
+		if !self.holds_key(&path, id, self.extension()).await? {
+			return Err(FsError {
+				source: None,
+				kind: FsErrorType::KeyCollision(path),
+			});
+		}
+
+		if util::is_long_key(self.extension(), &self.paths.file_stem(id)) {
+			util::write_atomic(&util::key_path(&path), id.as_bytes(), self.fsync).await?;
+		}
+
+		util::write_atomic(&path, serialized, self.fsync).await?;
+
+		// the entry is now stored with the main extension, so copies stored with an alias are stale.
+		for extension in self.paths.extension_aliases() {
+			self.remove_entry(table, id, extension).await?;
+		}
+
+		Ok(())
+	}
+
+	async fn remove_entry(&self, table: &str, id: &str, extension: &str) -> Result<(), FsError> {
+		let path = self.entry_path(table, id, extension);
+
+		// the file belongs to another key, so there's nothing stored for this one.
+		if !self.holds_key(&path, id, extension).await? {
+			return Ok(());
+		}
+
+		util::remove_file(&path).await?;
+
+		if util::is_long_key(extension, &self.paths.file_stem(id)) {
+			util::remove_file(&util::key_path(&path)).await?;
+		}
+
+		Ok(())
+	}
 }
 
 impl<T: Transcoder> Backend for FsBackend<T> {
@@ -89,14 +425,19 @@ impl<T: Transcoder> Backend for FsBackend<T> {
 	fn init(&self) -> InitFuture<'_, Self::Error> {
 		async move {
 			let path = self.base_directory();
-			let exists = match fs::read_dir(path).await {
-				Ok(_) => true,
-				Err(e) if e.kind() == ErrorKind::NotFound => false,
-				Err(e) => return Err(e.into()),
-			};
+			if !io::dir_exists(path).await? {
+				io::create_dir_all(path).await?;
+			}
+
+			if let Some(policy) = self.lock {
+				let path = self.lock_path();
+				lock::acquire(&path, &self.lock_token, policy).await?;
+				self.held_lock
+					.get_or_init(|| LockGuard::new(path, &self.lock_token));
+			}
 
-			if !exists {
-				fs::create_dir_all(path).await?;
+			if let Some(wal) = &self.wal {
+				self.recover(wal).await?;
 			}
 
 			Ok(())
@@ -104,32 +445,103 @@ impl<T: Transcoder> Backend for FsBackend<T> {
 		.boxed()
 	}
 
+	fn is_self_describing(&self) -> bool {
+		self.transcoder.is_self_describing()
+	}
+
+	fn entry_size<S: Entry>(&self, _: &str, _: &str, entry: &S) -> Option<usize> {
+		self.transcoder
+			.serialize_value(entry)
+			.ok()
+			.map(|serialized| serialized.len())
+	}
+
 	fn has_table<'a>(&'a self, table: &'a str) -> HasTableFuture<'a, Self::Error> {
-		let path = self.base_directory().join(table);
-		fs::read_dir(path)
-			.map(|res| match res {
-				Err(e) if e.kind() == ErrorKind::NotFound => Ok(false),
-				Err(e) => Err(e.into()),
-				Ok(_) => Ok(true),
-			})
-			.boxed()
+		async move { Ok(io::dir_exists(&self.table_path(table)).await?) }.boxed()
+	}
+
+	fn tables<I>(&self) -> TablesFuture<'_, I, Self::Error>
+	where
+		I: FromIterator<String>,
+	{
+		async move {
+			let mut output = Vec::new();
+			let mut directories = vec![PathBuf::new()];
+
+			// nested layouts store tables below other directories, so every directory is walked.
+			while let Some(directory) = directories.pop() {
+				let entries = io::read_dir(&self.base_directory().join(&directory)).await?;
+				let mut has_entries = false;
+				let mut has_directories = false;
+
+				for entry in entries {
+					// the lock files, the write-ahead log, and temporary files are all hidden.
+					if entry.file_name.to_string_lossy().starts_with('.') {
+						continue;
+					}
+
+					if entry.file_type.is_dir() {
+						has_directories = true;
+						directories.push(directory.join(entry.file_name));
+					} else {
+						has_entries = true;
+					}
+				}
+
+				// directories that only hold other directories are the parents of nested tables.
+				if has_entries || !has_directories {
+					output.extend(self.paths.table(&directory));
+				}
+			}
+
+			Ok(output.into_iter().collect())
+		}
+		.boxed()
 	}
 
 	fn create_table<'a>(&'a self, table: &'a str) -> CreateTableFuture<'a, Self::Error> {
-		let path = self.base_directory().join(table);
-		fs::create_dir(path)
-			.map(|res| res.map_err(Into::into))
-			.boxed()
+		async move {
+			let path = self.table_path(table);
+
+			// nested layouts may store tables below directories that don't exist yet.
+			if let Some(parent) = path.parent() {
+				io::create_dir_all(parent).await?;
+			}
+
+			io::create_dir(&path).await?;
+
+			Ok(())
+		}
+		.boxed()
 	}
 
 	fn delete_table<'a>(&'a self, table: &'a str) -> DeleteTableFuture<'a, Self::Error> {
-		let path = self.base_directory().join(table);
-		fs::remove_dir(path)
-			.map(|res| match res {
+		async move {
+			match io::remove_dir_all(&self.table_path(table)).await {
 				Err(e) if e.kind() != ErrorKind::NotFound => Err(e.into()),
 				_ => Ok(()),
-			})
-			.boxed()
+			}
+		}
+		.boxed()
+	}
+
+	fn rename_table<'a>(
+		&'a self,
+		table: &'a str,
+		new: &'a str,
+	) -> RenameTableFuture<'a, Self::Error> {
+		async move {
+			let path = self.table_path(new);
+
+			if let Some(parent) = path.parent() {
+				io::create_dir_all(parent).await?;
+			}
+
+			io::rename(&self.table_path(table), &path).await?;
+
+			Ok(())
+		}
+		.boxed()
 	}
 
 	fn get_keys<'a, I>(&'a self, table: &'a str) -> GetKeysFuture<'a, I, Self::Error>
@@ -137,16 +549,30 @@ impl<T: Transcoder> Backend for FsBackend<T> {
 		I: FromIterator<String>,
 	{
 		async move {
-			let path = self.base_directory().join(table);
-			let mut read_dir = fs::read_dir(&path).await?;
+			let path = self.table_path(table);
+			let extensions = self.extensions().collect::<Vec<_>>();
+			let entries = io::read_dir(&path).await?;
 
 			let mut output = Vec::new();
-			while let Some(entry) = read_dir.next_entry().await? {
-				if entry.file_type().await?.is_dir() {
+			for entry in entries {
+				let file_name = entry.file_name;
+
+				if entry.file_type.is_dir()
+					|| util::is_key_file(&extensions, &file_name)
+					|| util::is_temp_file(&file_name)
+				{
 					continue;
 				}
 
-				output.push(util::resolve_key(self.extension(), &entry.file_name()));
+				if util::is_hashed(&file_name) {
+					let key_path = util::key_path(&entry.path);
+					output.push(io::read_to_string(&key_path).await.map_err(Into::into));
+				} else {
+					output.push(
+						util::resolve_key(&extensions, &file_name)
+							.map(|file_stem| self.paths.key(&file_stem)),
+					);
+				}
 			}
 
 			output.into_iter().collect::<Result<I, Self::Error>>()
@@ -159,30 +585,33 @@ impl<T: Transcoder> Backend for FsBackend<T> {
 		D: Entry,
 	{
 		async move {
-			let filename = [id, self.extension()].join(".");
-			let mut path = self.base_directory().to_path_buf();
-			path.extend(&[table, filename.as_str()]);
-			let file: std::fs::File = match fs::File::open(&path).await {
-				Err(e) if e.kind() == ErrorKind::NotFound => return Ok(None),
-				Err(e) => return Err(e.into()),
-				Ok(v) => v.into_std().await,
-			};
+			for extension in self.extensions() {
+				let path = self.entry_path(table, id, extension);
+				let data = match io::read(&path).await {
+					Err(e) if e.kind() == ErrorKind::NotFound => continue,
+					Err(e) => return Err(e.into()),
+					Ok(v) => v,
+				};
+
+				if !self.holds_key(&path, id, extension).await? {
+					continue;
+				}
+
+				return self
+					.transcoder()
+					.deserialize_entry(id, &*data)
+					.map(Some)
+					.map_err(|e| FsError::corrupt(table, id, e));
+			}
 
-			Ok(Some(self.transcoder().deserialize_data(file)?))
+			Ok(None)
 		}
 		.boxed()
 	}
 
 	fn has<'a>(&'a self, table: &'a str, id: &'a str) -> HasFuture<'a, Self::Error> {
-		let filename = [id, self.extension()].join(".");
-		let mut path = self.base_directory().to_path_buf();
-		path.extend(&[table, filename.as_str()]);
-		fs::metadata(path)
-			.map(|res| match res {
-				Err(e) if e.kind() == ErrorKind::NotFound => Ok(false),
-				Err(e) => Err(e.into()),
-				Ok(_) => Ok(true),
-			})
+		self.find_entry(table, id)
+			.map(|res| res.map(|path| path.is_some()))
 			.boxed()
 	}
 
@@ -195,18 +624,12 @@ impl<T: Transcoder> Backend for FsBackend<T> {
 	where
 		S: Entry,
 	{
-		let filename = [id, self.extension()].join(".");
-		let mut path = self.base_directory().to_path_buf();
-		path.extend(&[table, filename.as_str()]);
-
-		let serialized = match self.transcoder().serialize_value(value) {
+		let serialized = match self.transcoder().serialize_entry(id, value) {
 			Ok(v) => v,
 			Err(e) => return err(e).boxed(),
 		};
 
-		fs::write(path, serialized)
-			.map(|res| res.map_err(Into::into))
-			.boxed()
+		self.write_entry(table, id, serialized).boxed()
 	}
 
 	fn update<'a, S>(
@@ -218,33 +641,25 @@ impl<T: Transcoder> Backend for FsBackend<T> {
 	where
 		S: Entry,
 	{
-		let serialized = match self.transcoder().serialize_value(value) {
+		let serialized = match self.transcoder().serialize_entry(id, value) {
 			Ok(v) => v,
 			Err(e) => return err(e).boxed(),
 		};
 
-		let filepath = [id, self.extension()].join(".");
-		let mut path = self.base_directory().to_path_buf();
-		path.extend(&[table, filepath.as_str()]);
-
-		fs::write(path, serialized)
-			.map(|res| res.map_err(Into::into))
-			.boxed()
+		self.write_entry(table, id, serialized).boxed()
 	}
 
 	fn delete<'a>(&'a self, table: &'a str, id: &'a str) -> DeleteFuture<'a, Self::Error> {
-		let filename = [id, self.extension()].join(".");
-		let mut path = self.base_directory().to_path_buf();
-		path.extend(&[table, filename.as_str()]);
-		fs::remove_file(path)
-			.map(|res| match res {
-				Err(e) if e.kind() != ErrorKind::NotFound => Err(e.into()),
-				_ => Ok(()),
-			})
-			.boxed()
+		self.journaled(Operation::Delete {
+			table: Cow::Borrowed(table),
+			id: Cow::Borrowed(id),
+		})
+		.boxed()
 	}
 }
 
+impl<T: Transcoder> TransactionalBackend for FsBackend<T> {}
+
 /// The transcoder trait for transforming data for the [`FsBackend`].
 #[cfg(feature = "fs")]
 pub trait Transcoder: Send + Sync {
@@ -261,12 +676,51 @@ pub trait Transcoder: Send + Sync {
 	///
 	/// Any errors from the transcoder should use [`FsError::serde`] to return properly.
 	fn deserialize_data<T: Entry, R: Read>(&self, rdr: R) -> Result<T, FsError>;
+
+	/// Serializes the entry stored under the given key, for transcoders that bind their output to it,
+	/// such as the `EncryptedTranscoder`. This is what the [`FsBackend`] calls.
+	///
+	/// By default this calls [`Self::serialize_value`], transcoders wrapping another transcoder
+	/// should pass the key on.
+	///
+	/// # Errors
+	///
+	/// Any errors from the transcoder should use [`FsError::serde`] to return properly.
+	fn serialize_entry<T: Entry>(&self, key: &str, value: &T) -> Result<Vec<u8>, FsError> {
+		let _ = key;
+
+		self.serialize_value(value)
+	}
+
+	/// Deserializes the entry stored under the given key, written by [`Self::serialize_entry`].
+	///
+	/// By default this calls [`Self::deserialize_data`].
+	///
+	/// # Errors
+	///
+	/// Any errors from the transcoder should use [`FsError::serde`] to return properly.
+	fn deserialize_entry<T: Entry, R: Read>(&self, key: &str, rdr: R) -> Result<T, FsError> {
+		let _ = key;
+
+		self.deserialize_data(rdr)
+	}
+
+	/// Whether the format can be read without knowing the entry type, see [`Backend::is_self_describing`].
+	///
+	/// The default impl returns `true`, transcoders wrapping another transcoder should ask it.
+	fn is_self_describing(&self) -> bool {
+		true
+	}
 }
 
 /// The transcoders for the [`FsBackend`].
 pub mod transcoders {
 	#[cfg(feature = "binary")]
 	pub use super::binary::{BinaryFormat, BinaryTranscoder};
+	#[cfg(any(feature = "gzip", feature = "zstd"))]
+	pub use super::compressed::{CompressedTranscoder, Compression};
+	#[cfg(feature = "encryption")]
+	pub use super::encrypted::{Cipher, EncryptedTranscoder};
 	#[cfg(feature = "json")]
 	pub use super::json::JsonTranscoder;
 	#[cfg(feature = "toml")]
@@ -293,13 +747,142 @@ pub mod transcoders {
 }
 
 mod util {
-	use std::{ffi::OsStr, path::Path};
+	use std::{
+		ffi::OsStr,
+		io::ErrorKind,
+		path::{Path, PathBuf},
+		process,
+		sync::atomic::{AtomicU64, Ordering},
+	};
+
+	use super::{io, FsError, FsErrorType};
+
+	/// The longest file name most filesystems allow, in bytes.
+	const MAX_FILE_NAME: usize = 255;
+
+	/// The prefix of file names for keys that are stored hashed.
+	const HASHED_PREFIX: &str = "__long_";
+
+	const KEY_EXTENSION: &str = "key";
+
+	/// The prefix of the temporary files entries are written to before they're renamed into place.
+	const TEMP_PREFIX: &str = ".__tmp_";
+
+	/// Distinguishes temporary files written at the same time by the same process.
+	static TEMP_COUNTER: AtomicU64 = AtomicU64::new(0);
+
+	/// Checks if a key has to be hashed to be used as a file name.
+	///
+	/// Keys that start with [`HASHED_PREFIX`] are always hashed, so they can't be mistaken for a hashed key.
+	pub fn is_long_key(extension: &str, id: &str) -> bool {
+		id.len() + extension.len() + 1 > MAX_FILE_NAME || id.starts_with(HASHED_PREFIX)
+	}
+
+	pub fn file_name(extension: &str, id: &str) -> String {
+		if is_long_key(extension, id) {
+			format!("{HASHED_PREFIX}{:032x}.{extension}", hash(id))
+		} else {
+			[id, extension].join(".")
+		}
+	}
+
+	/// Checks if the file holds an entry with a hashed key.
+	pub fn is_hashed(file_name: &OsStr) -> bool {
+		file_name.to_string_lossy().starts_with(HASHED_PREFIX)
+	}
+
+	/// Checks if the file holds the key of an entry with a hashed key, rather than an entry.
+	pub fn is_key_file(extensions: &[&str], file_name: &OsStr) -> bool {
+		let file_name = file_name.to_string_lossy();
+
+		file_name.starts_with(HASHED_PREFIX)
+			&& extensions
+				.iter()
+				.any(|extension| file_name.ends_with(&format!(".{extension}.{KEY_EXTENSION}")))
+	}
+
+	/// Returns the path of the file holding the key for an entry with a hashed key.
+	pub fn key_path(entry_path: &Path) -> PathBuf {
+		let mut path = entry_path.as_os_str().to_owned();
+		path.push(".");
+		path.push(KEY_EXTENSION);
+		path.into()
+	}
+
+	/// Checks if the file is a temporary file, left behind by a write that hasn't finished or never will.
+	pub fn is_temp_file(file_name: &OsStr) -> bool {
+		file_name.to_string_lossy().starts_with(TEMP_PREFIX)
+	}
+
+	/// Writes the contents to a temporary file next to the path, then renames it over the path,
+	/// so readers only ever see the old or the new contents.
+	///
+	/// If `sync` is set, the file and it's directory are flushed to disk before returning.
+	pub async fn write_atomic(path: &Path, contents: &[u8], sync: bool) -> Result<(), FsError> {
+		let (Some(directory), Some(file_name)) = (path.parent(), path.file_name()) else {
+			return Err(FsError {
+				source: None,
+				kind: FsErrorType::InvalidFile(path.to_path_buf()),
+			});
+		};
+
+		let mut temp_name = OsStr::new(TEMP_PREFIX).to_owned();
+		temp_name.push(format!(
+			"{}_{}_",
+			process::id(),
+			TEMP_COUNTER.fetch_add(1, Ordering::Relaxed)
+		));
+		temp_name.push(file_name);
+		let temp_path = directory.join(temp_name);
+
+		let written = async {
+			io::write(&temp_path, contents).await?;
+
+			if sync {
+				io::sync(&temp_path).await?;
+			}
+
+			io::rename(&temp_path, path).await
+		}
+		.await;
+
+		if let Err(e) = written {
+			let _removed = io::remove_file(&temp_path).await;
+			return Err(e.into());
+		}
+
+		// the rename is only durable once the directory is, which can only be flushed this way on unix.
+		#[cfg(unix)]
+		if sync {
+			io::sync(directory).await?;
+		}
+
+		Ok(())
+	}
+
+	pub async fn remove_file(path: &Path) -> Result<(), FsError> {
+		match io::remove_file(path).await {
+			Err(e) if e.kind() != ErrorKind::NotFound => Err(e.into()),
+			_ => Ok(()),
+		}
+	}
+
+	/// A 128 bit FNV-1a hash, stable across platforms and releases.
+	fn hash(id: &str) -> u128 {
+		id.bytes().fold(
+			0x6c62_272e_07bb_0142_62b8_2175_6295_c58d_u128,
+			|hash, byte| {
+				(hash ^ u128::from(byte)).wrapping_mul(0x0000_0000_0100_0000_0000_0000_0000_013b)
+			},
+		)
+	}
 
-	use super::{FsError, FsErrorType};
-	pub fn resolve_key(extension: &str, file_name: &OsStr) -> Result<String, FsError> {
+	pub fn resolve_key(extensions: &[&str], file_name: &OsStr) -> Result<String, FsError> {
 		let path_ref: &Path = file_name.as_ref();
 
-		if path_ref.extension().map_or(false, |path| path == extension) {
+		if path_ref.extension().map_or(false, |path| {
+			extensions.iter().any(|extension| path == *extension)
+		}) {
 			path_ref
 				.file_stem()
 				.ok_or(FsError {
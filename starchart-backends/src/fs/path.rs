@@ -0,0 +1,179 @@
+use std::{
+	fmt::Debug,
+	path::{Component, Path, PathBuf},
+};
+
+/// Decides where the [`FsBackend`] stores tables and entries.
+///
+/// Every method has a default matching the default layout, where each table is a directory in the
+/// base directory, and each entry is a file named after it's key. Overriding them allows adopting
+/// directories created by other tools without renaming everything on disk.
+///
+/// [`FsBackend`]: super::FsBackend
+pub trait PathStrategy: Debug + Send + Sync {
+	/// The directory a table's entries are stored in, relative to the base directory.
+	fn table_directory(&self, table: &str) -> PathBuf {
+		PathBuf::from(table)
+	}
+
+	/// Recovers the name of a table from it's directory, relative to the base directory.
+	///
+	/// This should undo [`Self::table_directory`], returning [`None`] for directories that aren't tables.
+	/// The default treats every directory directly in the base directory as a table.
+	fn table(&self, directory: &Path) -> Option<String> {
+		single_component(directory).map(ToOwned::to_owned)
+	}
+
+	/// The name of the file an entry is stored in, without it's extension.
+	fn file_stem(&self, id: &str) -> String {
+		id.to_owned()
+	}
+
+	/// Recovers the key of an entry from the name of it's file, without it's extension.
+	///
+	/// This should undo [`Self::file_stem`], for strategies where that's possible.
+	fn key(&self, file_stem: &str) -> String {
+		file_stem.to_owned()
+	}
+
+	/// Other extensions entries may have been stored with, such as `yml` for `yaml` entries.
+	///
+	/// Entries stored with an alias are read, and are replaced by a file with the extension
+	/// of the [`FsBackend`] when they're next written.
+	///
+	/// [`FsBackend`]: super::FsBackend
+	fn extension_aliases(&self) -> &[String] {
+		&[]
+	}
+}
+
+/// The casing applied to table and file names by a [`Layout`].
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum Casing {
+	/// Names are used as-is. This is the default.
+	#[default]
+	Preserve,
+	/// Names are lowercased.
+	Lower,
+	/// Names are uppercased.
+	Upper,
+}
+
+impl Casing {
+	fn apply(self, name: &str) -> String {
+		match self {
+			Self::Preserve => name.to_owned(),
+			Self::Lower => name.to_lowercase(),
+			Self::Upper => name.to_uppercase(),
+		}
+	}
+}
+
+/// A configurable [`PathStrategy`], covering the most common layouts.
+///
+/// The default [`Layout`] is the default layout of the [`FsBackend`].
+///
+/// [`FsBackend`]: super::FsBackend
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+#[must_use = "a layout does nothing until it's given to an FsBackend"]
+pub struct Layout {
+	root: PathBuf,
+	casing: Casing,
+	separator: Option<char>,
+	aliases: Vec<String>,
+}
+
+impl Layout {
+	/// Creates a new [`Layout`], with the default layout.
+	pub fn new() -> Self {
+		Self::default()
+	}
+
+	/// Stores every table in the given directory, relative to the base directory.
+	pub fn with_root(mut self, root: impl Into<PathBuf>) -> Self {
+		self.root = root.into();
+
+		self
+	}
+
+	/// Sets the [`Casing`] of table and file names.
+	///
+	/// Keys are read back as the file names are on disk, so changing the casing of keys
+	/// that aren't already in that casing changes the keys themselves.
+	pub const fn with_casing(mut self, casing: Casing) -> Self {
+		self.casing = casing;
+
+		self
+	}
+
+	/// Splits table names on the separator into nested directories, so the table `guilds.settings`
+	/// is stored in `guilds/settings`.
+	///
+	/// Nesting on the [`namespace::SEPARATOR`] stores each namespace of a [`Starchart`] in it's own directory.
+	///
+	/// [`namespace::SEPARATOR`]: starchart::namespace::SEPARATOR
+	/// [`Starchart`]: starchart::Starchart
+	pub const fn with_nesting(mut self, separator: char) -> Self {
+		self.separator = Some(separator);
+
+		self
+	}
+
+	/// Adds another extension entries may have been stored with, see [`PathStrategy::extension_aliases`].
+	pub fn with_extension_alias(mut self, extension: impl Into<String>) -> Self {
+		self.aliases.push(extension.into());
+
+		self
+	}
+}
+
+impl PathStrategy for Layout {
+	fn table_directory(&self, table: &str) -> PathBuf {
+		let table = self.casing.apply(table);
+
+		match self.separator {
+			Some(separator) => self.root.join(table.split(separator).collect::<PathBuf>()),
+			None => self.root.join(table),
+		}
+	}
+
+	/// Tables are named after their directories as they are on disk, so tables with names that aren't
+	/// in the layout's casing are listed in it's casing.
+	fn table(&self, directory: &Path) -> Option<String> {
+		let directory = directory.strip_prefix(&self.root).ok()?;
+
+		match self.separator {
+			Some(separator) => {
+				let mut names = Vec::new();
+				for component in directory.components() {
+					match component {
+						Component::Normal(name) => names.push(name.to_str()?),
+						_ => return None,
+					}
+				}
+
+				(!names.is_empty()).then(|| names.join(&separator.to_string()))
+			}
+			None => single_component(directory).map(ToOwned::to_owned),
+		}
+	}
+
+	fn file_stem(&self, id: &str) -> String {
+		self.casing.apply(id)
+	}
+
+	fn extension_aliases(&self) -> &[String] {
+		&self.aliases
+	}
+}
+
+/// Returns the name of a path made of a single directory.
+fn single_component(path: &Path) -> Option<&str> {
+	let mut components = path.components();
+
+	match (components.next(), components.next()) {
+		(Some(Component::Normal(name)), None) => name.to_str(),
+		_ => None,
+	}
+}
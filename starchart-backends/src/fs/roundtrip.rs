@@ -0,0 +1,245 @@
+//! Property tests checking a [`Transcoder`] reads back every entry it writes, enabled with the
+//! `test-util` feature.
+//!
+//! Formats differ in what they can represent, TOML for example has no null and can't store integers
+//! above [`i64::MAX`], so [`check`] generates entries covering the types entries commonly use, along
+//! with strings and map keys that formats are known to mangle.
+//!
+//! ```rust,ignore
+//! #[test]
+//! fn round_trips() {
+//!     starchart_backends::fs::roundtrip::check(&MyTranscoder::new()).unwrap();
+//! }
+//! ```
+
+use std::{collections::BTreeMap, io::Cursor};
+
+use proptest::{
+	collection::{btree_map, vec},
+	num::f64,
+	option,
+	prelude::*,
+	test_runner::{Config, TestCaseError, TestError, TestRunner},
+};
+use serde::{Deserialize, Serialize};
+use starchart::Entry;
+
+use super::Transcoder;
+
+/// Strings that formats are known to mangle, such as ones that look like other types in YAML or
+/// need quoting in TOML.
+pub const PATHOLOGICAL_STRINGS: &[&str] = &[
+	"",
+	" ",
+	"null",
+	"~",
+	"true",
+	"no",
+	"1",
+	"-0",
+	"1e3",
+	"0x1f",
+	".inf",
+	"a.b",
+	"a/b",
+	"..",
+	"[table]",
+	"#comment",
+	"key = value",
+	"\"quoted\"",
+	"'single'",
+	"\\",
+	"line\nbreak",
+	"\ttab",
+	"\r\n",
+	"\u{0}",
+	"\u{7f}",
+	"\u{feff}bom",
+	"ключ",
+	"🦀",
+	"e\u{301}",
+];
+
+/// An entry using the types entries commonly have, generated by [`entries`].
+///
+/// The tables are the last fields, as TOML requires.
+#[derive(Debug, Default, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RoundTripEntry {
+	/// An unsigned integer.
+	pub id: u32,
+	/// A signed integer.
+	pub count: i64,
+	/// A finite float.
+	pub ratio: f64,
+	/// A boolean.
+	pub enabled: bool,
+	/// A string.
+	pub name: String,
+	/// A unit enum.
+	pub kind: RoundTripKind,
+	/// An optional string.
+	pub nickname: Option<String>,
+	/// A list of strings.
+	pub tags: Vec<String>,
+	/// A list of integers.
+	pub scores: Vec<i64>,
+	/// A map with arbitrary keys.
+	pub attributes: BTreeMap<String, String>,
+	/// A nested entry.
+	pub nested: RoundTripNested,
+}
+
+/// The unit enum of a [`RoundTripEntry`].
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[allow(missing_docs)]
+pub enum RoundTripKind {
+	#[default]
+	Empty,
+	Small,
+	Large,
+}
+
+/// The nested entry of a [`RoundTripEntry`].
+#[derive(Debug, Default, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RoundTripNested {
+	/// A small integer.
+	pub level: u8,
+	/// A string.
+	pub label: String,
+	/// An optional integer.
+	pub limit: Option<i32>,
+}
+
+/// Generates strings, mixing [`PATHOLOGICAL_STRINGS`] with arbitrary ones.
+pub fn strings() -> impl Strategy<Value = String> {
+	prop_oneof![
+		prop::sample::select(PATHOLOGICAL_STRINGS).prop_map(ToOwned::to_owned),
+		any::<String>(),
+	]
+}
+
+/// Generates arbitrary [`RoundTripEntry`]s.
+pub fn entries() -> impl Strategy<Value = RoundTripEntry> {
+	let nested =
+		(any::<u8>(), strings(), option::of(any::<i32>())).prop_map(|(level, label, limit)| {
+			RoundTripNested {
+				level,
+				label,
+				limit,
+			}
+		});
+
+	let kind = prop_oneof![
+		Just(RoundTripKind::Empty),
+		Just(RoundTripKind::Small),
+		Just(RoundTripKind::Large),
+	];
+
+	(
+		(
+			any::<u32>(),
+			any::<i64>(),
+			f64::NORMAL | f64::SUBNORMAL | f64::ZERO,
+			any::<bool>(),
+			strings(),
+			kind,
+		),
+		(
+			option::of(strings()),
+			vec(strings(), 0..8),
+			vec(any::<i64>(), 0..8),
+			btree_map(strings(), strings(), 0..8),
+			nested,
+		),
+	)
+		.prop_map(
+			|(
+				(id, count, ratio, enabled, name, kind),
+				(nickname, tags, scores, attributes, nested),
+			)| {
+				RoundTripEntry {
+					id,
+					count,
+					ratio,
+					enabled,
+					name,
+					kind,
+					nickname,
+					tags,
+					scores,
+					attributes,
+					nested,
+				}
+			},
+		)
+}
+
+/// Serializes the entry with the [`Transcoder`] and deserializes it again, failing if it can't
+/// or the entry changed.
+///
+/// # Errors
+///
+/// Returns a [`TestCaseError`] describing the transcoder error or the changed entry.
+pub fn round_trip<T, E>(transcoder: &T, entry: &E) -> Result<(), TestCaseError>
+where
+	T: Transcoder,
+	E: Entry + PartialEq,
+{
+	let bytes = transcoder
+		.serialize_value(entry)
+		.map_err(|e| TestCaseError::fail(format!("failed to serialize {entry:?}: {e}")))?;
+
+	let read = transcoder
+		.deserialize_data::<E, _>(Cursor::new(&bytes))
+		.map_err(|e| {
+			let written = String::from_utf8_lossy(&bytes);
+
+			TestCaseError::fail(format!(
+				"failed to deserialize {entry:?} from {written:?}: {e}"
+			))
+		})?;
+
+	if read == *entry {
+		Ok(())
+	} else {
+		Err(TestCaseError::fail(format!(
+			"{entry:?} was read back as {read:?}"
+		)))
+	}
+}
+
+/// Checks that the [`Transcoder`] round-trips arbitrary [`RoundTripEntry`]s, using the default
+/// proptest [`Config`] without persisting failures.
+///
+/// # Errors
+///
+/// Returns the smallest entry that failed to round-trip.
+pub fn check<T: Transcoder>(transcoder: &T) -> Result<(), Box<TestError<RoundTripEntry>>> {
+	let config = Config {
+		failure_persistence: None,
+		..Config::default()
+	};
+
+	check_with(transcoder, config, entries())
+}
+
+/// Checks that the [`Transcoder`] round-trips the entries generated by the strategy, for formats
+/// that can only represent some of the types a [`RoundTripEntry`] has.
+///
+/// # Errors
+///
+/// Returns the smallest entry that failed to round-trip.
+pub fn check_with<T, S>(
+	transcoder: &T,
+	config: Config,
+	strategy: S,
+) -> Result<(), Box<TestError<S::Value>>>
+where
+	T: Transcoder,
+	S: Strategy,
+	S::Value: Entry + PartialEq,
+{
+	TestRunner::new(config)
+		.run(&strategy, |entry| round_trip(transcoder, &entry))
+		.map_err(Box::new)
+}
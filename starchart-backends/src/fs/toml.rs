@@ -69,6 +69,33 @@ mod tests {
 
 	assert_impl_all!(TomlTranscoder: Clone, Copy, Debug, Send, Sync);
 
+	#[test]
+	#[cfg(feature = "test-util")]
+	fn arbitrary_entries() {
+		for transcoder in [TomlTranscoder::standard(), TomlTranscoder::pretty()] {
+			crate::fs::roundtrip::check(&transcoder).unwrap();
+		}
+	}
+
+	#[test]
+	#[cfg(feature = "test-util")]
+	fn limitations() {
+		use serde::{Deserialize, Serialize};
+
+		use crate::fs::roundtrip::round_trip;
+
+		#[derive(Debug, Default, Clone, PartialEq, Serialize, Deserialize)]
+		struct Large {
+			value: u64,
+		}
+
+		let transcoder = TomlTranscoder::default();
+
+		assert!(round_trip(&transcoder, &Large { value: 1 }).is_ok());
+		assert!(round_trip(&transcoder, &Large { value: u64::MAX }).is_err());
+		assert!(round_trip(&transcoder, &"not a table".to_owned()).is_err());
+	}
+
 	#[tokio::test]
 	async fn init() -> Result<(), FsError> {
 		let _lock = TEST_GUARD.lock().await;
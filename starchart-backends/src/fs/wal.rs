@@ -0,0 +1,393 @@
+use std::{
+	borrow::Cow,
+	convert::{TryFrom, TryInto},
+	fs::{File, OpenOptions},
+	io::{self as std_io, ErrorKind, Write},
+	path::{Path, PathBuf},
+	sync::{Arc, Mutex, MutexGuard, PoisonError},
+};
+
+use super::{io, FsError};
+
+/// The name of the write-ahead log created in the base directory of an [`FsBackend`] with
+/// [`FsBackend::with_write_ahead_log`] enabled.
+///
+/// [`FsBackend`]: super::FsBackend
+/// [`FsBackend::with_write_ahead_log`]: super::FsBackend::with_write_ahead_log
+pub const WAL_FILE: &str = ".starchart.wal";
+
+const WRITE: u8 = 1;
+const DELETE: u8 = 2;
+const COMMIT: u8 = 3;
+
+/// The size of the length and checksum preceding every record.
+const HEADER_LEN: usize = 8;
+
+/// A mutation recorded in the log before it's applied.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(super) enum Operation<'a> {
+	Write {
+		table: Cow<'a, str>,
+		id: Cow<'a, str>,
+		data: Cow<'a, [u8]>,
+	},
+	Delete {
+		table: Cow<'a, str>,
+		id: Cow<'a, str>,
+	},
+}
+
+impl Operation<'_> {
+	pub(super) fn table(&self) -> &str {
+		match self {
+			Self::Write { table, .. } | Self::Delete { table, .. } => table,
+		}
+	}
+}
+
+/// An append-only log of the mutations made by an [`FsBackend`].
+///
+/// Every mutation is appended and flushed to disk before it's applied, and marked as committed
+/// once it's done. Once no mutations are in flight the log is truncated and flushed, so it only ever
+/// holds the mutations of concurrent writes. The log is written on a blocking thread, see [`io::unblock`].
+///
+/// Commit markers aren't flushed, so a crash can lose them and replay a mutation that was already
+/// applied. This is only safe because every mutation replaces or removes whole files, replaying
+/// it leaves the same files behind.
+///
+/// [`FsBackend`]: super::FsBackend
+#[derive(Debug)]
+pub(super) struct Wal {
+	path: PathBuf,
+	state: Mutex<State>,
+}
+
+#[derive(Debug, Default)]
+struct State {
+	file: Option<File>,
+	next: u64,
+	pending: usize,
+}
+
+impl Wal {
+	pub(super) fn new(path: PathBuf) -> Self {
+		Self {
+			path,
+			state: Mutex::default(),
+		}
+	}
+
+	pub(super) fn path(&self) -> &Path {
+		&self.path
+	}
+
+	/// Reads the mutations that were logged but never committed, in the order they were logged.
+	///
+	/// A record cut short by a crash mid-append is ignored, along with anything after it, as the
+	/// mutation it describes was never applied. Sequence numbers continue after the highest one
+	/// found, so they're never reused for a mutation still in the log.
+	pub(super) async fn recover(&self) -> Result<Vec<Operation<'static>>, FsError> {
+		let data = match io::read(&self.path).await {
+			Ok(data) => data,
+			Err(e) if e.kind() == ErrorKind::NotFound => return Ok(Vec::new()),
+			Err(e) => return Err(e.into()),
+		};
+
+		let mut logged = Vec::new();
+		let mut committed = Vec::new();
+		let mut rest = data.as_slice();
+		while let Some((body, next)) = split_record(rest) {
+			rest = next;
+
+			let Some((kind, seq, mut fields)) = parse_body(body) else {
+				break;
+			};
+
+			let mut state = self.state();
+			state.next = state.next.max(seq.saturating_add(1));
+			drop(state);
+
+			let operation = match kind {
+				COMMIT => {
+					committed.push(seq);
+					continue;
+				}
+				WRITE => fields.next().zip(fields.next()).zip(fields.next()).map(
+					|((table, id), data)| Operation::Write {
+						table: Cow::Owned(String::from_utf8_lossy(table).into_owned()),
+						id: Cow::Owned(String::from_utf8_lossy(id).into_owned()),
+						data: Cow::Owned(data.to_vec()),
+					},
+				),
+				DELETE => fields
+					.next()
+					.zip(fields.next())
+					.map(|(table, id)| Operation::Delete {
+						table: Cow::Owned(String::from_utf8_lossy(table).into_owned()),
+						id: Cow::Owned(String::from_utf8_lossy(id).into_owned()),
+					}),
+				_ => None,
+			};
+
+			let Some(operation) = operation else {
+				break;
+			};
+
+			logged.push((seq, operation));
+		}
+
+		Ok(logged
+			.into_iter()
+			.filter(|(seq, _)| !committed.contains(seq))
+			.map(|(_, operation)| operation)
+			.collect())
+	}
+
+	/// Empties the log, after it's mutations have been recovered.
+	pub(super) async fn reset(&self) -> Result<(), FsError> {
+		{
+			let mut state = self.state();
+
+			state.file = None;
+			state.pending = 0;
+		}
+
+		match io::remove_file(&self.path).await {
+			Err(e) if e.kind() != ErrorKind::NotFound => Err(e.into()),
+			_ => Ok(()),
+		}
+	}
+
+	/// Logs a mutation before it's applied, returning the sequence number to commit it with.
+	pub(super) async fn append(
+		self: &Arc<Self>,
+		operation: &Operation<'_>,
+	) -> Result<u64, FsError> {
+		let mut fields = Vec::new();
+		let kind = match operation {
+			Operation::Write { table, id, data } => {
+				push_field(&mut fields, table.as_bytes())?;
+				push_field(&mut fields, id.as_bytes())?;
+				push_field(&mut fields, data)?;
+
+				WRITE
+			}
+			Operation::Delete { table, id } => {
+				push_field(&mut fields, table.as_bytes())?;
+				push_field(&mut fields, id.as_bytes())?;
+
+				DELETE
+			}
+		};
+
+		let wal = Arc::clone(self);
+		Ok(io::unblock(move || wal.append_blocking(kind, &fields)).await?)
+	}
+
+	/// Marks a logged mutation as applied, truncating the log if no other mutations are in flight.
+	pub(super) async fn commit(self: &Arc<Self>, seq: u64) -> Result<(), FsError> {
+		let wal = Arc::clone(self);
+		Ok(io::unblock(move || wal.commit_blocking(seq)).await?)
+	}
+
+	fn append_blocking(&self, kind: u8, fields: &[u8]) -> std_io::Result<u64> {
+		let mut state = self.state();
+		let seq = state.next;
+
+		let mut body = Vec::with_capacity(9 + fields.len());
+		push_header(&mut body, kind, seq);
+		body.extend_from_slice(fields);
+
+		let file = self.file(&mut state)?;
+		file.write_all(&encode_record(&body)?)?;
+		file.sync_data()?;
+
+		state.next += 1;
+		state.pending += 1;
+		drop(state);
+
+		Ok(seq)
+	}
+
+	fn commit_blocking(&self, seq: u64) -> std_io::Result<()> {
+		let mut state = self.state();
+		state.pending = state.pending.saturating_sub(1);
+
+		let truncate = state.pending == 0;
+		let file = self.file(&mut state)?;
+
+		if truncate {
+			// flushed, so a crash never replays a log that was already emptied.
+			file.set_len(0)?;
+			file.sync_data()?;
+		} else {
+			let mut body = Vec::new();
+			push_header(&mut body, COMMIT, seq);
+			file.write_all(&encode_record(&body)?)?;
+		}
+
+		drop(state);
+
+		Ok(())
+	}
+
+	fn state(&self) -> MutexGuard<'_, State> {
+		// the state is only ever updated after the log is written, so it's consistent even if a
+		// panic poisoned the mutex.
+		self.state.lock().unwrap_or_else(PoisonError::into_inner)
+	}
+
+	fn file<'a>(&self, state: &'a mut State) -> std_io::Result<&'a mut File> {
+		if state.file.is_none() {
+			state.file = Some(
+				OpenOptions::new()
+					.create(true)
+					.append(true)
+					.open(&self.path)?,
+			);
+		}
+
+		Ok(state.file.as_mut().expect("the log file was just opened"))
+	}
+}
+
+fn push_header(body: &mut Vec<u8>, kind: u8, seq: u64) {
+	body.push(kind);
+	body.extend_from_slice(&seq.to_le_bytes());
+}
+
+fn push_field(body: &mut Vec<u8>, field: &[u8]) -> std_io::Result<()> {
+	body.extend_from_slice(&length(field.len())?.to_le_bytes());
+	body.extend_from_slice(field);
+
+	Ok(())
+}
+
+fn encode_record(body: &[u8]) -> std_io::Result<Vec<u8>> {
+	let mut record = Vec::with_capacity(HEADER_LEN + body.len());
+	record.extend_from_slice(&length(body.len())?.to_le_bytes());
+	record.extend_from_slice(&checksum(body).to_le_bytes());
+	record.extend_from_slice(body);
+
+	Ok(record)
+}
+
+/// Splits the next record's body off the log, if it was written completely.
+fn split_record(data: &[u8]) -> Option<(&[u8], &[u8])> {
+	let len = read_u32(data.get(..4)?)? as usize;
+	let expected = read_u32(data.get(4..HEADER_LEN)?)?;
+	let body = data.get(HEADER_LEN..HEADER_LEN.checked_add(len)?)?;
+
+	(checksum(body) == expected).then(|| (body, &data[HEADER_LEN + len..]))
+}
+
+fn parse_body(body: &[u8]) -> Option<(u8, u64, impl Iterator<Item = &[u8]>)> {
+	let (&kind, rest) = body.split_first()?;
+	let seq = u64::from_le_bytes(rest.get(..8)?.try_into().ok()?);
+
+	let mut rest = &rest[8..];
+	let fields = std::iter::from_fn(move || {
+		let len = read_u32(rest.get(..4)?)? as usize;
+		let field = rest.get(4..4 + len)?;
+		rest = &rest[4 + len..];
+
+		Some(field)
+	});
+
+	Some((kind, seq, fields))
+}
+
+fn read_u32(bytes: &[u8]) -> Option<u32> {
+	bytes.try_into().ok().map(u32::from_le_bytes)
+}
+
+fn length(len: usize) -> std_io::Result<u32> {
+	u32::try_from(len).map_err(|e| std_io::Error::new(ErrorKind::InvalidInput, e))
+}
+
+/// FNV-1a, which is plenty to detect a record cut short by a crash.
+fn checksum(data: &[u8]) -> u32 {
+	data.iter().fold(0x811c_9dc5, |hash, byte| {
+		(hash ^ u32::from(*byte)).wrapping_mul(0x0100_0193)
+	})
+}
+
+#[cfg(all(test, feature = "json", not(miri)))]
+mod tests {
+	use std::{borrow::Cow, io::Write, path::Path};
+
+	use starchart::backend::Backend;
+
+	use super::{Operation, WAL_FILE};
+	use crate::{
+		fs::{transcoders::JsonTranscoder, FsBackend, FsError, Transcoder},
+		testing::{TestPath, TestSettings, TEST_GUARD},
+	};
+
+	#[tokio::test]
+	async fn recovery() -> Result<(), FsError> {
+		let _lock = TEST_GUARD.lock().await;
+		let path = TestPath::new("recovery", "wal");
+		let backend = FsBackend::new(JsonTranscoder::default(), "json".to_owned(), &path)?
+			.with_write_ahead_log(true);
+
+		backend.init().await?;
+		backend.create_table("table").await?;
+		backend
+			.create("table", "1", &TestSettings::default())
+			.await?;
+
+		let log = AsRef::<Path>::as_ref(&path).join(WAL_FILE);
+		assert_eq!(std::fs::metadata(&log)?.len(), 0);
+
+		// log mutations without applying them, as if the process crashed right after logging them.
+		let settings = TestSettings {
+			id: 2,
+			..TestSettings::default()
+		};
+		let data = JsonTranscoder::default().serialize_value(&settings)?;
+		let wal = backend.wal.as_ref().unwrap();
+		wal.append(&Operation::Write {
+			table: Cow::Borrowed("table"),
+			id: Cow::Borrowed("2"),
+			data: Cow::Owned(data),
+		})
+		.await?;
+		wal.append(&Operation::Delete {
+			table: Cow::Borrowed("table"),
+			id: Cow::Borrowed("1"),
+		})
+		.await?;
+
+		// a record cut short mid-append is ignored.
+		std::fs::OpenOptions::new()
+			.append(true)
+			.open(&log)?
+			.write_all(&[42, 0, 0, 0, 1, 2])?;
+
+		let recovered = FsBackend::new(JsonTranscoder::default(), "json".to_owned(), &path)?
+			.with_write_ahead_log(true);
+		recovered.init().await?;
+
+		assert_eq!(
+			recovered.get::<TestSettings>("table", "2").await?,
+			Some(settings)
+		);
+		assert!(!recovered.has("table", "1").await?);
+		assert!(!log.exists());
+
+		// sequence numbers continue after the recovered ones, and the truncated log stays empty.
+		let wal = recovered.wal.as_ref().unwrap();
+		let seq = wal
+			.append(&Operation::Delete {
+				table: Cow::Borrowed("table"),
+				id: Cow::Borrowed("2"),
+			})
+			.await?;
+		assert_eq!(seq, 3);
+		wal.commit(seq).await?;
+		assert_eq!(std::fs::metadata(&log)?.len(), 0);
+
+		Ok(())
+	}
+}
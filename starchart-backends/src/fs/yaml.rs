@@ -42,6 +42,12 @@ mod tests {
 
 	assert_impl_all!(YamlTranscoder: Clone, Copy, Debug, Send, Sync);
 
+	#[test]
+	#[cfg(feature = "test-util")]
+	fn arbitrary_entries() {
+		crate::fs::roundtrip::check(&YamlTranscoder::new()).unwrap();
+	}
+
 	#[tokio::test]
 	async fn init() -> Result<(), FsError> {
 		let _lock = TEST_GUARD.lock().await;
@@ -0,0 +1,405 @@
+//! A backend that forwards every call over gRPC to a [`GrpcService`], so processes written in any
+//! language can share one chart.
+//!
+//! The protocol is defined in `proto/starchart.proto`, with entries sent as JSON encoded bytes.
+//! Every [`Backend`] method maps to the RPC of the same name, with [`get_all_stream`] mapping to the
+//! server streaming `GetAll` RPC, so large tables aren't collected into a single message.
+//!
+//! Errors from the service's backend are reported with the `INTERNAL` status code and the error message,
+//! and entries that aren't valid JSON with the `INVALID_ARGUMENT` status code.
+//!
+//! The service is enabled with the `grpc-server` feature.
+//!
+//! [`GrpcService`]: self::service::GrpcService
+//! [`get_all_stream`]: Backend::get_all_stream
+
+#[cfg(feature = "grpc-server")]
+pub mod service;
+
+use std::{
+	error::Error,
+	fmt::{Display, Formatter, Result as FmtResult},
+	iter::FromIterator,
+};
+
+use futures_util::{future::ready, FutureExt, StreamExt, TryFutureExt, TryStreamExt};
+use starchart::{
+	backend::{
+		futures::{
+			CreateFuture, CreateTableFuture, DeleteFuture, DeleteTableFuture, GetAllStream,
+			GetFuture, GetKeysFuture, HasFuture, HasTableFuture, HealthCheckFuture, TablesFuture,
+			UpdateFuture,
+		},
+		Backend, CorruptEntryError,
+	},
+	Entry,
+};
+use tonic::{
+	transport::{Channel, Endpoint, Error as TransportError},
+	Code, Status,
+};
+
+use self::proto::{
+	chart_client::ChartClient, EntryRequest, HealthCheckRequest, TableRequest, TablesRequest,
+};
+
+/// The messages and client generated from the protocol definition.
+///
+/// The service trait and server are also generated when the `grpc-server` feature is enabled.
+#[allow(missing_docs, clippy::all, clippy::pedantic, clippy::nursery)]
+pub mod proto {
+	tonic::include_proto!("starchart");
+}
+
+/// An error returned from the [`GrpcBackend`].
+#[derive(Debug)]
+pub struct GrpcError {
+	source: Option<Box<dyn Error + Send + Sync>>,
+	kind: GrpcErrorType,
+}
+
+impl GrpcError {
+	/// Immutable reference to the type of error that occurred.
+	#[must_use = "retrieving the type has no effect if left unused"]
+	pub const fn kind(&self) -> &GrpcErrorType {
+		&self.kind
+	}
+
+	/// Consume the error, returning the source error if there is any.
+	#[must_use = "consuming the error and retrieving the source has no effect if left unused"]
+	pub fn into_source(self) -> Option<Box<dyn Error + Send + Sync>> {
+		self.source
+	}
+
+	/// Consume the error, returning the owned error type and the source error.
+	#[must_use = "consuming the error into it's parts has no effect if left unused"]
+	pub fn into_parts(self) -> (GrpcErrorType, Option<Box<dyn Error + Send + Sync>>) {
+		(self.kind, self.source)
+	}
+
+	fn serialization(err: serde_json::Error) -> Self {
+		Self {
+			source: Some(Box::new(err)),
+			kind: GrpcErrorType::Serialization,
+		}
+	}
+
+	fn deserialization(table: &str, key: &str, err: serde_json::Error) -> Self {
+		Self {
+			source: Some(Box::new(CorruptEntryError::new(table, key, err))),
+			kind: GrpcErrorType::Deserialization,
+		}
+	}
+}
+
+impl Display for GrpcError {
+	fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+		match &self.kind {
+			GrpcErrorType::InvalidUrl => f.write_str("the service url was invalid"),
+			GrpcErrorType::Status { code, message } => {
+				f.write_str("the service responded with ")?;
+				Display::fmt(code, f)?;
+
+				if !message.is_empty() {
+					f.write_str(": ")?;
+					f.write_str(message)?;
+				}
+
+				Ok(())
+			}
+			GrpcErrorType::Serialization => f.write_str("a serialization error occurred"),
+			GrpcErrorType::Deserialization => f.write_str("a deserialization error occurred"),
+		}
+	}
+}
+
+impl Error for GrpcError {
+	fn source(&self) -> Option<&(dyn Error + 'static)> {
+		self.source
+			.as_ref()
+			.map(|source| &**source as &(dyn Error + 'static))
+	}
+}
+
+impl From<TransportError> for GrpcError {
+	fn from(err: TransportError) -> Self {
+		Self {
+			source: Some(Box::new(err)),
+			kind: GrpcErrorType::InvalidUrl,
+		}
+	}
+}
+
+impl From<Status> for GrpcError {
+	fn from(status: Status) -> Self {
+		Self {
+			kind: GrpcErrorType::Status {
+				code: status.code(),
+				message: status.message().to_owned(),
+			},
+			source: Some(Box::new(status)),
+		}
+	}
+}
+
+impl From<GrpcError> for starchart::Error {
+	fn from(e: GrpcError) -> Self {
+		Self::backend(Some(Box::new(e)))
+	}
+}
+
+/// The type of [`GrpcError`] that occurred.
+#[derive(Debug)]
+#[non_exhaustive]
+pub enum GrpcErrorType {
+	/// The url of the service was invalid.
+	InvalidUrl,
+	/// The call failed, either because the service couldn't be reached or because it's backend failed.
+	Status {
+		/// The status code of the call.
+		code: Code,
+		/// The message of the status, which is the error message for backend failures.
+		message: String,
+	},
+	/// A serialization error occurred.
+	Serialization,
+	/// An entry couldn't be deserialized, the source is a [`CorruptEntryError`].
+	Deserialization,
+}
+
+/// A backend that sends every call to a [`GrpcService`].
+///
+/// Calls are multiplexed over a single HTTP/2 connection, which is made on the first call
+/// and must be used within a [`tokio`] runtime.
+///
+/// [`GrpcService`]: self::service::GrpcService
+/// [`tokio`]: https://docs.rs/tokio
+#[derive(Debug, Clone)]
+#[must_use = "a grpc backend does nothing on it's own"]
+pub struct GrpcBackend {
+	client: ChartClient<Channel>,
+}
+
+impl GrpcBackend {
+	/// Creates a new [`GrpcBackend`] for the service at the given url, such as `http://localhost:50051`.
+	///
+	/// The connection isn't made until the first call, so this must be called within a [`tokio`] runtime.
+	///
+	/// # Errors
+	///
+	/// Returns an error if the url isn't valid.
+	///
+	/// [`tokio`]: https://docs.rs/tokio
+	pub fn new(url: &str) -> Result<Self, GrpcError> {
+		let endpoint = Endpoint::from_shared(url.to_owned())?;
+
+		Ok(Self::from_channel(endpoint.connect_lazy()))
+	}
+
+	/// Creates a new [`GrpcBackend`] from an already configured [`Channel`], such as one using TLS.
+	pub fn from_channel(channel: Channel) -> Self {
+		Self {
+			client: ChartClient::new(channel),
+		}
+	}
+
+	fn table_request(table: &str) -> TableRequest {
+		TableRequest {
+			table: table.to_owned(),
+		}
+	}
+
+	fn entry_request(table: &str, id: &str) -> EntryRequest {
+		EntryRequest {
+			table: table.to_owned(),
+			key: id.to_owned(),
+		}
+	}
+
+	fn entry<S: Entry>(table: &str, id: &str, value: &S) -> Result<proto::Entry, GrpcError> {
+		Ok(proto::Entry {
+			table: table.to_owned(),
+			key: id.to_owned(),
+			value: serde_json::to_vec(value).map_err(GrpcError::serialization)?,
+		})
+	}
+}
+
+impl Backend for GrpcBackend {
+	type Error = GrpcError;
+
+	fn health_check(&self) -> HealthCheckFuture<'_, Self::Error> {
+		let mut client = self.client.clone();
+
+		async move {
+			client.health_check(HealthCheckRequest {}).await?;
+
+			Ok(())
+		}
+		.boxed()
+	}
+
+	fn has_table<'a>(&'a self, table: &'a str) -> HasTableFuture<'a, Self::Error> {
+		let mut client = self.client.clone();
+
+		async move {
+			let reply = client.has_table(Self::table_request(table)).await?;
+
+			Ok(reply.into_inner().exists)
+		}
+		.boxed()
+	}
+
+	fn tables<I>(&self) -> TablesFuture<'_, I, Self::Error>
+	where
+		I: FromIterator<String>,
+	{
+		let mut client = self.client.clone();
+
+		async move {
+			let reply = client.get_tables(TablesRequest {}).await?;
+
+			Ok(reply.into_inner().tables.into_iter().collect())
+		}
+		.boxed()
+	}
+
+	fn create_table<'a>(&'a self, table: &'a str) -> CreateTableFuture<'a, Self::Error> {
+		let mut client = self.client.clone();
+
+		async move {
+			client.create_table(Self::table_request(table)).await?;
+
+			Ok(())
+		}
+		.boxed()
+	}
+
+	fn delete_table<'a>(&'a self, table: &'a str) -> DeleteTableFuture<'a, Self::Error> {
+		let mut client = self.client.clone();
+
+		async move {
+			client.delete_table(Self::table_request(table)).await?;
+
+			Ok(())
+		}
+		.boxed()
+	}
+
+	fn get_all_stream<'a, D>(&'a self, table: &'a str) -> GetAllStream<'a, D, Self::Error>
+	where
+		D: Entry + 'a,
+	{
+		let mut client = self.client.clone();
+
+		async move { client.get_all(Self::table_request(table)).await }
+			.map_ok(move |reply| {
+				reply
+					.into_inner()
+					.map_err(GrpcError::from)
+					.and_then(move |entry| {
+						ready(
+							serde_json::from_slice(&entry.value)
+								.map_err(|e| GrpcError::deserialization(table, &entry.key, e))
+								.map(|value| (entry.key, value)),
+						)
+					})
+			})
+			.map_err(GrpcError::from)
+			.try_flatten_stream()
+			.boxed()
+	}
+
+	fn get_keys<'a, I>(&'a self, table: &'a str) -> GetKeysFuture<'a, I, Self::Error>
+	where
+		I: FromIterator<String>,
+	{
+		let mut client = self.client.clone();
+
+		async move {
+			let reply = client.get_keys(Self::table_request(table)).await?;
+
+			Ok(reply.into_inner().keys.into_iter().collect())
+		}
+		.boxed()
+	}
+
+	fn get<'a, D>(&'a self, table: &'a str, id: &'a str) -> GetFuture<'a, D, Self::Error>
+	where
+		D: Entry,
+	{
+		let mut client = self.client.clone();
+
+		async move {
+			let reply = client.get(Self::entry_request(table, id)).await?;
+
+			reply
+				.into_inner()
+				.value
+				.map(|value| serde_json::from_slice(&value))
+				.transpose()
+				.map_err(|e| GrpcError::deserialization(table, id, e))
+		}
+		.boxed()
+	}
+
+	fn has<'a>(&'a self, table: &'a str, id: &'a str) -> HasFuture<'a, Self::Error> {
+		let mut client = self.client.clone();
+
+		async move {
+			let reply = client.has(Self::entry_request(table, id)).await?;
+
+			Ok(reply.into_inner().exists)
+		}
+		.boxed()
+	}
+
+	fn create<'a, S>(
+		&'a self,
+		table: &'a str,
+		id: &'a str,
+		value: &'a S,
+	) -> CreateFuture<'a, Self::Error>
+	where
+		S: Entry,
+	{
+		let mut client = self.client.clone();
+
+		async move {
+			client.create(Self::entry(table, id, value)?).await?;
+
+			Ok(())
+		}
+		.boxed()
+	}
+
+	fn update<'a, S>(
+		&'a self,
+		table: &'a str,
+		id: &'a str,
+		value: &'a S,
+	) -> UpdateFuture<'a, Self::Error>
+	where
+		S: Entry,
+	{
+		let mut client = self.client.clone();
+
+		async move {
+			client.update(Self::entry(table, id, value)?).await?;
+
+			Ok(())
+		}
+		.boxed()
+	}
+
+	fn delete<'a>(&'a self, table: &'a str, id: &'a str) -> DeleteFuture<'a, Self::Error> {
+		let mut client = self.client.clone();
+
+		async move {
+			client.delete(Self::entry_request(table, id)).await?;
+
+			Ok(())
+		}
+		.boxed()
+	}
+}
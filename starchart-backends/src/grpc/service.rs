@@ -0,0 +1,398 @@
+//! The service side of the [gRPC] protocol, exposing a [`Starchart`] to other processes.
+//!
+//! [gRPC]: super
+
+use std::pin::Pin;
+
+use futures_util::{stream, Stream, StreamExt};
+use serde_json::Value;
+use starchart::{backend::Backend, Starchart};
+use tokio::sync::mpsc;
+use tonic::{Request, Response, Status};
+
+use super::proto::{
+	chart_server::{Chart, ChartServer},
+	EmptyReply, Entry, EntryRequest, ExistsReply, GetReply, HealthCheckRequest, KeysReply,
+	TableRequest, TablesReply, TablesRequest,
+};
+
+/// How many entries of a `GetAll` call are read ahead of the client.
+const GET_ALL_BUFFER: usize = 16;
+
+/// A gRPC service that exposes a [`Starchart`] to [`GrpcBackend`]s, or any other client of the protocol.
+///
+/// Entries are handled as untyped JSON values, so the chart doesn't need to know the entry types
+/// of it's clients. The chart isn't shut down when the service stops.
+///
+/// [`GrpcBackend`]: super::GrpcBackend
+#[derive(Debug)]
+#[must_use = "a grpc service does nothing until it's served"]
+pub struct GrpcService<B: Backend> {
+	chart: Starchart<B>,
+}
+
+impl<B: Backend + 'static> GrpcService<B> {
+	/// Creates a new [`GrpcService`] for the given [`Starchart`].
+	pub const fn new(chart: Starchart<B>) -> Self {
+		Self { chart }
+	}
+
+	/// Returns a reference to the underlying [`Starchart`].
+	#[must_use]
+	pub const fn chart(&self) -> &Starchart<B> {
+		&self.chart
+	}
+
+	/// Wraps the service in a [`ChartServer`], to be added to a [`tonic`] server.
+	///
+	/// [`tonic`]: https://docs.rs/tonic
+	#[must_use]
+	pub fn into_server(self) -> ChartServer<Self> {
+		ChartServer::new(self)
+	}
+}
+
+impl<B: Backend> Clone for GrpcService<B> {
+	fn clone(&self) -> Self {
+		Self {
+			chart: self.chart.clone(),
+		}
+	}
+}
+
+#[tonic::async_trait]
+impl<B: Backend + 'static> Chart for GrpcService<B> {
+	type GetAllStream = Pin<Box<dyn Stream<Item = Result<Entry, Status>> + Send>>;
+
+	async fn has_table(
+		&self,
+		request: Request<TableRequest>,
+	) -> Result<Response<ExistsReply>, Status> {
+		let exists = self
+			.chart
+			.has_table(&request.into_inner().table)
+			.await
+			.map_err(internal)?;
+
+		Ok(Response::new(ExistsReply { exists }))
+	}
+
+	async fn health_check(
+		&self,
+		_: Request<HealthCheckRequest>,
+	) -> Result<Response<EmptyReply>, Status> {
+		self.chart
+			.health()
+			.await
+			.map_err(|e| Status::unavailable(e.to_string()))?;
+
+		Ok(Response::new(EmptyReply {}))
+	}
+
+	async fn get_tables(&self, _: Request<TablesRequest>) -> Result<Response<TablesReply>, Status> {
+		// the backend's tables are listed, so the client's chart filters them like any other backend's.
+		let tables = (*self.chart).tables().await.map_err(internal)?;
+
+		Ok(Response::new(TablesReply { tables }))
+	}
+
+	async fn create_table(
+		&self,
+		request: Request<TableRequest>,
+	) -> Result<Response<EmptyReply>, Status> {
+		self.chart
+			.create_table(&request.into_inner().table)
+			.await
+			.map_err(internal)?;
+
+		Ok(Response::new(EmptyReply {}))
+	}
+
+	async fn delete_table(
+		&self,
+		request: Request<TableRequest>,
+	) -> Result<Response<EmptyReply>, Status> {
+		self.chart
+			.delete_table(&request.into_inner().table)
+			.await
+			.map_err(internal)?;
+
+		Ok(Response::new(EmptyReply {}))
+	}
+
+	async fn get_keys(
+		&self,
+		request: Request<TableRequest>,
+	) -> Result<Response<KeysReply>, Status> {
+		let keys = self
+			.chart
+			.get_keys(&request.into_inner().table)
+			.await
+			.map_err(internal)?;
+
+		Ok(Response::new(KeysReply { keys }))
+	}
+
+	async fn get_all(
+		&self,
+		request: Request<TableRequest>,
+	) -> Result<Response<Self::GetAllStream>, Status> {
+		let chart = self.chart.clone();
+		let table = request.into_inner().table;
+		let (sender, receiver) = mpsc::channel(GET_ALL_BUFFER);
+
+		// the backend's stream borrows the chart, so it's driven on it's own task.
+		tokio::spawn(async move {
+			let mut entries = chart.get_all_stream::<Value>(&table);
+
+			while let Some(entry) = entries.next().await {
+				let entry = entry.map_err(internal).and_then(|(key, value)| {
+					Ok(Entry {
+						table: String::new(),
+						key,
+						value: serialize(&value)?,
+					})
+				});
+
+				// the client stopped listening, so there's no need to read the rest of the table.
+				if sender.send(entry).await.is_err() {
+					break;
+				}
+			}
+		});
+
+		let entries = stream::unfold(receiver, |mut receiver| async move {
+			receiver.recv().await.map(|entry| (entry, receiver))
+		});
+
+		Ok(Response::new(entries.boxed()))
+	}
+
+	async fn get(&self, request: Request<EntryRequest>) -> Result<Response<GetReply>, Status> {
+		let EntryRequest { table, key } = request.into_inner();
+
+		let value = self
+			.chart
+			.get::<Value>(&table, &key)
+			.await
+			.map_err(internal)?
+			.map(|value| serialize(&value))
+			.transpose()?;
+
+		Ok(Response::new(GetReply { value }))
+	}
+
+	async fn has(&self, request: Request<EntryRequest>) -> Result<Response<ExistsReply>, Status> {
+		let EntryRequest { table, key } = request.into_inner();
+
+		let exists = self.chart.has(&table, &key).await.map_err(internal)?;
+
+		Ok(Response::new(ExistsReply { exists }))
+	}
+
+	async fn create(&self, request: Request<Entry>) -> Result<Response<EmptyReply>, Status> {
+		let Entry { table, key, value } = request.into_inner();
+
+		self.chart
+			.create(&table, &key, &deserialize(&value)?)
+			.await
+			.map_err(internal)?;
+
+		Ok(Response::new(EmptyReply {}))
+	}
+
+	async fn update(&self, request: Request<Entry>) -> Result<Response<EmptyReply>, Status> {
+		let Entry { table, key, value } = request.into_inner();
+
+		self.chart
+			.update(&table, &key, &deserialize(&value)?)
+			.await
+			.map_err(internal)?;
+
+		Ok(Response::new(EmptyReply {}))
+	}
+
+	async fn delete(&self, request: Request<EntryRequest>) -> Result<Response<EmptyReply>, Status> {
+		let EntryRequest { table, key } = request.into_inner();
+
+		self.chart.delete(&table, &key).await.map_err(internal)?;
+
+		Ok(Response::new(EmptyReply {}))
+	}
+}
+
+fn internal<E: std::error::Error>(err: E) -> Status {
+	Status::internal(err.to_string())
+}
+
+fn serialize(value: &Value) -> Result<Vec<u8>, Status> {
+	serde_json::to_vec(value).map_err(internal)
+}
+
+fn deserialize(value: &[u8]) -> Result<Value, Status> {
+	serde_json::from_slice(value).map_err(|e| Status::invalid_argument(e.to_string()))
+}
+
+#[cfg(all(test, feature = "memory"))]
+mod tests {
+	use std::fmt::Debug;
+
+	use futures_util::TryStreamExt;
+	use starchart::{
+		backend::{testsuite, Backend},
+		Error, Starchart,
+	};
+	use static_assertions::assert_impl_all;
+	use tokio::net::TcpListener;
+	use tonic::{
+		transport::{server::TcpIncoming, Server},
+		Code,
+	};
+
+	use super::GrpcService;
+	use crate::{
+		grpc::{GrpcBackend, GrpcErrorType},
+		memory::MemoryBackend,
+		testing::TestSettings,
+	};
+
+	assert_impl_all!(GrpcBackend: Backend, Clone, Debug, Send, Sync);
+	assert_impl_all!(GrpcService<MemoryBackend>: Clone, Debug, Send, Sync);
+
+	async fn spawn() -> Result<GrpcBackend, Error> {
+		let listener = TcpListener::bind("127.0.0.1:0")
+			.await
+			.map_err(|e| Error::backend(Some(Box::new(e))))?;
+		let addr = listener
+			.local_addr()
+			.map_err(|e| Error::backend(Some(Box::new(e))))?;
+		let chart = Starchart::new(MemoryBackend::new()).await?;
+
+		tokio::spawn(
+			Server::builder()
+				.add_service(GrpcService::new(chart).into_server())
+				.serve_with_incoming(TcpIncoming::from(listener)),
+		);
+
+		Ok(GrpcBackend::new(&format!("http://{addr}"))?)
+	}
+
+	#[tokio::test]
+	async fn testsuite() -> Result<(), Error> {
+		let backend = spawn().await?;
+
+		Ok(testsuite::run(backend).await?)
+	}
+
+	#[tokio::test]
+	async fn health_check() -> Result<(), Error> {
+		let backend = spawn().await?;
+
+		backend.health_check().await?;
+
+		// nothing is listening once the listener is dropped.
+		let addr = TcpListener::bind("127.0.0.1:0")
+			.await
+			.and_then(|listener| listener.local_addr())
+			.map_err(|e| Error::backend(Some(Box::new(e))))?;
+		let unreachable = GrpcBackend::new(&format!("http://{addr}"))?;
+
+		assert!(unreachable.health_check().await.is_err());
+
+		Ok(())
+	}
+
+	#[tokio::test]
+	async fn table_methods() -> Result<(), Error> {
+		let backend = spawn().await?;
+
+		assert!(!backend.has_table("a table").await?);
+
+		backend.create_table("a table").await?;
+
+		assert!(backend.has_table("a table").await?);
+
+		backend.delete_table("a table").await?;
+
+		assert!(!backend.has_table("a table").await?);
+
+		backend.create_table("a table").await?;
+		backend.create_table("another table").await?;
+
+		let mut tables: Vec<String> = backend.tables().await?;
+		tables.sort();
+		assert_eq!(tables, ["a table", "another table"]);
+
+		Ok(())
+	}
+
+	#[tokio::test]
+	async fn entries() -> Result<(), Error> {
+		let backend = spawn().await?;
+
+		backend.create_table("table").await?;
+
+		let mut settings = TestSettings::default();
+		backend.create("table", "1", &settings).await?;
+		settings.id = 2;
+		settings.opt = None;
+		backend.create("table", "2", &settings).await?;
+
+		let mut keys: Vec<String> = backend.get_keys("table").await?;
+		keys.sort();
+		assert_eq!(keys, ["1", "2"]);
+
+		assert_eq!(
+			backend.get::<TestSettings>("table", "2").await?,
+			Some(settings.clone())
+		);
+		assert_eq!(backend.get::<TestSettings>("table", "3").await?, None);
+
+		settings.value = "updated".to_owned();
+		backend.update("table", "2", &settings).await?;
+
+		let mut all: Vec<(String, TestSettings)> =
+			backend.get_all_stream("table").try_collect().await?;
+		all.sort_by(|(a, _), (b, _)| a.cmp(b));
+		assert_eq!(
+			all,
+			[
+				("1".to_owned(), TestSettings::default()),
+				("2".to_owned(), settings)
+			]
+		);
+
+		backend.delete("table", "1").await?;
+		assert!(!backend.has("table", "1").await?);
+		assert!(backend.has("table", "2").await?);
+
+		let error = backend.get::<u32>("table", "2").await.unwrap_err();
+		assert!(matches!(error.kind(), GrpcErrorType::Deserialization));
+
+		Ok(())
+	}
+
+	#[tokio::test]
+	async fn unreachable() -> Result<(), Error> {
+		let listener = TcpListener::bind("127.0.0.1:0")
+			.await
+			.map_err(|e| Error::backend(Some(Box::new(e))))?;
+		let addr = listener
+			.local_addr()
+			.map_err(|e| Error::backend(Some(Box::new(e))))?;
+		drop(listener);
+
+		let backend = GrpcBackend::new(&format!("http://{addr}"))?;
+		let error = backend.has_table("table").await.unwrap_err();
+
+		assert!(matches!(
+			error.kind(),
+			GrpcErrorType::Status {
+				code: Code::Unavailable,
+				..
+			}
+		));
+
+		Ok(())
+	}
+}
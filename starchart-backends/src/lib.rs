@@ -17,9 +17,22 @@
 #![cfg_attr(not(test), warn(clippy::panic_in_result_fn))]
 //! All the basic backends for the starchart crate
 
+#[cfg(feature = "cache")]
+pub mod cached;
 #[cfg(feature = "fs")]
 pub mod fs;
+#[cfg(feature = "grpc")]
+pub mod grpc;
 #[cfg(feature = "memory")]
 pub mod memory;
+#[cfg(feature = "object_store")]
+pub mod object_store;
+#[cfg(feature = "postgres")]
+pub mod postgres;
+pub mod prelude;
+#[cfg(feature = "remote")]
+pub mod remote;
+#[cfg(feature = "sled")]
+pub mod sled;
 #[cfg(test)]
 pub(crate) mod testing;
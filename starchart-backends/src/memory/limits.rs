@@ -0,0 +1,393 @@
+use std::collections::{BTreeMap, HashMap, HashSet};
+
+use serde_value::Value;
+
+/// How a [`MemoryBackend`] makes room for a write that would exceed it's [`MemoryLimits`].
+///
+/// [`MemoryBackend`]: super::MemoryBackend
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum EvictionPolicy {
+	/// Evict the entries that were read or written the longest time ago.
+	#[default]
+	LeastRecentlyUsed,
+	/// Evict the entries that were read or written the fewest times, the least recently used first.
+	LeastFrequentlyUsed,
+	/// Never evict, failing the write with a [`MemoryErrorType::CapacityExceeded`] error instead.
+	///
+	/// [`MemoryErrorType::CapacityExceeded`]: super::MemoryErrorType::CapacityExceeded
+	Reject,
+}
+
+/// The most entries and bytes a [`MemoryBackend`] holds, see [`MemoryBackend::with_limits`].
+///
+/// Bytes are estimated from the stored values, counting the length of keys, strings and byte arrays,
+/// and the width of numbers, which is roughly the size of the entries in a compact binary format.
+///
+/// Table metadata and the tables starchart keeps alongside a table, such as it's secondary indexes
+/// and expiry times, are neither counted nor evicted. When an entry is evicted, it's rows in those
+/// tables are removed with it. Entries pinned with [`Starchart::pin`] are never evicted, if only
+/// pinned entries could make room for a write, it fails as with [`EvictionPolicy::Reject`].
+///
+/// [`Starchart::pin`]: starchart::Starchart::pin
+///
+/// [`MemoryBackend`]: super::MemoryBackend
+/// [`MemoryBackend::with_limits`]: super::MemoryBackend::with_limits
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+#[must_use]
+pub struct MemoryLimits {
+	max_entries: Option<usize>,
+	max_bytes: Option<usize>,
+	policy: EvictionPolicy,
+}
+
+impl MemoryLimits {
+	/// Creates new [`MemoryLimits`], without any limits.
+	pub const fn new() -> Self {
+		Self {
+			max_entries: None,
+			max_bytes: None,
+			policy: EvictionPolicy::LeastRecentlyUsed,
+		}
+	}
+
+	/// Sets the most entries the backend holds, across every table.
+	pub const fn with_max_entries(mut self, max_entries: usize) -> Self {
+		self.max_entries = Some(max_entries);
+
+		self
+	}
+
+	/// Sets the most bytes the backend holds, across every table.
+	pub const fn with_max_bytes(mut self, max_bytes: usize) -> Self {
+		self.max_bytes = Some(max_bytes);
+
+		self
+	}
+
+	/// Sets the [`EvictionPolicy`], defaults to [`EvictionPolicy::LeastRecentlyUsed`].
+	pub const fn with_policy(mut self, policy: EvictionPolicy) -> Self {
+		self.policy = policy;
+
+		self
+	}
+
+	/// The most entries the backend holds.
+	#[must_use]
+	pub const fn max_entries(self) -> Option<usize> {
+		self.max_entries
+	}
+
+	/// The most bytes the backend holds.
+	#[must_use]
+	pub const fn max_bytes(self) -> Option<usize> {
+		self.max_bytes
+	}
+
+	/// The [`EvictionPolicy`] used once a limit is reached.
+	#[must_use]
+	pub const fn policy(self) -> EvictionPolicy {
+		self.policy
+	}
+
+	fn fits(self, entries: usize, bytes: usize) -> bool {
+		self.max_entries.is_none_or(|max| entries <= max)
+			&& self.max_bytes.is_none_or(|max| bytes <= max)
+	}
+}
+
+type Id = (String, String);
+
+/// Tracks the size and use of every counted entry, to pick which ones to evict.
+#[derive(Debug, Clone, Default)]
+pub(super) struct Usage {
+	limits: MemoryLimits,
+	tick: u64,
+	bytes: usize,
+	evictions: u64,
+	entries: HashMap<Id, Tracked>,
+	/// The order entries are evicted in, the first entry being evicted first.
+	order: BTreeMap<(u64, u64), Id>,
+	/// The entries that are never evicted.
+	pinned: HashSet<Id>,
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Tracked {
+	size: usize,
+	uses: u64,
+	rank: (u64, u64),
+}
+
+impl Usage {
+	pub(super) fn new(limits: MemoryLimits) -> Self {
+		Self {
+			limits,
+			..Self::default()
+		}
+	}
+
+	pub(super) const fn limits(&self) -> MemoryLimits {
+		self.limits
+	}
+
+	pub(super) const fn bytes(&self) -> usize {
+		self.bytes
+	}
+
+	pub(super) const fn evictions(&self) -> u64 {
+		self.evictions
+	}
+
+	/// Records a write, returning the entries to evict to make room for it, or [`None`] if it
+	/// doesn't fit.
+	pub(super) fn write(&mut self, table: &str, key: &str, value: &Value) -> Option<Vec<Id>> {
+		if is_reserved(table, key) {
+			return Some(Vec::new());
+		}
+
+		let id = (table.to_owned(), key.to_owned());
+		let size = key.len() + size_of(value);
+		let (mut entries, mut bytes) = match self.entries.get(&id) {
+			Some(tracked) => (self.entries.len(), self.bytes - tracked.size + size),
+			None => (self.entries.len() + 1, self.bytes + size),
+		};
+
+		// victims are picked before anything changes, so a write that doesn't fit changes nothing.
+		let mut evicted = Vec::new();
+		for candidate in self.order.values() {
+			if self.limits.fits(entries, bytes) {
+				break;
+			}
+
+			if *candidate == id || self.pinned.contains(candidate) {
+				continue;
+			}
+
+			entries -= 1;
+			bytes -= self.entries[candidate].size;
+			evicted.push(candidate.clone());
+		}
+
+		if !self.limits.fits(entries, bytes)
+			|| (self.limits.policy == EvictionPolicy::Reject && !evicted.is_empty())
+		{
+			return None;
+		}
+
+		self.track(id, size);
+
+		for (table, key) in &evicted {
+			self.remove(table, key);
+			self.evictions += 1;
+		}
+
+		Some(evicted)
+	}
+
+	/// Marks an entry as pinned or unpinned, pinned entries are never evicted.
+	pub(super) fn set_pinned(&mut self, table: &str, key: &str, pinned: bool) {
+		let id = (table.to_owned(), key.to_owned());
+
+		if pinned {
+			self.pinned.insert(id);
+		} else {
+			self.pinned.remove(&id);
+		}
+	}
+
+	/// Records a read of an entry.
+	pub(super) fn read(&mut self, table: &str, key: &str) {
+		let id = (table.to_owned(), key.to_owned());
+		if let Some(size) = self.entries.get(&id).map(|tracked| tracked.size) {
+			self.track(id, size);
+		}
+	}
+
+	pub(super) fn remove(&mut self, table: &str, key: &str) {
+		if let Some(tracked) = self.entries.remove(&(table.to_owned(), key.to_owned())) {
+			self.order.remove(&tracked.rank);
+			self.bytes -= tracked.size;
+		}
+	}
+
+	/// Drops the pins of a deleted table.
+	pub(super) fn unpin_table(&mut self, table: &str) {
+		self.pinned.retain(|(name, _)| name != table);
+	}
+
+	pub(super) fn remove_table(&mut self, table: &str) {
+		let keys = self
+			.entries
+			.keys()
+			.filter(|(name, _)| name == table)
+			.map(|(_, key)| key.clone())
+			.collect::<Vec<_>>();
+
+		for key in keys {
+			self.remove(table, &key);
+		}
+	}
+
+	/// Moves the tracked entries of a renamed table, keeping how they were used.
+	///
+	/// Like the pins of a [`Starchart`], the pins of the old name are dropped.
+	///
+	/// [`Starchart`]: starchart::Starchart
+	pub(super) fn rename_table(&mut self, table: &str, new: &str) {
+		self.unpin_table(table);
+
+		if is_reserved(new, "") {
+			self.remove_table(table);
+			return;
+		}
+
+		let ids = self
+			.entries
+			.keys()
+			.filter(|(name, _)| name == table)
+			.cloned()
+			.collect::<Vec<_>>();
+
+		for id in ids {
+			if let Some(tracked) = self.entries.remove(&id) {
+				let id = (new.to_owned(), id.1);
+				self.order.insert(tracked.rank, id.clone());
+				self.entries.insert(id, tracked);
+			}
+		}
+	}
+
+	pub(super) fn clear(&mut self) {
+		*self = Self {
+			evictions: self.evictions,
+			pinned: std::mem::take(&mut self.pinned),
+			..Self::new(self.limits)
+		};
+	}
+
+	/// Marks an entry as the most recently used one, with the given size.
+	fn track(&mut self, id: Id, size: usize) {
+		self.tick += 1;
+
+		let uses = match self.entries.get(&id) {
+			Some(tracked) => {
+				self.order.remove(&tracked.rank);
+				self.bytes -= tracked.size;
+				tracked.uses + 1
+			}
+			None => 1,
+		};
+
+		let rank = match self.limits.policy {
+			EvictionPolicy::LeastFrequentlyUsed => (uses, self.tick),
+			_ => (0, self.tick),
+		};
+
+		self.bytes += size;
+		self.order.insert(rank, id.clone());
+		self.entries.insert(id, Tracked { size, uses, rank });
+	}
+}
+
+/// The tables starchart keeps alongside a table, which can be prefixed by a child chart or namespace.
+const RESERVED_TABLES: &[&str] = &[
+	INDEX_TABLE,
+	"__indexes__",
+	INDEX_KEYS_TABLE,
+	EXPIRY_TABLE,
+	VERSION_TABLE,
+	"__retained__",
+	"__sequence__",
+	"__journal__",
+	"__migrations__",
+];
+
+pub(super) const INDEX_TABLE: &str = "__index__";
+
+pub(super) const INDEX_KEYS_TABLE: &str = "__index_keys__";
+
+pub(super) const EXPIRY_TABLE: &str = "__expiry__";
+
+pub(super) const VERSION_TABLE: &str = "__version__";
+
+const METADATA_KEY: &str = "__metadata__";
+
+fn is_reserved(table: &str, key: &str) -> bool {
+	key == METADATA_KEY
+		|| RESERVED_TABLES
+			.iter()
+			.any(|reserved| table.contains(reserved))
+}
+
+fn size_of(value: &Value) -> usize {
+	match value {
+		Value::Unit | Value::Option(None) => 0,
+		Value::Bool(_) | Value::U8(_) | Value::I8(_) => 1,
+		Value::U16(_) | Value::I16(_) => 2,
+		Value::U32(_) | Value::I32(_) | Value::F32(_) | Value::Char(_) => 4,
+		Value::U64(_) | Value::I64(_) | Value::F64(_) => 8,
+		Value::String(string) => string.len(),
+		Value::Bytes(bytes) => bytes.len(),
+		Value::Option(Some(value)) | Value::Newtype(value) => size_of(value),
+		Value::Seq(values) => values.iter().map(size_of).sum(),
+		Value::Map(map) => map
+			.iter()
+			.map(|(key, value)| size_of(key) + size_of(value))
+			.sum(),
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use serde_value::Value;
+
+	use super::{EvictionPolicy, MemoryLimits, Usage};
+
+	fn id(key: &str) -> (String, String) {
+		("table".to_owned(), key.to_owned())
+	}
+
+	#[test]
+	fn least_frequently_used() {
+		let mut usage = Usage::new(
+			MemoryLimits::new()
+				.with_max_entries(2)
+				.with_policy(EvictionPolicy::LeastFrequentlyUsed),
+		);
+		let value = Value::U8(0);
+
+		assert_eq!(usage.write("table", "1", &value), Some(Vec::new()));
+		assert_eq!(usage.write("table", "2", &value), Some(Vec::new()));
+		usage.read("table", "1");
+
+		// the new entry is never evicted to make room for itself, even though it's the least used.
+		assert_eq!(usage.write("table", "3", &value), Some(vec![id("2")]));
+		assert_eq!(usage.write("table", "4", &value), Some(vec![id("3")]));
+		assert_eq!(
+			usage.write("__index__5_table__name", "1", &value),
+			Some(Vec::new())
+		);
+		assert_eq!(usage.evictions(), 2);
+	}
+
+	#[test]
+	fn byte_limits() {
+		let mut usage = Usage::new(MemoryLimits::new().with_max_bytes(8));
+
+		assert_eq!(
+			usage.write("table", "1", &Value::String("abc".to_owned())),
+			Some(Vec::new())
+		);
+		assert_eq!(usage.bytes(), 4);
+		assert_eq!(usage.write("table", "2", &Value::U64(0)), None);
+		assert_eq!(
+			usage.write("table", "2", &Value::U32(0)),
+			Some(vec![id("1")])
+		);
+		assert_eq!(usage.bytes(), 5);
+
+		usage.remove_table("table");
+		assert_eq!(usage.bytes(), 0);
+	}
+}
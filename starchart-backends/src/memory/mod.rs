@@ -0,0 +1,1150 @@
+//! A memory based backend. Useful for debugging or applications
+//! who only need to store data at runtime.
+
+use std::{
+	collections::{hash_map::RandomState, BTreeMap},
+	error::Error,
+	fmt::{Debug, Display, Formatter, Result as FmtResult},
+	hash::BuildHasher,
+	iter::{self, FromIterator},
+	sync::{Mutex, MutexGuard, PoisonError},
+};
+
+use dashmap::DashMap;
+use futures_util::{
+	future::{ok, ready},
+	FutureExt,
+};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use serde_value::{to_value, DeserializerError, SerializerError, Value};
+use starchart::{
+	backend::{
+		futures::{
+			CreateFuture, CreateTableFuture, DeleteFuture, DeleteTableFuture, GetAllFuture,
+			GetFilteredFuture, GetFuture, GetKeysFuture, GetMultiFuture, HasFuture, HasTableFuture,
+			RenameTableFuture, ReplaceFuture, TablesFuture, TruncateTableFuture, UpdateFuture,
+		},
+		Backend, CorruptEntryError,
+	},
+	query::Filter,
+	transaction::TransactionalBackend,
+	Entry,
+};
+
+pub use self::limits::{EvictionPolicy, MemoryLimits};
+use self::limits::{Usage, EXPIRY_TABLE, INDEX_KEYS_TABLE, INDEX_TABLE, VERSION_TABLE};
+
+mod limits;
+
+/// An error returned from the [`MemoryBackend`].
+#[cfg(feature = "memory")]
+#[derive(Debug)]
+pub struct MemoryError {
+	source: Option<Box<dyn Error + Send + Sync>>,
+	kind: MemoryErrorType,
+}
+
+impl MemoryError {
+	/// Immutable reference to the type of error that occurred.
+	#[must_use = "retrieving the type has no effect if left unused"]
+	pub const fn kind(&self) -> &MemoryErrorType {
+		&self.kind
+	}
+
+	/// Consume the error, returning the source error if there is any.
+	#[must_use = "consuming the error and retrieving the source has no effect if left unused"]
+	pub fn into_source(self) -> Option<Box<dyn Error + Send + Sync>> {
+		self.source
+	}
+
+	/// Consume the error, returning the owned error type and the source error.
+	#[must_use = "consuming the error into it's parts has no effect if left unused"]
+	pub fn into_parts(self) -> (MemoryErrorType, Option<Box<dyn Error + Send + Sync>>) {
+		(self.kind, self.source)
+	}
+
+	fn corrupt(table: &str, key: &str, err: DeserializerError) -> Self {
+		Self {
+			source: Some(Box::new(CorruptEntryError::new(table, key, err))),
+			kind: MemoryErrorType::Deserialization,
+		}
+	}
+}
+
+impl Display for MemoryError {
+	fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+		match &self.kind {
+			MemoryErrorType::Serialization => f.write_str("a serialization error occurred"),
+			MemoryErrorType::Deserialization => f.write_str("a deserialization error occurred"),
+			MemoryErrorType::CapacityExceeded => {
+				f.write_str("the entry doesn't fit within the backend's limits")
+			}
+		}
+	}
+}
+
+impl Error for MemoryError {
+	fn source(&self) -> Option<&(dyn Error + 'static)> {
+		self.source
+			.as_ref()
+			.map(|source| &**source as &(dyn Error + 'static))
+	}
+}
+
+impl From<SerializerError> for MemoryError {
+	fn from(err: SerializerError) -> Self {
+		Self {
+			source: Some(Box::new(err)),
+			kind: MemoryErrorType::Serialization,
+		}
+	}
+}
+
+impl From<DeserializerError> for MemoryError {
+	fn from(err: DeserializerError) -> Self {
+		Self {
+			source: Some(Box::new(err)),
+			kind: MemoryErrorType::Deserialization,
+		}
+	}
+}
+
+impl From<MemoryError> for starchart::Error {
+	fn from(e: MemoryError) -> Self {
+		Self::backend(Some(Box::new(e)))
+	}
+}
+
+/// The type of [`MemoryError`] that occurred.
+#[cfg(feature = "memory")]
+#[allow(missing_copy_implementations)]
+#[derive(Debug)]
+#[non_exhaustive]
+pub enum MemoryErrorType {
+	/// A serialization error occurred.
+	Serialization,
+	/// An entry couldn't be deserialized, if it was read from the backend the source is a
+	/// [`CorruptEntryError`].
+	Deserialization,
+	/// An entry doesn't fit within the [`MemoryLimits`] of the backend, either because
+	/// it's [`EvictionPolicy`] is [`EvictionPolicy::Reject`], or because the entry is
+	/// larger than the limits on it's own.
+	CapacityExceeded,
+}
+
+/// A copy of every table and entry in a [`MemoryBackend`], from [`MemoryBackend::snapshot`].
+///
+/// Snapshots can be serialized with any serde format, so the state of a backend can be saved to a file
+/// and loaded into another backend with [`MemoryBackend::restore`]. Tables and entries are ordered by name,
+/// so serialized snapshots of the same state are identical.
+#[cfg(feature = "memory")]
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+#[must_use = "a snapshot does nothing until it's restored"]
+pub struct Snapshot {
+	tables: BTreeMap<String, BTreeMap<String, Value>>,
+}
+
+impl Snapshot {
+	/// The names of the tables in the snapshot, in order.
+	pub fn tables(&self) -> impl Iterator<Item = &str> {
+		self.tables.keys().map(String::as_str)
+	}
+
+	/// The amount of entries in the given table, or [`None`] if the table isn't in the snapshot.
+	#[must_use]
+	pub fn table_len(&self, table: &str) -> Option<usize> {
+		self.tables.get(table).map(BTreeMap::len)
+	}
+
+	/// Reads an entry from the snapshot.
+	///
+	/// # Errors
+	///
+	/// Returns an error if the entry can't be deserialized into the given type.
+	pub fn get<E: Entry>(&self, table: &str, id: &str) -> Result<Option<E>, MemoryError> {
+		self.tables
+			.get(table)
+			.and_then(|table| table.get(id))
+			.map(|value| value.clone().deserialize_into().map_err(Into::into))
+			.transpose()
+	}
+
+	/// Whether the snapshot has no tables.
+	#[must_use]
+	pub fn is_empty(&self) -> bool {
+		self.tables.is_empty()
+	}
+}
+
+impl Serialize for Snapshot {
+	fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+		self.tables.serialize(serializer)
+	}
+}
+
+impl<'de> Deserialize<'de> for Snapshot {
+	fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+		BTreeMap::deserialize(deserializer).map(|tables| Self { tables })
+	}
+}
+
+/// A memory-based backend, uses a [`DashMap`] of [`Value`]s
+/// to represent data.
+#[cfg(feature = "memory")]
+#[must_use = "a memory backend does nothing on it's own"]
+pub struct MemoryBackend<S = RandomState> {
+	tables: DashMap<String, DashMap<String, Value, S>, S>,
+	usage: Option<Mutex<Usage>>,
+}
+
+impl MemoryBackend<RandomState> {
+	/// Creates a new [`MemoryBackend`].
+	pub fn new() -> Self {
+		Self::with_capacity_and_hasher(0, RandomState::default())
+	}
+
+	/// Creates a new [`MemoryBackend`] with the specified capacity.
+	pub fn with_capacity(cap: usize) -> Self {
+		Self::with_capacity_and_hasher(cap, RandomState::default())
+	}
+}
+
+impl<S: BuildHasher + Clone> MemoryBackend<S> {
+	/// Creates a new [`MemoryBackend`] with the specified hasher.
+	pub fn with_hasher(hasher: S) -> Self {
+		Self::with_capacity_and_hasher(0, hasher)
+	}
+
+	/// Creates a new [`MemoryBackend`] with the specified capacity and hasher.
+	pub fn with_capacity_and_hasher(cap: usize, hasher: S) -> Self {
+		Self {
+			tables: DashMap::with_capacity_and_hasher(cap, hasher),
+			usage: None,
+		}
+	}
+
+	/// Limits how many entries and bytes the backend holds, evicting entries or rejecting
+	/// writes once a limit is reached, depending on the [`EvictionPolicy`].
+	///
+	/// This makes the backend suitable as a bounded cache in long-running services. Entries are
+	/// counted as they're written, so this should be set before any entries are created.
+	/// Reads through [`Backend::get`] count as a use of the entry.
+	pub fn with_limits(mut self, limits: MemoryLimits) -> Self {
+		self.usage = Some(Mutex::new(Usage::new(limits)));
+
+		self
+	}
+
+	/// The limits of the backend, if any were set with [`Self::with_limits`].
+	#[must_use]
+	pub fn limits(&self) -> Option<MemoryLimits> {
+		self.usage().map(|usage| usage.limits())
+	}
+
+	/// The estimated bytes held by the backend, or 0 if it has no limits.
+	#[must_use]
+	pub fn used_bytes(&self) -> usize {
+		self.usage().map_or(0, |usage| usage.bytes())
+	}
+
+	/// The amount of entries evicted to make room for others since the backend was created.
+	#[must_use]
+	pub fn evictions(&self) -> u64 {
+		self.usage().map_or(0, |usage| usage.evictions())
+	}
+
+	/// Synchronously copies every entry in a table, sorted by key.
+	///
+	/// This is meant for debug dumps and assertions in synchronous test helpers, entries are
+	/// returned as stored, including the table's metadata entry. A missing table is returned as empty.
+	///
+	/// # Errors
+	///
+	/// Returns an error if an entry can't be deserialized into the given type.
+	pub fn snapshot_table<E: Entry>(&self, table: &str) -> Result<Vec<(String, E)>, MemoryError> {
+		let Some(table) = self.tables.get(table) else {
+			return Ok(Vec::new());
+		};
+
+		let mut entries = table
+			.iter()
+			.map(|entry| {
+				Ok((
+					entry.key().clone(),
+					entry.value().clone().deserialize_into()?,
+				))
+			})
+			.collect::<Result<Vec<_>, MemoryError>>()?;
+
+		entries.sort_unstable_by(|(a, _), (b, _)| a.cmp(b));
+
+		Ok(entries)
+	}
+
+	/// Copies every table and entry into a [`Snapshot`], including metadata entries.
+	///
+	/// Each table is copied as it is when it's reached, so writes made while the snapshot
+	/// is being taken may or may not be included. Taking a snapshot while nothing else is
+	/// writing, such as between test steps, always gives a consistent copy.
+	pub fn snapshot(&self) -> Snapshot {
+		let tables = self
+			.tables
+			.iter()
+			.map(|table| {
+				let entries = table
+					.value()
+					.iter()
+					.map(|entry| (entry.key().clone(), entry.value().clone()))
+					.collect();
+
+				(table.key().clone(), entries)
+			})
+			.collect();
+
+		Snapshot { tables }
+	}
+
+	/// Replaces every table and entry with the ones in the [`Snapshot`].
+	///
+	/// Tables that aren't in the snapshot are removed. Like [`Self::snapshot`], this isn't atomic
+	/// with concurrent reads and writes.
+	///
+	/// With [`Self::with_limits`], entries are restored in order and evicted as usual, so
+	/// only the last entries that fit are kept. Entries rejected by [`EvictionPolicy::Reject`]
+	/// are skipped.
+	pub fn restore(&self, snapshot: Snapshot) {
+		self.tables.clear();
+
+		if let Some(mut usage) = self.usage() {
+			usage.clear();
+		}
+
+		for (name, entries) in snapshot.tables {
+			let table =
+				DashMap::with_capacity_and_hasher(entries.len(), self.tables.hasher().clone());
+			self.tables.insert(name.clone(), table);
+
+			for (key, value) in entries {
+				// the table can only be missing if it was evicted by a concurrent restore.
+				let _ = self.insert(&name, key, value);
+			}
+		}
+	}
+
+	fn usage(&self) -> Option<MutexGuard<'_, Usage>> {
+		// usage is only updated once it's bookkeeping is complete, so it's consistent even if a
+		// panic poisoned the mutex.
+		self.usage
+			.as_ref()
+			.map(|usage| usage.lock().unwrap_or_else(PoisonError::into_inner))
+	}
+
+	/// Inserts an entry into an existing table, evicting other entries if needed.
+	fn insert(&self, table: &str, key: String, value: Value) -> Result<(), MemoryError> {
+		let evicted = {
+			let Some(entries) = self.tables.get(table) else {
+				return Ok(());
+			};
+
+			let evicted = match self.usage() {
+				Some(mut usage) => usage.write(table, &key, &value).ok_or(MemoryError {
+					source: None,
+					kind: MemoryErrorType::CapacityExceeded,
+				})?,
+				None => Vec::new(),
+			};
+
+			entries.insert(key, value);
+
+			evicted
+		};
+
+		for (table, key) in evicted {
+			if let Some(entries) = self.tables.get(&table) {
+				entries.remove(&key);
+			}
+
+			self.remove_reserved_rows(&table, &key);
+		}
+
+		Ok(())
+	}
+
+	/// Removes the rows starchart keeps for an evicted entry, it's expiry time, version, and
+	/// secondary index entries.
+	///
+	/// The tables are found by name, as a child chart or namespace prefixes their names along with
+	/// the entry's table.
+	fn remove_reserved_rows(&self, table: &str, key: &str) {
+		for reserved in &self.tables {
+			let name = reserved.key();
+
+			if is_owned_by(name, EXPIRY_TABLE, table)
+				|| is_owned_by(name, VERSION_TABLE, table)
+				|| is_owned_by(name, INDEX_KEYS_TABLE, table)
+			{
+				reserved.value().remove(key);
+			} else if is_owned_by(name, INDEX_TABLE, table) {
+				// index tables are keyed by the indexed value, holding the keys of every entry with it.
+				reserved.value().retain(|_, value| {
+					let Value::Seq(keys) = value else {
+						return true;
+					};
+
+					keys.retain(
+						|indexed| !matches!(indexed, Value::String(indexed) if indexed == key),
+					);
+
+					!keys.is_empty()
+				});
+			}
+		}
+	}
+}
+
+/// Checks whether the reserved table `name` was made for `table` by prefixing it with `marker`,
+/// after any prefix the two share. Index tables have the length of the table's name between the marker
+/// and the table's name, and the index name after it.
+fn is_owned_by(name: &str, marker: &str, table: &str) -> bool {
+	name.match_indices(marker).any(|(start, _)| {
+		let (shared, rest) = (&name[..start], &name[start + marker.len()..]);
+
+		if marker == INDEX_TABLE {
+			rest.split_once('_')
+				.and_then(|(len, rest)| rest.split_at_checked(len.parse().ok()?))
+				.is_some_and(|(unmarked, index)| {
+					[shared, unmarked].concat() == table && index.starts_with("__")
+				})
+		} else {
+			[shared, rest].concat() == table
+		}
+	})
+}
+
+impl<S: BuildHasher + Clone> Debug for MemoryBackend<S> {
+	fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+		f.debug_struct("MemoryBackend")
+			.field("tables", &self.tables)
+			.field("limits", &self.limits())
+			.finish_non_exhaustive()
+	}
+}
+
+impl<S: Default + BuildHasher + Clone> Default for MemoryBackend<S> {
+	fn default() -> Self {
+		Self {
+			tables: DashMap::default(),
+			usage: None,
+		}
+	}
+}
+
+impl<S: BuildHasher + Clone> Clone for MemoryBackend<S> {
+	fn clone(&self) -> Self {
+		Self {
+			tables: self.tables.clone(),
+			usage: self.usage().map(|usage| Mutex::new(usage.clone())),
+		}
+	}
+}
+
+impl<S: BuildHasher + Clone + Send + Sync> Backend for MemoryBackend<S> {
+	type Error = MemoryError;
+
+	fn has_table<'a>(&'a self, table: &'a str) -> HasTableFuture<'a, Self::Error> {
+		ok(self.tables.contains_key(table)).boxed()
+	}
+
+	fn tables<I>(&self) -> TablesFuture<'_, I, Self::Error>
+	where
+		I: FromIterator<String>,
+	{
+		async move {
+			Ok(self
+				.tables
+				.iter()
+				.map(|table| table.key().clone())
+				.collect())
+		}
+		.boxed()
+	}
+
+	fn create_table<'a>(&'a self, table: &'a str) -> CreateTableFuture<'a, Self::Error> {
+		self.tables.insert(
+			table.to_owned(),
+			DashMap::with_hasher(self.tables.hasher().clone()),
+		);
+
+		ok(()).boxed()
+	}
+
+	fn delete_table<'a>(&'a self, table: &'a str) -> DeleteTableFuture<'a, Self::Error> {
+		self.tables.remove(table);
+
+		if let Some(mut usage) = self.usage() {
+			usage.remove_table(table);
+			usage.unpin_table(table);
+		}
+
+		ok(()).boxed()
+	}
+
+	fn rename_table<'a>(
+		&'a self,
+		table: &'a str,
+		new: &'a str,
+	) -> RenameTableFuture<'a, Self::Error> {
+		if let Some((_, entries)) = self.tables.remove(table) {
+			self.tables.insert(new.to_owned(), entries);
+
+			if let Some(mut usage) = self.usage() {
+				usage.rename_table(table, new);
+			}
+		}
+
+		ok(()).boxed()
+	}
+
+	fn truncate_table<'a>(&'a self, table: &'a str) -> TruncateTableFuture<'a, Self::Error> {
+		if let Some(entries) = self.tables.get(table) {
+			entries.clear();
+		}
+
+		if let Some(mut usage) = self.usage() {
+			usage.remove_table(table);
+			usage.unpin_table(table);
+		}
+
+		ok(()).boxed()
+	}
+
+	fn get_keys<'a, I>(&'a self, table: &'a str) -> GetKeysFuture<'a, I, Self::Error>
+	where
+		I: FromIterator<String>,
+	{
+		async move {
+			self.tables.get(table).map_or_else(
+				|| Ok(None.into_iter().collect()),
+				|table| Ok(table.clone().into_iter().map(|(key, _)| key).collect()),
+			)
+		}
+		.boxed()
+	}
+
+	fn get_all<'a, D, I>(
+		&'a self,
+		table: &'a str,
+		entries: &'a [&'a str],
+	) -> GetAllFuture<'a, I, Self::Error>
+	where
+		D: Entry,
+		I: FromIterator<D>,
+	{
+		async move {
+			self.tables.get(table).map_or_else(
+				|| Ok(None.into_iter().collect::<I>()),
+				|stored| {
+					stored
+						.clone()
+						.into_iter()
+						.filter_map(|(key, value)| {
+							if entries.contains(&key.as_str()) {
+								Some(
+									value
+										.deserialize_into()
+										.map_err(|e| MemoryError::corrupt(table, &key, e)),
+								)
+							} else {
+								None
+							}
+						})
+						.collect::<Result<I, Self::Error>>()
+				},
+			)
+		}
+		.boxed()
+	}
+
+	fn get_filtered<'a, D, I>(
+		&'a self,
+		table: &'a str,
+		filter: &'a Filter<'a, D>,
+	) -> GetFilteredFuture<'a, I, Self::Error>
+	where
+		D: Entry,
+		I: FromIterator<D>,
+	{
+		async move {
+			self.tables.get(table).map_or_else(
+				|| Ok(iter::empty().collect::<I>()),
+				|stored| {
+					stored
+						.iter()
+						.filter_map(
+							|entry| match entry.value().clone().deserialize_into::<D>() {
+								Ok(value) => filter.matches(entry.key(), &value).then(|| Ok(value)),
+								Err(e) => Some(Err(MemoryError::corrupt(table, entry.key(), e))),
+							},
+						)
+						.collect::<Result<I, Self::Error>>()
+				},
+			)
+		}
+		.boxed()
+	}
+
+	fn get<'a, D>(&'a self, table: &'a str, id: &'a str) -> GetFuture<'a, D, Self::Error>
+	where
+		D: Entry,
+	{
+		async move {
+			if let Some(entries) = self.tables.get(table) {
+				let value = match entries.get(id) {
+					None => return Ok(None),
+					Some(json) => json.value().clone(),
+				};
+				drop(entries);
+
+				if let Some(mut usage) = self.usage() {
+					usage.read(table, id);
+				}
+
+				value
+					.deserialize_into()
+					.map(Some)
+					.map_err(|e| MemoryError::corrupt(table, id, e))
+			} else {
+				Ok(None)
+			}
+		}
+		.boxed()
+	}
+
+	fn get_multi<'a, D>(
+		&'a self,
+		table: &'a str,
+		ids: &'a [&'a str],
+	) -> GetMultiFuture<'a, D, Self::Error>
+	where
+		D: Entry,
+	{
+		async move {
+			let values = match self.tables.get(table) {
+				Some(entries) => ids
+					.iter()
+					.map(|id| entries.get(*id).map(|json| json.value().clone()))
+					.collect::<Vec<_>>(),
+				None => return Ok(iter::repeat_with(|| None).take(ids.len()).collect()),
+			};
+
+			if let Some(mut usage) = self.usage() {
+				for (id, value) in ids.iter().zip(&values) {
+					if value.is_some() {
+						usage.read(table, id);
+					}
+				}
+			}
+
+			ids.iter()
+				.zip(values)
+				.map(|(id, value)| {
+					value
+						.map(|value| {
+							value
+								.deserialize_into()
+								.map_err(|e| MemoryError::corrupt(table, id, e))
+						})
+						.transpose()
+				})
+				.collect()
+		}
+		.boxed()
+	}
+
+	fn has<'a>(&'a self, table: &'a str, id: &'a str) -> HasFuture<'a, Self::Error> {
+		ok(self
+			.tables
+			.get(table)
+			.map_or(false, |table| table.contains_key(id)))
+		.boxed()
+	}
+
+	fn create<'a, E>(
+		&'a self,
+		table: &'a str,
+		id: &'a str,
+		value: &'a E,
+	) -> CreateFuture<'a, Self::Error>
+	where
+		E: Entry,
+	{
+		let result = to_value(value)
+			.map_err(MemoryError::from)
+			.and_then(|serialized| self.insert(table, id.to_owned(), serialized));
+
+		ready(result).boxed()
+	}
+
+	fn update<'a, E>(
+		&'a self,
+		table: &'a str,
+		id: &'a str,
+		value: &'a E,
+	) -> UpdateFuture<'a, Self::Error>
+	where
+		E: Entry,
+	{
+		let result = to_value(value)
+			.map_err(MemoryError::from)
+			.and_then(|to_replace| self.insert(table, id.to_owned(), to_replace));
+
+		ready(result).boxed()
+	}
+
+	fn replace<'a, E>(
+		&'a self,
+		table: &'a str,
+		id: &'a str,
+		value: &'a E,
+	) -> ReplaceFuture<'a, Self::Error>
+	where
+		E: Entry,
+	{
+		self.update(table, id, value)
+	}
+
+	fn set_pinned(&self, table: &str, id: &str, pinned: bool) {
+		if let Some(mut usage) = self.usage() {
+			usage.set_pinned(table, id, pinned);
+		}
+	}
+
+	fn delete<'a>(&'a self, table: &'a str, id: &'a str) -> DeleteFuture<'a, Self::Error> {
+		if let Some(entries) = self.tables.get(table) {
+			entries.remove(id);
+		}
+
+		if let Some(mut usage) = self.usage() {
+			usage.remove(table, id);
+		}
+
+		ok(()).boxed()
+	}
+}
+
+impl<S: BuildHasher + Clone + Send + Sync> TransactionalBackend for MemoryBackend<S> {}
+
+#[cfg(all(test, not(miri)))]
+mod tests {
+	use std::{fmt::Debug, time::Duration};
+
+	use fxhash::FxBuildHasher;
+	use serde::{Deserialize, Serialize};
+	use starchart::{
+		action::{CreateEntryAction, ReadEntryAction},
+		backend::{testsuite, Backend},
+		query::Filter,
+		Error, IndexEntry, Starchart,
+	};
+	use static_assertions::assert_impl_all;
+
+	use super::{
+		EvictionPolicy, MemoryBackend, MemoryError, MemoryErrorType, MemoryLimits, Snapshot,
+	};
+	use crate::testing::TestSettings;
+
+	assert_impl_all!(MemoryBackend: Backend, Clone, Debug, Default, Send, Sync);
+
+	#[tokio::test]
+	async fn snapshot_table() -> Result<(), MemoryError> {
+		let backend = MemoryBackend::new();
+
+		assert!(backend.snapshot_table::<TestSettings>("table")?.is_empty());
+
+		backend.create_table("table").await?;
+
+		let mut settings = TestSettings::default();
+		for id in [3, 1, 2] {
+			settings.id = id;
+			backend.create("table", &id.to_string(), &settings).await?;
+		}
+
+		let snapshot = backend.snapshot_table::<TestSettings>("table")?;
+		let keys = snapshot
+			.iter()
+			.map(|(key, _)| key.as_str())
+			.collect::<Vec<_>>();
+
+		assert_eq!(keys, ["1", "2", "3"]);
+		assert_eq!(snapshot[1].1, settings);
+
+		Ok(())
+	}
+
+	#[tokio::test]
+	async fn table_methods() -> Result<(), MemoryError> {
+		let backend = MemoryBackend::with_hasher(FxBuildHasher::default());
+
+		backend.init().await?;
+
+		assert!(!backend.has_table("table").await?);
+
+		backend.create_table("table").await?;
+
+		assert!(backend.has_table("table").await?);
+		assert_eq!(backend.tables::<Vec<_>>().await?, ["table"]);
+
+		backend.delete_table("table").await?;
+
+		assert!(!backend.has_table("table").await?);
+		assert!(backend.tables::<Vec<String>>().await?.is_empty());
+
+		Ok(())
+	}
+
+	#[tokio::test]
+	async fn rename_and_truncate_table() -> Result<(), MemoryError> {
+		let backend = MemoryBackend::new();
+
+		backend.init().await?;
+
+		backend.create_table("table").await?;
+		backend
+			.create("table", "1", &TestSettings::default())
+			.await?;
+
+		backend.rename_table("table", "renamed").await?;
+
+		assert!(!backend.has_table("table").await?);
+		assert_eq!(
+			backend.get::<TestSettings>("renamed", "1").await?,
+			Some(TestSettings::default())
+		);
+
+		backend.truncate_table("renamed").await?;
+
+		assert!(backend.has_table("renamed").await?);
+		assert!(backend.get_keys::<Vec<String>>("renamed").await?.is_empty());
+
+		Ok(())
+	}
+
+	#[tokio::test]
+	async fn get_keys() -> Result<(), MemoryError> {
+		let backend = MemoryBackend::with_capacity_and_hasher(1, FxBuildHasher::default());
+		backend.init().await?;
+
+		backend.create_table("table").await?;
+
+		let mut settings = TestSettings::default();
+
+		backend.create("table", "1", &settings).await?;
+		settings.id = 2;
+		settings.opt = None;
+		backend.create("table", "2", &settings);
+
+		let mut keys: Vec<String> = backend.get_keys("table").await?;
+
+		let mut expected = vec!["1".to_owned(), "2".to_owned()];
+
+		keys.sort();
+		expected.sort();
+
+		assert_eq!(keys, expected);
+
+		Ok(())
+	}
+
+	#[tokio::test]
+	async fn get_and_create() -> Result<(), MemoryError> {
+		let backend = MemoryBackend::with_capacity_and_hasher(1, FxBuildHasher::default());
+
+		backend.init().await?;
+
+		backend.create_table("table").await?;
+		backend
+			.create("table", "1", &TestSettings::default())
+			.await?;
+
+		assert_eq!(
+			backend.get::<TestSettings>("table", "1").await?,
+			Some(TestSettings::default())
+		);
+
+		assert_eq!(backend.get::<TestSettings>("table", "2").await?, None);
+
+		let settings = TestSettings {
+			id: 2,
+			..TestSettings::default()
+		};
+
+		assert!(backend.create("table", "2", &settings).await.is_ok());
+
+		Ok(())
+	}
+
+	#[tokio::test]
+	async fn update_and_delete() -> Result<(), MemoryError> {
+		let backend = MemoryBackend::with_capacity_and_hasher(1, FxBuildHasher::default());
+		backend.init().await?;
+
+		backend.create_table("table").await?;
+
+		let mut settings = TestSettings::default();
+		backend.create("table", "1", &settings).await?;
+
+		settings.opt = None;
+
+		backend.update("table", "1", &settings).await?;
+
+		assert_eq!(
+			backend.get::<TestSettings>("table", "1").await?,
+			Some(settings)
+		);
+
+		backend.delete("table", "1").await?;
+
+		assert_eq!(backend.get::<TestSettings>("table", "1").await?, None);
+
+		Ok(())
+	}
+
+	#[tokio::test]
+	async fn get_filtered() -> Result<(), MemoryError> {
+		let backend = MemoryBackend::with_capacity_and_hasher(1, FxBuildHasher::default());
+		backend.init().await?;
+
+		backend.create_table("table").await?;
+
+		let mut settings = TestSettings::default();
+		backend.create("table", "1", &settings).await?;
+		settings.id = 2;
+		settings.opt = None;
+		backend.create("table", "2", &settings).await?;
+
+		let filter = Filter::entry(|settings: &TestSettings| settings.opt.is_none());
+		let filtered: Vec<TestSettings> = backend.get_filtered("table", &filter).await?;
+
+		assert_eq!(filtered, vec![settings]);
+
+		let filter = Filter::value(|value| value["id"] == 1);
+		let filtered: Vec<TestSettings> = backend.get_filtered("table", &filter).await?;
+
+		assert_eq!(filtered, vec![TestSettings::default()]);
+
+		Ok(())
+	}
+
+	#[tokio::test]
+	async fn get_multi() -> Result<(), MemoryError> {
+		let backend = MemoryBackend::new();
+		backend.init().await?;
+
+		assert_eq!(
+			backend.get_multi::<TestSettings>("table", &["1"]).await?,
+			vec![None]
+		);
+
+		backend.create_table("table").await?;
+
+		let mut settings = TestSettings::default();
+		backend.create("table", "1", &settings).await?;
+		settings.id = 2;
+		backend.create("table", "2", &settings).await?;
+
+		let entries = backend
+			.get_multi::<TestSettings>("table", &["2", "3", "1", "2"])
+			.await?;
+
+		assert_eq!(
+			entries,
+			vec![
+				Some(settings.clone()),
+				None,
+				Some(TestSettings::default()),
+				Some(settings)
+			]
+		);
+
+		Ok(())
+	}
+
+	#[tokio::test]
+	async fn testsuite() -> Result<(), MemoryError> {
+		testsuite::run(MemoryBackend::new()).await
+	}
+
+	#[derive(Debug, Default, Clone, PartialEq, Serialize, Deserialize)]
+	struct Profile {
+		id: u32,
+		email: String,
+		settings: ProfileSettings,
+	}
+
+	#[derive(Debug, Default, Clone, PartialEq, Serialize, Deserialize)]
+	struct ProfileSettings {
+		theme: String,
+		notifications: bool,
+	}
+
+	impl IndexEntry for Profile {
+		type Key = u32;
+
+		fn key(&self) -> &Self::Key {
+			&self.id
+		}
+
+		fn indexes(&self) -> Vec<(&'static str, String)> {
+			vec![("email", self.email.clone())]
+		}
+	}
+
+	#[tokio::test]
+	async fn snapshots() -> Result<(), Error> {
+		let backend = MemoryBackend::new();
+		backend.create_table("table").await?;
+		backend
+			.create("table", "1", &TestSettings::default())
+			.await?;
+
+		let snapshot = backend.snapshot();
+		assert_eq!(snapshot.tables().collect::<Vec<_>>(), ["table"]);
+		assert_eq!(snapshot.table_len("table"), Some(1));
+		assert_eq!(
+			snapshot.get::<TestSettings>("table", "1")?,
+			Some(TestSettings::default())
+		);
+
+		backend.delete("table", "1").await?;
+		backend.create_table("other").await?;
+
+		// snapshots survive being serialized, and restore into any backend.
+		let serialized = serde_value::to_value(&snapshot).map_err(MemoryError::from)?;
+		let deserialized: Snapshot = serialized.deserialize_into().map_err(MemoryError::from)?;
+		assert_eq!(deserialized, snapshot);
+
+		backend.restore(deserialized);
+		assert!(!backend.has_table("other").await?);
+		assert_eq!(
+			backend.get::<TestSettings>("table", "1").await?,
+			Some(TestSettings::default())
+		);
+
+		let other = MemoryBackend::with_hasher(FxBuildHasher::default());
+		other.restore(snapshot);
+		assert_eq!(other.snapshot(), backend.snapshot());
+
+		Ok(())
+	}
+
+	#[tokio::test]
+	async fn limits() -> Result<(), MemoryError> {
+		let settings = TestSettings::default();
+		let backend = MemoryBackend::new().with_limits(MemoryLimits::new().with_max_entries(2));
+		backend.create_table("table").await?;
+		backend.create("table", "1", &settings).await?;
+		backend.create("table", "2", &settings).await?;
+		backend.get::<TestSettings>("table", "1").await?;
+
+		backend.create("table", "3", &settings).await?;
+		assert!(backend.has("table", "1").await?);
+		assert!(!backend.has("table", "2").await?);
+		assert_eq!(backend.evictions(), 1);
+
+		// reserved entries are never counted.
+		backend.create("table", "__metadata__", &settings).await?;
+		assert!(backend.has("table", "1").await?);
+
+		backend.delete("table", "1").await?;
+		backend.create("table", "4", &settings).await?;
+		assert!(backend.has("table", "3").await?);
+
+		let rejecting = MemoryBackend::new().with_limits(
+			MemoryLimits::new()
+				.with_max_entries(1)
+				.with_policy(EvictionPolicy::Reject),
+		);
+		rejecting.create_table("table").await?;
+		rejecting.create("table", "1", &settings).await?;
+		rejecting.update("table", "1", &settings).await?;
+
+		let error = rejecting.create("table", "2", &settings).await.unwrap_err();
+		assert!(matches!(error.kind(), MemoryErrorType::CapacityExceeded));
+		assert!(!rejecting.has("table", "2").await?);
+
+		rejecting.delete_table("table").await?;
+		rejecting.create_table("table").await?;
+		rejecting.create("table", "2", &settings).await?;
+		assert_eq!(rejecting.used_bytes(), rejecting.clone().used_bytes());
+
+		Ok(())
+	}
+
+	#[tokio::test]
+	async fn limits_in_a_chart() -> Result<(), Error> {
+		let backend = MemoryBackend::new().with_limits(MemoryLimits::new().with_max_entries(1));
+		let chart = Starchart::new(backend).await?;
+		chart.create_table("table").await?;
+
+		let profile = |id: u32| Profile {
+			id,
+			email: format!("{id}@example.com"),
+			..Profile::default()
+		};
+		let read = |id: u32| {
+			ReadEntryAction::<Profile>::for_table("table")
+				.with_key(&id)
+				.run_read_entry(&chart)
+		};
+
+		// pinned entries are never evicted, so writes that would need to fail instead.
+		chart.pin("table", &1);
+		let first = profile(1);
+		CreateEntryAction::for_table("table")
+			.with_entry(&first)
+			.run_create_entry(&chart)
+			.await?;
+		let second = profile(2);
+		assert!(CreateEntryAction::for_table("table")
+			.with_entry(&second)
+			.run_create_entry(&chart)
+			.await
+			.is_err());
+		assert_eq!(read(1).await?, Some(first));
+
+		// evicted entries take their expiry time, version, and index rows with them.
+		chart.unpin("table", &1);
+		ReadEntryAction::<Profile>::for_table("table")
+			.with_key(&1_u32)
+			.run_read_versioned(&chart)
+			.await?;
+		let mut action = CreateEntryAction::new();
+		action
+			.set_table("table")
+			.set_entry(&second)
+			.set_ttl(Duration::from_secs(10));
+		action.run_create_entry(&chart).await?;
+		assert!(chart.has("__expiry__table", "2").await?);
+		assert!(chart.has("__version__table", "2").await?);
+		assert!(chart.has("__index__5_table__email", &second.email).await?);
+
+		CreateEntryAction::for_table("table")
+			.with_entry(&profile(3))
+			.run_create_entry(&chart)
+			.await?;
+		assert_eq!(read(2).await?, None);
+		assert!(!chart.has("__expiry__table", "2").await?);
+		assert!(!chart.has("__version__table", "2").await?);
+		assert!(!chart.has("__index__5_table__email", &second.email).await?);
+		assert!(!chart.has("__index_keys__table", "2").await?);
+
+		// only starchart's own tables are reserved, not every table starting with `__`.
+		let limited = MemoryBackend::new().with_limits(MemoryLimits::new().with_max_entries(1));
+		limited.create_table("__cache").await?;
+		limited.create("__cache", "1", &profile(1)).await?;
+		limited.create("__cache", "2", &profile(2)).await?;
+		assert!(!limited.has("__cache", "1").await?);
+
+		Ok(())
+	}
+}
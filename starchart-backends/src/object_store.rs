@@ -0,0 +1,454 @@
+//! An [`object_store`] based backend, storing entries in S3, GCS, Azure, or any other object store.
+//!
+//! The cloud stores are enabled with the `aws`, `gcp`, and `azure` features.
+
+use std::{
+	error::Error,
+	fmt::{Display, Formatter, Result as FmtResult},
+	iter::FromIterator,
+	str::Utf8Error,
+	sync::Arc,
+};
+
+use futures_util::{FutureExt, StreamExt, TryStreamExt};
+use object_store::{
+	path::{Error as PathError, Path, PathPart},
+	Error as StoreError, ObjectStore, PutPayload,
+};
+use percent_encoding::percent_decode_str;
+use starchart::{
+	backend::{
+		futures::{
+			CreateFuture, CreateTableFuture, DeleteFuture, DeleteTableFuture, GetFuture,
+			GetKeysFuture, HasFuture, HasTableFuture, HealthCheckFuture, TablesFuture,
+			UpdateFuture,
+		},
+		Backend, CorruptEntryError,
+	},
+	Entry,
+};
+
+/// The name of the object that marks a table as existing.
+///
+/// Keys always have `%` encoded, so this can never be the path of an entry.
+const TABLE_MARKER: &str = "%table";
+
+/// An error returned from the [`ObjectStoreBackend`].
+#[derive(Debug)]
+pub struct ObjectStoreError {
+	source: Option<Box<dyn Error + Send + Sync>>,
+	kind: ObjectStoreErrorType,
+}
+
+impl ObjectStoreError {
+	/// Immutable reference to the type of error that occurred.
+	#[must_use = "retrieving the type has no effect if left unused"]
+	pub const fn kind(&self) -> &ObjectStoreErrorType {
+		&self.kind
+	}
+
+	/// Consume the error, returning the source error if there is any.
+	#[must_use = "consuming the error and retrieving the source has no effect if left unused"]
+	pub fn into_source(self) -> Option<Box<dyn Error + Send + Sync>> {
+		self.source
+	}
+
+	/// Consume the error, returning the owned error type and the source error.
+	#[must_use = "consuming the error into it's parts has no effect if left unused"]
+	pub fn into_parts(self) -> (ObjectStoreErrorType, Option<Box<dyn Error + Send + Sync>>) {
+		(self.kind, self.source)
+	}
+
+	fn serialization(err: serde_json::Error) -> Self {
+		Self {
+			source: Some(Box::new(err)),
+			kind: ObjectStoreErrorType::Serialization,
+		}
+	}
+
+	fn deserialization(table: &str, key: &str, err: serde_json::Error) -> Self {
+		Self {
+			source: Some(Box::new(CorruptEntryError::new(table, key, err))),
+			kind: ObjectStoreErrorType::Deserialization,
+		}
+	}
+}
+
+impl Display for ObjectStoreError {
+	fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+		match &self.kind {
+			ObjectStoreErrorType::Store => f.write_str("an error occurred within the object store"),
+			ObjectStoreErrorType::Serialization => f.write_str("a serialization error occurred"),
+			ObjectStoreErrorType::Deserialization => {
+				f.write_str("a deserialization error occurred")
+			}
+			ObjectStoreErrorType::InvalidKey => f.write_str("a stored key was not valid UTF-8"),
+		}
+	}
+}
+
+impl Error for ObjectStoreError {
+	fn source(&self) -> Option<&(dyn Error + 'static)> {
+		self.source
+			.as_ref()
+			.map(|source| &**source as &(dyn Error + 'static))
+	}
+}
+
+impl From<StoreError> for ObjectStoreError {
+	fn from(err: StoreError) -> Self {
+		Self {
+			source: Some(Box::new(err)),
+			kind: ObjectStoreErrorType::Store,
+		}
+	}
+}
+
+impl From<PathError> for ObjectStoreError {
+	fn from(err: PathError) -> Self {
+		StoreError::from(err).into()
+	}
+}
+
+impl From<Utf8Error> for ObjectStoreError {
+	fn from(err: Utf8Error) -> Self {
+		Self {
+			source: Some(Box::new(err)),
+			kind: ObjectStoreErrorType::InvalidKey,
+		}
+	}
+}
+
+impl From<ObjectStoreError> for starchart::Error {
+	fn from(e: ObjectStoreError) -> Self {
+		Self::backend(Some(Box::new(e)))
+	}
+}
+
+/// The type of [`ObjectStoreError`] that occurred.
+#[derive(Debug)]
+#[allow(missing_copy_implementations)]
+#[non_exhaustive]
+pub enum ObjectStoreErrorType {
+	/// An error occurred within the object store.
+	Store,
+	/// A serialization error occurred.
+	Serialization,
+	/// An entry couldn't be deserialized, the source is a [`CorruptEntryError`].
+	Deserialization,
+	/// A key stored in the object store was not valid UTF-8.
+	InvalidKey,
+}
+
+/// An [`ObjectStore`] backend, storing each entry as a JSON object at `table/key`.
+///
+/// Entries are written individually, so writes only touch the entry being written, and
+/// the keys of a table are read by listing the objects under it's prefix.
+#[derive(Debug, Clone)]
+#[must_use = "an object store backend does nothing on it's own"]
+pub struct ObjectStoreBackend {
+	store: Arc<dyn ObjectStore>,
+}
+
+impl ObjectStoreBackend {
+	/// Creates a new [`ObjectStoreBackend`] from an [`ObjectStore`].
+	pub fn new<O: ObjectStore>(store: O) -> Self {
+		Self::from_arc(Arc::new(store))
+	}
+
+	/// Creates a new [`ObjectStoreBackend`] from an already shared [`ObjectStore`].
+	pub fn from_arc(store: Arc<dyn ObjectStore>) -> Self {
+		Self { store }
+	}
+
+	/// Returns a reference to the underlying [`ObjectStore`].
+	#[must_use]
+	pub fn store(&self) -> &Arc<dyn ObjectStore> {
+		&self.store
+	}
+
+	fn table_path(table: &str) -> Path {
+		Path::from_iter([PathPart::from(table)])
+	}
+
+	fn marker_path(table: &str) -> Result<Path, ObjectStoreError> {
+		Ok(Path::parse(format!(
+			"{}/{}",
+			Self::table_path(table),
+			TABLE_MARKER
+		))?)
+	}
+
+	fn entry_path(table: &str, id: &str) -> Path {
+		Path::from_iter([table, id])
+	}
+
+	async fn exists(&self, path: &Path) -> Result<bool, ObjectStoreError> {
+		match self.store.head(path).await {
+			Ok(_) => Ok(true),
+			Err(StoreError::NotFound { .. }) => Ok(false),
+			Err(e) => Err(e.into()),
+		}
+	}
+
+	async fn insert<S: Entry>(
+		&self,
+		table: &str,
+		id: &str,
+		value: &S,
+	) -> Result<(), ObjectStoreError> {
+		if !self.exists(&Self::marker_path(table)?).await? {
+			return Ok(());
+		}
+
+		let serialized = serde_json::to_vec(value).map_err(ObjectStoreError::serialization)?;
+
+		self.store
+			.put(&Self::entry_path(table, id), PutPayload::from(serialized))
+			.await?;
+
+		Ok(())
+	}
+}
+
+impl Backend for ObjectStoreBackend {
+	type Error = ObjectStoreError;
+
+	fn health_check(&self) -> HealthCheckFuture<'_, Self::Error> {
+		async move {
+			// the store has to answer this cheap request, whether or not the object exists.
+			self.exists(&Path::from(TABLE_MARKER)).await?;
+
+			Ok(())
+		}
+		.boxed()
+	}
+
+	fn has_table<'a>(&'a self, table: &'a str) -> HasTableFuture<'a, Self::Error> {
+		async move { self.exists(&Self::marker_path(table)?).await }.boxed()
+	}
+
+	fn tables<I>(&self) -> TablesFuture<'_, I, Self::Error>
+	where
+		I: FromIterator<String>,
+	{
+		async move {
+			let listing = self.store.list_with_delimiter(None).await?;
+
+			listing
+				.common_prefixes
+				.iter()
+				.filter_map(Path::filename)
+				.map(|name| Ok(percent_decode_str(name).decode_utf8()?.into_owned()))
+				.collect()
+		}
+		.boxed()
+	}
+
+	fn create_table<'a>(&'a self, table: &'a str) -> CreateTableFuture<'a, Self::Error> {
+		async move {
+			self.store
+				.put(&Self::marker_path(table)?, PutPayload::new())
+				.await?;
+
+			Ok(())
+		}
+		.boxed()
+	}
+
+	fn delete_table<'a>(&'a self, table: &'a str) -> DeleteTableFuture<'a, Self::Error> {
+		async move {
+			let prefix = Self::table_path(table);
+			let locations = self
+				.store
+				.list(Some(&prefix))
+				.map_ok(|meta| meta.location)
+				.boxed();
+
+			self.store
+				.delete_stream(locations)
+				.try_collect::<Vec<_>>()
+				.await?;
+
+			Ok(())
+		}
+		.boxed()
+	}
+
+	fn get_keys<'a, I>(&'a self, table: &'a str) -> GetKeysFuture<'a, I, Self::Error>
+	where
+		I: FromIterator<String>,
+	{
+		async move {
+			let prefix = Self::table_path(table);
+
+			let keys = self
+				.store
+				.list(Some(&prefix))
+				.map_err(ObjectStoreError::from)
+				.try_filter_map(|meta| async move {
+					match meta.location.filename() {
+						None | Some(TABLE_MARKER) => Ok(None),
+						Some(name) => {
+							Ok(Some(percent_decode_str(name).decode_utf8()?.into_owned()))
+						}
+					}
+				})
+				.try_collect::<Vec<_>>()
+				.await?;
+
+			Ok(keys.into_iter().collect())
+		}
+		.boxed()
+	}
+
+	fn get<'a, D>(&'a self, table: &'a str, id: &'a str) -> GetFuture<'a, D, Self::Error>
+	where
+		D: Entry,
+	{
+		async move {
+			let raw = match self.store.get(&Self::entry_path(table, id)).await {
+				Ok(result) => result.bytes().await?,
+				Err(StoreError::NotFound { .. }) => return Ok(None),
+				Err(e) => return Err(e.into()),
+			};
+
+			serde_json::from_slice(&raw)
+				.map(Some)
+				.map_err(|e| ObjectStoreError::deserialization(table, id, e))
+		}
+		.boxed()
+	}
+
+	fn has<'a>(&'a self, table: &'a str, id: &'a str) -> HasFuture<'a, Self::Error> {
+		async move { self.exists(&Self::entry_path(table, id)).await }.boxed()
+	}
+
+	fn create<'a, S>(
+		&'a self,
+		table: &'a str,
+		id: &'a str,
+		value: &'a S,
+	) -> CreateFuture<'a, Self::Error>
+	where
+		S: Entry,
+	{
+		self.insert(table, id, value).boxed()
+	}
+
+	fn update<'a, S>(
+		&'a self,
+		table: &'a str,
+		id: &'a str,
+		value: &'a S,
+	) -> UpdateFuture<'a, Self::Error>
+	where
+		S: Entry,
+	{
+		self.insert(table, id, value).boxed()
+	}
+
+	fn delete<'a>(&'a self, table: &'a str, id: &'a str) -> DeleteFuture<'a, Self::Error> {
+		async move {
+			match self.store.delete(&Self::entry_path(table, id)).await {
+				Ok(()) | Err(StoreError::NotFound { .. }) => Ok(()),
+				Err(e) => Err(e.into()),
+			}
+		}
+		.boxed()
+	}
+}
+
+#[cfg(all(test, not(miri)))]
+mod tests {
+	use std::fmt::Debug;
+
+	use object_store::memory::InMemory;
+	use starchart::backend::{testsuite, Backend};
+	use static_assertions::assert_impl_all;
+
+	use super::{ObjectStoreBackend, ObjectStoreError};
+	use crate::testing::TestSettings;
+
+	assert_impl_all!(ObjectStoreBackend: Backend, Clone, Debug, Send, Sync);
+
+	#[tokio::test]
+	async fn testsuite() -> Result<(), ObjectStoreError> {
+		testsuite::run(ObjectStoreBackend::new(InMemory::new())).await
+	}
+
+	#[tokio::test]
+	async fn table_methods() -> Result<(), ObjectStoreError> {
+		let backend = ObjectStoreBackend::new(InMemory::new());
+
+		assert!(!backend.has_table("table").await?);
+
+		backend.create_table("table").await?;
+		backend
+			.create("table", "1", &TestSettings::default())
+			.await?;
+
+		assert!(backend.has_table("table").await?);
+
+		backend.delete_table("table").await?;
+
+		assert!(!backend.has_table("table").await?);
+		assert!(!backend.has("table", "1").await?);
+
+		Ok(())
+	}
+
+	#[tokio::test]
+	async fn get_keys() -> Result<(), ObjectStoreError> {
+		let backend = ObjectStoreBackend::new(InMemory::new());
+
+		backend.create_table("table").await?;
+		backend.create_table("table/nested").await?;
+
+		let mut settings = TestSettings::default();
+		backend.create("table", "1", &settings).await?;
+		settings.id = 2;
+		settings.opt = None;
+		backend.create("table", "a/%b", &settings).await?;
+		backend.create("table/nested", "3", &settings).await?;
+
+		let mut keys: Vec<String> = backend.get_keys("table").await?;
+		keys.sort();
+
+		assert_eq!(keys, ["1", "a/%b"]);
+
+		Ok(())
+	}
+
+	#[tokio::test]
+	async fn get_and_create() -> Result<(), ObjectStoreError> {
+		let backend = ObjectStoreBackend::new(InMemory::new());
+
+		backend.create_table("table").await?;
+
+		backend
+			.create("table", "1", &TestSettings::default())
+			.await?;
+
+		assert_eq!(
+			backend.get::<TestSettings>("table", "1").await?,
+			Some(TestSettings::default())
+		);
+
+		assert_eq!(backend.get::<TestSettings>("table", "2").await?, None);
+
+		assert_eq!(backend.get::<TestSettings>("missing", "1").await?, None);
+
+		backend
+			.create("missing", "1", &TestSettings::default())
+			.await?;
+
+		assert!(!backend.has("missing", "1").await?);
+
+		backend.delete("table", "1").await?;
+
+		assert!(!backend.has("table", "1").await?);
+
+		Ok(())
+	}
+}
@@ -0,0 +1,538 @@
+//! A Postgres based backend, storing entries as `JSONB` in schema-qualified tables.
+
+use std::{
+	error::Error,
+	fmt::{Debug, Display, Formatter, Result as FmtResult},
+	iter::FromIterator,
+	sync::Arc,
+};
+
+use deadpool_postgres::{
+	tokio_postgres::{types::Json, Error as QueryError, NoTls},
+	Config, CreatePoolError, Object, Pool, PoolError, Runtime,
+};
+use futures_util::FutureExt;
+use starchart::{
+	backend::{
+		futures::{
+			CreateFuture, CreateTableFuture, DeleteFuture, DeleteTableFuture, GetFuture,
+			GetKeysFuture, HasFuture, HasTableFuture, HealthCheckFuture, IncrementFuture,
+			InitFuture, RenameTableFuture, TablesFuture, TruncateTableFuture, UpdateFuture,
+		},
+		Backend, CorruptEntryError,
+	},
+	transaction::TransactionalBackend,
+	Entry,
+};
+
+/// An error returned from the [`PostgresBackend`].
+#[derive(Debug)]
+pub struct PostgresError {
+	source: Option<Box<dyn Error + Send + Sync>>,
+	kind: PostgresErrorType,
+}
+
+impl PostgresError {
+	/// Immutable reference to the type of error that occurred.
+	#[must_use = "retrieving the type has no effect if left unused"]
+	pub const fn kind(&self) -> &PostgresErrorType {
+		&self.kind
+	}
+
+	/// Consume the error, returning the source error if there is any.
+	#[must_use = "consuming the error and retrieving the source has no effect if left unused"]
+	pub fn into_source(self) -> Option<Box<dyn Error + Send + Sync>> {
+		self.source
+	}
+
+	/// Consume the error, returning the owned error type and the source error.
+	#[must_use = "consuming the error into it's parts has no effect if left unused"]
+	pub fn into_parts(self) -> (PostgresErrorType, Option<Box<dyn Error + Send + Sync>>) {
+		(self.kind, self.source)
+	}
+
+	fn corrupt(table: &str, key: &str, err: QueryError) -> Self {
+		Self {
+			source: Some(Box::new(CorruptEntryError::new(table, key, err))),
+			kind: PostgresErrorType::Query,
+		}
+	}
+}
+
+impl Display for PostgresError {
+	fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+		match &self.kind {
+			PostgresErrorType::CreatePool => f.write_str("failed to create the connection pool"),
+			PostgresErrorType::Pool => f.write_str("failed to get a connection from the pool"),
+			PostgresErrorType::Query => f.write_str("an error occurred running a query"),
+		}
+	}
+}
+
+impl Error for PostgresError {
+	fn source(&self) -> Option<&(dyn Error + 'static)> {
+		self.source
+			.as_ref()
+			.map(|source| &**source as &(dyn Error + 'static))
+	}
+}
+
+impl From<CreatePoolError> for PostgresError {
+	fn from(err: CreatePoolError) -> Self {
+		Self {
+			source: Some(Box::new(err)),
+			kind: PostgresErrorType::CreatePool,
+		}
+	}
+}
+
+impl From<PoolError> for PostgresError {
+	fn from(err: PoolError) -> Self {
+		Self {
+			source: Some(Box::new(err)),
+			kind: PostgresErrorType::Pool,
+		}
+	}
+}
+
+impl From<QueryError> for PostgresError {
+	fn from(err: QueryError) -> Self {
+		Self {
+			source: Some(Box::new(err)),
+			kind: PostgresErrorType::Query,
+		}
+	}
+}
+
+impl From<PostgresError> for starchart::Error {
+	fn from(e: PostgresError) -> Self {
+		Self::backend(Some(Box::new(e)))
+	}
+}
+
+/// The type of [`PostgresError`] that occurred.
+#[derive(Debug)]
+#[allow(missing_copy_implementations)]
+#[non_exhaustive]
+pub enum PostgresErrorType {
+	/// The connection pool could not be created.
+	CreatePool,
+	/// A connection could not be retrieved from the pool.
+	Pool,
+	/// A query failed, this includes (de)serialization of `JSONB` values.
+	///
+	/// If an entry's value couldn't be deserialized, the source is a [`CorruptEntryError`].
+	Query,
+}
+
+/// A Postgres backend, using a [`Pool`] of connections.
+///
+/// Every table is created as `"schema"."table" (key TEXT PRIMARY KEY, value JSONB NOT NULL)`.
+///
+/// The pool is closed once the backend and all of it's clones are dropped.
+#[derive(Clone)]
+#[must_use = "a postgres backend does nothing on it's own"]
+pub struct PostgresBackend {
+	// shared by every clone, closing the pool once the last of them is dropped.
+	pool: Arc<OwnedPool>,
+	schema: String,
+}
+
+impl PostgresBackend {
+	/// The schema used if none is provided.
+	pub const DEFAULT_SCHEMA: &'static str = "public";
+
+	/// Creates a new [`PostgresBackend`] using the `public` schema.
+	pub fn new(pool: Pool) -> Self {
+		Self::with_schema(pool, Self::DEFAULT_SCHEMA.to_owned())
+	}
+
+	/// Creates a new [`PostgresBackend`] using the specified schema.
+	pub fn with_schema(pool: Pool, schema: String) -> Self {
+		Self {
+			pool: Arc::new(OwnedPool(pool)),
+			schema,
+		}
+	}
+
+	/// Creates a new [`PostgresBackend`] from a pool [`Config`], without TLS.
+	///
+	/// # Errors
+	///
+	/// Returns an error if the pool could not be created from the config.
+	pub fn from_config(config: &Config, schema: String) -> Result<Self, PostgresError> {
+		let pool = config.create_pool(Some(Runtime::Tokio1), NoTls)?;
+
+		Ok(Self::with_schema(pool, schema))
+	}
+
+	/// Returns a reference to the connection [`Pool`].
+	#[must_use]
+	pub fn pool(&self) -> &Pool {
+		&self.pool.0
+	}
+
+	/// Returns the schema the tables are created in.
+	#[must_use]
+	pub fn schema(&self) -> &str {
+		&self.schema
+	}
+
+	async fn client(&self) -> Result<Object, PostgresError> {
+		Ok(self.pool.0.get().await?)
+	}
+
+	fn qualified(&self, table: &str) -> String {
+		[util::quote_ident(&self.schema), util::quote_ident(table)].join(".")
+	}
+}
+
+/// A [`Pool`] that's closed when dropped.
+struct OwnedPool(Pool);
+
+impl Drop for OwnedPool {
+	fn drop(&mut self) {
+		self.0.close();
+	}
+}
+
+impl Debug for PostgresBackend {
+	fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+		f.debug_struct("PostgresBackend")
+			.field("pool", &self.pool.0.status())
+			.field("schema", &self.schema)
+			.finish()
+	}
+}
+
+impl Backend for PostgresBackend {
+	type Error = PostgresError;
+
+	fn init(&self) -> InitFuture<'_, Self::Error> {
+		async move {
+			let client = self.client().await?;
+			let statement = format!(
+				"CREATE SCHEMA IF NOT EXISTS {}",
+				util::quote_ident(&self.schema)
+			);
+
+			client.batch_execute(&statement).await?;
+
+			Ok(())
+		}
+		.boxed()
+	}
+
+	fn health_check(&self) -> HealthCheckFuture<'_, Self::Error> {
+		async move {
+			self.client().await?.batch_execute("SELECT 1").await?;
+
+			Ok(())
+		}
+		.boxed()
+	}
+
+	fn has_table<'a>(&'a self, table: &'a str) -> HasTableFuture<'a, Self::Error> {
+		async move {
+			let client = self.client().await?;
+			let row = client
+				.query_one(
+					"SELECT EXISTS (SELECT 1 FROM information_schema.tables WHERE table_schema = \
+					 $1 AND table_name = $2)",
+					&[&self.schema, &table],
+				)
+				.await?;
+
+			Ok(row.try_get(0)?)
+		}
+		.boxed()
+	}
+
+	fn tables<I>(&self) -> TablesFuture<'_, I, Self::Error>
+	where
+		I: FromIterator<String>,
+	{
+		async move {
+			let client = self.client().await?;
+			client
+				.query(
+					"SELECT table_name FROM information_schema.tables WHERE table_schema = $1",
+					&[&self.schema],
+				)
+				.await?
+				.into_iter()
+				.map(|row| row.try_get(0).map_err(Into::into))
+				.collect()
+		}
+		.boxed()
+	}
+
+	fn create_table<'a>(&'a self, table: &'a str) -> CreateTableFuture<'a, Self::Error> {
+		async move {
+			let client = self.client().await?;
+			let statement = format!(
+				"CREATE TABLE IF NOT EXISTS {} (key TEXT PRIMARY KEY, value JSONB NOT NULL)",
+				self.qualified(table)
+			);
+
+			client.batch_execute(&statement).await?;
+
+			Ok(())
+		}
+		.boxed()
+	}
+
+	fn delete_table<'a>(&'a self, table: &'a str) -> DeleteTableFuture<'a, Self::Error> {
+		async move {
+			let client = self.client().await?;
+			let statement = format!("DROP TABLE IF EXISTS {}", self.qualified(table));
+
+			client.batch_execute(&statement).await?;
+
+			Ok(())
+		}
+		.boxed()
+	}
+
+	fn rename_table<'a>(
+		&'a self,
+		table: &'a str,
+		new: &'a str,
+	) -> RenameTableFuture<'a, Self::Error> {
+		async move {
+			let client = self.client().await?;
+			let statement = format!(
+				"ALTER TABLE {} RENAME TO {}",
+				self.qualified(table),
+				util::quote_ident(new)
+			);
+
+			client.batch_execute(&statement).await?;
+
+			Ok(())
+		}
+		.boxed()
+	}
+
+	fn truncate_table<'a>(&'a self, table: &'a str) -> TruncateTableFuture<'a, Self::Error> {
+		async move {
+			let client = self.client().await?;
+			let statement = format!("TRUNCATE TABLE {}", self.qualified(table));
+
+			client.batch_execute(&statement).await?;
+
+			Ok(())
+		}
+		.boxed()
+	}
+
+	fn get_keys<'a, I>(&'a self, table: &'a str) -> GetKeysFuture<'a, I, Self::Error>
+	where
+		I: FromIterator<String>,
+	{
+		async move {
+			let client = self.client().await?;
+			let statement = format!("SELECT key FROM {}", self.qualified(table));
+
+			client
+				.query(statement.as_str(), &[])
+				.await?
+				.into_iter()
+				.map(|row| row.try_get(0).map_err(Into::into))
+				.collect()
+		}
+		.boxed()
+	}
+
+	fn get<'a, D>(&'a self, table: &'a str, id: &'a str) -> GetFuture<'a, D, Self::Error>
+	where
+		D: Entry,
+	{
+		async move {
+			let client = self.client().await?;
+			let statement = format!("SELECT value FROM {} WHERE key = $1", self.qualified(table));
+
+			match client.query_opt(statement.as_str(), &[&id]).await? {
+				Some(row) => {
+					let Json(value) = row
+						.try_get::<_, Json<D>>(0)
+						.map_err(|e| PostgresError::corrupt(table, id, e))?;
+					Ok(Some(value))
+				}
+				None => Ok(None),
+			}
+		}
+		.boxed()
+	}
+
+	fn has<'a>(&'a self, table: &'a str, id: &'a str) -> HasFuture<'a, Self::Error> {
+		async move {
+			let client = self.client().await?;
+			let statement = format!(
+				"SELECT EXISTS (SELECT 1 FROM {} WHERE key = $1)",
+				self.qualified(table)
+			);
+
+			let row = client.query_one(statement.as_str(), &[&id]).await?;
+
+			Ok(row.try_get(0)?)
+		}
+		.boxed()
+	}
+
+	fn create<'a, S>(
+		&'a self,
+		table: &'a str,
+		id: &'a str,
+		value: &'a S,
+	) -> CreateFuture<'a, Self::Error>
+	where
+		S: Entry,
+	{
+		async move {
+			let client = self.client().await?;
+			let statement = format!(
+				"INSERT INTO {} (key, value) VALUES ($1, $2) ON CONFLICT (key) DO NOTHING",
+				self.qualified(table)
+			);
+
+			client
+				.execute(statement.as_str(), &[&id, &Json(value)])
+				.await?;
+
+			Ok(())
+		}
+		.boxed()
+	}
+
+	fn update<'a, S>(
+		&'a self,
+		table: &'a str,
+		id: &'a str,
+		value: &'a S,
+	) -> UpdateFuture<'a, Self::Error>
+	where
+		S: Entry,
+	{
+		async move {
+			let client = self.client().await?;
+			let statement = format!(
+				"INSERT INTO {} (key, value) VALUES ($1, $2) ON CONFLICT (key) DO UPDATE SET \
+				 value = EXCLUDED.value",
+				self.qualified(table)
+			);
+
+			client
+				.execute(statement.as_str(), &[&id, &Json(value)])
+				.await?;
+
+			Ok(())
+		}
+		.boxed()
+	}
+
+	fn delete<'a>(&'a self, table: &'a str, id: &'a str) -> DeleteFuture<'a, Self::Error> {
+		async move {
+			let client = self.client().await?;
+			let statement = format!("DELETE FROM {} WHERE key = $1", self.qualified(table));
+
+			client.execute(statement.as_str(), &[&id]).await?;
+
+			Ok(())
+		}
+		.boxed()
+	}
+
+	fn increment<'a>(
+		&'a self,
+		table: &'a str,
+		id: &'a str,
+		field: &'a [&'a str],
+		delta: i64,
+	) -> IncrementFuture<'a, Self::Error> {
+		async move {
+			let client = self.client().await?;
+			// postgres doesn't short circuit `AND`, so the field is only cast once it's known to be an integer.
+			let statement = format!(
+				"UPDATE {} SET value = jsonb_set(value, $2, to_jsonb((value #>> $2)::BIGINT + $3)) \
+				 WHERE key = $1 AND CASE WHEN value #>> $2 ~ '^-?[0-9]{{1,19}}$' THEN \
+				 (value #>> $2)::NUMERIC + $3 BETWEEN -9223372036854775808 AND \
+				 9223372036854775807 ELSE FALSE END RETURNING (value #>> $2)::BIGINT",
+				self.qualified(table)
+			);
+
+			let row = client
+				.query_opt(statement.as_str(), &[&id, &field, &delta])
+				.await?;
+
+			Ok(row.map(|row| row.try_get(0)).transpose()?)
+		}
+		.boxed()
+	}
+}
+
+impl TransactionalBackend for PostgresBackend {}
+
+mod util {
+	/// Quotes an identifier, escaping any double quotes within it.
+	pub fn quote_ident(ident: &str) -> String {
+		let mut output = String::with_capacity(ident.len() + 2);
+		output.push('"');
+		for c in ident.chars() {
+			if c == '"' {
+				output.push('"');
+			}
+			output.push(c);
+		}
+		output.push('"');
+		output
+	}
+}
+
+#[cfg(all(test, not(miri)))]
+mod tests {
+	use std::fmt::Debug;
+
+	use starchart::backend::{testsuite, Backend};
+	use static_assertions::assert_impl_all;
+
+	use super::{util::quote_ident, Config, PostgresBackend, PostgresError};
+
+	assert_impl_all!(PostgresBackend: Backend, Clone, Debug, Send, Sync);
+
+	#[tokio::test]
+	#[ignore = "needs a Postgres database, connected to with the STARCHART_POSTGRES_URL environment variable"]
+	async fn testsuite() -> Result<(), PostgresError> {
+		let config = Config {
+			url: std::env::var("STARCHART_POSTGRES_URL").ok(),
+			..Config::new()
+		};
+
+		testsuite::run(PostgresBackend::from_config(
+			&config,
+			"starchart_testsuite".to_owned(),
+		)?)
+		.await
+	}
+
+	#[test]
+	fn pool_closed_on_last_drop() {
+		let mut config = Config::new();
+		config.dbname = Some("starchart".to_owned());
+		let backend = PostgresBackend::from_config(&config, "public".to_owned()).unwrap();
+		let pool = backend.pool().clone();
+		let clone = backend.clone();
+
+		drop(clone);
+		assert!(!pool.is_closed());
+
+		drop(backend);
+		assert!(pool.is_closed());
+	}
+
+	#[test]
+	fn quoting() {
+		assert_eq!(quote_ident("table"), "\"table\"");
+		assert_eq!(quote_ident("weird\"name"), "\"weird\"\"name\"");
+	}
+}
@@ -0,0 +1,58 @@
+//! The most commonly used items along with the enabled backends, to be glob imported.
+//!
+//! This includes everything in [`starchart::prelude`].
+
+#[doc(no_inline)]
+pub use starchart::prelude::*;
+
+#[cfg(feature = "cache")]
+#[doc(no_inline)]
+pub use crate::cached::{CacheLayer, CachedBackend};
+#[cfg(feature = "json")]
+#[doc(no_inline)]
+pub use crate::fs::transcoders::JsonTranscoder;
+#[cfg(feature = "toml")]
+#[doc(no_inline)]
+pub use crate::fs::transcoders::TomlTranscoder;
+#[cfg(any(feature = "toml", feature = "json"))]
+#[doc(no_inline)]
+pub use crate::fs::transcoders::TranscoderFormat;
+#[cfg(feature = "yaml")]
+#[doc(no_inline)]
+pub use crate::fs::transcoders::YamlTranscoder;
+#[cfg(feature = "binary")]
+#[doc(no_inline)]
+pub use crate::fs::transcoders::{BinaryFormat, BinaryTranscoder};
+#[cfg(feature = "encryption")]
+#[doc(no_inline)]
+pub use crate::fs::transcoders::{Cipher, EncryptedTranscoder};
+#[cfg(any(feature = "gzip", feature = "zstd"))]
+#[doc(no_inline)]
+pub use crate::fs::transcoders::{CompressedTranscoder, Compression};
+#[cfg(feature = "fs")]
+#[doc(no_inline)]
+pub use crate::fs::{FsBackend, Transcoder};
+#[cfg(feature = "grpc-server")]
+#[doc(no_inline)]
+pub use crate::grpc::service::GrpcService;
+#[cfg(feature = "grpc")]
+#[doc(no_inline)]
+pub use crate::grpc::GrpcBackend;
+#[cfg(feature = "memory")]
+#[doc(no_inline)]
+pub use crate::memory::MemoryBackend;
+#[cfg(feature = "object_store")]
+#[doc(no_inline)]
+pub use crate::object_store::ObjectStoreBackend;
+#[cfg(feature = "postgres")]
+#[doc(no_inline)]
+pub use crate::postgres::PostgresBackend;
+#[cfg(feature = "server")]
+#[doc(no_inline)]
+pub use crate::remote::server::RemoteServer;
+#[cfg(feature = "remote")]
+#[doc(no_inline)]
+pub use crate::remote::RemoteBackend;
+#[cfg(feature = "sled")]
+#[doc(no_inline)]
+pub use crate::sled::SledBackend;
@@ -0,0 +1,455 @@
+//! A backend that forwards every call over HTTP to a [`RemoteServer`], so thin clients can share one chart.
+//!
+//! Entries are sent as JSON, with table names and keys percent-encoded into the path:
+//!
+//! | Method   | Path                             | Backend method                            |
+//! |----------|----------------------------------|-------------------------------------------|
+//! | `GET`    | `/health`                        | [`health_check`]                          |
+//! | `GET`    | `/tables`                        | [`tables`]                                |
+//! | `HEAD`   | `/tables/{table}`                | [`has_table`]                             |
+//! | `PUT`    | `/tables/{table}`                | [`create_table`]                          |
+//! | `DELETE` | `/tables/{table}`                | [`delete_table`]                          |
+//! | `GET`    | `/tables/{table}/keys`           | [`get_keys`]                              |
+//! | `HEAD`   | `/tables/{table}/entries/{key}`  | [`has`]                                   |
+//! | `GET`    | `/tables/{table}/entries/{key}`  | [`get`]                                   |
+//! | `POST`   | `/tables/{table}/entries/{key}`  | [`create`]                                |
+//! | `PUT`    | `/tables/{table}/entries/{key}`  | [`update`]                                |
+//! | `DELETE` | `/tables/{table}/entries/{key}`  | [`delete`]                                |
+//!
+//! Missing tables and entries are reported with `404 Not Found`, successful writes with `204 No Content`,
+//! and errors from the server's backend with `500 Internal Server Error`, or `503 Service Unavailable` if it failed
+//! it's health check. The backend's error is only logged by the server, and never sent to the client.
+//!
+//! The server is enabled with the `server` feature.
+//!
+//! [`RemoteServer`]: self::server::RemoteServer
+//! [`health_check`]: Backend::health_check
+//! [`tables`]: Backend::tables
+//! [`has_table`]: Backend::has_table
+//! [`create_table`]: Backend::create_table
+//! [`delete_table`]: Backend::delete_table
+//! [`get_keys`]: Backend::get_keys
+//! [`has`]: Backend::has
+//! [`get`]: Backend::get
+//! [`create`]: Backend::create
+//! [`update`]: Backend::update
+//! [`delete`]: Backend::delete
+
+#[cfg(feature = "server")]
+pub mod server;
+
+use std::{
+	error::Error,
+	fmt::{Debug, Display, Formatter, Result as FmtResult},
+	iter::FromIterator,
+};
+
+use bytes::Bytes;
+use futures_util::FutureExt;
+use http_body_util::{BodyExt, Full};
+use hyper::{
+	header::CONTENT_TYPE,
+	http::{uri::InvalidUri, Error as HttpError},
+	Error as HyperError, Method, Request, StatusCode, Uri,
+};
+use hyper_util::{
+	client::legacy::{connect::HttpConnector, Client, Error as ClientError},
+	rt::TokioExecutor,
+};
+use percent_encoding::{utf8_percent_encode, NON_ALPHANUMERIC};
+use starchart::{
+	backend::{
+		futures::{
+			CreateFuture, CreateTableFuture, DeleteFuture, DeleteTableFuture, GetFuture,
+			GetKeysFuture, HasFuture, HasTableFuture, HealthCheckFuture, TablesFuture,
+			UpdateFuture,
+		},
+		Backend, CorruptEntryError,
+	},
+	Entry,
+};
+
+/// An error returned from the [`RemoteBackend`].
+#[derive(Debug)]
+pub struct RemoteError {
+	source: Option<Box<dyn Error + Send + Sync>>,
+	kind: RemoteErrorType,
+}
+
+impl RemoteError {
+	/// Immutable reference to the type of error that occurred.
+	#[must_use = "retrieving the type has no effect if left unused"]
+	pub const fn kind(&self) -> &RemoteErrorType {
+		&self.kind
+	}
+
+	/// Consume the error, returning the source error if there is any.
+	#[must_use = "consuming the error and retrieving the source has no effect if left unused"]
+	pub fn into_source(self) -> Option<Box<dyn Error + Send + Sync>> {
+		self.source
+	}
+
+	/// Consume the error, returning the owned error type and the source error.
+	#[must_use = "consuming the error into it's parts has no effect if left unused"]
+	pub fn into_parts(self) -> (RemoteErrorType, Option<Box<dyn Error + Send + Sync>>) {
+		(self.kind, self.source)
+	}
+
+	fn request<E: Error + Send + Sync + 'static>(err: E) -> Self {
+		Self {
+			source: Some(Box::new(err)),
+			kind: RemoteErrorType::Request,
+		}
+	}
+
+	fn serialization(err: serde_json::Error) -> Self {
+		Self {
+			source: Some(Box::new(err)),
+			kind: RemoteErrorType::Serialization,
+		}
+	}
+
+	fn deserialization(err: serde_json::Error) -> Self {
+		Self {
+			source: Some(Box::new(err)),
+			kind: RemoteErrorType::Deserialization,
+		}
+	}
+
+	fn corrupt(table: &str, key: &str, err: serde_json::Error) -> Self {
+		Self {
+			source: Some(Box::new(CorruptEntryError::new(table, key, err))),
+			kind: RemoteErrorType::Deserialization,
+		}
+	}
+
+	fn server(status: StatusCode, body: &[u8]) -> Self {
+		Self {
+			source: None,
+			kind: RemoteErrorType::Server {
+				status: status.as_u16(),
+				message: String::from_utf8_lossy(body).into_owned(),
+			},
+		}
+	}
+}
+
+impl Display for RemoteError {
+	fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+		match &self.kind {
+			RemoteErrorType::InvalidUrl => f.write_str("the server url was invalid"),
+			RemoteErrorType::Request => f.write_str("an error occurred sending a request"),
+			RemoteErrorType::Server { status, message } => {
+				f.write_str("the server responded with status ")?;
+				Display::fmt(status, f)?;
+
+				if !message.is_empty() {
+					f.write_str(": ")?;
+					f.write_str(message)?;
+				}
+
+				Ok(())
+			}
+			RemoteErrorType::Serialization => f.write_str("a serialization error occurred"),
+			RemoteErrorType::Deserialization => f.write_str("a deserialization error occurred"),
+		}
+	}
+}
+
+impl Error for RemoteError {
+	fn source(&self) -> Option<&(dyn Error + 'static)> {
+		self.source
+			.as_ref()
+			.map(|source| &**source as &(dyn Error + 'static))
+	}
+}
+
+impl From<InvalidUri> for RemoteError {
+	fn from(err: InvalidUri) -> Self {
+		Self {
+			source: Some(Box::new(err)),
+			kind: RemoteErrorType::InvalidUrl,
+		}
+	}
+}
+
+impl From<HttpError> for RemoteError {
+	fn from(err: HttpError) -> Self {
+		Self::request(err)
+	}
+}
+
+impl From<HyperError> for RemoteError {
+	fn from(err: HyperError) -> Self {
+		Self::request(err)
+	}
+}
+
+impl From<ClientError> for RemoteError {
+	fn from(err: ClientError) -> Self {
+		Self::request(err)
+	}
+}
+
+impl From<RemoteError> for starchart::Error {
+	fn from(e: RemoteError) -> Self {
+		Self::backend(Some(Box::new(e)))
+	}
+}
+
+/// The type of [`RemoteError`] that occurred.
+#[derive(Debug)]
+#[non_exhaustive]
+pub enum RemoteErrorType {
+	/// The url of the server was invalid.
+	InvalidUrl,
+	/// The request couldn't be sent, or the response couldn't be received.
+	Request,
+	/// The server responded with an unexpected status, such as when it's backend failed.
+	Server {
+		/// The status code of the response.
+		status: u16,
+		/// The body of the response, which is empty for backend failures.
+		message: String,
+	},
+	/// A serialization error occurred.
+	Serialization,
+	/// A response couldn't be deserialized, if it was an entry the source is a [`CorruptEntryError`].
+	Deserialization,
+}
+
+/// A backend that sends every call to a [`RemoteServer`] over HTTP.
+///
+/// Requests are made with a pooled HTTP/1 client, which must be used within a [`tokio`] runtime.
+///
+/// [`RemoteServer`]: self::server::RemoteServer
+/// [`tokio`]: https://docs.rs/tokio
+#[derive(Clone)]
+#[must_use = "a remote backend does nothing on it's own"]
+pub struct RemoteBackend {
+	client: Client<HttpConnector, Full<Bytes>>,
+	url: String,
+}
+
+impl RemoteBackend {
+	/// Creates a new [`RemoteBackend`] for the server at the given url, such as `http://localhost:8080`.
+	///
+	/// # Errors
+	///
+	/// Returns an error if the url isn't a valid absolute `http` url.
+	pub fn new(url: &str) -> Result<Self, RemoteError> {
+		let url = url.trim_end_matches('/');
+		let parsed = url.parse::<Uri>()?;
+
+		if parsed.scheme_str() != Some("http") || parsed.host().is_none() {
+			return Err(RemoteError {
+				source: None,
+				kind: RemoteErrorType::InvalidUrl,
+			});
+		}
+
+		Ok(Self {
+			client: Client::builder(TokioExecutor::new()).build_http(),
+			url: url.to_owned(),
+		})
+	}
+
+	/// The url of the server.
+	#[must_use]
+	pub fn url(&self) -> &str {
+		&self.url
+	}
+
+	fn table_path(&self, table: &str) -> String {
+		format!("{}/tables/{}", self.url, encode(table))
+	}
+
+	fn entry_path(&self, table: &str, id: &str) -> String {
+		format!("{}/entries/{}", self.table_path(table), encode(id))
+	}
+
+	async fn send(
+		&self,
+		method: Method,
+		path: String,
+		body: Option<Vec<u8>>,
+	) -> Result<(StatusCode, Bytes), RemoteError> {
+		let mut request = Request::builder().method(method).uri(path.parse::<Uri>()?);
+
+		if body.is_some() {
+			request = request.header(CONTENT_TYPE, "application/json");
+		}
+
+		let request = request.body(Full::new(Bytes::from(body.unwrap_or_default())))?;
+
+		let response = self.client.request(request).await?;
+		let status = response.status();
+		let body = response.into_body().collect().await?.to_bytes();
+
+		Ok((status, body))
+	}
+
+	/// Sends a request that's expected to respond with either `204 No Content` or `404 Not Found`,
+	/// returning whether it found what it was sent to.
+	async fn send_empty(
+		&self,
+		method: Method,
+		path: String,
+		body: Option<Vec<u8>>,
+	) -> Result<bool, RemoteError> {
+		match self.send(method, path, body).await? {
+			(StatusCode::NO_CONTENT, _) => Ok(true),
+			(StatusCode::NOT_FOUND, _) => Ok(false),
+			(status, body) => Err(RemoteError::server(status, &body)),
+		}
+	}
+
+	async fn write<S: Entry>(
+		&self,
+		method: Method,
+		table: &str,
+		id: &str,
+		value: &S,
+	) -> Result<(), RemoteError> {
+		let body = serde_json::to_vec(value).map_err(RemoteError::serialization)?;
+
+		self.send_empty(method, self.entry_path(table, id), Some(body))
+			.await
+			.map(drop)
+	}
+}
+
+impl Debug for RemoteBackend {
+	fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+		f.debug_struct("RemoteBackend")
+			.field("url", &self.url)
+			.finish_non_exhaustive()
+	}
+}
+
+impl Backend for RemoteBackend {
+	type Error = RemoteError;
+
+	fn has_table<'a>(&'a self, table: &'a str) -> HasTableFuture<'a, Self::Error> {
+		self.send_empty(Method::HEAD, self.table_path(table), None)
+			.boxed()
+	}
+
+	fn health_check(&self) -> HealthCheckFuture<'_, Self::Error> {
+		async move {
+			let path = format!("{}/health", self.url);
+
+			match self.send(Method::GET, path, None).await? {
+				(StatusCode::NO_CONTENT, _) => Ok(()),
+				(status, body) => Err(RemoteError::server(status, &body)),
+			}
+		}
+		.boxed()
+	}
+
+	fn tables<I>(&self) -> TablesFuture<'_, I, Self::Error>
+	where
+		I: FromIterator<String>,
+	{
+		async move {
+			let path = format!("{}/tables", self.url);
+
+			match self.send(Method::GET, path, None).await? {
+				(StatusCode::OK, body) => serde_json::from_slice::<Vec<String>>(&body)
+					.map(|tables| tables.into_iter().collect())
+					.map_err(RemoteError::deserialization),
+				(status, body) => Err(RemoteError::server(status, &body)),
+			}
+		}
+		.boxed()
+	}
+
+	fn create_table<'a>(&'a self, table: &'a str) -> CreateTableFuture<'a, Self::Error> {
+		self.send_empty(Method::PUT, self.table_path(table), None)
+			.map(|result| result.map(drop))
+			.boxed()
+	}
+
+	fn delete_table<'a>(&'a self, table: &'a str) -> DeleteTableFuture<'a, Self::Error> {
+		self.send_empty(Method::DELETE, self.table_path(table), None)
+			.map(|result| result.map(drop))
+			.boxed()
+	}
+
+	fn get_keys<'a, I>(&'a self, table: &'a str) -> GetKeysFuture<'a, I, Self::Error>
+	where
+		I: FromIterator<String>,
+	{
+		async move {
+			let path = format!("{}/keys", self.table_path(table));
+
+			match self.send(Method::GET, path, None).await? {
+				(StatusCode::OK, body) => serde_json::from_slice::<Vec<String>>(&body)
+					.map(|keys| keys.into_iter().collect())
+					.map_err(RemoteError::deserialization),
+				(StatusCode::NOT_FOUND, _) => Ok(I::from_iter(None)),
+				(status, body) => Err(RemoteError::server(status, &body)),
+			}
+		}
+		.boxed()
+	}
+
+	fn get<'a, D>(&'a self, table: &'a str, id: &'a str) -> GetFuture<'a, D, Self::Error>
+	where
+		D: Entry,
+	{
+		async move {
+			match self
+				.send(Method::GET, self.entry_path(table, id), None)
+				.await?
+			{
+				(StatusCode::OK, body) => serde_json::from_slice(&body)
+					.map(Some)
+					.map_err(|e| RemoteError::corrupt(table, id, e)),
+				(StatusCode::NOT_FOUND, _) => Ok(None),
+				(status, body) => Err(RemoteError::server(status, &body)),
+			}
+		}
+		.boxed()
+	}
+
+	fn has<'a>(&'a self, table: &'a str, id: &'a str) -> HasFuture<'a, Self::Error> {
+		self.send_empty(Method::HEAD, self.entry_path(table, id), None)
+			.boxed()
+	}
+
+	fn create<'a, S>(
+		&'a self,
+		table: &'a str,
+		id: &'a str,
+		value: &'a S,
+	) -> CreateFuture<'a, Self::Error>
+	where
+		S: Entry,
+	{
+		self.write(Method::POST, table, id, value).boxed()
+	}
+
+	fn update<'a, S>(
+		&'a self,
+		table: &'a str,
+		id: &'a str,
+		value: &'a S,
+	) -> UpdateFuture<'a, Self::Error>
+	where
+		S: Entry,
+	{
+		self.write(Method::PUT, table, id, value).boxed()
+	}
+
+	fn delete<'a>(&'a self, table: &'a str, id: &'a str) -> DeleteFuture<'a, Self::Error> {
+		self.send_empty(Method::DELETE, self.entry_path(table, id), None)
+			.map(|result| result.map(drop))
+			.boxed()
+	}
+}
+
+/// Percent-encodes a table name or key into a single path segment.
+fn encode(segment: &str) -> String {
+	utf8_percent_encode(segment, NON_ALPHANUMERIC).to_string()
+}
@@ -0,0 +1,553 @@
+//! The server side of the [remote] protocol, exposing any [`Backend`] over HTTP.
+//!
+//! [remote]: super
+
+use std::{
+	borrow::Cow,
+	convert::Infallible,
+	fmt::{Debug, Formatter, Result as FmtResult},
+	future::Future,
+	io::Result as IoResult,
+	sync::Arc,
+};
+
+use bytes::Bytes;
+use futures_util::future::{pending, select, Either};
+use http_body_util::{BodyExt, Full, LengthLimitError, Limited};
+use hyper::{
+	body::Incoming,
+	header::{HeaderValue, CONTENT_TYPE},
+	server::conn::http1,
+	service::service_fn,
+	Method, Request, Response, StatusCode,
+};
+use hyper_util::rt::TokioIo;
+use percent_encoding::percent_decode_str;
+use serde_json::Value;
+use starchart::backend::Backend;
+use tokio::net::TcpListener;
+
+/// The default limit on the size of request bodies, see [`RemoteServer::with_body_limit`].
+pub const DEFAULT_BODY_LIMIT: usize = 16 * 1024 * 1024;
+
+/// A check ran on every request before it's handled, see [`RemoteServer::with_interceptor`].
+pub type Interceptor = Arc<dyn Fn(&Request<Incoming>) -> Result<(), StatusCode> + Send + Sync>;
+
+/// A server that exposes a [`Backend`] to [`RemoteBackend`]s over HTTP.
+///
+/// Entries are stored as untyped JSON values, so the backend doesn't need to know the entry types
+/// of it's clients. The backend should already be initialized, and isn't shut down when the server stops.
+///
+/// The server exposes the raw [`Backend`], not a [`Starchart`]. Every request is a single backend call,
+/// requests from different clients aren't locked against each other, and indexes, expiry times, versions,
+/// hooks and the journal are only maintained by each client's own chart. Clients sharing a server
+/// don't get chart-level atomicity across each other's actions.
+///
+/// Errors from the backend are logged, and reported to clients without their message.
+///
+/// [`RemoteBackend`]: super::RemoteBackend
+/// [`Starchart`]: starchart::Starchart
+#[must_use = "a remote server does nothing until it's served"]
+pub struct RemoteServer<B> {
+	backend: Arc<B>,
+	body_limit: usize,
+	interceptor: Option<Interceptor>,
+}
+
+impl<B: Backend + 'static> RemoteServer<B> {
+	/// Creates a new [`RemoteServer`] for the given [`Backend`].
+	pub fn new(backend: B) -> Self {
+		Self::from_arc(Arc::new(backend))
+	}
+
+	/// Creates a new [`RemoteServer`] for an already shared [`Backend`].
+	pub const fn from_arc(backend: Arc<B>) -> Self {
+		Self {
+			backend,
+			body_limit: DEFAULT_BODY_LIMIT,
+			interceptor: None,
+		}
+	}
+
+	/// Sets the largest request body, in bytes, the server will read, defaults to [`DEFAULT_BODY_LIMIT`].
+	///
+	/// Larger requests are rejected with `413 Payload Too Large`.
+	pub const fn with_body_limit(mut self, limit: usize) -> Self {
+		self.body_limit = limit;
+
+		self
+	}
+
+	/// Runs a check on every request before it's handled, such as authenticating the client.
+	///
+	/// Requests the check returns an error for are rejected with that status, without reaching the backend.
+	pub fn with_interceptor<F>(mut self, interceptor: F) -> Self
+	where
+		F: Fn(&Request<Incoming>) -> Result<(), StatusCode> + Send + Sync + 'static,
+	{
+		self.interceptor = Some(Arc::new(interceptor));
+
+		self
+	}
+
+	/// Returns a reference to the underlying [`Backend`].
+	#[must_use]
+	pub fn backend(&self) -> &B {
+		&self.backend
+	}
+
+	/// Accepts connections from the listener until an error occurs.
+	///
+	/// Each connection is served on it's own [`tokio`] task.
+	///
+	/// # Errors
+	///
+	/// Returns an error if accepting a connection fails.
+	///
+	/// [`tokio`]: https://docs.rs/tokio
+	pub async fn serve(self, listener: TcpListener) -> IoResult<()> {
+		self.serve_with_shutdown(listener, pending()).await
+	}
+
+	/// Accepts connections from the listener until the `shutdown` future completes.
+	///
+	/// Connections that were already accepted are served until they're closed.
+	///
+	/// # Errors
+	///
+	/// Returns an error if accepting a connection fails.
+	pub async fn serve_with_shutdown<F>(self, listener: TcpListener, shutdown: F) -> IoResult<()>
+	where
+		F: Future<Output = ()> + Send,
+	{
+		let mut shutdown = Box::pin(shutdown);
+
+		loop {
+			let accept = Box::pin(listener.accept());
+
+			let stream = match select(accept, shutdown).await {
+				Either::Left((accepted, unfinished)) => {
+					shutdown = unfinished;
+					accepted?.0
+				}
+				Either::Right(_) => return Ok(()),
+			};
+
+			let server = self.clone();
+			let service = service_fn(move |request| {
+				let server = server.clone();
+
+				async move { Ok::<_, Infallible>(server.handle(request).await) }
+			});
+
+			tokio::spawn(async move {
+				// a client disconnecting part way through a request isn't an error for the server.
+				let _served = http1::Builder::new()
+					.serve_connection(TokioIo::new(stream), service)
+					.await;
+			});
+		}
+	}
+
+	/// Handles a single request, see the [remote] module for the routes.
+	///
+	/// [remote]: super
+	async fn handle(&self, request: Request<Incoming>) -> Response<Full<Bytes>> {
+		if let Some(Err(status_code)) = self.interceptor.as_ref().map(|check| check(&request)) {
+			return status(status_code);
+		}
+
+		let backend = &*self.backend;
+
+		if request.uri().path() == "/health" {
+			return match *request.method() {
+				Method::GET => backend.health_check().await.map_or_else(
+					|e| {
+						let mut response = internal_error(e);
+						*response.status_mut() = StatusCode::SERVICE_UNAVAILABLE;
+
+						response
+					},
+					|()| no_content(),
+				),
+				_ => status(StatusCode::METHOD_NOT_ALLOWED),
+			};
+		}
+
+		if request.uri().path() == "/tables" {
+			return match *request.method() {
+				Method::GET => backend
+					.tables::<Vec<_>>()
+					.await
+					.map_or_else(internal_error, |tables| json(&tables)),
+				_ => status(StatusCode::METHOD_NOT_ALLOWED),
+			};
+		}
+
+		let Some(segments) = request
+			.uri()
+			.path()
+			.strip_prefix("/tables/")
+			.map(|path| path.split('/').map(decode).collect::<Option<Vec<_>>>())
+		else {
+			return status(StatusCode::NOT_FOUND);
+		};
+
+		let Some(segments) = segments else {
+			return status(StatusCode::BAD_REQUEST);
+		};
+
+		let method = request.method().clone();
+
+		let result = match (segments.as_slice(), method) {
+			([table], Method::HEAD) => backend.has_table(table).await.map(found),
+			([table], Method::PUT) => backend.create_table(table).await.map(|()| no_content()),
+			([table], Method::DELETE) => backend.delete_table(table).await.map(|()| no_content()),
+			([table, keys], Method::GET) if keys == "keys" => get_keys(backend, table).await,
+			([table, entries, key], method) if entries == "entries" => match method {
+				Method::HEAD => backend.has(table, key).await.map(found),
+				Method::GET => backend.get::<Value>(table, key).await.map(|entry| {
+					entry.map_or_else(|| status(StatusCode::NOT_FOUND), |entry| json(&entry))
+				}),
+				Method::POST | Method::PUT => {
+					let entry = match read_json(request, self.body_limit).await {
+						Ok(entry) => entry,
+						Err(response) => return response,
+					};
+
+					if method == Method::POST {
+						backend.create(table, key, &entry).await
+					} else {
+						backend.update(table, key, &entry).await
+					}
+					.map(|()| no_content())
+				}
+				Method::DELETE => backend.delete(table, key).await.map(|()| no_content()),
+				_ => Ok(status(StatusCode::METHOD_NOT_ALLOWED)),
+			},
+			([_], _) => Ok(status(StatusCode::METHOD_NOT_ALLOWED)),
+			_ => Ok(status(StatusCode::NOT_FOUND)),
+		};
+
+		result.unwrap_or_else(internal_error)
+	}
+}
+
+impl<B> Clone for RemoteServer<B> {
+	fn clone(&self) -> Self {
+		Self {
+			backend: self.backend.clone(),
+			body_limit: self.body_limit,
+			interceptor: self.interceptor.clone(),
+		}
+	}
+}
+
+impl<B: Debug> Debug for RemoteServer<B> {
+	fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+		f.debug_struct("RemoteServer")
+			.field("backend", &self.backend)
+			.field("body_limit", &self.body_limit)
+			.field("interceptor", &self.interceptor.is_some())
+			.finish()
+	}
+}
+
+/// Logs the backend's error, responding without it's message so backend details aren't sent to clients.
+fn internal_error<E: std::error::Error>(e: E) -> Response<Full<Bytes>> {
+	log::error!("the remote server's backend failed: {e}");
+
+	status(StatusCode::INTERNAL_SERVER_ERROR)
+}
+
+async fn get_keys<B: Backend>(backend: &B, table: &str) -> Result<Response<Full<Bytes>>, B::Error> {
+	if !backend.has_table(table).await? {
+		return Ok(status(StatusCode::NOT_FOUND));
+	}
+
+	let keys = backend.get_keys::<Vec<_>>(table).await?;
+
+	Ok(json(&keys))
+}
+
+async fn read_json(
+	request: Request<Incoming>,
+	limit: usize,
+) -> Result<Value, Response<Full<Bytes>>> {
+	let body = Limited::new(request.into_body(), limit)
+		.collect()
+		.await
+		.map_err(|e| {
+			status(if e.downcast_ref::<LengthLimitError>().is_some() {
+				StatusCode::PAYLOAD_TOO_LARGE
+			} else {
+				StatusCode::BAD_REQUEST
+			})
+		})?
+		.to_bytes();
+
+	serde_json::from_slice(&body).map_err(|_| status(StatusCode::BAD_REQUEST))
+}
+
+fn decode(segment: &str) -> Option<String> {
+	percent_decode_str(segment)
+		.decode_utf8()
+		.ok()
+		.map(Cow::into_owned)
+}
+
+fn json<T: serde::Serialize + ?Sized>(value: &T) -> Response<Full<Bytes>> {
+	serde_json::to_vec(value).map_or_else(
+		|_| status(StatusCode::INTERNAL_SERVER_ERROR),
+		|body| {
+			let mut response = Response::new(Full::new(Bytes::from(body)));
+			response
+				.headers_mut()
+				.insert(CONTENT_TYPE, HeaderValue::from_static("application/json"));
+
+			response
+		},
+	)
+}
+
+fn found(found: bool) -> Response<Full<Bytes>> {
+	if found {
+		no_content()
+	} else {
+		status(StatusCode::NOT_FOUND)
+	}
+}
+
+fn no_content() -> Response<Full<Bytes>> {
+	status(StatusCode::NO_CONTENT)
+}
+
+fn status(status: StatusCode) -> Response<Full<Bytes>> {
+	let mut response = Response::new(Full::default());
+	*response.status_mut() = status;
+
+	response
+}
+
+#[cfg(all(test, feature = "memory"))]
+mod tests {
+	use std::fmt::Debug;
+
+	use hyper::{header::AUTHORIZATION, StatusCode};
+	use starchart::{
+		backend::{testsuite, Backend},
+		Error,
+	};
+	use static_assertions::assert_impl_all;
+	use tokio::net::TcpListener;
+
+	use super::RemoteServer;
+	use crate::{
+		memory::MemoryBackend,
+		remote::{RemoteBackend, RemoteError, RemoteErrorType},
+		testing::TestSettings,
+	};
+
+	assert_impl_all!(RemoteBackend: Backend, Clone, Debug, Send, Sync);
+	assert_impl_all!(RemoteServer<MemoryBackend>: Clone, Debug, Send, Sync);
+
+	async fn spawn() -> Result<RemoteBackend, Error> {
+		spawn_server(RemoteServer::new(MemoryBackend::new())).await
+	}
+
+	async fn spawn_server(server: RemoteServer<MemoryBackend>) -> Result<RemoteBackend, Error> {
+		let listener = TcpListener::bind("127.0.0.1:0")
+			.await
+			.map_err(|e| Error::backend(Some(Box::new(e))))?;
+		let addr = listener
+			.local_addr()
+			.map_err(|e| Error::backend(Some(Box::new(e))))?;
+
+		tokio::spawn(server.serve(listener));
+
+		Ok(RemoteBackend::new(&format!("http://{addr}/"))?)
+	}
+
+	#[tokio::test]
+	async fn testsuite() -> Result<(), Error> {
+		let backend = spawn().await?;
+
+		Ok(testsuite::run(backend).await?)
+	}
+
+	#[tokio::test]
+	async fn health_check() -> Result<(), Error> {
+		let backend = spawn().await?;
+
+		backend.health_check().await?;
+
+		// nothing is listening once the listener is dropped.
+		let addr = TcpListener::bind("127.0.0.1:0")
+			.await
+			.and_then(|listener| listener.local_addr())
+			.map_err(|e| Error::backend(Some(Box::new(e))))?;
+		let unreachable = RemoteBackend::new(&format!("http://{addr}/"))?;
+
+		assert!(unreachable.health_check().await.is_err());
+
+		Ok(())
+	}
+
+	#[tokio::test]
+	async fn timeout() -> Result<(), Error> {
+		use std::time::Duration;
+
+		use starchart::{
+			action::{ActionErrorType, ReadEntryAction},
+			backend::TimeoutBackend,
+			error::ErrorType,
+			timer::FnTimer,
+			Starchart,
+		};
+
+		// the listener accepts connections, but never responds to them.
+		let listener = TcpListener::bind("127.0.0.1:0")
+			.await
+			.map_err(|e| Error::backend(Some(Box::new(e))))?;
+		let addr = listener
+			.local_addr()
+			.map_err(|e| Error::backend(Some(Box::new(e))))?;
+		let hung = RemoteBackend::new(&format!("http://{addr}/"))?;
+
+		let backend = TimeoutBackend::new(hung, Duration::from_millis(50)).with_timer(
+			FnTimer::new(|duration| Box::pin(tokio::time::sleep(duration))),
+		);
+		assert!(backend.has_table("table").await.unwrap_err().is_elapsed());
+
+		let chart = Starchart::new(backend).await?;
+		let mut action = ReadEntryAction::<TestSettings>::new();
+		action.set_table("table").set_key(&1_u32);
+		let err = action.run_read_entry(&chart).await.unwrap_err();
+		assert!(matches!(err.kind(), ActionErrorType::Timeout));
+
+		let err = Error::from(err);
+		assert!(matches!(err.kind(), ErrorType::Timeout));
+
+		drop(listener);
+
+		Ok(())
+	}
+
+	#[tokio::test]
+	async fn table_methods() -> Result<(), Error> {
+		let backend = spawn().await?;
+
+		assert!(!backend.has_table("a table").await?);
+
+		backend.create_table("a table").await?;
+
+		assert!(backend.has_table("a table").await?);
+
+		backend.delete_table("a table").await?;
+
+		assert!(!backend.has_table("a table").await?);
+
+		backend.create_table("a table").await?;
+		backend.create_table("another table").await?;
+
+		let mut tables: Vec<String> = backend.tables().await?;
+		tables.sort();
+		assert_eq!(tables, ["a table", "another table"]);
+
+		Ok(())
+	}
+
+	#[tokio::test]
+	async fn entries() -> Result<(), Error> {
+		let backend = spawn().await?;
+
+		assert!(backend.get_keys::<Vec<_>>("table").await?.is_empty());
+
+		backend.create_table("table").await?;
+
+		let mut settings = TestSettings::default();
+		backend.create("table", "1", &settings).await?;
+		settings.id = 2;
+		settings.opt = None;
+		backend.create("table", "../2", &settings).await?;
+
+		let mut keys: Vec<String> = backend.get_keys("table").await?;
+		keys.sort();
+		assert_eq!(keys, ["../2", "1"]);
+
+		assert_eq!(
+			backend.get::<TestSettings>("table", "../2").await?,
+			Some(settings.clone())
+		);
+		assert_eq!(backend.get::<TestSettings>("table", "3").await?, None);
+
+		settings.value = "updated".to_owned();
+		backend.update("table", "../2", &settings).await?;
+		assert_eq!(
+			backend.get::<TestSettings>("table", "../2").await?,
+			Some(settings)
+		);
+
+		backend.delete("table", "1").await?;
+		assert!(!backend.has("table", "1").await?);
+		assert!(backend.has("table", "../2").await?);
+
+		let error = backend.get::<u32>("table", "../2").await.unwrap_err();
+		assert!(matches!(error.kind(), RemoteErrorType::Deserialization));
+
+		Ok(())
+	}
+
+	#[tokio::test]
+	async fn body_limit() -> Result<(), Error> {
+		let backend =
+			spawn_server(RemoteServer::new(MemoryBackend::new()).with_body_limit(16)).await?;
+
+		backend.create_table("table").await?;
+		backend.create("table", "1", &1_u32).await?;
+
+		let error = backend
+			.create("table", "2", &TestSettings::default())
+			.await
+			.unwrap_err();
+		assert!(matches!(
+			error.kind(),
+			RemoteErrorType::Server { status, .. } if *status == StatusCode::PAYLOAD_TOO_LARGE.as_u16()
+		));
+		assert!(!backend.has("table", "2").await?);
+
+		Ok(())
+	}
+
+	#[tokio::test]
+	async fn interceptor() -> Result<(), Error> {
+		let backend = spawn_server(RemoteServer::new(MemoryBackend::new()).with_interceptor(
+			|request| {
+				if request.headers().contains_key(AUTHORIZATION) {
+					Ok(())
+				} else {
+					Err(StatusCode::UNAUTHORIZED)
+				}
+			},
+		))
+		.await?;
+
+		let error = backend.create_table("table").await.unwrap_err();
+		assert!(matches!(
+			error.kind(),
+			RemoteErrorType::Server { status, .. } if *status == StatusCode::UNAUTHORIZED.as_u16()
+		));
+
+		Ok(())
+	}
+
+	#[test]
+	fn invalid_url() {
+		for url in ["not a url", "ftp://localhost", "/tables"] {
+			assert!(matches!(
+				RemoteBackend::new(url).map_err(RemoteError::into_parts),
+				Err((RemoteErrorType::InvalidUrl, _))
+			));
+		}
+	}
+}
@@ -0,0 +1,634 @@
+//! A [`sled`] based backend, an embedded and crash-safe key-value store.
+//!
+//! [`sled`]: sled_db
+
+use std::{
+	error::Error,
+	fmt::{Display, Formatter, Result as FmtResult},
+	iter::FromIterator,
+	ops::Bound,
+	path::Path,
+	string::FromUtf8Error,
+};
+
+use futures_util::{
+	future::{ok, ready},
+	FutureExt,
+};
+use sled_db::{Config, Db, Error as DbError, Tree};
+use starchart::{
+	backend::{
+		futures::{
+			CreateFuture, CreateTableFuture, DeleteFuture, DeleteTableFuture, GetFuture,
+			GetKeysFuture, GetKeysInRangeFuture, GetPageFuture, HasFuture, HasTableFuture,
+			RenameTableFuture, ShutdownFuture, TablesFuture, TruncateTableFuture, UpdateFuture,
+		},
+		Backend, CorruptEntryError,
+	},
+	query::PageRange,
+	transaction::TransactionalBackend,
+	Entry,
+};
+
+/// The name of the tree every sled database has, which isn't a table.
+const DEFAULT_TREE: &[u8] = b"__sled__default";
+
+/// An error returned from the [`SledBackend`].
+#[derive(Debug)]
+pub struct SledError {
+	source: Option<Box<dyn Error + Send + Sync>>,
+	kind: SledErrorType,
+}
+
+impl SledError {
+	/// Immutable reference to the type of error that occurred.
+	#[must_use = "retrieving the type has no effect if left unused"]
+	pub const fn kind(&self) -> &SledErrorType {
+		&self.kind
+	}
+
+	/// Consume the error, returning the source error if there is any.
+	#[must_use = "consuming the error and retrieving the source has no effect if left unused"]
+	pub fn into_source(self) -> Option<Box<dyn Error + Send + Sync>> {
+		self.source
+	}
+
+	/// Consume the error, returning the owned error type and the source error.
+	#[must_use = "consuming the error into it's parts has no effect if left unused"]
+	pub fn into_parts(self) -> (SledErrorType, Option<Box<dyn Error + Send + Sync>>) {
+		(self.kind, self.source)
+	}
+
+	fn serialization(err: serde_cbor::Error) -> Self {
+		Self {
+			source: Some(Box::new(err)),
+			kind: SledErrorType::Serialization,
+		}
+	}
+
+	fn deserialization(table: &str, key: &str, err: serde_cbor::Error) -> Self {
+		Self {
+			source: Some(Box::new(CorruptEntryError::new(table, key, err))),
+			kind: SledErrorType::Deserialization,
+		}
+	}
+}
+
+impl Display for SledError {
+	fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+		match &self.kind {
+			SledErrorType::Database => f.write_str("an error occurred within the database"),
+			SledErrorType::Serialization => f.write_str("a serialization error occurred"),
+			SledErrorType::Deserialization => f.write_str("a deserialization error occurred"),
+			SledErrorType::InvalidKey => f.write_str("a stored key was not valid UTF-8"),
+		}
+	}
+}
+
+impl Error for SledError {
+	fn source(&self) -> Option<&(dyn Error + 'static)> {
+		self.source
+			.as_ref()
+			.map(|source| &**source as &(dyn Error + 'static))
+	}
+}
+
+impl From<DbError> for SledError {
+	fn from(err: DbError) -> Self {
+		Self {
+			source: Some(Box::new(err)),
+			kind: SledErrorType::Database,
+		}
+	}
+}
+
+impl From<FromUtf8Error> for SledError {
+	fn from(err: FromUtf8Error) -> Self {
+		Self {
+			source: Some(Box::new(err)),
+			kind: SledErrorType::InvalidKey,
+		}
+	}
+}
+
+impl From<SledError> for starchart::Error {
+	fn from(e: SledError) -> Self {
+		Self::backend(Some(Box::new(e)))
+	}
+}
+
+/// The type of [`SledError`] that occurred.
+#[derive(Debug)]
+#[allow(missing_copy_implementations)]
+#[non_exhaustive]
+pub enum SledErrorType {
+	/// An error occurred within the database.
+	Database,
+	/// A serialization error occurred.
+	Serialization,
+	/// An entry couldn't be deserialized, the source is a [`CorruptEntryError`].
+	Deserialization,
+	/// A key stored in the database was not valid UTF-8.
+	InvalidKey,
+}
+
+/// A [`sled`] backend, using a [`Tree`] for each table.
+///
+/// Entries are stored individually as CBOR, so writes only touch the entry being written.
+///
+/// [`sled`]: sled_db
+#[derive(Debug, Clone)]
+#[must_use = "a sled backend does nothing on it's own"]
+pub struct SledBackend {
+	db: Db,
+}
+
+impl SledBackend {
+	/// Creates a new [`SledBackend`] from an already opened [`Db`].
+	pub const fn new(db: Db) -> Self {
+		Self { db }
+	}
+
+	/// Opens (or creates) the database at the given path.
+	///
+	/// # Errors
+	///
+	/// Returns an error if the database could not be opened.
+	pub fn open<P: AsRef<Path>>(path: P) -> Result<Self, SledError> {
+		Ok(Self::new(sled_db::open(path)?))
+	}
+
+	/// Creates a temporary database that is removed when the backend is dropped.
+	///
+	/// # Errors
+	///
+	/// Returns an error if the database could not be created.
+	pub fn temporary() -> Result<Self, SledError> {
+		Ok(Self::new(Config::new().temporary(true).open()?))
+	}
+
+	/// Returns a reference to the underlying [`Db`].
+	#[must_use]
+	pub const fn db(&self) -> &Db {
+		&self.db
+	}
+
+	fn tree(&self, table: &str) -> Result<Option<Tree>, SledError> {
+		if self.contains_tree(table) {
+			Ok(Some(self.db.open_tree(table)?))
+		} else {
+			Ok(None)
+		}
+	}
+
+	fn contains_tree(&self, table: &str) -> bool {
+		self.db
+			.tree_names()
+			.iter()
+			.any(|name| name.as_ref() == table.as_bytes())
+	}
+
+	fn insert<S: Entry>(&self, table: &str, id: &str, value: &S) -> Result<(), SledError> {
+		if let Some(tree) = self.tree(table)? {
+			let serialized = serde_cbor::to_vec(value).map_err(SledError::serialization)?;
+			tree.insert(id, serialized)?;
+		}
+
+		Ok(())
+	}
+}
+
+impl Backend for SledBackend {
+	type Error = SledError;
+
+	unsafe fn shutdown(&self) -> ShutdownFuture<'_> {
+		// there's nothing to be done if flushing fails, sled will recover on the next open.
+		let _flushed = self.db.flush();
+
+		ready(()).boxed()
+	}
+
+	fn entry_size<S: Entry>(&self, _: &str, _: &str, entry: &S) -> Option<usize> {
+		serde_cbor::to_vec(entry)
+			.ok()
+			.map(|serialized| serialized.len())
+	}
+
+	fn has_table<'a>(&'a self, table: &'a str) -> HasTableFuture<'a, Self::Error> {
+		ok(self.contains_tree(table)).boxed()
+	}
+
+	fn tables<I>(&self) -> TablesFuture<'_, I, Self::Error>
+	where
+		I: FromIterator<String>,
+	{
+		async move {
+			self.db
+				.tree_names()
+				.into_iter()
+				.filter(|name| name.as_ref() != DEFAULT_TREE)
+				.map(|name| Ok(String::from_utf8(name.to_vec())?))
+				.collect()
+		}
+		.boxed()
+	}
+
+	fn create_table<'a>(&'a self, table: &'a str) -> CreateTableFuture<'a, Self::Error> {
+		ready(self.db.open_tree(table).map(|_| ()).map_err(Into::into)).boxed()
+	}
+
+	fn delete_table<'a>(&'a self, table: &'a str) -> DeleteTableFuture<'a, Self::Error> {
+		ready(self.db.drop_tree(table).map(|_| ()).map_err(Into::into)).boxed()
+	}
+
+	fn rename_table<'a>(
+		&'a self,
+		table: &'a str,
+		new: &'a str,
+	) -> RenameTableFuture<'a, Self::Error> {
+		async move {
+			let renamed = self.db.open_tree(new)?;
+
+			// entries are copied as they're stored, rather than being deserialized and serialized again.
+			if let Some(tree) = self.tree(table)? {
+				for entry in &tree {
+					let (key, value) = entry?;
+					renamed.insert(key, value)?;
+				}
+			}
+
+			self.db.drop_tree(table)?;
+
+			Ok(())
+		}
+		.boxed()
+	}
+
+	fn truncate_table<'a>(&'a self, table: &'a str) -> TruncateTableFuture<'a, Self::Error> {
+		async move {
+			if let Some(tree) = self.tree(table)? {
+				tree.clear()?;
+			}
+
+			Ok(())
+		}
+		.boxed()
+	}
+
+	fn get_keys<'a, I>(&'a self, table: &'a str) -> GetKeysFuture<'a, I, Self::Error>
+	where
+		I: FromIterator<String>,
+	{
+		async move {
+			self.tree(table)?
+				.into_iter()
+				.flat_map(|tree| tree.iter().keys())
+				.map(|key| Ok(String::from_utf8(key?.to_vec())?))
+				.collect()
+		}
+		.boxed()
+	}
+
+	fn get_keys_in_range<'a, I>(
+		&'a self,
+		table: &'a str,
+		range: &'a PageRange,
+	) -> GetKeysInRangeFuture<'a, I, Self::Error>
+	where
+		I: FromIterator<String>,
+	{
+		async move {
+			let Some(tree) = self.tree(table)? else {
+				return Ok(I::from_iter(None));
+			};
+
+			tree.range::<&[u8], _>((range.lower_bound().map(str::as_bytes), Bound::Unbounded))
+				.keys()
+				.map(|key| Ok(String::from_utf8(key?.to_vec())?))
+				.take_while(|key| key.as_ref().map_or(true, |key| !range.is_past(key)))
+				.filter(|key| key.as_ref().map_or(true, |key| range.includes(key)))
+				.skip(range.offset)
+				.take(range.limit.unwrap_or(usize::MAX))
+				.collect()
+		}
+		.boxed()
+	}
+
+	fn get<'a, D>(&'a self, table: &'a str, id: &'a str) -> GetFuture<'a, D, Self::Error>
+	where
+		D: Entry,
+	{
+		async move {
+			match self.tree(table)? {
+				Some(tree) => tree
+					.get(id)?
+					.map(|raw| {
+						serde_cbor::from_slice(&raw)
+							.map_err(|e| SledError::deserialization(table, id, e))
+					})
+					.transpose(),
+				None => Ok(None),
+			}
+		}
+		.boxed()
+	}
+
+	fn get_page<'a, D, I>(
+		&'a self,
+		table: &'a str,
+		range: &'a PageRange,
+	) -> GetPageFuture<'a, I, Self::Error>
+	where
+		D: Entry,
+		I: FromIterator<D>,
+	{
+		async move {
+			let Some(tree) = self.tree(table)? else {
+				return Ok(I::from_iter(None));
+			};
+
+			tree.range::<&[u8], _>((range.lower_bound().map(str::as_bytes), Bound::Unbounded))
+				.take_while(|pair| {
+					pair.as_ref().map_or(true, |(key, _)| {
+						!range.is_past(&String::from_utf8_lossy(key))
+					})
+				})
+				.filter(|pair| {
+					pair.as_ref().map_or(true, |(key, _)| {
+						range.includes(&String::from_utf8_lossy(key))
+					})
+				})
+				.skip(range.offset)
+				.take(range.limit.unwrap_or(usize::MAX))
+				.map(|pair| {
+					let (key, raw) = pair?;
+					serde_cbor::from_slice(&raw).map_err(|e| {
+						SledError::deserialization(table, &String::from_utf8_lossy(&key), e)
+					})
+				})
+				.collect()
+		}
+		.boxed()
+	}
+
+	fn has<'a>(&'a self, table: &'a str, id: &'a str) -> HasFuture<'a, Self::Error> {
+		let result = self.tree(table).and_then(|tree| match tree {
+			Some(tree) => Ok(tree.contains_key(id)?),
+			None => Ok(false),
+		});
+
+		ready(result).boxed()
+	}
+
+	fn create<'a, S>(
+		&'a self,
+		table: &'a str,
+		id: &'a str,
+		value: &'a S,
+	) -> CreateFuture<'a, Self::Error>
+	where
+		S: Entry,
+	{
+		ready(self.insert(table, id, value)).boxed()
+	}
+
+	fn update<'a, S>(
+		&'a self,
+		table: &'a str,
+		id: &'a str,
+		value: &'a S,
+	) -> UpdateFuture<'a, Self::Error>
+	where
+		S: Entry,
+	{
+		ready(self.insert(table, id, value)).boxed()
+	}
+
+	fn delete<'a>(&'a self, table: &'a str, id: &'a str) -> DeleteFuture<'a, Self::Error> {
+		let result = self.tree(table).and_then(|tree| {
+			if let Some(tree) = tree {
+				tree.remove(id)?;
+			}
+
+			Ok(())
+		});
+
+		ready(result).boxed()
+	}
+}
+
+impl TransactionalBackend for SledBackend {}
+
+#[cfg(all(test, not(miri)))]
+mod tests {
+	use std::{fmt::Debug, ops::Bound};
+
+	use starchart::{
+		backend::{testsuite, Backend},
+		query::PageRange,
+	};
+	use static_assertions::assert_impl_all;
+
+	use super::{SledBackend, SledError};
+	use crate::testing::TestSettings;
+
+	assert_impl_all!(SledBackend: Backend, Clone, Debug, Send, Sync);
+
+	#[tokio::test]
+	async fn testsuite() -> Result<(), SledError> {
+		testsuite::run(SledBackend::temporary()?).await
+	}
+
+	#[tokio::test]
+	async fn table_methods() -> Result<(), SledError> {
+		let backend = SledBackend::temporary()?;
+
+		backend.init().await?;
+
+		assert!(!backend.has_table("table").await?);
+
+		backend.create_table("table").await?;
+
+		assert!(backend.has_table("table").await?);
+		assert_eq!(backend.tables::<Vec<_>>().await?, ["table"]);
+
+		backend.delete_table("table").await?;
+
+		assert!(!backend.has_table("table").await?);
+		assert!(backend.tables::<Vec<String>>().await?.is_empty());
+
+		Ok(())
+	}
+
+	#[tokio::test]
+	async fn rename_and_truncate_table() -> Result<(), SledError> {
+		let backend = SledBackend::temporary()?;
+
+		backend.init().await?;
+
+		backend.create_table("table").await?;
+		backend
+			.create("table", "1", &TestSettings::default())
+			.await?;
+
+		backend.rename_table("table", "renamed").await?;
+
+		assert!(!backend.has_table("table").await?);
+		assert_eq!(
+			backend.get::<TestSettings>("renamed", "1").await?,
+			Some(TestSettings::default())
+		);
+
+		backend.truncate_table("renamed").await?;
+
+		assert!(backend.has_table("renamed").await?);
+		assert!(backend.get_keys::<Vec<String>>("renamed").await?.is_empty());
+
+		Ok(())
+	}
+
+	#[tokio::test]
+	async fn get_keys() -> Result<(), SledError> {
+		let backend = SledBackend::temporary()?;
+
+		backend.init().await?;
+		backend.create_table("table").await?;
+
+		let mut settings = TestSettings::default();
+		backend.create("table", "1", &settings).await?;
+		settings.id = 2;
+		settings.opt = None;
+		backend.create("table", "2", &settings).await?;
+
+		let mut keys: Vec<String> = backend.get_keys("table").await?;
+
+		let mut expected = vec!["1".to_owned(), "2".to_owned()];
+
+		keys.sort();
+		expected.sort();
+
+		assert_eq!(keys, expected);
+
+		Ok(())
+	}
+
+	#[tokio::test]
+	async fn get_and_create() -> Result<(), SledError> {
+		let backend = SledBackend::temporary()?;
+
+		backend.init().await?;
+		backend.create_table("table").await?;
+
+		backend
+			.create("table", "1", &TestSettings::default())
+			.await?;
+
+		assert_eq!(
+			backend.get::<TestSettings>("table", "1").await?,
+			Some(TestSettings::default())
+		);
+
+		assert_eq!(backend.get::<TestSettings>("table", "2").await?, None);
+
+		assert_eq!(backend.get::<TestSettings>("missing", "1").await?, None);
+
+		assert!(!backend.has_table("missing").await?);
+
+		Ok(())
+	}
+
+	#[tokio::test]
+	async fn update_and_delete() -> Result<(), SledError> {
+		let backend = SledBackend::temporary()?;
+
+		backend.init().await?;
+		backend.create_table("table").await?;
+
+		let mut settings = TestSettings::default();
+		backend.create("table", "1", &settings).await?;
+
+		settings.opt = None;
+
+		backend.update("table", "1", &settings).await?;
+
+		assert_eq!(
+			backend.get::<TestSettings>("table", "1").await?,
+			Some(settings)
+		);
+
+		backend.delete("table", "1").await?;
+
+		assert_eq!(backend.get::<TestSettings>("table", "1").await?, None);
+
+		Ok(())
+	}
+
+	#[tokio::test]
+	async fn get_page() -> Result<(), SledError> {
+		let backend = SledBackend::temporary()?;
+
+		backend.init().await?;
+		backend.create_table("table").await?;
+
+		for id in 1..=5 {
+			let settings = TestSettings {
+				id,
+				..TestSettings::default()
+			};
+			backend.create("table", &id.to_string(), &settings).await?;
+		}
+
+		let range = PageRange {
+			after: Some("1".to_owned()),
+			offset: 1,
+			limit: Some(2),
+			..PageRange::new()
+		};
+
+		let page: Vec<TestSettings> = backend.get_page("table", &range).await?;
+
+		assert_eq!(
+			page.iter().map(|settings| settings.id).collect::<Vec<_>>(),
+			[3, 4]
+		);
+
+		Ok(())
+	}
+
+	#[tokio::test]
+	async fn get_keys_in_range() -> Result<(), SledError> {
+		let backend = SledBackend::temporary()?;
+
+		backend.init().await?;
+		backend.create_table("table").await?;
+
+		for key in ["post:1", "user:1", "user:2", "user:3", "video:1"] {
+			backend
+				.create("table", key, &TestSettings::default())
+				.await?;
+		}
+
+		let range = PageRange {
+			prefix: Some("user:".to_owned()),
+			..PageRange::new()
+		};
+		let keys: Vec<String> = backend.get_keys_in_range("table", &range).await?;
+		assert_eq!(keys, ["user:1", "user:2", "user:3"]);
+
+		let range = PageRange {
+			start: Bound::Excluded("post:1".to_owned()),
+			end: Bound::Included("user:2".to_owned()),
+			offset: 1,
+			..PageRange::new()
+		};
+		let keys: Vec<String> = backend.get_keys_in_range("table", &range).await?;
+		assert_eq!(keys, ["user:2"]);
+
+		let keys: Vec<String> = backend
+			.get_keys_in_range("missing", &PageRange::new())
+			.await?;
+		assert!(keys.is_empty());
+
+		Ok(())
+	}
+}
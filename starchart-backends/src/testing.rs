@@ -7,6 +7,7 @@ use std::{
 };
 
 use serde::{Deserialize, Serialize};
+use starchart::IndexEntry;
 #[cfg(all(feature = "fs", not(miri)))]
 use tokio::sync::Mutex;
 
@@ -68,3 +69,11 @@ impl Default for TestSettings {
 		}
 	}
 }
+
+impl IndexEntry for TestSettings {
+	type Key = u32;
+
+	fn key(&self) -> &Self::Key {
+		&self.id
+	}
+}
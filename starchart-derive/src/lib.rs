@@ -14,12 +14,36 @@
 
 const KEY_IDENT: &str = "key";
 const ID_IDENT: &str = "id";
+const INDEX_IDENT: &str = "index";
+const WITH_IDENT: &str = "with";
+const FORMAT_IDENT: &str = "format";
+const PATH_IDENT: &str = "path";
+const ENTRY_IDENT: &str = "entry";
+const TABLE_IDENT: &str = "table";
+
+mod patch;
 
 use proc_macro2::TokenStream;
 use quote::{quote, quote_spanned};
-use syn::{parse_macro_input, spanned::Spanned, Data, DeriveInput, Error, Field, Fields, Result};
+use syn::{
+	parse_macro_input, spanned::Spanned, Attribute, Data, DeriveInput, Error, ExprPath, Field,
+	Fields, Ident, Lit, LitStr, Meta, NestedMeta, Result,
+};
+
+/// How the key field is turned into a key string, set with `#[key(with = "...")]` or `#[key(format = "...")]`.
+enum KeyEncoding {
+	With(ExprPath),
+	Format(LitStr),
+}
 
-#[proc_macro_derive(IndexEntry, attributes(key))]
+/// The options set in a `#[key(...)]` attribute.
+#[derive(Default)]
+struct KeyOptions {
+	encoding: Option<KeyEncoding>,
+	path: Option<LitStr>,
+}
+
+#[proc_macro_derive(IndexEntry, attributes(entry, key, index))]
 pub fn derive_entity(input: proc_macro::TokenStream) -> proc_macro::TokenStream {
 	let input = parse_macro_input!(input as DeriveInput);
 	parse(&input)
@@ -27,6 +51,14 @@ pub fn derive_entity(input: proc_macro::TokenStream) -> proc_macro::TokenStream
 		.into()
 }
 
+#[proc_macro_derive(EntryPatch, attributes(patch))]
+pub fn derive_patch(input: proc_macro::TokenStream) -> proc_macro::TokenStream {
+	let input = parse_macro_input!(input as DeriveInput);
+	patch::parse(&input)
+		.unwrap_or_else(|err| err.to_compile_error())
+		.into()
+}
+
 fn parse(input: &DeriveInput) -> Result<TokenStream> {
 	let ident = input.ident.clone();
 
@@ -52,12 +84,7 @@ fn parse(input: &DeriveInput) -> Result<TokenStream> {
 
 	let fields = named_fields.into_iter().cloned().collect::<Vec<_>>();
 
-	let id_field = get_id_field(&fields).ok_or_else(|| {
-		Error::new_spanned(
-			&input,
-			"Expected a #[key] attribute or a field named `key` or `id`.",
-		)
-	})?;
+	let (id_field, nested, encoding) = get_key(input, &fields)?;
 
 	let id_ident = id_field
 		.ident
@@ -68,24 +95,128 @@ fn parse(input: &DeriveInput) -> Result<TokenStream> {
 
 	let id_span = id_field.span();
 
+	let (key_type, key_fn, key_wrapper) = if nested.is_empty() && encoding.is_none() {
+		(quote! { #id_type }, quote! { &self.#id_ident }, quote! {})
+	} else {
+		wrap_key(input, id_field, id_ident, &nested, encoding)
+	};
+
+	let indexes_fn = get_indexes_fn(&fields)?;
+
+	let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+
+	let table = get_table(input)?.map(|table| {
+		let vis = &input.vis;
+
+		quote! {
+			#[automatically_derived]
+			impl #impl_generics #ident #ty_generics #where_clause {
+				/// The name of the table these entries are stored in.
+				#vis const TABLE: &'static str = #table;
+
+				/// Creates an [`Action`](::starchart::Action) over [`Self::TABLE`].
+				#vis const fn action<Operation, Target>() -> ::starchart::Action<'static, Self, Operation, Target> {
+					::starchart::Action::for_table(Self::TABLE)
+				}
+			}
+		}
+	});
+
+	// checked separately from the impl, so missing traits are reported on the struct rather than the key field.
+	// generic entries are only entries for some parameters, so their impl is bounded on being one instead.
+	let (entry_check, entry_bound) = if input.generics.params.is_empty() {
+		let check = quote_spanned! {ident.span()=>
+			const _: fn() = {
+				fn assert_entry<T: ::starchart::Entry>() {}
+
+				assert_entry::<#ident>
+			};
+		};
+
+		(check, quote! { #where_clause })
+	} else {
+		let predicates = where_clause.map(|clause| &clause.predicates);
+
+		(
+			quote! {},
+			quote! { where Self: ::starchart::Entry, #predicates },
+		)
+	};
+
 	let implementation = quote_spanned! {id_span=>
+		#key_wrapper
+
 		#[automatically_derived]
-		impl ::starchart::IndexEntry for #ident {
-			type Key = #id_type;
+		impl #impl_generics ::starchart::IndexEntry for #ident #ty_generics #entry_bound {
+			type Key = #key_type;
 
 			fn key(&self) -> &Self::Key {
-				&self.#id_ident
+				#key_fn
 			}
+
+			#indexes_fn
 		}
 	};
 
 	let quote_impl = quote! {
+		#entry_check
+
 		#implementation
+
+		#table
 	};
 
 	Ok(quote_impl)
 }
 
+/// Finds the key field, along with the nested fields the key is read through and how it's encoded.
+fn get_key<'a>(
+	input: &DeriveInput,
+	fields: &'a [Field],
+) -> Result<(&'a Field, Vec<Ident>, Option<KeyEncoding>)> {
+	if let Some(attr) = find_key_attr(&input.attrs) {
+		let options = parse_key_options(attr)?;
+
+		if let Some(field) = fields
+			.iter()
+			.find(|field| find_key_attr(&field.attrs).is_some())
+		{
+			return Err(Error::new_spanned(
+				field,
+				"the key is already set with #[key(path = \"...\")] on the struct",
+			));
+		}
+
+		let path = options
+			.path
+			.ok_or_else(|| Error::new_spanned(attr, "expected #[key(path = \"...\")]"))?;
+		let (field, nested) = resolve_key_path(fields, &path)?;
+
+		return Ok((field, nested, options.encoding));
+	}
+
+	let id_field = get_id_field(fields).ok_or_else(|| {
+		Error::new_spanned(
+			&input,
+			"Expected a #[key] attribute or a field named `key` or `id`.",
+		)
+	})?;
+
+	let options = find_key_attr(&id_field.attrs)
+		.map(parse_key_options)
+		.transpose()?
+		.unwrap_or_default();
+
+	if let Some(path) = options.path {
+		return Err(Error::new_spanned(
+			path,
+			"`path` can only be set in a #[key] attribute on the struct",
+		));
+	}
+
+	Ok((id_field, Vec::new(), options.encoding))
+}
+
 fn get_id_field(fields: &[Field]) -> Option<&Field> {
 	for field in fields {
 		if field.attrs.iter().any(|attr| attr.path.is_ident(KEY_IDENT)) {
@@ -105,3 +236,214 @@ fn get_id_field(fields: &[Field]) -> Option<&Field> {
 
 	None
 }
+
+/// Splits a `#[key(path = "...")]` path into the top-level field it starts at, and the nested fields after it.
+fn resolve_key_path<'a>(fields: &'a [Field], path: &LitStr) -> Result<(&'a Field, Vec<Ident>)> {
+	let mut segments = path
+		.value()
+		.split('.')
+		.map(|segment| {
+			syn::parse_str::<Ident>(segment)
+				.map(|ident| Ident::new(&ident.to_string(), path.span()))
+				.map_err(|_| {
+					Error::new_spanned(path, "expected a path of fields like `ids.primary`")
+				})
+		})
+		.collect::<Result<Vec<_>>>()?
+		.into_iter();
+
+	let first = segments
+		.next()
+		.ok_or_else(|| Error::new_spanned(path, "expected a path of fields like `ids.primary`"))?;
+
+	let field = fields
+		.iter()
+		.find(|field| field.ident.as_ref() == Some(&first))
+		.ok_or_else(|| Error::new_spanned(path, format!("no field named `{first}`")))?;
+
+	Ok((field, segments.collect()))
+}
+
+/// Implements `KeyEncoder` on the entry, encoding the key field with the given encoding and reading the
+/// nested fields of the key field, if any, so the entry can be used as it's own `EncodedKey`.
+///
+/// Returns the key type, the body of `IndexEntry::key`, and the `KeyEncoder` impl.
+fn wrap_key(
+	input: &DeriveInput,
+	field: &Field,
+	field_ident: &Ident,
+	nested: &[Ident],
+	encoding: Option<KeyEncoding>,
+) -> (TokenStream, TokenStream, TokenStream) {
+	let ident = &input.ident;
+	let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+	let to_key = match encoding {
+		Some(KeyEncoding::With(path)) => quote! { #path(&self.#field_ident #(.#nested)*) },
+		Some(KeyEncoding::Format(format)) => {
+			quote! { ::std::format!(#format, self.#field_ident #(.#nested)*) }
+		}
+		None => quote! { ::starchart::Key::to_key(&self.#field_ident #(.#nested)*) },
+	};
+
+	(
+		quote! { ::starchart::__private::EncodedKey<Self> },
+		quote! { ::starchart::__private::EncodedKey::new(self) },
+		quote_spanned! {field.span()=>
+			#[automatically_derived]
+			impl #impl_generics ::starchart::__private::KeyEncoder for #ident #ty_generics #where_clause {
+				fn encode_key(&self) -> ::std::string::String {
+					#to_key
+				}
+			}
+		},
+	)
+}
+
+fn find_key_attr(attrs: &[Attribute]) -> Option<&Attribute> {
+	attrs.iter().find(|attr| attr.path.is_ident(KEY_IDENT))
+}
+
+fn parse_key_options(attr: &Attribute) -> Result<KeyOptions> {
+	let list = match attr.parse_meta()? {
+		Meta::Path(_) => return Ok(KeyOptions::default()),
+		Meta::List(list) => list,
+		Meta::NameValue(meta) => return Err(Error::new_spanned(
+			meta,
+			"expected #[key(with = \"...\")], #[key(format = \"...\")] or #[key(path = \"...\")]",
+		)),
+	};
+
+	let mut options = KeyOptions::default();
+	for nested in list.nested {
+		let (path, value) = match nested {
+			NestedMeta::Meta(Meta::NameValue(meta)) => match meta.lit {
+				Lit::Str(value) => (meta.path, value),
+				other => return Err(Error::new_spanned(other, "expected a string literal")),
+			},
+			nested => {
+				return Err(Error::new_spanned(
+					nested,
+					"expected `with = \"...\"`, `format = \"...\"` or `path = \"...\"`",
+				))
+			}
+		};
+
+		if path.is_ident(PATH_IDENT) {
+			if options.path.is_some() {
+				return Err(Error::new_spanned(path, "`path` can only be set once"));
+			}
+
+			options.path = Some(value);
+			continue;
+		}
+
+		if options.encoding.is_some() {
+			return Err(Error::new_spanned(
+				path,
+				"only one of `with` or `format` can be set",
+			));
+		}
+
+		options.encoding = if path.is_ident(WITH_IDENT) {
+			Some(KeyEncoding::With(value.parse()?))
+		} else if path.is_ident(FORMAT_IDENT) {
+			Some(KeyEncoding::Format(value))
+		} else {
+			return Err(Error::new_spanned(
+				path,
+				"expected `with`, `format` or `path`",
+			));
+		};
+	}
+
+	Ok(options)
+}
+
+fn get_table(input: &DeriveInput) -> Result<Option<LitStr>> {
+	let Some(attr) = input
+		.attrs
+		.iter()
+		.find(|attr| attr.path.is_ident(ENTRY_IDENT))
+	else {
+		return Ok(None);
+	};
+
+	let list = match attr.parse_meta()? {
+		Meta::List(list) => list,
+		meta => {
+			return Err(Error::new_spanned(
+				meta,
+				"expected #[entry(table = \"...\")]",
+			))
+		}
+	};
+
+	let mut table = None;
+	for nested in list.nested {
+		let meta = match nested {
+			NestedMeta::Meta(Meta::NameValue(meta)) if meta.path.is_ident(TABLE_IDENT) => meta,
+			nested => return Err(Error::new_spanned(nested, "expected `table = \"...\"`")),
+		};
+
+		if table.is_some() {
+			return Err(Error::new_spanned(
+				meta.path,
+				"`table` can only be set once",
+			));
+		}
+
+		table = match meta.lit {
+			Lit::Str(value) if !value.value().is_empty() => Some(value),
+			other => {
+				return Err(Error::new_spanned(
+					other,
+					"expected a non-empty string literal",
+				))
+			}
+		};
+	}
+
+	Ok(table)
+}
+
+fn get_indexes_fn(fields: &[Field]) -> Result<TokenStream> {
+	let indexes = get_index_fields(fields)
+		.map(|field| {
+			let field_ident = field
+				.ident
+				.as_ref()
+				.ok_or_else(|| Error::new_spanned(field, "expected a named field"))?;
+			let name = field_ident.to_string();
+
+			if name.contains("__") {
+				return Err(Error::new_spanned(
+					field_ident,
+					"index names can't contain `__`",
+				));
+			}
+
+			Ok(quote_spanned! {field.span()=>
+				(#name, ::starchart::Key::to_key(&self.#field_ident))
+			})
+		})
+		.collect::<Result<Vec<_>>>()?;
+
+	if indexes.is_empty() {
+		return Ok(quote! {});
+	}
+
+	Ok(quote! {
+		fn indexes(&self) -> ::std::vec::Vec<(&'static str, ::std::string::String)> {
+			::std::vec![#(#indexes),*]
+		}
+	})
+}
+
+fn get_index_fields(fields: &[Field]) -> impl Iterator<Item = &Field> {
+	fields.iter().filter(|field| {
+		field
+			.attrs
+			.iter()
+			.any(|attr| attr.path.is_ident(INDEX_IDENT))
+	})
+}
@@ -0,0 +1,151 @@
+use proc_macro2::TokenStream;
+use quote::{format_ident, quote, quote_spanned};
+use syn::{
+	spanned::Spanned, Attribute, Data, DeriveInput, Error, Fields, Meta, NestedMeta, Result,
+};
+
+const PATCH_IDENT: &str = "patch";
+const SKIP_IDENT: &str = "skip";
+const SERDE_IDENT: &str = "serde";
+const RENAME_IDENT: &str = "rename";
+const RENAME_ALL_IDENT: &str = "rename_all";
+
+pub fn parse(input: &DeriveInput) -> Result<TokenStream> {
+	let ident = &input.ident;
+	let vis = &input.vis;
+	let patch_ident = format_ident!("{}Patch", ident);
+
+	if !input.generics.params.is_empty() {
+		return Err(Error::new_spanned(
+			&input.generics,
+			"EntryPatch can't be derived on generic structs",
+		));
+	}
+
+	let named_fields = match &input.data {
+		Data::Struct(st) => match &st.fields {
+			Fields::Named(named) => &named.named,
+			fields => {
+				return Err(Error::new_spanned(
+					fields,
+					"EntryPatch can only be derived on a struct with named fields",
+				))
+			}
+		},
+		_ => {
+			return Err(Error::new_spanned(
+				input,
+				"EntryPatch can only be derived on structs",
+			))
+		}
+	};
+
+	let mut fields = Vec::new();
+	let mut applies = Vec::new();
+	let mut idents = Vec::new();
+	for field in named_fields {
+		if is_skipped(&field.attrs)? {
+			continue;
+		}
+
+		let field_ident = field
+			.ident
+			.as_ref()
+			.ok_or_else(|| Error::new_spanned(field, "expected a named field"))?;
+		let field_vis = &field.vis;
+		let field_type = &field.ty;
+		let renames = serde_renames(&field.attrs, RENAME_IDENT)?;
+
+		fields.push(quote_spanned! {field.span()=>
+			#[serde(skip_serializing_if = "::std::option::Option::is_none")]
+			#(#renames)*
+			#field_vis #field_ident: ::std::option::Option<#field_type>
+		});
+		applies.push(quote_spanned! {field.span()=>
+			if let ::std::option::Option::Some(value) = self.#field_ident {
+				entry.#field_ident = value;
+			}
+		});
+		idents.push(field_ident);
+	}
+
+	let renames = serde_renames(&input.attrs, RENAME_ALL_IDENT)?;
+	let doc = format!(
+		"A partial [`{ident}`], where every field that's set replaces the field of the entry.\n\n\
+		 Unset fields are left out when serialized, so the patch can be passed to \
+		 [`PatchAction::set_patch`](::starchart::action::PatchAction::set_patch)."
+	);
+
+	Ok(quote! {
+		#[doc = #doc]
+		#[derive(
+			::std::fmt::Debug,
+			::std::clone::Clone,
+			::std::default::Default,
+			::starchart::__private::serde::Serialize,
+		)]
+		#[serde(crate = "::starchart::__private::serde")]
+		#(#renames)*
+		#vis struct #patch_ident {
+			#(#fields),*
+		}
+
+		#[automatically_derived]
+		impl #patch_ident {
+			/// Sets every field of the entry that's set in this patch.
+			#vis fn apply(self, entry: &mut #ident) {
+				#(#applies)*
+			}
+
+			/// Whether no fields are set in this patch.
+			#[must_use]
+			#vis const fn is_empty(&self) -> bool {
+				true #(&& self.#idents.is_none())*
+			}
+		}
+	})
+}
+
+fn is_skipped(attrs: &[Attribute]) -> Result<bool> {
+	let mut skipped = false;
+	for attr in attrs.iter().filter(|attr| attr.path.is_ident(PATCH_IDENT)) {
+		match attr.parse_meta()? {
+			Meta::List(list)
+				if list.nested.len() == 1
+					&& matches!(
+						list.nested.first(),
+						Some(NestedMeta::Meta(Meta::Path(path))) if path.is_ident(SKIP_IDENT)
+					) =>
+			{
+				skipped = true;
+			}
+			meta => return Err(Error::new_spanned(meta, "expected #[patch(skip)]")),
+		}
+	}
+
+	Ok(skipped)
+}
+
+/// Copies the given renaming option out of the `#[serde(...)]` attributes, so the patch serializes
+/// with the same field names as the entry.
+fn serde_renames(attrs: &[Attribute], option: &str) -> Result<Vec<TokenStream>> {
+	let mut renames = Vec::new();
+	for attr in attrs.iter().filter(|attr| attr.path.is_ident(SERDE_IDENT)) {
+		let Meta::List(list) = attr.parse_meta()? else {
+			continue;
+		};
+
+		for nested in list.nested {
+			match nested {
+				NestedMeta::Meta(meta @ (Meta::NameValue(_) | Meta::List(_)))
+					if meta.path().is_ident(option) =>
+				{
+					renames.push(quote! { #[serde(#meta)] });
+				}
+				_ => {}
+			}
+		}
+	}
+
+	Ok(renames)
+}
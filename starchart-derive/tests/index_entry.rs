@@ -0,0 +1,123 @@
+#![forbid(unsafe_code)]
+
+use std::fmt::Display;
+
+use serde::{Deserialize, Serialize};
+use starchart::{IndexEntry, Key};
+
+#[derive(Debug, Default, Clone, Serialize, Deserialize, IndexEntry)]
+struct Plain {
+	id: u64,
+	#[index]
+	email: String,
+}
+
+#[derive(Debug, Default, Clone, Serialize, Deserialize, IndexEntry)]
+struct Formatted {
+	#[key(format = "user-{}")]
+	id: u64,
+}
+
+fn hex(value: &u64) -> String {
+	format!("{value:x}")
+}
+
+#[derive(Debug, Default, Clone, Serialize, Deserialize, IndexEntry)]
+struct Encoded {
+	#[key(with = "hex")]
+	id: u64,
+}
+
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+struct Ids {
+	primary: u64,
+}
+
+#[derive(Debug, Default, Clone, Serialize, Deserialize, IndexEntry)]
+#[key(path = "ids.primary")]
+struct Nested {
+	ids: Ids,
+}
+
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+struct PrivateId(u64);
+
+impl Display for PrivateId {
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		Display::fmt(&self.0, f)
+	}
+}
+
+#[derive(Debug, Default, Clone, Serialize, Deserialize, IndexEntry)]
+#[entry(table = "public")]
+pub struct Public {
+	#[key(format = "public-{}")]
+	id: PrivateId,
+}
+
+#[derive(Debug, Default, Clone, Serialize, Deserialize, IndexEntry)]
+#[entry(table = "generic")]
+struct Generic<T> {
+	#[key(format = "generic-{}")]
+	id: u64,
+	value: T,
+}
+
+#[derive(Debug, Default, Clone, Serialize, Deserialize, IndexEntry)]
+#[key(path = "inner.id")]
+struct GenericNested<T: Display> {
+	inner: Wrapper<T>,
+}
+
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+struct Wrapper<T> {
+	id: T,
+}
+
+#[test]
+fn plain() {
+	let entry = Plain {
+		id: 1,
+		email: "user@example.com".to_owned(),
+	};
+
+	assert_eq!(entry.key().to_key(), "1");
+	assert_eq!(entry.indexes(), [("email", "user@example.com".to_owned())]);
+}
+
+#[test]
+fn encoded() {
+	assert_eq!(Formatted { id: 1 }.key().to_key(), "user-1");
+	assert_eq!(Encoded { id: 255 }.key().to_key(), "ff");
+	assert_eq!(
+		Nested {
+			ids: Ids { primary: 2 }
+		}
+		.key()
+		.to_key(),
+		"2"
+	);
+}
+
+#[test]
+fn private_key_type() {
+	assert_eq!(Public { id: PrivateId(3) }.key().to_key(), "public-3");
+	assert_eq!(Public::TABLE, "public");
+}
+
+#[test]
+fn generic() {
+	let entry = Generic {
+		id: 4,
+		value: "value".to_owned(),
+	};
+	assert_eq!(entry.key().to_key(), "generic-4");
+	assert_eq!(Generic::<String>::TABLE, "generic");
+
+	let entry = GenericNested {
+		inner: Wrapper {
+			id: "nested".to_owned(),
+		},
+	};
+	assert_eq!(entry.key().to_key(), "nested");
+}
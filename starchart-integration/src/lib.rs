@@ -0,0 +1,454 @@
+#![warn(
+	clippy::pedantic,
+	clippy::nursery,
+	clippy::suspicious,
+	clippy::str_to_string,
+	missing_copy_implementations,
+	missing_docs
+)]
+#![deny(clippy::all)]
+#![allow(clippy::module_name_repetitions, clippy::no_effect_underscore_binding)]
+//! An example application that's run against every backend, as an executable compatibility contract.
+//!
+//! The application ([`Directory`]) is only written against the [`Backend`] trait, and [`scenario`]
+//! checks it behaves the same on every backend. Backends are either picked at compile time with
+//! [`run_on`], or at runtime with [`run`].
+//!
+//! If a change to the backends or actions breaks this crate, it breaks every application written the same way.
+
+use std::{
+	collections::BTreeMap,
+	env,
+	error::Error as StdError,
+	fmt::{Debug, Display, Formatter, Result as FmtResult},
+	fs,
+	io::ErrorKind,
+	process,
+};
+
+use serde::{Deserialize, Serialize};
+use starchart::{
+	action::{
+		CreateBatchAction, CreateEntryAction, CreateTableAction, DeleteEntryAction,
+		DeleteTableAction, ReadEntryAction, ReadTableAction, UpdateEntryAction,
+	},
+	backend::Backend,
+	Error, IndexEntry, Starchart,
+};
+use starchart_backends::{
+	fs::{
+		transcoders::{JsonTranscoder, TomlTranscoder},
+		FsBackend, Transcoder,
+	},
+	memory::MemoryBackend,
+};
+
+/// The table the [`Directory`] stores it's members in.
+pub const TABLE: &str = "members";
+
+/// A member of the [`Directory`].
+#[derive(Debug, Default, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Member {
+	/// The unique id of the member.
+	pub id: u64,
+	/// The display name of the member.
+	pub name: String,
+	/// The roles the member has.
+	pub roles: Vec<String>,
+	/// An optional nickname, which isn't written by formats without null values.
+	pub nickname: Option<String>,
+}
+
+impl Member {
+	/// Creates a new [`Member`] with the given roles.
+	#[must_use]
+	pub fn new(id: u64, name: &str, roles: &[&str]) -> Self {
+		Self {
+			id,
+			name: name.to_owned(),
+			roles: roles.iter().map(|role| (*role).to_owned()).collect(),
+			nickname: None,
+		}
+	}
+}
+
+impl IndexEntry for Member {
+	type Key = u64;
+
+	fn key(&self) -> &Self::Key {
+		&self.id
+	}
+}
+
+/// The example application, a directory of members.
+#[derive(Debug)]
+pub struct Directory<'a, B: Backend> {
+	chart: &'a Starchart<B>,
+}
+
+impl<'a, B: Backend> Directory<'a, B> {
+	/// Creates a new [`Directory`] stored in the chart.
+	#[must_use]
+	pub const fn new(chart: &'a Starchart<B>) -> Self {
+		Self { chart }
+	}
+
+	/// Creates the members table.
+	///
+	/// # Errors
+	///
+	/// Returns any errors from the [`CreateTableAction`].
+	pub async fn open(&self) -> Result<(), Error> {
+		let mut action = CreateTableAction::<Member>::new();
+		action.set_table(TABLE);
+
+		Ok(action.run_create_table(self.chart).await?)
+	}
+
+	/// Adds a member.
+	///
+	/// # Errors
+	///
+	/// Returns any errors from the [`CreateEntryAction`].
+	pub async fn join(&self, member: &Member) -> Result<(), Error> {
+		let mut action = CreateEntryAction::new();
+		action.set_table(TABLE).set_entry(member);
+
+		Ok(action.run_create_entry(self.chart).await?)
+	}
+
+	/// Adds many members at once.
+	///
+	/// # Errors
+	///
+	/// Returns any errors from the [`CreateBatchAction`].
+	pub async fn join_all(&self, members: &[Member]) -> Result<(), Error> {
+		let mut action = CreateBatchAction::new();
+		action.set_table(TABLE).add_entries(members);
+
+		Ok(action.run_create_many(self.chart).await?)
+	}
+
+	/// Gets a member by id.
+	///
+	/// # Errors
+	///
+	/// Returns any errors from the [`ReadEntryAction`].
+	pub async fn get(&self, id: u64) -> Result<Option<Member>, Error> {
+		let mut action = ReadEntryAction::<Member>::new();
+		action.set_table(TABLE).set_key(&id);
+
+		Ok(action.run_read_entry(self.chart).await?)
+	}
+
+	/// Renames a member, returning whether they exist.
+	///
+	/// # Errors
+	///
+	/// Returns any errors from the [`ReadEntryAction`] or [`UpdateEntryAction`].
+	pub async fn rename(&self, id: u64, name: &str) -> Result<bool, Error> {
+		let Some(mut member) = self.get(id).await? else {
+			return Ok(false);
+		};
+
+		member.name = name.to_owned();
+
+		let mut action = UpdateEntryAction::new();
+		action.set_table(TABLE).set_entry(&member);
+		action.run_update_entry(self.chart).await?;
+
+		Ok(true)
+	}
+
+	/// Gets every member with the role, sorted by id.
+	///
+	/// # Errors
+	///
+	/// Returns any errors from the [`ReadTableAction`].
+	pub async fn with_role(&self, role: &str) -> Result<Vec<Member>, Error> {
+		let role = role.to_owned();
+
+		let mut action = ReadTableAction::<Member>::new();
+		action
+			.set_table(TABLE)
+			.filter(move |member| member.roles.contains(&role));
+
+		let mut members: Vec<Member> = action.run_read_table(self.chart).await?;
+		members.sort_by_key(|member| member.id);
+
+		Ok(members)
+	}
+
+	/// Gets every member, keyed by id.
+	///
+	/// # Errors
+	///
+	/// Returns any errors from [`Table::read_map`].
+	///
+	/// [`Table::read_map`]: starchart::table::Table::read_map
+	pub async fn members(&self) -> Result<BTreeMap<u64, Member>, Error> {
+		self.chart.table::<Member>(TABLE).read_map().await
+	}
+
+	/// Removes a member, returning whether they existed.
+	///
+	/// # Errors
+	///
+	/// Returns any errors from the [`DeleteEntryAction`].
+	pub async fn leave(&self, id: u64) -> Result<bool, Error> {
+		let mut action = DeleteEntryAction::<Member>::new();
+		action.set_table(TABLE).set_key(&id);
+
+		Ok(action.run_delete_entry(self.chart).await?)
+	}
+
+	/// Deletes the members table, returning whether it existed.
+	///
+	/// # Errors
+	///
+	/// Returns any errors from the [`DeleteTableAction`].
+	pub async fn close(&self) -> Result<bool, Error> {
+		let mut action = DeleteTableAction::<Member>::new();
+		action.set_table(TABLE);
+
+		Ok(action.run_delete_table(self.chart).await?)
+	}
+}
+
+/// Runs the [`Directory`] through every operation, checking the results are the same as on every other backend.
+///
+/// # Errors
+///
+/// Returns a [`Failure`] for the first operation that failed, or that returned an unexpected result.
+pub async fn scenario<B: Backend>(chart: &Starchart<B>) -> Result<(), Failure> {
+	let directory = Directory::new(chart);
+
+	directory.open().await?;
+
+	let mut ferris = Member::new(1, "Ferris", &["admin", "crab"]);
+	ferris.nickname = Some("the crab".to_owned());
+	directory.join(&ferris).await?;
+	directory
+		.join_all(&[
+			Member::new(2, "Corro", &["crab"]),
+			Member::new(10, "Clippy", &[]),
+		])
+		.await?;
+
+	check("reading a member", directory.get(1).await?, Some(ferris))?;
+	check("reading a missing member", directory.get(3).await?, None)?;
+
+	check(
+		"renaming a member",
+		directory.rename(2, "Corro the Unsafe").await?,
+		true,
+	)?;
+	check(
+		"renaming a missing member",
+		directory.rename(3, "Nobody").await?,
+		false,
+	)?;
+	check(
+		"reading a renamed member",
+		directory.get(2).await?.map(|member| member.name),
+		Some("Corro the Unsafe".to_owned()),
+	)?;
+
+	check(
+		"filtering members",
+		ids(directory.with_role("crab").await?),
+		vec![1, 2],
+	)?;
+	check(
+		"reading every member",
+		directory.members().await?.into_keys().collect(),
+		vec![1, 2, 10],
+	)?;
+
+	check("removing a member", directory.leave(10).await?, true)?;
+	check(
+		"removing a missing member",
+		directory.leave(10).await?,
+		false,
+	)?;
+	check(
+		"reading members after removing one",
+		directory.members().await?.into_keys().collect(),
+		vec![1, 2],
+	)?;
+
+	check("deleting the table", directory.close().await?, true)?;
+	check(
+		"reading from a deleted table",
+		directory.get(1).await.is_err(),
+		true,
+	)?;
+
+	Ok(())
+}
+
+/// Runs the [`scenario`] on a backend known at compile time.
+///
+/// # Errors
+///
+/// Returns a [`Failure`] if the backend couldn't be initialized, or if the scenario fails.
+pub async fn run_on<B: Backend>(backend: B) -> Result<(), Failure> {
+	let chart = Starchart::new(backend)
+		.await
+		.map_err(|e| Failure::Init(Box::new(e)))?;
+
+	scenario(&chart).await
+}
+
+/// Runs the [`scenario`] on a backend picked at runtime.
+///
+/// File-based backends are stored in a temporary directory, which is removed afterwards.
+///
+/// # Errors
+///
+/// Returns a [`Failure`] if the backend couldn't be created or initialized, or if the scenario fails.
+pub async fn run(kind: BackendKind) -> Result<(), Failure> {
+	match kind {
+		BackendKind::Memory => run_on(MemoryBackend::new()).await,
+		BackendKind::Json => run_fs(kind, JsonTranscoder::pretty(), "json").await,
+		BackendKind::Toml => run_fs(kind, TomlTranscoder::pretty(), "toml").await,
+	}
+}
+
+async fn run_fs<T: Transcoder>(
+	kind: BackendKind,
+	transcoder: T,
+	extension: &str,
+) -> Result<(), Failure> {
+	let directory =
+		env::temp_dir()
+			.join("starchart-integration")
+			.join(format!("{}-{}", kind, process::id()));
+
+	if let Err(e) = fs::remove_dir_all(&directory) {
+		if e.kind() != ErrorKind::NotFound {
+			return Err(Failure::Init(Box::new(e)));
+		}
+	}
+
+	fs::create_dir_all(&directory).map_err(|e| Failure::Init(Box::new(e)))?;
+
+	let backend = FsBackend::new(transcoder, extension.to_owned(), &directory)
+		.map_err(|e| Failure::Init(Box::new(e)))?;
+
+	let result = run_on(backend).await;
+
+	// leftover files are harmless, and removed by the next run.
+	let _removed = fs::remove_dir_all(&directory);
+
+	result
+}
+
+/// The backends the [`scenario`] can be [`run`] on.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum BackendKind {
+	/// The [`MemoryBackend`].
+	Memory,
+	/// The [`FsBackend`] with a [`JsonTranscoder`].
+	Json,
+	/// The [`FsBackend`] with a [`TomlTranscoder`].
+	Toml,
+}
+
+impl BackendKind {
+	/// Every backend the scenario can be run on.
+	pub const ALL: [Self; 3] = [Self::Memory, Self::Json, Self::Toml];
+}
+
+impl Display for BackendKind {
+	fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+		f.write_str(match self {
+			Self::Memory => "memory",
+			Self::Json => "json",
+			Self::Toml => "toml",
+		})
+	}
+}
+
+/// Why the [`scenario`] failed on a backend.
+#[derive(Debug)]
+#[non_exhaustive]
+pub enum Failure {
+	/// The backend couldn't be created or initialized.
+	Init(Box<dyn StdError + Send + Sync>),
+	/// An operation returned an error.
+	Chart(Error),
+	/// An operation returned a different result than expected.
+	Mismatch {
+		/// The operation that returned the result.
+		operation: &'static str,
+		/// The result that was returned.
+		actual: String,
+		/// The result that was expected.
+		expected: String,
+	},
+}
+
+impl Display for Failure {
+	fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+		match self {
+			Self::Init(e) => {
+				f.write_str("the backend couldn't be initialized: ")?;
+				Display::fmt(e, f)
+			}
+			Self::Chart(e) => {
+				f.write_str("an operation failed: ")?;
+				Display::fmt(e, f)
+			}
+			Self::Mismatch {
+				operation,
+				actual,
+				expected,
+			} => {
+				f.write_str(operation)?;
+				f.write_str(" returned ")?;
+				f.write_str(actual)?;
+				f.write_str(", expected ")?;
+				f.write_str(expected)
+			}
+		}
+	}
+}
+
+impl StdError for Failure {
+	fn source(&self) -> Option<&(dyn StdError + 'static)> {
+		match self {
+			Self::Init(e) => Some(&**e),
+			Self::Chart(e) => Some(e),
+			Self::Mismatch { .. } => None,
+		}
+	}
+}
+
+impl From<Error> for Failure {
+	fn from(e: Error) -> Self {
+		Self::Chart(e)
+	}
+}
+
+#[allow(clippy::needless_pass_by_value)]
+fn check<T: Debug + PartialEq>(
+	operation: &'static str,
+	actual: T,
+	expected: T,
+) -> Result<(), Failure> {
+	if actual == expected {
+		Ok(())
+	} else {
+		Err(Failure::Mismatch {
+			operation,
+			actual: format!("{actual:?}"),
+			expected: format!("{expected:?}"),
+		})
+	}
+}
+
+fn ids(members: Vec<Member>) -> Vec<u64> {
+	members.into_iter().map(|member| member.id).collect()
+}
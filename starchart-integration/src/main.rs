@@ -0,0 +1,26 @@
+//! Runs the example application against every backend, exiting with an error if any of them fail.
+
+use std::process::ExitCode;
+
+use starchart_integration::{run, BackendKind};
+
+#[tokio::main(flavor = "current_thread")]
+async fn main() -> ExitCode {
+	let mut failed = false;
+
+	for kind in BackendKind::ALL {
+		match run(kind).await {
+			Ok(()) => println!("{kind}: ok"),
+			Err(e) => {
+				eprintln!("{kind}: {e}");
+				failed = true;
+			}
+		}
+	}
+
+	if failed {
+		ExitCode::FAILURE
+	} else {
+		ExitCode::SUCCESS
+	}
+}
@@ -0,0 +1,22 @@
+use starchart_backends::memory::MemoryBackend;
+use starchart_integration::{run, run_on, BackendKind, Failure};
+
+#[tokio::test]
+async fn generic() -> Result<(), Failure> {
+	run_on(MemoryBackend::new()).await
+}
+
+#[tokio::test]
+async fn memory() -> Result<(), Failure> {
+	run(BackendKind::Memory).await
+}
+
+#[tokio::test]
+async fn json() -> Result<(), Failure> {
+	run(BackendKind::Json).await
+}
+
+#[tokio::test]
+async fn toml() -> Result<(), Failure> {
+	run(BackendKind::Toml).await
+}
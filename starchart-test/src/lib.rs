@@ -0,0 +1,286 @@
+#![warn(
+	clippy::pedantic,
+	clippy::nursery,
+	clippy::suspicious,
+	clippy::str_to_string,
+	missing_copy_implementations,
+	missing_docs
+)]
+#![deny(clippy::all)]
+#![allow(clippy::module_name_repetitions, clippy::no_effect_underscore_binding)]
+//! Testing helpers for the starchart crate.
+//!
+//! The main helper is [`Snapshot`], which asserts that the contents of a table match a checked-in
+//! fixture file. Set the [`UPDATE_VAR`] environment variable to regenerate fixtures instead.
+
+use std::{
+	env,
+	fmt::{Debug, Formatter, Result as FmtResult},
+	fs,
+	path::{Path, PathBuf},
+};
+
+use serde_json::Value;
+use starchart::{backend::Backend, Entry, Starchart};
+
+/// The environment variable that makes [`Snapshot`]s overwrite their fixtures instead of comparing against them.
+pub const UPDATE_VAR: &str = "STARCHART_UPDATE_SNAPSHOTS";
+
+/// The value that redacted fields are replaced with.
+pub const REDACTED: &str = "[redacted]";
+
+type Redaction = Box<dyn Fn(&str, &mut Value) + Send + Sync>;
+
+/// A golden snapshot of a table, stored as pretty-printed JSON in a fixture file.
+///
+/// The table is rendered with [`Starchart::dump_pretty`], so the fixture is the same for every [`Backend`].
+/// Fields that change between runs (such as timestamps or generated ids) can be redacted before comparing.
+#[must_use = "a snapshot does nothing until it's asserted"]
+pub struct Snapshot {
+	path: PathBuf,
+	redactions: Vec<Redaction>,
+	update: bool,
+}
+
+impl Snapshot {
+	/// Creates a new [`Snapshot`] stored at the given path.
+	///
+	/// The fixture is regenerated if the [`UPDATE_VAR`] environment variable is set to anything but `0`.
+	pub fn new<P: AsRef<Path>>(path: P) -> Self {
+		Self {
+			path: path.as_ref().to_owned(),
+			redactions: Vec::new(),
+			update: env::var_os(UPDATE_VAR).is_some_and(|value| value != "0"),
+		}
+	}
+
+	/// The path of the fixture file.
+	#[must_use]
+	pub fn path(&self) -> &Path {
+		&self.path
+	}
+
+	/// Replaces the value at the given [JSON pointer] with [`REDACTED`] in every entry that has it.
+	///
+	/// [JSON pointer]: https://datatracker.ietf.org/doc/html/rfc6901
+	pub fn redact(self, pointer: &str) -> Self {
+		let pointer = pointer.to_owned();
+
+		self.redact_with(move |_, entry| {
+			if let Some(value) = entry.pointer_mut(&pointer) {
+				*value = Value::String(REDACTED.to_owned());
+			}
+		})
+	}
+
+	/// Runs the given closure over every entry, along with it's key, before comparing.
+	pub fn redact_with<F>(mut self, redaction: F) -> Self
+	where
+		F: Fn(&str, &mut Value) + Send + Sync + 'static,
+	{
+		self.redactions.push(Box::new(redaction));
+
+		self
+	}
+
+	/// Overrides whether the fixture is regenerated, instead of reading [`UPDATE_VAR`].
+	pub const fn update(mut self, update: bool) -> Self {
+		self.update = update;
+
+		self
+	}
+
+	/// Asserts that the table matches the fixture, or regenerates the fixture if updating.
+	///
+	/// # Panics
+	///
+	/// Panics if the table doesn't match the fixture, if the fixture is missing and snapshots
+	/// aren't being updated, or if the table or fixture can't be read or written.
+	pub async fn assert_matches<B: Backend, S: Entry>(&self, chart: &Starchart<B>, table: &str) {
+		let actual = self.render::<B, S>(chart, table).await;
+
+		if self.update {
+			if let Some(parent) = self.path.parent() {
+				fs::create_dir_all(parent)
+					.unwrap_or_else(|e| panic!("failed to create {}: {}", parent.display(), e));
+			}
+
+			fs::write(&self.path, actual)
+				.unwrap_or_else(|e| panic!("failed to write {}: {}", self.path.display(), e));
+
+			return;
+		}
+
+		let expected = fs::read_to_string(&self.path).unwrap_or_else(|e| {
+			panic!(
+				"failed to read snapshot {} ({}), set {}=1 to create it",
+				self.path.display(),
+				e,
+				UPDATE_VAR
+			)
+		});
+
+		assert!(
+			expected.replace("\r\n", "\n") == actual,
+			"table {} doesn't match snapshot {}, set {}=1 to update it\n\nexpected:\n{}\nactual:\n{}",
+			table,
+			self.path.display(),
+			UPDATE_VAR,
+			expected,
+			actual
+		);
+	}
+
+	async fn render<B: Backend, S: Entry>(&self, chart: &Starchart<B>, table: &str) -> String {
+		let mut output = Vec::new();
+
+		chart
+			.dump_pretty::<S, _>(table, &mut output)
+			.await
+			.unwrap_or_else(|e| panic!("failed to dump table {}: {}", table, e));
+
+		let mut entries = serde_json::from_slice::<Value>(&output)
+			.unwrap_or_else(|e| panic!("failed to parse dump of table {}: {}", table, e));
+
+		if let Value::Object(entries) = &mut entries {
+			for (key, entry) in entries.iter_mut() {
+				for redaction in &self.redactions {
+					redaction(key, entry);
+				}
+			}
+		}
+
+		let mut rendered = serde_json::to_string_pretty(&entries)
+			.unwrap_or_else(|e| panic!("failed to render table {}: {}", table, e));
+		rendered.push('\n');
+
+		rendered
+	}
+}
+
+impl Debug for Snapshot {
+	fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+		f.debug_struct("Snapshot")
+			.field("path", &self.path)
+			.field("redactions", &self.redactions.len())
+			.field("update", &self.update)
+			.finish()
+	}
+}
+
+/// Asserts that a table matches the fixture at the given path, with no redactions.
+///
+/// This is a shorthand for [`Snapshot::new`] followed by [`Snapshot::assert_matches`].
+///
+/// # Panics
+///
+/// Panics for the same reasons as [`Snapshot::assert_matches`].
+pub async fn assert_table_snapshot<B, S, P>(chart: &Starchart<B>, table: &str, path: P)
+where
+	B: Backend,
+	S: Entry,
+	P: AsRef<Path> + Send,
+{
+	Snapshot::new(path)
+		.assert_matches::<B, S>(chart, table)
+		.await;
+}
+
+#[cfg(test)]
+mod tests {
+	use std::{env, fs, path::PathBuf};
+
+	use serde::{Deserialize, Serialize};
+	use starchart::{backend::Backend, Error, IndexEntry, Starchart};
+	use starchart_backends::memory::MemoryBackend;
+
+	use super::Snapshot;
+
+	#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+	struct Settings {
+		id: u32,
+		name: String,
+		created_at: u64,
+	}
+
+	impl IndexEntry for Settings {
+		type Key = u32;
+
+		fn key(&self) -> &Self::Key {
+			&self.id
+		}
+	}
+
+	async fn chart() -> Result<Starchart<MemoryBackend>, Error> {
+		let chart = Starchart::new(MemoryBackend::new()).await?;
+		chart.create_table("settings").await?;
+
+		let table = chart.table::<Settings>("settings");
+		for (id, name) in [(2, "second"), (1, "first")] {
+			table
+				.insert(&Settings {
+					id,
+					name: name.to_owned(),
+					created_at: u64::from(id) * 1000,
+				})
+				.await?;
+		}
+
+		Ok(chart)
+	}
+
+	fn fixture(name: &str) -> PathBuf {
+		[env!("CARGO_MANIFEST_DIR"), "fixtures", name]
+			.iter()
+			.collect()
+	}
+
+	#[tokio::test]
+	async fn matches_fixture() -> Result<(), Error> {
+		let chart = chart().await?;
+
+		Snapshot::new(fixture("settings.json"))
+			.update(false)
+			.redact("/created_at")
+			.assert_matches::<_, Settings>(&chart, "settings")
+			.await;
+
+		Ok(())
+	}
+
+	#[tokio::test]
+	#[should_panic(expected = "doesn't match snapshot")]
+	async fn mismatch() {
+		let chart = chart().await.unwrap();
+
+		Snapshot::new(fixture("settings.json"))
+			.update(false)
+			.assert_matches::<_, Settings>(&chart, "settings")
+			.await;
+	}
+
+	#[tokio::test]
+	async fn update() -> Result<(), Error> {
+		let chart = chart().await?;
+
+		let path = env::temp_dir().join("starchart-test").join("update.json");
+		let _ = fs::remove_file(&path);
+
+		let snapshot = Snapshot::new(&path)
+			.redact_with(|key, entry| entry["name"] = key.into())
+			.update(true);
+		snapshot
+			.assert_matches::<_, Settings>(&chart, "settings")
+			.await;
+
+		let written = fs::read_to_string(&path).unwrap();
+		assert!(written.contains("\"name\": \"1\""));
+
+		snapshot
+			.update(false)
+			.assert_matches::<_, Settings>(&chart, "settings")
+			.await;
+
+		Ok(())
+	}
+}
@@ -0,0 +1,398 @@
+use std::{
+	fmt::{Debug, Formatter, Result as FmtResult},
+	future::Future,
+	marker::PhantomData,
+};
+
+use super::{
+	ActionError, ActionKind, ActionRunError, ActionRunErrorType, ActionValidationError,
+	CreateOperation, CrudOperation, InnerAction, TargetKind, UpdateOperation,
+};
+use crate::{
+	backend::Backend, index, journal::ChangeKind, metrics::Observer, IndexEntry, Key, Starchart,
+};
+
+/// A type alias for a [`BatchAction`] with [`CreateOperation`] as the parameter.
+pub type CreateBatchAction<'a, S> = BatchAction<'a, S, CreateOperation>;
+
+/// A type alias for a [`BatchAction`] with [`UpdateOperation`] as the parameter.
+pub type UpdateBatchAction<'a, S> = BatchAction<'a, S, UpdateOperation>;
+
+/// An action for writing many entries to a table at once.
+///
+/// The exclusive guard is only acquired once for the whole batch, and the entries
+/// are written with [`Backend::create_multi`] or [`Backend::update_multi`].
+#[must_use = "an action alone has no side effects"]
+pub struct BatchAction<'a, S, C> {
+	inner: InnerAction<'a, S>,
+	entries: Vec<&'a S>,
+	kind: PhantomData<C>,
+}
+
+impl<'a, S: IndexEntry, C: CrudOperation> BatchAction<'a, S, C> {
+	/// Creates a new, empty [`BatchAction`].
+	pub const fn new() -> Self {
+		Self {
+			inner: InnerAction::new(),
+			entries: Vec::new(),
+			kind: PhantomData,
+		}
+	}
+
+	/// Get a reference to the currently set table.
+	#[must_use]
+	pub const fn table(&self) -> Option<&str> {
+		self.inner.table
+	}
+
+	/// Get the entries that will be written.
+	#[must_use]
+	pub fn entries(&self) -> &[&'a S] {
+		&self.entries
+	}
+
+	/// Returns the [`ActionKind`] we will be performing with said action.
+	#[allow(clippy::unused_self)]
+	pub fn kind(&self) -> ActionKind {
+		C::kind()
+	}
+
+	fn observer<B: Backend>(&self, chart: &Starchart<B>) -> Observer<'a> {
+		Observer::new(
+			chart,
+			self.kind(),
+			TargetKind::Table,
+			self.inner.table,
+			None,
+			self.entries.iter().copied(),
+		)
+	}
+
+	/// Sets the table for this action.
+	pub fn set_table(&mut self, table_name: &'a str) -> &mut Self {
+		self.inner.table.replace(table_name);
+
+		self // coverage:ignore-line
+	}
+
+	/// Adds an entry to be written.
+	pub fn add_entry(&mut self, entry: &'a S) -> &mut Self {
+		self.entries.push(entry);
+
+		self // coverage:ignore-line
+	}
+
+	/// Adds many entries to be written.
+	pub fn add_entries<I: IntoIterator<Item = &'a S>>(&mut self, entries: I) -> &mut Self {
+		self.entries.extend(entries);
+
+		self // coverage:ignore-line
+	}
+
+	/// Validates that the table key is set.
+	///
+	/// # Errors
+	///
+	/// Errors if [`Self::set_table`] has not yet been called.
+	pub fn validate_table(&self) -> Result<(), ActionValidationError> {
+		self.inner.validate_table().map(drop)
+	}
+
+	fn keyed(&self) -> Result<Vec<(String, &'a S)>, ActionValidationError> {
+		self.entries
+			.iter()
+			.map(|entry| {
+				let key = entry.key().to_key();
+				self.inner.validate_metadata(Some(&key))?;
+				Ok((key, *entry))
+			})
+			.collect()
+	}
+
+	async fn create_many<B: Backend>(self, chart: &Starchart<B>) -> Result<(), ActionError> {
+		let table = self.inner.validate_table()?;
+		let keyed = self.keyed()?;
+
+		let backend = &**chart;
+
+		let lock = chart.guard.exclusive(table);
+
+		self.inner.check_writable_table(chart, table).await?;
+		self.inner.check_metadata(chart, table, true).await?;
+		InnerAction::repair_table(chart, table, Some(S::indexes as fn(&S) -> _)).await?;
+
+		let mut new: Vec<(&str, &S)> = Vec::with_capacity(keyed.len());
+		for (key, entry) in &keyed {
+			if new.iter().any(|(k, _)| k == key) {
+				continue;
+			}
+
+			if !backend.has(table, key).await.map_err(|e| ActionRunError {
+				source: Some(Box::new(e)),
+				kind: ActionRunErrorType::Backend,
+			})? {
+				new.push((key, *entry));
+			}
+		}
+
+		let entries = new.iter().map(|(_, entry)| *entry).collect::<Vec<_>>();
+		InnerAction::check_schema(chart, table, &entries).await?;
+
+		for (key, entry) in &new {
+			chart.record_payload(table, key, *entry);
+
+			InnerAction::<S>::journal(chart, table, key, ChangeKind::Write).await?;
+		}
+
+		backend
+			.create_multi(table, &new)
+			.await
+			.map_err(|e| ActionRunError {
+				source: Some(Box::new(e)),
+				kind: ActionRunErrorType::Backend,
+			})?;
+
+		let pending = new
+			.iter()
+			.map(|(key, _)| chart.repairs.pending(table, key))
+			.collect::<Vec<_>>();
+
+		for ((key, entry), pending) in new.iter().zip(pending) {
+			index::update(chart, table, key, &[], &entry.indexes())
+				.await
+				.map_err(|e| ActionRunError {
+					source: Some(Box::new(e)),
+					kind: ActionRunErrorType::Backend,
+				})?;
+
+			InnerAction::<S>::bump_version(chart, table, key, Some(entry)).await?;
+
+			pending.complete();
+		}
+
+		drop(lock);
+
+		Ok(())
+	}
+
+	async fn update_many<B: Backend>(self, chart: &Starchart<B>) -> Result<(), ActionError> {
+		let table = self.inner.validate_table()?;
+		let keyed = self.keyed()?;
+
+		let backend = &**chart;
+
+		let lock = chart.guard.exclusive(table);
+
+		self.inner.check_writable_table(chart, table).await?;
+		self.inner.check_metadata(chart, table, true).await?;
+		InnerAction::repair_table(chart, table, Some(S::indexes as fn(&S) -> _)).await?;
+
+		let entries = keyed.iter().map(|(_, entry)| *entry).collect::<Vec<_>>();
+		InnerAction::check_schema(chart, table, &entries).await?;
+
+		let mut previous = Vec::with_capacity(keyed.len());
+		for (key, entry) in &keyed {
+			let indexes = if entry.indexes().is_empty() {
+				Vec::new()
+			} else {
+				backend
+					.get::<S>(table, key)
+					.await
+					.map_err(|e| ActionRunError {
+						source: Some(Box::new(e)),
+						kind: ActionRunErrorType::Backend,
+					})?
+					.map(|previous| previous.indexes())
+					.unwrap_or_default()
+			};
+
+			chart.record_payload(table, key, *entry);
+
+			InnerAction::<S>::journal(chart, table, key, ChangeKind::Write).await?;
+
+			previous.push(indexes);
+		}
+
+		let entries = keyed
+			.iter()
+			.map(|(key, entry)| (key.as_str(), *entry))
+			.collect::<Vec<_>>();
+
+		backend
+			.update_multi(table, &entries)
+			.await
+			.map_err(|e| ActionRunError {
+				source: Some(Box::new(e)),
+				kind: ActionRunErrorType::Backend,
+			})?;
+
+		let pending = keyed
+			.iter()
+			.map(|(key, _)| chart.repairs.pending(table, key))
+			.collect::<Vec<_>>();
+
+		for (((key, entry), previous), pending) in keyed.iter().zip(&previous).zip(pending) {
+			index::update(chart, table, key, previous, &entry.indexes())
+				.await
+				.map_err(|e| ActionRunError {
+					source: Some(Box::new(e)),
+					kind: ActionRunErrorType::Backend,
+				})?;
+
+			InnerAction::<S>::bump_version(chart, table, key, Some(entry)).await?;
+
+			pending.complete();
+		}
+
+		drop(lock);
+
+		Ok(())
+	}
+}
+
+impl<S: IndexEntry, C: CrudOperation> Debug for BatchAction<'_, S, C> {
+	fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+		let mut state = f.debug_struct("BatchAction");
+
+		state
+			.field("kind", &self.kind())
+			.field("entries", &self.entries.len());
+
+		if let Some(table) = self.table() {
+			state.field("table", &table);
+		}
+
+		state.finish()
+	}
+}
+
+impl<S: IndexEntry, C: CrudOperation> Default for BatchAction<'_, S, C> {
+	fn default() -> Self {
+		Self::new()
+	}
+}
+
+impl<S: IndexEntry, C: CrudOperation> Clone for BatchAction<'_, S, C> {
+	fn clone(&self) -> Self {
+		Self {
+			inner: self.inner.clone(),
+			entries: self.entries.clone(),
+			kind: PhantomData,
+		}
+	}
+}
+
+impl<'a, S: IndexEntry> CreateBatchAction<'a, S> {
+	/// Validates and runs a [`CreateBatchAction`], entries that already exist are left untouched.
+	///
+	/// # Errors
+	///
+	/// This returns an error if [`Self::validate_table`] fails, or if any of the [`Backend`] methods fail.
+	pub fn run_create_many<B: Backend>(
+		self,
+		chart: &'a Starchart<B>,
+	) -> impl Future<Output = Result<(), ActionError>> + 'a {
+		self.observer(chart).run(self.create_many(chart))
+	}
+}
+
+impl<'a, S: IndexEntry> UpdateBatchAction<'a, S> {
+	/// Validates and runs an [`UpdateBatchAction`].
+	///
+	/// # Errors
+	///
+	/// This returns an error if [`Self::validate_table`] fails, or if any of the [`Backend`] methods fail.
+	pub fn run_update_many<B: Backend>(
+		self,
+		chart: &'a Starchart<B>,
+	) -> impl Future<Output = Result<(), ActionError>> + 'a {
+		self.observer(chart).run(self.update_many(chart))
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use std::fmt::Debug;
+
+	use serde::{Deserialize, Serialize};
+	use static_assertions::assert_impl_all;
+
+	use super::{BatchAction, CreateBatchAction};
+	use crate::{
+		action::{CreateOperation, UpdateBatchAction},
+		backend::{Backend, MockBackend},
+		testing::TestSettings,
+		Error, IndexEntry, Starchart,
+	};
+
+	#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+	struct Settings {
+		id: u32,
+	}
+
+	impl IndexEntry for Settings {
+		type Key = u32;
+
+		fn key(&self) -> &Self::Key {
+			&self.id
+		}
+	}
+
+	assert_impl_all!(BatchAction<'static, Settings, CreateOperation>: Clone, Debug, Default, Send, Sync);
+
+	#[test]
+	fn entries() {
+		let first = Settings { id: 1 };
+		let second = Settings { id: 2 };
+
+		let mut action = CreateBatchAction::new();
+		action
+			.set_table("table")
+			.add_entry(&first)
+			.add_entries(vec![&second]);
+
+		assert_eq!(action.table(), Some("table"));
+		assert_eq!(action.entries().len(), 2);
+		assert!(action.validate_table().is_ok());
+	}
+
+	#[tokio::test]
+	async fn batch_actions() -> Result<(), Error> {
+		let chart = Starchart::new(MockBackend::new()).await?;
+		chart.create_table("table").await?;
+
+		let entries = (1..=3)
+			.map(|id| TestSettings {
+				id,
+				..TestSettings::default()
+			})
+			.collect::<Vec<_>>();
+
+		let mut create = CreateBatchAction::new();
+		create.set_table("table").add_entries(&entries);
+		create.run_create_many(&chart).await?;
+
+		let mut keys: Vec<String> = chart.get_keys("table").await?;
+		keys.sort();
+		assert_eq!(keys, ["1", "2", "3"]);
+
+		let updated = entries
+			.iter()
+			.map(|entry| TestSettings {
+				opt: None,
+				..entry.clone()
+			})
+			.collect::<Vec<_>>();
+
+		let mut update = UpdateBatchAction::new();
+		update.set_table("table").add_entries(&updated);
+		update.run_update_many(&chart).await?;
+
+		assert_eq!(
+			chart.get::<TestSettings>("table", "2").await?,
+			Some(updated[1].clone())
+		);
+
+		Ok(())
+	}
+}
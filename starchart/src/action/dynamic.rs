@@ -11,14 +11,14 @@ use serde::{
 
 use super::{
 	ActionError, ActionKind, ActionResult, ActionValidationError, ActionValidationErrorType,
-	CrudOperation, InnerAction, OperationTarget, TargetKind,
+	CreateMode, CrudOperation, InnerAction, OperationTarget, TargetKind,
 };
 use crate::{
 	action::{
 		CreateOperation, DeleteOperation, EntryTarget, ReadOperation, TableTarget, UpdateOperation,
 	},
 	backend::Backend,
-	util::InnerUnwrap,
+	query::PageRange,
 	Action, Entry, IndexEntry, Key, Starchart,
 };
 
@@ -221,10 +221,10 @@ impl<S: Entry + ?Sized> DynamicAction<S> {
 	/// # Errors
 	///
 	/// This will raise an error if any of the static run methods in [`Action`] fail, as it uses those internally.
-	pub async fn run<B: Backend>(
-		self,
-		chart: &Starchart<B>,
-	) -> Result<ActionResult<S>, ActionError> {
+	pub async fn run<B: Backend>(self, chart: &Starchart<B>) -> Result<ActionResult<S>, ActionError>
+	where
+		S: 'static,
+	{
 		match (self.kind(), self.target()) {
 			(ActionKind::Create, TargetKind::Entry) => {
 				let stat = self.as_static::<CreateOperation, EntryTarget>()?;
@@ -284,6 +284,15 @@ impl<S: Entry + ?Sized> DynamicAction<S> {
 				data: self.data.as_deref(),
 				key: self.key.clone(),
 				table: self.table.as_deref(),
+				filter: None,
+				indexer: None,
+				page: PageRange::new(),
+				skip_unchanged: false,
+				ttl: None,
+				expected_version: None,
+				create_mode: CreateMode::Skip,
+				condition: None,
+				lock_timeout: None,
 			},
 			kind: PhantomData,
 			target: PhantomData,
@@ -417,16 +426,10 @@ impl<'de, S: ?Sized> Visitor<'de> for ActionVisitor<S> {
 		E: DeError,
 	{
 		let sections = v.split('.').collect::<Vec<_>>();
-		if !(2..=3).contains(&sections.len()) {
-			return Err(DeError::custom("failed to parse DynamicAction"));
-		}
-
-		let (kind, target, table) = unsafe {
-			(
-				sections.get(0).inner_unwrap(),
-				sections.get(1).inner_unwrap(),
-				sections.get(2),
-			)
+		let (kind, target, table) = match sections.as_slice() {
+			[kind, target] => (kind, target, None),
+			[kind, target, table] => (kind, target, Some(table)),
+			_ => return Err(DeError::custom("failed to parse DynamicAction")),
 		};
 
 		let kind = match *kind {
@@ -3,6 +3,14 @@ use std::{
 	fmt::{Debug, Display, Formatter, Result as FmtResult},
 };
 
+#[cfg(feature = "metadata")]
+use crate::schema::SchemaError;
+use crate::{
+	backend::{corrupted, timed_out},
+	error::ErrorCategory,
+	hook::Rejection,
+};
+
 /// A general [`Action`] error.
 ///
 /// [`Action`]: super::Action
@@ -30,6 +38,90 @@ impl ActionError {
 	pub fn into_parts(self) -> (ActionErrorType, Option<Box<dyn Error + Send + Sync>>) {
 		(self.kind, self.source)
 	}
+
+	/// The [`ErrorCategory`] of the error, for branching on it without matching every type.
+	#[must_use]
+	pub fn category(&self) -> ErrorCategory {
+		match self.kind {
+			ActionErrorType::Run => match self.source.as_deref() {
+				Some(source) if source.is::<crate::Error>() => source
+					.downcast_ref::<crate::Error>()
+					.map_or(ErrorCategory::Backend, crate::Error::category),
+				source => source
+					.and_then(|source| source.downcast_ref::<ActionRunError>())
+					.map_or(ErrorCategory::Backend, ActionRunError::category),
+			},
+			ActionErrorType::Validation | ActionErrorType::Rejected => ErrorCategory::Invalid,
+			ActionErrorType::Timeout => ErrorCategory::Timeout,
+		}
+	}
+
+	/// Whether the error is an [`ErrorCategory::NotFound`] error.
+	#[must_use]
+	pub fn is_not_found(&self) -> bool {
+		self.category() == ErrorCategory::NotFound
+	}
+
+	/// Whether the error is an [`ErrorCategory::Conflict`] error.
+	#[must_use]
+	pub fn is_conflict(&self) -> bool {
+		self.category() == ErrorCategory::Conflict
+	}
+
+	/// Whether the error is an [`ErrorCategory::Invalid`] error.
+	#[must_use]
+	pub fn is_invalid(&self) -> bool {
+		self.category() == ErrorCategory::Invalid
+	}
+
+	/// Whether the error is an [`ErrorCategory::Serialization`] error.
+	#[must_use]
+	pub fn is_serialization(&self) -> bool {
+		self.category() == ErrorCategory::Serialization
+	}
+
+	/// Whether the error is an [`ErrorCategory::Backend`] error.
+	#[must_use]
+	pub fn is_backend(&self) -> bool {
+		self.category() == ErrorCategory::Backend
+	}
+
+	/// Whether the error is an [`ErrorCategory::Timeout`] error.
+	#[must_use]
+	pub fn is_timeout(&self) -> bool {
+		self.category() == ErrorCategory::Timeout
+	}
+
+	pub(crate) fn rejected(err: Rejection) -> Self {
+		Self {
+			source: Some(err),
+			kind: ActionErrorType::Rejected,
+		}
+	}
+
+	/// Wraps the error of a chart operation ran between hooks, which converts back into the same [`Error`].
+	///
+	/// [`Error`]: crate::Error
+	pub(crate) fn chart(err: crate::Error) -> Self {
+		Self {
+			source: Some(Box::new(err)),
+			kind: ActionErrorType::Run,
+		}
+	}
+
+	/// Unwraps the error of a chart operation wrapped with [`Self::chart`].
+	pub(crate) fn into_chart_error(self) -> Result<crate::Error, Self> {
+		match (self.kind, self.source) {
+			(ActionErrorType::Run, Some(source)) => source
+				.downcast::<crate::Error>()
+				.map(|error| *error)
+				.map_err(|source| Self {
+					source: Some(source),
+					kind: ActionErrorType::Run,
+				}),
+			(kind, source) => Err(Self { source, kind }),
+		}
+	}
 }
 
 impl Display for ActionError {
@@ -37,6 +129,8 @@ impl Display for ActionError {
 		match &self.kind {
 			ActionErrorType::Run => f.write_str("a run error occurred"),
 			ActionErrorType::Validation => f.write_str("a validation error occurred"),
+			ActionErrorType::Rejected => f.write_str("the action was rejected by a hook"),
+			ActionErrorType::Timeout => f.write_str("a backend operation timed out"),
 		}
 	}
 }
@@ -52,8 +146,12 @@ impl Error for ActionError {
 impl From<ActionRunError> for ActionError {
 	fn from(err: ActionRunError) -> Self {
 		Self {
+			kind: if err.is_timeout() {
+				ActionErrorType::Timeout
+			} else {
+				ActionErrorType::Run
+			},
 			source: Some(Box::new(err)),
-			kind: ActionErrorType::Run,
 		}
 	}
 }
@@ -78,6 +176,19 @@ pub enum ActionErrorType {
 	Run,
 	/// A validation error has occurred.
 	Validation,
+	/// A [`Hook`] rejected the action before it ran, the source is the error it was rejected with.
+	///
+	/// [`Hook`]: crate::hook::Hook
+	Rejected,
+	/// A backend operation or waiting for the table's lock took too long,
+	/// the source is the [`ActionRunError`] it failed with.
+	///
+	/// This is raised when a [`TimeoutBackend`] gives up on an operation, or when the action's
+	/// [`Action::lock_timeout`] passes.
+	///
+	/// [`Action::lock_timeout`]: super::Action::lock_timeout
+	/// [`TimeoutBackend`]: crate::backend::TimeoutBackend
+	Timeout,
 }
 
 /// An error occurred during validation of an [`Action`].
@@ -112,6 +223,13 @@ impl ActionValidationError {
 	) {
 		(self.kind, self.source)
 	}
+
+	/// The [`ErrorCategory`] of the error, validation errors are always [`ErrorCategory::Invalid`].
+	#[must_use]
+	#[allow(clippy::unused_self)]
+	pub const fn category(&self) -> ErrorCategory {
+		ErrorCategory::Invalid
+	}
 }
 
 impl Display for ActionValidationError {
@@ -164,8 +282,8 @@ pub enum ActionValidationErrorType {
 /// [`Action`]: crate::action::Action
 #[derive(Debug)]
 pub struct ActionRunError {
-	pub(super) source: Option<Box<dyn Error + Send + Sync>>,
-	pub(super) kind: ActionRunErrorType,
+	pub(crate) source: Option<Box<dyn Error + Send + Sync>>,
+	pub(crate) kind: ActionRunErrorType,
 }
 
 impl ActionRunError {
@@ -181,11 +299,71 @@ impl ActionRunError {
 		self.source
 	}
 
+	/// Whether the error was caused by the table not existing.
+	#[must_use]
+	pub const fn is_table_missing(&self) -> bool {
+		matches!(self.kind, ActionRunErrorType::MissingTable)
+	}
+
+	/// Whether the error was caused by a backend operation or waiting for a lock taking too long.
+	#[must_use]
+	pub fn is_timeout(&self) -> bool {
+		match self.kind {
+			ActionRunErrorType::LockTimeout { .. } => true,
+			ActionRunErrorType::Backend => self.source.as_deref().is_some_and(|e| timed_out(e)),
+			_ => false,
+		}
+	}
+
+	/// The [`ErrorCategory`] of the error, for branching on it without matching every type.
+	#[must_use]
+	pub fn category(&self) -> ErrorCategory {
+		match self.kind {
+			ActionRunErrorType::Backend if self.is_timeout() => ErrorCategory::Timeout,
+			ActionRunErrorType::Backend if self.is_corrupt() => ErrorCategory::Serialization,
+			ActionRunErrorType::Backend | ActionRunErrorType::Retention => ErrorCategory::Backend,
+			ActionRunErrorType::MissingTable => ErrorCategory::NotFound,
+			ActionRunErrorType::AlreadyExists
+			| ActionRunErrorType::Conflict { .. }
+			| ActionRunErrorType::NeedsRepair { .. } => ErrorCategory::Conflict,
+			ActionRunErrorType::Loader
+			| ActionRunErrorType::InvalidKey { .. }
+			| ActionRunErrorType::InvalidPatch => ErrorCategory::Serialization,
+			ActionRunErrorType::LockTimeout { .. } => ErrorCategory::Timeout,
+			ActionRunErrorType::KeyChanged { .. } => ErrorCategory::Invalid,
+			#[cfg(feature = "metadata")]
+			ActionRunErrorType::Metadata { .. } | ActionRunErrorType::Schema(_) => ErrorCategory::Invalid,
+		}
+	}
+
+	/// Whether the error was caused by the backend failing to decode an entry, see [`CorruptEntryError`].
+	///
+	/// [`CorruptEntryError`]: crate::backend::CorruptEntryError
+	#[must_use]
+	pub fn is_corrupt(&self) -> bool {
+		matches!(self.kind, ActionRunErrorType::Backend)
+			&& self.source.as_deref().is_some_and(|e| corrupted(e))
+	}
+
 	/// Consume the error, returning the owned error type and the source error.
 	#[must_use = "consuming the error into it's parts has no effect if left unused"]
 	pub fn into_parts(self) -> (ActionRunErrorType, Option<Box<dyn Error + Send + Sync>>) {
 		(self.kind, self.source)
 	}
+
+	pub(crate) const fn lock_timeout(table: String) -> Self {
+		Self {
+			source: None,
+			kind: ActionRunErrorType::LockTimeout { table },
+		}
+	}
+
+	pub(crate) const fn key_changed(key: String) -> Self {
+		Self {
+			source: None,
+			kind: ActionRunErrorType::KeyChanged { key },
+		}
+	}
 }
 
 impl Display for ActionRunError {
@@ -195,6 +373,42 @@ impl Display for ActionRunError {
 			ActionRunErrorType::MissingTable => {
 				f.write_str("an operation was ran on a missing table")
 			}
+			ActionRunErrorType::Loader => {
+				f.write_str("a loader returned a value that doesn't match the entry type")
+			}
+			ActionRunErrorType::AlreadyExists => f.write_str("the entry already exists"),
+			ActionRunErrorType::Conflict { expected, found } => {
+				f.write_str("expected entry to be at version ")?;
+				Display::fmt(expected, f)?;
+				f.write_str(", but it was at version ")?;
+				Display::fmt(found, f)
+			}
+			ActionRunErrorType::InvalidKey { key } => {
+				f.write_str("the key ")?;
+				Debug::fmt(key, f)?;
+				f.write_str(" could not be parsed into the key type")
+			}
+			ActionRunErrorType::NeedsRepair { table } => {
+				f.write_str("the table ")?;
+				Debug::fmt(table, f)?;
+				f.write_str(" has partially written entries that need repair")
+			}
+			ActionRunErrorType::InvalidPatch => {
+				f.write_str("the patched fields don't match the entry type")
+			}
+			ActionRunErrorType::Retention => f.write_str(
+				"the entry was written, but it couldn't be retained in the table's history",
+			),
+			ActionRunErrorType::LockTimeout { table } => {
+				f.write_str("the table ")?;
+				Debug::fmt(table, f)?;
+				f.write_str(" was still locked when the lock timeout passed")
+			}
+			ActionRunErrorType::KeyChanged { key } => {
+				f.write_str("the entry at ")?;
+				Debug::fmt(key, f)?;
+				f.write_str(" was modified to have a different key")
+			}
 			#[cfg(feature = "metadata")]
 			ActionRunErrorType::Metadata {
 				type_name,
@@ -205,6 +419,11 @@ impl Display for ActionRunError {
 				f.write_str(" does not match the metadata for table ")?;
 				Display::fmt(&table_name, f)
 			}
+			#[cfg(feature = "metadata")]
+			ActionRunErrorType::Schema(err) => {
+				f.write_str("invalid entry was provided, ")?;
+				Display::fmt(err, f)
+			}
 		}
 	}
 }
@@ -228,6 +447,61 @@ pub enum ActionRunErrorType {
 	Backend,
 	/// An operation was ran on a missing table.
 	MissingTable,
+	/// A value returned from a loader couldn't be converted to the entry type.
+	Loader,
+	/// The entry already exists, and the action was set to [`CreateMode::Fail`].
+	///
+	/// [`CreateMode::Fail`]: crate::action::CreateMode::Fail
+	AlreadyExists,
+	/// The entry was modified since it was read, see [`UpdateEntryAction::expect_version`].
+	///
+	/// [`UpdateEntryAction::expect_version`]: crate::action::UpdateEntryAction::expect_version
+	Conflict {
+		/// The version the entry was expected to be at.
+		expected: u64,
+		/// The version the entry was found at.
+		found: u64,
+	},
+	/// A key read from the table couldn't be parsed with [`FromKey`].
+	///
+	/// [`FromKey`]: crate::FromKey
+	InvalidKey {
+		/// The key that couldn't be parsed.
+		key: String,
+	},
+	/// The table has entries that need repair, and the chart's [`RepairPolicy`] is [`RepairPolicy::Fail`].
+	///
+	/// [`RepairPolicy`]: crate::repair::RepairPolicy
+	/// [`RepairPolicy::Fail`]: crate::repair::RepairPolicy::Fail
+	NeedsRepair {
+		/// The table that needs repair.
+		table: String,
+	},
+	/// The entry with a [`PatchAction`]'s fields merged in couldn't be converted back into the entry type,
+	/// or the entry type dropped or changed one of the fields, such as a field it doesn't have.
+	///
+	/// [`PatchAction`]: crate::action::PatchAction
+	InvalidPatch,
+	/// The entry was written, but it couldn't be retained in the table's history, see
+	/// [`StarchartBuilder::keep_history`]. The source is the cause.
+	///
+	/// [`StarchartBuilder::keep_history`]: crate::StarchartBuilder::keep_history
+	Retention,
+	/// Another operation held the table's lock for longer than the action's [`Action::lock_timeout`].
+	///
+	/// [`Action::lock_timeout`]: crate::action::Action::lock_timeout
+	LockTimeout {
+		/// The table that was locked.
+		table: String,
+	},
+	/// An entry was modified in place, but the modification changed the entry's [`IndexEntry::key`].
+	/// Entries are moved by deleting and re-creating them instead.
+	///
+	/// [`IndexEntry::key`]: crate::IndexEntry::key
+	KeyChanged {
+		/// The key the entry is stored at.
+		key: String,
+	},
 	/// A value did not match the table's metadata.
 	#[cfg(feature = "metadata")]
 	Metadata {
@@ -236,4 +510,7 @@ pub enum ActionRunErrorType {
 		/// The table metadata to match against.
 		table_name: String,
 	},
+	/// A field of the entry being written didn't match the table's recorded schema.
+	#[cfg(feature = "metadata")]
+	Schema(SchemaError),
 }
@@ -0,0 +1,116 @@
+use super::ActionError;
+
+/// The entries of a table read with [`ReadTableAction::run_read_table_lossy`], along with
+/// the entries that couldn't be read.
+///
+/// An entry that fails to deserialize (such as one written by an older version of it's type)
+/// is skipped and listed with it's error, instead of failing the whole read.
+///
+/// [`ReadTableAction::run_read_table_lossy`]: super::ReadTableAction::run_read_table_lossy
+#[derive(Debug)]
+#[must_use = "a lossy read should be checked for skipped entries"]
+pub struct LossyTable<S> {
+	pub(super) entries: Vec<(String, S)>,
+	pub(super) skipped: Vec<(String, ActionError)>,
+}
+
+impl<S> LossyTable<S> {
+	/// The entries that were read, along with their keys, in key order.
+	#[must_use]
+	pub fn entries(&self) -> &[(String, S)] {
+		&self.entries
+	}
+
+	/// The keys of the entries that couldn't be read, along with why, in key order.
+	#[must_use]
+	pub fn skipped(&self) -> &[(String, ActionError)] {
+		&self.skipped
+	}
+
+	/// Whether every entry was read.
+	#[must_use]
+	pub fn is_complete(&self) -> bool {
+		self.skipped.is_empty()
+	}
+
+	/// Consumes the result, returning the entries that were read without their keys.
+	#[must_use]
+	pub fn into_entries(self) -> Vec<S> {
+		self.entries.into_iter().map(|(_, entry)| entry).collect()
+	}
+
+	/// Consumes the result, returning the entries that were read and the entries that were skipped.
+	#[must_use]
+	#[allow(clippy::type_complexity)]
+	pub fn into_parts(self) -> (Vec<(String, S)>, Vec<(String, ActionError)>) {
+		(self.entries, self.skipped)
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use crate::{
+		action::ReadTableAction,
+		backend::{Backend, MockBackend},
+		testing::TestSettings,
+		Error, Starchart,
+	};
+
+	#[tokio::test]
+	async fn read_table_lossy() -> Result<(), Error> {
+		let chart = Starchart::new(MockBackend::new()).await?;
+		chart.create_table("table").await?;
+
+		let table = chart.table::<TestSettings>("table");
+		for id in [1, 2, 3] {
+			table
+				.insert(&TestSettings {
+					id,
+					..TestSettings::default()
+				})
+				.await?;
+		}
+
+		chart.update("table", "2", &"corrupt".to_owned()).await?;
+
+		let mut action = ReadTableAction::<TestSettings>::new();
+		action.set_table("table");
+		assert!(action
+			.clone()
+			.run_read_table::<_, Vec<_>>(&chart)
+			.await
+			.is_err());
+
+		let result = action.run_read_table_lossy(&chart).await?;
+		assert!(!result.is_complete());
+		assert_eq!(
+			result
+				.entries()
+				.iter()
+				.map(|(key, entry)| (key.as_str(), entry.id))
+				.collect::<Vec<_>>(),
+			[("1", 1), ("3", 3)]
+		);
+		assert_eq!(
+			result
+				.skipped()
+				.iter()
+				.map(|(key, _)| key.as_str())
+				.collect::<Vec<_>>(),
+			["2"]
+		);
+
+		let mut action = ReadTableAction::<TestSettings>::new();
+		action.set_table("table").offset(1);
+		let entries = action.run_read_table_lossy(&chart).await?.into_entries();
+		assert_eq!(
+			entries.iter().map(|entry| entry.id).collect::<Vec<_>>(),
+			[3]
+		);
+
+		chart.delete("table", "2").await?;
+		assert!(table.read_lossy().await?.is_complete());
+
+		Ok(())
+	}
+}
@@ -1,25 +1,35 @@
 //! The action structs for CRUD operations.
 
-// TODO: Add overwrite option.
-
+mod batch;
 mod dynamic;
 mod error;
 mod r#impl;
 mod kind;
+mod lossy;
+mod mode;
+mod patch;
 mod result;
+mod scan;
 mod target;
 
 #[cfg(feature = "metadata")]
 use std::any::type_name;
 use std::{
+	collections::HashSet,
 	fmt::{Debug, Formatter, Result as FmtResult},
-	iter::FromIterator,
+	iter::{self, FromIterator},
 	marker::PhantomData,
+	ops::RangeBounds,
+	time::Duration,
 };
 
 #[cfg(not(feature = "metadata"))]
 use futures_util::future::ok;
-use futures_util::Future;
+use futures_util::{
+	future::ready,
+	stream::{once, Stream},
+	Future, TryFutureExt, TryStreamExt,
+};
 
 #[doc(hidden)]
 pub use self::error::{
@@ -27,21 +37,40 @@ pub use self::error::{
 	ActionValidationErrorType,
 };
 pub use self::{
+	batch::{BatchAction, CreateBatchAction, UpdateBatchAction},
 	dynamic::DynamicAction,
 	kind::ActionKind,
+	lossy::LossyTable,
+	mode::CreateMode,
+	patch::PatchAction,
 	r#impl::{
 		CreateOperation, CrudOperation, DeleteOperation, EntryTarget, OperationTarget,
 		ReadOperation, TableTarget, UpdateOperation,
 	},
 	result::ActionResult,
+	scan::Scan,
 	target::TargetKind,
 };
-#[cfg(feature = "metadata")]
-use crate::METADATA_KEY;
 use crate::{
+	atomics::{ExclusiveGuard, SharedGuard},
 	backend::Backend,
-	util::{is_metadata, InnerUnwrap},
-	Entry, IndexEntry, Key, Starchart,
+	expiry,
+	index::{self, Indexes},
+	instrument::Span,
+	journal::ChangeKind,
+	metrics::Observer,
+	pagination,
+	query::{Filter, PageRange},
+	repair::{self, RepairPolicy},
+	schema::SchemaValue,
+	util::{fingerprint, is_metadata},
+	version::{self, Versioned},
+	Entry, FromKey, IndexEntry, Key, Starchart,
+};
+#[cfg(feature = "metadata")]
+use crate::{
+	schema::{self, MetadataPolicy},
+	METADATA_KEY,
 };
 
 /// A type alias for an [`Action`] with [`CreateOperation`] and [`EntryTarget`] as the parameters.
@@ -75,6 +104,15 @@ pub(crate) struct InnerAction<'a, S: ?Sized> {
 	pub data: Option<&'a S>,
 	pub key: Option<String>,
 	pub table: Option<&'a str>,
+	pub filter: Option<Filter<'a, S>>,
+	pub indexer: Option<fn(&S) -> Indexes>,
+	pub page: PageRange,
+	pub skip_unchanged: bool,
+	pub ttl: Option<Duration>,
+	pub expected_version: Option<u64>,
+	pub create_mode: CreateMode,
+	pub condition: Option<Filter<'a, S>>,
+	pub lock_timeout: Option<Duration>,
 }
 
 impl<'a, S: ?Sized> InnerAction<'a, S> {
@@ -83,45 +121,105 @@ impl<'a, S: ?Sized> InnerAction<'a, S> {
 			data: None,
 			key: None,
 			table: None,
+			filter: None,
+			indexer: None,
+			page: PageRange::new(),
+			skip_unchanged: false,
+			ttl: None,
+			expected_version: None,
+			create_mode: CreateMode::Skip,
+			condition: None,
+			lock_timeout: None,
 		}
 	}
 
-	fn validate_entry(&self) -> Result<(), ActionValidationError> {
-		self.validate_key()?;
-		self.validate_data()
+	/// Locks the table for reading, failing if it's still locked once the lock timeout has passed.
+	fn lock_shared<'c, B: Backend>(
+		&self,
+		chart: &'c Starchart<B>,
+		table: &str,
+	) -> Result<SharedGuard<'c>, ActionRunError> {
+		self.lock_timeout.map_or_else(
+			|| Ok(chart.guard.shared(table)),
+			|timeout| {
+				chart
+					.guard
+					.shared_within(table, timeout)
+					.ok_or_else(|| ActionRunError::lock_timeout(table.to_owned()))
+			},
+		)
 	}
 
-	fn validate_table(&self) -> Result<(), ActionValidationError> {
-		if self.table.is_none() {
-			return Err(ActionValidationError {
-				source: None,
-				kind: ActionValidationErrorType::Table,
-			});
+	/// Locks the tables, failing if any are still locked once the lock timeout has passed.
+	///
+	/// The first table is the one the action runs on, and is the one reported if the lock times out.
+	fn lock_exclusive<'c, B: Backend>(
+		&self,
+		chart: &'c Starchart<B>,
+		tables: &[&str],
+	) -> Result<ExclusiveGuard<'c>, ActionRunError> {
+		let tables = tables.iter().copied();
+
+		match self.lock_timeout {
+			Some(timeout) => chart
+				.guard
+				.exclusive_many_within(tables.clone(), timeout)
+				.ok_or_else(|| ActionRunError::lock_timeout(tables.take(1).collect())),
+			None => Ok(chart.guard.exclusive_many(tables)),
 		}
+	}
 
-		self.validate_metadata(self.table)
+	fn validate_entry(&self) -> Result<(&str, &'a S), ActionValidationError> {
+		Ok((self.validate_key()?, self.validate_data()?))
 	}
 
-	fn validate_data(&self) -> Result<(), ActionValidationError> {
-		if self.data.is_none() {
-			return Err(ActionValidationError {
-				source: None,
-				kind: ActionValidationErrorType::Data,
-			});
-		}
+	fn validate_table(&self) -> Result<&'a str, ActionValidationError> {
+		let table = self.table.ok_or(ActionValidationError {
+			source: None,
+			kind: ActionValidationErrorType::Table,
+		})?;
 
-		Ok(())
+		self.validate_metadata(Some(table))?;
+
+		Ok(table)
 	}
 
-	fn validate_key(&self) -> Result<(), ActionValidationError> {
-		if self.key.is_none() {
-			return Err(ActionValidationError {
-				source: None,
-				kind: ActionValidationErrorType::Key,
-			});
-		}
+	fn validate_data(&self) -> Result<&'a S, ActionValidationError> {
+		self.data.ok_or(ActionValidationError {
+			source: None,
+			kind: ActionValidationErrorType::Data,
+		})
+	}
+
+	fn validate_key(&self) -> Result<&str, ActionValidationError> {
+		let key = self.key.as_deref().ok_or(ActionValidationError {
+			source: None,
+			kind: ActionValidationErrorType::Key,
+		})?;
+
+		self.validate_metadata(Some(key))?;
+
+		Ok(key)
+	}
+
+	/// Validates the table and key, taking them out of the action to run it.
+	fn take_key(&mut self) -> Result<(&'a str, String), ActionValidationError> {
+		let table = self.validate_table()?;
+		let key = self.key.take().ok_or(ActionValidationError {
+			source: None,
+			kind: ActionValidationErrorType::Key,
+		})?;
+
+		self.validate_metadata(Some(&key))?;
+
+		Ok((table, key))
+	}
 
-		self.validate_metadata(self.key.as_deref())
+	/// Validates the table, key, and data, taking them out of the action to run it.
+	fn take_entry(&mut self) -> Result<(&'a str, String, &'a S), ActionValidationError> {
+		let (table, key) = self.take_key()?;
+
+		Ok((table, key, self.validate_data()?))
 	}
 
 	#[cfg(feature = "metadata")]
@@ -145,30 +243,127 @@ impl<'a, S: ?Sized> InnerAction<'a, S> {
 }
 
 impl<'a, S: Entry + ?Sized> InnerAction<'a, S> {
+	/// Checks the table's metadata matches the entry type, according to the chart's [`MetadataPolicy`].
+	///
+	/// Metadata is only repaired by writes, which hold the table's exclusive lock, reads leave it to the next write.
 	#[cfg(feature = "metadata")]
 	async fn check_metadata<B: Backend>(
 		&self,
-		backend: &B,
+		chart: &Starchart<B>,
 		table_name: &str,
+		write: bool,
 	) -> Result<(), ActionRunError> {
+		let backend = &**chart;
+
+		let error = |e: B::Error| ActionRunError {
+			source: Some(Box::new(e)),
+			kind: ActionRunErrorType::Metadata {
+				type_name: type_name::<S>(),
+				table_name: table_name.to_owned(),
+			},
+		};
+
+		let policy = chart.metadata_policy();
+		let mismatched = match policy {
+			MetadataPolicy::Ignore => return Ok(()),
+			MetadataPolicy::Strict => {
+				return backend
+					.get::<S>(table_name, METADATA_KEY)
+					.await
+					.map(|_| {})
+					.map_err(error);
+			}
+			MetadataPolicy::Repair | MetadataPolicy::Replace => {
+				match backend.get::<S>(table_name, METADATA_KEY).await {
+					Ok(Some(_)) => return Ok(()),
+					Ok(None) => false,
+					Err(e) if policy == MetadataPolicy::Repair => return Err(error(e)),
+					Err(e) => {
+						// only replace metadata that was read, so a failing backend isn't mistaken for a mismatch.
+						backend
+							.get::<SchemaValue>(table_name, METADATA_KEY)
+							.await
+							.map_err(|_| error(e))?;
+
+						true
+					}
+				}
+			}
+		};
+
+		if !write {
+			return Ok(());
+		}
+
+		log::warn!(
+			"{} metadata for table {:?}, replacing it with the metadata for {}",
+			if mismatched { "mismatched" } else { "missing" },
+			table_name,
+			type_name::<S>()
+		);
+
 		backend
-			.get::<S>(table_name, METADATA_KEY)
+			.replace(table_name, METADATA_KEY, &S::default())
+			.await
+			.map_err(error)
+	}
+
+	#[cfg(not(feature = "metadata"))]
+	fn check_metadata<B: Backend>(
+		&self,
+		_: &Starchart<B>,
+		_: &str,
+		_: bool,
+	) -> impl Future<Output = Result<(), ActionRunError>> {
+		ok(())
+	}
+
+	/// Checks that the entries being written match the schema recorded in the table's metadata.
+	#[cfg(feature = "metadata")]
+	async fn check_schema<B: Backend>(
+		chart: &Starchart<B>,
+		table_name: &str,
+		entries: &[&S],
+	) -> Result<(), ActionRunError> {
+		// the recorded schema can only be read as an untyped value from self-describing backends.
+		if chart.metadata_policy() == MetadataPolicy::Ignore || !chart.is_self_describing() {
+			return Ok(());
+		}
+
+		let Some(recorded) = chart
+			.get::<SchemaValue>(table_name, METADATA_KEY)
 			.await
-			.map(|_| {})
 			.map_err(|e| ActionRunError {
+				source: Some(Box::new(e)),
+				kind: ActionRunErrorType::Backend,
+			})?
+		else {
+			return Ok(());
+		};
+
+		for entry in entries {
+			let value = schema::to_schema_value(*entry).map_err(|e| ActionRunError {
 				source: Some(Box::new(e)),
 				kind: ActionRunErrorType::Metadata {
 					type_name: type_name::<S>(),
 					table_name: table_name.to_owned(),
 				},
-			})
+			})?;
+
+			schema::validate(&recorded, &value).map_err(|e| ActionRunError {
+				source: None,
+				kind: ActionRunErrorType::Schema(e),
+			})?;
+		}
+
+		Ok(())
 	}
 
 	#[cfg(not(feature = "metadata"))]
-	fn check_metadata<B: Backend>(
-		&self,
-		_: &B,
+	fn check_schema<B: Backend>(
+		_: &Starchart<B>,
 		_: &str,
+		_: &[&S],
 	) -> impl Future<Output = Result<(), ActionRunError>> {
 		ok(())
 	}
@@ -191,188 +386,916 @@ impl<'a, S: Entry + ?Sized> InnerAction<'a, S> {
 		}
 	}
 
-	async fn create_entry<B: Backend>(mut self, chart: &Starchart<B>) -> Result<(), ActionError> {
-		self.validate_entry()?;
-		self.validate_table()?;
+	/// Fails if the table has entries that need repair, and the chart's [`RepairPolicy`] is [`RepairPolicy::Fail`].
+	fn check_repairs<B: Backend>(chart: &Starchart<B>, table: &str) -> Result<(), ActionRunError> {
+		if chart.repair_policy() == RepairPolicy::Fail && chart.repairs.contains(table) {
+			return Err(ActionRunError {
+				source: None,
+				kind: ActionRunErrorType::NeedsRepair {
+					table: table.to_owned(),
+				},
+			});
+		}
 
-		let lock = chart.guard.exclusive();
+		Ok(())
+	}
 
-		let backend = &**chart;
+	/// Repairs the entries of the table that need it before writing, see [`Self::check_repairs`].
+	///
+	/// This must only be called while holding the table's exclusive lock.
+	async fn repair_table<B: Backend>(
+		chart: &Starchart<B>,
+		table: &str,
+		indexer: Option<fn(&S) -> Indexes>,
+	) -> Result<(), ActionRunError> {
+		Self::check_repairs(chart, table)?;
 
-		let (table, key, entry) = unsafe {
-			(
-				self.table.take().inner_unwrap(),
-				self.key.take().inner_unwrap(),
-				self.data.take().inner_unwrap(),
-			)
-		};
+		if !chart.repairs.contains(table) {
+			return Ok(());
+		}
 
-		self.check_table(backend, table).await?;
-		self.check_metadata(backend, table).await?;
+		repair::run(chart, table, indexer).await.map(drop)
+	}
 
-		backend
-			.ensure(table, &key, &*entry)
+	/// Reads the index values of the stored entry, if the action maintains indexes.
+	async fn previous_indexes<B: Backend>(
+		&self,
+		backend: &B,
+		table: &str,
+		key: &str,
+	) -> Result<Indexes, ActionRunError> {
+		let Some(indexer) = self.indexer else {
+			return Ok(Vec::new());
+		};
+
+		let previous = backend
+			.get::<S>(table, key)
 			.await
 			.map_err(|e| ActionRunError {
 				source: Some(Box::new(e)),
 				kind: ActionRunErrorType::Backend,
 			})?;
 
-		drop(lock);
-		Ok(())
+		Ok(previous.as_ref().map(indexer).unwrap_or_default())
 	}
 
-	async fn read_entry<B: Backend>(
-		mut self,
+	/// Records the expiry time of the entry, if a TTL was set.
+	///
+	/// Entries created or replaced without a TTL forget any expiry time left over from an earlier
+	/// entry at the same key, while updates keep it.
+	async fn set_expiry<B: Backend>(
+		&self,
 		chart: &Starchart<B>,
-	) -> Result<Option<S>, ActionError> {
-		self.validate_table()?;
-		self.validate_key()?;
-
-		let lock = chart.guard.shared();
-
+		table: &str,
+		key: &str,
+		replaced: bool,
+	) -> Result<(), ActionRunError> {
 		let backend = &**chart;
 
-		let (table, key) = unsafe {
-			(
-				self.table.take().inner_unwrap(),
-				self.key.take().inner_unwrap(),
-			)
-		};
-
-		self.check_table(backend, table).await?;
-		self.check_metadata(backend, table).await?;
+		match self.ttl {
+			Some(ttl) => expiry::set(backend, table, key, chart.clock().now() + ttl).await,
+			None if replaced => expiry::clear(backend, table, key).await,
+			None => Ok(()),
+		}
+		.map_err(|e| ActionRunError {
+			source: Some(Box::new(e)),
+			kind: ActionRunErrorType::Backend,
+		})
+	}
 
-		let res = backend.get(table, &key).await.map_err(|e| ActionRunError {
+	/// Returns the keys of the table's expired entries, which reads over the table skip.
+	async fn expired_keys<B: Backend>(
+		chart: &Starchart<B>,
+		table: &str,
+	) -> Result<HashSet<String>, ActionRunError> {
+		chart.expired_keys(table).await.map_err(|e| ActionRunError {
 			source: Some(Box::new(e)),
 			kind: ActionRunErrorType::Backend,
-		})?;
+		})
+	}
 
-		drop(lock);
+	/// Removes the expired entries found while reading a table, once the table has been unlocked.
+	async fn purge_expired<B: Backend>(
+		chart: &Starchart<B>,
+		table: &str,
+		expired: &HashSet<String>,
+	) -> Result<(), ActionRunError> {
+		for key in expired {
+			chart.purge_expired_entry(table, key).await?;
+		}
 
-		Ok(res)
+		Ok(())
 	}
 
-	async fn update_entry<B: Backend>(mut self, chart: &Starchart<B>) -> Result<(), ActionError> {
-		self.validate_table()?;
-		self.validate_entry()?;
+	/// Appends a change to the entry to the journal before it's made, if journaling is enabled.
+	async fn journal<B: Backend>(
+		chart: &Starchart<B>,
+		table: &str,
+		key: &str,
+		kind: ChangeKind,
+	) -> Result<(), ActionRunError> {
+		chart
+			.record_change(table, Some(key), kind)
+			.await
+			.map_err(|e| ActionRunError {
+				source: Some(Box::new(e)),
+				kind: ActionRunErrorType::Backend,
+			})
+	}
 
-		let lock = chart.guard.exclusive();
+	/// Increments the version of the entry if versions are tracked for the table, retaining the written entry
+	/// if the table keeps a history, with [`None`] recording a delete.
+	async fn bump_version<B: Backend>(
+		chart: &Starchart<B>,
+		table: &str,
+		key: &str,
+		entry: Option<&S>,
+	) -> Result<(), ActionRunError> {
+		chart
+			.record_write(table, key)
+			.await
+			.map_err(|e| ActionRunError {
+				source: Some(Box::new(e)),
+				kind: ActionRunErrorType::Backend,
+			})?;
 
-		let backend = &**chart;
+		chart.retain(table, key, entry).await
+	}
 
-		let (table, key, entry) = unsafe {
-			(
-				self.table.take().inner_unwrap(),
-				self.key.take().inner_unwrap(),
-				self.data.take().inner_unwrap(),
-			)
+	/// Checks that the stored entry is at the expected version, if one was set.
+	async fn check_version<B: Backend>(
+		&self,
+		backend: &B,
+		table: &str,
+		key: &str,
+	) -> Result<(), ActionRunError> {
+		let Some(expected) = self.expected_version else {
+			return Ok(());
 		};
 
-		self.check_table(backend, table).await?;
-		self.check_metadata(backend, table).await?;
+		let found = version::get(backend, table, key)
+			.await
+			.map_err(|e| ActionRunError {
+				source: Some(Box::new(e)),
+				kind: ActionRunErrorType::Backend,
+			})?;
 
-		backend
-			.update(table, &key, &*entry)
+		if found == expected {
+			Ok(())
+		} else {
+			Err(ActionRunError {
+				source: None,
+				kind: ActionRunErrorType::Conflict { expected, found },
+			})
+		}
+	}
+
+	/// Checks the stored entry against the condition, if one was set.
+	///
+	/// Missing entries never pass the condition.
+	async fn check_condition<B: Backend>(
+		&self,
+		backend: &B,
+		table: &str,
+		key: &str,
+	) -> Result<bool, ActionRunError> {
+		let Some(condition) = &self.condition else {
+			return Ok(true);
+		};
+
+		let stored = backend
+			.get::<S>(table, key)
 			.await
 			.map_err(|e| ActionRunError {
 				source: Some(Box::new(e)),
 				kind: ActionRunErrorType::Backend,
 			})?;
 
-		drop(lock);
+		Ok(stored.is_some_and(|stored| condition.matches(key, &stored)))
+	}
 
-		Ok(())
+	/// Checks whether the stored entry serializes the same as the given one.
+	async fn is_unchanged<B: Backend>(
+		&self,
+		backend: &B,
+		table: &str,
+		key: &str,
+		entry: &S,
+	) -> Result<bool, ActionRunError> {
+		let stored = backend
+			.get::<S>(table, key)
+			.await
+			.map_err(|e| ActionRunError {
+				source: Some(Box::new(e)),
+				kind: ActionRunErrorType::Backend,
+			})?;
+
+		Ok(stored.is_some_and(|stored| {
+			let stored = fingerprint(&stored);
+			stored.is_some() && stored == fingerprint(entry)
+		}))
 	}
 
-	async fn delete_entry<B: Backend>(mut self, chart: &Starchart<B>) -> Result<bool, ActionError> {
-		self.validate_table()?;
-		self.validate_key()?;
-		let lock = chart.guard.exclusive();
+	async fn create_entry<B: Backend>(mut self, chart: &Starchart<B>) -> Result<(), ActionError> {
+		let (table, key, entry) = self.take_entry()?;
 
 		let backend = &**chart;
 
-		let (table, key) = unsafe {
-			(
-				self.table.take().inner_unwrap(),
-				self.key.take().inner_unwrap(),
-			)
-		};
+		let lock = self.lock_exclusive(chart, &[table])?;
 
-		self.check_table(backend, table).await?;
-		self.check_metadata(backend, table).await?;
+		self.check_writable_table(chart, table).await?;
+		self.check_metadata(chart, table, true).await?;
+		Self::check_schema(chart, table, &[entry]).await?;
+		Self::repair_table(chart, table, self.indexer).await?;
 
-		if !backend.has(table, &key).await.map_err(|e| ActionRunError {
+		chart.record_payload(table, &key, entry);
+
+		let mut existed = backend.has(table, &key).await.map_err(|e| ActionRunError {
 			source: Some(Box::new(e)),
 			kind: ActionRunErrorType::Backend,
-		})? {
-			drop(lock);
-			return Ok(false);
+		})?;
+
+		// an expired entry that hasn't been purged yet is replaced as if it were missing.
+		if existed
+			&& chart
+				.is_expired(table, &key)
+				.await
+				.map_err(|e| ActionRunError {
+					source: Some(Box::new(e)),
+					kind: ActionRunErrorType::Backend,
+				})? {
+			let previous = self.previous_indexes(backend, table, &key).await?;
+
+			chart.remove_expired(table, &key).await?;
+
+			index::update(chart, table, &key, &previous, &[])
+				.await
+				.map_err(|e| ActionRunError {
+					source: Some(Box::new(e)),
+					kind: ActionRunErrorType::Backend,
+				})?;
+
+			existed = false;
+		}
+
+		match self.create_mode {
+			CreateMode::Fail if existed => {
+				return Err(ActionRunError {
+					source: None,
+					kind: ActionRunErrorType::AlreadyExists,
+				}
+				.into());
+			}
+			CreateMode::Replace if existed => {
+				self.replace_entry(chart, table, &key, entry).await?;
+
+				drop(lock);
+				return Ok(());
+			}
+			_ => {}
+		}
+
+		if !existed {
+			Self::journal(chart, table, &key, ChangeKind::Write).await?;
 		}
 
 		backend
-			.delete(table, &key)
+			.ensure(table, &key, &*entry)
 			.await
 			.map_err(|e| ActionRunError {
 				source: Some(Box::new(e)),
 				kind: ActionRunErrorType::Backend,
 			})?;
 
-		drop(lock);
+		let pending = chart.repairs.pending(table, &key);
 
-		Ok(true)
-	}
+		if let Some(indexer) = self.indexer.filter(|_| !existed) {
+			index::update(chart, table, &key, &[], &indexer(entry))
+				.await
+				.map_err(|e| ActionRunError {
+					source: Some(Box::new(e)),
+					kind: ActionRunErrorType::Backend,
+				})?;
+		}
 
-	async fn create_table<B: Backend>(self, chart: &Starchart<B>) -> Result<(), ActionError> {
-		self.validate_table()?;
+		if !existed {
+			self.set_expiry(chart, table, &key, true).await?;
+			Self::bump_version(chart, table, &key, Some(entry)).await?;
+		}
 
-		let lock = chart.guard.exclusive();
+		pending.complete();
+
+		drop(lock);
+		Ok(())
+	}
 
+	/// Replaces an existing entry, as an update that doesn't check the version.
+	async fn replace_entry<B: Backend>(
+		&self,
+		chart: &Starchart<B>,
+		table: &str,
+		key: &str,
+		entry: &S,
+	) -> Result<(), ActionRunError> {
 		let backend = &**chart;
 
-		let table = unsafe { self.table.inner_unwrap() };
+		let previous = self.previous_indexes(backend, table, key).await?;
+
+		Self::journal(chart, table, key, ChangeKind::Write).await?;
 
 		backend
-			.ensure_table(table)
+			.replace(table, key, entry)
 			.await
 			.map_err(|e| ActionRunError {
 				source: Some(Box::new(e)),
 				kind: ActionRunErrorType::Backend,
 			})?;
 
-		#[cfg(feature = "metadata")]
-		{
-			let metadata = S::default();
-			backend
-				.ensure(table, METADATA_KEY, &metadata)
+		let pending = chart.repairs.pending(table, key);
+
+		if let Some(indexer) = self.indexer {
+			index::update(chart, table, key, &previous, &indexer(entry))
 				.await
 				.map_err(|e| ActionRunError {
 					source: Some(Box::new(e)),
-					kind: ActionRunErrorType::Metadata {
-						type_name: type_name::<S>(),
-						table_name: table.to_owned(),
-					},
+					kind: ActionRunErrorType::Backend,
 				})?;
 		}
 
-		drop(lock);
+		self.set_expiry(chart, table, key, true).await?;
+
+		Self::bump_version(chart, table, key, Some(entry)).await?;
+
+		pending.complete();
 
 		Ok(())
 	}
 
-	async fn read_table<B: Backend, I>(mut self, chart: &Starchart<B>) -> Result<I, ActionError>
+	async fn read_entry<B: Backend>(
+		mut self,
+		chart: &Starchart<B>,
+	) -> Result<Option<S>, ActionError>
 	where
-		I: FromIterator<S>,
+		S: 'static,
 	{
-		self.validate_table()?;
-		let lock = chart.guard.shared();
+		let (table, key) = self.take_key()?;
 
 		let backend = &**chart;
 
-		let table = unsafe { self.table.take().inner_unwrap() };
+		let lock = self.lock_shared(chart, table)?;
 
 		self.check_table(backend, table).await?;
-		self.check_metadata(backend, table).await?;
+		self.check_metadata(chart, table, false).await?;
+		Self::check_repairs(chart, table)?;
+
+		let mut res = backend.get(table, &key).await.map_err(|e| ActionRunError {
+			source: Some(Box::new(e)),
+			kind: ActionRunErrorType::Backend,
+		})?;
+
+		let expired = res.is_some()
+			&& !chart.pins.is_pinned(table, &key)
+			&& expiry::is_expired(backend, table, &key, chart.clock().now())
+				.await
+				.map_err(|e| ActionRunError {
+					source: Some(Box::new(e)),
+					kind: ActionRunErrorType::Backend,
+				})?;
+
+		drop(lock);
+
+		if expired {
+			chart.purge_expired_entry(table, &key).await?;
+			res = None;
+		}
+
+		if res.is_none() && chart.loaders.contains(table) {
+			return self.load_missing(chart, table, &key).await;
+		}
+
+		Ok(res)
+	}
+
+	/// Reads an entry along with it's version, starting to track versions for the table if needed.
+	///
+	/// Expired entries aren't returned, but unlike [`Self::read_entry`] loaders aren't ran for missing entries.
+	async fn read_versioned<B: Backend>(
+		mut self,
+		chart: &Starchart<B>,
+	) -> Result<Option<Versioned<S>>, ActionError> {
+		let (table, key) = self.take_key()?;
+
+		let backend = &**chart;
+
+		let lock = self.lock_exclusive(chart, &[table])?;
+
+		self.check_table(backend, table).await?;
+		self.check_metadata(chart, table, true).await?;
+		Self::check_repairs(chart, table)?;
+
+		let Some(entry) = backend.get(table, &key).await.map_err(|e| ActionRunError {
+			source: Some(Box::new(e)),
+			kind: ActionRunErrorType::Backend,
+		})?
+		else {
+			return Ok(None);
+		};
+
+		let expired = !chart.pins.is_pinned(table, &key)
+			&& expiry::is_expired(backend, table, &key, chart.clock().now())
+				.await
+				.map_err(|e| ActionRunError {
+					source: Some(Box::new(e)),
+					kind: ActionRunErrorType::Backend,
+				})?;
+
+		if expired {
+			return Ok(None);
+		}
+
+		let version = version::get(backend, table, &key)
+			.await
+			.map_err(|e| ActionRunError {
+				source: Some(Box::new(e)),
+				kind: ActionRunErrorType::Backend,
+			})?;
+
+		drop(lock);
+
+		Ok(Some(Versioned { version, entry }))
+	}
+
+	/// Runs the table's loader for a missing entry, persisting the loaded value.
+	///
+	/// The value is persisted with a [`CreateEntryAction`], so it's checked and recorded like any other
+	/// created entry, and an entry created at the key while loading is kept and returned instead.
+	async fn load_missing<B: Backend>(
+		&self,
+		chart: &Starchart<B>,
+		table: &str,
+		key: &str,
+	) -> Result<Option<S>, ActionError>
+	where
+		S: 'static,
+	{
+		let loaded = chart
+			.loaders
+			.load::<S>(table, key, chart.clock().now())
+			.await
+			.map_err(|e| ActionRunError {
+				source: Some(Box::new(e)),
+				kind: ActionRunErrorType::Loader,
+			})?;
+
+		let Some(entry) = loaded else {
+			return Ok(None);
+		};
+
+		let mut create = CreateEntryAction::for_table(table).with_data(&entry);
+		create.inner.key = Some(key.to_owned());
+		create.inner.lock_timeout = self.lock_timeout;
+		create.run_create_entry(chart).await?;
+
+		let lock = self.lock_shared(chart, table)?;
+
+		let res = chart.get(table, key).await.map_err(|e| ActionRunError {
+			source: Some(Box::new(e)),
+			kind: ActionRunErrorType::Backend,
+		})?;
+
+		drop(lock);
+
+		Ok(res)
+	}
+
+	/// Updates the entry, returning whether it was written.
+	async fn update_entry<B: Backend>(mut self, chart: &Starchart<B>) -> Result<bool, ActionError> {
+		let (table, key, entry) = self.take_entry()?;
+
+		let backend = &**chart;
+
+		let lock = self.lock_exclusive(chart, &[table])?;
+
+		self.check_writable_table(chart, table).await?;
+		self.check_metadata(chart, table, true).await?;
+		Self::check_schema(chart, table, &[entry]).await?;
+		Self::repair_table(chart, table, self.indexer).await?;
+
+		self.check_version(backend, table, &key).await?;
+
+		if !self.check_condition(backend, table, &key).await? {
+			drop(lock);
+
+			return Ok(false);
+		}
+
+		if self.skip_unchanged && self.is_unchanged(backend, table, &key, entry).await? {
+			drop(lock);
+
+			return Ok(false);
+		}
+
+		chart.record_payload(table, &key, entry);
+
+		let previous = self.previous_indexes(backend, table, &key).await?;
+
+		Self::journal(chart, table, &key, ChangeKind::Write).await?;
+
+		backend
+			.update(table, &key, &*entry)
+			.await
+			.map_err(|e| ActionRunError {
+				source: Some(Box::new(e)),
+				kind: ActionRunErrorType::Backend,
+			})?;
+
+		let pending = chart.repairs.pending(table, &key);
+
+		if let Some(indexer) = self.indexer {
+			index::update(chart, table, &key, &previous, &indexer(entry))
+				.await
+				.map_err(|e| ActionRunError {
+					source: Some(Box::new(e)),
+					kind: ActionRunErrorType::Backend,
+				})?;
+		}
+
+		self.set_expiry(chart, table, &key, false).await?;
+
+		Self::bump_version(chart, table, &key, Some(entry)).await?;
+
+		pending.complete();
+
+		drop(lock);
+
+		Ok(true)
+	}
+
+	/// Reads, modifies, and writes back an entry under a single exclusive lock, returning the modified entry.
+	///
+	/// The entry's indexes are kept up to date if an indexer is set. Fails without writing if the closure
+	/// changes the entry's key.
+	pub(crate) async fn modify_entry<B: Backend, F>(
+		mut self,
+		chart: &Starchart<B>,
+		f: F,
+	) -> Result<Option<S>, ActionError>
+	where
+		S: IndexEntry,
+		F: FnOnce(&mut S) -> Result<(), ActionRunError> + Send,
+	{
+		let (table, key) = self.take_key()?;
+
+		let backend = &**chart;
+
+		let lock = self.lock_exclusive(chart, &[table])?;
+
+		self.check_table(backend, table).await?;
+		self.check_metadata(chart, table, true).await?;
+		Self::repair_table(chart, table, self.indexer).await?;
+
+		let Some(mut entry) = backend
+			.get::<S>(table, &key)
+			.await
+			.map_err(|e| ActionRunError {
+				source: Some(Box::new(e)),
+				kind: ActionRunErrorType::Backend,
+			})?
+		else {
+			return Ok(None);
+		};
+
+		let expired = !chart.pins.is_pinned(table, &key)
+			&& expiry::is_expired(backend, table, &key, chart.clock().now())
+				.await
+				.map_err(|e| ActionRunError {
+					source: Some(Box::new(e)),
+					kind: ActionRunErrorType::Backend,
+				})?;
+
+		if expired {
+			return Ok(None);
+		}
+
+		let previous = self
+			.indexer
+			.map(|indexer| indexer(&entry))
+			.unwrap_or_default();
+		let previous_key = entry.key().to_key();
+
+		f(&mut entry)?;
+
+		if entry.key().to_key() != previous_key {
+			return Err(ActionRunError::key_changed(key).into());
+		}
+
+		Self::check_schema(chart, table, &[&entry]).await?;
+
+		chart.record_payload(table, &key, &entry);
+
+		Self::journal(chart, table, &key, ChangeKind::Write).await?;
+
+		backend
+			.update(table, &key, &entry)
+			.await
+			.map_err(|e| ActionRunError {
+				source: Some(Box::new(e)),
+				kind: ActionRunErrorType::Backend,
+			})?;
+
+		let pending = chart.repairs.pending(table, &key);
+
+		if let Some(indexer) = self.indexer {
+			index::update(chart, table, &key, &previous, &indexer(&entry))
+				.await
+				.map_err(|e| ActionRunError {
+					source: Some(Box::new(e)),
+					kind: ActionRunErrorType::Backend,
+				})?;
+		}
+
+		Self::bump_version(chart, table, &key, Some(&entry)).await?;
+
+		pending.complete();
+
+		drop(lock);
+
+		Ok(Some(entry))
+	}
+
+	async fn delete_entry<B: Backend>(mut self, chart: &Starchart<B>) -> Result<bool, ActionError> {
+		let (table, key) = self.take_key()?;
+
+		let backend = &**chart;
+
+		let lock = self.lock_exclusive(chart, &[table])?;
+
+		self.check_table(backend, table).await?;
+		self.check_metadata(chart, table, true).await?;
+		Self::repair_table(chart, table, self.indexer).await?;
+
+		if !backend.has(table, &key).await.map_err(|e| ActionRunError {
+			source: Some(Box::new(e)),
+			kind: ActionRunErrorType::Backend,
+		})? {
+			drop(lock);
+			return Ok(false);
+		}
+
+		let previous = self.previous_indexes(backend, table, &key).await?;
+
+		Self::journal(chart, table, &key, ChangeKind::Delete).await?;
+
+		backend
+			.delete(table, &key)
+			.await
+			.map_err(|e| ActionRunError {
+				source: Some(Box::new(e)),
+				kind: ActionRunErrorType::Backend,
+			})?;
+
+		let pending = chart.repairs.pending(table, &key);
+
+		index::update(chart, table, &key, &previous, &[])
+			.await
+			.map_err(|e| ActionRunError {
+				source: Some(Box::new(e)),
+				kind: ActionRunErrorType::Backend,
+			})?;
+
+		expiry::clear(backend, table, &key)
+			.await
+			.map_err(|e| ActionRunError {
+				source: Some(Box::new(e)),
+				kind: ActionRunErrorType::Backend,
+			})?;
+
+		Self::bump_version(chart, table, &key, None).await?;
+
+		pending.complete();
+
+		drop(lock);
+
+		Ok(true)
+	}
+
+	async fn create_table<B: Backend>(self, chart: &Starchart<B>) -> Result<(), ActionError> {
+		let table = self.validate_table()?;
+
+		let backend = &**chart;
+
+		let lock = self.lock_exclusive(chart, &[table])?;
+
+		Self::init_table(backend, table).await?;
+
+		drop(lock);
+
+		Ok(())
+	}
+
+	/// Creates the table if it doesn't exist, along with it's metadata.
+	async fn init_table<B: Backend>(backend: &B, table: &str) -> Result<(), ActionRunError> {
+		backend
+			.ensure_table(table)
+			.await
+			.map_err(|e| ActionRunError {
+				source: Some(Box::new(e)),
+				kind: ActionRunErrorType::Backend,
+			})?;
+
+		#[cfg(feature = "metadata")]
+		{
+			let metadata = S::default();
+			backend
+				.ensure(table, METADATA_KEY, &metadata)
+				.await
+				.map_err(|e| ActionRunError {
+					source: Some(Box::new(e)),
+					kind: ActionRunErrorType::Metadata {
+						type_name: type_name::<S>(),
+						table_name: table.to_owned(),
+					},
+				})?;
+		}
+
+		Ok(())
+	}
+
+	/// Checks the table exists before writing to it, creating it if the chart auto-creates tables.
+	async fn check_writable_table<B: Backend>(
+		&self,
+		chart: &Starchart<B>,
+		table: &str,
+	) -> Result<(), ActionRunError> {
+		if chart.auto_creates_tables() {
+			Self::init_table(&**chart, table).await
+		} else {
+			self.check_table(&**chart, table).await
+		}
+	}
+
+	fn stream_table<B: Backend>(
+		mut self,
+		chart: &'a Starchart<B>,
+	) -> impl Stream<Item = Result<(String, S), ActionError>> + 'a {
+		let setup = async move {
+			let table = self.validate_table()?;
+
+			let backend = &**chart;
+
+			let lock = self.lock_shared(chart, table)?;
+
+			self.check_table(backend, table).await?;
+			self.check_metadata(chart, table, false).await?;
+			Self::check_repairs(chart, table)?;
+
+			let expired = Self::expired_keys(chart, table).await?;
+
+			drop(lock);
+
+			Self::purge_expired(chart, table, &expired).await?;
+
+			let filter = self.filter.take();
+
+			let entries = backend
+				.get_all_stream::<S>(table)
+				.map_err(|e| {
+					ActionError::from(ActionRunError {
+						source: Some(Box::new(e)),
+						kind: ActionRunErrorType::Backend,
+					})
+				})
+				.try_filter(move |(key, entry)| {
+					ready(
+						!expired.contains(key)
+							&& filter.as_ref().map_or_else(
+								|| !is_metadata(key),
+								|filter| filter.matches(key, entry),
+							),
+					)
+				});
+
+			Ok::<_, ActionError>(entries)
+		};
+
+		once(Box::pin(setup)).try_flatten()
+	}
+
+	/// Reads a page of entries that match the filter, skipping the expired keys.
+	///
+	/// If there's a filter, the range is applied after filtering.
+	async fn filtered_page<B: Backend, I>(
+		backend: &B,
+		table: &str,
+		filter: Option<&Filter<'_, S>>,
+		range: &PageRange,
+		expired: &HashSet<String>,
+	) -> Result<I, B::Error>
+	where
+		I: FromIterator<S>,
+	{
+		let entries = Self::keyed_page(backend, table, filter, range, expired).await?;
+
+		Ok(entries.into_iter().map(|(_, entry)| entry).collect())
+	}
+
+	/// Reads a page of entries along with their keys in key order, skipping the expired keys.
+	///
+	/// If there's a filter, the range is applied after filtering.
+	async fn keyed_page<B: Backend>(
+		backend: &B,
+		table: &str,
+		filter: Option<&Filter<'_, S>>,
+		range: &PageRange,
+		expired: &HashSet<String>,
+	) -> Result<Vec<(String, S)>, B::Error> {
+		let keys = backend
+			.get_keys_in_range::<Vec<String>>(table, &range.bounds())
+			.await?;
+
+		let limit = range.limit.unwrap_or(usize::MAX);
+		let mut skipped = 0;
+		let mut entries = Vec::new();
+
+		for key in keys {
+			if entries.len() >= limit {
+				break;
+			}
+
+			if expired.contains(&key) {
+				continue;
+			}
+
+			let Some(entry) = backend.get::<S>(table, &key).await? else {
+				continue;
+			};
+
+			if filter.is_some_and(|filter| !filter.matches(&key, &entry)) {
+				continue;
+			}
+
+			if skipped < range.offset {
+				skipped += 1;
+			} else {
+				entries.push((key, entry));
+			}
+		}
+
+		Ok(entries)
+	}
+
+	async fn read_table<B: Backend, I>(mut self, chart: &Starchart<B>) -> Result<I, ActionError>
+	where
+		I: FromIterator<S>,
+	{
+		let table = self.validate_table()?;
+
+		let backend = &**chart;
+
+		let lock = self.lock_shared(chart, table)?;
+
+		self.check_table(backend, table).await?;
+		self.check_metadata(chart, table, false).await?;
+		Self::check_repairs(chart, table)?;
+
+		let expired = Self::expired_keys(chart, table).await?;
+
+		// the backend can only read the whole table or page when nothing in it has expired.
+		if let Some(filter) = self.filter.take() {
+			let data = if self.page.is_unbounded() && expired.is_empty() {
+				backend.get_filtered::<S, I>(table, &filter).await
+			} else {
+				Self::filtered_page(backend, table, Some(&filter), &self.page, &expired).await
+			}
+			.map_err(|e| ActionRunError {
+				source: Some(Box::new(e)),
+				kind: ActionRunErrorType::Backend,
+			})?;
+
+			drop(lock);
+
+			Self::purge_expired(chart, table, &expired).await?;
+
+			return Ok(data);
+		}
+
+		if !self.page.is_unbounded() {
+			let data = if expired.is_empty() {
+				backend.get_page::<S, I>(table, &self.page).await
+			} else {
+				Self::filtered_page(backend, table, None, &self.page, &expired).await
+			}
+			.map_err(|e| ActionRunError {
+				source: Some(Box::new(e)),
+				kind: ActionRunErrorType::Backend,
+			})?;
+
+			drop(lock);
+
+			Self::purge_expired(chart, table, &expired).await?;
+
+			return Ok(data);
+		}
 
 		let keys = backend
 			.get_keys::<Vec<_>>(table)
@@ -385,7 +1308,7 @@ impl<'a, S: Entry + ?Sized> InnerAction<'a, S> {
 		let keys = keys
 			.iter()
 			.filter_map(|v| {
-				if is_metadata(v) {
+				if is_metadata(v) || expired.contains(v) {
 					None
 				} else {
 					Some(v.as_str())
@@ -393,47 +1316,277 @@ impl<'a, S: Entry + ?Sized> InnerAction<'a, S> {
 			})
 			.collect::<Vec<_>>();
 
-		let data = backend
-			.get_all::<S, I>(table, &keys)
+		let data = backend
+			.get_all::<S, I>(table, &keys)
+			.await
+			.map_err(|e| ActionRunError {
+				source: Some(Box::new(e)),
+				kind: ActionRunErrorType::Backend,
+			})?;
+
+		drop(lock);
+
+		Self::purge_expired(chart, table, &expired).await?;
+
+		Ok(data)
+	}
+
+	/// Reads the entries at the given keys, in the same order, with [`None`] for missing entries.
+	///
+	/// Expired entries and entries that don't match the filter are [`None`], loaders aren't ran for missing entries.
+	async fn read_entries<B: Backend>(
+		self,
+		chart: &Starchart<B>,
+		keys: Vec<String>,
+	) -> Result<Vec<Option<S>>, ActionError> {
+		let table = self.validate_table()?;
+
+		let backend = &**chart;
+
+		let lock = self.lock_shared(chart, table)?;
+
+		self.check_table(backend, table).await?;
+		self.check_metadata(chart, table, false).await?;
+		Self::check_repairs(chart, table)?;
+
+		let (positions, ids): (Vec<_>, Vec<_>) = keys
+			.iter()
+			.map(String::as_str)
+			.enumerate()
+			.filter(|(_, key)| !is_metadata(key))
+			.unzip();
+
+		let found = backend
+			.get_multi::<S>(table, &ids)
+			.await
+			.map_err(|e| ActionRunError {
+				source: Some(Box::new(e)),
+				kind: ActionRunErrorType::Backend,
+			})?;
+
+		let mut entries = iter::repeat_with(|| None)
+			.take(keys.len())
+			.collect::<Vec<_>>();
+		let mut expired = Vec::new();
+		for ((position, key), entry) in positions.into_iter().zip(ids).zip(found) {
+			let Some(entry) = entry else {
+				continue;
+			};
+
+			if !chart.pins.is_pinned(table, key)
+				&& expiry::is_expired(backend, table, key, chart.clock().now())
+					.await
+					.map_err(|e| ActionRunError {
+						source: Some(Box::new(e)),
+						kind: ActionRunErrorType::Backend,
+					})? {
+				expired.push(key);
+			} else if self
+				.filter
+				.as_ref()
+				.is_none_or(|filter| filter.matches(key, &entry))
+			{
+				entries[position] = Some(entry);
+			}
+		}
+
+		drop(lock);
+
+		for key in expired {
+			chart.purge_expired_entry(table, key).await?;
+		}
+
+		Ok(entries)
+	}
+
+	async fn read_table_lossy<B: Backend>(
+		mut self,
+		chart: &Starchart<B>,
+	) -> Result<LossyTable<S>, ActionError> {
+		let table = self.validate_table()?;
+
+		let backend = &**chart;
+
+		let lock = self.lock_shared(chart, table)?;
+
+		self.check_table(backend, table).await?;
+		self.check_metadata(chart, table, false).await?;
+		Self::check_repairs(chart, table)?;
+
+		let keys = backend
+			.get_keys_in_range::<Vec<String>>(table, &self.page.bounds())
+			.await
+			.map_err(|e| ActionRunError {
+				source: Some(Box::new(e)),
+				kind: ActionRunErrorType::Backend,
+			})?;
+
+		let expired = Self::expired_keys(chart, table).await?;
+
+		let filter = self.filter.take();
+		let limit = self.page.limit.unwrap_or(usize::MAX);
+		let mut skipped_offset = 0;
+		let mut result = LossyTable {
+			entries: Vec::new(),
+			skipped: Vec::new(),
+		};
+
+		for key in keys {
+			if result.entries.len() >= limit {
+				break;
+			}
+
+			if expired.contains(&key) {
+				continue;
+			}
+
+			let entry = match backend.get::<S>(table, &key).await {
+				Ok(Some(entry)) => entry,
+				Ok(None) => continue,
+				Err(e) => {
+					result.skipped.push((
+						key,
+						ActionRunError {
+							source: Some(Box::new(e)),
+							kind: ActionRunErrorType::Backend,
+						}
+						.into(),
+					));
+					continue;
+				}
+			};
+
+			if filter
+				.as_ref()
+				.is_some_and(|filter| !filter.matches(&key, &entry))
+			{
+				continue;
+			}
+
+			if skipped_offset < self.page.offset {
+				skipped_offset += 1;
+			} else {
+				result.entries.push((key, entry));
+			}
+		}
+
+		drop(lock);
+
+		Self::purge_expired(chart, table, &expired).await?;
+
+		Ok(result)
+	}
+
+	async fn read_map<B: Backend, K, M>(mut self, chart: &Starchart<B>) -> Result<M, ActionError>
+	where
+		K: FromKey,
+		M: FromIterator<(K, S)>,
+	{
+		let table = self.validate_table()?;
+
+		let backend = &**chart;
+
+		let lock = self.lock_shared(chart, table)?;
+
+		self.check_table(backend, table).await?;
+		self.check_metadata(chart, table, false).await?;
+		Self::check_repairs(chart, table)?;
+
+		let expired = Self::expired_keys(chart, table).await?;
+
+		let filter = self.filter.take();
+		let entries = Self::keyed_page(backend, table, filter.as_ref(), &self.page, &expired)
+			.await
+			.map_err(|e| ActionRunError {
+				source: Some(Box::new(e)),
+				kind: ActionRunErrorType::Backend,
+			})?;
+
+		drop(lock);
+
+		Self::purge_expired(chart, table, &expired).await?;
+
+		entries
+			.into_iter()
+			.map(|(key, entry)| {
+				K::from_key(&key)
+					.map(|parsed| (parsed, entry))
+					.ok_or_else(|| {
+						ActionRunError {
+							source: None,
+							kind: ActionRunErrorType::InvalidKey { key },
+						}
+						.into()
+					})
+			})
+			.collect()
+	}
+
+	async fn delete_table<B: Backend>(self, chart: &Starchart<B>) -> Result<bool, ActionError> {
+		let table = self.validate_table()?;
+
+		let backend = &**chart;
+
+		let lock = self.lock_exclusive(chart, &[table])?;
+
+		self.check_table(backend, table).await?;
+		self.check_metadata(chart, table, true).await?;
+
+		if !backend.has_table(table).await.map_err(|e| ActionRunError {
+			source: Some(Box::new(e)),
+			kind: ActionRunErrorType::Backend,
+		})? {
+			drop(lock);
+			return Ok(false);
+		}
+
+		chart
+			.record_change(table, None, ChangeKind::DeleteTable)
+			.await
+			.map_err(|e| ActionRunError {
+				source: Some(Box::new(e)),
+				kind: ActionRunErrorType::Backend,
+			})?;
+
+		backend
+			.delete_table(table)
+			.await
+			.map_err(|e| ActionRunError {
+				source: Some(Box::new(e)),
+				kind: ActionRunErrorType::Backend,
+			})?;
+
+		pagination::clear(backend, table)
+			.await
+			.map_err(|e| ActionRunError {
+				source: Some(Box::new(e)),
+				kind: ActionRunErrorType::Backend,
+			})?;
+
+		expiry::clear_table(backend, table)
+			.await
+			.map_err(|e| ActionRunError {
+				source: Some(Box::new(e)),
+				kind: ActionRunErrorType::Backend,
+			})?;
+
+		index::clear_table(chart, table)
 			.await
 			.map_err(|e| ActionRunError {
 				source: Some(Box::new(e)),
 				kind: ActionRunErrorType::Backend,
 			})?;
 
-		drop(lock);
-
-		Ok(data)
-	}
-
-	async fn delete_table<B: Backend>(mut self, chart: &Starchart<B>) -> Result<bool, ActionError> {
-		self.validate_table()?;
-
-		let lock = chart.guard.exclusive();
-
-		let backend = &**chart;
-
-		let table = unsafe { self.table.take().inner_unwrap() };
-
-		self.check_table(backend, table).await?;
-		self.check_metadata(backend, table).await?;
-
-		if !backend.has_table(table).await.map_err(|e| ActionRunError {
-			source: Some(Box::new(e)),
-			kind: ActionRunErrorType::Backend,
-		})? {
-			drop(lock);
-			return Ok(false);
-		}
-
-		backend
-			.delete_table(table)
+		version::clear_table(backend, table)
 			.await
 			.map_err(|e| ActionRunError {
 				source: Some(Box::new(e)),
 				kind: ActionRunErrorType::Backend,
 			})?;
 
+		chart.pins.clear_table(table);
+		chart.repairs.clear_table(table);
+
 		drop(lock);
 
 		Ok(true)
@@ -452,12 +1605,24 @@ impl<'a, S: ?Sized> Clone for InnerAction<'a, S> {
 			key: self.key.clone(),
 			data: self.data,
 			table: self.table,
+			filter: self.filter.clone(),
+			indexer: self.indexer,
+			page: self.page.clone(),
+			skip_unchanged: self.skip_unchanged,
+			ttl: self.ttl,
+			expected_version: self.expected_version,
+			create_mode: self.create_mode,
+			condition: self.condition.clone(),
+			lock_timeout: self.lock_timeout,
 		}
 	}
 }
 
 /// An [`Action`] for easy [`CRUD`] operations within a [`Starchart`].
 ///
+/// Actions can either be set up in place with the `set_*` methods, or built in a single expression
+/// with [`Self::for_table`] and the consuming `with_*` methods, then passed straight to a run method.
+///
 /// [`CRUD`]: https://en.wikipedia.org/wiki/Create,_read,_update_and_delete
 /// [`Starchart`]: crate::Starchart
 #[derive(Clone)]
@@ -478,6 +1643,18 @@ impl<'a, S, C, T> Action<'a, S, C, T> {
 		}
 	}
 
+	/// Creates a new [`Action`] over the given table.
+	pub const fn for_table(table_name: &'a str) -> Self {
+		Self::new().with_table(table_name)
+	}
+
+	/// Sets the table for this action, consuming and returning it.
+	pub const fn with_table(mut self, table_name: &'a str) -> Self {
+		self.inner.table = Some(table_name);
+
+		self
+	}
+
 	/// Get a reference to the currently set table.
 	#[must_use]
 	pub const fn table(&self) -> Option<&str> {
@@ -521,6 +1698,29 @@ impl<'a, S: Entry, C: CrudOperation, T: OperationTarget> Action<'a, S, C, T> {
 		}
 	}
 
+	fn span(&self) -> Span {
+		Span::action(self.kind(), self.target(), self.table(), self.key())
+	}
+
+	fn observer<B: Backend>(&self, chart: &Starchart<B>) -> Observer<'a> {
+		Observer::new(
+			chart,
+			self.kind(),
+			self.target(),
+			self.inner.table,
+			self.key(),
+			self.inner.data,
+		)
+	}
+
+	/// Fails the action with [`ActionRunErrorType::LockTimeout`] if the table it runs on is still locked
+	/// by another operation once the timeout has passed, instead of waiting for it forever.
+	pub fn lock_timeout(&mut self, timeout: Duration) -> &mut Self {
+		self.inner.lock_timeout = Some(timeout);
+
+		self // coverage:ignore-line
+	}
+
 	/// Sets the table for this action.
 	pub fn set_table(&mut self, table_name: &'a str) -> &mut Self {
 		self.inner.table.replace(table_name);
@@ -534,7 +1734,7 @@ impl<'a, S: Entry, C: CrudOperation, T: OperationTarget> Action<'a, S, C, T> {
 	///
 	/// Errors if [`Self::set_table`] has not yet been called.
 	pub fn validate_table(&self) -> Result<(), ActionValidationError> {
-		self.inner.validate_table()
+		self.inner.validate_table().map(drop)
 	}
 
 	/// Validates that the key is not the private metadata key.
@@ -581,13 +1781,31 @@ impl<'a, S: Entry, C: CrudOperation> Action<'a, S, C, EntryTarget> {
 		self // coverage:ignore-line
 	}
 
+	/// Sets the key for the action, consuming and returning it.
+	///
+	/// See [`Self::set_key`].
+	pub fn with_key<K: Key>(mut self, key: &K) -> Self {
+		self.set_key(key);
+
+		self
+	}
+
+	/// Sets the data for the action, consuming and returning it.
+	///
+	/// See [`Self::set_data`].
+	pub fn with_data(mut self, entity: &'a S) -> Self {
+		self.set_data(entity);
+
+		self
+	}
+
 	/// Validate that the key has been set.
 	///
 	/// # Errors
 	///
 	/// Errors if [`Self::set_key`] has not yet been called.
 	pub fn validate_key(&self) -> Result<(), ActionValidationError> {
-		self.inner.validate_key()
+		self.inner.validate_key().map(drop)
 	}
 
 	/// Validates that the data has been set.
@@ -596,7 +1814,7 @@ impl<'a, S: Entry, C: CrudOperation> Action<'a, S, C, EntryTarget> {
 	///
 	/// Errors if [`Self::set_data`] has not yet been called.
 	pub fn validate_data(&self) -> Result<(), ActionValidationError> {
-		self.inner.validate_data()
+		self.inner.validate_data().map(drop)
 	}
 
 	/// Validates that both the key and data have been set.
@@ -605,15 +1823,28 @@ impl<'a, S: Entry, C: CrudOperation> Action<'a, S, C, EntryTarget> {
 	///
 	/// This errors if both the [`Self::set_key`] and [`Self::set_data`] (or [`Self::set_entry`]) has not been called.
 	pub fn validate_entry(&self) -> Result<(), ActionValidationError> {
-		self.inner.validate_entry()
+		self.inner.validate_entry().map(drop)
 	}
 }
 
 impl<'a, S: IndexEntry, C: CrudOperation> Action<'a, S, C, EntryTarget> {
 	/// Sets the [`Entry`] and [`Key`] that this [`Action`] will act over.
+	///
+	/// This also makes the action keep the entry's secondary [`IndexEntry::indexes`] up to date.
 	pub fn set_entry(&mut self, entity: &'a S) -> &mut Self {
+		self.inner.indexer.replace(S::indexes);
+
 		self.set_key(entity.key()).set_data(entity)
 	}
+
+	/// Sets the [`Entry`] and [`Key`] for the action, consuming and returning it.
+	///
+	/// See [`Self::set_entry`].
+	pub fn with_entry(mut self, entity: &'a S) -> Self {
+		self.set_entry(entity);
+
+		self
+	}
 }
 
 impl<'a, S: Entry, C: CrudOperation, T: OperationTarget> Debug for Action<'a, S, C, T> {
@@ -661,6 +1892,34 @@ impl<'a, S: Entry + Unpin, C: CrudOperation, T: OperationTarget> Unpin for Actio
 // Action run impls
 
 impl<'a, S: Entry> CreateEntryAction<'a, S> {
+	/// Sets the entry to expire after the given [`Duration`].
+	///
+	/// The expiry is only set if the entry is created or replaced, expired entries are removed when they're
+	/// next read, or by [`Starchart::purge_expired`]. Entries created or replaced without a TTL never expire.
+	pub fn set_ttl(&mut self, ttl: Duration) -> &mut Self {
+		self.inner.ttl = Some(ttl);
+
+		self // coverage:ignore-line
+	}
+
+	/// Sets what happens if the entry already exists, see [`CreateMode`] for details.
+	pub fn mode(&mut self, mode: CreateMode) -> &mut Self {
+		self.inner.create_mode = mode;
+
+		self // coverage:ignore-line
+	}
+
+	/// Whether to replace the entry if it already exists, instead of leaving it untouched.
+	///
+	/// This is a shorthand for [`Self::mode`] with [`CreateMode::Replace`] or [`CreateMode::Skip`].
+	pub fn overwrite(&mut self, overwrite: bool) -> &mut Self {
+		self.mode(if overwrite {
+			CreateMode::Replace
+		} else {
+			CreateMode::Skip
+		})
+	}
+
 	/// Validates and runs a [`CreateEntryAction`].
 	///
 	/// # Errors
@@ -670,7 +1929,7 @@ impl<'a, S: Entry> CreateEntryAction<'a, S> {
 		self,
 		chart: &'a Starchart<B>,
 	) -> impl Future<Output = Result<(), ActionError>> + 'a {
-		self.inner.create_entry(chart)
+		self.observer(chart).run(self.inner.create_entry(chart))
 	}
 }
 
@@ -683,12 +1942,73 @@ impl<'a, S: Entry> ReadEntryAction<'a, S> {
 	pub fn run_read_entry<B: Backend>(
 		self,
 		gateway: &'a Starchart<B>,
-	) -> impl Future<Output = Result<Option<S>, ActionError>> + 'a {
-		self.inner.read_entry(gateway)
+	) -> impl Future<Output = Result<Option<S>, ActionError>> + 'a
+	where
+		S: 'static,
+	{
+		self.observer(gateway).run(self.inner.read_entry(gateway))
+	}
+
+	/// Validates and runs a [`ReadEntryAction`], returning the entry along with it's [version].
+	///
+	/// Unlike [`Self::run_read_entry`], loaders aren't ran for missing entries.
+	///
+	/// # Errors
+	///
+	/// This returns an error if [`Self::validate_table`] or [`Self::validate_key`] fails, or if any of the [`Backend`] methods fail.
+	///
+	/// [version]: crate::version
+	pub fn run_read_versioned<B: Backend>(
+		self,
+		chart: &'a Starchart<B>,
+	) -> impl Future<Output = Result<Option<Versioned<S>>, ActionError>> + 'a {
+		self.observer(chart).run(self.inner.read_versioned(chart))
 	}
 }
 
 impl<'a, S: Entry> UpdateEntryAction<'a, S> {
+	/// Sets the entry to expire after the given [`Duration`], replacing any previous expiry.
+	///
+	/// Updates without a TTL keep the previous expiry. Expired entries are removed when they're next read, or by [`Starchart::purge_expired`].
+	pub fn set_ttl(&mut self, ttl: Duration) -> &mut Self {
+		self.inner.ttl = Some(ttl);
+
+		self // coverage:ignore-line
+	}
+
+	/// Compares the entry against the stored one before writing, skipping the write if they're identical.
+	///
+	/// This costs an extra read, but avoids rewriting unchanged entries in loops that write back state.
+	pub fn skip_unchanged(&mut self, skip: bool) -> &mut Self {
+		self.inner.skip_unchanged = skip;
+
+		self // coverage:ignore-line
+	}
+
+	/// Only updates the entry if the stored entry passes the predicate, making the update a compare-and-swap.
+	///
+	/// The stored entry is read and checked under the same exclusive lock as the write, so it can't change
+	/// in between. Missing entries never pass, use [`Self::run_conditional_update`] to find out if the entry
+	/// was written.
+	pub fn only_if<F>(&mut self, predicate: F) -> &mut Self
+	where
+		F: Fn(&S) -> bool + Send + Sync + 'a,
+	{
+		self.inner.condition = Some(Filter::entry(predicate));
+
+		self // coverage:ignore-line
+	}
+
+	/// Only updates the entry if it's still at the given [version], failing with
+	/// [`ActionRunErrorType::Conflict`] if it was modified since it was read.
+	///
+	/// [version]: crate::version
+	pub fn expect_version(&mut self, version: u64) -> &mut Self {
+		self.inner.expected_version = Some(version);
+
+		self // coverage:ignore-line
+	}
+
 	/// Validates and runs a [`UpdateEntryAction`].
 	///
 	/// # Errors
@@ -698,7 +2018,23 @@ impl<'a, S: Entry> UpdateEntryAction<'a, S> {
 		self,
 		chart: &'a Starchart<B>,
 	) -> impl Future<Output = Result<(), ActionError>> + 'a {
-		self.inner.update_entry(chart)
+		self.observer(chart)
+			.run(self.inner.update_entry(chart).map_ok(drop))
+	}
+
+	/// Validates and runs a [`UpdateEntryAction`], returning whether the entry was written.
+	///
+	/// The entry isn't written if it didn't pass the condition set with [`Self::only_if`],
+	/// or if it was unchanged and [`Self::skip_unchanged`] was set.
+	///
+	/// # Errors
+	///
+	/// This returns an error if [`Self::validate_table`] or [`Self::validate_entry`] fails, or if any of the [`Backend`] methods fail.
+	pub fn run_conditional_update<B: Backend>(
+		self,
+		chart: &'a Starchart<B>,
+	) -> impl Future<Output = Result<bool, ActionError>> + 'a {
+		self.observer(chart).run(self.inner.update_entry(chart))
 	}
 }
 
@@ -712,7 +2048,7 @@ impl<'a, S: Entry> DeleteEntryAction<'a, S> {
 		self,
 		gateway: &'a Starchart<B>,
 	) -> impl Future<Output = Result<bool, ActionError>> + 'a {
-		self.inner.delete_entry(gateway)
+		self.observer(gateway).run(self.inner.delete_entry(gateway))
 	}
 }
 
@@ -726,11 +2062,80 @@ impl<'a, S: Entry> CreateTableAction<'a, S> {
 		self,
 		gateway: &'a Starchart<B>,
 	) -> impl Future<Output = Result<(), ActionError>> + 'a {
-		self.inner.create_table(gateway)
+		self.observer(gateway).run(self.inner.create_table(gateway))
 	}
 }
 
 impl<'a, S: Entry> ReadTableAction<'a, S> {
+	/// Sets a predicate over deserialized entries, only entries that match will be read.
+	///
+	/// This replaces any filter set with [`Self::filter_value`].
+	pub fn filter<F>(&mut self, predicate: F) -> &mut Self
+	where
+		F: Fn(&S) -> bool + Send + Sync + 'a,
+	{
+		self.inner.filter.replace(Filter::entry(predicate));
+
+		self // coverage:ignore-line
+	}
+
+	/// Sets a predicate over the raw [`SchemaValue`] of entries, only entries that match will be read.
+	///
+	/// This replaces any filter set with [`Self::filter`].
+	pub fn filter_value<F>(&mut self, predicate: F) -> &mut Self
+	where
+		F: Fn(&SchemaValue) -> bool + Send + Sync + 'a,
+	{
+		self.inner.filter.replace(Filter::value(predicate));
+
+		self // coverage:ignore-line
+	}
+
+	/// Only reads entries whose keys sort after the given key.
+	///
+	/// Entries are ordered by key when any of [`Self::after_key`], [`Self::key_prefix`], [`Self::key_range`],
+	/// [`Self::offset`], or [`Self::limit`] are set.
+	pub fn after_key<K: Key>(&mut self, key: &K) -> &mut Self {
+		self.inner.page.after = Some(key.to_key());
+
+		self // coverage:ignore-line
+	}
+
+	/// Only reads entries whose keys start with the given prefix, such as `user:`.
+	///
+	/// Backends that store keys in order only read the keys with the prefix, see [`Backend::get_keys_in_range`].
+	pub fn key_prefix(&mut self, prefix: &str) -> &mut Self {
+		self.inner.page.prefix = Some(prefix.to_owned());
+
+		self // coverage:ignore-line
+	}
+
+	/// Only reads entries whose keys are within the given range, such as `"a".."n"`.
+	///
+	/// Keys are compared as strings, so numeric keys sort lexicographically, with `"10"` before `"9"`.
+	pub fn key_range<K: Key, R: RangeBounds<K>>(&mut self, range: R) -> &mut Self {
+		self.inner.page.start = range.start_bound().map(Key::to_key);
+		self.inner.page.end = range.end_bound().map(Key::to_key);
+
+		self // coverage:ignore-line
+	}
+
+	/// Skips the given amount of entries.
+	///
+	/// If a filter is set, only entries that match it are counted.
+	pub fn offset(&mut self, offset: usize) -> &mut Self {
+		self.inner.page.offset = offset;
+
+		self // coverage:ignore-line
+	}
+
+	/// Reads at most the given amount of entries.
+	pub fn limit(&mut self, limit: usize) -> &mut Self {
+		self.inner.page.limit = Some(limit);
+
+		self // coverage:ignore-line
+	}
+
 	/// Validates and runs a [`ReadTableAction`].
 	///
 	/// # Errors
@@ -743,7 +2148,104 @@ impl<'a, S: Entry> ReadTableAction<'a, S> {
 	where
 		I: FromIterator<S> + 'a,
 	{
-		self.inner.read_table(gateway)
+		self.observer(gateway).run(self.inner.read_table(gateway))
+	}
+
+	/// Validates and runs a [`ReadTableAction`], reading only the entries at the given keys.
+	///
+	/// The entries are returned in the same order as the keys, with [`None`] for entries that don't exist,
+	/// have expired, or don't match the filter. The offset and limit are ignored, and unlike
+	/// [`ReadEntryAction::run_read_entry`], loaders aren't ran for missing entries.
+	///
+	/// # Errors
+	///
+	/// This returns an error if [`Self::validate_table`] fails, or if any of the [`Backend`] methods fail.
+	pub fn run_read_entries<B: Backend, K: Key>(
+		self,
+		gateway: &'a Starchart<B>,
+		keys: &[K],
+	) -> impl Future<Output = Result<Vec<Option<S>>, ActionError>> + 'a {
+		let keys = keys.iter().map(Key::to_key).collect();
+
+		self.observer(gateway)
+			.run(self.inner.read_entries(gateway, keys))
+	}
+
+	/// Validates and runs a [`ReadTableAction`], skipping entries that can't be read instead of failing.
+	///
+	/// Entries are read one at a time in key order, and the filter, offset, and limit only count
+	/// the entries that were read.
+	///
+	/// # Errors
+	///
+	/// This returns an error if [`Self::validate_table`] fails, or if the table or it's keys couldn't be read,
+	/// errors reading individual entries are returned in the [`LossyTable`] instead.
+	pub fn run_read_table_lossy<B: Backend>(
+		self,
+		gateway: &'a Starchart<B>,
+	) -> impl Future<Output = Result<LossyTable<S>, ActionError>> + 'a {
+		self.observer(gateway)
+			.run(self.inner.read_table_lossy(gateway))
+	}
+
+	/// Validates and runs a [`ReadTableAction`], collecting the entries along with their keys,
+	/// parsed with [`FromKey`], such as into a [`BTreeMap<K, S>`] or [`HashMap<K, S>`].
+	///
+	/// Entries are read in the order of their keys as strings, so collections that keep their own
+	/// order (such as a [`BTreeMap`] of integers) are ordered by the parsed keys instead.
+	///
+	/// # Errors
+	///
+	/// This returns an error if [`Self::validate_table`] fails, if any of the [`Backend`] methods fail,
+	/// or if a key couldn't be parsed.
+	///
+	/// [`BTreeMap<K, S>`]: std::collections::BTreeMap
+	/// [`BTreeMap`]: std::collections::BTreeMap
+	/// [`HashMap<K, S>`]: std::collections::HashMap
+	pub fn run_read_map<B: Backend, K, M>(
+		self,
+		gateway: &'a Starchart<B>,
+	) -> impl Future<Output = Result<M, ActionError>> + 'a
+	where
+		K: FromKey + 'a,
+		M: FromIterator<(K, S)> + 'a,
+	{
+		self.observer(gateway).run(self.inner.read_map(gateway))
+	}
+
+	/// Validates a [`ReadTableAction`], returning a [`Scan`] that reads the table in batches of
+	/// `batch_size` keys, only locking the table while each batch is read.
+	///
+	/// The scan starts after the key set with [`Self::after_key`], if any, and only yields entries
+	/// that match the filter. The limit and offset are ignored.
+	///
+	/// # Errors
+	///
+	/// This returns an error if [`Self::validate_table`] fails.
+	pub fn run_scan<B: Backend>(
+		self,
+		gateway: &'a Starchart<B>,
+		batch_size: usize,
+	) -> Result<Scan<'a, B, S>, ActionError> {
+		let table = self.inner.validate_table()?;
+
+		Ok(Scan::new(gateway, self.inner, table, batch_size))
+	}
+
+	/// Validates and runs a [`ReadTableAction`], streaming entries along with their keys instead
+	/// of collecting the whole table.
+	///
+	/// Entries are read from the [`Backend`] as the stream is polled, so writes made
+	/// while streaming may or may not be seen.
+	///
+	/// # Errors
+	///
+	/// The stream yields an error if [`Self::validate_table`] fails, or if any of the [`Backend`] methods fail.
+	pub fn run_stream_table<B: Backend>(
+		self,
+		gateway: &'a Starchart<B>,
+	) -> impl Stream<Item = Result<(String, S), ActionError>> + 'a {
+		self.span().instrument(self.inner.stream_table(gateway))
 	}
 }
 
@@ -757,6 +2259,266 @@ impl<'a, S: Entry> DeleteTableAction<'a, S> {
 		self,
 		gateway: &'a Starchart<B>,
 	) -> impl Future<Output = Result<bool, ActionError>> + 'a {
-		self.inner.delete_table(gateway)
+		self.observer(gateway).run(self.inner.delete_table(gateway))
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use std::collections::{BTreeMap, HashMap};
+
+	use futures_util::{future::join_all, TryStreamExt};
+
+	use super::{
+		ActionError, ActionErrorType, ActionRunError, ActionRunErrorType, ActionValidationError,
+		ActionValidationErrorType, CreateEntryAction, CreateTableAction, ReadEntryAction,
+		ReadTableAction, UpdateEntryAction,
+	};
+	use crate::{
+		backend::{Backend, MockBackend},
+		testing::TestSettings,
+		Error, Starchart,
+	};
+
+	#[tokio::test]
+	async fn stream_table() -> Result<(), Error> {
+		let chart = Starchart::new(MockBackend::new()).await?;
+		chart.create_table("table").await?;
+
+		for id in 1..=3 {
+			let settings = TestSettings {
+				id,
+				..TestSettings::default()
+			};
+			chart.create("table", &id.to_string(), &settings).await?;
+		}
+
+		let mut action = ReadTableAction::<TestSettings>::new();
+		action
+			.set_table("table")
+			.filter(|settings| settings.id != 2);
+
+		let mut entries = action
+			.run_stream_table(&chart)
+			.map_ok(|(key, _)| key)
+			.try_collect::<Vec<_>>()
+			.await
+			.map_err(|e| Error::backend(Some(Box::new(e))))?;
+		entries.sort();
+
+		assert_eq!(entries, ["1", "3"]);
+
+		let mut missing = ReadTableAction::<TestSettings>::new();
+		missing.set_table("missing");
+
+		assert!(missing.run_stream_table(&chart).try_next().await.is_err());
+
+		Ok(())
+	}
+
+	#[tokio::test]
+	async fn modify() -> Result<(), Error> {
+		let chart = Starchart::new(MockBackend::new()).await?;
+		chart.create_table("table").await?;
+
+		let settings = TestSettings::default();
+
+		let mut action = CreateEntryAction::new();
+		action.set_table("table").set_entry(&settings);
+		action.run_create_entry(&chart).await?;
+
+		let modifications = (0..20).map(|i| {
+			chart.modify("table", &1_u32, move |settings: &mut TestSettings| {
+				settings.array.push(i);
+			})
+		});
+
+		for modified in join_all(modifications).await {
+			assert!(modified?.is_some());
+		}
+
+		let mut action = ReadEntryAction::<TestSettings>::new();
+		action.set_table("table").set_key(&1_u32);
+		let settings = action.run_read_entry(&chart).await?.unwrap();
+		assert_eq!(settings.array.len(), 25);
+
+		assert_eq!(
+			chart
+				.modify("table", &2_u32, |_: &mut TestSettings| unreachable!())
+				.await?,
+			None
+		);
+
+		Ok(())
+	}
+
+	#[tokio::test]
+	async fn fluent_actions() -> Result<(), Error> {
+		let chart = Starchart::new(MockBackend::new()).await?;
+		chart.create_table("table").await?;
+
+		let settings = TestSettings::default();
+
+		CreateEntryAction::for_table("table")
+			.with_entry(&settings)
+			.run_create_entry(&chart)
+			.await?;
+
+		let read = ReadEntryAction::<TestSettings>::for_table("table")
+			.with_key(&1_u32)
+			.run_read_entry(&chart)
+			.await?;
+		assert_eq!(read, Some(settings.clone()));
+
+		let updated = TestSettings {
+			opt: Some(2.5),
+			..settings
+		};
+
+		UpdateEntryAction::for_table("table")
+			.with_key(&1_u32)
+			.with_data(&updated)
+			.run_update_entry(&chart)
+			.await?;
+
+		let action = ReadEntryAction::<TestSettings>::new()
+			.with_table("table")
+			.with_key(&1_u32);
+		assert_eq!(action.table(), Some("table"));
+		assert_eq!(action.run_read_entry(&chart).await?, Some(updated));
+
+		Ok(())
+	}
+
+	#[tokio::test]
+	async fn read_map() -> Result<(), Error> {
+		let chart = Starchart::new(MockBackend::new()).await?;
+		chart.create_table("table").await?;
+
+		let table = chart.table::<TestSettings>("table");
+		for id in [1, 2, 10] {
+			table
+				.insert(&TestSettings {
+					id,
+					..TestSettings::default()
+				})
+				.await?;
+		}
+
+		let map: BTreeMap<u32, TestSettings> = table.read_map().await?;
+		assert_eq!(map.keys().copied().collect::<Vec<_>>(), vec![1, 2, 10]);
+		assert!(map.iter().all(|(key, entry)| *key == entry.id));
+
+		let mut action = ReadTableAction::<TestSettings>::new();
+		action
+			.set_table("table")
+			.filter(|entry| entry.id > 1)
+			.limit(1);
+		let map: HashMap<u32, TestSettings> = action.run_read_map(&chart).await?;
+		assert_eq!(map.keys().copied().collect::<Vec<_>>(), vec![10]);
+
+		chart
+			.create("table", "not a number", &TestSettings::default())
+			.await?;
+		let mut action = ReadTableAction::<TestSettings>::new();
+		action.set_table("table");
+		let err = action
+			.run_read_map::<_, u32, BTreeMap<_, _>>(&chart)
+			.await
+			.unwrap_err();
+		let source = err
+			.into_source()
+			.unwrap()
+			.downcast::<ActionRunError>()
+			.unwrap();
+		assert!(
+			matches!(source.kind(), ActionRunErrorType::InvalidKey { key } if key == "not a number")
+		);
+
+		let map: BTreeMap<String, TestSettings> = table.read_map().await?;
+		assert_eq!(map.len(), 4);
+
+		Ok(())
+	}
+
+	#[tokio::test]
+	async fn conditional_update() -> Result<(), Error> {
+		let chart = Starchart::new(MockBackend::new()).await?;
+		chart.create_table("table").await?;
+
+		let original = TestSettings::default();
+
+		let mut action = CreateEntryAction::new();
+		action.set_table("table").set_entry(&original);
+		action.run_create_entry(&chart).await?;
+
+		let swapped = TestSettings {
+			opt: None,
+			..TestSettings::default()
+		};
+
+		let mut action = UpdateEntryAction::new();
+		action
+			.set_table("table")
+			.set_entry(&swapped)
+			.only_if(|stored| stored.opt.is_some());
+		assert!(action.clone().run_conditional_update(&chart).await?);
+
+		// the stored entry no longer passes
+		assert!(!action.run_conditional_update(&chart).await?);
+
+		// missing entries never pass
+		let missing = TestSettings {
+			id: 2,
+			..TestSettings::default()
+		};
+		let mut action = UpdateEntryAction::new();
+		action
+			.set_table("table")
+			.set_entry(&missing)
+			.only_if(|_| true);
+		assert!(!action.run_conditional_update(&chart).await?);
+
+		let mut action = ReadEntryAction::<TestSettings>::new();
+		action.set_table("table").set_key(&2_u32);
+		assert_eq!(action.run_read_entry(&chart).await?, None);
+
+		Ok(())
+	}
+
+	#[tokio::test]
+	async fn unset_fields() -> Result<(), Error> {
+		let chart = Starchart::new(MockBackend::new()).await?;
+
+		let validation = |error: ActionError| {
+			assert!(matches!(error.kind(), ActionErrorType::Validation));
+			let source = error.into_source().unwrap();
+			source
+				.downcast::<ActionValidationError>()
+				.unwrap()
+				.into_parts()
+				.0
+		};
+
+		let error = CreateTableAction::<TestSettings>::new()
+			.run_create_table(&chart)
+			.await
+			.unwrap_err();
+		assert!(matches!(
+			validation(error),
+			ActionValidationErrorType::Table
+		));
+
+		let mut action = ReadEntryAction::<TestSettings>::new();
+		action.set_table("table");
+		let error = action.run_read_entry(&chart).await.unwrap_err();
+		assert!(matches!(validation(error), ActionValidationErrorType::Key));
+
+		let mut action = CreateEntryAction::<TestSettings>::new();
+		action.set_table("table").set_key(&1_u32);
+		let error = action.run_create_entry(&chart).await.unwrap_err();
+		assert!(matches!(validation(error), ActionValidationErrorType::Data));
+
+		Ok(())
 	}
 }
@@ -0,0 +1,82 @@
+/// What a [`CreateEntryAction`] does when the entry already exists.
+///
+/// [`CreateEntryAction`]: crate::action::CreateEntryAction
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum CreateMode {
+	/// Fail with [`ActionRunErrorType::AlreadyExists`].
+	///
+	/// [`ActionRunErrorType::AlreadyExists`]: crate::action::ActionRunErrorType::AlreadyExists
+	Fail,
+	/// Leave the existing entry untouched, this is the default.
+	#[default]
+	Skip,
+	/// Replace the existing entry with [`Backend::replace`].
+	///
+	/// [`Backend::replace`]: crate::backend::Backend::replace
+	Replace,
+}
+
+#[cfg(test)]
+mod tests {
+	use std::fmt::Debug;
+
+	use static_assertions::assert_impl_all;
+
+	use super::CreateMode;
+	use crate::{
+		action::{ActionRunError, ActionRunErrorType, CreateEntryAction, ReadEntryAction},
+		backend::{Backend, MockBackend},
+		testing::TestSettings,
+		Error, Starchart,
+	};
+
+	assert_impl_all!(CreateMode: Clone, Copy, Debug, Default, PartialEq, Send, Sync);
+
+	#[test]
+	fn default() {
+		assert_eq!(CreateMode::default(), CreateMode::Skip);
+	}
+
+	#[tokio::test]
+	async fn create_modes() -> Result<(), Error> {
+		let chart = Starchart::new(MockBackend::new()).await?;
+		chart.create_table("table").await?;
+
+		let mut settings = TestSettings::default();
+
+		let mut action = CreateEntryAction::new();
+		action.set_table("table").set_entry(&settings);
+		action.run_create_entry(&chart).await?;
+
+		let read = || {
+			let mut action = ReadEntryAction::<TestSettings>::new();
+			action.set_table("table").set_key(&1_u32);
+			action.run_read_entry(&chart)
+		};
+
+		let original = settings.clone();
+		settings.opt = None;
+
+		let mut action = CreateEntryAction::new();
+		action.set_table("table").set_entry(&settings);
+		action.clone().run_create_entry(&chart).await?;
+		assert_eq!(read().await?, Some(original.clone()));
+
+		action.mode(CreateMode::Fail);
+		let err = action.clone().run_create_entry(&chart).await.unwrap_err();
+		assert!(err.is_conflict());
+		let source = err
+			.into_source()
+			.unwrap()
+			.downcast::<ActionRunError>()
+			.unwrap();
+		assert!(matches!(source.kind(), ActionRunErrorType::AlreadyExists));
+		assert_eq!(read().await?, Some(original));
+
+		action.overwrite(true);
+		action.run_create_entry(&chart).await?;
+		assert_eq!(read().await?, Some(settings.clone()));
+
+		Ok(())
+	}
+}
@@ -0,0 +1,493 @@
+use std::{
+	fmt::{Debug, Formatter, Result as FmtResult},
+	future::Future,
+	iter,
+};
+
+use serde::Serialize;
+
+use super::{
+	ActionError, ActionKind, ActionRunError, ActionRunErrorType, ActionValidationError,
+	ActionValidationErrorType, InnerAction, TargetKind,
+};
+use crate::{
+	backend::Backend,
+	metrics::Observer,
+	schema::{self, SchemaMap, SchemaValue},
+	IndexEntry, Key, Starchart,
+};
+
+/// An action for changing some fields of an entry, without reading and rewriting the whole entry.
+///
+/// The fields are merged into the stored entry under a single exclusive lock, so no other write
+/// can happen in between. Nested objects are merged field by field, while every other value, including
+/// arrays and nulls, replaces the stored one. The merged entry must still deserialize into the entry type,
+/// and every patched field must survive being serialized again, so fields the entry type doesn't have
+/// fail the patch instead of being silently dropped.
+#[must_use = "an action alone has no side effects"]
+pub struct PatchAction<'a, S> {
+	inner: InnerAction<'a, S>,
+	fields: SchemaMap,
+}
+
+impl<'a, S: IndexEntry> PatchAction<'a, S> {
+	/// Creates a new, empty [`PatchAction`].
+	pub fn new() -> Self {
+		let mut inner = InnerAction::new();
+		inner.indexer.replace(S::indexes);
+
+		Self {
+			inner,
+			fields: SchemaMap::new(),
+		}
+	}
+
+	/// Creates a new [`PatchAction`] for the given table.
+	pub fn for_table(table_name: &'a str) -> Self {
+		let mut action = Self::new();
+		action.set_table(table_name);
+
+		action
+	}
+
+	/// Get a reference to the currently set table.
+	#[must_use]
+	pub const fn table(&self) -> Option<&str> {
+		self.inner.table
+	}
+
+	/// Get a reference to the currently set key.
+	#[must_use]
+	pub fn key(&self) -> Option<&str> {
+		self.inner.key.as_deref()
+	}
+
+	/// Get the fields that will be merged into the entry.
+	#[must_use]
+	pub const fn fields(&self) -> &SchemaMap {
+		&self.fields
+	}
+
+	/// Returns the [`ActionKind`] we will be performing with said action.
+	#[allow(clippy::unused_self)]
+	pub const fn kind(&self) -> ActionKind {
+		ActionKind::Update
+	}
+
+	fn observer<B: Backend>(&self, chart: &Starchart<B>) -> Observer<'a> {
+		Observer::new(
+			chart,
+			self.kind(),
+			TargetKind::Entry,
+			self.inner.table,
+			self.key(),
+			iter::once(&self.fields),
+		)
+	}
+
+	/// Sets the table for this action.
+	pub fn set_table(&mut self, table_name: &'a str) -> &mut Self {
+		self.inner.table.replace(table_name);
+
+		self // coverage:ignore-line
+	}
+
+	/// Sets the key of the entry to patch.
+	pub fn set_key<K: Key>(&mut self, key: &K) -> &mut Self {
+		self.inner.key.replace(key.to_key());
+
+		self // coverage:ignore-line
+	}
+
+	/// Sets a single field to merge into the entry, replacing any value already set for it.
+	pub fn set_field(&mut self, name: &str, value: SchemaValue) -> &mut Self {
+		self.fields.insert(name.to_owned(), value);
+
+		self // coverage:ignore-line
+	}
+
+	/// Sets many fields to merge into the entry, replacing any values already set for them.
+	pub fn set_fields(&mut self, fields: SchemaMap) -> &mut Self {
+		self.fields.extend(fields);
+
+		self // coverage:ignore-line
+	}
+
+	/// Sets the fields to merge from any value that serializes to a map, such as a struct whose
+	/// fields are all [`Option`]s skipped when they're [`None`].
+	///
+	/// # Errors
+	///
+	/// Returns an [`ActionValidationErrorType::Data`] error if the value doesn't serialize to a map.
+	pub fn set_patch<P: Serialize + ?Sized>(
+		&mut self,
+		patch: &P,
+	) -> Result<&mut Self, ActionValidationError> {
+		match schema::to_schema_value(patch) {
+			Ok(SchemaValue::Object(fields)) => Ok(self.set_fields(fields)),
+			Ok(_) => Err(ActionValidationError {
+				source: None,
+				kind: ActionValidationErrorType::Data,
+			}),
+			Err(e) => Err(ActionValidationError {
+				source: Some(Box::new(e)),
+				kind: ActionValidationErrorType::Data,
+			}),
+		}
+	}
+
+	/// Sets the key for the action, consuming and returning it.
+	///
+	/// See [`Self::set_key`].
+	pub fn with_key<K: Key>(mut self, key: &K) -> Self {
+		self.set_key(key);
+
+		self
+	}
+
+	/// Sets a single field for the action, consuming and returning it.
+	///
+	/// See [`Self::set_field`].
+	pub fn with_field(mut self, name: &str, value: SchemaValue) -> Self {
+		self.set_field(name, value);
+
+		self
+	}
+
+	/// Validates that the table key is set.
+	///
+	/// # Errors
+	///
+	/// Errors if [`Self::set_table`] has not yet been called.
+	pub fn validate_table(&self) -> Result<(), ActionValidationError> {
+		self.inner.validate_table().map(drop)
+	}
+
+	/// Validates that the key is set.
+	///
+	/// # Errors
+	///
+	/// Errors if [`Self::set_key`] has not yet been called.
+	pub fn validate_key(&self) -> Result<(), ActionValidationError> {
+		self.inner.validate_key()?;
+		self.inner.validate_metadata(self.key())
+	}
+
+	/// Validates and runs a [`PatchAction`], returning the patched entry, or [`None`] if the entry
+	/// doesn't exist (or has expired).
+	///
+	/// The entry's secondary [`IndexEntry::indexes`] are kept up to date.
+	///
+	/// # Errors
+	///
+	/// This returns an error if [`Self::validate_table`] or [`Self::validate_key`] fails, an
+	/// [`ActionRunErrorType::InvalidPatch`] error if the patched entry doesn't match the entry type or drops any of
+	/// the patched fields, an [`ActionRunErrorType::KeyChanged`] error if the patch changes the entry's key,
+	/// or if any of the [`Backend`] methods fail.
+	pub fn run_patch_entry<B: Backend>(
+		self,
+		chart: &'a Starchart<B>,
+	) -> impl Future<Output = Result<Option<S>, ActionError>> + 'a {
+		self.observer(chart).run(self.patch_entry(chart))
+	}
+
+	async fn patch_entry<B: Backend>(self, chart: &Starchart<B>) -> Result<Option<S>, ActionError> {
+		self.validate_key()?;
+
+		let fields = self.fields;
+
+		self.inner
+			.modify_entry(chart, move |entry| {
+				let invalid = |e: serde_json::Error| ActionRunError {
+					source: Some(Box::new(e)),
+					kind: ActionRunErrorType::InvalidPatch,
+				};
+
+				let mut value = schema::to_schema_value(&*entry).map_err(invalid)?;
+				merge(&mut value, fields.clone());
+				let patched = serde_json::from_value(value).map_err(invalid)?;
+
+				// the entry type may ignore fields it doesn't have, or store a field differently than it was
+				// given, so the patch is only applied if every field reads back the same.
+				let written = schema::to_schema_value(&patched).map_err(invalid)?;
+				if !contains(&written, &fields) {
+					return Err(ActionRunError {
+						source: None,
+						kind: ActionRunErrorType::InvalidPatch,
+					});
+				}
+
+				*entry = patched;
+
+				Ok(())
+			})
+			.await
+	}
+}
+
+/// Merges the fields into the value, merging nested objects field by field.
+fn merge(value: &mut SchemaValue, fields: SchemaMap) {
+	let SchemaValue::Object(stored) = value else {
+		*value = SchemaValue::Object(fields);
+		return;
+	};
+
+	for (name, field) in fields {
+		match (stored.get_mut(&name), field) {
+			(Some(existing @ SchemaValue::Object(_)), SchemaValue::Object(nested)) => {
+				merge(existing, nested);
+			}
+			(_, field) => {
+				stored.insert(name, field);
+			}
+		}
+	}
+}
+
+/// Whether every field reads back from the value the same as it was patched, comparing nested objects
+/// field by field. Null fields may also be left out, as entries often skip serializing [`None`]s.
+fn contains(value: &SchemaValue, fields: &SchemaMap) -> bool {
+	let SchemaValue::Object(stored) = value else {
+		return false;
+	};
+
+	fields
+		.iter()
+		.all(|(name, field)| match (stored.get(name), field) {
+			(Some(existing), SchemaValue::Object(nested)) => contains(existing, nested),
+			(Some(existing), field) => existing == field,
+			(None, field) => field.is_null(),
+		})
+}
+
+impl<S: IndexEntry> Debug for PatchAction<'_, S> {
+	fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+		let mut state = f.debug_struct("PatchAction");
+
+		state.field("fields", &self.fields);
+
+		if let Some(table) = self.table() {
+			state.field("table", &table);
+		}
+
+		if let Some(key) = self.key() {
+			state.field("key", &key);
+		}
+
+		state.finish()
+	}
+}
+
+impl<S: IndexEntry> Default for PatchAction<'_, S> {
+	fn default() -> Self {
+		Self::new()
+	}
+}
+
+impl<S: IndexEntry> Clone for PatchAction<'_, S> {
+	fn clone(&self) -> Self {
+		Self {
+			inner: self.inner.clone(),
+			fields: self.fields.clone(),
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use serde::Serialize;
+	use serde_json::json;
+
+	use super::merge;
+	use crate::{
+		action::{ActionRunError, ActionRunErrorType, CreateEntryAction, PatchAction},
+		backend::{Backend, MockBackend},
+		schema::{SchemaMap, SchemaValue},
+		testing::{Profile, ProfileSettings},
+		Error, Starchart,
+	};
+
+	#[test]
+	fn merging() {
+		let mut value = json!({
+			"name": "entry",
+			"tags": ["a", "b"],
+			"stats": { "views": 1, "likes": 2 },
+			"opt": 1.5,
+		});
+
+		let fields = json!({
+			"tags": ["c"],
+			"stats": { "views": 3 },
+			"opt": null,
+			"new": true,
+		});
+
+		merge(&mut value, fields.as_object().cloned().unwrap());
+
+		assert_eq!(
+			value,
+			json!({
+				"name": "entry",
+				"tags": ["c"],
+				"stats": { "views": 3, "likes": 2 },
+				"opt": null,
+				"new": true,
+			})
+		);
+	}
+
+	#[derive(Debug, Default, Serialize)]
+	struct ProfilePatch {
+		#[serde(skip_serializing_if = "Option::is_none")]
+		email: Option<String>,
+		#[serde(skip_serializing_if = "Option::is_none")]
+		settings: Option<SettingsPatch>,
+	}
+
+	#[derive(Debug, Default, Serialize)]
+	struct SettingsPatch {
+		#[serde(skip_serializing_if = "Option::is_none")]
+		theme: Option<String>,
+		#[serde(skip_serializing_if = "Option::is_none")]
+		notifications: Option<bool>,
+	}
+
+	#[tokio::test]
+	async fn patch() -> Result<(), Error> {
+		let chart = Starchart::new(MockBackend::new()).await?;
+		chart.create_table("profiles").await?;
+
+		let profile = Profile {
+			id: 1,
+			email: "old@example.com".to_owned(),
+			settings: ProfileSettings {
+				theme: "light".to_owned(),
+				notifications: true,
+			},
+		};
+		CreateEntryAction::for_table("profiles")
+			.with_entry(&profile)
+			.run_create_entry(&chart)
+			.await?;
+
+		let mut action = PatchAction::<Profile>::for_table("profiles");
+		action.set_key(&1_u32).set_patch(&ProfilePatch {
+			email: Some("new@example.com".to_owned()),
+			settings: Some(SettingsPatch {
+				theme: Some("dark".to_owned()),
+				notifications: None,
+			}),
+		})?;
+		let patched = action.run_patch_entry(&chart).await?;
+
+		let expected = Profile {
+			email: "new@example.com".to_owned(),
+			settings: ProfileSettings {
+				theme: "dark".to_owned(),
+				notifications: true,
+			},
+			..profile
+		};
+		assert_eq!(patched, Some(expected.clone()));
+		assert_eq!(chart.get::<Profile>("profiles", "1").await?, Some(expected));
+		assert!(chart
+			.find_by_index::<Profile, _, Vec<_>>("profiles", "email", &"old@example.com")
+			.await?
+			.is_empty());
+		assert_eq!(
+			chart
+				.find_by_index::<Profile, _, Vec<_>>("profiles", "email", &"new@example.com")
+				.await?
+				.len(),
+			1
+		);
+
+		assert_eq!(
+			PatchAction::<Profile>::for_table("profiles")
+				.with_key(&2_u32)
+				.with_field("email", "missing@example.com".into())
+				.run_patch_entry(&chart)
+				.await?,
+			None
+		);
+
+		let error = PatchAction::<Profile>::for_table("profiles")
+			.with_key(&1_u32)
+			.with_field("id", 2.into())
+			.run_patch_entry(&chart)
+			.await
+			.unwrap_err()
+			.into_source()
+			.unwrap();
+		assert!(matches!(
+			error
+				.downcast_ref::<ActionRunError>()
+				.map(ActionRunError::kind),
+			Some(ActionRunErrorType::KeyChanged { key }) if key == "1"
+		));
+
+		let mut action = PatchAction::<Profile>::new();
+		assert!(action.set_patch(&"not a map").is_err());
+
+		Ok(())
+	}
+
+	#[tokio::test]
+	async fn invalid_patch() -> Result<(), Error> {
+		let chart = Starchart::new(MockBackend::new()).await?;
+		chart.create_table("profiles").await?;
+
+		let profile = Profile {
+			id: 1,
+			..Profile::default()
+		};
+		CreateEntryAction::for_table("profiles")
+			.with_entry(&profile)
+			.run_create_entry(&chart)
+			.await?;
+
+		let mut settings = SchemaMap::new();
+		settings.insert("notifications".to_owned(), "sometimes".into());
+		let error = PatchAction::<Profile>::for_table("profiles")
+			.with_key(&1_u32)
+			.with_field("settings", SchemaValue::Object(settings))
+			.run_patch_entry(&chart)
+			.await
+			.unwrap_err()
+			.into_source()
+			.unwrap();
+		assert!(matches!(
+			error
+				.downcast_ref::<ActionRunError>()
+				.map(ActionRunError::kind),
+			Some(ActionRunErrorType::InvalidPatch)
+		));
+
+		let mut settings = SchemaMap::new();
+		settings.insert("font".to_owned(), "serif".into());
+		for (name, value) in [
+			("unknown", true.into()),
+			("settings", SchemaValue::Object(settings)),
+		] {
+			let error = PatchAction::<Profile>::for_table("profiles")
+				.with_key(&1_u32)
+				.with_field(name, value)
+				.run_patch_entry(&chart)
+				.await
+				.unwrap_err()
+				.into_source()
+				.unwrap();
+			assert!(matches!(
+				error
+					.downcast_ref::<ActionRunError>()
+					.map(ActionRunError::kind),
+				Some(ActionRunErrorType::InvalidPatch)
+			));
+		}
+
+		assert_eq!(chart.get::<Profile>("profiles", "1").await?, Some(profile));
+
+		Ok(())
+	}
+}
@@ -0,0 +1,263 @@
+use std::fmt::{Debug, Formatter, Result as FmtResult};
+
+use super::{ActionRunError, ActionRunErrorType, InnerAction};
+use crate::{backend::Backend, pagination::ScanToken, query::PageRange, Entry, Error, Starchart};
+
+/// A resumable cursor over a table, created with [`ReadTableAction::run_scan`].
+///
+/// Entries are read in batches, in the order of their keys. The table is only locked while a batch
+/// is being read, so writes can happen between batches, and a scan of a large table never blocks
+/// writers for long. Entries written before the scan reaches their key are included.
+///
+/// Where the scan left off can be saved with [`Self::token`], and picked up again with [`Self::resume`].
+///
+/// [`ReadTableAction::run_scan`]: super::ReadTableAction::run_scan
+pub struct Scan<'a, B: Backend, S> {
+	chart: &'a Starchart<B>,
+	inner: InnerAction<'a, S>,
+	table: &'a str,
+	batch_size: usize,
+	after: Option<String>,
+	finished: bool,
+}
+
+impl<'a, B: Backend, S: Entry> Scan<'a, B, S> {
+	pub(super) fn new(
+		chart: &'a Starchart<B>,
+		mut inner: InnerAction<'a, S>,
+		table: &'a str,
+		batch_size: usize,
+	) -> Self {
+		let after = inner.page.after.take();
+
+		Self {
+			chart,
+			inner,
+			table,
+			batch_size: batch_size.max(1),
+			after,
+			finished: false,
+		}
+	}
+
+	/// Continues the scan from where the [`ScanToken`] was created.
+	///
+	/// # Errors
+	///
+	/// Returns an [`ErrorType::InvalidPageToken`] error if the token is malformed, or was created
+	/// for a different table or chart.
+	///
+	/// [`ErrorType::InvalidPageToken`]: crate::error::ErrorType::InvalidPageToken
+	pub fn resume(mut self, token: &ScanToken) -> Result<Self, Error> {
+		let after = self
+			.chart
+			.paginator
+			.verify_key(self.table, token.as_str())
+			.ok_or_else(Error::invalid_page_token)?;
+
+		self.after = Some(after);
+		self.finished = false;
+
+		Ok(self)
+	}
+
+	/// The maximum amount of keys read in a batch.
+	#[must_use]
+	pub const fn batch_size(&self) -> usize {
+		self.batch_size
+	}
+
+	/// Whether every entry has been read.
+	#[must_use]
+	pub const fn is_finished(&self) -> bool {
+		self.finished
+	}
+
+	/// A token for resuming the scan after the last batch read, or [`None`] if no batches have been read.
+	#[must_use]
+	pub fn token(&self) -> Option<ScanToken> {
+		self.after
+			.as_deref()
+			.map(|after| ScanToken(self.chart.paginator.sign_key(self.table, after)))
+	}
+
+	/// Reads the next batch of entries along with their keys, or [`None`] if the scan is finished.
+	///
+	/// Batches hold at most [`Self::batch_size`] entries. If the action has a filter, entries that
+	/// don't match it are skipped, and the table is unlocked after every [`Self::batch_size`] keys
+	/// until a matching entry is found.
+	///
+	/// # Errors
+	///
+	/// Returns an error if the table doesn't exist, or if any of the [`Backend`] methods fail.
+	pub async fn next_batch(&mut self) -> Result<Option<Vec<(String, S)>>, Error> {
+		while !self.finished {
+			let batch = self.read_batch().await?;
+
+			if !batch.is_empty() {
+				return Ok(Some(batch));
+			}
+		}
+
+		Ok(None)
+	}
+
+	async fn read_batch(&mut self) -> Result<Vec<(String, S)>, Error> {
+		let backend = &**self.chart;
+		let table = self.table;
+
+		let lock = self.inner.lock_shared(self.chart, table)?;
+
+		self.inner.check_table(backend, table).await?;
+		self.inner.check_metadata(self.chart, table, false).await?;
+
+		// one extra key is read to find out if this is the last batch.
+		let range = PageRange {
+			after: self.after.clone(),
+			limit: Some(self.batch_size.saturating_add(1)),
+			..self.inner.page.bounds()
+		};
+
+		let mut keys = backend
+			.get_keys_in_range::<Vec<String>>(table, &range)
+			.await
+			.map_err(|e| ActionRunError {
+				source: Some(Box::new(e)),
+				kind: ActionRunErrorType::Backend,
+			})?;
+
+		let finished = keys.len() <= self.batch_size;
+		keys.truncate(self.batch_size);
+
+		// the cursor only moves once the whole batch is read, so a failed batch can be retried.
+		let last = keys.last().cloned();
+		let mut batch = Vec::with_capacity(keys.len());
+		let mut expired = Vec::new();
+		for key in keys {
+			let entry = backend
+				.get::<S>(table, &key)
+				.await
+				.map_err(|e| ActionRunError {
+					source: Some(Box::new(e)),
+					kind: ActionRunErrorType::Backend,
+				})?;
+
+			let Some(entry) = entry else {
+				continue;
+			};
+
+			if self
+				.chart
+				.is_expired(table, &key)
+				.await
+				.map_err(|e| ActionRunError {
+					source: Some(Box::new(e)),
+					kind: ActionRunErrorType::Backend,
+				})? {
+				expired.push(key);
+				continue;
+			}
+
+			if self
+				.inner
+				.filter
+				.as_ref()
+				.is_none_or(|filter| filter.matches(&key, &entry))
+			{
+				batch.push((key, entry));
+			}
+		}
+
+		drop(lock);
+
+		for key in expired {
+			self.chart.purge_expired_entry(table, &key).await?;
+		}
+
+		if last.is_some() {
+			self.after = last;
+		}
+		self.finished = finished;
+
+		Ok(batch)
+	}
+}
+
+impl<B: Backend, S> Debug for Scan<'_, B, S> {
+	fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+		f.debug_struct("Scan")
+			.field("table", &self.table)
+			.field("batch_size", &self.batch_size)
+			.field("after", &self.after)
+			.field("finished", &self.finished)
+			.finish_non_exhaustive()
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use crate::{
+		action::ReadTableAction,
+		backend::{Backend, MockBackend},
+		testing::TestSettings,
+		Error, Starchart,
+	};
+
+	#[tokio::test]
+	async fn scan() -> Result<(), Error> {
+		let chart = Starchart::new(MockBackend::new()).await?;
+		chart.create_table("table").await?;
+
+		let table = chart.table::<TestSettings>("table");
+		for id in 0..5 {
+			table
+				.insert(&TestSettings {
+					id,
+					..TestSettings::default()
+				})
+				.await?;
+		}
+
+		let mut scan = table.scan(2)?;
+		let batch = scan.next_batch().await?.unwrap();
+		assert_eq!(
+			batch
+				.iter()
+				.map(|(key, _)| key.as_str())
+				.collect::<Vec<_>>(),
+			["0", "1"]
+		);
+		assert!(!scan.is_finished());
+
+		// the scan picks up where it left off in another scan, with writes in between.
+		let token = scan.token().unwrap().to_string();
+		table.remove(&2_u32).await?;
+		table
+			.insert(&TestSettings {
+				id: 9,
+				..TestSettings::default()
+			})
+			.await?;
+
+		let mut scan = table.scan(2)?.resume(&token.parse().unwrap())?;
+		let mut keys = Vec::new();
+		while let Some(batch) = scan.next_batch().await? {
+			keys.extend(batch.into_iter().map(|(key, _)| key));
+		}
+		assert_eq!(keys, ["3", "4", "9"]);
+		assert!(scan.is_finished());
+
+		let mut action = ReadTableAction::<TestSettings>::new();
+		action.set_table("table").filter(|entry| entry.id % 3 == 0);
+		let mut scan = action.run_scan(&chart, 1)?;
+		assert_eq!(scan.next_batch().await?.unwrap()[0].0, "0");
+		assert_eq!(scan.next_batch().await?.unwrap()[0].0, "3");
+		assert_eq!(scan.next_batch().await?.unwrap()[0].0, "9");
+		assert!(scan.next_batch().await?.is_none());
+
+		let other = chart.table::<TestSettings>("other");
+		assert!(other.scan(2)?.resume(&token.parse().unwrap()).is_err());
+
+		Ok(())
+	}
+}
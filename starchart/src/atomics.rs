@@ -1,24 +1,209 @@
 #![allow(clippy::non_send_fields_in_send_ty)]
-use parking_lot::{lock_api::RawRwLock as _, RawRwLock, RwLock, RwLockReadGuard, RwLockWriteGuard};
+use std::{
+	array,
+	sync::Arc,
+	time::{Duration, Instant},
+};
 
+use parking_lot::{RwLock, RwLockReadGuard, RwLockWriteGuard};
+
+use crate::instrument;
+
+/// The amount of locks tables are striped across.
+const STRIPES: usize = 64;
+
+/// How the locks of a [`Starchart`] are handed to operations waiting on them,
+/// see [`StarchartBuilder::lock_fairness`].
+///
+/// [`Starchart`]: crate::Starchart
+/// [`StarchartBuilder::lock_fairness`]: crate::StarchartBuilder::lock_fairness
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum LockFairness {
+	/// An operation releasing a lock may take it again before the operations waiting on it,
+	/// with a fair hand-off happening every so often so waiters can't starve. This is the default,
+	/// and is the fastest under contention.
+	#[default]
+	Eventual,
+	/// Released locks are always handed to the operation that has waited the longest,
+	/// so busy writers can't starve other operations at the cost of throughput.
+	Fair,
+}
+
+/// A set of locks striped by table name, so operations on different tables don't contend with each other.
+///
+/// Two tables may share a stripe, in which case they contend as if they were one table.
 #[derive(Debug)]
-pub struct Guard(RwLock<()>);
+pub struct Guard {
+	stripes: Arc<[RwLock<()>; STRIPES]>,
+	prefix: String,
+	fairness: LockFairness,
+}
 
 impl Guard {
-	pub const fn new() -> Self {
-		Self(RwLock::const_new(RawRwLock::INIT, ()))
+	pub fn new() -> Self {
+		Self::with_fairness(LockFairness::default())
+	}
+
+	pub fn with_fairness(fairness: LockFairness) -> Self {
+		Self {
+			stripes: Arc::new(array::from_fn(|_| RwLock::new(()))),
+			prefix: String::new(),
+			fairness,
+		}
+	}
+
+	pub const fn fairness(&self) -> LockFairness {
+		self.fairness
+	}
+
+	/// Creates a [`Guard`] sharing the same locks, for a chart whose table names are prefixed.
+	///
+	/// Tables are locked by their full name, so a table locked through the child
+	/// is also locked for the parent.
+	pub fn child(&self, prefix: &str) -> Self {
+		Self {
+			stripes: self.stripes.clone(),
+			prefix: [self.prefix.as_str(), prefix].concat(),
+			fairness: self.fairness,
+		}
+	}
+
+	/// Whether both guards share the same locks, so locking a table through one may block the other.
+	pub fn shares_locks(&self, other: &Self) -> bool {
+		Arc::ptr_eq(&self.stripes, &other.stripes)
+	}
+
+	pub fn shared(&self, table: &str) -> SharedGuard<'_> {
+		let stripe = self.stripe(table);
+
+		self.shared_guard(instrument::read(&self.stripes[stripe], stripe))
+	}
+
+	/// Locks the table for reading, giving up if it isn't unlocked before the timeout.
+	pub fn shared_within(&self, table: &str, timeout: Duration) -> Option<SharedGuard<'_>> {
+		let Some(deadline) = Instant::now().checked_add(timeout) else {
+			return Some(self.shared(table));
+		};
+
+		let stripe = self.stripe(table);
+
+		instrument::read_until(&self.stripes[stripe], stripe, deadline)
+			.map(|inner| self.shared_guard(inner))
+	}
+
+	/// Locks every given table for reading, for reads that need a consistent view across tables.
+	///
+	/// The stripes are always locked in the same order, so this can't deadlock with [`Self::exclusive_many`].
+	pub fn shared_many<'a, I>(&self, tables: I) -> Vec<SharedGuard<'_>>
+	where
+		I: IntoIterator<Item = &'a str>,
+	{
+		self.sorted_stripes(tables)
+			.into_iter()
+			.map(|stripe| self.shared_guard(instrument::read(&self.stripes[stripe], stripe)))
+			.collect()
+	}
+
+	pub fn exclusive(&self, table: &str) -> ExclusiveGuard<'_> {
+		self.exclusive_many(Some(table))
+	}
+
+	/// Locks every given table, for operations that span multiple tables.
+	///
+	/// The stripes are always locked in the same order, so this can't deadlock with other calls.
+	pub fn exclusive_many<'a, I>(&self, tables: I) -> ExclusiveGuard<'_>
+	where
+		I: IntoIterator<Item = &'a str>,
+	{
+		self.lock_stripes(self.sorted_stripes(tables))
+	}
+
+	/// Locks every given table like [`Self::exclusive_many`], giving up if they aren't all unlocked
+	/// before the timeout.
+	///
+	/// The stripes locked before giving up are released, so waiting on a table held by an operation
+	/// that waits on this one fails instead of deadlocking.
+	pub fn exclusive_many_within<'a, I>(
+		&self,
+		tables: I,
+		timeout: Duration,
+	) -> Option<ExclusiveGuard<'_>>
+	where
+		I: IntoIterator<Item = &'a str>,
+	{
+		let stripes = self.sorted_stripes(tables);
+
+		let Some(deadline) = Instant::now().checked_add(timeout) else {
+			return Some(self.lock_stripes(stripes));
+		};
+
+		let inner = stripes
+			.into_iter()
+			.map(|stripe| instrument::write_until(&self.stripes[stripe], stripe, deadline))
+			.collect::<Option<_>>()?;
+
+		Some(self.exclusive_guard(inner))
+	}
+
+	/// Locks every table, for operations that may touch any table.
+	pub fn exclusive_all(&self) -> ExclusiveGuard<'_> {
+		self.lock_stripes(0..STRIPES)
+	}
+
+	fn lock_stripes<I>(&self, stripes: I) -> ExclusiveGuard<'_>
+	where
+		I: IntoIterator<Item = usize>,
+	{
+		let inner = stripes
+			.into_iter()
+			.map(|stripe| instrument::write(&self.stripes[stripe], stripe))
+			.collect();
+
+		self.exclusive_guard(inner)
+	}
+
+	const fn shared_guard<'a>(&self, inner: RwLockReadGuard<'a, ()>) -> SharedGuard<'a> {
+		SharedGuard {
+			inner: Some(inner),
+			fairness: self.fairness,
+		}
 	}
 
-	pub fn shared(&self) -> SharedGuard {
-		let inner = self.0.read();
+	const fn exclusive_guard<'a>(
+		&self,
+		inner: Vec<RwLockWriteGuard<'a, ()>>,
+	) -> ExclusiveGuard<'a> {
+		ExclusiveGuard {
+			inner,
+			fairness: self.fairness,
+		}
+	}
+
+	fn sorted_stripes<'a, I>(&self, tables: I) -> Vec<usize>
+	where
+		I: IntoIterator<Item = &'a str>,
+	{
+		let mut stripes = tables
+			.into_iter()
+			.map(|table| self.stripe(table))
+			.collect::<Vec<_>>();
+		stripes.sort_unstable();
+		stripes.dedup();
 
-		SharedGuard(inner)
+		stripes
 	}
 
-	pub fn exclusive(&self) -> ExclusiveGuard {
-		let inner = self.0.write();
+	fn stripe(&self, table: &str) -> usize {
+		let hash = self
+			.prefix
+			.bytes()
+			.chain(table.bytes())
+			.fold(0xcbf2_9ce4_8422_2325_u64, |hash, byte| {
+				(hash ^ u64::from(byte)).wrapping_mul(0x0100_0000_01b3)
+			});
 
-		ExclusiveGuard(inner)
+		usize::from(hash.to_le_bytes()[0]) % STRIPES
 	}
 }
 
@@ -29,10 +214,282 @@ impl Default for Guard {
 }
 
 // implementing send doesn't matter bc we're not actually editing the value, just using it for a locking mechanism
-pub struct SharedGuard<'a>(RwLockReadGuard<'a, ()>);
+pub struct SharedGuard<'a> {
+	inner: Option<RwLockReadGuard<'a, ()>>,
+	fairness: LockFairness,
+}
 
-unsafe impl<'a> Send for SharedGuard<'a> {}
+unsafe impl Send for SharedGuard<'_> {}
 
-pub struct ExclusiveGuard<'a>(RwLockWriteGuard<'a, ()>);
+impl Drop for SharedGuard<'_> {
+	fn drop(&mut self) {
+		if let (Some(inner), LockFairness::Fair) = (self.inner.take(), self.fairness) {
+			RwLockReadGuard::unlock_fair(inner);
+		}
+	}
+}
+
+pub struct ExclusiveGuard<'a> {
+	inner: Vec<RwLockWriteGuard<'a, ()>>,
+	fairness: LockFairness,
+}
+
+unsafe impl Send for ExclusiveGuard<'_> {}
 
-unsafe impl<'a> Send for ExclusiveGuard<'a> {}
+impl Drop for ExclusiveGuard<'_> {
+	fn drop(&mut self) {
+		if self.fairness == LockFairness::Fair {
+			self.inner.drain(..).for_each(RwLockWriteGuard::unlock_fair);
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use std::{
+		io::{self, BufReader, Read},
+		sync::mpsc,
+		thread,
+		time::Duration,
+	};
+
+	use futures_executor::block_on;
+	use futures_util::future::join_all;
+	use serde::{Deserialize, Serialize};
+
+	use super::{Guard, LockFairness, STRIPES};
+	use crate::{
+		action::{ActionErrorType, CreateTableAction, ReadEntryAction},
+		backend::{Backend, MockBackend},
+		error::ErrorType,
+		testing::TestSettings,
+		Error, IndexEntry, Starchart,
+	};
+
+	#[test]
+	fn striped() {
+		let guard = Guard::new();
+
+		let first = "table_0".to_owned();
+		let second = (1..STRIPES)
+			.map(|i| format!("table_{i}"))
+			.find(|table| guard.stripe(table) != guard.stripe(&first))
+			.unwrap();
+
+		let lock = guard.exclusive(&first);
+		assert!(guard.stripes[guard.stripe(&second)].try_write().is_some());
+		assert!(guard.stripes[guard.stripe(&first)].try_read().is_none());
+		drop(lock);
+
+		let child = guard.child("child_");
+		let lock = child.exclusive(&first);
+		assert!(guard.stripes[guard.stripe(&format!("child_{first}"))]
+			.try_read()
+			.is_none());
+		drop(lock);
+	}
+
+	#[test]
+	fn timeouts() {
+		let guard = Guard::with_fairness(LockFairness::Fair);
+
+		let first = "table_0".to_owned();
+		let second = (1..STRIPES)
+			.map(|i| format!("table_{i}"))
+			.find(|table| guard.stripe(table) != guard.stripe(&first))
+			.unwrap();
+
+		let lock = guard.exclusive(&second);
+		assert!(guard
+			.shared_within(&second, Duration::from_millis(10))
+			.is_none());
+		assert!(guard
+			.exclusive_many_within([first.as_str(), second.as_str()], Duration::from_millis(10))
+			.is_none());
+		// stripes locked before timing out are released
+		assert!(guard.stripes[guard.stripe(&first)].try_write().is_some());
+		drop(lock);
+
+		let lock = guard.shared_within(&second, Duration::from_millis(10));
+		assert_eq!(
+			lock.as_ref().map(|lock| lock.fairness),
+			Some(LockFairness::Fair)
+		);
+		drop(lock);
+
+		assert!(guard
+			.exclusive_many_within([first.as_str(), second.as_str()], Duration::ZERO)
+			.is_some());
+		assert_eq!(guard.child("child_").fairness(), LockFairness::Fair);
+	}
+
+	#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+	struct PageViews {
+		name: String,
+		stats: PageStats,
+	}
+
+	#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+	struct PageStats {
+		views: i64,
+		ratio: f64,
+	}
+
+	impl IndexEntry for PageViews {
+		type Key = String;
+
+		fn key(&self) -> &Self::Key {
+			&self.name
+		}
+
+		fn indexes(&self) -> Vec<(&'static str, String)> {
+			vec![("views", self.stats.views.to_string())]
+		}
+	}
+
+	#[tokio::test]
+	async fn increment() -> Result<(), Error> {
+		let chart = Starchart::new(MockBackend::new()).await?;
+
+		let err = chart
+			.increment::<PageViews, _>("pages", &1_u32, "stats.views", 1)
+			.await
+			.unwrap_err();
+		assert!(matches!(err.kind(), ErrorType::TableMissing));
+
+		chart.create_table("pages").await?;
+		chart.create("pages", "1", &PageViews::default()).await?;
+
+		let increments =
+			(0..20).map(|_| chart.increment::<PageViews, _>("pages", &1_u32, "stats.views", 2));
+		for incremented in join_all(increments).await {
+			incremented?;
+		}
+
+		assert_eq!(
+			chart
+				.increment::<PageViews, _>("pages", &1_u32, "stats.views", -5)
+				.await?,
+			35
+		);
+		assert_eq!(
+			chart
+				.get::<PageViews>("pages", "1")
+				.await?
+				.map(|page| page.stats.views),
+			Some(35)
+		);
+
+		for (key, field, delta) in [
+			(1_u32, "stats.ratio", 1),
+			(1, "stats.missing", 1),
+			(1, "name", 1),
+			(1, "stats.views", i64::MAX),
+		] {
+			let err = chart
+				.increment::<PageViews, _>("pages", &key, field, delta)
+				.await
+				.unwrap_err();
+			assert!(
+				matches!(err.kind(), ErrorType::InvalidCounter { field: invalid } if invalid == field)
+			);
+		}
+
+		let err = chart
+			.increment::<PageViews, _>("pages", &2_u32, "stats.views", 1)
+			.await
+			.unwrap_err();
+		assert!(matches!(err.kind(), ErrorType::EntryMissing));
+
+		assert_eq!(
+			chart
+				.find_by_index::<PageViews, _, Vec<_>>("pages", "views", &35)
+				.await?
+				.len(),
+			1
+		);
+		assert!(chart
+			.find_by_index::<PageViews, _, Vec<_>>("pages", "views", &33)
+			.await?
+			.is_empty());
+
+		chart.create_table("table").await?;
+		chart.create("table", "1", &TestSettings::default()).await?;
+		let err = chart
+			.increment::<TestSettings, _>("table", &1_u32, "id", 1)
+			.await
+			.unwrap_err();
+		assert!(err.is_invalid());
+		assert_eq!(
+			chart.get::<TestSettings>("table", "1").await?,
+			Some(TestSettings::default())
+		);
+
+		Ok(())
+	}
+
+	#[tokio::test]
+	async fn lock_timeout() -> Result<(), Error> {
+		// a reader holding the import, and with it the table's lock, until released
+		struct Held {
+			started: Option<mpsc::Sender<()>>,
+			release: mpsc::Receiver<()>,
+		}
+
+		impl Read for Held {
+			fn read(&mut self, _: &mut [u8]) -> io::Result<usize> {
+				if let Some(started) = self.started.take() {
+					started.send(()).unwrap();
+				}
+
+				self.release.recv().ok();
+				Ok(0)
+			}
+		}
+
+		let chart = Starchart::builder(MockBackend::new())
+			.lock_fairness(LockFairness::Fair)
+			.build()
+			.await?;
+		assert_eq!(chart.lock_fairness(), LockFairness::Fair);
+
+		let mut action = CreateTableAction::<TestSettings>::new();
+		action.set_table("table");
+		action.run_create_table(&chart).await?;
+
+		let (started, wait) = mpsc::channel();
+		let (release, held) = mpsc::channel();
+		let importing = {
+			let chart = chart.clone();
+			thread::spawn(move || {
+				let reader = BufReader::new(Held {
+					started: Some(started),
+					release: held,
+				});
+				block_on(chart.import("table", reader))
+			})
+		};
+		wait.recv().unwrap();
+
+		let read = || {
+			let mut action = ReadEntryAction::<TestSettings>::new();
+			action
+				.set_table("table")
+				.set_key(&1_u32)
+				.lock_timeout(Duration::from_millis(10));
+			action.run_read_entry(&chart)
+		};
+
+		let error = read().await.unwrap_err();
+		assert!(matches!(error.kind(), ActionErrorType::Timeout));
+		assert!(matches!(Error::from(error).kind(), ErrorType::Timeout));
+
+		release.send(()).unwrap();
+		// the held reader never yields a backup
+		assert!(importing.join().unwrap().is_err());
+
+		assert!(read().await?.is_none());
+
+		Ok(())
+	}
+}
@@ -0,0 +1,108 @@
+//! The error [`Backend`]s report entries they couldn't decode with.
+//!
+//! [`Backend`]: super::Backend
+
+use std::{
+	error::Error as StdError,
+	fmt::{Debug, Display, Formatter, Result as FmtResult},
+	iter::successors,
+};
+
+/// An entry a [`Backend`] read but couldn't decode into the entry type, such as a file
+/// that was only partially written or edited by hand.
+///
+/// Backends return this as the source of their own error when decoding an entry fails,
+/// so the [`Starchart`] reports it as [`ErrorType::CorruptEntry`] whichever backend is used.
+///
+/// [`Backend`]: super::Backend
+/// [`ErrorType::CorruptEntry`]: crate::error::ErrorType::CorruptEntry
+/// [`Starchart`]: crate::Starchart
+#[derive(Debug)]
+pub struct CorruptEntryError {
+	source: Box<dyn StdError + Send + Sync>,
+	table: String,
+	key: String,
+}
+
+impl CorruptEntryError {
+	/// Creates an error for the entry, caused by the error decoding it.
+	pub fn new<E>(table: &str, key: &str, source: E) -> Self
+	where
+		E: Into<Box<dyn StdError + Send + Sync>>,
+	{
+		Self {
+			source: source.into(),
+			table: table.to_owned(),
+			key: key.to_owned(),
+		}
+	}
+
+	/// The table the entry is in.
+	#[must_use]
+	pub fn table(&self) -> &str {
+		&self.table
+	}
+
+	/// The key of the entry.
+	#[must_use]
+	pub fn key(&self) -> &str {
+		&self.key
+	}
+
+	/// Consume the error, returning the error decoding the entry.
+	#[must_use = "consuming the error and retrieving the source has no effect if left unused"]
+	pub fn into_source(self) -> Box<dyn StdError + Send + Sync> {
+		self.source
+	}
+}
+
+impl Display for CorruptEntryError {
+	fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+		f.write_str("the entry ")?;
+		Debug::fmt(&self.key, f)?;
+		f.write_str(" in table ")?;
+		Debug::fmt(&self.table, f)?;
+		f.write_str(" could not be decoded")
+	}
+}
+
+impl StdError for CorruptEntryError {
+	fn source(&self) -> Option<&(dyn StdError + 'static)> {
+		Some(&*self.source)
+	}
+}
+
+/// Checks whether the error, or any error it was caused by, is a [`CorruptEntryError`].
+pub fn corrupted(error: &(dyn StdError + 'static)) -> bool {
+	successors(Some(error), |&error| error.source()).any(<dyn StdError>::is::<CorruptEntryError>)
+}
+
+#[cfg(test)]
+mod tests {
+	use std::{error::Error as StdError, fmt::Debug};
+
+	use static_assertions::assert_impl_all;
+
+	use super::{corrupted, CorruptEntryError};
+	use crate::{error::ErrorType, Error};
+
+	assert_impl_all!(CorruptEntryError: Debug, StdError, Send, Sync);
+
+	#[test]
+	fn detected_through_sources() {
+		let decoding = serde_json::from_str::<u32>("{").unwrap_err();
+		let error = CorruptEntryError::new("table", "1", decoding);
+		assert_eq!(
+			error.to_string(),
+			"the entry \"1\" in table \"table\" could not be decoded"
+		);
+		assert_eq!((error.table(), error.key()), ("table", "1"));
+
+		let error = Error::backend(Some(Box::new(Error::backend(Some(Box::new(error))))));
+		assert!(corrupted(&error));
+		assert!(matches!(error.kind(), ErrorType::CorruptEntry));
+
+		let missing = Error::backend(None);
+		assert!(!corrupted(&missing));
+	}
+}
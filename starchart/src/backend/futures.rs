@@ -3,6 +3,8 @@
 //! [`Backend`]: crate::backend::Backend
 use std::{future::Future, pin::Pin};
 
+use futures_util::Stream;
+
 #[cfg(doc)]
 use crate::backend::Backend;
 
@@ -12,40 +14,82 @@ pub type InitFuture<'a, E> = PinBoxFuture<'a, Result<(), E>>;
 /// The future returned from [`Backend::shutdown`].
 pub type ShutdownFuture<'a> = PinBoxFuture<'a>;
 
+/// The future returned from [`Backend::health_check`].
+pub type HealthCheckFuture<'a, E> = PinBoxFuture<'a, Result<(), E>>;
+
 /// The future returned from [`Backend::has_table`].
 pub type HasTableFuture<'a, E> = PinBoxFuture<'a, Result<bool, E>>;
 
+/// The future returned from [`Backend::tables`].
+pub type TablesFuture<'a, I, E> = PinBoxFuture<'a, Result<I, E>>;
+
 /// The future returned from [`Backend::create_table`].
 pub type CreateTableFuture<'a, E> = PinBoxFuture<'a, Result<(), E>>;
 
 /// The future returned from [`Backend::delete_table`].
 pub type DeleteTableFuture<'a, E> = PinBoxFuture<'a, Result<(), E>>;
 
+/// The future returned from [`Backend::rename_table`].
+pub type RenameTableFuture<'a, E> = PinBoxFuture<'a, Result<(), E>>;
+
+/// The future returned from [`Backend::truncate_table`].
+pub type TruncateTableFuture<'a, E> = PinBoxFuture<'a, Result<(), E>>;
+
 /// The future returned from [`Backend::ensure_table`].
 pub type EnsureTableFuture<'a, E> = PinBoxFuture<'a, Result<(), E>>;
 
 /// The future returned from [`Backend::get_all`].
 pub type GetAllFuture<'a, I, E> = PinBoxFuture<'a, Result<I, E>>;
 
+/// The stream returned from [`Backend::get_all_stream`].
+pub type GetAllStream<'a, D, E> = Pin<Box<dyn Stream<Item = Result<(String, D), E>> + Send + 'a>>;
+
+/// The future returned from [`Backend::get_filtered`].
+pub type GetFilteredFuture<'a, I, E> = PinBoxFuture<'a, Result<I, E>>;
+
+/// The future returned from [`Backend::get_page`].
+pub type GetPageFuture<'a, I, E> = PinBoxFuture<'a, Result<I, E>>;
+
 /// The future returned from [`Backend::get_keys`].
 pub type GetKeysFuture<'a, I, E> = PinBoxFuture<'a, Result<I, E>>;
 
+/// The future returned from [`Backend::get_keys_in_range`].
+pub type GetKeysInRangeFuture<'a, I, E> = PinBoxFuture<'a, Result<I, E>>;
+
 /// The future returned from [`Backend::get`].
 pub type GetFuture<'a, D, E> = PinBoxFuture<'a, Result<Option<D>, E>>;
 
+/// The future returned from [`Backend::get_multi`].
+pub type GetMultiFuture<'a, D, E> = PinBoxFuture<'a, Result<Vec<Option<D>>, E>>;
+
 /// The future returned from [`Backend::has`].
 pub type HasFuture<'a, E> = PinBoxFuture<'a, Result<bool, E>>;
 
 /// The future returned from [`Backend::create`].
 pub type CreateFuture<'a, E> = PinBoxFuture<'a, Result<(), E>>;
 
+/// The future returned from [`Backend::create_multi`].
+pub type CreateMultiFuture<'a, E> = PinBoxFuture<'a, Result<(), E>>;
+
 /// The future returned from [`Backend::ensure`].
 pub type EnsureFuture<'a, E> = PinBoxFuture<'a, Result<(), E>>;
 
+/// The future returned from [`Backend::replace`].
+pub type ReplaceFuture<'a, E> = PinBoxFuture<'a, Result<(), E>>;
+
 /// The future returned from [`Backend::update`].
 pub type UpdateFuture<'a, E> = PinBoxFuture<'a, Result<(), E>>;
 
+/// The future returned from [`Backend::update_multi`].
+pub type UpdateMultiFuture<'a, E> = PinBoxFuture<'a, Result<(), E>>;
+
 /// The future returned from [`Backend::delete`].
 pub type DeleteFuture<'a, E> = PinBoxFuture<'a, Result<(), E>>;
 
+/// The future returned from [`Backend::expire`].
+pub type ExpireFuture<'a, E> = PinBoxFuture<'a, Result<bool, E>>;
+
+/// The future returned from [`Backend::increment`].
+pub type IncrementFuture<'a, E> = PinBoxFuture<'a, Result<Option<i64>, E>>;
+
 type PinBoxFuture<'a, Rt = ()> = Pin<Box<dyn Future<Output = Rt> + Send + 'a>>;
@@ -0,0 +1,140 @@
+//! Composing [`Backend`] wrappers as layers.
+
+use std::fmt::{Debug, Formatter, Result as FmtResult};
+
+use super::Backend;
+
+/// Wraps a [`Backend`] in another [`Backend`], such as a cache or a fallback.
+///
+/// Layers describe how to build a wrapper without the backend it wraps, so they can be stacked with a
+/// [`BackendBuilder`] and applied to any [`Backend`].
+pub trait BackendLayer<B: Backend> {
+	/// The wrapping [`Backend`].
+	type Backend: Backend;
+
+	/// Wraps the given [`Backend`].
+	fn layer(&self, inner: B) -> Self::Backend;
+}
+
+impl<B: Backend, L: BackendLayer<B>> BackendLayer<B> for &L {
+	type Backend = L::Backend;
+
+	fn layer(&self, inner: B) -> Self::Backend {
+		(**self).layer(inner)
+	}
+}
+
+/// A [`BackendLayer`] that returns the [`Backend`] unchanged.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct Identity;
+
+impl<B: Backend> BackendLayer<B> for Identity {
+	type Backend = B;
+
+	fn layer(&self, inner: B) -> Self::Backend {
+		inner
+	}
+}
+
+/// Two [`BackendLayer`]s applied one after the other, `inner` first.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct Stack<Inner, Outer> {
+	inner: Inner,
+	outer: Outer,
+}
+
+impl<Inner, Outer> Stack<Inner, Outer> {
+	/// Creates a new [`Stack`].
+	pub const fn new(inner: Inner, outer: Outer) -> Self {
+		Self { inner, outer }
+	}
+}
+
+impl<B, Inner, Outer> BackendLayer<B> for Stack<Inner, Outer>
+where
+	B: Backend,
+	Inner: BackendLayer<B>,
+	Outer: BackendLayer<Inner::Backend>,
+{
+	type Backend = Outer::Backend;
+
+	fn layer(&self, inner: B) -> Self::Backend {
+		self.outer.layer(self.inner.layer(inner))
+	}
+}
+
+/// A [`BackendLayer`] made from a function, see [`layer_fn`].
+#[derive(Clone, Copy)]
+pub struct LayerFn<F>(F);
+
+impl<B, F, W> BackendLayer<B> for LayerFn<F>
+where
+	B: Backend,
+	F: Fn(B) -> W,
+	W: Backend,
+{
+	type Backend = W;
+
+	fn layer(&self, inner: B) -> Self::Backend {
+		(self.0)(inner)
+	}
+}
+
+impl<F> Debug for LayerFn<F> {
+	fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+		f.debug_struct("LayerFn").finish_non_exhaustive()
+	}
+}
+
+/// Creates a [`BackendLayer`] from a function that wraps a [`Backend`], for wrappers that don't have their own layer.
+pub const fn layer_fn<F>(f: F) -> LayerFn<F> {
+	LayerFn(f)
+}
+
+/// Stacks [`BackendLayer`]s, then applies them to a [`Backend`].
+///
+/// Layers are applied in the order they're added, so the first layer added wraps every other layer,
+/// and sees every operation first.
+///
+/// ```
+/// # use starchart::backend::{Backend, BackendBuilder, ConsistencyPolicy, TieredLayer};
+/// # fn wrap<B: Backend, R: Backend + Clone>(primary: B, replica: R) {
+/// let layer = TieredLayer::new(replica).with_policy(ConsistencyPolicy::Strict);
+/// let backend = BackendBuilder::new().layer(layer).backend(primary);
+/// # }
+/// ```
+#[derive(Debug, Default, Clone, Copy)]
+#[must_use = "a backend builder does nothing until a backend is built"]
+pub struct BackendBuilder<L> {
+	layer: L,
+}
+
+impl BackendBuilder<Identity> {
+	/// Creates a new [`BackendBuilder`] with no layers.
+	pub const fn new() -> Self {
+		Self { layer: Identity }
+	}
+}
+
+impl<L> BackendBuilder<L> {
+	/// Adds a [`BackendLayer`], which wraps the layers added after it.
+	pub fn layer<T>(self, layer: T) -> BackendBuilder<Stack<T, L>> {
+		BackendBuilder {
+			layer: Stack::new(layer, self.layer),
+		}
+	}
+
+	/// Returns the stacked layers, as a single [`BackendLayer`].
+	pub fn into_inner(self) -> L {
+		self.layer
+	}
+
+	/// Wraps the [`Backend`] in every layer.
+	pub fn backend<B>(&self, backend: B) -> L::Backend
+	where
+		B: Backend,
+		L: BackendLayer<B>,
+	{
+		self.layer.layer(backend)
+	}
+}
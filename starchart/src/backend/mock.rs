@@ -0,0 +1,603 @@
+//! A [`Backend`] for testing code that uses a [`Starchart`], enabled with the `test-util` feature.
+//!
+//! [`Starchart`]: crate::Starchart
+
+use std::{
+	collections::BTreeMap,
+	error::Error as StdError,
+	fmt::{Debug, Display, Formatter, Result as FmtResult},
+	iter::FromIterator,
+	sync::Arc,
+};
+
+use futures_util::{future::ready, FutureExt};
+use parking_lot::Mutex;
+use serde_json::Value;
+
+use super::{
+	futures::{
+		CreateFuture, CreateTableFuture, DeleteFuture, DeleteTableFuture, GetFuture, GetKeysFuture,
+		HasFuture, HasTableFuture, HealthCheckFuture, InitFuture, TablesFuture, UpdateFuture,
+	},
+	Backend, CorruptEntryError,
+};
+use crate::{transaction::TransactionalBackend, Entry, Error};
+
+type MakeSource = Arc<dyn Fn() -> Box<dyn StdError + Send + Sync> + Send + Sync>;
+
+/// An in-memory [`Backend`] that records every call made to it, and fails the calls it's told to.
+///
+/// Cloning a [`MockBackend`] shares it's state, so a clone can be kept to inspect the calls after the
+/// backend has been given to a [`Starchart`].
+///
+/// Calls are recorded by the name of the [`Backend`] method, along with the table and key, if any.
+/// Methods with a default impl, such as [`Backend::get_all`], are recorded as the methods they call.
+///
+/// ```rust
+/// # use starchart::{backend::{Backend, MockBackend}, Starchart};
+/// # #[tokio::main(flavor = "current_thread")]
+/// # async fn main() -> Result<(), starchart::Error> {
+/// let backend = MockBackend::new();
+/// backend.create_table("settings").await?;
+/// backend.fail("get", Some("settings"));
+///
+/// let chart = Starchart::new(backend.clone()).await?;
+/// let settings = chart.table::<Option<String>>("settings");
+/// assert!(settings.get(&"1".to_owned()).await.is_err());
+///
+/// backend.assert_sequence(&["init", "has_table", "get"]);
+/// # Ok(()) }
+/// ```
+///
+/// [`Starchart`]: crate::Starchart
+#[derive(Debug, Default, Clone)]
+pub struct MockBackend {
+	state: Arc<Mutex<MockState>>,
+}
+
+impl MockBackend {
+	/// Creates a new, empty [`MockBackend`].
+	#[must_use]
+	pub fn new() -> Self {
+		Self::default()
+	}
+
+	/// Makes every call to the method fail, only for the given table if there is one.
+	pub fn fail(&self, method: &'static str, table: Option<&str>) {
+		self.add_failure(method, table, None);
+	}
+
+	/// Makes every call to the method fail with the error returned by the function as the source,
+	/// only for the given table if there is one.
+	///
+	/// This can be used to check how the code reacts to specific errors, such as a [`CorruptEntryError`],
+	/// which the [`Starchart`] reports as [`ErrorType::CorruptEntry`].
+	///
+	/// [`ErrorType::CorruptEntry`]: crate::error::ErrorType::CorruptEntry
+	/// [`Starchart`]: crate::Starchart
+	pub fn fail_with<F>(&self, method: &'static str, table: Option<&str>, source: F)
+	where
+		F: Fn() -> Box<dyn StdError + Send + Sync> + Send + Sync + 'static,
+	{
+		self.add_failure(method, table, Some(Arc::new(source)));
+	}
+
+	/// Removes every failure added with [`Self::fail`] or [`Self::fail_with`].
+	pub fn clear_failures(&self) {
+		self.state.lock().failures.clear();
+	}
+
+	/// Returns every call made to the backend, in the order they were made.
+	#[must_use]
+	pub fn calls(&self) -> Vec<MockCall> {
+		self.state.lock().calls.clone()
+	}
+
+	/// Returns the calls made to the method.
+	#[must_use]
+	pub fn calls_to(&self, method: &str) -> Vec<MockCall> {
+		self.state
+			.lock()
+			.calls
+			.iter()
+			.filter(|call| call.method == method)
+			.cloned()
+			.collect()
+	}
+
+	/// Forgets every call recorded so far.
+	pub fn clear_calls(&self) {
+		self.state.lock().calls.clear();
+	}
+
+	/// Asserts that the methods were called in the given order.
+	///
+	/// Other calls may be made between the expected ones, as the [`Starchart`] checks tables and
+	/// metadata as it needs to.
+	///
+	/// # Panics
+	///
+	/// Panics if the methods weren't called in order, listing every call that was made.
+	///
+	/// [`Starchart`]: crate::Starchart
+	#[track_caller]
+	pub fn assert_sequence(&self, methods: &[&str]) {
+		let calls = self.calls();
+		let mut recorded = calls.iter().map(MockCall::method);
+
+		for method in methods {
+			assert!(
+				recorded.any(|called| called == *method),
+				"expected the calls {:?} in order, but {:?} wasn't called after the previous ones, the calls were {:#?}",
+				methods,
+				method,
+				calls
+			);
+		}
+	}
+
+	/// Returns the entry stored in the backend, or [`None`] if it doesn't exist or isn't an `S`.
+	#[must_use]
+	pub fn entry<S: Entry>(&self, table: &str, key: &str) -> Option<S> {
+		let value = self.state.lock().tables.get(table)?.get(key)?.clone();
+
+		serde_json::from_value(value).ok()
+	}
+
+	fn add_failure(&self, method: &'static str, table: Option<&str>, source: Option<MakeSource>) {
+		self.state.lock().failures.push(Failure {
+			method,
+			table: table.map(ToOwned::to_owned),
+			source,
+		});
+	}
+
+	/// Records the call, returning an error if it should fail.
+	fn call(
+		&self,
+		method: &'static str,
+		table: Option<&str>,
+		key: Option<&str>,
+	) -> Result<(), MockError> {
+		let mut state = self.state.lock();
+
+		state.calls.push(MockCall {
+			method,
+			table: table.map(ToOwned::to_owned),
+			key: key.map(ToOwned::to_owned),
+		});
+
+		let failure = state
+			.failures
+			.iter()
+			.find(|failure| {
+				failure.method == method
+					&& failure
+						.table
+						.as_deref()
+						.is_none_or(|failing| table == Some(failing))
+			})
+			.map(|failure| MockError {
+				source: failure.source.as_ref().map(|source| source()),
+				kind: MockErrorType::Injected,
+			});
+		drop(state);
+
+		failure.map_or(Ok(()), Err)
+	}
+
+	fn write<S: Entry>(
+		&self,
+		method: &'static str,
+		table: &str,
+		key: &str,
+		value: &S,
+	) -> Result<(), MockError> {
+		self.call(method, Some(table), Some(key))?;
+
+		let value = serde_json::to_value(value).map_err(|e| MockError {
+			source: Some(Box::new(e)),
+			kind: MockErrorType::Serialization,
+		})?;
+
+		if let Some(entries) = self.state.lock().tables.get_mut(table) {
+			entries.insert(key.to_owned(), value);
+		}
+
+		Ok(())
+	}
+}
+
+impl Backend for MockBackend {
+	type Error = MockError;
+
+	fn init(&self) -> InitFuture<'_, MockError> {
+		ready(self.call("init", None, None)).boxed()
+	}
+
+	fn health_check(&self) -> HealthCheckFuture<'_, MockError> {
+		ready(self.call("health_check", None, None)).boxed()
+	}
+
+	fn has_table<'a>(&'a self, table: &'a str) -> HasTableFuture<'a, MockError> {
+		let result = self
+			.call("has_table", Some(table), None)
+			.map(|()| self.state.lock().tables.contains_key(table));
+
+		ready(result).boxed()
+	}
+
+	fn tables<I>(&self) -> TablesFuture<'_, I, MockError>
+	where
+		I: FromIterator<String>,
+	{
+		async move {
+			self.call("tables", None, None)?;
+
+			Ok(self.state.lock().tables.keys().cloned().collect())
+		}
+		.boxed()
+	}
+
+	fn create_table<'a>(&'a self, table: &'a str) -> CreateTableFuture<'a, MockError> {
+		let result = self.call("create_table", Some(table), None).map(|()| {
+			self.state
+				.lock()
+				.tables
+				.entry(table.to_owned())
+				.or_default();
+		});
+
+		ready(result).boxed()
+	}
+
+	fn delete_table<'a>(&'a self, table: &'a str) -> DeleteTableFuture<'a, MockError> {
+		let result = self.call("delete_table", Some(table), None).map(|()| {
+			self.state.lock().tables.remove(table);
+		});
+
+		ready(result).boxed()
+	}
+
+	fn get_keys<'a, I>(&'a self, table: &'a str) -> GetKeysFuture<'a, I, MockError>
+	where
+		I: FromIterator<String>,
+	{
+		async move {
+			self.call("get_keys", Some(table), None)?;
+
+			let keys = self
+				.state
+				.lock()
+				.tables
+				.get(table)
+				.map(|entries| entries.keys().cloned().collect::<Vec<_>>())
+				.unwrap_or_default();
+
+			Ok(keys.into_iter().collect())
+		}
+		.boxed()
+	}
+
+	fn get<'a, D>(&'a self, table: &'a str, id: &'a str) -> GetFuture<'a, D, MockError>
+	where
+		D: Entry,
+	{
+		async move {
+			self.call("get", Some(table), Some(id))?;
+
+			let value = self
+				.state
+				.lock()
+				.tables
+				.get(table)
+				.and_then(|entries| entries.get(id).cloned());
+
+			value
+				.map(|value| {
+					serde_json::from_value(value).map_err(|e| MockError {
+						source: Some(Box::new(CorruptEntryError::new(table, id, e))),
+						kind: MockErrorType::Deserialization,
+					})
+				})
+				.transpose()
+		}
+		.boxed()
+	}
+
+	fn has<'a>(&'a self, table: &'a str, id: &'a str) -> HasFuture<'a, MockError> {
+		let result = self.call("has", Some(table), Some(id)).map(|()| {
+			self.state
+				.lock()
+				.tables
+				.get(table)
+				.is_some_and(|entries| entries.contains_key(id))
+		});
+
+		ready(result).boxed()
+	}
+
+	fn create<'a, S>(
+		&'a self,
+		table: &'a str,
+		id: &'a str,
+		value: &'a S,
+	) -> CreateFuture<'a, MockError>
+	where
+		S: Entry,
+	{
+		ready(self.write("create", table, id, value)).boxed()
+	}
+
+	fn update<'a, S>(
+		&'a self,
+		table: &'a str,
+		id: &'a str,
+		value: &'a S,
+	) -> UpdateFuture<'a, MockError>
+	where
+		S: Entry,
+	{
+		ready(self.write("update", table, id, value)).boxed()
+	}
+
+	fn delete<'a>(&'a self, table: &'a str, id: &'a str) -> DeleteFuture<'a, MockError> {
+		let result = self.call("delete", Some(table), Some(id)).map(|()| {
+			if let Some(entries) = self.state.lock().tables.get_mut(table) {
+				entries.remove(id);
+			}
+		});
+
+		ready(result).boxed()
+	}
+}
+
+impl TransactionalBackend for MockBackend {}
+
+#[derive(Debug, Default)]
+struct MockState {
+	tables: BTreeMap<String, BTreeMap<String, Value>>,
+	calls: Vec<MockCall>,
+	failures: Vec<Failure>,
+}
+
+struct Failure {
+	method: &'static str,
+	table: Option<String>,
+	source: Option<MakeSource>,
+}
+
+impl Debug for Failure {
+	fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+		f.debug_struct("Failure")
+			.field("method", &self.method)
+			.field("table", &self.table)
+			.finish_non_exhaustive()
+	}
+}
+
+/// A call made to a [`MockBackend`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MockCall {
+	method: &'static str,
+	table: Option<String>,
+	key: Option<String>,
+}
+
+impl MockCall {
+	/// The name of the [`Backend`] method that was called.
+	#[must_use]
+	pub const fn method(&self) -> &'static str {
+		self.method
+	}
+
+	/// The table the method was called with, if any.
+	#[must_use]
+	pub fn table(&self) -> Option<&str> {
+		self.table.as_deref()
+	}
+
+	/// The key the method was called with, if any.
+	#[must_use]
+	pub fn key(&self) -> Option<&str> {
+		self.key.as_deref()
+	}
+}
+
+/// An error returned from the [`MockBackend`].
+#[derive(Debug)]
+pub struct MockError {
+	source: Option<Box<dyn StdError + Send + Sync>>,
+	kind: MockErrorType,
+}
+
+impl MockError {
+	/// Immutable reference to the type of error that occurred.
+	#[must_use = "retrieving the type has no effect if left unused"]
+	pub const fn kind(&self) -> &MockErrorType {
+		&self.kind
+	}
+
+	/// Consume the error, returning the source error if there is any.
+	#[must_use = "consuming the error and retrieving the source has no effect if left unused"]
+	pub fn into_source(self) -> Option<Box<dyn StdError + Send + Sync>> {
+		self.source
+	}
+
+	/// Consume the error, returning the owned error type and the source error.
+	#[must_use = "consuming the error into it's parts has no effect if left unused"]
+	pub fn into_parts(self) -> (MockErrorType, Option<Box<dyn StdError + Send + Sync>>) {
+		(self.kind, self.source)
+	}
+}
+
+impl Display for MockError {
+	fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+		match self.kind {
+			MockErrorType::Injected => f.write_str("the call was set to fail"),
+			MockErrorType::Serialization => f.write_str("an entry couldn't be serialized"),
+			MockErrorType::Deserialization => f.write_str("an entry couldn't be deserialized"),
+		}
+	}
+}
+
+impl StdError for MockError {
+	fn source(&self) -> Option<&(dyn StdError + 'static)> {
+		self.source
+			.as_ref()
+			.map(|source| &**source as &(dyn StdError + 'static))
+	}
+}
+
+impl From<MockError> for Error {
+	fn from(e: MockError) -> Self {
+		Self::backend(Some(Box::new(e)))
+	}
+}
+
+/// The type of [`MockError`] that occurred.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum MockErrorType {
+	/// The call was made to fail with [`MockBackend::fail`] or [`MockBackend::fail_with`].
+	Injected,
+	/// An entry couldn't be serialized.
+	Serialization,
+	/// An entry couldn't be deserialized, the source is a [`CorruptEntryError`].
+	Deserialization,
+}
+
+#[cfg(test)]
+mod tests {
+	use std::fmt::Debug;
+
+	use futures_executor::block_on;
+	use static_assertions::assert_impl_all;
+
+	use super::{MockBackend, MockError, MockErrorType};
+	use crate::{
+		backend::{testsuite, Backend, CorruptEntryError},
+		error::ErrorType,
+		transaction::CommitError,
+		Error, Starchart,
+	};
+
+	assert_impl_all!(MockBackend: Backend, Clone, Debug, Default, Send, Sync);
+	assert_impl_all!(MockError: Debug, Send, Sync);
+
+	#[test]
+	fn testsuite() -> Result<(), MockError> {
+		block_on(testsuite::run(MockBackend::new()))
+	}
+
+	#[tokio::test]
+	async fn records_calls() -> Result<(), Error> {
+		let backend = MockBackend::new();
+		let chart = Starchart::new(backend.clone()).await?;
+		backend.create_table("settings").await?;
+		backend.clear_calls();
+
+		let settings = chart.table::<(String, u32)>("settings");
+		settings.insert(&("1".to_owned(), 5)).await?;
+
+		assert_eq!(
+			backend.entry::<(String, u32)>("settings", "1"),
+			Some(("1".to_owned(), 5))
+		);
+		assert_eq!(
+			settings.get(&"1".to_owned()).await?,
+			Some(("1".to_owned(), 5))
+		);
+		backend.assert_sequence(&["has_table", "get"]);
+
+		let gets = backend.calls_to("get");
+		let last = gets.last().expect("a get call");
+		assert_eq!((last.table(), last.key()), (Some("settings"), Some("1")));
+
+		Ok(())
+	}
+
+	#[test]
+	#[should_panic(expected = "wasn't called after the previous ones")]
+	fn out_of_order() {
+		let backend = MockBackend::new();
+		block_on(async {
+			backend.init().await.unwrap();
+			backend.has_table("settings").await.unwrap();
+		});
+
+		backend.assert_sequence(&["has_table", "init"]);
+	}
+
+	#[test]
+	fn injected_failures() -> Result<(), Error> {
+		block_on(async {
+			let backend = MockBackend::new();
+			backend.create_table("settings").await?;
+			backend.create_table("other").await?;
+
+			backend.fail("get", Some("settings"));
+			let err = backend.get::<u32>("settings", "1").await.unwrap_err();
+			assert_eq!(err.kind(), &MockErrorType::Injected);
+			assert_eq!(backend.get::<u32>("other", "1").await?, None);
+
+			backend.clear_failures();
+			backend.fail_with("has", None, || {
+				Box::new(CorruptEntryError::new("other", "1", "bad entry"))
+			});
+			let err = Error::from(backend.has("other", "1").await.unwrap_err());
+			assert!(matches!(err.kind(), ErrorType::CorruptEntry));
+
+			backend.clear_failures();
+			backend.create("settings", "1", &"one".to_owned()).await?;
+			let err = backend.get::<u32>("settings", "1").await.unwrap_err();
+			assert_eq!(err.kind(), &MockErrorType::Deserialization);
+			assert!(matches!(Error::from(err).kind(), ErrorType::CorruptEntry));
+
+			Ok(())
+		})
+	}
+
+	#[tokio::test]
+	async fn rollback_failed() -> Result<(), Error> {
+		let backend = MockBackend::new();
+		let chart = Starchart::new(backend.clone()).await?;
+		backend.create_table("settings").await?;
+		backend.create_table("other").await?;
+
+		let commit = || {
+			chart.transaction(|tx| async move {
+				tx.create("settings", &1, "one".to_owned())
+					.create("other", &1, "one".to_owned());
+
+				Ok::<_, Error>(())
+			})
+		};
+
+		// the first create is rolled back when the second fails.
+		backend.fail("create", Some("other"));
+		let err = commit().await.unwrap_err();
+		assert!(matches!(err.kind(), ErrorType::Backend));
+		assert_eq!(backend.entry::<String>("settings", "1"), None);
+
+		backend.fail("delete", Some("settings"));
+		let err = commit().await.unwrap_err();
+		assert!(matches!(err.kind(), ErrorType::RollbackFailed));
+		assert!(err.is_backend());
+		assert_eq!(
+			backend.entry::<String>("settings", "1"),
+			Some("one".to_owned())
+		);
+
+		let source = err.into_source().expect("a commit error");
+		let source = source
+			.downcast_ref::<CommitError<MockError>>()
+			.expect("a commit error");
+		assert!(matches!(
+			source,
+			CommitError::RollbackFailed { rollback, .. } if rollback.kind() == &MockErrorType::Injected
+		));
+
+		Ok(())
+	}
+}
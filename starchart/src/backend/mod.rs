@@ -2,21 +2,55 @@
 //!
 //! [`Starchart`]: crate::Starchart
 
-use std::{error::Error as StdError, iter::FromIterator};
+use std::{error::Error as StdError, iter::FromIterator, time::SystemTime};
 
 use futures_util::{
 	future::{join_all, ok, ready},
-	FutureExt,
+	stream, FutureExt, StreamExt, TryFutureExt, TryStreamExt,
 };
+use serde_json::Value;
 
 use self::futures::{
-	CreateFuture, CreateTableFuture, DeleteFuture, DeleteTableFuture, EnsureFuture,
-	EnsureTableFuture, GetAllFuture, GetFuture, GetKeysFuture, HasFuture, HasTableFuture,
-	InitFuture, ShutdownFuture, UpdateFuture,
+	CreateFuture, CreateMultiFuture, CreateTableFuture, DeleteFuture, DeleteTableFuture,
+	EnsureFuture, EnsureTableFuture, ExpireFuture, GetAllFuture, GetAllStream, GetFilteredFuture,
+	GetFuture, GetKeysFuture, GetKeysInRangeFuture, GetMultiFuture, GetPageFuture, HasFuture,
+	HasTableFuture, HealthCheckFuture, IncrementFuture, InitFuture, RenameTableFuture,
+	ReplaceFuture, ShutdownFuture, TablesFuture, TruncateTableFuture, UpdateFuture,
+	UpdateMultiFuture,
+};
+use crate::{
+	instrument::Span,
+	query::{Filter, PageRange},
+	Entry,
 };
-use crate::Entry;
 
+mod corrupt;
 pub mod futures;
+mod layer;
+#[cfg(any(test, feature = "test-util"))]
+mod mock;
+mod prefixed;
+mod replicated;
+mod retry;
+mod sharded;
+#[cfg(any(test, feature = "test-util"))]
+pub mod testsuite;
+mod tiered;
+mod timeout;
+
+#[cfg(any(test, feature = "test-util"))]
+pub use self::mock::{MockBackend, MockCall, MockError, MockErrorType};
+pub(crate) use self::{corrupt::corrupted, timeout::timed_out};
+pub use self::{
+	corrupt::CorruptEntryError,
+	layer::{layer_fn, BackendBuilder, BackendLayer, Identity, LayerFn, Stack},
+	prefixed::PrefixedBackend,
+	replicated::{Quorum, ReplicatedBackend},
+	retry::{Backoff, RetryBackend, RetryLayer},
+	sharded::{FnvHasher, RebalanceProgress, ShardedBackend, VIRTUAL_NODES},
+	tiered::{ConsistencyPolicy, TieredBackend, TieredError, TieredErrorType, TieredLayer},
+	timeout::{TimeoutBackend, TimeoutError, TimeoutErrorType, TimeoutLayer},
+};
 
 /// The backend to be used to manage data.
 pub trait Backend: Send + Sync {
@@ -38,7 +72,7 @@ pub trait Backend: Send + Sync {
 	///
 	/// # Safety
 	///
-	/// This should not fail, as it's ran upon dropping the [`Starchart`],
+	/// This should not fail, as it's ran upon dropping the last clone of the [`Starchart`],
 	/// and panicking during a drop means resources haven't adequately been cleaned up,
 	/// which isn't inherintly UB however it should still be documented.
 	///
@@ -47,26 +81,112 @@ pub trait Backend: Send + Sync {
 		ready(()).boxed()
 	}
 
+	/// Checks that the backend can serve requests, such as by pinging the database it's connected to.
+	///
+	/// The default impl does nothing, backends that connect to a server should override this.
+	fn health_check(&self) -> HealthCheckFuture<'_, Self::Error> {
+		ok(()).boxed()
+	}
+
+	/// Whether entries can be read without knowing their type, such as into a [`serde_json::Value`].
+	///
+	/// Operations that don't know the entry type, such as [`Starchart::backup`] or [`Starchart::increment`],
+	/// fail with an [`ErrorType::Untyped`] error on backends that aren't. The default impl returns `true`,
+	/// backends storing entries in formats like bincode should override this, and rename tables without
+	/// the default [`Self::rename_table`].
+	///
+	/// [`Starchart::backup`]: crate::Starchart::backup
+	/// [`Starchart::increment`]: crate::Starchart::increment
+	/// [`ErrorType::Untyped`]: crate::error::ErrorType::Untyped
+	fn is_self_describing(&self) -> bool {
+		true
+	}
+
+	/// The size, in bytes, the backend stores the entry as, used for the chart's [`PayloadStats`].
+	///
+	/// The default impl returns [`None`], in which case the entry is measured as compact JSON. Backends storing
+	/// entries in another format should override this, backends wrapping others should ask the one that
+	/// would store the entry.
+	///
+	/// [`PayloadStats`]: crate::stats::PayloadStats
+	fn entry_size<S: Entry>(&self, table: &str, id: &str, entry: &S) -> Option<usize> {
+		let _ = (table, id, entry);
+
+		None
+	}
+
 	/// Check if a table exists.
 	fn has_table<'a>(&'a self, table: &'a str) -> HasTableFuture<'a, Self::Error>;
 
+	/// Gets the names of every table, in no particular order.
+	///
+	/// This includes the internal tables the [`Starchart`] stores metadata in,
+	/// which [`Starchart::tables`] filters out.
+	///
+	/// [`Starchart`]: crate::Starchart
+	/// [`Starchart::tables`]: crate::Starchart::tables
+	fn tables<I>(&self) -> TablesFuture<'_, I, Self::Error>
+	where
+		I: FromIterator<String>;
+
 	/// Inserts or creates a table.
 	fn create_table<'a>(&'a self, table: &'a str) -> CreateTableFuture<'a, Self::Error>;
 
 	/// Deletes or drops a table.
 	fn delete_table<'a>(&'a self, table: &'a str) -> DeleteTableFuture<'a, Self::Error>;
 
+	/// Renames a table, moving every entry in it to the new table.
+	///
+	/// The new table doesn't exist when this is called.
+	///
+	/// The default impl copies every entry to the new table as an untyped value, then deletes the old one,
+	/// backends that can rename tables in place, or aren't [self-describing], should override this.
+	///
+	/// [self-describing]: Self::is_self_describing
+	fn rename_table<'a>(
+		&'a self,
+		table: &'a str,
+		new: &'a str,
+	) -> RenameTableFuture<'a, Self::Error> {
+		Span::backend("rename_table", table)
+			.instrument(async move {
+				self.create_table(new).await?;
+
+				for key in self.get_keys::<Vec<String>>(table).await? {
+					if let Some(entry) = self.get::<Value>(table, &key).await? {
+						self.create(new, &key, &entry).await?;
+					}
+				}
+
+				self.delete_table(table).await
+			})
+			.boxed()
+	}
+
+	/// Deletes every entry in a table, keeping the table itself.
+	///
+	/// The default impl deletes the table and creates it again.
+	fn truncate_table<'a>(&'a self, table: &'a str) -> TruncateTableFuture<'a, Self::Error> {
+		Span::backend("truncate_table", table)
+			.instrument(async move {
+				self.delete_table(table).await?;
+				self.create_table(table).await
+			})
+			.boxed()
+	}
+
 	/// Ensures a table exists.
 	/// Uses [`Self::has_table`] first, then [`Self::create_table`] if it returns false.
 	fn ensure_table<'a>(&'a self, table: &'a str) -> EnsureTableFuture<'a, Self::Error> {
-		async move {
-			if !self.has_table(table).await? {
-				self.create_table(table).await?;
-			}
+		Span::backend("ensure_table", table)
+			.instrument(async move {
+				if !self.has_table(table).await? {
+					self.create_table(table).await?;
+				}
 
-			Ok(())
-		}
-		.boxed()
+				Ok(())
+			})
+			.boxed()
 	}
 
 	/// Gets all entries that match a predicate, to get all entries, use [`get_keys`] first.
@@ -81,16 +201,102 @@ pub trait Backend: Send + Sync {
 		D: Entry,
 		I: FromIterator<D>,
 	{
-		async move {
-			let gets = entries.iter().copied().map(|v| self.get::<D>(table, v));
+		Span::backend("get_all", table)
+			.instrument(async move {
+				let gets = entries.iter().copied().map(|v| self.get::<D>(table, v));
+
+				join_all(gets)
+					.await
+					.into_iter()
+					.filter_map(Result::transpose)
+					.collect::<Result<I, Self::Error>>()
+			})
+			.boxed()
+	}
+
+	/// Streams every entry in the table along with it's key, without collecting the table into memory.
+	///
+	/// The default impl reads the keys with [`Self::get_keys`], then each entry with [`Self::get`] as the
+	/// stream is polled, backends that can iterate natively should override this.
+	fn get_all_stream<'a, D>(&'a self, table: &'a str) -> GetAllStream<'a, D, Self::Error>
+	where
+		D: Entry + 'a,
+	{
+		let entries = self
+			.get_keys::<Vec<String>>(table)
+			.map_ok(move |keys| {
+				stream::iter(keys).then(move |key| async move {
+					let entry = self.get::<D>(table, &key).await?;
+
+					Ok(entry.map(|entry| (key, entry)))
+				})
+			})
+			.try_flatten_stream()
+			.try_filter_map(ok);
+
+		Span::backend("get_all_stream", table)
+			.instrument(entries)
+			.boxed()
+	}
+
+	/// Gets all entries in the table that match the [`Filter`].
+	///
+	/// The default impl reads every entry with [`Self::get_keys`] and [`Self::get`],
+	/// backends that can filter natively should override this.
+	fn get_filtered<'a, D, I>(
+		&'a self,
+		table: &'a str,
+		filter: &'a Filter<'a, D>,
+	) -> GetFilteredFuture<'a, I, Self::Error>
+	where
+		D: Entry,
+		I: FromIterator<D>,
+	{
+		Span::backend("get_filtered", table)
+			.instrument(async move {
+				let keys = self.get_keys::<Vec<_>>(table).await?;
+
+				let gets = keys.iter().map(|key| async move {
+					self.get::<D>(table, key)
+						.await
+						.map(|entry| entry.filter(|entry| filter.matches(key, entry)))
+				});
+
+				join_all(gets)
+					.await
+					.into_iter()
+					.filter_map(Result::transpose)
+					.collect::<Result<I, Self::Error>>()
+			})
+			.boxed()
+	}
+
+	/// Gets the entries in the [`PageRange`], ordered by key.
+	///
+	/// The default impl reads the selected keys with [`Self::get_keys_in_range`], then the
+	/// entries with [`Self::get`].
+	fn get_page<'a, D, I>(
+		&'a self,
+		table: &'a str,
+		range: &'a PageRange,
+	) -> GetPageFuture<'a, I, Self::Error>
+	where
+		D: Entry,
+		I: FromIterator<D>,
+	{
+		Span::backend("get_page", table)
+			.instrument(async move {
+				let keys = self.get_keys_in_range::<Vec<String>>(table, range).await?;
+
+				let gets = keys.iter().map(|key| self.get::<D>(table, key));
 
-			join_all(gets)
-				.await
-				.into_iter()
-				.filter_map(Result::transpose)
-				.collect::<Result<I, Self::Error>>()
-		}
-		.boxed()
+				join_all(gets)
+					.await
+					.into_iter()
+					.filter_map(Result::transpose)
+					.collect::<Result<I, Self::Error>>()
+			})
+			.boxed()
 	}
 
 	/// Gets all the keys in the table.
@@ -98,11 +304,57 @@ pub trait Backend: Send + Sync {
 	where
 		I: FromIterator<String>;
 
+	/// Gets the keys in the [`PageRange`], in order, after skipping the offset and applying the limit.
+	///
+	/// The default impl reads every key with [`Self::get_keys`] and sorts them,
+	/// backends that store keys in order should override this.
+	fn get_keys_in_range<'a, I>(
+		&'a self,
+		table: &'a str,
+		range: &'a PageRange,
+	) -> GetKeysInRangeFuture<'a, I, Self::Error>
+	where
+		I: FromIterator<String>,
+	{
+		Span::backend("get_keys_in_range", table)
+			.instrument(async move {
+				let keys = self.get_keys::<Vec<_>>(table).await?;
+
+				Ok(range
+					.select(&keys)
+					.into_iter()
+					.map(ToOwned::to_owned)
+					.collect())
+			})
+			.boxed()
+	}
+
 	/// Gets a certain entry from a table.
 	fn get<'a, D>(&'a self, table: &'a str, id: &'a str) -> GetFuture<'a, D, Self::Error>
 	where
 		D: Entry;
 
+	/// Gets many entries from a table, in the same order as the keys, with [`None`] for the missing ones.
+	///
+	/// The default impl calls [`Self::get`] for every key concurrently,
+	/// backends that can read many entries at once should override this.
+	fn get_multi<'a, D>(
+		&'a self,
+		table: &'a str,
+		ids: &'a [&'a str],
+	) -> GetMultiFuture<'a, D, Self::Error>
+	where
+		D: Entry,
+	{
+		Span::backend("get_multi", table)
+			.instrument(async move {
+				let gets = ids.iter().map(|id| self.get::<D>(table, id));
+
+				join_all(gets).await.into_iter().collect()
+			})
+			.boxed()
+	}
+
 	/// Checks if an entry exists in a table.
 	fn has<'a>(&'a self, table: &'a str, id: &'a str) -> HasFuture<'a, Self::Error>;
 
@@ -116,6 +368,29 @@ pub trait Backend: Send + Sync {
 	where
 		S: Entry;
 
+	/// Inserts multiple new entries into a table.
+	///
+	/// The default impl calls [`Self::create`] for each entry in order,
+	/// backends that can write many entries at once should override this.
+	fn create_multi<'a, S>(
+		&'a self,
+		table: &'a str,
+		entries: &'a [(&'a str, &'a S)],
+	) -> CreateMultiFuture<'a, Self::Error>
+	where
+		S: Entry,
+	{
+		Span::backend("create_multi", table)
+			.instrument(async move {
+				for (id, value) in entries {
+					self.create(table, id, *value).await?;
+				}
+
+				Ok(())
+			})
+			.boxed()
+	}
+
 	/// Ensures a value exists in the table.
 	fn ensure<'a, S>(
 		&'a self,
@@ -126,14 +401,39 @@ pub trait Backend: Send + Sync {
 	where
 		S: Entry,
 	{
-		async move {
-			if !self.has(table, id).await? {
-				self.create(table, id, value).await?;
-			}
+		Span::backend("ensure", table)
+			.instrument(async move {
+				if !self.has(table, id).await? {
+					self.create(table, id, value).await?;
+				}
 
-			Ok(())
-		}
-		.boxed()
+				Ok(())
+			})
+			.boxed()
+	}
+
+	/// Inserts an entry into a table, replacing it if it already exists.
+	///
+	/// The default impl uses [`Self::has`], then [`Self::update`] or [`Self::create`],
+	/// backends that can upsert natively should override this.
+	fn replace<'a, S>(
+		&'a self,
+		table: &'a str,
+		id: &'a str,
+		value: &'a S,
+	) -> ReplaceFuture<'a, Self::Error>
+	where
+		S: Entry,
+	{
+		Span::backend("replace", table)
+			.instrument(async move {
+				if self.has(table, id).await? {
+					self.update(table, id, value).await
+				} else {
+					self.create(table, id, value).await
+				}
+			})
+			.boxed()
 	}
 
 	/// Updates an existing entry in a table.
@@ -146,6 +446,79 @@ pub trait Backend: Send + Sync {
 	where
 		S: Entry;
 
+	/// Updates multiple existing entries in a table.
+	///
+	/// The default impl calls [`Self::update`] for each entry in order,
+	/// backends that can write many entries at once should override this.
+	fn update_multi<'a, S>(
+		&'a self,
+		table: &'a str,
+		entries: &'a [(&'a str, &'a S)],
+	) -> UpdateMultiFuture<'a, Self::Error>
+	where
+		S: Entry,
+	{
+		Span::backend("update_multi", table)
+			.instrument(async move {
+				for (id, value) in entries {
+					self.update(table, id, *value).await?;
+				}
+
+				Ok(())
+			})
+			.boxed()
+	}
+
 	/// Deletes an entry from a table.
 	fn delete<'a>(&'a self, table: &'a str, id: &'a str) -> DeleteFuture<'a, Self::Error>;
+
+	/// Sets an entry to expire at the given time, returning whether the backend handles expiry itself.
+	///
+	/// The default impl returns false, in which case the [`Starchart`] records the expiry time in
+	/// a separate table and removes the entry once it's expired. Backends with native expiry
+	/// (such as Redis) should override this and return true.
+	///
+	/// [`Starchart`]: crate::Starchart
+	fn expire<'a>(
+		&'a self,
+		table: &'a str,
+		id: &'a str,
+		expires_at: SystemTime,
+	) -> ExpireFuture<'a, Self::Error> {
+		let _ = (table, id, expires_at);
+
+		ok(false).boxed()
+	}
+
+	/// Marks an entry as pinned or unpinned, called by [`Starchart::pin`] and [`Starchart::unpin`].
+	///
+	/// The default impl does nothing. Backends that evict entries on their own (such as an in-memory
+	/// cache with a size limit) should override this and never evict pinned entries.
+	///
+	/// [`Starchart::pin`]: crate::Starchart::pin
+	/// [`Starchart::unpin`]: crate::Starchart::unpin
+	fn set_pinned(&self, table: &str, id: &str, pinned: bool) {
+		let _ = (table, id, pinned);
+	}
+
+	/// Adds `delta` to the integer at the `field` path of an entry, returning the new value, or [`None`]
+	/// if the backend doesn't increment natively.
+	///
+	/// The default impl returns [`None`], in which case the [`Starchart`] reads the entry, adds to the field,
+	/// and writes it back under the table's exclusive lock. Backends with native atomic increments
+	/// (such as Redis or SQL databases) should override this, returning [`None`] if the entry is missing or
+	/// the field isn't an integer, so the chart can report it.
+	///
+	/// [`Starchart`]: crate::Starchart
+	fn increment<'a>(
+		&'a self,
+		table: &'a str,
+		id: &'a str,
+		field: &'a [&'a str],
+		delta: i64,
+	) -> IncrementFuture<'a, Self::Error> {
+		let _ = (table, id, field, delta);
+
+		ok(None).boxed()
+	}
 }
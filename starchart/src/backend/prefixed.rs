@@ -0,0 +1,376 @@
+//! A [`Backend`] that namespaces every table of another [`Backend`].
+
+use std::{iter::FromIterator, sync::Arc, time::SystemTime};
+
+use futures_util::{future::ready, FutureExt};
+
+use super::{
+	futures::{
+		CreateFuture, CreateMultiFuture, CreateTableFuture, DeleteFuture, DeleteTableFuture,
+		ExpireFuture, GetAllFuture, GetFilteredFuture, GetFuture, GetKeysFuture,
+		GetKeysInRangeFuture, GetMultiFuture, GetPageFuture, HasFuture, HasTableFuture,
+		HealthCheckFuture, IncrementFuture, InitFuture, RenameTableFuture, ReplaceFuture,
+		ShutdownFuture, TablesFuture, TruncateTableFuture, UpdateFuture, UpdateMultiFuture,
+	},
+	Backend,
+};
+use crate::{
+	query::{Filter, PageRange},
+	transaction::TransactionalBackend,
+	Entry,
+};
+
+/// A [`Backend`] that prepends a prefix to every table name before passing it to a shared inner [`Backend`].
+///
+/// This is the backend of the charts created with [`Starchart::child`]. The inner backend is owned by
+/// the parent chart, so [`Backend::init`] and [`Backend::shutdown`] do nothing.
+///
+/// [`Starchart::child`]: crate::Starchart::child
+#[derive(Debug)]
+pub struct PrefixedBackend<B> {
+	inner: Arc<B>,
+	prefix: String,
+}
+
+impl<B: Backend> PrefixedBackend<B> {
+	/// Creates a new [`PrefixedBackend`] over the given [`Backend`].
+	///
+	/// The prefix is prepended as-is, so it should end with a separator, such as `"my_library_"`.
+	pub fn new(inner: Arc<B>, prefix: impl Into<String>) -> Self {
+		Self {
+			inner,
+			prefix: prefix.into(),
+		}
+	}
+
+	/// Returns the prefix prepended to table names.
+	#[must_use]
+	pub fn prefix(&self) -> &str {
+		&self.prefix
+	}
+
+	/// Returns a reference to the inner [`Backend`].
+	#[must_use]
+	pub fn inner(&self) -> &B {
+		&self.inner
+	}
+
+	/// Returns the name of the table in the inner [`Backend`].
+	#[must_use]
+	pub fn table_name(&self, table: &str) -> String {
+		[self.prefix.as_str(), table].concat()
+	}
+}
+
+impl<B: Backend> Backend for PrefixedBackend<B> {
+	type Error = B::Error;
+
+	fn init(&self) -> InitFuture<'_, Self::Error> {
+		ready(Ok(())).boxed()
+	}
+
+	unsafe fn shutdown(&self) -> ShutdownFuture<'_> {
+		ready(()).boxed()
+	}
+
+	fn health_check(&self) -> HealthCheckFuture<'_, Self::Error> {
+		self.inner.health_check()
+	}
+
+	fn is_self_describing(&self) -> bool {
+		self.inner.is_self_describing()
+	}
+
+	fn entry_size<S: Entry>(&self, table: &str, id: &str, entry: &S) -> Option<usize> {
+		self.inner.entry_size(&self.table_name(table), id, entry)
+	}
+
+	fn has_table<'a>(&'a self, table: &'a str) -> HasTableFuture<'a, Self::Error> {
+		async move { self.inner.has_table(&self.table_name(table)).await }.boxed()
+	}
+
+	fn tables<I>(&self) -> TablesFuture<'_, I, Self::Error>
+	where
+		I: FromIterator<String>,
+	{
+		async move {
+			let tables = self.inner.tables::<Vec<String>>().await?;
+
+			Ok(tables
+				.into_iter()
+				.filter_map(|table| {
+					table
+						.strip_prefix(self.prefix.as_str())
+						.map(ToOwned::to_owned)
+				})
+				.collect())
+		}
+		.boxed()
+	}
+
+	fn create_table<'a>(&'a self, table: &'a str) -> CreateTableFuture<'a, Self::Error> {
+		async move { self.inner.create_table(&self.table_name(table)).await }.boxed()
+	}
+
+	fn delete_table<'a>(&'a self, table: &'a str) -> DeleteTableFuture<'a, Self::Error> {
+		async move { self.inner.delete_table(&self.table_name(table)).await }.boxed()
+	}
+
+	fn rename_table<'a>(
+		&'a self,
+		table: &'a str,
+		new: &'a str,
+	) -> RenameTableFuture<'a, Self::Error> {
+		async move {
+			self.inner
+				.rename_table(&self.table_name(table), &self.table_name(new))
+				.await
+		}
+		.boxed()
+	}
+
+	fn truncate_table<'a>(&'a self, table: &'a str) -> TruncateTableFuture<'a, Self::Error> {
+		async move { self.inner.truncate_table(&self.table_name(table)).await }.boxed()
+	}
+
+	fn get_all<'a, D, I>(
+		&'a self,
+		table: &'a str,
+		entries: &'a [&'a str],
+	) -> GetAllFuture<'a, I, Self::Error>
+	where
+		D: Entry,
+		I: FromIterator<D>,
+	{
+		async move {
+			self.inner
+				.get_all::<D, I>(&self.table_name(table), entries)
+				.await
+		}
+		.boxed()
+	}
+
+	fn get_filtered<'a, D, I>(
+		&'a self,
+		table: &'a str,
+		filter: &'a Filter<'a, D>,
+	) -> GetFilteredFuture<'a, I, Self::Error>
+	where
+		D: Entry,
+		I: FromIterator<D>,
+	{
+		async move {
+			self.inner
+				.get_filtered::<D, I>(&self.table_name(table), filter)
+				.await
+		}
+		.boxed()
+	}
+
+	fn get_page<'a, D, I>(
+		&'a self,
+		table: &'a str,
+		range: &'a PageRange,
+	) -> GetPageFuture<'a, I, Self::Error>
+	where
+		D: Entry,
+		I: FromIterator<D>,
+	{
+		async move {
+			self.inner
+				.get_page::<D, I>(&self.table_name(table), range)
+				.await
+		}
+		.boxed()
+	}
+
+	fn get_keys<'a, I>(&'a self, table: &'a str) -> GetKeysFuture<'a, I, Self::Error>
+	where
+		I: FromIterator<String>,
+	{
+		async move { self.inner.get_keys(&self.table_name(table)).await }.boxed()
+	}
+
+	fn get_keys_in_range<'a, I>(
+		&'a self,
+		table: &'a str,
+		range: &'a PageRange,
+	) -> GetKeysInRangeFuture<'a, I, Self::Error>
+	where
+		I: FromIterator<String>,
+	{
+		async move {
+			self.inner
+				.get_keys_in_range(&self.table_name(table), range)
+				.await
+		}
+		.boxed()
+	}
+
+	fn get<'a, D>(&'a self, table: &'a str, id: &'a str) -> GetFuture<'a, D, Self::Error>
+	where
+		D: Entry,
+	{
+		async move { self.inner.get(&self.table_name(table), id).await }.boxed()
+	}
+
+	fn get_multi<'a, D>(
+		&'a self,
+		table: &'a str,
+		ids: &'a [&'a str],
+	) -> GetMultiFuture<'a, D, Self::Error>
+	where
+		D: Entry,
+	{
+		async move { self.inner.get_multi(&self.table_name(table), ids).await }.boxed()
+	}
+
+	fn has<'a>(&'a self, table: &'a str, id: &'a str) -> HasFuture<'a, Self::Error> {
+		async move { self.inner.has(&self.table_name(table), id).await }.boxed()
+	}
+
+	fn create<'a, S>(
+		&'a self,
+		table: &'a str,
+		id: &'a str,
+		value: &'a S,
+	) -> CreateFuture<'a, Self::Error>
+	where
+		S: Entry,
+	{
+		async move { self.inner.create(&self.table_name(table), id, value).await }.boxed()
+	}
+
+	fn create_multi<'a, S>(
+		&'a self,
+		table: &'a str,
+		entries: &'a [(&'a str, &'a S)],
+	) -> CreateMultiFuture<'a, Self::Error>
+	where
+		S: Entry,
+	{
+		async move {
+			self.inner
+				.create_multi(&self.table_name(table), entries)
+				.await
+		}
+		.boxed()
+	}
+
+	fn replace<'a, S>(
+		&'a self,
+		table: &'a str,
+		id: &'a str,
+		value: &'a S,
+	) -> ReplaceFuture<'a, Self::Error>
+	where
+		S: Entry,
+	{
+		async move { self.inner.replace(&self.table_name(table), id, value).await }.boxed()
+	}
+
+	fn update<'a, S>(
+		&'a self,
+		table: &'a str,
+		id: &'a str,
+		value: &'a S,
+	) -> UpdateFuture<'a, Self::Error>
+	where
+		S: Entry,
+	{
+		async move { self.inner.update(&self.table_name(table), id, value).await }.boxed()
+	}
+
+	fn update_multi<'a, S>(
+		&'a self,
+		table: &'a str,
+		entries: &'a [(&'a str, &'a S)],
+	) -> UpdateMultiFuture<'a, Self::Error>
+	where
+		S: Entry,
+	{
+		async move {
+			self.inner
+				.update_multi(&self.table_name(table), entries)
+				.await
+		}
+		.boxed()
+	}
+
+	fn delete<'a>(&'a self, table: &'a str, id: &'a str) -> DeleteFuture<'a, Self::Error> {
+		async move { self.inner.delete(&self.table_name(table), id).await }.boxed()
+	}
+
+	fn set_pinned(&self, table: &str, id: &str, pinned: bool) {
+		self.inner.set_pinned(&self.table_name(table), id, pinned);
+	}
+
+	fn expire<'a>(
+		&'a self,
+		table: &'a str,
+		id: &'a str,
+		expires_at: SystemTime,
+	) -> ExpireFuture<'a, Self::Error> {
+		async move {
+			self.inner
+				.expire(&self.table_name(table), id, expires_at)
+				.await
+		}
+		.boxed()
+	}
+
+	fn increment<'a>(
+		&'a self,
+		table: &'a str,
+		id: &'a str,
+		field: &'a [&'a str],
+		delta: i64,
+	) -> IncrementFuture<'a, Self::Error> {
+		async move {
+			self.inner
+				.increment(&self.table_name(table), id, field, delta)
+				.await
+		}
+		.boxed()
+	}
+}
+
+impl<B: TransactionalBackend> TransactionalBackend for PrefixedBackend<B> {}
+
+#[cfg(test)]
+mod tests {
+	use crate::{
+		action::CreateEntryAction,
+		backend::{Backend, MockBackend},
+		testing::TestSettings,
+		Error, Starchart,
+	};
+
+	#[tokio::test]
+	async fn child_charts() -> Result<(), Error> {
+		let chart = Starchart::new(MockBackend::new()).await?;
+		let child = chart.child("library_");
+
+		chart.create_table("table").await?;
+		child.create_table("table").await?;
+
+		chart.create("table", "1", &TestSettings::default()).await?;
+
+		assert!(chart.has_table("library_table").await?);
+		assert!(!child.has("table", "1").await?);
+
+		let settings = TestSettings::default();
+		let mut action = CreateEntryAction::new();
+		action.set_table("table").set_entry(&settings);
+		action.run_create_entry(&child).await?;
+
+		assert!(chart.has("library_table", "1").await?);
+
+		let grandchild = child.child("inner_");
+		grandchild.create_table("table").await?;
+
+		assert!(chart.has_table("library_inner_table").await?);
+
+		Ok(())
+	}
+}
@@ -0,0 +1,701 @@
+//! A [`Backend`] that keeps a copy of every entry on multiple [`Backend`]s.
+
+use std::{
+	cmp::Reverse, collections::BTreeSet, future::Future, iter::FromIterator, time::SystemTime,
+};
+
+use futures_util::{future::join_all, FutureExt};
+use parking_lot::Mutex;
+
+use super::{
+	futures::{
+		CreateFuture, CreateMultiFuture, CreateTableFuture, DeleteFuture, DeleteTableFuture,
+		EnsureTableFuture, ExpireFuture, GetFuture, GetKeysFuture, HasFuture, HasTableFuture,
+		HealthCheckFuture, InitFuture, ReplaceFuture, ShutdownFuture, TablesFuture, UpdateFuture,
+		UpdateMultiFuture,
+	},
+	Backend,
+};
+use crate::{stats::ReplicationStats, transaction::TransactionalBackend, util::fingerprint, Entry};
+
+/// How many replicas must respond for a read or write to succeed.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Quorum {
+	/// A single replica.
+	One,
+	/// More than half of the replicas.
+	#[default]
+	Majority,
+	/// Every replica.
+	All,
+	/// A fixed amount of replicas, clamped to between one and the amount of replicas.
+	Count(usize),
+}
+
+impl Quorum {
+	/// The amount of replicas that must respond, out of the given amount of replicas.
+	#[must_use]
+	pub const fn required(self, replicas: usize) -> usize {
+		match self {
+			Self::One | Self::Count(0) => 1,
+			Self::Majority => replicas / 2 + 1,
+			Self::All => replicas,
+			Self::Count(count) if count > replicas => replicas,
+			Self::Count(count) => count,
+		}
+	}
+}
+
+/// A [`Backend`] that writes every entry to multiple replica [`Backend`]s.
+///
+/// Writes are sent to every replica, and succeed once the [write quorum] of replicas have succeeded.
+/// Reads ask every replica, and need the [read quorum] of replicas to respond. If the replicas that
+/// responded disagree, the entry returned by the most replicas wins, and if at least the read quorum of
+/// replicas returned it, the other replicas are overwritten with it, which is counted in [`Self::stats`].
+///
+/// Replicas don't record when entries were written, so a tie can't be settled. Ties go to an entry over
+/// a missing one (and to the earliest replica between entries), and are never repaired, so a tie
+/// between a stale replica and a replica that missed a delete never deletes the entry elsewhere.
+///
+/// [write quorum]: Self::with_write_quorum
+/// [read quorum]: Self::with_read_quorum
+#[derive(Debug)]
+pub struct ReplicatedBackend<B> {
+	replicas: Vec<B>,
+	read_quorum: Quorum,
+	write_quorum: Quorum,
+	stats: Mutex<ReplicationStats>,
+}
+
+impl<B: Backend> ReplicatedBackend<B> {
+	/// Creates a new [`ReplicatedBackend`] over the given replicas, with a [`Quorum::Majority`] for reads and writes.
+	///
+	/// # Panics
+	///
+	/// Panics if no replicas are given.
+	#[must_use]
+	pub fn new(replicas: Vec<B>) -> Self {
+		assert!(
+			!replicas.is_empty(),
+			"a replicated backend needs at least one replica"
+		);
+
+		Self {
+			replicas,
+			read_quorum: Quorum::default(),
+			write_quorum: Quorum::default(),
+			stats: Mutex::default(),
+		}
+	}
+
+	/// Sets how many replicas must respond to a read, consuming and returning the backend.
+	#[must_use]
+	pub const fn with_read_quorum(mut self, quorum: Quorum) -> Self {
+		self.read_quorum = quorum;
+
+		self
+	}
+
+	/// Sets how many replicas must succeed for a write to succeed, consuming and returning the backend.
+	#[must_use]
+	pub const fn with_write_quorum(mut self, quorum: Quorum) -> Self {
+		self.write_quorum = quorum;
+
+		self
+	}
+
+	/// Returns the replica [`Backend`]s.
+	#[must_use]
+	pub fn replicas(&self) -> &[B] {
+		&self.replicas
+	}
+
+	/// The [`Quorum`] used for reads.
+	#[must_use]
+	pub const fn read_quorum(&self) -> Quorum {
+		self.read_quorum
+	}
+
+	/// The [`Quorum`] used for writes.
+	#[must_use]
+	pub const fn write_quorum(&self) -> Quorum {
+		self.write_quorum
+	}
+
+	/// Statistics on how often the replicas have disagreed.
+	pub fn stats(&self) -> ReplicationStats {
+		*self.stats.lock()
+	}
+
+	/// Runs the operation on every replica, returning the successful results if the quorum was met.
+	async fn quorum<'a, T, F, Fut>(&'a self, quorum: Quorum, op: F) -> Result<Vec<T>, B::Error>
+	where
+		F: FnMut(&'a B) -> Fut,
+		Fut: Future<Output = Result<T, B::Error>>,
+	{
+		let results = join_all(self.replicas.iter().map(op)).await;
+
+		self.check_quorum(quorum, results)
+	}
+
+	/// Returns the successful results if enough of them succeeded to meet the quorum.
+	fn check_quorum<T>(
+		&self,
+		quorum: Quorum,
+		results: Vec<Result<T, B::Error>>,
+	) -> Result<Vec<T>, B::Error> {
+		let mut succeeded = Vec::with_capacity(results.len());
+		let mut error = None;
+		for result in results {
+			match result {
+				Ok(value) => succeeded.push(value),
+				Err(e) => {
+					error.get_or_insert(e);
+				}
+			}
+		}
+
+		match error {
+			Some(e) if succeeded.len() < quorum.required(self.replicas.len()) => Err(e),
+			_ => Ok(succeeded),
+		}
+	}
+
+	async fn write<'a, T, F, Fut>(&'a self, op: F) -> Result<Vec<T>, B::Error>
+	where
+		F: FnMut(&'a B) -> Fut,
+		Fut: Future<Output = Result<T, B::Error>>,
+	{
+		let results = join_all(self.replicas.iter().map(op)).await;
+
+		let failed = results.iter().filter(|result| result.is_err()).count();
+		if failed > 0 {
+			self.stats.lock().failed_writes += failed as u64;
+		}
+
+		self.check_quorum(self.write_quorum, results)
+	}
+
+	/// Picks the value returned by the most replicas, ties going to the value `prefer` ranks highest,
+	/// then the earliest replica.
+	fn vote<T: PartialEq, P: Fn(&T) -> bool>(values: &[T], prefer: P) -> Option<Vote<'_, T>> {
+		let count = |value: &T| values.iter().filter(|other| *other == value).count();
+
+		let winner = values.iter().max_by_key(|value| {
+			(
+				count(value),
+				prefer(value),
+				Reverse(values.iter().position(|other| other == *value)),
+			)
+		})?;
+
+		let votes = count(winner);
+		let tied = values
+			.iter()
+			.any(|value| value != winner && count(value) == votes);
+
+		Some(Vote {
+			value: winner,
+			votes,
+			tied,
+		})
+	}
+
+	/// Picks the answer given by the most replicas, ties going to `true` as with entries in [`Self::get`].
+	fn vote_exists(values: &[bool]) -> bool {
+		Self::vote(values, |exists| *exists).is_some_and(|vote| *vote.value)
+	}
+}
+
+/// The outcome of [`ReplicatedBackend::vote`].
+struct Vote<'a, T> {
+	value: &'a T,
+	votes: usize,
+	tied: bool,
+}
+
+impl<B: Backend> Backend for ReplicatedBackend<B> {
+	type Error = B::Error;
+
+	fn init(&self) -> InitFuture<'_, Self::Error> {
+		async move {
+			self.quorum(Quorum::All, Backend::init).await?;
+
+			Ok(())
+		}
+		.boxed()
+	}
+
+	unsafe fn shutdown(&self) -> ShutdownFuture<'_> {
+		async move {
+			join_all(
+				self.replicas
+					.iter()
+					.map(|replica| unsafe { replica.shutdown() }),
+			)
+			.await;
+		}
+		.boxed()
+	}
+
+	/// The replicas are healthy if enough of them are to meet the write quorum.
+	fn health_check(&self) -> HealthCheckFuture<'_, Self::Error> {
+		async move {
+			self.quorum(self.write_quorum, Backend::health_check)
+				.await?;
+
+			Ok(())
+		}
+		.boxed()
+	}
+
+	fn is_self_describing(&self) -> bool {
+		self.replicas.iter().all(Backend::is_self_describing)
+	}
+
+	fn entry_size<S: Entry>(&self, table: &str, id: &str, entry: &S) -> Option<usize> {
+		self.replicas
+			.first()
+			.and_then(|replica| replica.entry_size(table, id, entry))
+	}
+
+	fn has_table<'a>(&'a self, table: &'a str) -> HasTableFuture<'a, Self::Error> {
+		async move {
+			let exists = self
+				.quorum(self.read_quorum, |replica| replica.has_table(table))
+				.await?;
+
+			Ok(Self::vote_exists(&exists))
+		}
+		.boxed()
+	}
+
+	fn tables<I>(&self) -> TablesFuture<'_, I, Self::Error>
+	where
+		I: FromIterator<String>,
+	{
+		async move {
+			let tables = self
+				.quorum(self.read_quorum, B::tables::<Vec<String>>)
+				.await?;
+
+			// tables that only reached some replicas are still listed, like keys are.
+			Ok(tables
+				.into_iter()
+				.flatten()
+				.collect::<BTreeSet<_>>()
+				.into_iter()
+				.collect())
+		}
+		.boxed()
+	}
+
+	fn create_table<'a>(&'a self, table: &'a str) -> CreateTableFuture<'a, Self::Error> {
+		async move {
+			self.write(|replica| replica.create_table(table)).await?;
+
+			Ok(())
+		}
+		.boxed()
+	}
+
+	fn delete_table<'a>(&'a self, table: &'a str) -> DeleteTableFuture<'a, Self::Error> {
+		async move {
+			self.write(|replica| replica.delete_table(table)).await?;
+
+			Ok(())
+		}
+		.boxed()
+	}
+
+	fn ensure_table<'a>(&'a self, table: &'a str) -> EnsureTableFuture<'a, Self::Error> {
+		async move {
+			self.write(|replica| replica.ensure_table(table)).await?;
+
+			Ok(())
+		}
+		.boxed()
+	}
+
+	fn get_keys<'a, I>(&'a self, table: &'a str) -> GetKeysFuture<'a, I, Self::Error>
+	where
+		I: FromIterator<String>,
+	{
+		async move {
+			let keys = self
+				.quorum(self.read_quorum, |replica| {
+					replica.get_keys::<Vec<String>>(table)
+				})
+				.await?;
+
+			// entries that only reached some replicas are still listed, reading them repairs the rest.
+			Ok(keys
+				.into_iter()
+				.flatten()
+				.collect::<BTreeSet<_>>()
+				.into_iter()
+				.collect())
+		}
+		.boxed()
+	}
+
+	fn get<'a, D>(&'a self, table: &'a str, id: &'a str) -> GetFuture<'a, D, Self::Error>
+	where
+		D: Entry,
+	{
+		async move {
+			let results = join_all(
+				self.replicas
+					.iter()
+					.map(|replica| replica.get::<D>(table, id)),
+			)
+			.await;
+
+			let mut responses = Vec::with_capacity(results.len());
+			let mut error = None;
+			for (replica, result) in self.replicas.iter().zip(results) {
+				match result {
+					Ok(entry) => {
+						responses.push((replica, (entry.is_some(), fingerprint(&entry)), entry));
+					}
+					Err(e) => {
+						error.get_or_insert(e);
+					}
+				}
+			}
+
+			if let Some(e) = error {
+				if responses.len() < self.read_quorum.required(self.replicas.len()) {
+					return Err(e);
+				}
+			}
+
+			let fingerprints = responses
+				.iter()
+				.map(|(_, fingerprint, _)| *fingerprint)
+				.collect::<Vec<_>>();
+			let Some(vote) = Self::vote(&fingerprints, |(exists, _)| *exists) else {
+				return Ok(None);
+			};
+			let agreed = *vote.value;
+
+			// only an answer given by enough replicas to meet the read quorum is trusted to overwrite the others.
+			let repair = !vote.tied && vote.votes >= self.read_quorum.required(self.replicas.len());
+
+			let divergent = responses
+				.iter()
+				.filter(|(_, fingerprint, _)| *fingerprint != agreed)
+				.map(|(replica, ..)| *replica)
+				.collect::<Vec<_>>();
+
+			let position = responses
+				.iter()
+				.position(|(_, fingerprint, _)| *fingerprint == agreed)
+				.expect("the agreed fingerprint was returned by a replica");
+			let entry = responses.swap_remove(position).2;
+
+			let mut repairs = 0;
+			for replica in divergent.iter().filter(|_| repair) {
+				let repaired = match &entry {
+					Some(entry) => replica.replace(table, id, entry).await,
+					None => replica.delete(table, id).await,
+				};
+
+				// a failed repair is retried on the next read.
+				if repaired.is_ok() {
+					repairs += 1;
+				}
+			}
+
+			let mut stats = self.stats.lock();
+			stats.reads += 1;
+			if !divergent.is_empty() {
+				stats.divergent_reads += 1;
+				stats.repairs += repairs;
+			}
+			drop(stats);
+
+			Ok(entry)
+		}
+		.boxed()
+	}
+
+	fn has<'a>(&'a self, table: &'a str, id: &'a str) -> HasFuture<'a, Self::Error> {
+		async move {
+			let exists = self
+				.quorum(self.read_quorum, |replica| replica.has(table, id))
+				.await?;
+
+			Ok(Self::vote_exists(&exists))
+		}
+		.boxed()
+	}
+
+	fn create<'a, S>(
+		&'a self,
+		table: &'a str,
+		id: &'a str,
+		value: &'a S,
+	) -> CreateFuture<'a, Self::Error>
+	where
+		S: Entry,
+	{
+		async move {
+			self.write(|replica| replica.create(table, id, value))
+				.await?;
+
+			Ok(())
+		}
+		.boxed()
+	}
+
+	fn create_multi<'a, S>(
+		&'a self,
+		table: &'a str,
+		entries: &'a [(&'a str, &'a S)],
+	) -> CreateMultiFuture<'a, Self::Error>
+	where
+		S: Entry,
+	{
+		async move {
+			self.write(|replica| replica.create_multi(table, entries))
+				.await?;
+
+			Ok(())
+		}
+		.boxed()
+	}
+
+	fn replace<'a, S>(
+		&'a self,
+		table: &'a str,
+		id: &'a str,
+		value: &'a S,
+	) -> ReplaceFuture<'a, Self::Error>
+	where
+		S: Entry,
+	{
+		async move {
+			self.write(|replica| replica.replace(table, id, value))
+				.await?;
+
+			Ok(())
+		}
+		.boxed()
+	}
+
+	fn update<'a, S>(
+		&'a self,
+		table: &'a str,
+		id: &'a str,
+		value: &'a S,
+	) -> UpdateFuture<'a, Self::Error>
+	where
+		S: Entry,
+	{
+		async move {
+			self.write(|replica| replica.update(table, id, value))
+				.await?;
+
+			Ok(())
+		}
+		.boxed()
+	}
+
+	fn update_multi<'a, S>(
+		&'a self,
+		table: &'a str,
+		entries: &'a [(&'a str, &'a S)],
+	) -> UpdateMultiFuture<'a, Self::Error>
+	where
+		S: Entry,
+	{
+		async move {
+			self.write(|replica| replica.update_multi(table, entries))
+				.await?;
+
+			Ok(())
+		}
+		.boxed()
+	}
+
+	fn delete<'a>(&'a self, table: &'a str, id: &'a str) -> DeleteFuture<'a, Self::Error> {
+		async move {
+			self.write(|replica| replica.delete(table, id)).await?;
+
+			Ok(())
+		}
+		.boxed()
+	}
+
+	fn set_pinned(&self, table: &str, id: &str, pinned: bool) {
+		for replica in &self.replicas {
+			replica.set_pinned(table, id, pinned);
+		}
+	}
+
+	fn expire<'a>(
+		&'a self,
+		table: &'a str,
+		id: &'a str,
+		expires_at: SystemTime,
+	) -> ExpireFuture<'a, Self::Error> {
+		async move {
+			let supported = self
+				.write(|replica| replica.expire(table, id, expires_at))
+				.await?;
+
+			// the chart tracks the expiry itself unless every replica will expire the entry.
+			Ok(supported.len() == self.replicas.len()
+				&& supported.into_iter().all(|supported| supported))
+		}
+		.boxed()
+	}
+}
+
+impl<B: TransactionalBackend> TransactionalBackend for ReplicatedBackend<B> {}
+
+#[cfg(test)]
+mod tests {
+	use super::{Quorum, ReplicatedBackend};
+	use crate::{
+		backend::{Backend, MockBackend, MockError},
+		testing::TestSettings,
+		Error, Starchart,
+	};
+
+	async fn replicas(
+		amount: usize,
+	) -> Result<(Vec<MockBackend>, ReplicatedBackend<MockBackend>), MockError> {
+		let replicas = (0..amount).map(|_| MockBackend::new()).collect::<Vec<_>>();
+		let backend = ReplicatedBackend::new(replicas.clone());
+		backend.create_table("table").await?;
+
+		Ok((replicas, backend))
+	}
+
+	#[tokio::test]
+	async fn read_quorum_failure() -> Result<(), MockError> {
+		let (replicas, backend) = replicas(3).await?;
+		backend.create("table", "1", &1_u32).await?;
+
+		replicas[0].fail("get", Some("table"));
+		assert_eq!(backend.get::<u32>("table", "1").await?, Some(1));
+
+		replicas[1].fail("get", Some("table"));
+		assert!(backend.get::<u32>("table", "1").await.is_err());
+
+		let backend = backend.with_read_quorum(Quorum::One);
+		assert_eq!(backend.get::<u32>("table", "1").await?, Some(1));
+
+		Ok(())
+	}
+
+	#[tokio::test]
+	async fn write_quorum_failure() -> Result<(), MockError> {
+		let (replicas, backend) = replicas(3).await?;
+
+		replicas[0].fail("create", Some("table"));
+		backend.create("table", "1", &1_u32).await?;
+		assert_eq!(backend.stats().failed_writes(), 1);
+
+		replicas[1].fail("create", Some("table"));
+		assert!(backend.create("table", "2", &2_u32).await.is_err());
+		assert_eq!(backend.stats().failed_writes(), 3);
+		assert_eq!(replicas[2].entry::<u32>("table", "2"), Some(2));
+
+		Ok(())
+	}
+
+	#[tokio::test]
+	async fn ties_are_not_repaired() -> Result<(), MockError> {
+		let (replicas, backend) = replicas(2).await?;
+		replicas[1].create("table", "1", &1_u32).await?;
+
+		// a missing entry never wins a tie, and neither replica is overwritten.
+		assert_eq!(backend.get::<u32>("table", "1").await?, Some(1));
+		assert!(backend.has("table", "1").await?);
+		assert_eq!(replicas[0].entry::<u32>("table", "1"), None);
+		assert_eq!(replicas[1].entry::<u32>("table", "1"), Some(1));
+
+		let stats = backend.stats();
+		assert_eq!((stats.divergent_reads(), stats.repairs()), (1, 0));
+
+		Ok(())
+	}
+
+	#[tokio::test]
+	async fn repairs_need_the_read_quorum() -> Result<(), MockError> {
+		let (replicas, backend) = replicas(4).await?;
+		let backend = backend.with_read_quorum(Quorum::Count(3));
+		replicas[0].create("table", "1", &1_u32).await?;
+		replicas[1].create("table", "1", &1_u32).await?;
+		replicas[2].create("table", "1", &2_u32).await?;
+
+		// two votes out of the three required aren't enough to overwrite the other replicas.
+		assert_eq!(backend.get::<u32>("table", "1").await?, Some(1));
+		assert_eq!(backend.stats().repairs(), 0);
+		assert_eq!(replicas[2].entry::<u32>("table", "1"), Some(2));
+
+		replicas[2].replace("table", "1", &1_u32).await?;
+		assert_eq!(backend.get::<u32>("table", "1").await?, Some(1));
+		assert_eq!(backend.stats().repairs(), 1);
+		assert_eq!(replicas[3].entry::<u32>("table", "1"), Some(1));
+
+		Ok(())
+	}
+
+	#[tokio::test]
+	async fn replicated() -> Result<(), Error> {
+		let chart = Starchart::new(ReplicatedBackend::new(
+			(0..3).map(|_| MockBackend::new()).collect::<Vec<_>>(),
+		))
+		.await?;
+		chart.create_table("table").await?;
+
+		let table = chart.table::<TestSettings>("table");
+		table.insert(&TestSettings::default()).await?;
+
+		for replica in chart.replicas() {
+			assert!(replica.has("table", "1").await?);
+		}
+
+		let stale = TestSettings {
+			value: "stale".to_owned(),
+			..TestSettings::default()
+		};
+		chart.replicas()[2].replace("table", "1", &stale).await?;
+
+		assert_eq!(table.get(&1_u32).await?, Some(TestSettings::default()));
+		assert_eq!(
+			chart.replicas()[2]
+				.get::<TestSettings>("table", "1")
+				.await?,
+			Some(TestSettings::default())
+		);
+
+		let stats = chart.stats();
+		assert_eq!(stats.divergent_reads(), 1);
+		assert_eq!(stats.repairs(), 1);
+
+		chart.replicas()[0].delete("table", "1").await?;
+		chart.replicas()[1].delete("table", "1").await?;
+
+		assert!(!chart.has("table", "1").await?);
+		assert_eq!(table.get(&1_u32).await?, None);
+		assert!(!chart.replicas()[2].has("table", "1").await?);
+		assert_eq!(chart.stats().repairs(), 2);
+
+		chart.replicas()[0].delete_table("table").await?;
+		chart.replicas()[1].delete_table("table").await?;
+		assert!(!chart.has_table("table").await?);
+
+		assert_eq!(Quorum::One.required(3), 1);
+		assert_eq!(Quorum::Majority.required(4), 3);
+		assert_eq!(Quorum::All.required(3), 3);
+		assert_eq!(Quorum::Count(5).required(3), 3);
+		assert_eq!(Quorum::Count(0).required(3), 1);
+
+		Ok(())
+	}
+}
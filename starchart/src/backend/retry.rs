@@ -0,0 +1,557 @@
+//! A [`Backend`] that retries the failed operations of another [`Backend`].
+
+use std::{
+	fmt::{Debug, Formatter, Result as FmtResult},
+	future::Future,
+	iter::FromIterator,
+	sync::Arc,
+	time::{Duration, SystemTime},
+};
+
+use futures_util::FutureExt;
+use parking_lot::Mutex;
+
+use super::{
+	futures::{
+		CreateFuture, CreateMultiFuture, CreateTableFuture, DeleteFuture, DeleteTableFuture,
+		ExpireFuture, GetAllFuture, GetFilteredFuture, GetFuture, GetKeysFuture,
+		GetKeysInRangeFuture, GetMultiFuture, GetPageFuture, HasFuture, HasTableFuture,
+		HealthCheckFuture, IncrementFuture, InitFuture, RenameTableFuture, ReplaceFuture,
+		ShutdownFuture, TablesFuture, TruncateTableFuture, UpdateFuture, UpdateMultiFuture,
+	},
+	Backend, BackendLayer,
+};
+use crate::{
+	query::{Filter, PageRange},
+	stats::RetryStats,
+	timer::{ThreadTimer, Timer},
+	transaction::TransactionalBackend,
+	Entry,
+};
+
+/// Decides whether an error is transient and the operation should be attempted again.
+type Retryable<E> = Arc<dyn Fn(&E) -> bool + Send + Sync>;
+
+/// How long a [`RetryBackend`] waits before attempting an operation again.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum Backoff {
+	/// Retry immediately.
+	None,
+	/// Wait the same amount of time before every retry.
+	Constant(Duration),
+	/// Wait `initial` before the first retry, doubling the wait for every retry after it, up to `max`.
+	///
+	/// This is the default, starting at 100 milliseconds and waiting at most 10 seconds.
+	Exponential {
+		/// The wait before the first retry.
+		initial: Duration,
+		/// The longest wait between two attempts.
+		max: Duration,
+	},
+}
+
+impl Backoff {
+	/// The time to wait before the given retry, starting at 1 for the retry after the first attempt.
+	#[must_use]
+	pub fn delay(self, retry: u32) -> Duration {
+		match self {
+			Self::None => Duration::ZERO,
+			Self::Constant(delay) => delay,
+			Self::Exponential { initial, max } => 2_u32
+				.checked_pow(retry.saturating_sub(1))
+				.and_then(|factor| initial.checked_mul(factor))
+				.map_or(max, |delay| delay.min(max)),
+		}
+	}
+}
+
+impl Default for Backoff {
+	fn default() -> Self {
+		Self::Exponential {
+			initial: Duration::from_millis(100),
+			max: Duration::from_secs(10),
+		}
+	}
+}
+
+/// A [`BackendLayer`] that wraps a [`Backend`] in a [`RetryBackend`], retrying every error.
+///
+/// Use [`RetryBackend::with_retryable`] to only retry some errors.
+#[derive(Debug, Clone)]
+pub struct RetryLayer {
+	max_attempts: u32,
+	backoff: Backoff,
+	timer: Arc<dyn Timer>,
+}
+
+impl RetryLayer {
+	/// Creates a new [`RetryLayer`], making 3 attempts with the default [`Backoff`].
+	#[must_use]
+	pub fn new() -> Self {
+		Self {
+			max_attempts: 3,
+			backoff: Backoff::default(),
+			timer: Arc::new(ThreadTimer::new()),
+		}
+	}
+
+	/// Sets the amount of times an operation is attempted, including the first attempt.
+	#[must_use]
+	pub fn with_max_attempts(mut self, max_attempts: u32) -> Self {
+		self.max_attempts = max_attempts.max(1);
+
+		self
+	}
+
+	/// Sets the [`Backoff`] between attempts.
+	#[must_use]
+	pub const fn with_backoff(mut self, backoff: Backoff) -> Self {
+		self.backoff = backoff;
+
+		self
+	}
+
+	/// Sets the [`Timer`] used to wait between attempts.
+	#[must_use]
+	pub fn with_timer<T: Timer + 'static>(mut self, timer: T) -> Self {
+		self.timer = Arc::new(timer);
+
+		self
+	}
+}
+
+impl Default for RetryLayer {
+	fn default() -> Self {
+		Self::new()
+	}
+}
+
+impl<B: Backend> BackendLayer<B> for RetryLayer {
+	type Backend = RetryBackend<B>;
+
+	fn layer(&self, inner: B) -> Self::Backend {
+		RetryBackend {
+			max_attempts: self.max_attempts,
+			backoff: self.backoff,
+			timer: self.timer.clone(),
+			..RetryBackend::new(inner)
+		}
+	}
+}
+
+/// A [`Backend`] that attempts the operations of another [`Backend`] again when they fail,
+/// so transient failures of networked backends don't reach the caller.
+///
+/// Every error is retried unless told otherwise with [`Self::with_retryable`], the error of the
+/// last attempt is returned once the attempts are used up. Writes are retried as well,
+/// so a [`Backend::create`] that succeeded but failed to report it will fail again with
+/// whatever the backend reports for an existing entry, which should be classified as not retryable.
+///
+/// The waits between attempts come from a [`Timer`], which is a [`ThreadTimer`] by default.
+pub struct RetryBackend<B: Backend> {
+	inner: B,
+	max_attempts: u32,
+	backoff: Backoff,
+	retryable: Retryable<B::Error>,
+	timer: Arc<dyn Timer>,
+	stats: Mutex<RetryStats>,
+}
+
+impl<B: Backend> RetryBackend<B> {
+	/// Creates a new [`RetryBackend`], making 3 attempts with the default [`Backoff`] and retrying every error.
+	#[must_use]
+	pub fn new(inner: B) -> Self {
+		Self {
+			inner,
+			max_attempts: 3,
+			backoff: Backoff::default(),
+			retryable: Arc::new(|_| true),
+			timer: Arc::new(ThreadTimer::new()),
+			stats: Mutex::default(),
+		}
+	}
+
+	/// Sets the amount of times an operation is attempted, including the first attempt.
+	///
+	/// This is at least 1, which disables retries.
+	#[must_use]
+	pub fn with_max_attempts(mut self, max_attempts: u32) -> Self {
+		self.max_attempts = max_attempts.max(1);
+
+		self
+	}
+
+	/// Sets the [`Backoff`] between attempts.
+	#[must_use]
+	pub const fn with_backoff(mut self, backoff: Backoff) -> Self {
+		self.backoff = backoff;
+
+		self
+	}
+
+	/// Sets which errors are transient and worth retrying, errors the function returns false for
+	/// are returned right away.
+	#[must_use]
+	pub fn with_retryable<F>(mut self, retryable: F) -> Self
+	where
+		F: Fn(&B::Error) -> bool + Send + Sync + 'static,
+	{
+		self.retryable = Arc::new(retryable);
+
+		self
+	}
+
+	/// Sets the [`Timer`] used to wait between attempts.
+	#[must_use]
+	pub fn with_timer<T: Timer + 'static>(mut self, timer: T) -> Self {
+		self.timer = Arc::new(timer);
+
+		self
+	}
+
+	/// Returns a reference to the inner [`Backend`].
+	pub const fn inner(&self) -> &B {
+		&self.inner
+	}
+
+	/// The amount of times an operation is attempted, including the first attempt.
+	#[must_use]
+	pub const fn max_attempts(&self) -> u32 {
+		self.max_attempts
+	}
+
+	/// The [`Backoff`] between attempts.
+	#[must_use]
+	pub const fn backoff(&self) -> Backoff {
+		self.backoff
+	}
+
+	/// Statistics on how often operations were retried.
+	pub fn stats(&self) -> RetryStats {
+		*self.stats.lock()
+	}
+
+	/// Runs the operation until it succeeds, fails with an error that isn't retryable, or runs out of attempts.
+	async fn retry<T, F, Fut>(&self, mut operation: F) -> Result<T, B::Error>
+	where
+		F: FnMut() -> Fut,
+		Fut: Future<Output = Result<T, B::Error>>,
+	{
+		let mut attempt = 1;
+
+		loop {
+			let e = match operation().await {
+				Ok(value) => return Ok(value),
+				Err(e) => e,
+			};
+
+			if !(self.retryable)(&e) {
+				return Err(e);
+			}
+
+			if attempt >= self.max_attempts {
+				self.stats.lock().exhausted += 1;
+
+				return Err(e);
+			}
+
+			self.timer.sleep(self.backoff.delay(attempt)).await;
+			self.stats.lock().retries += 1;
+			attempt += 1;
+		}
+	}
+}
+
+impl<B: Backend + Debug> Debug for RetryBackend<B> {
+	fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+		f.debug_struct("RetryBackend")
+			.field("inner", &self.inner)
+			.field("max_attempts", &self.max_attempts)
+			.field("backoff", &self.backoff)
+			.field("timer", &self.timer)
+			.field("stats", &self.stats)
+			.finish_non_exhaustive()
+	}
+}
+
+impl<B: Backend> Backend for RetryBackend<B> {
+	type Error = B::Error;
+
+	fn init(&self) -> InitFuture<'_, Self::Error> {
+		self.retry(move || self.inner.init()).boxed()
+	}
+
+	unsafe fn shutdown(&self) -> ShutdownFuture<'_> {
+		unsafe { self.inner.shutdown() }
+	}
+
+	fn health_check(&self) -> HealthCheckFuture<'_, Self::Error> {
+		self.retry(move || self.inner.health_check()).boxed()
+	}
+
+	fn is_self_describing(&self) -> bool {
+		self.inner.is_self_describing()
+	}
+
+	fn entry_size<S: Entry>(&self, table: &str, id: &str, entry: &S) -> Option<usize> {
+		self.inner.entry_size(table, id, entry)
+	}
+
+	fn has_table<'a>(&'a self, table: &'a str) -> HasTableFuture<'a, Self::Error> {
+		self.retry(move || self.inner.has_table(table)).boxed()
+	}
+
+	fn tables<I>(&self) -> TablesFuture<'_, I, Self::Error>
+	where
+		I: FromIterator<String>,
+	{
+		async move {
+			self.retry(move || self.inner.tables::<Vec<_>>())
+				.await
+				.map(|tables| tables.into_iter().collect())
+		}
+		.boxed()
+	}
+
+	fn create_table<'a>(&'a self, table: &'a str) -> CreateTableFuture<'a, Self::Error> {
+		self.retry(move || self.inner.create_table(table)).boxed()
+	}
+
+	fn delete_table<'a>(&'a self, table: &'a str) -> DeleteTableFuture<'a, Self::Error> {
+		self.retry(move || self.inner.delete_table(table)).boxed()
+	}
+
+	fn rename_table<'a>(
+		&'a self,
+		table: &'a str,
+		new: &'a str,
+	) -> RenameTableFuture<'a, Self::Error> {
+		self.retry(move || self.inner.rename_table(table, new))
+			.boxed()
+	}
+
+	fn truncate_table<'a>(&'a self, table: &'a str) -> TruncateTableFuture<'a, Self::Error> {
+		self.retry(move || self.inner.truncate_table(table)).boxed()
+	}
+
+	fn get_all<'a, D, I>(
+		&'a self,
+		table: &'a str,
+		entries: &'a [&'a str],
+	) -> GetAllFuture<'a, I, Self::Error>
+	where
+		D: Entry,
+		I: FromIterator<D>,
+	{
+		async move {
+			self.retry(move || self.inner.get_all::<D, Vec<_>>(table, entries))
+				.await
+				.map(|entries| entries.into_iter().collect())
+		}
+		.boxed()
+	}
+
+	fn get_filtered<'a, D, I>(
+		&'a self,
+		table: &'a str,
+		filter: &'a Filter<'a, D>,
+	) -> GetFilteredFuture<'a, I, Self::Error>
+	where
+		D: Entry,
+		I: FromIterator<D>,
+	{
+		async move {
+			self.retry(move || self.inner.get_filtered::<D, Vec<_>>(table, filter))
+				.await
+				.map(|entries| entries.into_iter().collect())
+		}
+		.boxed()
+	}
+
+	fn get_page<'a, D, I>(
+		&'a self,
+		table: &'a str,
+		range: &'a PageRange,
+	) -> GetPageFuture<'a, I, Self::Error>
+	where
+		D: Entry,
+		I: FromIterator<D>,
+	{
+		async move {
+			self.retry(move || self.inner.get_page::<D, Vec<_>>(table, range))
+				.await
+				.map(|entries| entries.into_iter().collect())
+		}
+		.boxed()
+	}
+
+	fn get_keys<'a, I>(&'a self, table: &'a str) -> GetKeysFuture<'a, I, Self::Error>
+	where
+		I: FromIterator<String>,
+	{
+		async move {
+			self.retry(move || self.inner.get_keys::<Vec<_>>(table))
+				.await
+				.map(|keys| keys.into_iter().collect())
+		}
+		.boxed()
+	}
+
+	fn get_keys_in_range<'a, I>(
+		&'a self,
+		table: &'a str,
+		range: &'a PageRange,
+	) -> GetKeysInRangeFuture<'a, I, Self::Error>
+	where
+		I: FromIterator<String>,
+	{
+		async move {
+			self.retry(move || self.inner.get_keys_in_range::<Vec<_>>(table, range))
+				.await
+				.map(|keys| keys.into_iter().collect())
+		}
+		.boxed()
+	}
+
+	fn get<'a, D>(&'a self, table: &'a str, id: &'a str) -> GetFuture<'a, D, Self::Error>
+	where
+		D: Entry,
+	{
+		async move { self.retry(move || self.inner.get(table, id)).await }.boxed()
+	}
+
+	fn get_multi<'a, D>(
+		&'a self,
+		table: &'a str,
+		ids: &'a [&'a str],
+	) -> GetMultiFuture<'a, D, Self::Error>
+	where
+		D: Entry,
+	{
+		async move { self.retry(move || self.inner.get_multi(table, ids)).await }.boxed()
+	}
+
+	fn has<'a>(&'a self, table: &'a str, id: &'a str) -> HasFuture<'a, Self::Error> {
+		self.retry(move || self.inner.has(table, id)).boxed()
+	}
+
+	fn create<'a, S>(
+		&'a self,
+		table: &'a str,
+		id: &'a str,
+		value: &'a S,
+	) -> CreateFuture<'a, Self::Error>
+	where
+		S: Entry,
+	{
+		self.retry(move || self.inner.create(table, id, value))
+			.boxed()
+	}
+
+	fn create_multi<'a, S>(
+		&'a self,
+		table: &'a str,
+		entries: &'a [(&'a str, &'a S)],
+	) -> CreateMultiFuture<'a, Self::Error>
+	where
+		S: Entry,
+	{
+		self.retry(move || self.inner.create_multi(table, entries))
+			.boxed()
+	}
+
+	fn replace<'a, S>(
+		&'a self,
+		table: &'a str,
+		id: &'a str,
+		value: &'a S,
+	) -> ReplaceFuture<'a, Self::Error>
+	where
+		S: Entry,
+	{
+		self.retry(move || self.inner.replace(table, id, value))
+			.boxed()
+	}
+
+	fn update<'a, S>(
+		&'a self,
+		table: &'a str,
+		id: &'a str,
+		value: &'a S,
+	) -> UpdateFuture<'a, Self::Error>
+	where
+		S: Entry,
+	{
+		self.retry(move || self.inner.update(table, id, value))
+			.boxed()
+	}
+
+	fn update_multi<'a, S>(
+		&'a self,
+		table: &'a str,
+		entries: &'a [(&'a str, &'a S)],
+	) -> UpdateMultiFuture<'a, Self::Error>
+	where
+		S: Entry,
+	{
+		self.retry(move || self.inner.update_multi(table, entries))
+			.boxed()
+	}
+
+	fn delete<'a>(&'a self, table: &'a str, id: &'a str) -> DeleteFuture<'a, Self::Error> {
+		self.retry(move || self.inner.delete(table, id)).boxed()
+	}
+
+	fn set_pinned(&self, table: &str, id: &str, pinned: bool) {
+		self.inner.set_pinned(table, id, pinned);
+	}
+
+	fn expire<'a>(
+		&'a self,
+		table: &'a str,
+		id: &'a str,
+		expires_at: SystemTime,
+	) -> ExpireFuture<'a, Self::Error> {
+		self.retry(move || self.inner.expire(table, id, expires_at))
+			.boxed()
+	}
+
+	fn increment<'a>(
+		&'a self,
+		table: &'a str,
+		id: &'a str,
+		field: &'a [&'a str],
+		delta: i64,
+	) -> IncrementFuture<'a, Self::Error> {
+		self.retry(move || self.inner.increment(table, id, field, delta))
+			.boxed()
+	}
+}
+
+impl<B: TransactionalBackend> TransactionalBackend for RetryBackend<B> {}
+
+#[cfg(test)]
+mod tests {
+	use std::time::Duration;
+
+	use super::Backoff;
+
+	#[test]
+	fn backoff() {
+		let exponential = Backoff::Exponential {
+			initial: Duration::from_millis(100),
+			max: Duration::from_secs(1),
+		};
+
+		assert_eq!(Backoff::None.delay(3), Duration::ZERO);
+		assert_eq!(
+			Backoff::Constant(Duration::from_secs(1)).delay(3),
+			Duration::from_secs(1)
+		);
+		assert_eq!(exponential.delay(1), Duration::from_millis(100));
+		assert_eq!(exponential.delay(3), Duration::from_millis(400));
+		assert_eq!(exponential.delay(5), Duration::from_secs(1));
+		assert_eq!(exponential.delay(u32::MAX), Duration::from_secs(1));
+	}
+}
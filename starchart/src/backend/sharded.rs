@@ -0,0 +1,1374 @@
+//! A [`Backend`] that spreads the entries of every table over multiple [`Backend`]s.
+
+use std::{
+	collections::{BTreeMap, BTreeSet},
+	hash::{BuildHasher, BuildHasherDefault, Hasher},
+	iter::{self, FromIterator},
+	mem,
+	sync::{
+		atomic::{AtomicUsize, Ordering},
+		Arc,
+	},
+	task::Poll,
+	time::SystemTime,
+};
+
+use futures_util::{
+	future::{join_all, poll_fn, try_join_all},
+	lock::Mutex as AsyncMutex,
+	task::AtomicWaker,
+	FutureExt,
+};
+use parking_lot::RwLock;
+
+use super::{
+	futures::{
+		CreateFuture, CreateMultiFuture, CreateTableFuture, DeleteFuture, DeleteTableFuture,
+		EnsureTableFuture, ExpireFuture, GetAllFuture, GetFilteredFuture, GetFuture, GetKeysFuture,
+		GetMultiFuture, HasFuture, HasTableFuture, HealthCheckFuture, IncrementFuture, InitFuture,
+		RenameTableFuture, ReplaceFuture, ShutdownFuture, TablesFuture, TruncateTableFuture,
+		UpdateFuture, UpdateMultiFuture,
+	},
+	Backend,
+};
+use crate::{query::Filter, transaction::TransactionalBackend, Entry};
+
+/// The amount of points each shard is given on the hash ring.
+///
+/// More points spread keys more evenly, at the cost of a larger ring to search.
+pub const VIRTUAL_NODES: usize = 64;
+
+/// A [`Backend`] that distributes the keys of every table across multiple inner [`Backend`]s using consistent hashing.
+///
+/// Every table exists on every shard, and each key is stored on exactly one of them. Operations on a single
+/// key are routed to it's shard, while operations over a whole table (such as [`Backend::get_keys`]) fan out
+/// to every shard and merge the results.
+///
+/// Every shard has an id, which places it on the hash ring. Because the hashing is consistent, adding a
+/// shard only moves the keys that the new shard takes over, about `1 / shards` of them, and removing one only
+/// moves the keys it held, as long as every other shard keeps it's id. Those keys are moved with
+/// [`Self::rebalance`]. Shards given without ids are identified by their position, so use
+/// [`Self::with_ids`] if shards may be removed or reordered later.
+///
+/// Keys are hashed with `H`, which defaults to [`FnvHasher`]. The hasher must produce the same hashes across
+/// runs, so randomly seeded hashers (such as [`RandomState`]) can't be used.
+///
+/// [`RandomState`]: std::collections::hash_map::RandomState
+#[derive(Debug)]
+pub struct ShardedBackend<B, H = BuildHasherDefault<FnvHasher>> {
+	routing: RwLock<Routing<B>>,
+	// held while moving a single key, so writes and the rebalance never move the same key at once.
+	migration: AsyncMutex<()>,
+	rebalancing: AsyncMutex<()>,
+	hasher: H,
+}
+
+impl<B: Backend> ShardedBackend<B> {
+	/// Creates a new [`ShardedBackend`] over the given shards, hashing keys with [`FnvHasher`].
+	///
+	/// # Panics
+	///
+	/// Panics if no shards are given.
+	#[must_use]
+	pub fn new(shards: Vec<B>) -> Self {
+		Self::with_hasher(shards, BuildHasherDefault::default())
+	}
+
+	/// Creates a new [`ShardedBackend`] over the given shards and their ids, hashing keys with [`FnvHasher`].
+	///
+	/// # Panics
+	///
+	/// Panics if no shards are given, or if two shards share an id.
+	#[must_use]
+	pub fn with_ids(shards: Vec<(String, B)>) -> Self {
+		Self::with_ids_and_hasher(shards, BuildHasherDefault::default())
+	}
+}
+
+impl<B: Backend, H: BuildHasher + Send + Sync> ShardedBackend<B, H> {
+	/// Creates a new [`ShardedBackend`] over the given shards, hashing keys with the given hasher.
+	///
+	/// Each shard's id is it's position in `shards`.
+	///
+	/// # Panics
+	///
+	/// Panics if no shards are given.
+	pub fn with_hasher(shards: Vec<B>, hasher: H) -> Self {
+		let shards = shards
+			.into_iter()
+			.enumerate()
+			.map(|(index, shard)| (index.to_string(), shard))
+			.collect();
+
+		Self::with_ids_and_hasher(shards, hasher)
+	}
+
+	/// Creates a new [`ShardedBackend`] over the given shards and their ids, hashing keys with the given hasher.
+	///
+	/// # Panics
+	///
+	/// Panics if no shards are given, or if two shards share an id.
+	pub fn with_ids_and_hasher(shards: Vec<(String, B)>, hasher: H) -> Self {
+		let topology = Topology::new(
+			shards
+				.into_iter()
+				.map(|(id, shard)| (id, Arc::new(shard)))
+				.collect(),
+			&hasher,
+		);
+
+		Self {
+			routing: RwLock::new(Routing {
+				current: Arc::new(topology),
+				previous: None,
+			}),
+			migration: AsyncMutex::new(()),
+			rebalancing: AsyncMutex::new(()),
+			hasher,
+		}
+	}
+
+	/// Returns the inner [`Backend`]s, in the order they were given.
+	#[must_use]
+	pub fn shards(&self) -> Vec<Arc<B>> {
+		self.routing.read().current.shards.clone()
+	}
+
+	/// Returns the ids of the inner [`Backend`]s, in the same order as [`Self::shards`].
+	#[must_use]
+	pub fn shard_ids(&self) -> Vec<String> {
+		self.routing.read().current.ids.clone()
+	}
+
+	/// Returns the index of the shard the given key is stored on.
+	#[must_use]
+	pub fn shard_index(&self, key: &str) -> usize {
+		self.routing.read().current.index(hash(&self.hasher, key))
+	}
+
+	/// Returns the shard the given key is stored on.
+	#[must_use]
+	pub fn shard_for(&self, key: &str) -> Arc<B> {
+		let routing = self.routing.read();
+
+		Arc::clone(&routing.current.shards[routing.current.index(hash(&self.hasher, key))])
+	}
+
+	/// Whether a [`Self::rebalance`] is in progress, or was interrupted by an error.
+	///
+	/// While rebalancing, reads fall back to the shard a key was on before, and writes of whole entries move
+	/// the key first.
+	#[must_use]
+	pub fn is_rebalancing(&self) -> bool {
+		self.routing.read().previous.is_some()
+	}
+
+	/// Switches to the given shards and their ids, then moves every entry in the given tables to it's new shard.
+	///
+	/// The backend stays usable throughout: reads of keys that haven't been moved yet fall back to their
+	/// old shard, and writes move the key before writing it. Writes that were routed before the switch are
+	/// waited on before any keys are moved, so they can't write to a shard the key has already left.
+	///
+	/// Keys are placed by shard id, so only keys whose shard id changes are moved. Adding a shard moves the
+	/// keys the new shard takes over, and removing one moves the keys it held, as long as the remaining
+	/// [`Self::shards`] are given with the same [`Self::shard_ids`]. Giving an existing shard a new id
+	/// (including the position-based ids of [`Self::new`] shifting when a shard is removed) moves it's
+	/// keys as well.
+	///
+	/// `progress` is called with the table and the running totals after every key that's checked.
+	/// Tables not listed won't be moved, and won't be created on new shards.
+	///
+	/// Entries are moved as `S`, so every table listed must store it, along with it's metadata entry. Shards that are
+	/// [self-describing] can move any entry as a [`serde_json::Value`].
+	///
+	/// # Errors
+	///
+	/// Returns any error from the shards. The backend keeps reading from every shard until a later
+	/// rebalance finishes, so it's safe to call this again with the same shards.
+	///
+	/// # Panics
+	///
+	/// Panics if no shards are given, or if two shards share an id.
+	///
+	/// [self-describing]: Backend::is_self_describing
+	pub async fn rebalance<S, F>(
+		&self,
+		tables: &[&str],
+		shards: Vec<(String, Arc<B>)>,
+		mut progress: F,
+	) -> Result<RebalanceProgress, B::Error>
+	where
+		S: Entry,
+		F: FnMut(&str, RebalanceProgress) + Send,
+	{
+		let _rebalancing = self.rebalancing.lock().await;
+
+		let topology = Arc::new(Topology::new(shards, &self.hasher));
+
+		for shard in &topology.shards {
+			for table in tables {
+				shard.ensure_table(table).await?;
+			}
+		}
+
+		let (sources, replaced) = {
+			let mut routing = self.routing.write();
+			let sources = routing.shards();
+
+			if routing.previous.is_none() {
+				routing.previous = Some(Arc::clone(&routing.current));
+			}
+			let replaced = mem::replace(&mut routing.current, Arc::clone(&topology));
+			drop(routing);
+
+			(sources, replaced)
+		};
+
+		// writes that located their shard before the switch may still write to the old one,
+		// so they need to finish before their keys are moved.
+		replaced.writes.drained().await;
+
+		let mut totals = RebalanceProgress::default();
+		for table in tables {
+			for shard in &sources {
+				if !shard.has_table(table).await? {
+					continue;
+				}
+
+				for key in shard.get_keys::<Vec<String>>(table).await? {
+					let target = &topology.shards[topology.index(hash(&self.hasher, &key))];
+
+					if !Arc::ptr_eq(shard, target) {
+						let _migration = self.migration.lock().await;
+
+						if move_key::<_, S>(table, &key, &**shard, &**target).await? {
+							totals.moved += 1;
+						}
+					}
+
+					totals.checked += 1;
+					progress(table, totals);
+				}
+			}
+		}
+
+		self.routing.write().previous = None;
+
+		Ok(totals)
+	}
+
+	// registers a write with the current topology, which a rebalance waits on before moving any keys.
+	fn start_write(&self) -> WriteGuard<B> {
+		let routing = self.routing.read();
+		routing.current.writes.count.fetch_add(1, Ordering::SeqCst);
+
+		WriteGuard(Arc::clone(&routing.current))
+	}
+
+	// the shard a key is on now, and the shard it may still be on if a rebalance hasn't moved it yet.
+	fn locate(&self, key: &str) -> (Arc<B>, Option<Arc<B>>) {
+		let routing = self.routing.read();
+		let point = hash(&self.hasher, key);
+
+		let current = Arc::clone(&routing.current.shards[routing.current.index(point)]);
+		let previous = routing
+			.previous
+			.as_ref()
+			.map(|previous| Arc::clone(&previous.shards[previous.index(point)]))
+			.filter(|previous| !Arc::ptr_eq(previous, &current));
+		drop(routing);
+
+		(current, previous)
+	}
+
+	// locates the key, moving it to it's current shard first as an `S` if it hasn't been moved yet.
+	async fn locate_for_write<S: Entry>(&self, table: &str, key: &str) -> Result<Arc<B>, B::Error> {
+		let (current, previous) = self.locate(key);
+
+		if let Some(previous) = previous {
+			let _migration = self.migration.lock().await;
+
+			move_key::<_, S>(table, key, &*previous, &*current).await?;
+		}
+
+		Ok(current)
+	}
+
+	// locates the shard the key is stored on without moving it, for writes that don't know the entry type.
+	async fn locate_stored(&self, table: &str, key: &str) -> Result<Arc<B>, B::Error> {
+		let (current, previous) = self.locate(key);
+
+		if let Some(previous) = previous {
+			if !current.has(table, key).await? && previous.has(table, key).await? {
+				return Ok(previous);
+			}
+		}
+
+		Ok(current)
+	}
+
+	fn group<T: Copy>(&self, items: &[T], key: impl Fn(&T) -> &str) -> Vec<(Arc<B>, Vec<T>)> {
+		let topology = Arc::clone(&self.routing.read().current);
+		let mut groups = BTreeMap::<usize, Vec<T>>::new();
+
+		for item in items {
+			groups
+				.entry(topology.index(hash(&self.hasher, key(item))))
+				.or_default()
+				.push(*item);
+		}
+
+		groups
+			.into_iter()
+			.map(|(index, items)| (Arc::clone(&topology.shards[index]), items))
+			.collect()
+	}
+}
+
+impl<B: Backend, H: BuildHasher + Send + Sync> Backend for ShardedBackend<B, H> {
+	type Error = B::Error;
+
+	fn init(&self) -> InitFuture<'_, Self::Error> {
+		async move {
+			let shards = self.routing.read().shards();
+
+			try_join_all(shards.iter().map(|shard| shard.init())).await?;
+
+			Ok(())
+		}
+		.boxed()
+	}
+
+	unsafe fn shutdown(&self) -> ShutdownFuture<'_> {
+		async move {
+			let shards = self.routing.read().shards();
+
+			join_all(shards.iter().map(|shard| unsafe { shard.shutdown() })).await;
+		}
+		.boxed()
+	}
+
+	fn health_check(&self) -> HealthCheckFuture<'_, Self::Error> {
+		async move {
+			let shards = self.shards();
+
+			try_join_all(shards.iter().map(|shard| shard.health_check())).await?;
+
+			Ok(())
+		}
+		.boxed()
+	}
+
+	fn is_self_describing(&self) -> bool {
+		self.shards().iter().all(|shard| shard.is_self_describing())
+	}
+
+	fn entry_size<S: Entry>(&self, table: &str, id: &str, entry: &S) -> Option<usize> {
+		self.shard_for(id).entry_size(table, id, entry)
+	}
+
+	fn has_table<'a>(&'a self, table: &'a str) -> HasTableFuture<'a, Self::Error> {
+		async move {
+			let shards = self.shards();
+
+			let exists = try_join_all(shards.iter().map(|shard| shard.has_table(table))).await?;
+
+			Ok(exists.into_iter().all(|exists| exists))
+		}
+		.boxed()
+	}
+
+	fn tables<I>(&self) -> TablesFuture<'_, I, Self::Error>
+	where
+		I: FromIterator<String>,
+	{
+		async move {
+			let shards = self.shards();
+
+			let tables = try_join_all(
+				shards
+					.iter()
+					.map(|shard| shard.tables::<BTreeSet<String>>()),
+			)
+			.await?;
+
+			// only tables on every shard are listed, matching `has_table`.
+			let mut tables = tables.into_iter();
+			let first = tables.next().unwrap_or_default();
+
+			Ok(tables
+				.fold(first, |all, shard| &all & &shard)
+				.into_iter()
+				.collect())
+		}
+		.boxed()
+	}
+
+	fn create_table<'a>(&'a self, table: &'a str) -> CreateTableFuture<'a, Self::Error> {
+		async move {
+			let shards = self.shards();
+
+			try_join_all(shards.iter().map(|shard| shard.create_table(table))).await?;
+
+			Ok(())
+		}
+		.boxed()
+	}
+
+	fn delete_table<'a>(&'a self, table: &'a str) -> DeleteTableFuture<'a, Self::Error> {
+		async move {
+			let shards = self.routing.read().shards();
+
+			let deletes = shards.iter().map(|shard| async move {
+				if shard.has_table(table).await? {
+					shard.delete_table(table).await?;
+				}
+
+				Ok(())
+			});
+
+			try_join_all(deletes).await?;
+
+			Ok(())
+		}
+		.boxed()
+	}
+
+	fn rename_table<'a>(
+		&'a self,
+		table: &'a str,
+		new: &'a str,
+	) -> RenameTableFuture<'a, Self::Error> {
+		async move {
+			let shards = self.shards();
+
+			let renames = shards.iter().map(|shard| async move {
+				if shard.has_table(table).await? {
+					shard.rename_table(table, new).await
+				} else {
+					shard.create_table(new).await
+				}
+			});
+
+			try_join_all(renames).await?;
+
+			Ok(())
+		}
+		.boxed()
+	}
+
+	fn truncate_table<'a>(&'a self, table: &'a str) -> TruncateTableFuture<'a, Self::Error> {
+		async move {
+			let shards = self.shards();
+
+			let truncates = shards.iter().map(|shard| async move {
+				if shard.has_table(table).await? {
+					shard.truncate_table(table).await
+				} else {
+					shard.create_table(table).await
+				}
+			});
+
+			try_join_all(truncates).await?;
+
+			Ok(())
+		}
+		.boxed()
+	}
+
+	// the default impl would recreate the table on every shard if any one of them is missing it.
+	fn ensure_table<'a>(&'a self, table: &'a str) -> EnsureTableFuture<'a, Self::Error> {
+		async move {
+			let shards = self.shards();
+
+			try_join_all(shards.iter().map(|shard| shard.ensure_table(table))).await?;
+
+			Ok(())
+		}
+		.boxed()
+	}
+
+	fn get_all<'a, D, I>(
+		&'a self,
+		table: &'a str,
+		entries: &'a [&'a str],
+	) -> GetAllFuture<'a, I, Self::Error>
+	where
+		D: Entry,
+		I: FromIterator<D>,
+	{
+		async move {
+			if self.is_rebalancing() {
+				let gets = entries.iter().map(|key| self.get::<D>(table, key));
+
+				return join_all(gets)
+					.await
+					.into_iter()
+					.filter_map(Result::transpose)
+					.collect();
+			}
+
+			let groups = self.group(entries, |key| *key);
+
+			let gets = groups
+				.iter()
+				.map(|(shard, keys)| shard.get_all::<D, Vec<D>>(table, keys));
+
+			Ok(try_join_all(gets).await?.into_iter().flatten().collect())
+		}
+		.boxed()
+	}
+
+	fn get_multi<'a, D>(
+		&'a self,
+		table: &'a str,
+		ids: &'a [&'a str],
+	) -> GetMultiFuture<'a, D, Self::Error>
+	where
+		D: Entry,
+	{
+		async move {
+			if self.is_rebalancing() {
+				let gets = ids.iter().map(|id| self.get::<D>(table, id));
+
+				return join_all(gets).await.into_iter().collect();
+			}
+
+			let indexed = ids.iter().copied().enumerate().collect::<Vec<_>>();
+			let groups = self.group(&indexed, |(_, id)| id);
+			let keys = groups
+				.iter()
+				.map(|(_, items)| items.iter().map(|(_, id)| *id).collect::<Vec<_>>())
+				.collect::<Vec<_>>();
+
+			let gets = groups
+				.iter()
+				.zip(&keys)
+				.map(|((shard, _), keys)| shard.get_multi::<D>(table, keys));
+
+			// every shard returns it's entries in the order of it's keys, which are put back in the order of `ids`.
+			let mut entries = iter::repeat_with(|| None)
+				.take(ids.len())
+				.collect::<Vec<_>>();
+			for ((_, items), found) in groups.iter().zip(try_join_all(gets).await?) {
+				for ((index, _), entry) in items.iter().zip(found) {
+					entries[*index] = entry;
+				}
+			}
+
+			Ok(entries)
+		}
+		.boxed()
+	}
+
+	fn get_filtered<'a, D, I>(
+		&'a self,
+		table: &'a str,
+		filter: &'a Filter<'a, D>,
+	) -> GetFilteredFuture<'a, I, Self::Error>
+	where
+		D: Entry,
+		I: FromIterator<D>,
+	{
+		async move {
+			// an entry can briefly be on both shards while it's moved, so read it through it's key instead.
+			if self.is_rebalancing() {
+				let keys = self.get_keys::<Vec<String>>(table).await?;
+
+				let gets = keys.iter().map(|key| async move {
+					self.get::<D>(table, key)
+						.await
+						.map(|entry| entry.filter(|entry| filter.matches(key, entry)))
+				});
+
+				return join_all(gets)
+					.await
+					.into_iter()
+					.filter_map(Result::transpose)
+					.collect();
+			}
+
+			let shards = self.shards();
+
+			let gets = shards
+				.iter()
+				.map(|shard| shard.get_filtered::<D, Vec<D>>(table, filter));
+
+			Ok(try_join_all(gets).await?.into_iter().flatten().collect())
+		}
+		.boxed()
+	}
+
+	fn get_keys<'a, I>(&'a self, table: &'a str) -> GetKeysFuture<'a, I, Self::Error>
+	where
+		I: FromIterator<String>,
+	{
+		async move {
+			let shards = self.routing.read().shards();
+
+			let gets = shards.iter().map(|shard| async move {
+				if shard.has_table(table).await? {
+					shard.get_keys::<Vec<String>>(table).await
+				} else {
+					Ok(Vec::new())
+				}
+			});
+
+			// a key can briefly be on two shards while a rebalance moves it.
+			Ok(try_join_all(gets)
+				.await?
+				.into_iter()
+				.flatten()
+				.collect::<BTreeSet<_>>()
+				.into_iter()
+				.collect())
+		}
+		.boxed()
+	}
+
+	fn get<'a, D>(&'a self, table: &'a str, id: &'a str) -> GetFuture<'a, D, Self::Error>
+	where
+		D: Entry,
+	{
+		async move {
+			let (current, previous) = self.locate(id);
+
+			match current.get(table, id).await? {
+				Some(entry) => Ok(Some(entry)),
+				None => match previous {
+					Some(previous) => previous.get(table, id).await,
+					None => Ok(None),
+				},
+			}
+		}
+		.boxed()
+	}
+
+	fn has<'a>(&'a self, table: &'a str, id: &'a str) -> HasFuture<'a, Self::Error> {
+		async move {
+			let (current, previous) = self.locate(id);
+
+			if current.has(table, id).await? {
+				return Ok(true);
+			}
+
+			match previous {
+				Some(previous) => previous.has(table, id).await,
+				None => Ok(false),
+			}
+		}
+		.boxed()
+	}
+
+	fn create<'a, S>(
+		&'a self,
+		table: &'a str,
+		id: &'a str,
+		value: &'a S,
+	) -> CreateFuture<'a, Self::Error>
+	where
+		S: Entry,
+	{
+		async move {
+			let _write = self.start_write();
+			let shard = self.locate_for_write::<S>(table, id).await?;
+
+			shard.create(table, id, value).await
+		}
+		.boxed()
+	}
+
+	fn create_multi<'a, S>(
+		&'a self,
+		table: &'a str,
+		entries: &'a [(&'a str, &'a S)],
+	) -> CreateMultiFuture<'a, Self::Error>
+	where
+		S: Entry,
+	{
+		async move {
+			let _write = self.start_write();
+			if self.is_rebalancing() {
+				for (id, _) in entries {
+					self.locate_for_write::<S>(table, id).await?;
+				}
+			}
+
+			let groups = self.group(entries, |(id, _)| *id);
+
+			let creates = groups
+				.iter()
+				.map(|(shard, entries)| shard.create_multi(table, entries));
+
+			try_join_all(creates).await?;
+
+			Ok(())
+		}
+		.boxed()
+	}
+
+	fn replace<'a, S>(
+		&'a self,
+		table: &'a str,
+		id: &'a str,
+		value: &'a S,
+	) -> ReplaceFuture<'a, Self::Error>
+	where
+		S: Entry,
+	{
+		async move {
+			let _write = self.start_write();
+			let shard = self.locate_for_write::<S>(table, id).await?;
+
+			shard.replace(table, id, value).await
+		}
+		.boxed()
+	}
+
+	fn update<'a, S>(
+		&'a self,
+		table: &'a str,
+		id: &'a str,
+		value: &'a S,
+	) -> UpdateFuture<'a, Self::Error>
+	where
+		S: Entry,
+	{
+		async move {
+			let _write = self.start_write();
+			let shard = self.locate_for_write::<S>(table, id).await?;
+
+			shard.update(table, id, value).await
+		}
+		.boxed()
+	}
+
+	fn update_multi<'a, S>(
+		&'a self,
+		table: &'a str,
+		entries: &'a [(&'a str, &'a S)],
+	) -> UpdateMultiFuture<'a, Self::Error>
+	where
+		S: Entry,
+	{
+		async move {
+			let _write = self.start_write();
+			if self.is_rebalancing() {
+				for (id, _) in entries {
+					self.locate_for_write::<S>(table, id).await?;
+				}
+			}
+
+			let groups = self.group(entries, |(id, _)| *id);
+
+			let updates = groups
+				.iter()
+				.map(|(shard, entries)| shard.update_multi(table, entries));
+
+			try_join_all(updates).await?;
+
+			Ok(())
+		}
+		.boxed()
+	}
+
+	fn delete<'a>(&'a self, table: &'a str, id: &'a str) -> DeleteFuture<'a, Self::Error> {
+		async move {
+			let _write = self.start_write();
+			let (current, previous) = self.locate(id);
+
+			if let Some(previous) = previous {
+				let _migration = self.migration.lock().await;
+
+				previous.delete(table, id).await?;
+			}
+
+			current.delete(table, id).await
+		}
+		.boxed()
+	}
+
+	fn set_pinned(&self, table: &str, id: &str, pinned: bool) {
+		// the owning shard can change while rebalancing, so every shard is told.
+		let shards = self.routing.read().shards();
+
+		for shard in shards {
+			shard.set_pinned(table, id, pinned);
+		}
+	}
+
+	fn expire<'a>(
+		&'a self,
+		table: &'a str,
+		id: &'a str,
+		expires_at: SystemTime,
+	) -> ExpireFuture<'a, Self::Error> {
+		async move {
+			let _write = self.start_write();
+			let _migration = self.migration.lock().await;
+			let shard = self.locate_stored(table, id).await?;
+
+			shard.expire(table, id, expires_at).await
+		}
+		.boxed()
+	}
+
+	fn increment<'a>(
+		&'a self,
+		table: &'a str,
+		id: &'a str,
+		field: &'a [&'a str],
+		delta: i64,
+	) -> IncrementFuture<'a, Self::Error> {
+		async move {
+			let _write = self.start_write();
+			let _migration = self.migration.lock().await;
+			let shard = self.locate_stored(table, id).await?;
+
+			shard.increment(table, id, field, delta).await
+		}
+		.boxed()
+	}
+}
+
+impl<B: TransactionalBackend, H: BuildHasher + Send + Sync> TransactionalBackend
+	for ShardedBackend<B, H>
+{
+}
+
+/// The running totals of a [`ShardedBackend::rebalance`].
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+#[must_use = "retrieving progress has no side effects"]
+pub struct RebalanceProgress {
+	checked: usize,
+	moved: usize,
+}
+
+impl RebalanceProgress {
+	/// The amount of keys that have been checked.
+	#[must_use]
+	pub const fn checked(self) -> usize {
+		self.checked
+	}
+
+	/// The amount of keys that were moved to a different shard.
+	#[must_use]
+	pub const fn moved(self) -> usize {
+		self.moved
+	}
+}
+
+/// A 64-bit [FNV-1a] [`Hasher`], the default hasher of a [`ShardedBackend`].
+///
+/// Unlike the standard library's hashers, it's output is stable across runs and Rust versions,
+/// so keys stay on the same shard.
+///
+/// [FNV-1a]: https://en.wikipedia.org/wiki/Fowler%E2%80%93Noll%E2%80%93Vo_hash_function
+#[derive(Debug, Clone, Copy)]
+pub struct FnvHasher(u64);
+
+impl Default for FnvHasher {
+	fn default() -> Self {
+		Self(0xcbf2_9ce4_8422_2325)
+	}
+}
+
+impl Hasher for FnvHasher {
+	fn finish(&self) -> u64 {
+		self.0
+	}
+
+	fn write(&mut self, bytes: &[u8]) {
+		for byte in bytes {
+			self.0 = (self.0 ^ u64::from(*byte)).wrapping_mul(0x0100_0000_01b3);
+		}
+	}
+}
+
+#[derive(Debug)]
+struct Routing<B> {
+	current: Arc<Topology<B>>,
+	previous: Option<Arc<Topology<B>>>,
+}
+
+impl<B> Routing<B> {
+	// every distinct shard in either topology.
+	fn shards(&self) -> Vec<Arc<B>> {
+		let mut shards = self.current.shards.clone();
+
+		for shard in self.previous.iter().flat_map(|previous| &previous.shards) {
+			if !shards.iter().any(|existing| Arc::ptr_eq(existing, shard)) {
+				shards.push(Arc::clone(shard));
+			}
+		}
+
+		shards
+	}
+}
+
+#[derive(Debug)]
+struct Topology<B> {
+	ids: Vec<String>,
+	shards: Vec<Arc<B>>,
+	ring: Vec<(u64, usize)>,
+	writes: Writes,
+}
+
+impl<B> Topology<B> {
+	fn new<H: BuildHasher>(shards: Vec<(String, Arc<B>)>, hasher: &H) -> Self {
+		assert!(
+			!shards.is_empty(),
+			"a sharded backend needs at least one shard"
+		);
+
+		let (ids, shards): (Vec<_>, Vec<_>) = shards.into_iter().unzip();
+
+		assert_eq!(
+			ids.iter().collect::<BTreeSet<_>>().len(),
+			ids.len(),
+			"every shard needs a unique id"
+		);
+
+		// points are derived from the id rather than the position, so shards keep their points when others
+		// are added or removed.
+		let mut ring = ids
+			.iter()
+			.enumerate()
+			.flat_map(|(shard, id)| {
+				(0..VIRTUAL_NODES).map(move |node| (format!("shard-{id}-{node}"), shard))
+			})
+			.map(|(point, shard)| (hash(hasher, &point), shard))
+			.collect::<Vec<_>>();
+
+		ring.sort_unstable();
+
+		Self {
+			ids,
+			shards,
+			ring,
+			writes: Writes::default(),
+		}
+	}
+
+	fn index(&self, point: u64) -> usize {
+		let position = self.ring.partition_point(|(hash, _)| *hash < point);
+
+		self.ring.get(position).unwrap_or(&self.ring[0]).1
+	}
+}
+
+// the writes in progress that were routed with a topology.
+#[derive(Debug, Default)]
+struct Writes {
+	count: AtomicUsize,
+	drained: AtomicWaker,
+}
+
+impl Writes {
+	// waits until every write has finished, new writes are routed with the next topology by then.
+	async fn drained(&self) {
+		poll_fn(|cx| {
+			self.drained.register(cx.waker());
+
+			if self.count.load(Ordering::SeqCst) == 0 {
+				Poll::Ready(())
+			} else {
+				Poll::Pending
+			}
+		})
+		.await;
+	}
+}
+
+struct WriteGuard<B>(Arc<Topology<B>>);
+
+impl<B> Drop for WriteGuard<B> {
+	fn drop(&mut self) {
+		if self.0.writes.count.fetch_sub(1, Ordering::SeqCst) == 1 {
+			self.0.writes.drained.wake();
+		}
+	}
+}
+
+// copies the entry to it's new shard (unless it was already written there), then removes the old copy.
+async fn move_key<B: Backend, S: Entry>(
+	table: &str,
+	key: &str,
+	from: &B,
+	to: &B,
+) -> Result<bool, B::Error> {
+	let Some(entry) = from.get::<S>(table, key).await? else {
+		return Ok(false);
+	};
+
+	if !to.has(table, key).await? {
+		to.create(table, key, &entry).await?;
+	}
+
+	from.delete(table, key).await?;
+
+	Ok(true)
+}
+
+// hashes the bytes directly, as `Hash for str` writes an extra byte some hashers handle differently,
+// then mixes the result, as simple hashers barely change their high bits between similar keys.
+fn hash<H: BuildHasher>(hasher: &H, value: &str) -> u64 {
+	let mut hasher = hasher.build_hasher();
+	hasher.write(value.as_bytes());
+
+	let mut z = hasher.finish();
+	z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+	z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+	z ^ (z >> 31)
+}
+
+#[cfg(test)]
+mod tests {
+	use std::{hash::Hasher, sync::Arc, thread, time::Duration};
+
+	use futures_executor::block_on;
+
+	use super::{FnvHasher, ShardedBackend};
+	use crate::{
+		backend::{Backend, MockBackend, MockError},
+		testing::TestSettings,
+		Error, Starchart,
+	};
+
+	fn with_ids(ids: &[&str]) -> ShardedBackend<MockBackend> {
+		ShardedBackend::with_ids(
+			ids.iter()
+				.map(|id| ((*id).to_owned(), MockBackend::new()))
+				.collect(),
+		)
+	}
+
+	fn shard_id(backend: &ShardedBackend<MockBackend>, key: &str) -> String {
+		backend.shard_ids()[backend.shard_index(key)].clone()
+	}
+
+	#[test]
+	fn routing_stable() {
+		let fnv = |value: &str| {
+			let mut hasher = FnvHasher::default();
+			hasher.write(value.as_bytes());
+			hasher.finish()
+		};
+		assert_eq!(fnv(""), 0xcbf2_9ce4_8422_2325);
+		assert_eq!(fnv("a"), 0xaf63_dc4c_8601_ec8c);
+		assert_eq!(fnv("foobar"), 0x8594_4171_f739_67e8);
+
+		// where keys are routed is part of how entries are stored, so it can't change between versions.
+		let backend = with_ids(&["a", "b", "c"]);
+		let keys = (1..=200)
+			.map(|key: u32| key.to_string())
+			.collect::<Vec<_>>();
+		let routed = keys[..12]
+			.iter()
+			.map(|key| shard_id(&backend, key))
+			.collect::<Vec<_>>();
+		assert_eq!(
+			routed,
+			["a", "b", "a", "a", "c", "c", "a", "b", "a", "b", "a", "b"]
+		);
+
+		// keys follow the shard id, not it's position.
+		let reordered = with_ids(&["c", "a", "b"]);
+		for key in &keys {
+			assert_eq!(shard_id(&reordered, key), shard_id(&backend, key));
+		}
+
+		// adding a shard only moves keys onto it, and removing one only moves the keys it held.
+		let grown = with_ids(&["a", "b", "c", "d"]);
+		let shrunk = with_ids(&["a", "c"]);
+		for key in &keys {
+			let id = shard_id(&backend, key);
+			let grown = shard_id(&grown, key);
+			assert!(grown == id || grown == "d");
+
+			if id != "b" {
+				assert_eq!(shard_id(&shrunk, key), id);
+			}
+		}
+	}
+
+	#[test]
+	fn rebalance_waits_for_writes() -> Result<(), MockError> {
+		let backend = Arc::new(with_ids(&["a"]));
+		let key = (1..=100)
+			.map(|key: u32| key.to_string())
+			.find(|key| shard_id(&with_ids(&["a", "b"]), key) == "b")
+			.unwrap();
+		block_on(backend.create_table("table"))?;
+		block_on(backend.create("table", &key, &TestSettings::default()))?;
+
+		// a write that was routed to the key's old shard, but hasn't written yet.
+		let write = backend.start_write();
+		let (stale, _) = backend.locate(&key);
+
+		let mut shards = backend
+			.shard_ids()
+			.into_iter()
+			.zip(backend.shards())
+			.collect::<Vec<_>>();
+		shards.push(("b".to_owned(), Arc::new(MockBackend::new())));
+
+		let rebalance = thread::spawn({
+			let backend = Arc::clone(&backend);
+
+			move || block_on(backend.rebalance::<TestSettings, _>(&["table"], shards, |_, _| {}))
+		});
+
+		while !backend.is_rebalancing() && !rebalance.is_finished() {
+			thread::yield_now();
+		}
+		thread::sleep(Duration::from_millis(50));
+		assert!(!rebalance.is_finished());
+
+		let settings = TestSettings {
+			value: "updated".to_owned(),
+			..TestSettings::default()
+		};
+		block_on(stale.update("table", &key, &settings))?;
+		drop(write);
+
+		assert_eq!(rebalance.join().unwrap()?.moved(), 1);
+		assert_eq!(shard_id(&backend, &key), "b");
+		assert_eq!(
+			backend.shard_for(&key).entry::<TestSettings>("table", &key),
+			Some(settings)
+		);
+		assert_eq!(stale.entry::<TestSettings>("table", &key), None);
+
+		Ok(())
+	}
+
+	#[test]
+	fn concurrent_migration() -> Result<(), MockError> {
+		let backend = Arc::new(with_ids(&["a", "b"]));
+		block_on(backend.create_table("table"))?;
+		for id in 0..400 {
+			let settings = TestSettings {
+				id,
+				..TestSettings::default()
+			};
+			block_on(backend.create("table", &id.to_string(), &settings))?;
+		}
+
+		let mut shards = backend
+			.shard_ids()
+			.into_iter()
+			.zip(backend.shards())
+			.collect::<Vec<_>>();
+		shards.push(("c".to_owned(), Arc::new(MockBackend::new())));
+
+		let rebalance = thread::spawn({
+			let backend = Arc::clone(&backend);
+
+			move || block_on(backend.rebalance::<TestSettings, _>(&["table"], shards, |_, _| {}))
+		});
+
+		// writes racing the rebalance have to land on the key's new shard, and can't be overwritten
+		// by the entry the rebalance moves.
+		for id in 0..400 {
+			let key = id.to_string();
+
+			if id % 4 == 0 {
+				block_on(backend.delete("table", &key))?;
+			} else {
+				let settings = TestSettings {
+					id,
+					value: "updated".to_owned(),
+					..TestSettings::default()
+				};
+				block_on(backend.update("table", &key, &settings))?;
+			}
+		}
+
+		assert!(rebalance.join().unwrap()?.checked() <= 400);
+		assert!(!backend.is_rebalancing());
+
+		let shards = backend.shards();
+		for id in 0..400 {
+			let key = id.to_string();
+			let holders = shards
+				.iter()
+				.filter(|shard| shard.entry::<TestSettings>("table", &key).is_some())
+				.count();
+
+			if id % 4 == 0 {
+				assert_eq!(holders, 0, "deleted key {key} was moved back");
+			} else {
+				assert_eq!(holders, 1, "key {key} is stored on {holders} shards");
+				let stored = backend.shard_for(&key).entry::<TestSettings>("table", &key);
+				assert_eq!(
+					stored.map(|settings| settings.value).as_deref(),
+					Some("updated")
+				);
+			}
+		}
+
+		Ok(())
+	}
+
+	#[tokio::test]
+	async fn sharded() -> Result<(), Error> {
+		let chart = Starchart::new(ShardedBackend::new(
+			(0..3).map(|_| MockBackend::new()).collect::<Vec<_>>(),
+		))
+		.await?;
+		chart.create_table("table").await?;
+
+		let table = chart.table::<TestSettings>("table");
+		for id in 1..=60 {
+			table
+				.insert(&TestSettings {
+					id,
+					..TestSettings::default()
+				})
+				.await?;
+		}
+
+		let mut total = 0;
+		for (index, shard) in chart.shards().iter().enumerate() {
+			let keys = shard.get_keys::<Vec<String>>("table").await?;
+			assert!(!keys.is_empty());
+			assert!(keys.iter().all(|key| chart.shard_index(key) == index));
+			total += keys.len();
+		}
+		assert_eq!(total, 60);
+
+		assert_eq!(chart.get_keys::<Vec<String>>("table").await?.len(), 60);
+		assert_eq!(table.get(&42_u32).await?.map(|entry| entry.id), Some(42));
+
+		let keys = (1..=60).map(|id: u32| id.to_string()).collect::<Vec<_>>();
+		let keys = keys.iter().map(String::as_str).collect::<Vec<_>>();
+		let entries = chart
+			.get_all::<TestSettings, Vec<_>>("table", &keys)
+			.await?;
+		assert_eq!(entries.len(), 60);
+
+		let entries = chart
+			.get_multi::<TestSettings>("table", &["60", "61", "1", "30"])
+			.await?
+			.into_iter()
+			.map(|entry| entry.map(|entry| entry.id))
+			.collect::<Vec<_>>();
+		assert_eq!(entries, [Some(60), None, Some(1), Some(30)]);
+
+		let grown = ShardedBackend::new((0..4).map(|_| MockBackend::new()).collect::<Vec<_>>());
+		let moved = keys
+			.iter()
+			.filter(|key| grown.shard_index(key) != chart.shard_index(key))
+			.count();
+		assert!(moved < 30);
+
+		Ok(())
+	}
+
+	#[tokio::test]
+	async fn sharded_rebalance() -> Result<(), Error> {
+		let chart = Starchart::new(ShardedBackend::new(
+			(0..3).map(|_| MockBackend::new()).collect::<Vec<_>>(),
+		))
+		.await?;
+		chart.create_table("table").await?;
+
+		let table = chart.table::<TestSettings>("table");
+		for id in 1..=60 {
+			table
+				.insert(&TestSettings {
+					id,
+					..TestSettings::default()
+				})
+				.await?;
+		}
+
+		let mut shards = chart
+			.shard_ids()
+			.into_iter()
+			.zip(chart.shards())
+			.collect::<Vec<_>>();
+		shards.push(("3".to_owned(), Arc::new(MockBackend::new())));
+
+		let mut reports = 0;
+		let totals = chart
+			.rebalance::<TestSettings, _>(&["table"], shards, |table, _| {
+				assert_eq!(table, "table");
+				reports += 1;
+			})
+			.await?;
+
+		assert_eq!(reports, 60);
+		assert_eq!(totals.checked(), 60);
+		assert!(totals.moved() > 0 && totals.moved() < 30);
+		assert!(!chart.is_rebalancing());
+
+		let shards = chart.shards();
+		assert_eq!(shards.len(), 4);
+		for (index, shard) in shards.iter().enumerate() {
+			let keys = shard.get_keys::<Vec<String>>("table").await?;
+			assert!(keys.iter().all(|key| chart.shard_index(key) == index));
+		}
+		assert_eq!(
+			shards[3].get_keys::<Vec<String>>("table").await?.len(),
+			totals.moved()
+		);
+
+		assert_eq!(chart.get_keys::<Vec<String>>("table").await?.len(), 60);
+		for id in 1..=60_u32 {
+			assert_eq!(table.get(&id).await?.map(|entry| entry.id), Some(id));
+		}
+
+		Ok(())
+	}
+
+	#[tokio::test]
+	async fn sharded_remove() -> Result<(), Error> {
+		let ids = ["a", "b", "c"];
+		let chart = Starchart::new(ShardedBackend::with_ids(
+			ids.iter()
+				.map(|id| ((*id).to_owned(), MockBackend::new()))
+				.collect(),
+		))
+		.await?;
+		chart.create_table("table").await?;
+
+		let table = chart.table::<TestSettings>("table");
+		for id in 1..=60 {
+			table
+				.insert(&TestSettings {
+					id,
+					..TestSettings::default()
+				})
+				.await?;
+		}
+
+		let before = (1..=60_u32)
+			.map(|id| chart.shard_ids()[chart.shard_index(&id.to_string())].clone())
+			.collect::<Vec<_>>();
+		let held = before.iter().filter(|id| *id == "b").count();
+
+		let shards = chart
+			.shard_ids()
+			.into_iter()
+			.zip(chart.shards())
+			.filter(|(id, _)| id != "b")
+			.collect::<Vec<_>>();
+		let totals = chart
+			.rebalance::<TestSettings, _>(&["table"], shards, |_, _| {})
+			.await?;
+		assert_eq!(totals.moved(), held);
+		assert_eq!(chart.shard_ids(), ["a", "c"]);
+
+		for (id, before) in (1..=60_u32).zip(before) {
+			let after = &chart.shard_ids()[chart.shard_index(&id.to_string())];
+			if before != "b" {
+				assert_eq!(*after, before);
+			}
+			assert_eq!(table.get(&id).await?.map(|entry| entry.id), Some(id));
+		}
+
+		Ok(())
+	}
+}
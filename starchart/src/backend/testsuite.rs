@@ -0,0 +1,547 @@
+//! A test suite for [`Backend`] implementations, enabled with the `test-util` feature.
+//!
+//! Authors of third-party backends can check their backend behaves like the ones the
+//! crate is tested against with a single call to [`run`], which checks both the [`Backend`] methods
+//! and how the backend's errors are reported through a [`Starchart`].
+//!
+//! ```rust,ignore
+//! #[tokio::test]
+//! async fn testsuite() -> Result<(), MyError> {
+//!     starchart::backend::testsuite::run(MyBackend::new()).await
+//! }
+//! ```
+//!
+//! [`Starchart`]: crate::Starchart
+
+use std::time::{Duration, SystemTime};
+
+use futures_util::TryStreamExt;
+use serde::{Deserialize, Serialize};
+
+use super::Backend;
+use crate::{
+	action::{CreateEntryAction, ReadEntryAction, ReadTableAction, UpdateEntryAction},
+	error::ErrorType,
+	query::{Filter, PageRange},
+	Error, IndexEntry, Starchart, METADATA_KEY,
+};
+
+/// The table the suite creates and deletes, any existing table with this name is dropped first.
+pub const TABLE: &str = "__starchart_testsuite__";
+
+/// The name [`TABLE`] is renamed to while testing [`Backend::rename_table`].
+pub const RENAMED_TABLE: &str = "__starchart_testsuite_renamed__";
+
+/// The keys the suite checks can be stored and listed, beyond plain ASCII.
+pub const UNICODE_KEYS: [&str; 5] = ["ключ", "キー", "🦀", "caf\u{e9}", "with spaces"];
+
+#[derive(Debug, Default, Clone, PartialEq, Serialize, Deserialize)]
+struct SuiteEntry {
+	id: u32,
+	name: String,
+	count: i64,
+	tags: Vec<String>,
+}
+
+impl SuiteEntry {
+	fn new(id: u32, name: &str) -> Self {
+		Self {
+			id,
+			name: name.to_owned(),
+			count: i64::from(id),
+			tags: vec!["tag".to_owned(), name.to_owned()],
+		}
+	}
+}
+
+impl IndexEntry for SuiteEntry {
+	type Key = u32;
+
+	fn key(&self) -> &Self::Key {
+		&self.id
+	}
+
+	fn indexes(&self) -> Vec<(&'static str, String)> {
+		vec![("name", self.name.clone())]
+	}
+}
+
+/// Runs every [`Backend`] method against the backend, checking the results match what the
+/// [`Starchart`] expects.
+///
+/// Along with the happy paths, this checks empty tables, creating an entry that already exists,
+/// the key the [`Starchart`] stores metadata under, and non-ASCII keys. The backend is then wrapped in
+/// a [`Starchart`], to check that missing tables, missing entries, and entries that can't be decoded
+/// are reported the same way as every other backend. The backend is shut down once the [`Starchart`]
+/// is dropped at the end of the suite.
+///
+/// The suite only uses [`TABLE`] and [`RENAMED_TABLE`], which are both deleted once it's finished.
+///
+/// # Errors
+///
+/// Returns any error the backend reports.
+///
+/// # Panics
+///
+/// Panics if the backend returns a result other than the expected one, describing the method called,
+/// or if the [`Starchart`] fails for any other reason than the one expected.
+pub async fn run<B: Backend>(backend: B) -> Result<(), B::Error> {
+	backend.init().await?;
+	backend_methods(&backend).await?;
+
+	let chart = Starchart::new(backend).await?;
+	missing_tables(&chart).await?;
+	corrupt_entries(&chart).await?;
+	chart.delete_table(TABLE).await?;
+
+	Ok(())
+}
+
+#[allow(clippy::panic_in_result_fn, clippy::too_many_lines)]
+async fn backend_methods<B: Backend>(backend: &B) -> Result<(), B::Error> {
+	backend.health_check().await?;
+
+	for table in [TABLE, RENAMED_TABLE] {
+		if backend.has_table(table).await? {
+			backend.delete_table(table).await?;
+		}
+	}
+
+	// tables
+	assert!(
+		!backend.has_table(TABLE).await?,
+		"has_table returned true for a deleted table"
+	);
+	backend.create_table(TABLE).await?;
+	assert!(
+		backend.has_table(TABLE).await?,
+		"has_table returned false for a created table"
+	);
+	assert!(
+		backend.tables::<Vec<_>>().await?.iter().any(|t| t == TABLE),
+		"tables didn't include a created table"
+	);
+	backend.ensure_table(TABLE).await?;
+
+	// empty tables
+	let range = PageRange::new();
+	let all_matching = Filter::entry(|_: &SuiteEntry| true);
+	assert!(
+		backend.get_keys::<Vec<_>>(TABLE).await?.is_empty(),
+		"get_keys returned keys for an empty table"
+	);
+	assert_eq!(
+		backend.get::<SuiteEntry>(TABLE, "1").await?,
+		None,
+		"get returned an entry from an empty table"
+	);
+	assert!(
+		!backend.has(TABLE, "1").await?,
+		"has returned true for an empty table"
+	);
+	assert!(
+		backend
+			.get_all::<SuiteEntry, Vec<_>>(TABLE, &["1"])
+			.await?
+			.is_empty(),
+		"get_all returned entries from an empty table"
+	);
+	assert!(
+		backend
+			.get_all_stream::<SuiteEntry>(TABLE)
+			.try_collect::<Vec<_>>()
+			.await?
+			.is_empty(),
+		"get_all_stream returned entries from an empty table"
+	);
+	assert!(
+		backend
+			.get_filtered::<SuiteEntry, Vec<_>>(TABLE, &all_matching)
+			.await?
+			.is_empty(),
+		"get_filtered returned entries from an empty table"
+	);
+	assert!(
+		backend
+			.get_page::<SuiteEntry, Vec<_>>(TABLE, &range)
+			.await?
+			.is_empty(),
+		"get_page returned entries from an empty table"
+	);
+	assert!(
+		backend
+			.get_keys_in_range::<Vec<_>>(TABLE, &range)
+			.await?
+			.is_empty(),
+		"get_keys_in_range returned keys for an empty table"
+	);
+	assert_eq!(
+		backend.get_multi::<SuiteEntry>(TABLE, &["1", "2"]).await?,
+		[None, None],
+		"get_multi returned entries from an empty table"
+	);
+
+	// single entries
+	let first = SuiteEntry::new(1, "first");
+	backend.create(TABLE, "1", &first).await?;
+	assert!(
+		backend.has(TABLE, "1").await?,
+		"has returned false for a created entry"
+	);
+	assert_eq!(
+		backend.get(TABLE, "1").await?,
+		Some(first.clone()),
+		"get didn't return the created entry"
+	);
+	assert_eq!(
+		backend.get_keys::<Vec<_>>(TABLE).await?,
+		["1"],
+		"get_keys didn't return the created key"
+	);
+
+	// creating an entry that exists may fail or overwrite it, but must never store the key twice
+	let duplicate = SuiteEntry::new(1, "duplicate");
+	let _ = backend.create(TABLE, "1", &duplicate).await;
+	let stored = backend.get::<SuiteEntry>(TABLE, "1").await?;
+	assert!(
+		stored == Some(first.clone()) || stored == Some(duplicate.clone()),
+		"creating an existing entry stored {:?}",
+		stored
+	);
+	assert_eq!(
+		backend.get_keys::<Vec<_>>(TABLE).await?,
+		["1"],
+		"creating an existing entry stored the key twice"
+	);
+
+	let updated = SuiteEntry::new(1, "updated");
+	backend.update(TABLE, "1", &updated).await?;
+	assert_eq!(
+		backend.get(TABLE, "1").await?,
+		Some(updated.clone()),
+		"get didn't return the updated entry"
+	);
+
+	backend.ensure(TABLE, "1", &first).await?;
+	assert_eq!(
+		backend.get(TABLE, "1").await?,
+		Some(updated.clone()),
+		"ensure overwrote an existing entry"
+	);
+	let second = SuiteEntry::new(2, "second");
+	backend.ensure(TABLE, "2", &second).await?;
+	assert_eq!(
+		backend.get(TABLE, "2").await?,
+		Some(second.clone()),
+		"ensure didn't create a missing entry"
+	);
+
+	backend.replace(TABLE, "1", &first).await?;
+	let third = SuiteEntry::new(3, "third");
+	backend.replace(TABLE, "3", &third).await?;
+	assert_eq!(
+		backend.get_multi(TABLE, &["1", "missing", "3"]).await?,
+		[Some(first.clone()), None, Some(third.clone())],
+		"get_multi didn't return the replaced entries in order"
+	);
+
+	// many entries
+	let fourth = SuiteEntry::new(4, "fourth");
+	let fifth = SuiteEntry::new(5, "fifth");
+	backend
+		.create_multi(TABLE, &[("4", &fourth), ("5", &fifth)])
+		.await?;
+	let fourth = SuiteEntry::new(4, "fourth updated");
+	let fifth = SuiteEntry::new(5, "fifth updated");
+	backend
+		.update_multi(TABLE, &[("4", &fourth), ("5", &fifth)])
+		.await?;
+	let everything = vec![first, second, third, fourth, fifth];
+
+	let mut keys = backend.get_keys::<Vec<String>>(TABLE).await?;
+	keys.sort();
+	assert_eq!(keys, ["1", "2", "3", "4", "5"], "get_keys missed some keys");
+
+	let key_refs = keys.iter().map(String::as_str).collect::<Vec<_>>();
+	let mut all = backend
+		.get_all::<SuiteEntry, Vec<_>>(TABLE, &key_refs)
+		.await?;
+	all.sort_by_key(|entry| entry.id);
+	assert_eq!(all, everything, "get_all didn't return every entry");
+
+	let mut streamed = backend
+		.get_all_stream::<SuiteEntry>(TABLE)
+		.try_collect::<Vec<_>>()
+		.await?;
+	streamed.sort_by_key(|(_, entry)| entry.id);
+	assert_eq!(
+		streamed,
+		keys.iter()
+			.cloned()
+			.zip(everything.iter().cloned())
+			.collect::<Vec<_>>(),
+		"get_all_stream didn't return every entry with it's key"
+	);
+
+	let matching = Filter::entry(|entry: &SuiteEntry| entry.id > 3);
+	let mut filtered = backend
+		.get_filtered::<SuiteEntry, Vec<_>>(TABLE, &matching)
+		.await?;
+	filtered.sort_by_key(|entry| entry.id);
+	assert_eq!(
+		filtered,
+		[everything[3].clone(), everything[4].clone()],
+		"get_filtered didn't return the matching entries"
+	);
+
+	let mut range = PageRange::new();
+	range.after = Some("1".to_owned());
+	range.offset = 1;
+	range.limit = Some(2);
+	assert_eq!(
+		backend.get_keys_in_range::<Vec<_>>(TABLE, &range).await?,
+		["3", "4"],
+		"get_keys_in_range didn't apply the range in order"
+	);
+	assert_eq!(
+		backend
+			.get_page::<SuiteEntry, Vec<_>>(TABLE, &range)
+			.await?,
+		[everything[2].clone(), everything[3].clone()],
+		"get_page didn't return the entries in the range in order"
+	);
+
+	// native expiry and increments are optional, but mustn't lose the entry.
+	let expires_at = SystemTime::now() + Duration::from_secs(90);
+	backend.expire(TABLE, "1", expires_at).await?;
+	assert!(
+		backend.has(TABLE, "1").await?,
+		"expire removed an entry before it expired"
+	);
+	if let Some(count) = backend.increment(TABLE, "1", &["count"], 5).await? {
+		assert_eq!(count, 6, "increment returned the wrong value");
+		assert_eq!(
+			backend
+				.get::<SuiteEntry>(TABLE, "1")
+				.await?
+				.map(|e| e.count),
+			Some(6),
+			"increment didn't store the new value"
+		);
+	}
+
+	backend.delete(TABLE, "5").await?;
+	assert!(
+		!backend.has(TABLE, "5").await?,
+		"has returned true for a deleted entry"
+	);
+	assert_eq!(
+		backend.get::<SuiteEntry>(TABLE, "5").await?,
+		None,
+		"get returned a deleted entry"
+	);
+
+	// keys the chart or users may store
+	let metadata = SuiteEntry::new(0, METADATA_KEY);
+	backend.create(TABLE, METADATA_KEY, &metadata).await?;
+	assert_eq!(
+		backend.get(TABLE, METADATA_KEY).await?,
+		Some(metadata),
+		"get didn't return the entry stored under the metadata key"
+	);
+	assert!(
+		backend
+			.get_keys::<Vec<String>>(TABLE)
+			.await?
+			.iter()
+			.any(|key| key == METADATA_KEY),
+		"get_keys didn't include the metadata key"
+	);
+
+	for (id, key) in (10..).zip(UNICODE_KEYS) {
+		let entry = SuiteEntry::new(id, key);
+		backend.create(TABLE, key, &entry).await?;
+		assert_eq!(
+			backend.get(TABLE, key).await?,
+			Some(entry),
+			"get didn't return the entry stored under {key:?}"
+		);
+	}
+	let keys = backend.get_keys::<Vec<String>>(TABLE).await?;
+	for key in UNICODE_KEYS {
+		assert!(
+			keys.iter().any(|stored| stored == key),
+			"get_keys didn't include {:?}",
+			key
+		);
+	}
+
+	// whole tables
+	let entries = keys.len();
+	backend.rename_table(TABLE, RENAMED_TABLE).await?;
+	assert!(
+		!backend.has_table(TABLE).await?,
+		"rename_table kept the old table"
+	);
+	assert_eq!(
+		backend.get_keys::<Vec<String>>(RENAMED_TABLE).await?.len(),
+		entries,
+		"rename_table didn't move every entry"
+	);
+
+	backend.ensure_table(RENAMED_TABLE).await?;
+	assert_eq!(
+		backend.get_keys::<Vec<String>>(RENAMED_TABLE).await?.len(),
+		entries,
+		"ensure_table emptied an existing table"
+	);
+
+	backend.truncate_table(RENAMED_TABLE).await?;
+	assert!(
+		backend.has_table(RENAMED_TABLE).await?,
+		"truncate_table deleted the table"
+	);
+	assert!(
+		backend
+			.get_keys::<Vec<String>>(RENAMED_TABLE)
+			.await?
+			.is_empty(),
+		"truncate_table kept some entries"
+	);
+
+	backend.delete_table(RENAMED_TABLE).await?;
+	assert!(
+		!backend.has_table(RENAMED_TABLE).await?,
+		"has_table returned true for a deleted table"
+	);
+
+	Ok(())
+}
+
+/// Checks that reading a missing entry from an existing table returns [`None`], incrementing one fails with
+/// [`ErrorType::EntryMissing`], while any action on a missing table fails with [`ErrorType::TableMissing`].
+#[allow(clippy::panic_in_result_fn)]
+async fn missing_tables<B: Backend>(chart: &Starchart<B>) -> Result<(), B::Error> {
+	let entry = SuiteEntry::new(1, "first");
+
+	let mut read = ReadEntryAction::<SuiteEntry>::new();
+	read.set_table(TABLE).set_key(&entry.id);
+
+	let mut read_table = ReadTableAction::<SuiteEntry>::new();
+	read_table.set_table(TABLE);
+
+	let mut create = CreateEntryAction::new();
+	create.set_table(TABLE).set_entry(&entry);
+
+	let mut update = UpdateEntryAction::new();
+	update.set_table(TABLE).set_entry(&entry);
+
+	let results = [
+		(
+			"read_entry",
+			read.clone().run_read_entry(chart).await.map(drop),
+		),
+		(
+			"read_table",
+			read_table
+				.run_read_table::<_, Vec<_>>(chart)
+				.await
+				.map(drop),
+		),
+		("create_entry", create.run_create_entry(chart).await),
+		("update_entry", update.run_update_entry(chart).await),
+	];
+
+	for (action, result) in results {
+		let kind = result.map_err(|e| Error::from(e).into_parts().0);
+		assert!(
+			matches!(kind, Err(ErrorType::TableMissing)),
+			"{} on a missing table returned {:?}",
+			action,
+			kind
+		);
+	}
+
+	let found = chart
+		.find_by_index::<SuiteEntry, _, Vec<_>>(TABLE, "name", &"first")
+		.await
+		.map_err(Error::into_parts);
+	assert!(
+		matches!(found, Err((ErrorType::TableMissing, None))),
+		"find_by_index on a missing table returned {:?}",
+		found
+	);
+
+	chart.create_table(TABLE).await?;
+
+	let read = read.run_read_entry(chart).await;
+	assert!(
+		matches!(read, Ok(None)),
+		"read_entry of a missing entry returned {:?}",
+		read
+	);
+
+	let incremented = chart
+		.increment::<SuiteEntry, _>(TABLE, &entry.id, "count", 1)
+		.await
+		.map_err(Error::into_parts);
+	assert!(
+		matches!(incremented, Err((ErrorType::EntryMissing, None))),
+		"increment of a missing entry returned {:?}",
+		incremented
+	);
+
+	Ok(())
+}
+
+/// Checks that reading an entry stored as a different type fails with [`ErrorType::CorruptEntry`], whether
+/// it's read on it's own or along with the rest of the table, while the other entries can still be read.
+#[allow(clippy::panic_in_result_fn)]
+async fn corrupt_entries<B: Backend>(chart: &Starchart<B>) -> Result<(), B::Error> {
+	let entry = SuiteEntry::new(1, "first");
+
+	let mut create = CreateEntryAction::new();
+	create.set_table(TABLE).set_entry(&entry);
+	let created = create.run_create_entry(chart).await;
+	assert!(created.is_ok(), "create_entry returned {:?}", created);
+
+	chart.create(TABLE, "2", &"not an entry".to_owned()).await?;
+
+	let mut read = ReadEntryAction::<SuiteEntry>::new();
+	read.set_table(TABLE).set_key(&2_u32);
+
+	let mut read_table = ReadTableAction::<SuiteEntry>::new();
+	read_table.set_table(TABLE);
+
+	let results = [
+		("read_entry", read.run_read_entry(chart).await.map(drop)),
+		(
+			"read_table",
+			read_table
+				.run_read_table::<_, Vec<_>>(chart)
+				.await
+				.map(drop),
+		),
+	];
+
+	for (action, result) in results {
+		let error = result.map_err(Error::from);
+		assert!(
+			matches!(&error, Err(e) if matches!(e.kind(), ErrorType::CorruptEntry) && e.is_serialization()),
+			"{} of a corrupt entry returned {:?}",
+			action,
+			error
+		);
+	}
+
+	let mut read = ReadEntryAction::<SuiteEntry>::new();
+	read.set_table(TABLE).set_key(&entry.id);
+	let read = read.run_read_entry(chart).await;
+	assert!(
+		matches!(&read, Ok(Some(read)) if *read == entry),
+		"read_entry next to a corrupt entry returned {:?}",
+		read
+	);
+
+	Ok(())
+}
@@ -0,0 +1,480 @@
+//! A [`Backend`] that writes to a primary and a replica [`Backend`], reading from the replica when the primary fails.
+
+use std::{
+	error::Error as StdError,
+	fmt::{Display, Formatter, Result as FmtResult},
+	future::Future,
+	iter::FromIterator,
+};
+
+use futures_util::{future::join, FutureExt};
+use parking_lot::Mutex;
+
+use super::{
+	futures::{
+		CreateFuture, CreateMultiFuture, CreateTableFuture, DeleteFuture, DeleteTableFuture,
+		EnsureTableFuture, GetFuture, GetKeysFuture, HasFuture, HasTableFuture, HealthCheckFuture,
+		InitFuture, ReplaceFuture, ShutdownFuture, TablesFuture, UpdateFuture, UpdateMultiFuture,
+	},
+	Backend, BackendLayer,
+};
+use crate::{stats::TieredStats, transaction::TransactionalBackend, Entry, Error};
+
+/// An error returned from the [`TieredBackend`].
+#[derive(Debug)]
+pub struct TieredError {
+	source: Option<Box<dyn StdError + Send + Sync>>,
+	kind: TieredErrorType,
+}
+
+impl TieredError {
+	/// Immutable reference to the type of error that occurred.
+	#[must_use = "retrieving the type has no effect if left unused"]
+	pub const fn kind(&self) -> &TieredErrorType {
+		&self.kind
+	}
+
+	/// Consume the error, returning the source error if there is any.
+	#[must_use = "consuming the error and retrieving the source has no effect if left unused"]
+	pub fn into_source(self) -> Option<Box<dyn StdError + Send + Sync>> {
+		self.source
+	}
+
+	/// Consume the error, returning the owned error type and the source error.
+	#[must_use = "consuming the error into it's parts has no effect if left unused"]
+	pub fn into_parts(self) -> (TieredErrorType, Option<Box<dyn StdError + Send + Sync>>) {
+		(self.kind, self.source)
+	}
+
+	fn primary<E: StdError + Send + Sync + 'static>(e: E) -> Self {
+		Self {
+			source: Some(Box::new(e)),
+			kind: TieredErrorType::Primary,
+		}
+	}
+
+	fn replica<E: StdError + Send + Sync + 'static>(e: E) -> Self {
+		Self {
+			source: Some(Box::new(e)),
+			kind: TieredErrorType::Replica,
+		}
+	}
+}
+
+impl Display for TieredError {
+	fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+		match self.kind {
+			TieredErrorType::Primary => f.write_str("an error occurred in the primary backend"),
+			TieredErrorType::Replica => f.write_str("an error occurred in the replica backend"),
+		}
+	}
+}
+
+impl StdError for TieredError {
+	fn source(&self) -> Option<&(dyn StdError + 'static)> {
+		self.source
+			.as_ref()
+			.map(|source| &**source as &(dyn StdError + 'static))
+	}
+}
+
+impl From<TieredError> for Error {
+	fn from(e: TieredError) -> Self {
+		Self::backend(Some(Box::new(e)))
+	}
+}
+
+/// The type of [`TieredError`] that occurred.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum TieredErrorType {
+	/// The primary backend returned an error.
+	Primary,
+	/// The replica backend returned an error.
+	Replica,
+}
+
+/// Which backends of a [`TieredBackend`] must succeed for a write to succeed.
+///
+/// Failed writes are counted in [`TieredBackend::stats`] regardless of the policy.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum ConsistencyPolicy {
+	/// Both backends must succeed.
+	Strict,
+	/// The primary backend must succeed, failures on the replica are ignored. This is the default.
+	#[default]
+	Primary,
+	/// Either backend may succeed, so writes still succeed while the primary is unavailable.
+	Either,
+}
+
+/// A [`BackendLayer`] that wraps a [`Backend`] in a [`TieredBackend`], as the primary backend.
+#[derive(Debug, Clone)]
+pub struct TieredLayer<R> {
+	replica: R,
+	policy: ConsistencyPolicy,
+}
+
+impl<R> TieredLayer<R> {
+	/// Creates a new [`TieredLayer`], with the [`ConsistencyPolicy::Primary`] policy.
+	///
+	/// The replica is cloned into every backend the layer wraps.
+	#[must_use]
+	pub fn new(replica: R) -> Self {
+		Self {
+			replica,
+			policy: ConsistencyPolicy::default(),
+		}
+	}
+
+	/// Sets the [`ConsistencyPolicy`] for writes.
+	#[must_use]
+	pub const fn with_policy(mut self, policy: ConsistencyPolicy) -> Self {
+		self.policy = policy;
+
+		self
+	}
+}
+
+impl<P: Backend, R: Backend + Clone> BackendLayer<P> for TieredLayer<R> {
+	type Backend = TieredBackend<P, R>;
+
+	fn layer(&self, inner: P) -> Self::Backend {
+		TieredBackend::new(inner, self.replica.clone()).with_policy(self.policy)
+	}
+}
+
+/// A [`Backend`] that writes to a primary and a replica [`Backend`], reading from the replica when the primary fails.
+///
+/// Writes are sent to both backends at once, and whether they succeed is decided by the [`ConsistencyPolicy`].
+/// Entries are always written to the replica with [`Backend::replace`], so it catches up on entries it missed.
+/// Reads only go to the replica if the primary returns an error, such as a file-system primary
+/// falling back to a memory replica.
+#[derive(Debug)]
+pub struct TieredBackend<P, R> {
+	primary: P,
+	replica: R,
+	policy: ConsistencyPolicy,
+	stats: Mutex<TieredStats>,
+}
+
+impl<P: Backend, R: Backend> TieredBackend<P, R> {
+	/// Creates a new [`TieredBackend`], with the [`ConsistencyPolicy::Primary`] policy.
+	#[must_use]
+	pub fn new(primary: P, replica: R) -> Self {
+		Self {
+			primary,
+			replica,
+			policy: ConsistencyPolicy::default(),
+			stats: Mutex::default(),
+		}
+	}
+
+	/// Sets the [`ConsistencyPolicy`] for writes, consuming and returning the backend.
+	#[must_use]
+	pub const fn with_policy(mut self, policy: ConsistencyPolicy) -> Self {
+		self.policy = policy;
+
+		self
+	}
+
+	/// Returns the primary [`Backend`].
+	pub const fn primary(&self) -> &P {
+		&self.primary
+	}
+
+	/// Returns the replica [`Backend`].
+	pub const fn replica(&self) -> &R {
+		&self.replica
+	}
+
+	/// The [`ConsistencyPolicy`] used for writes.
+	#[must_use]
+	pub const fn policy(&self) -> ConsistencyPolicy {
+		self.policy
+	}
+
+	/// Statistics on how often the backends have failed.
+	pub fn stats(&self) -> TieredStats {
+		*self.stats.lock()
+	}
+
+	/// Reads from the primary, falling back to the replica if it fails.
+	async fn read<T, FP, FR, F>(&self, primary: FP, replica: F) -> Result<T, TieredError>
+	where
+		FP: Future<Output = Result<T, P::Error>>,
+		F: FnOnce() -> FR,
+		FR: Future<Output = Result<T, R::Error>>,
+	{
+		let e = match primary.await {
+			Ok(value) => return Ok(value),
+			Err(e) => e,
+		};
+
+		// the primary's error is more useful, as it's why the replica was read at all.
+		let value = replica().await.map_err(|_| TieredError::primary(e))?;
+
+		self.stats.lock().fallback_reads += 1;
+
+		Ok(value)
+	}
+
+	/// Writes to both backends, succeeding according to the [`ConsistencyPolicy`].
+	async fn write<T, U, FP, FR>(&self, primary: FP, replica: FR) -> Result<(), TieredError>
+	where
+		FP: Future<Output = Result<T, P::Error>>,
+		FR: Future<Output = Result<U, R::Error>>,
+	{
+		let (primary, replica) = join(primary, replica).await;
+
+		let mut stats = self.stats.lock();
+		stats.failed_primary_writes += u64::from(primary.is_err());
+		stats.failed_replica_writes += u64::from(replica.is_err());
+		drop(stats);
+
+		self.consistent(primary, replica)
+	}
+
+	/// Checks whether the results of an operation on both backends are consistent enough for the [`ConsistencyPolicy`].
+	fn consistent<T, U>(
+		&self,
+		primary: Result<T, P::Error>,
+		replica: Result<U, R::Error>,
+	) -> Result<(), TieredError> {
+		match (self.policy, primary, replica) {
+			(_, Ok(_), Ok(_))
+			| (ConsistencyPolicy::Primary | ConsistencyPolicy::Either, Ok(_), Err(_))
+			| (ConsistencyPolicy::Either, Err(_), Ok(_)) => Ok(()),
+			(_, Err(e), _) => Err(TieredError::primary(e)),
+			(_, Ok(_), Err(e)) => Err(TieredError::replica(e)),
+		}
+	}
+
+	async fn replace_replica<S: Entry>(
+		&self,
+		table: &str,
+		entries: &[(&str, &S)],
+	) -> Result<(), R::Error> {
+		for (id, value) in entries {
+			self.replica.replace(table, id, *value).await?;
+		}
+
+		Ok(())
+	}
+}
+
+impl<P: Backend, R: Backend> Backend for TieredBackend<P, R> {
+	type Error = TieredError;
+
+	fn init(&self) -> InitFuture<'_, Self::Error> {
+		async move {
+			self.primary.init().await.map_err(TieredError::primary)?;
+			self.replica.init().await.map_err(TieredError::replica)
+		}
+		.boxed()
+	}
+
+	unsafe fn shutdown(&self) -> ShutdownFuture<'_> {
+		async move {
+			unsafe {
+				join(self.primary.shutdown(), self.replica.shutdown()).await;
+			}
+		}
+		.boxed()
+	}
+
+	/// The backends are healthy if writes to them would succeed under the [`ConsistencyPolicy`].
+	fn health_check(&self) -> HealthCheckFuture<'_, Self::Error> {
+		async move {
+			let (primary, replica) =
+				join(self.primary.health_check(), self.replica.health_check()).await;
+
+			self.consistent(primary, replica)
+		}
+		.boxed()
+	}
+
+	fn is_self_describing(&self) -> bool {
+		self.primary.is_self_describing() && self.replica.is_self_describing()
+	}
+
+	fn entry_size<S: Entry>(&self, table: &str, id: &str, entry: &S) -> Option<usize> {
+		self.primary.entry_size(table, id, entry)
+	}
+
+	fn has_table<'a>(&'a self, table: &'a str) -> HasTableFuture<'a, Self::Error> {
+		self.read(self.primary.has_table(table), move || {
+			self.replica.has_table(table)
+		})
+		.boxed()
+	}
+
+	fn tables<I>(&self) -> TablesFuture<'_, I, Self::Error>
+	where
+		I: FromIterator<String>,
+	{
+		async move {
+			self.read(self.primary.tables::<Vec<_>>(), move || {
+				self.replica.tables::<Vec<_>>()
+			})
+			.await
+			.map(|tables| tables.into_iter().collect())
+		}
+		.boxed()
+	}
+
+	fn create_table<'a>(&'a self, table: &'a str) -> CreateTableFuture<'a, Self::Error> {
+		self.write(
+			self.primary.create_table(table),
+			self.replica.ensure_table(table),
+		)
+		.boxed()
+	}
+
+	fn delete_table<'a>(&'a self, table: &'a str) -> DeleteTableFuture<'a, Self::Error> {
+		self.write(
+			self.primary.delete_table(table),
+			self.replica.delete_table(table),
+		)
+		.boxed()
+	}
+
+	fn ensure_table<'a>(&'a self, table: &'a str) -> EnsureTableFuture<'a, Self::Error> {
+		self.write(
+			self.primary.ensure_table(table),
+			self.replica.ensure_table(table),
+		)
+		.boxed()
+	}
+
+	fn get_keys<'a, I>(&'a self, table: &'a str) -> GetKeysFuture<'a, I, Self::Error>
+	where
+		I: FromIterator<String>,
+	{
+		async move {
+			self.read(self.primary.get_keys::<Vec<_>>(table), move || {
+				self.replica.get_keys::<Vec<_>>(table)
+			})
+			.await
+			.map(|keys| keys.into_iter().collect())
+		}
+		.boxed()
+	}
+
+	fn get<'a, D>(&'a self, table: &'a str, id: &'a str) -> GetFuture<'a, D, Self::Error>
+	where
+		D: Entry,
+	{
+		async move {
+			self.read(self.primary.get(table, id), move || {
+				self.replica.get(table, id)
+			})
+			.await
+		}
+		.boxed()
+	}
+
+	fn has<'a>(&'a self, table: &'a str, id: &'a str) -> HasFuture<'a, Self::Error> {
+		self.read(self.primary.has(table, id), move || {
+			self.replica.has(table, id)
+		})
+		.boxed()
+	}
+
+	fn create<'a, S>(
+		&'a self,
+		table: &'a str,
+		id: &'a str,
+		value: &'a S,
+	) -> CreateFuture<'a, Self::Error>
+	where
+		S: Entry,
+	{
+		self.write(
+			self.primary.create(table, id, value),
+			self.replica.replace(table, id, value),
+		)
+		.boxed()
+	}
+
+	fn create_multi<'a, S>(
+		&'a self,
+		table: &'a str,
+		entries: &'a [(&'a str, &'a S)],
+	) -> CreateMultiFuture<'a, Self::Error>
+	where
+		S: Entry,
+	{
+		self.write(
+			self.primary.create_multi(table, entries),
+			self.replace_replica(table, entries),
+		)
+		.boxed()
+	}
+
+	fn replace<'a, S>(
+		&'a self,
+		table: &'a str,
+		id: &'a str,
+		value: &'a S,
+	) -> ReplaceFuture<'a, Self::Error>
+	where
+		S: Entry,
+	{
+		self.write(
+			self.primary.replace(table, id, value),
+			self.replica.replace(table, id, value),
+		)
+		.boxed()
+	}
+
+	fn update<'a, S>(
+		&'a self,
+		table: &'a str,
+		id: &'a str,
+		value: &'a S,
+	) -> UpdateFuture<'a, Self::Error>
+	where
+		S: Entry,
+	{
+		self.write(
+			self.primary.update(table, id, value),
+			self.replica.replace(table, id, value),
+		)
+		.boxed()
+	}
+
+	fn update_multi<'a, S>(
+		&'a self,
+		table: &'a str,
+		entries: &'a [(&'a str, &'a S)],
+	) -> UpdateMultiFuture<'a, Self::Error>
+	where
+		S: Entry,
+	{
+		self.write(
+			self.primary.update_multi(table, entries),
+			self.replace_replica(table, entries),
+		)
+		.boxed()
+	}
+
+	fn set_pinned(&self, table: &str, id: &str, pinned: bool) {
+		self.primary.set_pinned(table, id, pinned);
+		self.replica.set_pinned(table, id, pinned);
+	}
+
+	fn delete<'a>(&'a self, table: &'a str, id: &'a str) -> DeleteFuture<'a, Self::Error> {
+		self.write(
+			self.primary.delete(table, id),
+			self.replica.delete(table, id),
+		)
+		.boxed()
+	}
+}
+
+impl<P: TransactionalBackend, R: TransactionalBackend> TransactionalBackend
+	for TieredBackend<P, R>
+{
+}
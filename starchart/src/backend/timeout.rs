@@ -0,0 +1,472 @@
+//! A [`Backend`] that fails the operations of another [`Backend`] if they take too long.
+
+use std::{
+	error::Error as StdError,
+	fmt::{Display, Formatter, Result as FmtResult},
+	future::Future,
+	iter::{successors, FromIterator},
+	sync::Arc,
+	time::{Duration, SystemTime},
+};
+
+use futures_util::{
+	future::{select, Either},
+	FutureExt,
+};
+
+use super::{
+	futures::{
+		CreateFuture, CreateMultiFuture, CreateTableFuture, DeleteFuture, DeleteTableFuture,
+		ExpireFuture, GetAllFuture, GetFilteredFuture, GetFuture, GetKeysFuture,
+		GetKeysInRangeFuture, GetMultiFuture, GetPageFuture, HasFuture, HasTableFuture,
+		HealthCheckFuture, IncrementFuture, InitFuture, RenameTableFuture, ReplaceFuture,
+		ShutdownFuture, TablesFuture, TruncateTableFuture, UpdateFuture, UpdateMultiFuture,
+	},
+	Backend, BackendLayer,
+};
+use crate::{
+	query::{Filter, PageRange},
+	timer::{ThreadTimer, Timer},
+	transaction::TransactionalBackend,
+	Entry, Error,
+};
+
+/// An error returned from the [`TimeoutBackend`].
+#[derive(Debug)]
+pub struct TimeoutError {
+	source: Option<Box<dyn StdError + Send + Sync>>,
+	kind: TimeoutErrorType,
+}
+
+impl TimeoutError {
+	/// Immutable reference to the type of error that occurred.
+	#[must_use = "retrieving the type has no effect if left unused"]
+	pub const fn kind(&self) -> &TimeoutErrorType {
+		&self.kind
+	}
+
+	/// Consume the error, returning the source error if there is any.
+	#[must_use = "consuming the error and retrieving the source has no effect if left unused"]
+	pub fn into_source(self) -> Option<Box<dyn StdError + Send + Sync>> {
+		self.source
+	}
+
+	/// Consume the error, returning the owned error type and the source error.
+	#[must_use = "consuming the error into it's parts has no effect if left unused"]
+	pub fn into_parts(self) -> (TimeoutErrorType, Option<Box<dyn StdError + Send + Sync>>) {
+		(self.kind, self.source)
+	}
+
+	/// Whether the error was caused by the operation taking too long.
+	#[must_use]
+	pub const fn is_elapsed(&self) -> bool {
+		matches!(self.kind, TimeoutErrorType::Elapsed)
+	}
+
+	const fn elapsed() -> Self {
+		Self {
+			source: None,
+			kind: TimeoutErrorType::Elapsed,
+		}
+	}
+
+	fn backend<E: StdError + Send + Sync + 'static>(e: E) -> Self {
+		Self {
+			source: Some(Box::new(e)),
+			kind: TimeoutErrorType::Backend,
+		}
+	}
+}
+
+impl Display for TimeoutError {
+	fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+		match self.kind {
+			TimeoutErrorType::Elapsed => f.write_str("the backend operation timed out"),
+			TimeoutErrorType::Backend => f.write_str("an error occurred in the inner backend"),
+		}
+	}
+}
+
+impl StdError for TimeoutError {
+	fn source(&self) -> Option<&(dyn StdError + 'static)> {
+		self.source
+			.as_ref()
+			.map(|source| &**source as &(dyn StdError + 'static))
+	}
+}
+
+impl From<TimeoutError> for Error {
+	fn from(e: TimeoutError) -> Self {
+		Self::backend(Some(Box::new(e)))
+	}
+}
+
+/// The type of [`TimeoutError`] that occurred.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum TimeoutErrorType {
+	/// The operation didn't complete within the timeout.
+	Elapsed,
+	/// The inner backend returned an error.
+	Backend,
+}
+
+/// Checks whether the error, or any error it was caused by, is a timed out [`TimeoutError`].
+pub fn timed_out(error: &(dyn StdError + 'static)) -> bool {
+	successors(Some(error), |&error| error.source()).any(|error| {
+		error
+			.downcast_ref::<TimeoutError>()
+			.is_some_and(TimeoutError::is_elapsed)
+	})
+}
+
+/// A [`BackendLayer`] that wraps a [`Backend`] in a [`TimeoutBackend`].
+#[derive(Debug, Clone)]
+pub struct TimeoutLayer {
+	timeout: Duration,
+	timer: Arc<dyn Timer>,
+}
+
+impl TimeoutLayer {
+	/// Creates a new [`TimeoutLayer`], failing operations that take longer than the timeout.
+	#[must_use]
+	pub fn new(timeout: Duration) -> Self {
+		Self {
+			timeout,
+			timer: Arc::new(ThreadTimer::new()),
+		}
+	}
+
+	/// Sets the [`Timer`] used to time operations.
+	#[must_use]
+	pub fn with_timer<T: Timer + 'static>(mut self, timer: T) -> Self {
+		self.timer = Arc::new(timer);
+
+		self
+	}
+}
+
+impl<B: Backend> BackendLayer<B> for TimeoutLayer {
+	type Backend = TimeoutBackend<B>;
+
+	fn layer(&self, inner: B) -> Self::Backend {
+		TimeoutBackend {
+			inner,
+			timeout: self.timeout,
+			timer: self.timer.clone(),
+		}
+	}
+}
+
+/// A [`Backend`] that fails the operations of another [`Backend`] with [`TimeoutErrorType::Elapsed`]
+/// if they don't complete within the timeout.
+///
+/// The operation is dropped once it times out, so a hung connection can't hold the [`Starchart`]'s
+/// locks forever. Timeouts are reported as [`ActionErrorType::Timeout`] and [`ErrorType::Timeout`],
+/// even through other wrapping backends.
+///
+/// The timeout applies to every call into the inner backend separately, and comes from a [`Timer`],
+/// which is a [`ThreadTimer`] by default.
+///
+/// [`Starchart`]: crate::Starchart
+/// [`ActionErrorType::Timeout`]: crate::action::ActionErrorType::Timeout
+/// [`ErrorType::Timeout`]: crate::error::ErrorType::Timeout
+#[derive(Debug)]
+pub struct TimeoutBackend<B> {
+	inner: B,
+	timeout: Duration,
+	timer: Arc<dyn Timer>,
+}
+
+impl<B: Backend> TimeoutBackend<B> {
+	/// Creates a new [`TimeoutBackend`], failing operations that take longer than the timeout.
+	#[must_use]
+	pub fn new(inner: B, timeout: Duration) -> Self {
+		Self {
+			inner,
+			timeout,
+			timer: Arc::new(ThreadTimer::new()),
+		}
+	}
+
+	/// Sets the [`Timer`] used to time operations.
+	#[must_use]
+	pub fn with_timer<T: Timer + 'static>(mut self, timer: T) -> Self {
+		self.timer = Arc::new(timer);
+
+		self
+	}
+
+	/// Returns a reference to the inner [`Backend`].
+	pub const fn inner(&self) -> &B {
+		&self.inner
+	}
+
+	/// The time every operation is given to complete.
+	#[must_use]
+	pub const fn timeout(&self) -> Duration {
+		self.timeout
+	}
+
+	/// Runs the operation, failing if it doesn't complete within the timeout.
+	async fn time<T, F>(&self, operation: F) -> Result<T, TimeoutError>
+	where
+		F: Future<Output = Result<T, B::Error>> + Unpin,
+	{
+		match select(operation, self.timer.sleep(self.timeout)).await {
+			Either::Left((result, _)) => result.map_err(TimeoutError::backend),
+			Either::Right(((), _)) => Err(TimeoutError::elapsed()),
+		}
+	}
+}
+
+impl<B: Backend> Backend for TimeoutBackend<B> {
+	type Error = TimeoutError;
+
+	fn init(&self) -> InitFuture<'_, Self::Error> {
+		self.time(self.inner.init()).boxed()
+	}
+
+	/// Shuts down the inner backend, giving up once the timeout has passed.
+	unsafe fn shutdown(&self) -> ShutdownFuture<'_> {
+		async move {
+			let shutdown = unsafe { self.inner.shutdown() };
+
+			select(shutdown, self.timer.sleep(self.timeout)).await;
+		}
+		.boxed()
+	}
+
+	fn health_check(&self) -> HealthCheckFuture<'_, Self::Error> {
+		self.time(self.inner.health_check()).boxed()
+	}
+
+	fn is_self_describing(&self) -> bool {
+		self.inner.is_self_describing()
+	}
+
+	fn entry_size<S: Entry>(&self, table: &str, id: &str, entry: &S) -> Option<usize> {
+		self.inner.entry_size(table, id, entry)
+	}
+
+	fn has_table<'a>(&'a self, table: &'a str) -> HasTableFuture<'a, Self::Error> {
+		self.time(self.inner.has_table(table)).boxed()
+	}
+
+	fn tables<I>(&self) -> TablesFuture<'_, I, Self::Error>
+	where
+		I: FromIterator<String>,
+	{
+		async move { self.time(self.inner.tables()).await }.boxed()
+	}
+
+	fn create_table<'a>(&'a self, table: &'a str) -> CreateTableFuture<'a, Self::Error> {
+		self.time(self.inner.create_table(table)).boxed()
+	}
+
+	fn delete_table<'a>(&'a self, table: &'a str) -> DeleteTableFuture<'a, Self::Error> {
+		self.time(self.inner.delete_table(table)).boxed()
+	}
+
+	fn rename_table<'a>(
+		&'a self,
+		table: &'a str,
+		new: &'a str,
+	) -> RenameTableFuture<'a, Self::Error> {
+		self.time(self.inner.rename_table(table, new)).boxed()
+	}
+
+	fn truncate_table<'a>(&'a self, table: &'a str) -> TruncateTableFuture<'a, Self::Error> {
+		self.time(self.inner.truncate_table(table)).boxed()
+	}
+
+	fn get_all<'a, D, I>(
+		&'a self,
+		table: &'a str,
+		entries: &'a [&'a str],
+	) -> GetAllFuture<'a, I, Self::Error>
+	where
+		D: Entry,
+		I: FromIterator<D>,
+	{
+		async move { self.time(self.inner.get_all::<D, I>(table, entries)).await }.boxed()
+	}
+
+	fn get_filtered<'a, D, I>(
+		&'a self,
+		table: &'a str,
+		filter: &'a Filter<'a, D>,
+	) -> GetFilteredFuture<'a, I, Self::Error>
+	where
+		D: Entry,
+		I: FromIterator<D>,
+	{
+		async move {
+			self.time(self.inner.get_filtered::<D, I>(table, filter))
+				.await
+		}
+		.boxed()
+	}
+
+	fn get_page<'a, D, I>(
+		&'a self,
+		table: &'a str,
+		range: &'a PageRange,
+	) -> GetPageFuture<'a, I, Self::Error>
+	where
+		D: Entry,
+		I: FromIterator<D>,
+	{
+		async move { self.time(self.inner.get_page::<D, I>(table, range)).await }.boxed()
+	}
+
+	fn get_keys<'a, I>(&'a self, table: &'a str) -> GetKeysFuture<'a, I, Self::Error>
+	where
+		I: FromIterator<String>,
+	{
+		async move { self.time(self.inner.get_keys(table)).await }.boxed()
+	}
+
+	fn get_keys_in_range<'a, I>(
+		&'a self,
+		table: &'a str,
+		range: &'a PageRange,
+	) -> GetKeysInRangeFuture<'a, I, Self::Error>
+	where
+		I: FromIterator<String>,
+	{
+		async move { self.time(self.inner.get_keys_in_range(table, range)).await }.boxed()
+	}
+
+	fn get<'a, D>(&'a self, table: &'a str, id: &'a str) -> GetFuture<'a, D, Self::Error>
+	where
+		D: Entry,
+	{
+		async move { self.time(self.inner.get(table, id)).await }.boxed()
+	}
+
+	fn get_multi<'a, D>(
+		&'a self,
+		table: &'a str,
+		ids: &'a [&'a str],
+	) -> GetMultiFuture<'a, D, Self::Error>
+	where
+		D: Entry,
+	{
+		async move { self.time(self.inner.get_multi(table, ids)).await }.boxed()
+	}
+
+	fn has<'a>(&'a self, table: &'a str, id: &'a str) -> HasFuture<'a, Self::Error> {
+		self.time(self.inner.has(table, id)).boxed()
+	}
+
+	fn create<'a, S>(
+		&'a self,
+		table: &'a str,
+		id: &'a str,
+		value: &'a S,
+	) -> CreateFuture<'a, Self::Error>
+	where
+		S: Entry,
+	{
+		self.time(self.inner.create(table, id, value)).boxed()
+	}
+
+	fn create_multi<'a, S>(
+		&'a self,
+		table: &'a str,
+		entries: &'a [(&'a str, &'a S)],
+	) -> CreateMultiFuture<'a, Self::Error>
+	where
+		S: Entry,
+	{
+		self.time(self.inner.create_multi(table, entries)).boxed()
+	}
+
+	fn replace<'a, S>(
+		&'a self,
+		table: &'a str,
+		id: &'a str,
+		value: &'a S,
+	) -> ReplaceFuture<'a, Self::Error>
+	where
+		S: Entry,
+	{
+		self.time(self.inner.replace(table, id, value)).boxed()
+	}
+
+	fn update<'a, S>(
+		&'a self,
+		table: &'a str,
+		id: &'a str,
+		value: &'a S,
+	) -> UpdateFuture<'a, Self::Error>
+	where
+		S: Entry,
+	{
+		self.time(self.inner.update(table, id, value)).boxed()
+	}
+
+	fn update_multi<'a, S>(
+		&'a self,
+		table: &'a str,
+		entries: &'a [(&'a str, &'a S)],
+	) -> UpdateMultiFuture<'a, Self::Error>
+	where
+		S: Entry,
+	{
+		self.time(self.inner.update_multi(table, entries)).boxed()
+	}
+
+	fn delete<'a>(&'a self, table: &'a str, id: &'a str) -> DeleteFuture<'a, Self::Error> {
+		self.time(self.inner.delete(table, id)).boxed()
+	}
+
+	fn set_pinned(&self, table: &str, id: &str, pinned: bool) {
+		self.inner.set_pinned(table, id, pinned);
+	}
+
+	fn expire<'a>(
+		&'a self,
+		table: &'a str,
+		id: &'a str,
+		expires_at: SystemTime,
+	) -> ExpireFuture<'a, Self::Error> {
+		self.time(self.inner.expire(table, id, expires_at)).boxed()
+	}
+
+	fn increment<'a>(
+		&'a self,
+		table: &'a str,
+		id: &'a str,
+		field: &'a [&'a str],
+		delta: i64,
+	) -> IncrementFuture<'a, Self::Error> {
+		self.time(self.inner.increment(table, id, field, delta))
+			.boxed()
+	}
+}
+
+impl<B: TransactionalBackend> TransactionalBackend for TimeoutBackend<B> {}
+
+#[cfg(test)]
+mod tests {
+	use std::{fmt::Debug, io};
+
+	use static_assertions::assert_impl_all;
+
+	use super::{TimeoutError, TimeoutLayer};
+	use crate::{error::ErrorType, Error};
+
+	assert_impl_all!(TimeoutError: Debug, Send, Sync);
+	assert_impl_all!(TimeoutLayer: Clone, Debug, Send, Sync);
+
+	#[test]
+	fn timeouts_are_reported() {
+		let elapsed = Error::from(TimeoutError::elapsed());
+		assert!(matches!(elapsed.kind(), ErrorType::Timeout));
+
+		let failed = Error::from(TimeoutError::backend(io::Error::other("failed")));
+		assert!(matches!(failed.kind(), ErrorType::Backend));
+	}
+}
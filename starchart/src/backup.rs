@@ -0,0 +1,312 @@
+//! Backups of whole tables, written as newline-delimited JSON.
+//!
+//! A backup starts with a header line recording the format version, followed by one line
+//! per entry holding the table, key, and entry. Entries from different tables may be interleaved.
+//!
+//! Exports are backups of a single table, and can be imported into a table with a different name.
+
+use std::{
+	collections::HashSet,
+	io::{BufRead, Error as IoError, ErrorKind, Write},
+};
+
+use futures_util::{
+	future::ready,
+	stream::{self, FuturesUnordered, StreamExt, TryStreamExt},
+};
+use parking_lot::Mutex;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::{backend::Backend, Error};
+
+/// The version of the backup format written by [`write`].
+pub const FORMAT_VERSION: u64 = 1;
+
+#[derive(Serialize, Deserialize)]
+struct Header {
+	starchart_backup: u64,
+}
+
+#[derive(Serialize, Deserialize)]
+struct Record {
+	table: String,
+	key: String,
+	entry: Value,
+}
+
+/// Writes every entry of the given tables, reading up to `parallelism` tables at once.
+///
+/// Entries are written as they're read, so only one entry per table is held in memory at a time.
+pub async fn write<B, W>(
+	backend: &B,
+	tables: &[&str],
+	writer: W,
+	parallelism: usize,
+) -> Result<usize, Error>
+where
+	B: Backend,
+	W: Write + Send,
+{
+	let writer = Mutex::new(writer);
+
+	write_line(
+		&writer,
+		&Header {
+			starchart_backup: FORMAT_VERSION,
+		},
+	)?;
+
+	let written = stream::iter(tables.iter().copied())
+		.map(|table| write_table(backend, table, &writer))
+		.buffer_unordered(parallelism.max(1))
+		.try_fold(0, |total, written| ready(Ok(total + written)))
+		.await?;
+
+	writer.into_inner().flush().map_err(Error::io)?;
+
+	Ok(written)
+}
+
+async fn write_table<B, W>(backend: &B, table: &str, writer: &Mutex<W>) -> Result<usize, Error>
+where
+	B: Backend,
+	W: Write + Send,
+{
+	if !backend
+		.has_table(table)
+		.await
+		.map_err(|e| Error::backend(Some(Box::new(e))))?
+	{
+		return Err(Error::table_missing());
+	}
+
+	let keys = backend
+		.get_keys::<Vec<String>>(table)
+		.await
+		.map_err(|e| Error::backend(Some(Box::new(e))))?;
+
+	let mut written = 0;
+	for key in keys {
+		let entry = backend
+			.get::<Value>(table, &key)
+			.await
+			.map_err(|e| Error::backend(Some(Box::new(e))))?;
+
+		if let Some(entry) = entry {
+			let record = Record {
+				table: table.to_owned(),
+				key,
+				entry,
+			};
+
+			write_line(writer, &record)?;
+			written += 1;
+		}
+	}
+
+	Ok(written)
+}
+
+fn write_line<W: Write, T: Serialize>(writer: &Mutex<W>, value: &T) -> Result<(), Error> {
+	let mut line = serde_json::to_vec(value).map_err(Error::serialization)?;
+	line.push(b'\n');
+
+	writer.lock().write_all(&line).map_err(Error::io)
+}
+
+/// Restores every entry in a backup, writing up to `parallelism` entries at once.
+///
+/// Missing tables are created, and existing entries are replaced. If `target` is set, every entry is
+/// written to that table instead, and the backup must only hold entries from a single table.
+pub async fn read<B, R>(
+	backend: &B,
+	reader: R,
+	parallelism: usize,
+	target: Option<&str>,
+) -> Result<usize, Error>
+where
+	B: Backend,
+	R: BufRead + Send,
+{
+	let mut lines = reader.lines();
+
+	let header = lines
+		.next()
+		.ok_or_else(|| invalid("the backup is empty"))?
+		.map_err(Error::io)?;
+
+	if serde_json::from_str::<Header>(&header)
+		.map_err(Error::serialization)?
+		.starchart_backup
+		!= FORMAT_VERSION
+	{
+		return Err(invalid("the backup was written by an unsupported version"));
+	}
+
+	let parallelism = parallelism.max(1);
+	let mut source = None;
+	let mut created = HashSet::new();
+	let mut pending = FuturesUnordered::new();
+	let mut restored = 0;
+
+	for line in lines {
+		let line = line.map_err(Error::io)?;
+
+		if line.is_empty() {
+			continue;
+		}
+
+		let mut record = serde_json::from_str::<Record>(&line).map_err(Error::serialization)?;
+
+		if let Some(target) = target {
+			if *source.get_or_insert_with(|| record.table.clone()) != record.table {
+				return Err(invalid("the export holds entries from more than one table"));
+			}
+
+			target.clone_into(&mut record.table);
+		}
+
+		// tables are created here, rather than with the entries, so they're never created twice at once
+		if !created.contains(&record.table) {
+			backend
+				.ensure_table(&record.table)
+				.await
+				.map_err(|e| Error::backend(Some(Box::new(e))))?;
+			created.insert(record.table.clone());
+		}
+
+		if pending.len() >= parallelism {
+			pending.try_next().await?;
+			restored += 1;
+		}
+
+		pending.push(async move {
+			backend
+				.replace(&record.table, &record.key, &record.entry)
+				.await
+				.map_err(|e| Error::backend(Some(Box::new(e))))
+		});
+	}
+
+	while pending.try_next().await?.is_some() {
+		restored += 1;
+	}
+
+	Ok(restored)
+}
+
+fn invalid(message: &str) -> Error {
+	Error::serialization(IoError::new(ErrorKind::InvalidData, message))
+}
+
+#[cfg(test)]
+mod tests {
+	use std::collections::BTreeMap;
+
+	use futures_util::TryStreamExt;
+
+	use crate::{
+		backend::{Backend, MockBackend},
+		error::ErrorType,
+		testing::TestSettings,
+		Error, Starchart,
+	};
+
+	async fn entries(
+		chart: &Starchart<MockBackend>,
+		table: &str,
+	) -> Result<BTreeMap<String, TestSettings>, Error> {
+		chart.table(table).iter().try_collect().await
+	}
+
+	#[tokio::test]
+	async fn backup_and_restore() -> Result<(), Error> {
+		let chart = Starchart::new(MockBackend::new()).await?;
+
+		let tables = ["first", "second", "third"];
+		for (count, table) in (1..=3).zip(tables) {
+			chart.create_table(table).await?;
+
+			let handle = chart.table::<TestSettings>(table);
+			for id in 1..=count {
+				handle
+					.insert(&TestSettings {
+						id,
+						..TestSettings::default()
+					})
+					.await?;
+			}
+		}
+
+		let mut backup = Vec::new();
+		assert_eq!(chart.backup(&tables, &mut backup, 2).await?, 6);
+
+		let err = chart.backup(&["missing"], Vec::new(), 2).await.unwrap_err();
+		assert!(matches!(err.kind(), ErrorType::TableMissing));
+
+		let restored = Starchart::new(MockBackend::new()).await?;
+		assert_eq!(restored.restore(backup.as_slice(), 2).await?, 6);
+
+		for table in tables {
+			assert_eq!(
+				entries(&restored, table).await?,
+				entries(&chart, table).await?
+			);
+		}
+
+		let err = restored
+			.restore(&b"{\"starchart_backup\":2}\n"[..], 2)
+			.await
+			.unwrap_err();
+		assert!(matches!(err.kind(), ErrorType::Serialization));
+
+		Ok(())
+	}
+
+	#[tokio::test]
+	async fn export_and_import() -> Result<(), Error> {
+		let chart = Starchart::new(MockBackend::new()).await?;
+		chart.create_table("table").await?;
+
+		let table = chart.table::<TestSettings>("table");
+		for id in 1..=3 {
+			table
+				.insert(&TestSettings {
+					id,
+					..TestSettings::default()
+				})
+				.await?;
+		}
+
+		let mut export = Vec::new();
+		assert_eq!(chart.export("table", &mut export).await?, 3);
+
+		let imported = Starchart::new(MockBackend::new()).await?;
+		assert_eq!(imported.import("renamed", export.as_slice()).await?, 3);
+		assert_eq!(
+			entries(&imported, "renamed").await?,
+			entries(&chart, "table").await?
+		);
+		assert!(!imported.has_table("table").await?);
+
+		chart.create_table("other").await?;
+		chart
+			.table::<TestSettings>("other")
+			.insert(&TestSettings::default())
+			.await?;
+
+		let mut backup = Vec::new();
+		chart.backup(&["table", "other"], &mut backup, 1).await?;
+		let err = imported
+			.import("renamed", backup.as_slice())
+			.await
+			.unwrap_err();
+		assert!(matches!(err.kind(), ErrorType::Serialization));
+
+		let err = chart.export("missing", Vec::new()).await.unwrap_err();
+		assert!(matches!(err.kind(), ErrorType::TableMissing));
+
+		Ok(())
+	}
+}
@@ -0,0 +1,111 @@
+//! Time sources used by the time-based features of a [`Starchart`].
+//!
+//! [`Starchart`]: crate::Starchart
+
+use std::{
+	fmt::Debug,
+	sync::Arc,
+	time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use parking_lot::Mutex;
+
+/// A source of the current time.
+///
+/// Everything in the crate that needs to know what time it is asks the
+/// [`Clock`] attached to the [`Starchart`], rather than calling [`SystemTime::now`] directly.
+///
+/// [`Starchart`]: crate::Starchart
+pub trait Clock: Debug + Send + Sync {
+	/// Returns the current time.
+	fn now(&self) -> SystemTime;
+}
+
+/// A [`Clock`] backed by [`SystemTime::now`], this is the default.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct SystemClock;
+
+impl SystemClock {
+	/// Creates a new [`SystemClock`].
+	#[must_use]
+	pub const fn new() -> Self {
+		Self
+	}
+}
+
+impl Clock for SystemClock {
+	fn now(&self) -> SystemTime {
+		SystemTime::now()
+	}
+}
+
+/// A [`Clock`] that only moves when told to, useful for tests.
+///
+/// Cloning a [`ManualClock`] shares the underlying time, so a clone can be handed
+/// to a [`Starchart`] while the original is used to move time forward.
+///
+/// [`Starchart`]: crate::Starchart
+#[derive(Debug, Clone)]
+pub struct ManualClock(Arc<Mutex<SystemTime>>);
+
+impl ManualClock {
+	/// Creates a new [`ManualClock`] starting at the given time.
+	#[must_use]
+	pub fn new(start: SystemTime) -> Self {
+		Self(Arc::new(Mutex::new(start)))
+	}
+
+	/// Sets the current time.
+	pub fn set(&self, time: SystemTime) {
+		*self.0.lock() = time;
+	}
+
+	/// Moves the current time forward by the given [`Duration`].
+	pub fn advance(&self, duration: Duration) {
+		*self.0.lock() += duration;
+	}
+}
+
+impl Default for ManualClock {
+	fn default() -> Self {
+		Self::new(UNIX_EPOCH)
+	}
+}
+
+impl Clock for ManualClock {
+	fn now(&self) -> SystemTime {
+		*self.0.lock()
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use std::{
+		fmt::Debug,
+		time::{Duration, UNIX_EPOCH},
+	};
+
+	use static_assertions::assert_impl_all;
+
+	use super::{Clock, ManualClock, SystemClock};
+
+	assert_impl_all!(SystemClock: Clock, Clone, Copy, Debug, Default, Send, Sync);
+	assert_impl_all!(ManualClock: Clock, Clone, Debug, Default, Send, Sync);
+
+	#[test]
+	fn manual_advance() {
+		let clock = ManualClock::default();
+		let shared = clock.clone();
+
+		assert_eq!(clock.now(), UNIX_EPOCH);
+
+		clock.advance(Duration::from_secs(5));
+
+		assert_eq!(shared.now(), UNIX_EPOCH + Duration::from_secs(5));
+
+		shared.set(UNIX_EPOCH);
+
+		assert_eq!(clock.now(), UNIX_EPOCH);
+	}
+}
@@ -0,0 +1,155 @@
+//! Copying tables between charts with different backends.
+
+use serde_json::Value;
+
+use crate::{backend::Backend, Error, Starchart};
+
+/// Copies every entry of the given tables from one chart to another, returning the amount of entries copied.
+///
+/// This is meant for migrating between storage engines, so entries are copied as they're stored, including
+/// each table's metadata entry. Tables are created in `dst` if they're missing, entries that already exist
+/// are replaced, and entries that only exist in `dst` are left untouched. Entries are read and written one at
+/// a time, so memory use doesn't grow with the size of the tables.
+///
+/// The tables are locked for reading in `src`, and for writing in `dst`, for the whole copy. Keys are
+/// copied as they're stored, so both charts should use the same [`KeyObfuscator`], if any.
+///
+/// # Errors
+///
+/// Returns an [`ErrorType::TableMissing`] error if any of the tables don't exist in `src`, an
+/// [`ErrorType::Untyped`] error if either backend isn't self-describing, as entries are copied without knowing
+/// their type, or any errors that [`Backend::get_keys`], [`Backend::get`], [`Backend::ensure_table`] or
+/// [`Backend::replace`] can raise.
+///
+/// [`KeyObfuscator`]: crate::obfuscation::KeyObfuscator
+/// [`ErrorType::TableMissing`]: crate::error::ErrorType::TableMissing
+/// [`ErrorType::Untyped`]: crate::error::ErrorType::Untyped
+pub async fn copy<A: Backend, B: Backend>(
+	src: &Starchart<A>,
+	dst: &Starchart<B>,
+	tables: &[&str],
+) -> Result<usize, Error> {
+	src.check_self_describing()?;
+	dst.check_self_describing()?;
+
+	// charts sharing locks, like children of the same chart, could deadlock if locked separately
+	let (src_lock, dst_lock) = if src.guard.shares_locks(&dst.guard) {
+		(Vec::new(), dst.guard.exclusive_all())
+	} else {
+		(
+			src.guard.shared_many(tables.iter().copied()),
+			dst.guard.exclusive_many(tables.iter().copied()),
+		)
+	};
+
+	let mut copied = 0;
+	for table in tables {
+		copied += copy_table(&**src, &**dst, table).await?;
+	}
+
+	drop(dst_lock);
+	drop(src_lock);
+
+	Ok(copied)
+}
+
+async fn copy_table<A: Backend, B: Backend>(src: &A, dst: &B, table: &str) -> Result<usize, Error> {
+	if !src
+		.has_table(table)
+		.await
+		.map_err(|e| Error::backend(Some(Box::new(e))))?
+	{
+		return Err(Error::table_missing());
+	}
+
+	dst.ensure_table(table)
+		.await
+		.map_err(|e| Error::backend(Some(Box::new(e))))?;
+
+	let keys = src
+		.get_keys::<Vec<String>>(table)
+		.await
+		.map_err(|e| Error::backend(Some(Box::new(e))))?;
+
+	let mut copied = 0;
+	for key in keys {
+		let entry = src
+			.get::<Value>(table, &key)
+			.await
+			.map_err(|e| Error::backend(Some(Box::new(e))))?;
+
+		if let Some(entry) = entry {
+			dst.replace(table, &key, &entry)
+				.await
+				.map_err(|e| Error::backend(Some(Box::new(e))))?;
+			copied += 1;
+		}
+	}
+
+	Ok(copied)
+}
+
+#[cfg(test)]
+mod tests {
+	use crate::{
+		action::CreateTableAction,
+		backend::{Backend, MockBackend, ShardedBackend},
+		error::ErrorType,
+		testing::TestSettings,
+		Error, Starchart,
+	};
+
+	#[tokio::test]
+	async fn copy() -> Result<(), Error> {
+		let src = Starchart::new(MockBackend::new()).await?;
+		let dst = Starchart::new(ShardedBackend::new(vec![MockBackend::new(); 2])).await?;
+
+		for table in ["first", "second"] {
+			CreateTableAction::<TestSettings>::for_table(table)
+				.run_create_table(&src)
+				.await?;
+
+			let handle = src.table::<TestSettings>(table);
+			for id in 1..=3 {
+				handle
+					.insert(&TestSettings {
+						id,
+						..TestSettings::default()
+					})
+					.await?;
+			}
+		}
+
+		// the metadata entries are copied along with the entries
+		let stored = src.get_keys::<Vec<String>>("first").await?.len() * 2;
+		assert_eq!(super::copy(&src, &dst, &["first", "second"]).await?, stored);
+
+		for table in ["first", "second"] {
+			let mut expected = src.get_keys::<Vec<String>>(table).await?;
+			let mut copied = dst.get_keys::<Vec<String>>(table).await?;
+			expected.sort_unstable();
+			copied.sort_unstable();
+			assert_eq!(copied, expected);
+
+			assert_eq!(
+				dst.table::<TestSettings>(table).get(&2_u32).await?,
+				src.table::<TestSettings>(table).get(&2_u32).await?
+			);
+		}
+
+		let err = super::copy(&src, &dst, &["missing"]).await.unwrap_err();
+		assert!(matches!(err.kind(), ErrorType::TableMissing));
+
+		let first = src.child("first/");
+		let second = src.child("second/");
+		first.create_table("table").await?;
+		first
+			.table::<TestSettings>("table")
+			.insert(&TestSettings::default())
+			.await?;
+		assert_eq!(super::copy(&first, &second, &["table"]).await?, 1);
+		assert!(second.has("table", "1").await?);
+
+		Ok(())
+	}
+}
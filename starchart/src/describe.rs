@@ -0,0 +1,289 @@
+//! Summaries of tables for admin interfaces and debug commands, see [`Starchart::describe`].
+//!
+//! [`Starchart::describe`]: crate::Starchart::describe
+
+use std::fmt::{Display, Formatter, Result as FmtResult};
+
+use serde_json::Value;
+
+use crate::{backend::Backend, index, schema::SchemaValue, stats::PayloadStats, util};
+
+/// The amount of entries sampled by [`Starchart::describe`].
+///
+/// [`Starchart::describe`]: crate::Starchart::describe
+pub const DEFAULT_SAMPLES: usize = 5;
+
+/// Where the schema of a [`TableDescription`] came from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum SchemaSource {
+	/// The schema recorded for the table with the `metadata` feature.
+	Registered,
+	/// A schema inferred from the sampled entries, holding every field seen in any of them.
+	Inferred,
+}
+
+/// A summary of a table, from [`Starchart::describe`].
+///
+/// Entries are read as untyped [`SchemaValue`]s, so tables can be described without their entry type.
+/// The [`Display`] impl writes a human readable summary.
+///
+/// [`Starchart::describe`]: crate::Starchart::describe
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TableDescription {
+	pub(crate) table: String,
+	pub(crate) entries: usize,
+	pub(crate) schema: Option<(SchemaSource, SchemaValue)>,
+	pub(crate) indexes: Vec<String>,
+	pub(crate) payload: Option<PayloadStats>,
+	pub(crate) samples: Vec<(String, SchemaValue)>,
+}
+
+impl TableDescription {
+	/// The name of the table.
+	#[must_use]
+	pub fn table(&self) -> &str {
+		&self.table
+	}
+
+	/// The amount of entries in the table.
+	#[must_use]
+	pub const fn entries(&self) -> usize {
+		self.entries
+	}
+
+	/// The schema of the table, or [`None`] if it has no registered schema and no entries to infer one from.
+	#[must_use]
+	pub fn schema(&self) -> Option<&SchemaValue> {
+		self.schema.as_ref().map(|(_, schema)| schema)
+	}
+
+	/// Where the [`Self::schema`] came from.
+	#[must_use]
+	pub fn schema_source(&self) -> Option<SchemaSource> {
+		self.schema.as_ref().map(|(source, _)| *source)
+	}
+
+	/// The names of the table's secondary indexes, in order.
+	///
+	/// Indexes are listed once an entry has been written to them, see [`IndexEntry::indexes`].
+	///
+	/// [`IndexEntry::indexes`]: crate::IndexEntry::indexes
+	#[must_use]
+	pub fn indexes(&self) -> &[String] {
+		&self.indexes
+	}
+
+	/// The sizes of entries written to the table since the chart was created, see [`Starchart::payload_stats`].
+	///
+	/// [`Starchart::payload_stats`]: crate::Starchart::payload_stats
+	#[must_use]
+	pub const fn payload(&self) -> Option<PayloadStats> {
+		self.payload
+	}
+
+	/// The sampled entries along with their keys, which are the first entries ordered by key.
+	#[must_use]
+	pub fn samples(&self) -> &[(String, SchemaValue)] {
+		&self.samples
+	}
+}
+
+impl Display for TableDescription {
+	fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+		writeln!(f, "table {}: {} entries", self.table, self.entries)?;
+
+		match &self.schema {
+			Some((SchemaSource::Registered, schema)) => writeln!(f, "schema: {schema}")?,
+			Some((SchemaSource::Inferred, schema)) => writeln!(f, "schema (inferred): {schema}")?,
+			None => f.write_str("schema: unknown\n")?,
+		}
+
+		if self.indexes.is_empty() {
+			f.write_str("indexes: none\n")?;
+		} else {
+			writeln!(f, "indexes: {}", self.indexes.join(", "))?;
+		}
+
+		if let Some(payload) = self.payload {
+			writeln!(
+				f,
+				"payload: {} writes, {} bytes on average, {} bytes at most",
+				payload.writes(),
+				payload.average_bytes(),
+				payload.max_bytes()
+			)?;
+		}
+
+		for (key, entry) in &self.samples {
+			writeln!(f, "  {key}: {entry}")?;
+		}
+
+		Ok(())
+	}
+}
+
+/// Reads everything but the payload stats, which are kept by the chart rather than the backend.
+pub(crate) async fn read<B: Backend>(
+	backend: &B,
+	table: &str,
+	limit: usize,
+) -> Result<TableDescription, B::Error> {
+	let mut keys = backend.get_keys::<Vec<String>>(table).await?;
+	keys.retain(|key| !util::is_metadata(key));
+	keys.sort_unstable();
+
+	let mut sampled = Vec::with_capacity(limit.min(keys.len()));
+	for key in keys.iter().take(limit) {
+		if let Some(entry) = backend.get::<Value>(table, key).await? {
+			sampled.push((key.clone(), entry));
+		}
+	}
+
+	#[cfg(feature = "metadata")]
+	let registered = backend
+		.get::<SchemaValue>(table, crate::METADATA_KEY)
+		.await?;
+	#[cfg(not(feature = "metadata"))]
+	let registered = None;
+
+	let schema = registered
+		.map(|schema| (SchemaSource::Registered, schema))
+		.or_else(|| infer(&sampled).map(|schema| (SchemaSource::Inferred, schema)));
+
+	Ok(TableDescription {
+		table: table.to_owned(),
+		entries: keys.len(),
+		schema,
+		indexes: index::names(backend, table).await?,
+		payload: None,
+		samples: sampled,
+	})
+}
+
+fn infer(samples: &[(String, SchemaValue)]) -> Option<SchemaValue> {
+	let mut samples = samples.iter().map(|(_, entry)| entry);
+	let mut schema = samples.next()?.clone();
+
+	for entry in samples {
+		merge(&mut schema, entry);
+	}
+
+	Some(schema)
+}
+
+/// Adds fields missing from the schema, and fills in fields that were only seen as null.
+fn merge(schema: &mut SchemaValue, value: &SchemaValue) {
+	match (schema, value) {
+		(Value::Object(schema), Value::Object(value)) => {
+			for (name, field) in value {
+				match schema.get_mut(name) {
+					Some(existing) => merge(existing, field),
+					None => {
+						schema.insert(name.clone(), field.clone());
+					}
+				}
+			}
+		}
+		(schema @ Value::Null, value) => *schema = value.clone(),
+		_ => {}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use serde::{Deserialize, Serialize};
+	use serde_json::json;
+
+	use super::infer;
+	use crate::{
+		action::CreateEntryAction,
+		backend::{Backend, MockBackend},
+		error::ErrorType,
+		stats::PayloadStats,
+		Error, IndexEntry, Starchart,
+	};
+
+	#[test]
+	fn inference() {
+		let samples = vec![
+			("1".to_owned(), json!({ "id": 1, "name": null })),
+			(
+				"2".to_owned(),
+				json!({ "id": 2, "name": "two", "tags": [] }),
+			),
+		];
+
+		assert_eq!(
+			infer(&samples),
+			Some(json!({ "id": 1, "name": "two", "tags": [] }))
+		);
+		assert_eq!(infer(&[]), None);
+	}
+
+	#[tokio::test]
+	async fn describe() -> Result<(), Error> {
+		#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+		struct User {
+			id: u32,
+			email: String,
+			nickname: Option<String>,
+		}
+
+		impl IndexEntry for User {
+			type Key = u32;
+
+			fn key(&self) -> &Self::Key {
+				&self.id
+			}
+
+			fn indexes(&self) -> Vec<(&'static str, String)> {
+				vec![("email", self.email.clone())]
+			}
+		}
+
+		let chart = Starchart::builder(MockBackend::new())
+			.payload_stats(true)
+			.build()
+			.await?;
+		chart.create_table("users").await?;
+
+		for id in 0..8 {
+			let user = User {
+				id,
+				email: format!("{id}@example.com"),
+				nickname: (id % 2 == 1).then(|| format!("user {id}")),
+			};
+
+			CreateEntryAction::for_table("users")
+				.with_entry(&user)
+				.run_create_entry(&chart)
+				.await?;
+		}
+
+		let description = chart.describe_sampled("users", 3).await?;
+		assert_eq!(description.table(), "users");
+		assert_eq!(description.entries(), 8);
+		assert_eq!(description.indexes(), ["email"]);
+		assert_eq!(description.payload().map(PayloadStats::writes), Some(8));
+		assert_eq!(
+			description
+				.samples()
+				.iter()
+				.map(|(key, _)| key.as_str())
+				.collect::<Vec<_>>(),
+			["0", "1", "2"]
+		);
+		assert!(description
+			.schema()
+			.is_some_and(|schema| schema.get("nickname").is_some()));
+		assert!(description
+			.to_string()
+			.starts_with("table users: 8 entries\n"));
+
+		let missing = chart.describe("missing").await.unwrap_err();
+		assert!(matches!(missing.kind(), ErrorType::TableMissing));
+
+		Ok(())
+	}
+}
@@ -1,4 +1,4 @@
-use std::fmt::Debug;
+use std::{fmt::Debug, ptr, str::FromStr, sync::Arc};
 
 use serde::{de::DeserializeOwned, Serialize};
 
@@ -14,6 +14,50 @@ impl<T: ToString> Key for T {
 	}
 }
 
+/// Encodes the key of an entry, implemented by the [`IndexEntry`] derive for keys set with
+/// `#[key(with = "...")]`, `#[key(format = "...")]` or `#[key(path = "...")]`.
+#[doc(hidden)]
+pub trait KeyEncoder {
+	fn encode_key(&self) -> String;
+}
+
+/// An entry used as it's own [`Key`], encoded with it's [`KeyEncoder`].
+///
+/// This lets the [`IndexEntry`] derive return a reference to the entry as it's [`Key`], without
+/// generating any unsafe code or naming the type of the key field.
+#[doc(hidden)]
+#[repr(transparent)]
+pub struct EncodedKey<E>(E);
+
+impl<E: KeyEncoder> EncodedKey<E> {
+	pub const fn new(entry: &E) -> &Self {
+		// SAFETY: the struct is a transparent wrapper around the entry.
+		unsafe { &*ptr::from_ref(entry).cast::<Self>() }
+	}
+}
+
+impl<E: KeyEncoder> Key for EncodedKey<E> {
+	fn to_key(&self) -> String {
+		self.0.encode_key()
+	}
+}
+
+/// The inverse of [`Key`], parsing a key back into the type it was created from.
+///
+/// This is used to collect tables into maps with typed keys, see [`ReadTableAction::run_read_map`].
+///
+/// [`ReadTableAction::run_read_map`]: crate::action::ReadTableAction::run_read_map
+pub trait FromKey: Sized {
+	/// Parses a key returned from [`Key::to_key`], returning [`None`] if it isn't a valid key for this type.
+	fn from_key(key: &str) -> Option<Self>;
+}
+
+impl<T: FromStr> FromKey for T {
+	fn from_key(key: &str) -> Option<Self> {
+		key.parse().ok()
+	}
+}
+
 /// A marker trait for use within the [`Starchart`].
 ///
 /// This signifies that the type can be stored within a [`Starchart`].
@@ -30,16 +74,66 @@ pub trait IndexEntry: Entry {
 
 	/// Returns the valid key for the database to index from.
 	fn key(&self) -> &Self::Key;
+
+	/// Returns the secondary indexes of this entry, as pairs of index name and value.
+	///
+	/// Actions that set the entry with [`Action::set_entry`] keep the index tables up to date,
+	/// which are used by [`Starchart::find_by_index`]. Deleted entries are removed from the index tables
+	/// whether or not their type is known. The default impl has no secondary indexes.
+	///
+	/// Index names can't be empty or contain `__`, indexes with such names aren't kept.
+	///
+	/// [`Action::set_entry`]: crate::Action::set_entry
+	/// [`Starchart::find_by_index`]: crate::Starchart::find_by_index
+	fn indexes(&self) -> Vec<(&'static str, String)> {
+		Vec::new()
+	}
+}
+
+impl<T: IndexEntry> IndexEntry for Box<T> {
+	type Key = T::Key;
+
+	fn key(&self) -> &Self::Key {
+		(**self).key()
+	}
+
+	fn indexes(&self) -> Vec<(&'static str, String)> {
+		(**self).indexes()
+	}
+}
+
+impl<T: IndexEntry> IndexEntry for Arc<T> {
+	type Key = T::Key;
+
+	fn key(&self) -> &Self::Key {
+		(**self).key()
+	}
+
+	fn indexes(&self) -> Vec<(&'static str, String)> {
+		(**self).indexes()
+	}
+}
+
+/// Pairs are keyed by their first element, for values that don't hold their own key.
+impl<K: Key + Entry, V: Entry> IndexEntry for (K, V) {
+	type Key = K;
+
+	fn key(&self) -> &Self::Key {
+		&self.0
+	}
 }
 
 #[cfg(test)]
 mod tests {
-	use std::fmt::{Debug, Display, Formatter, Result as FmtResult};
+	use std::{
+		fmt::{Debug, Display, Formatter, Result as FmtResult},
+		sync::Arc,
+	};
 
 	use serde::{de::DeserializeOwned, Deserialize, Serialize};
 	use static_assertions::assert_impl_all;
 
-	use super::{Entry, Key};
+	use super::{Entry, IndexEntry, Key};
 
 	#[derive(Debug, Default, Clone, PartialEq, Serialize, Deserialize)]
 	struct Settings {
@@ -67,6 +161,41 @@ mod tests {
 		Serialize
 	);
 
+	#[derive(Debug, Default, Clone, PartialEq, Serialize, Deserialize)]
+	struct Indexed {
+		id: u32,
+		name: String,
+	}
+
+	impl IndexEntry for Indexed {
+		type Key = u32;
+
+		fn key(&self) -> &Self::Key {
+			&self.id
+		}
+
+		fn indexes(&self) -> Vec<(&'static str, String)> {
+			vec![("name", self.name.clone())]
+		}
+	}
+
+	assert_impl_all!(Box<Indexed>: IndexEntry);
+	assert_impl_all!(Arc<Indexed>: IndexEntry);
+	assert_impl_all!((String, Settings): IndexEntry);
+
+	#[test]
+	fn wrapper_keys() {
+		let indexed = Indexed {
+			id: 7,
+			name: "name".to_owned(),
+		};
+
+		assert_eq!(Box::new(indexed.clone()).key().to_key(), "7");
+		assert_eq!(Arc::new(indexed.clone()).indexes(), indexed.indexes());
+		assert_eq!(("key".to_owned(), Settings::default()).key(), "key");
+		assert!((1_u64, indexed).indexes().is_empty());
+	}
+
 	#[test]
 	fn to_key() {
 		let keyable = Keyable {
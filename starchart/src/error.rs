@@ -2,7 +2,7 @@
 
 use std::{
 	error::Error as StdError,
-	fmt::{Display, Formatter, Result as FmtResult},
+	fmt::{Debug, Display, Formatter, Result as FmtResult},
 };
 
 #[doc(inline)]
@@ -10,6 +10,10 @@ pub use crate::action::{
 	ActionError, ActionErrorType, ActionRunError, ActionRunErrorType, ActionValidationError,
 	ActionValidationErrorType,
 };
+use crate::{
+	backend::{corrupted, timed_out},
+	transaction::CommitError,
+};
 
 // NOTE: This error shouldn't be used anywhere inside this crate, it's only meant for end users as an ease of use
 // error struct.
@@ -40,12 +44,219 @@ impl Error {
 		(self.kind, self.source)
 	}
 
+	/// The [`ErrorCategory`] of the error, for branching on it without matching every type.
+	///
+	/// Errors running actions are categorized by the [`ActionRunError`] they failed with.
+	#[must_use]
+	pub fn category(&self) -> ErrorCategory {
+		match &self.kind {
+			ErrorType::Backend | ErrorType::RollbackFailed => ErrorCategory::Backend,
+			ErrorType::Timeout => ErrorCategory::Timeout,
+			ErrorType::ActionRun => self
+				.source
+				.as_deref()
+				.and_then(|source| source.downcast_ref::<ActionRunError>())
+				.map_or(ErrorCategory::Backend, ActionRunError::category),
+			ErrorType::ActionValidation
+			| ErrorType::ActionRejected
+			| ErrorType::InvalidNamespace
+			| ErrorType::InvalidIndex
+			| ErrorType::InvalidPageToken
+			| ErrorType::InvalidCounter { .. }
+			| ErrorType::Untyped
+			| ErrorType::JournalDisabled => ErrorCategory::Invalid,
+			ErrorType::TableMissing
+			| ErrorType::EntryMissing
+			| ErrorType::NotRetained
+			| ErrorType::JournalTruncated => ErrorCategory::NotFound,
+			ErrorType::CorruptEntry | ErrorType::Serialization => ErrorCategory::Serialization,
+			ErrorType::TableExists | ErrorType::MigrationMismatch { .. } => ErrorCategory::Conflict,
+			ErrorType::Io => ErrorCategory::Io,
+		}
+	}
+
+	/// Whether the error is an [`ErrorCategory::NotFound`] error.
+	#[must_use]
+	pub fn is_not_found(&self) -> bool {
+		self.category() == ErrorCategory::NotFound
+	}
+
+	/// Whether the error is an [`ErrorCategory::Conflict`] error.
+	#[must_use]
+	pub fn is_conflict(&self) -> bool {
+		self.category() == ErrorCategory::Conflict
+	}
+
+	/// Whether the error is an [`ErrorCategory::Invalid`] error.
+	#[must_use]
+	pub fn is_invalid(&self) -> bool {
+		self.category() == ErrorCategory::Invalid
+	}
+
+	/// Whether the error is an [`ErrorCategory::Serialization`] error.
+	#[must_use]
+	pub fn is_serialization(&self) -> bool {
+		self.category() == ErrorCategory::Serialization
+	}
+
+	/// Whether the error is an [`ErrorCategory::Io`] error.
+	#[must_use]
+	pub fn is_io(&self) -> bool {
+		self.category() == ErrorCategory::Io
+	}
+
+	/// Whether the error is an [`ErrorCategory::Backend`] error.
+	#[must_use]
+	pub fn is_backend(&self) -> bool {
+		self.category() == ErrorCategory::Backend
+	}
+
+	/// Whether the error is an [`ErrorCategory::Timeout`] error.
+	#[must_use]
+	pub fn is_timeout(&self) -> bool {
+		self.category() == ErrorCategory::Timeout
+	}
+
 	/// Creates a new error from a backend.
+	///
+	/// This is an [`ErrorType::Timeout`] if the error was caused by a [`TimeoutBackend`] giving up,
+	/// or an [`ErrorType::CorruptEntry`] if it was caused by a [`CorruptEntryError`].
+	///
+	/// [`CorruptEntryError`]: crate::backend::CorruptEntryError
+	/// [`TimeoutBackend`]: crate::backend::TimeoutBackend
 	#[must_use]
 	pub fn backend(e: Option<Box<dyn StdError + Send + Sync>>) -> Self {
+		let kind = match e.as_deref() {
+			Some(e) if timed_out(e) => ErrorType::Timeout,
+			Some(e) if corrupted(e) => ErrorType::CorruptEntry,
+			_ => ErrorType::Backend,
+		};
+
+		Self { source: e, kind }
+	}
+
+	/// Creates a new error from a failed [`TransactionalBackend::commit`].
+	///
+	/// This is an [`ErrorType::RollbackFailed`] error if the transaction couldn't be rolled back,
+	/// otherwise it's the same as [`Self::backend`] with the failed operation's error.
+	///
+	/// [`TransactionalBackend::commit`]: crate::transaction::TransactionalBackend::commit
+	pub(crate) fn commit<E: StdError + Send + Sync + 'static>(e: CommitError<E>) -> Self {
+		match e {
+			CommitError::Failed(e) => Self::backend(Some(Box::new(e))),
+			e @ CommitError::RollbackFailed { .. } => Self {
+				source: Some(Box::new(e)),
+				kind: ErrorType::RollbackFailed,
+			},
+		}
+	}
+
+	pub(crate) const fn table_missing() -> Self {
+		Self {
+			source: None,
+			kind: ErrorType::TableMissing,
+		}
+	}
+
+	pub(crate) const fn entry_missing() -> Self {
+		Self {
+			source: None,
+			kind: ErrorType::EntryMissing,
+		}
+	}
+
+	pub(crate) const fn table_exists() -> Self {
+		Self {
+			source: None,
+			kind: ErrorType::TableExists,
+		}
+	}
+
+	pub(crate) const fn invalid_namespace() -> Self {
+		Self {
+			source: None,
+			kind: ErrorType::InvalidNamespace,
+		}
+	}
+
+	pub(crate) fn io<E: StdError + Send + Sync + 'static>(e: E) -> Self {
+		Self {
+			source: Some(Box::new(e)),
+			kind: ErrorType::Io,
+		}
+	}
+
+	pub(crate) fn serialization<E: StdError + Send + Sync + 'static>(e: E) -> Self {
+		Self {
+			source: Some(Box::new(e)),
+			kind: ErrorType::Serialization,
+		}
+	}
+
+	/// Categorizes a JSON error, as [`serde_json`] reports failing to read or write along with invalid data.
+	pub(crate) fn json(e: serde_json::Error) -> Self {
+		if e.is_io() {
+			Self::io(e)
+		} else {
+			Self::serialization(e)
+		}
+	}
+
+	pub(crate) const fn not_retained() -> Self {
+		Self {
+			source: None,
+			kind: ErrorType::NotRetained,
+		}
+	}
+
+	pub(crate) const fn journal_truncated() -> Self {
+		Self {
+			source: None,
+			kind: ErrorType::JournalTruncated,
+		}
+	}
+
+	pub(crate) const fn journal_disabled() -> Self {
+		Self {
+			source: None,
+			kind: ErrorType::JournalDisabled,
+		}
+	}
+
+	pub(crate) const fn migration_mismatch(name: String) -> Self {
+		Self {
+			source: None,
+			kind: ErrorType::MigrationMismatch { name },
+		}
+	}
+
+	pub(crate) fn invalid_counter(field: &str) -> Self {
+		Self {
+			source: None,
+			kind: ErrorType::InvalidCounter {
+				field: field.to_owned(),
+			},
+		}
+	}
+
+	pub(crate) const fn invalid_index() -> Self {
+		Self {
+			source: None,
+			kind: ErrorType::InvalidIndex,
+		}
+	}
+
+	pub(crate) const fn invalid_page_token() -> Self {
+		Self {
+			source: None,
+			kind: ErrorType::InvalidPageToken,
+		}
+	}
+
+	pub(crate) const fn untyped() -> Self {
 		Self {
-			source: e,
-			kind: ErrorType::Backend,
+			source: None,
+			kind: ErrorType::Untyped,
 		}
 	}
 }
@@ -54,8 +265,40 @@ impl Display for Error {
 	fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
 		match &self.kind {
 			ErrorType::Backend => f.write_str("an error occurred within a backend"),
+			ErrorType::Timeout => f.write_str("a backend operation timed out"),
 			ErrorType::ActionRun => f.write_str("an error occurred running an action"),
 			ErrorType::ActionValidation => f.write_str("an action is invalid"),
+			ErrorType::ActionRejected => f.write_str("an action was rejected by a hook"),
+			ErrorType::TableMissing => f.write_str("the table does not exist"),
+			ErrorType::EntryMissing => f.write_str("the entry does not exist"),
+			ErrorType::CorruptEntry => f.write_str("an entry could not be decoded"),
+			ErrorType::TableExists => f.write_str("the table already exists"),
+			ErrorType::InvalidNamespace => f.write_str("the namespace name is invalid"),
+			ErrorType::InvalidIndex => f.write_str("the index name is invalid"),
+			ErrorType::InvalidPageToken => f.write_str("the page token is invalid for this table"),
+			ErrorType::Io => f.write_str("an error occurred reading or writing data"),
+			ErrorType::Serialization => f.write_str("data could not be serialized or deserialized"),
+			ErrorType::NotRetained => f.write_str("the entry was not retained at that point"),
+			ErrorType::JournalTruncated => {
+				f.write_str("the journal does not contain the requested records")
+			}
+			ErrorType::JournalDisabled => f.write_str("journaling is not enabled"),
+			ErrorType::MigrationMismatch { name } => {
+				f.write_str("the applied migration ")?;
+				Debug::fmt(name, f)?;
+				f.write_str(" does not match the migrator")
+			}
+			ErrorType::InvalidCounter { field } => {
+				f.write_str("the field ")?;
+				Debug::fmt(field, f)?;
+				f.write_str(" could not be incremented")
+			}
+			ErrorType::Untyped => {
+				f.write_str("the backend can't read entries without knowing their type")
+			}
+			ErrorType::RollbackFailed => {
+				f.write_str("a transaction failed and could not be rolled back")
+			}
 		}
 	}
 }
@@ -70,15 +313,31 @@ impl StdError for Error {
 
 impl From<ActionError> for Error {
 	fn from(e: ActionError) -> Self {
+		let e = match e.into_chart_error() {
+			Ok(error) => return error,
+			Err(e) => e,
+		};
+
 		let kind = match e.kind() {
 			ActionErrorType::Run => ErrorType::ActionRun,
 			ActionErrorType::Validation => ErrorType::ActionValidation,
+			ActionErrorType::Rejected => ErrorType::ActionRejected,
+			ActionErrorType::Timeout => ErrorType::Timeout,
 		};
-		Self {
-			// source will always be an ActionRunError or ActionValidationError
-			source: e.into_source(),
-			kind,
-		}
+
+		// source will always be an ActionRunError, ActionValidationError, or the hook's rejection
+		let source = e.into_source();
+
+		let kind = match source
+			.as_deref()
+			.and_then(|source| source.downcast_ref::<ActionRunError>())
+		{
+			Some(source) if source.is_table_missing() => ErrorType::TableMissing,
+			Some(source) if source.is_corrupt() => ErrorType::CorruptEntry,
+			_ => kind,
+		};
+
+		Self { source, kind }
 	}
 }
 
@@ -93,13 +352,76 @@ impl From<ActionValidationError> for Error {
 
 impl From<ActionRunError> for Error {
 	fn from(e: ActionRunError) -> Self {
+		let kind = if e.is_table_missing() {
+			ErrorType::TableMissing
+		} else if e.is_timeout() {
+			ErrorType::Timeout
+		} else if e.is_corrupt() {
+			ErrorType::CorruptEntry
+		} else {
+			ErrorType::ActionRun
+		};
+
 		Self {
 			source: Some(Box::new(e)),
-			kind: ErrorType::ActionRun,
+			kind,
+		}
+	}
+}
+
+/// The broad category of an error, shared by every error in the crate.
+///
+/// Categories and their [`codes`] are stable, so they can be stored, logged, or sent to clients
+/// and matched on later, new errors are sorted into the existing categories.
+///
+/// [`codes`]: Self::code
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum ErrorCategory {
+	/// Something that was asked for doesn't exist, such as a table or a retained version.
+	NotFound,
+	/// The operation clashed with the current state, such as an entry that already exists
+	/// or was modified since it was read.
+	Conflict,
+	/// The operation itself was invalid, such as an action missing it's table,
+	/// or one rejected by a [`Hook`].
+	///
+	/// [`Hook`]: crate::hook::Hook
+	Invalid,
+	/// A value couldn't be converted to or from the entry type.
+	Serialization,
+	/// Data couldn't be read or written.
+	Io,
+	/// An error occurred within a [`Backend`].
+	///
+	/// [`Backend`]: crate::backend::Backend
+	Backend,
+	/// An operation took too long.
+	Timeout,
+}
+
+impl ErrorCategory {
+	/// The stable code of the category, such as `not_found`.
+	#[must_use]
+	pub const fn code(self) -> &'static str {
+		match self {
+			Self::NotFound => "not_found",
+			Self::Conflict => "conflict",
+			Self::Invalid => "invalid",
+			Self::Serialization => "serialization",
+			Self::Io => "io",
+			Self::Backend => "backend",
+			Self::Timeout => "timeout",
 		}
 	}
 }
 
+impl Display for ErrorCategory {
+	fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+		f.write_str(self.code())
+	}
+}
+
 /// The type of [`Error`] that occurred.
 #[derive(Debug)]
 #[allow(missing_copy_implementations)]
@@ -107,8 +429,161 @@ impl From<ActionRunError> for Error {
 pub enum ErrorType {
 	/// An error occurred within a backend.
 	Backend,
+	/// A backend operation took too long and a [`TimeoutBackend`] gave up on it,
+	/// or an action's [`Action::lock_timeout`] passed while waiting for it's table.
+	///
+	/// [`TimeoutBackend`]: crate::backend::TimeoutBackend
+	/// [`Action::lock_timeout`]: crate::action::Action::lock_timeout
+	Timeout,
 	/// An [`ActionValidationError`] occurred.
 	ActionValidation,
 	/// An [`ActionRunError`] occurred.
 	ActionRun,
+	/// A [`Hook`] rejected an action, the source is the error it was rejected with.
+	///
+	/// [`Hook`]: crate::hook::Hook
+	ActionRejected,
+	/// An operation was ran on a table that doesn't exist.
+	///
+	/// This is raised the same way for every [`Backend`], while reading a missing entry
+	/// from an existing table returns [`None`].
+	///
+	/// [`Backend`]: crate::backend::Backend
+	TableMissing,
+	/// An operation that needs an existing entry, such as [`Starchart::increment`], was ran on
+	/// an entry that doesn't exist or has expired.
+	///
+	/// Reading a missing entry isn't an error, and returns [`None`] instead.
+	///
+	/// [`Starchart::increment`]: crate::Starchart::increment
+	EntryMissing,
+	/// An entry was read, but the [`Backend`] couldn't decode it into the entry type,
+	/// the source is the backend's error, caused by a [`CorruptEntryError`].
+	///
+	/// This is raised the same way for every [`Backend`], whether the stored data was damaged
+	/// or was written as a different type.
+	///
+	/// [`Backend`]: crate::backend::Backend
+	/// [`CorruptEntryError`]: crate::backend::CorruptEntryError
+	CorruptEntry,
+	/// A table was renamed with [`Starchart::rename_table`] to a name that's already taken.
+	///
+	/// [`Starchart::rename_table`]: crate::Starchart::rename_table
+	TableExists,
+	/// A namespace name was empty, contained the [`namespace::SEPARATOR`], or started with `__`.
+	///
+	/// [`namespace::SEPARATOR`]: crate::namespace::SEPARATOR
+	InvalidNamespace,
+	/// An index name passed to [`Starchart::find_by_index`] was empty or contained `__`.
+	///
+	/// [`Starchart::find_by_index`]: crate::Starchart::find_by_index
+	InvalidIndex,
+	/// A [`PageToken`] was malformed, or was created for a different table or chart.
+	///
+	/// [`PageToken`]: crate::pagination::PageToken
+	InvalidPageToken,
+	/// The input couldn't be read from, or the output couldn't be written to.
+	Io,
+	/// Entries or backups couldn't be serialized or deserialized, or a backup was invalid.
+	Serialization,
+	/// A field couldn't be incremented with [`Starchart::increment`].
+	///
+	/// This is raised if the field doesn't exist or isn't an integer, or if adding to it would overflow.
+	///
+	/// [`Starchart::increment`]: crate::Starchart::increment
+	InvalidCounter {
+		/// The path of the field that couldn't be incremented.
+		field: String,
+	},
+	/// A previous version of an entry was requested, but it isn't retained.
+	///
+	/// This is raised if neither [`StarchartBuilder::retain_versions`] nor [`StarchartBuilder::keep_history`]
+	/// apply to the table, or if the point is older than the kept revisions.
+	///
+	/// [`StarchartBuilder::retain_versions`]: crate::StarchartBuilder::retain_versions
+	/// [`StarchartBuilder::keep_history`]: crate::StarchartBuilder::keep_history
+	NotRetained,
+	/// Journal records were requested, but they were acknowledged by every consumer and deleted.
+	JournalTruncated,
+	/// The journal was read or acknowledged, but [`StarchartBuilder::journal`] isn't set.
+	///
+	/// [`StarchartBuilder::journal`]: crate::StarchartBuilder::journal
+	JournalDisabled,
+	/// A migration that was already applied doesn't match the [`Migrator`].
+	///
+	/// This is raised if the migration at it's position in the migrator has a different name or
+	/// checksum, or if the migrator doesn't have that many migrations.
+	///
+	/// [`Migrator`]: crate::migration::Migrator
+	MigrationMismatch {
+		/// The name the applied migration was recorded under.
+		name: String,
+	},
+	/// An operation that reads or writes entries without knowing their type, such as [`Starchart::backup`],
+	/// was ran on a [`Backend`] that isn't [self-describing].
+	///
+	/// [`Backend`]: crate::backend::Backend
+	/// [self-describing]: crate::backend::Backend::is_self_describing
+	/// [`Starchart::backup`]: crate::Starchart::backup
+	Untyped,
+	/// A [`Starchart::transaction`] failed part way through committing, and restoring the entries it had
+	/// already written failed as well, so some of it's writes may have been kept.
+	///
+	/// The source is a [`CommitError::RollbackFailed`], holding both errors.
+	///
+	/// [`Starchart::transaction`]: crate::Starchart::transaction
+	RollbackFailed,
+}
+
+#[cfg(test)]
+mod tests {
+	use super::ErrorCategory;
+	use crate::{
+		action::{CreateTableAction, ReadEntryAction},
+		backend::MockBackend,
+		testing::TestSettings,
+		Error, Starchart,
+	};
+
+	#[tokio::test]
+	async fn error_categories() -> Result<(), Error> {
+		let chart = Starchart::new(MockBackend::new()).await?;
+
+		let mut action = ReadEntryAction::<TestSettings>::new();
+		action.set_table("missing").set_key(&1_u32);
+		let error = action.run_read_entry(&chart).await.unwrap_err();
+		assert!(error.is_not_found());
+		assert_eq!(error.category().code(), "not_found");
+
+		let error = Error::from(error);
+		assert!(error.is_not_found());
+		assert_eq!(error.category(), ErrorCategory::NotFound);
+
+		let error = CreateTableAction::<TestSettings>::new()
+			.run_create_table(&chart)
+			.await
+			.unwrap_err();
+		assert!(error.is_invalid());
+		assert!(Error::from(error).is_invalid());
+
+		for table in ["first", "second"] {
+			let mut action = CreateTableAction::<TestSettings>::new();
+			action.set_table(table);
+			action.run_create_table(&chart).await?;
+		}
+		let error = chart.rename_table("first", "second").await.unwrap_err();
+		assert!(error.is_conflict());
+		assert_eq!(error.category().to_string(), "conflict");
+
+		let error = chart.restore(&b"not json\n"[..], 1).await.unwrap_err();
+		assert!(error.is_serialization());
+
+		let error = chart
+			.dump_pretty::<TestSettings, _>("first", &mut [0_u8; 0][..])
+			.await
+			.unwrap_err();
+		assert!(error.is_io());
+
+		Ok(())
+	}
 }
@@ -0,0 +1,399 @@
+//! Maintenance of the backend tables that record when entries expire.
+
+use std::{
+	convert::TryFrom,
+	time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use crate::backend::Backend;
+
+const EXPIRY_PREFIX: &str = "__expiry__";
+
+/// Returns the name of the table recording expiry times for the given table.
+pub fn table_name(table: &str) -> String {
+	[EXPIRY_PREFIX, table].concat()
+}
+
+/// Sets the time the entry at `key` expires.
+///
+/// Backends that expire entries natively are given the chance to handle it first,
+/// otherwise the time is recorded in the expiry table.
+pub async fn set<B: Backend>(
+	backend: &B,
+	table: &str,
+	key: &str,
+	expires_at: SystemTime,
+) -> Result<(), B::Error> {
+	if backend.expire(table, key, expires_at).await? {
+		return Ok(());
+	}
+
+	let expiry_table = table_name(table);
+	let millis = to_millis(expires_at);
+
+	backend.ensure_table(&expiry_table).await?;
+
+	if backend.has(&expiry_table, key).await? {
+		backend.update(&expiry_table, key, &millis).await
+	} else {
+		backend.create(&expiry_table, key, &millis).await
+	}
+}
+
+/// Returns whether the entry at `key` has a recorded expiry time that has passed.
+pub async fn is_expired<B: Backend>(
+	backend: &B,
+	table: &str,
+	key: &str,
+	now: SystemTime,
+) -> Result<bool, B::Error> {
+	let expiry_table = table_name(table);
+
+	if !backend.has_table(&expiry_table).await? {
+		return Ok(false);
+	}
+
+	Ok(backend
+		.get::<u64>(&expiry_table, key)
+		.await?
+		.is_some_and(|millis| millis <= to_millis(now)))
+}
+
+/// Returns the keys of every entry whose recorded expiry time has passed.
+pub async fn expired<B: Backend>(
+	backend: &B,
+	table: &str,
+	now: SystemTime,
+) -> Result<Vec<String>, B::Error> {
+	let expiry_table = table_name(table);
+
+	if !backend.has_table(&expiry_table).await? {
+		return Ok(Vec::new());
+	}
+
+	let now = to_millis(now);
+	let mut expired = Vec::new();
+
+	for key in backend.get_keys::<Vec<String>>(&expiry_table).await? {
+		if backend
+			.get::<u64>(&expiry_table, &key)
+			.await?
+			.is_some_and(|millis| millis <= now)
+		{
+			expired.push(key);
+		}
+	}
+
+	Ok(expired)
+}
+
+/// Forgets the expiry time of the entry at `key`.
+pub async fn clear<B: Backend>(backend: &B, table: &str, key: &str) -> Result<(), B::Error> {
+	let expiry_table = table_name(table);
+
+	if backend.has_table(&expiry_table).await? {
+		backend.delete(&expiry_table, key).await?;
+	}
+
+	Ok(())
+}
+
+/// Forgets the expiry times of every entry in a deleted table.
+pub async fn clear_table<B: Backend>(backend: &B, table: &str) -> Result<(), B::Error> {
+	let expiry_table = table_name(table);
+
+	if backend.has_table(&expiry_table).await? {
+		backend.delete_table(&expiry_table).await?;
+	}
+
+	Ok(())
+}
+
+/// Moves the expiry times of a renamed table's entries to it's new name.
+pub async fn rename_table<B: Backend>(backend: &B, table: &str, new: &str) -> Result<(), B::Error> {
+	clear_table(backend, new).await?;
+
+	let expiry_table = table_name(table);
+
+	if backend.has_table(&expiry_table).await? {
+		backend
+			.rename_table(&expiry_table, &table_name(new))
+			.await?;
+	}
+
+	Ok(())
+}
+
+pub fn to_millis(time: SystemTime) -> u64 {
+	let since_epoch = time.duration_since(UNIX_EPOCH).unwrap_or(Duration::ZERO);
+
+	u64::try_from(since_epoch.as_millis()).unwrap_or(u64::MAX)
+}
+
+pub fn from_millis(millis: u64) -> SystemTime {
+	UNIX_EPOCH + Duration::from_millis(millis)
+}
+
+#[cfg(test)]
+mod tests {
+	use std::{
+		collections::BTreeMap,
+		mem,
+		sync::{Arc, Mutex},
+		time::{Duration, UNIX_EPOCH},
+	};
+
+	use futures_util::{future::join_all, TryStreamExt};
+
+	use super::{from_millis, table_name, to_millis};
+	use crate::{
+		action::{CreateEntryAction, CreateMode, ReadEntryAction, ReadTableAction},
+		backend::{Backend, MockBackend},
+		clock::ManualClock,
+		spawner::FnSpawner,
+		testing::TestSettings,
+		Error, Starchart,
+	};
+
+	#[test]
+	fn naming_and_millis() {
+		assert_eq!(table_name("table"), "__expiry__table");
+
+		assert_eq!(to_millis(UNIX_EPOCH), 0);
+		assert_eq!(to_millis(UNIX_EPOCH + Duration::from_millis(1500)), 1500);
+		assert_eq!(from_millis(1500), UNIX_EPOCH + Duration::from_millis(1500));
+	}
+
+	#[tokio::test]
+	async fn expiry() -> Result<(), Error> {
+		let clock = ManualClock::default();
+		let chart = Starchart::builder(MockBackend::new())
+			.clock(clock.clone())
+			.build()
+			.await?;
+		chart.create_table("table").await?;
+
+		for id in 1..=3 {
+			let settings = TestSettings {
+				id,
+				..TestSettings::default()
+			};
+
+			let mut action = CreateEntryAction::new();
+			action
+				.set_table("table")
+				.set_entry(&settings)
+				.set_ttl(Duration::from_secs(10));
+			action.run_create_entry(&chart).await?;
+		}
+
+		chart.pin("table", &3);
+
+		let read = |id: u32| {
+			let mut action = ReadEntryAction::<TestSettings>::new();
+			action.set_table("table").set_key(&id);
+			action.run_read_entry(&chart)
+		};
+
+		clock.advance(Duration::from_secs(5));
+		assert!(read(1).await?.is_some());
+
+		clock.advance(Duration::from_secs(6));
+		assert!(read(1).await?.is_none());
+		assert!(!chart.has("table", "1").await?);
+
+		assert_eq!(chart.purge_expired("table").await?, 1);
+		assert!(!chart.has("table", "2").await?);
+		assert!(read(3).await?.is_some());
+
+		Ok(())
+	}
+
+	#[tokio::test]
+	async fn expired_table_reads() -> Result<(), Error> {
+		let clock = ManualClock::default();
+		let chart = Starchart::builder(MockBackend::new())
+			.clock(clock.clone())
+			.build()
+			.await?;
+		chart.create_table("table").await?;
+
+		for id in 1..=3 {
+			let settings = TestSettings {
+				id,
+				..TestSettings::default()
+			};
+
+			let mut action = CreateEntryAction::new();
+			action.set_table("table").set_entry(&settings);
+			if id != 3 {
+				action.set_ttl(Duration::from_secs(10));
+			}
+			action.run_create_entry(&chart).await?;
+		}
+
+		chart.pin("table", &2);
+		clock.advance(Duration::from_secs(11));
+
+		let ids = |entries: Vec<TestSettings>| {
+			let mut ids = entries.iter().map(|entry| entry.id).collect::<Vec<_>>();
+			ids.sort_unstable();
+			ids
+		};
+
+		let read = ReadTableAction::<TestSettings>::for_table("table");
+		assert_eq!(ids(read.clone().run_read_table(&chart).await?), [2, 3]);
+
+		// the expired entry was purged by the read.
+		assert!(!chart.has("table", "1").await?);
+
+		let settings = TestSettings::default();
+		let mut action = CreateEntryAction::new();
+		action
+			.set_table("table")
+			.set_entry(&settings)
+			.set_ttl(Duration::from_secs(10));
+		action.run_create_entry(&chart).await?;
+		clock.advance(Duration::from_secs(11));
+
+		let mut limited = read.clone();
+		limited.limit(5);
+		assert_eq!(ids(limited.run_read_table(&chart).await?), [2, 3]);
+
+		let stream = read
+			.clone()
+			.run_stream_table(&chart)
+			.map_ok(|(_, entry)| entry)
+			.try_collect()
+			.await?;
+		assert_eq!(ids(stream), [2, 3]);
+
+		let lossy = read.clone().run_read_table_lossy(&chart).await?;
+		assert_eq!(lossy.entries().len(), 2);
+
+		let map: BTreeMap<u32, TestSettings> = read.clone().run_read_map(&chart).await?;
+		assert_eq!(map.keys().copied().collect::<Vec<_>>(), [2, 3]);
+
+		let mut scan = read.run_scan(&chart, 10)?;
+		let batch = scan.next_batch().await?.unwrap_or_default();
+		assert_eq!(
+			ids(batch.into_iter().map(|(_, entry)| entry).collect()),
+			[2, 3]
+		);
+
+		let page = chart.read_page::<TestSettings>("table", None, 10).await?;
+		assert_eq!(
+			ids(page
+				.into_entries()
+				.into_iter()
+				.map(|(_, entry)| entry)
+				.collect()),
+			[2, 3]
+		);
+
+		Ok(())
+	}
+
+	#[tokio::test]
+	async fn stale_expiry() -> Result<(), Error> {
+		let clock = ManualClock::default();
+		let chart = Starchart::builder(MockBackend::new())
+			.clock(clock.clone())
+			.build()
+			.await?;
+		chart.create_table("table").await?;
+
+		let settings = TestSettings::default();
+		let read = ReadEntryAction::<TestSettings>::for_table("table").with_key(&settings.id);
+		let create = |ttl: Option<Duration>, mode: CreateMode| {
+			let mut action = CreateEntryAction::new();
+			action.set_table("table").set_entry(&settings).mode(mode);
+			if let Some(ttl) = ttl {
+				action.set_ttl(ttl);
+			}
+			action.run_create_entry(&chart)
+		};
+		let ttl = Some(Duration::from_secs(10));
+
+		// an entry deleted in a transaction and created again without a TTL doesn't expire.
+		create(ttl, CreateMode::Skip).await?;
+		chart
+			.transaction(|tx| async move {
+				tx.delete::<TestSettings, _>("table", &1);
+
+				Ok::<_, Error>(())
+			})
+			.await?;
+		create(None, CreateMode::Skip).await?;
+		clock.advance(Duration::from_secs(20));
+		assert_eq!(
+			read.clone().run_read_entry(&chart).await?,
+			Some(settings.clone())
+		);
+
+		// nor does one replaced without a TTL, or created again after a raw delete.
+		chart.delete("table", "1").await?;
+		create(ttl, CreateMode::Skip).await?;
+		create(None, CreateMode::Replace).await?;
+		clock.advance(Duration::from_secs(20));
+		assert_eq!(
+			read.clone().run_read_entry(&chart).await?,
+			Some(settings.clone())
+		);
+
+		// nor one written behind.
+		chart.delete("table", "1").await?;
+		create(ttl, CreateMode::Skip).await?;
+		chart.write_behind("table", &1, settings.clone());
+		chart.flush().await?;
+		clock.advance(Duration::from_secs(20));
+		assert_eq!(
+			read.clone().run_read_entry(&chart).await?,
+			Some(settings.clone())
+		);
+
+		// an expired entry that wasn't purged yet is replaced when created again.
+		chart.delete("table", "1").await?;
+		create(ttl, CreateMode::Skip).await?;
+		clock.advance(Duration::from_secs(20));
+		create(None, CreateMode::Fail).await?;
+		assert_eq!(read.run_read_entry(&chart).await?, Some(settings.clone()));
+
+		Ok(())
+	}
+
+	#[tokio::test]
+	async fn purge_expired_in_background() -> Result<(), Error> {
+		let clock = ManualClock::default();
+		let spawned = Arc::new(Mutex::new(Vec::new()));
+		let queue = spawned.clone();
+		let chart = Starchart::builder(MockBackend::new())
+			.clock(clock.clone())
+			.spawner(FnSpawner::new(move |task| queue.lock().unwrap().push(task)))
+			.build()
+			.await?;
+		chart.create_table("table").await?;
+
+		let settings = TestSettings::default();
+		let mut action = CreateEntryAction::new();
+		action
+			.set_table("table")
+			.set_entry(&settings)
+			.set_ttl(Duration::from_secs(10));
+		action.run_create_entry(&chart).await?;
+
+		clock.advance(Duration::from_secs(20));
+		chart.purge_expired_in_background("table");
+
+		// the purge is handed to the spawner rather than ran in place.
+		assert!(chart.has("table", "1").await?);
+
+		let tasks = mem::take(&mut *spawned.lock().unwrap());
+		assert_eq!(tasks.len(), 1);
+		join_all(tasks).await;
+
+		assert!(!chart.has("table", "1").await?);
+
+		Ok(())
+	}
+}
@@ -0,0 +1,151 @@
+//! Typed state that other crates can attach to a [`Starchart`].
+//!
+//! [`Starchart`]: crate::Starchart
+
+use std::{
+	any::{Any, TypeId},
+	collections::HashMap,
+	fmt::{Debug, Formatter, Result as FmtResult},
+	sync::Arc,
+};
+
+use parking_lot::RwLock;
+
+type AnyExtension = Arc<dyn Any + Send + Sync>;
+
+/// A map holding at most one value of each type, retrieved with [`Starchart::extensions`].
+///
+/// This lets companion crates (such as metrics exporters or schedulers) keep their state on the chart itself,
+/// instead of requiring users to pass around a separate handle. Values are stored in an [`Arc`], so they can
+/// be held onto after being retrieved, and should use interior mutability if they need to change.
+///
+/// [`Starchart::extensions`]: crate::Starchart::extensions
+#[derive(Default)]
+pub struct Extensions(RwLock<HashMap<TypeId, AnyExtension>>);
+
+impl Extensions {
+	/// Creates an empty [`Extensions`] map.
+	#[must_use]
+	pub fn new() -> Self {
+		Self::default()
+	}
+
+	/// Inserts a value, returning the previous value of the same type, if any.
+	pub fn insert<T: Send + Sync + 'static>(&self, value: T) -> Option<Arc<T>> {
+		self.0
+			.write()
+			.insert(TypeId::of::<T>(), Arc::new(value))
+			.and_then(downcast)
+	}
+
+	/// Gets the value of the given type, if one was inserted.
+	#[must_use]
+	pub fn get<T: Send + Sync + 'static>(&self) -> Option<Arc<T>> {
+		self.0
+			.read()
+			.get(&TypeId::of::<T>())
+			.cloned()
+			.and_then(downcast)
+	}
+
+	/// Gets the value of the given type, inserting the value returned by the closure if there isn't one.
+	pub fn get_or_insert_with<T, F>(&self, f: F) -> Arc<T>
+	where
+		T: Send + Sync + 'static,
+		F: FnOnce() -> T,
+	{
+		if let Some(value) = self.get() {
+			return value;
+		}
+
+		let mut extensions = self.0.write();
+
+		if let Some(value) = extensions.get(&TypeId::of::<T>()).cloned() {
+			if let Some(value) = downcast(value) {
+				return value;
+			}
+		}
+
+		let value = Arc::new(f());
+		extensions.insert(TypeId::of::<T>(), value.clone());
+
+		value
+	}
+
+	/// Checks if a value of the given type was inserted.
+	#[must_use]
+	pub fn contains<T: Send + Sync + 'static>(&self) -> bool {
+		self.0.read().contains_key(&TypeId::of::<T>())
+	}
+
+	/// Removes the value of the given type, returning it if there was one.
+	pub fn remove<T: Send + Sync + 'static>(&self) -> Option<Arc<T>> {
+		self.0.write().remove(&TypeId::of::<T>()).and_then(downcast)
+	}
+
+	/// Returns the amount of values in the map.
+	#[must_use]
+	pub fn len(&self) -> usize {
+		self.0.read().len()
+	}
+
+	/// Checks if the map is empty.
+	#[must_use]
+	pub fn is_empty(&self) -> bool {
+		self.0.read().is_empty()
+	}
+}
+
+impl Debug for Extensions {
+	fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+		f.debug_struct("Extensions")
+			.field("len", &self.len())
+			.finish()
+	}
+}
+
+fn downcast<T: Send + Sync + 'static>(value: AnyExtension) -> Option<Arc<T>> {
+	value.downcast().ok()
+}
+
+#[cfg(test)]
+mod tests {
+	use std::sync::atomic::{AtomicUsize, Ordering};
+
+	use super::Extensions;
+
+	#[derive(Debug, PartialEq, Eq)]
+	struct Name(&'static str);
+
+	#[test]
+	fn typed_slots() {
+		let extensions = Extensions::new();
+		assert!(extensions.is_empty());
+
+		assert!(extensions.insert(Name("first")).is_none());
+		assert!(extensions.insert(5_u32).is_none());
+		assert_eq!(extensions.len(), 2);
+
+		assert_eq!(extensions.get::<Name>().as_deref(), Some(&Name("first")));
+		assert_eq!(
+			extensions.insert(Name("second")).as_deref(),
+			Some(&Name("first"))
+		);
+		assert_eq!(extensions.get::<u32>().as_deref(), Some(&5));
+		assert!(!extensions.contains::<u64>());
+
+		assert_eq!(extensions.remove::<u32>().as_deref(), Some(&5));
+		assert!(!extensions.contains::<u32>());
+	}
+
+	#[test]
+	fn get_or_insert_with() {
+		let extensions = Extensions::new();
+
+		let counter = extensions.get_or_insert_with(AtomicUsize::default);
+		counter.fetch_add(1, Ordering::SeqCst);
+
+		let counter = extensions.get_or_insert_with(|| AtomicUsize::new(10));
+		assert_eq!(counter.load(Ordering::SeqCst), 1);
+	}
+}
@@ -0,0 +1,404 @@
+//! Callbacks ran around every action on a [`Starchart`], for cross-cutting concerns such as audit logging,
+//! validation, or rate limiting.
+//!
+//! [`Hook`]s are registered with [`StarchartBuilder::hook`], and ran in the order they were registered.
+//! Every [`Hook::before_action`] is awaited before the action runs, and the first one to return an error
+//! rejects the action, failing it with [`ActionErrorType::Rejected`]. Once the action completes, or is rejected,
+//! every [`Hook::after_action`] is awaited with it's outcome.
+//!
+//! Operations on the chart itself, such as [`Starchart::modify`], [`Starchart::increment`],
+//! [`Starchart::rename_table`], [`Starchart::truncate_table`] and [`Starchart::import`], are ran through
+//! hooks as actions without entries, and [`Starchart::transaction`] runs every staged write through them
+//! as an action on it's entry, rejecting the whole transaction if any of them are rejected. Writes queued
+//! with [`Starchart::write_behind`] are ran through hooks as create actions once they're [flushed].
+//!
+//! The only entry points that aren't ran through hooks are:
+//!
+//! - [`Starchart::restore`], which replaces every table at once.
+//! - [`Starchart::purge_expired`] and [`Starchart::repair`], which only maintain entries that were already written.
+//! - [`ReadTableAction::run_stream_table`] and [`ReadTableAction::run_scan`], as they don't complete at a single point.
+//! - Reads that bypass actions, namely [`Starchart::find_by_index`], [`Starchart::read_page`],
+//!   [`Starchart::read_entry_at`], [`Starchart::history`], [`Starchart::describe`], [`Starchart::backup`],
+//!   [`Starchart::export`] and [`Starchart::dump_pretty`].
+//! - Calls made directly on the [`Backend`] the chart dereferences to.
+//!
+//! [`Starchart`]: crate::Starchart
+//! [`Starchart::modify`]: crate::Starchart::modify
+//! [`Starchart::increment`]: crate::Starchart::increment
+//! [`Starchart::rename_table`]: crate::Starchart::rename_table
+//! [`Starchart::truncate_table`]: crate::Starchart::truncate_table
+//! [`Starchart::import`]: crate::Starchart::import
+//! [`Starchart::transaction`]: crate::Starchart::transaction
+//! [`Starchart::restore`]: crate::Starchart::restore
+//! [flushed]: crate::Starchart::flush
+//! [`Starchart::write_behind`]: crate::Starchart::write_behind
+//! [`Starchart::purge_expired`]: crate::Starchart::purge_expired
+//! [`Starchart::repair`]: crate::Starchart::repair
+//! [`Starchart::find_by_index`]: crate::Starchart::find_by_index
+//! [`Starchart::read_page`]: crate::Starchart::read_page
+//! [`Starchart::read_entry_at`]: crate::Starchart::read_entry_at
+//! [`Starchart::history`]: crate::Starchart::history
+//! [`Starchart::describe`]: crate::Starchart::describe
+//! [`Starchart::backup`]: crate::Starchart::backup
+//! [`Starchart::export`]: crate::Starchart::export
+//! [`Starchart::dump_pretty`]: crate::Starchart::dump_pretty
+//! [`ReadTableAction::run_stream_table`]: crate::action::ReadTableAction::run_stream_table
+//! [`ReadTableAction::run_scan`]: crate::action::ReadTableAction::run_scan
+//! [`Backend`]: crate::backend::Backend
+//! [`StarchartBuilder::hook`]: crate::StarchartBuilder::hook
+//! [`ActionErrorType::Rejected`]: crate::action::ActionErrorType::Rejected
+
+use std::{
+	error::Error,
+	fmt::Debug,
+	future::{ready, Future},
+	pin::Pin,
+	sync::Arc,
+};
+
+use serde_json::Value;
+
+use crate::action::{ActionError, ActionKind, TargetKind};
+
+/// The future returned from [`Hook::before_action`] and [`Hook::after_action`].
+pub type HookFuture<'a, T = ()> = Pin<Box<dyn Future<Output = T> + Send + 'a>>;
+
+/// The error a [`Hook`] rejects an action with.
+pub type Rejection = Box<dyn Error + Send + Sync>;
+
+/// A callback ran around every action on a [`Starchart`].
+///
+/// Both methods default to doing nothing, so hooks only need to implement the ones they use.
+///
+/// [`Starchart`]: crate::Starchart
+pub trait Hook: Debug + Send + Sync {
+	/// Called before the action runs, returning an error rejects the action.
+	///
+	/// No lock is held while this runs, so the action's table may change before the action does.
+	fn before_action<'a>(
+		&'a self,
+		context: &'a HookContext<'_>,
+	) -> HookFuture<'a, Result<(), Rejection>> {
+		let _ = context;
+
+		Box::pin(ready(Ok(())))
+	}
+
+	/// Called once the action completes, with the error it failed with, if any.
+	///
+	/// This is also called for actions that were rejected, including by this hook.
+	fn after_action<'a>(
+		&'a self,
+		context: &'a HookContext<'_>,
+		error: Option<&'a ActionError>,
+	) -> HookFuture<'a> {
+		let _ = (context, error);
+
+		Box::pin(ready(()))
+	}
+}
+
+impl<H: Hook + ?Sized> Hook for Arc<H> {
+	fn before_action<'a>(
+		&'a self,
+		context: &'a HookContext<'_>,
+	) -> HookFuture<'a, Result<(), Rejection>> {
+		(**self).before_action(context)
+	}
+
+	fn after_action<'a>(
+		&'a self,
+		context: &'a HookContext<'_>,
+		error: Option<&'a ActionError>,
+	) -> HookFuture<'a> {
+		(**self).after_action(context, error)
+	}
+}
+
+/// The action a [`Hook`] is ran for.
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[must_use = "a hook context has no side effects"]
+pub struct HookContext<'a> {
+	kind: ActionKind,
+	target: TargetKind,
+	table: Option<&'a str>,
+	key: Option<&'a str>,
+	entries: &'a [Value],
+}
+
+impl<'a> HookContext<'a> {
+	/// The kind of action being ran.
+	pub const fn kind(&self) -> ActionKind {
+		self.kind
+	}
+
+	/// Whether the action runs on an entry or a whole table.
+	pub const fn target(&self) -> TargetKind {
+		self.target
+	}
+
+	/// The table the action runs on, this is only missing if the action will fail because a table wasn't set.
+	#[must_use]
+	pub const fn table(&self) -> Option<&'a str> {
+		self.table
+	}
+
+	/// The key of the entry the action runs on, if it runs on a single entry.
+	#[must_use]
+	pub const fn key(&self) -> Option<&'a str> {
+		self.key
+	}
+
+	/// The entries being written, as JSON.
+	///
+	/// This is empty for reads and deletes. Entries that can't be serialized are left out,
+	/// as writing them fails regardless.
+	#[must_use]
+	pub const fn entries(&self) -> &'a [Value] {
+		self.entries
+	}
+}
+
+/// The [`Hook`]s registered on a chart.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct Hooks(Arc<[Arc<dyn Hook>]>);
+
+impl Hooks {
+	pub fn new(hooks: Vec<Arc<dyn Hook>>) -> Self {
+		Self(hooks.into())
+	}
+
+	pub fn is_empty(&self) -> bool {
+		self.0.is_empty()
+	}
+
+	pub async fn before(&self, context: &HookContext<'_>) -> Result<(), ActionError> {
+		for hook in self.0.iter() {
+			hook.before_action(context)
+				.await
+				.map_err(ActionError::rejected)?;
+		}
+
+		Ok(())
+	}
+
+	pub async fn after(&self, context: &HookContext<'_>, error: Option<&ActionError>) {
+		for hook in self.0.iter() {
+			hook.after_action(context, error).await;
+		}
+	}
+}
+
+/// The owned parts of a [`HookContext`], only collected if the chart has hooks.
+#[derive(Debug, Default)]
+pub(crate) struct ContextParts {
+	key: Option<String>,
+	entries: Vec<Value>,
+}
+
+impl ContextParts {
+	pub fn new(key: Option<&str>, entries: Vec<Value>) -> Self {
+		Self {
+			key: key.map(ToOwned::to_owned),
+			entries,
+		}
+	}
+
+	pub fn context<'a>(
+		&'a self,
+		kind: ActionKind,
+		target: TargetKind,
+		table: Option<&'a str>,
+	) -> HookContext<'a> {
+		HookContext {
+			kind,
+			target,
+			table,
+			key: self.key.as_deref(),
+			entries: &self.entries,
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use std::sync::{Arc, Mutex};
+
+	use futures_util::future::ready;
+
+	use super::{Hook, HookContext, HookFuture, Rejection};
+	use crate::{
+		action::{
+			ActionError, ActionKind, CreateBatchAction, CreateEntryAction, CreateTableAction,
+			ReadEntryAction, UpdateEntryAction,
+		},
+		backend::{Backend, MockBackend},
+		error::ErrorType,
+		testing::TestSettings,
+		Error, Starchart,
+	};
+
+	#[derive(Debug, Default)]
+	struct AuditHook(Mutex<Vec<(ActionKind, Option<String>, bool)>>);
+
+	impl Hook for AuditHook {
+		fn after_action<'a>(
+			&'a self,
+			context: &'a HookContext<'_>,
+			error: Option<&'a ActionError>,
+		) -> HookFuture<'a> {
+			self.0.lock().unwrap().push((
+				context.kind(),
+				context.key().map(ToOwned::to_owned),
+				error.is_none(),
+			));
+
+			Box::pin(ready(()))
+		}
+	}
+
+	#[derive(Debug)]
+	struct RejectEmptyValues;
+
+	impl Hook for RejectEmptyValues {
+		fn before_action<'a>(
+			&'a self,
+			context: &'a HookContext<'_>,
+		) -> HookFuture<'a, Result<(), Rejection>> {
+			let empty = context
+				.entries()
+				.iter()
+				.any(|entry| entry["value"].as_str() == Some(""));
+
+			Box::pin(ready(if empty {
+				Err("entries must have a value".into())
+			} else {
+				Ok(())
+			}))
+		}
+	}
+
+	#[tokio::test]
+	async fn hooks() -> Result<(), Error> {
+		let audit = Arc::new(AuditHook::default());
+		let chart = Starchart::builder(MockBackend::new())
+			.hook(RejectEmptyValues)
+			.hook(audit.clone())
+			.build()
+			.await?;
+
+		let mut action = CreateTableAction::<TestSettings>::new();
+		action.set_table("table");
+		action.run_create_table(&chart).await?;
+
+		let mut settings = TestSettings::default();
+		let mut action = CreateEntryAction::new();
+		action.set_table("table").set_entry(&settings);
+		action.run_create_entry(&chart).await?;
+
+		settings.value = String::new();
+		let mut action = UpdateEntryAction::new();
+		action.set_table("table").set_entry(&settings);
+		let error = Error::from(action.run_update_entry(&chart).await.unwrap_err());
+		assert!(matches!(error.kind(), ErrorType::ActionRejected));
+		assert_eq!(
+			error.into_source().unwrap().to_string(),
+			"entries must have a value"
+		);
+
+		let mut action = CreateBatchAction::new();
+		action.set_table("table").add_entries(vec![&settings]);
+		assert!(action.run_create_many(&chart).await.is_err());
+
+		let mut action = ReadEntryAction::<TestSettings>::new();
+		action.set_table("table").set_key(&1_u32);
+		assert_eq!(
+			action.run_read_entry(&chart).await?,
+			Some(TestSettings::default())
+		);
+
+		assert_eq!(
+			*audit.0.lock().unwrap(),
+			[
+				(ActionKind::Create, None, true),
+				(ActionKind::Create, Some("1".to_owned()), true),
+				(ActionKind::Update, Some("1".to_owned()), false),
+				(ActionKind::Create, None, false),
+				(ActionKind::Read, Some("1".to_owned()), true),
+			]
+		);
+
+		Ok(())
+	}
+
+	#[derive(Debug)]
+	struct RejectUpdates;
+
+	impl Hook for RejectUpdates {
+		fn before_action<'a>(
+			&'a self,
+			context: &'a HookContext<'_>,
+		) -> HookFuture<'a, Result<(), Rejection>> {
+			Box::pin(ready(if context.kind() == ActionKind::Update {
+				Err("entries can't be updated".into())
+			} else {
+				Ok(())
+			}))
+		}
+	}
+
+	#[tokio::test]
+	async fn hooks_reject_chart_operations() -> Result<(), Error> {
+		let chart = Starchart::builder(MockBackend::new())
+			.hook(RejectEmptyValues)
+			.hook(RejectUpdates)
+			.build()
+			.await?;
+
+		chart.create_table("table").await?;
+		chart.create("table", "1", &TestSettings::default()).await?;
+
+		let error = chart
+			.modify("table", &1_u32, |settings: &mut TestSettings| {
+				settings.value = "modified".to_owned();
+			})
+			.await
+			.unwrap_err();
+		assert!(matches!(error.kind(), ErrorType::ActionRejected));
+
+		let error = chart
+			.transaction(|tx| async move {
+				tx.create(
+					"table",
+					&2,
+					TestSettings {
+						id: 2,
+						..TestSettings::default()
+					},
+				)
+				.create(
+					"table",
+					&3,
+					TestSettings {
+						id: 3,
+						value: String::new(),
+						..TestSettings::default()
+					},
+				);
+
+				Ok::<_, Error>(())
+			})
+			.await
+			.unwrap_err();
+		assert!(matches!(error.kind(), ErrorType::ActionRejected));
+
+		assert_eq!(
+			chart.get::<TestSettings>("table", "1").await?,
+			Some(TestSettings::default())
+		);
+		assert!(!chart.has("table", "2").await?);
+		assert!(!chart.has("table", "3").await?);
+
+		Ok(())
+	}
+}
@@ -0,0 +1,444 @@
+//! Maintenance of the backend tables that back secondary indexes.
+//!
+//! Each index is a table mapping index values to the keys of the entries with them. Every indexed table also
+//! keeps a table mapping each key to it's index values, so an entry's index rows can be removed without knowing
+//! it's type, such as when it's deleted by an action without [`Action::set_entry`], purged after expiring,
+//! or deleted in a transaction.
+//!
+//! [`Action::set_entry`]: crate::Action::set_entry
+
+use std::collections::{BTreeSet, HashMap};
+
+use parking_lot::Mutex;
+
+use crate::{backend::Backend, Starchart};
+
+const INDEX_PREFIX: &str = "__index__";
+
+const REGISTRY_PREFIX: &str = "__indexes__";
+
+const KEYS_PREFIX: &str = "__index_keys__";
+
+/// The secondary index values of an entry, as pairs of index name and value.
+pub type Indexes = Vec<(&'static str, String)>;
+
+/// The index tables of a chart known to exist or not, so the backend is only asked once per table.
+///
+/// The tables are only created and deleted under their table's exclusive lock, so nothing else changes them.
+#[derive(Debug, Default)]
+pub struct IndexTables {
+	tables: Mutex<HashMap<String, bool>>,
+}
+
+impl IndexTables {
+	async fn exists<B: Backend>(&self, backend: &B, name: &str) -> Result<bool, B::Error> {
+		let cached = self.tables.lock().get(name).copied();
+
+		if let Some(exists) = cached {
+			return Ok(exists);
+		}
+
+		let exists = backend.has_table(name).await?;
+		self.tables.lock().insert(name.to_owned(), exists);
+
+		Ok(exists)
+	}
+
+	/// Creates the table if it doesn't exist, returning whether it was created.
+	async fn ensure<B: Backend>(&self, backend: &B, name: &str) -> Result<bool, B::Error> {
+		if self.exists(backend, name).await? {
+			return Ok(false);
+		}
+
+		backend.ensure_table(name).await?;
+		self.tables.lock().insert(name.to_owned(), true);
+
+		Ok(true)
+	}
+
+	fn forget(&self) {
+		self.tables.lock().clear();
+	}
+}
+
+/// Returns the name of the table backing the given index.
+///
+/// The table's name is prefixed with it's length, so it can't run into the index's name
+/// when either of them contain `__`.
+pub fn table_name(table: &str, index: &str) -> String {
+	format!("{INDEX_PREFIX}{}_{table}__{index}", table.len())
+}
+
+/// Checks whether the name can be used as an index.
+///
+/// Index names can't be empty, and can't contain `__`, which separates them from their table's name.
+pub fn is_valid(name: &str) -> bool {
+	!name.is_empty() && !name.contains("__")
+}
+
+/// Returns the names of the indexes of the table, which are registered when their index table is created.
+pub async fn names<B: Backend>(backend: &B, table: &str) -> Result<Vec<String>, B::Error> {
+	let registry = registry_name(table);
+
+	if !backend.has_table(&registry).await? {
+		return Ok(Vec::new());
+	}
+
+	let mut names = backend.get_keys::<Vec<_>>(&registry).await?;
+	names.sort_unstable();
+
+	Ok(names)
+}
+
+/// The registry of a table's indexes is it's own table, so it's only ever written under the table's lock.
+fn registry_name(table: &str) -> String {
+	[REGISTRY_PREFIX, table].concat()
+}
+
+fn keys_name(table: &str) -> String {
+	[KEYS_PREFIX, table].concat()
+}
+
+/// Forgets every index of a table, along with it's registry.
+pub async fn clear_table<B: Backend>(chart: &Starchart<B>, table: &str) -> Result<(), B::Error> {
+	let backend = &**chart;
+
+	chart.index_tables.forget();
+
+	for index in names(backend, table).await? {
+		let index_table = table_name(table, &index);
+
+		if backend.has_table(&index_table).await? {
+			backend.delete_table(&index_table).await?;
+		}
+	}
+
+	for name in [registry_name(table), keys_name(table)] {
+		if backend.has_table(&name).await? {
+			backend.delete_table(&name).await?;
+		}
+	}
+
+	Ok(())
+}
+
+/// Moves every index of a renamed table to it's new name, registering them under the new name.
+pub async fn rename_table<B: Backend>(
+	chart: &Starchart<B>,
+	table: &str,
+	new: &str,
+) -> Result<(), B::Error> {
+	let backend = &**chart;
+
+	clear_table(chart, new).await?;
+
+	let keys = keys_name(table);
+
+	if backend.has_table(&keys).await? {
+		backend.rename_table(&keys, &keys_name(new)).await?;
+	}
+
+	let registry = registry_name(table);
+
+	if !backend.has_table(&registry).await? {
+		return Ok(());
+	}
+
+	let renamed_registry = registry_name(new);
+	backend.create_table(&renamed_registry).await?;
+
+	for index in names(backend, table).await? {
+		let index_table = table_name(table, &index);
+		let renamed = table_name(new, &index);
+
+		if backend.has_table(&index_table).await? {
+			backend.rename_table(&index_table, &renamed).await?;
+		}
+
+		backend.create(&renamed_registry, &index, &renamed).await?;
+	}
+
+	backend.delete_table(&registry).await
+}
+
+/// Updates the index tables for the entry at `key`, going from the `previous` index values to the `current` ones.
+///
+/// The index values recorded for the key are removed as well, so `previous` only needs to be given
+/// for entries indexed before they were recorded.
+pub async fn update<B: Backend>(
+	chart: &Starchart<B>,
+	table: &str,
+	key: &str,
+	previous: &[(&'static str, String)],
+	current: &[(&'static str, String)],
+) -> Result<(), B::Error> {
+	let backend = &**chart;
+	let tables = &chart.index_tables;
+	let keys_table = keys_name(table);
+
+	let recorded = if tables.exists(backend, &keys_table).await? {
+		backend
+			.get::<Vec<(String, String)>>(&keys_table, key)
+			.await?
+	} else {
+		None
+	};
+
+	let current = current
+		.iter()
+		.filter(|(index, _)| is_valid(index))
+		.map(|(index, value)| ((*index).to_owned(), value.clone()))
+		.collect::<BTreeSet<_>>();
+	let previous = previous
+		.iter()
+		.map(|(index, value)| ((*index).to_owned(), value.clone()))
+		.chain(recorded.iter().flatten().cloned())
+		.collect::<BTreeSet<_>>();
+
+	for (index, value) in previous.difference(&current) {
+		remove(chart, &table_name(table, index), value, key).await?;
+	}
+
+	for (index, value) in current.difference(&previous) {
+		insert(chart, table, index, value, key).await?;
+	}
+
+	let current = current.into_iter().collect::<Vec<_>>();
+
+	match recorded {
+		Some(recorded) if recorded == current => Ok(()),
+		Some(_) if current.is_empty() => backend.delete(&keys_table, key).await,
+		Some(_) => backend.update(&keys_table, key, &current).await,
+		None if current.is_empty() => Ok(()),
+		None => {
+			tables.ensure(backend, &keys_table).await?;
+
+			backend.create(&keys_table, key, &current).await
+		}
+	}
+}
+
+/// Removes the entry at `key` from every index it's recorded in, for entries deleted without knowing their type.
+pub async fn remove_key<B: Backend>(
+	chart: &Starchart<B>,
+	table: &str,
+	key: &str,
+) -> Result<(), B::Error> {
+	update(chart, table, key, &[], &[]).await
+}
+
+async fn insert<B: Backend>(
+	chart: &Starchart<B>,
+	table: &str,
+	index: &str,
+	value: &str,
+	key: &str,
+) -> Result<(), B::Error> {
+	let backend = &**chart;
+	let tables = &chart.index_tables;
+	let index_table = &table_name(table, index);
+
+	if tables.ensure(backend, index_table).await? {
+		let registry = registry_name(table);
+		tables.ensure(backend, &registry).await?;
+		backend.ensure(&registry, index, index_table).await?;
+	}
+
+	let existing = backend.get::<Vec<String>>(index_table, value).await?;
+	let is_new = existing.is_none();
+	let mut keys = existing.unwrap_or_default();
+
+	if keys.iter().any(|k| k == key) {
+		return Ok(());
+	}
+
+	keys.push(key.to_owned());
+
+	if is_new {
+		backend.create(index_table, value, &keys).await?;
+	} else {
+		backend.update(index_table, value, &keys).await?;
+	}
+
+	Ok(())
+}
+
+async fn remove<B: Backend>(
+	chart: &Starchart<B>,
+	index_table: &str,
+	value: &str,
+	key: &str,
+) -> Result<(), B::Error> {
+	let backend = &**chart;
+
+	if !chart.index_tables.exists(backend, index_table).await? {
+		return Ok(());
+	}
+
+	if let Some(mut keys) = backend.get::<Vec<String>>(index_table, value).await? {
+		keys.retain(|k| k != key);
+
+		if keys.is_empty() {
+			backend.delete(index_table, value).await?;
+		} else {
+			backend.update(index_table, value, &keys).await?;
+		}
+	}
+
+	Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+	use std::time::Duration;
+
+	use super::{is_valid, table_name};
+	use crate::{
+		action::{CreateEntryAction, DeleteEntryAction},
+		backend::{Backend, MockBackend},
+		clock::ManualClock,
+		error::ErrorType,
+		testing::Profile,
+		Error, Starchart,
+	};
+
+	#[test]
+	fn naming() {
+		assert_eq!(table_name("users", "email"), "__index__5_users__email");
+		assert_ne!(table_name("a__b", "c"), table_name("a", "b__c"));
+		assert_ne!(table_name("a_", "b"), table_name("a", "_b"));
+
+		assert!(is_valid("email"));
+		assert!(is_valid("_email"));
+		assert!(!is_valid(""));
+		assert!(!is_valid("primary__email"));
+	}
+
+	#[tokio::test]
+	async fn untyped_deletes_clear_indexes() -> Result<(), Error> {
+		let clock = ManualClock::default();
+		let chart = Starchart::builder(MockBackend::new())
+			.clock(clock.clone())
+			.build()
+			.await?;
+		chart.create_table("profiles").await?;
+
+		for id in 1..=3 {
+			let profile = Profile {
+				id,
+				email: "shared@example.com".to_owned(),
+				..Profile::default()
+			};
+
+			let mut action = CreateEntryAction::for_table("profiles");
+			action.set_entry(&profile);
+			if id == 2 {
+				action.set_ttl(Duration::from_secs(10));
+			}
+			action.run_create_entry(&chart).await?;
+		}
+
+		let indexed = || async {
+			let mut ids = chart
+				.find_by_index::<Profile, _, Vec<_>>("profiles", "email", &"shared@example.com")
+				.await?
+				.into_iter()
+				.map(|profile| profile.id)
+				.collect::<Vec<_>>();
+			ids.sort_unstable();
+
+			Ok::<_, Error>(ids)
+		};
+		assert_eq!(indexed().await?, [1, 2, 3]);
+
+		let mut action = DeleteEntryAction::<Profile>::for_table("profiles");
+		action.set_key(&1_u32);
+		assert!(action.run_delete_entry(&chart).await?);
+		assert_eq!(indexed().await?, [2, 3]);
+
+		clock.advance(Duration::from_secs(11));
+		assert_eq!(chart.purge_expired("profiles").await?, 1);
+		assert_eq!(indexed().await?, [3]);
+
+		chart
+			.transaction(|tx| async move {
+				tx.delete::<Profile, _>("profiles", &3);
+
+				Ok::<_, Error>(())
+			})
+			.await?;
+		assert!(indexed().await?.is_empty());
+		assert!(chart
+			.get_keys::<Vec<String>>("__index__8_profiles__email")
+			.await?
+			.is_empty());
+
+		Ok(())
+	}
+
+	#[tokio::test]
+	async fn invalid_index_names() -> Result<(), Error> {
+		let chart = Starchart::new(MockBackend::new()).await?;
+		chart.create_table("profiles").await?;
+
+		let err = chart
+			.find_by_index::<Profile, _, Vec<_>>("profiles", "primary__email", &"new@example.com")
+			.await
+			.unwrap_err();
+		assert!(matches!(err.kind(), ErrorType::InvalidIndex));
+
+		Ok(())
+	}
+
+	#[tokio::test]
+	async fn modify_indexes() -> Result<(), Error> {
+		let chart = Starchart::new(MockBackend::new()).await?;
+		chart.create_table("profiles").await?;
+
+		let profile = Profile {
+			id: 1,
+			email: "new@example.com".to_owned(),
+			..Profile::default()
+		};
+		CreateEntryAction::for_table("profiles")
+			.with_entry(&profile)
+			.run_create_entry(&chart)
+			.await?;
+
+		let modified = chart
+			.modify("profiles", &1_u32, |profile: &mut Profile| {
+				profile.email = "modified@example.com".to_owned();
+			})
+			.await?;
+		assert_eq!(
+			modified.map(|profile| profile.email).as_deref(),
+			Some("modified@example.com")
+		);
+		assert!(chart
+			.find_by_index::<Profile, _, Vec<_>>("profiles", "email", &"new@example.com")
+			.await?
+			.is_empty());
+		assert_eq!(
+			chart
+				.find_by_index::<Profile, _, Vec<_>>("profiles", "email", &"modified@example.com")
+				.await?
+				.len(),
+			1
+		);
+
+		let error = chart
+			.modify("profiles", &1_u32, |profile: &mut Profile| profile.id = 2)
+			.await
+			.unwrap_err();
+		assert!(error.is_invalid());
+		assert_eq!(
+			chart
+				.get::<Profile>("profiles", "1")
+				.await?
+				.map(|profile| profile.id),
+			Some(1)
+		);
+
+		Ok(())
+	}
+}
@@ -0,0 +1,288 @@
+//! Spans and events for [`tracing`], which compile to nothing without the `tracing` feature.
+//!
+//! [`tracing`]: https://docs.rs/tracing
+
+use std::time::Instant;
+#[cfg(feature = "tracing")]
+use std::{
+	fmt::Display,
+	pin::Pin,
+	task::{Context, Poll},
+};
+
+#[cfg(feature = "tracing")]
+use futures_util::{Future, Stream};
+use parking_lot::{RwLock, RwLockReadGuard, RwLockWriteGuard};
+
+use crate::action::{ActionKind, TargetKind};
+
+/// A span that an operation runs in.
+#[derive(Debug, Clone)]
+#[must_use = "a span does nothing unless a future or stream is ran in it"]
+pub struct Span {
+	#[cfg(feature = "tracing")]
+	inner: tracing::Span,
+}
+
+impl Span {
+	/// Creates the span for running an [`Action`].
+	///
+	/// [`Action`]: crate::Action
+	#[cfg(feature = "tracing")]
+	pub fn action(
+		kind: ActionKind,
+		target: TargetKind,
+		table: Option<&str>,
+		key: Option<&str>,
+	) -> Self {
+		Self {
+			inner: tracing::debug_span!("action", %kind, %target, table, key),
+		}
+	}
+
+	#[cfg(not(feature = "tracing"))]
+	pub const fn action(_: ActionKind, _: TargetKind, _: Option<&str>, _: Option<&str>) -> Self {
+		Self {}
+	}
+
+	/// Creates the span for running a default [`Backend`] method.
+	///
+	/// [`Backend`]: crate::backend::Backend
+	#[cfg(feature = "tracing")]
+	pub fn backend(method: &'static str, table: &str) -> Self {
+		Self {
+			inner: tracing::trace_span!("backend", method, table),
+		}
+	}
+
+	#[cfg(not(feature = "tracing"))]
+	pub const fn backend(_: &'static str, _: &str) -> Self {
+		Self {}
+	}
+
+	/// Runs the future or stream in the span, emitting an event for every error it returns.
+	#[cfg(feature = "tracing")]
+	pub fn instrument<F>(self, inner: F) -> Instrumented<F> {
+		Instrumented {
+			inner: Box::pin(inner),
+			span: self.inner,
+		}
+	}
+
+	#[cfg(not(feature = "tracing"))]
+	#[allow(clippy::unused_self)]
+	pub const fn instrument<F>(self, inner: F) -> F {
+		inner
+	}
+}
+
+/// A future or stream that's polled in a [`Span`].
+///
+/// The inner value is boxed so the span can be entered without projecting the pin.
+#[cfg(feature = "tracing")]
+#[must_use = "futures and streams do nothing unless polled"]
+pub struct Instrumented<F> {
+	inner: Pin<Box<F>>,
+	span: tracing::Span,
+}
+
+#[cfg(feature = "tracing")]
+impl<F, T, E> Future for Instrumented<F>
+where
+	F: Future<Output = Result<T, E>>,
+	E: Display,
+{
+	type Output = F::Output;
+
+	fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+		let this = &mut *self;
+		let _entered = this.span.enter();
+
+		let poll = this.inner.as_mut().poll(cx);
+
+		if let Poll::Ready(Err(e)) = &poll {
+			tracing::debug!(error = %e, "operation failed");
+		}
+
+		poll
+	}
+}
+
+#[cfg(feature = "tracing")]
+impl<S, T, E> Stream for Instrumented<S>
+where
+	S: Stream<Item = Result<T, E>>,
+	E: Display,
+{
+	type Item = S::Item;
+
+	fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+		let this = &mut *self;
+		let _entered = this.span.enter();
+
+		let poll = this.inner.as_mut().poll_next(cx);
+
+		if let Poll::Ready(Some(Err(e))) = &poll {
+			tracing::debug!(error = %e, "operation failed");
+		}
+
+		poll
+	}
+}
+
+/// Locks the stripe for reading, emitting events if another operation holds it.
+#[cfg(feature = "tracing")]
+pub fn read(lock: &RwLock<()>, stripe: usize) -> RwLockReadGuard<'_, ()> {
+	lock.try_read()
+		.unwrap_or_else(|| contended(stripe, "shared", || lock.read(), |_| true))
+}
+
+#[cfg(not(feature = "tracing"))]
+pub fn read(lock: &RwLock<()>, _: usize) -> RwLockReadGuard<'_, ()> {
+	lock.read()
+}
+
+/// Locks the stripe for reading, giving up at the deadline, emitting events if another operation holds it.
+#[cfg(feature = "tracing")]
+pub fn read_until(
+	lock: &RwLock<()>,
+	stripe: usize,
+	deadline: Instant,
+) -> Option<RwLockReadGuard<'_, ()>> {
+	lock.try_read().or_else(|| {
+		contended(
+			stripe,
+			"shared",
+			|| lock.try_read_until(deadline),
+			Option::is_some,
+		)
+	})
+}
+
+#[cfg(not(feature = "tracing"))]
+pub fn read_until(
+	lock: &RwLock<()>,
+	_: usize,
+	deadline: Instant,
+) -> Option<RwLockReadGuard<'_, ()>> {
+	lock.try_read_until(deadline)
+}
+
+/// Locks the stripe for writing, emitting events if another operation holds it.
+#[cfg(feature = "tracing")]
+pub fn write(lock: &RwLock<()>, stripe: usize) -> RwLockWriteGuard<'_, ()> {
+	lock.try_write()
+		.unwrap_or_else(|| contended(stripe, "exclusive", || lock.write(), |_| true))
+}
+
+#[cfg(not(feature = "tracing"))]
+pub fn write(lock: &RwLock<()>, _: usize) -> RwLockWriteGuard<'_, ()> {
+	lock.write()
+}
+
+/// Locks the stripe for writing, giving up at the deadline, emitting events if another operation holds it.
+#[cfg(feature = "tracing")]
+pub fn write_until(
+	lock: &RwLock<()>,
+	stripe: usize,
+	deadline: Instant,
+) -> Option<RwLockWriteGuard<'_, ()>> {
+	lock.try_write().or_else(|| {
+		contended(
+			stripe,
+			"exclusive",
+			|| lock.try_write_until(deadline),
+			Option::is_some,
+		)
+	})
+}
+
+#[cfg(not(feature = "tracing"))]
+pub fn write_until(
+	lock: &RwLock<()>,
+	_: usize,
+	deadline: Instant,
+) -> Option<RwLockWriteGuard<'_, ()>> {
+	lock.try_write_until(deadline)
+}
+
+#[cfg(feature = "tracing")]
+fn contended<G>(
+	stripe: usize,
+	mode: &'static str,
+	lock: impl FnOnce() -> G,
+	acquired: impl FnOnce(&G) -> bool,
+) -> G {
+	tracing::trace!(stripe, mode, "waiting for a contended lock");
+
+	let started = Instant::now();
+	let guard = lock();
+
+	if acquired(&guard) {
+		tracing::debug!(
+			stripe,
+			mode,
+			waited = ?started.elapsed(),
+			"acquired a contended lock"
+		);
+	} else {
+		tracing::debug!(
+			stripe,
+			mode,
+			waited = ?started.elapsed(),
+			"timed out waiting for a contended lock"
+		);
+	}
+
+	guard
+}
+
+#[cfg(all(test, feature = "tracing"))]
+mod tests {
+	use std::{sync::Arc, thread, time::Duration};
+
+	use futures_util::{
+		future::{err, ok},
+		stream, TryStreamExt,
+	};
+	use parking_lot::RwLock;
+
+	use super::Span;
+
+	#[tokio::test]
+	async fn instrumented() {
+		let span = Span::backend("get", "table");
+
+		assert_eq!(span.clone().instrument(ok::<_, String>(1)).await, Ok(1));
+		assert_eq!(
+			span.clone()
+				.instrument(err::<u8, _>("failed".to_owned()))
+				.await,
+			Err("failed".to_owned())
+		);
+
+		let entries = span
+			.instrument(stream::iter(vec![Ok::<_, String>(1), Ok(2)]))
+			.try_collect::<Vec<_>>()
+			.await;
+
+		assert_eq!(entries, Ok(vec![1, 2]));
+	}
+
+	#[test]
+	fn contended() {
+		let lock = Arc::new(RwLock::new(()));
+		let guard = lock.write();
+
+		let waiting = {
+			let lock = lock.clone();
+			thread::spawn(move || drop(super::read(&lock, 0)))
+		};
+
+		thread::sleep(Duration::from_millis(10));
+		drop(guard);
+
+		waiting.join().unwrap();
+		drop(super::write(&lock, 0));
+	}
+}
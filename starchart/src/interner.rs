@@ -0,0 +1,155 @@
+//! Table names and keys deduplicated across a chart.
+
+use std::{
+	collections::HashSet,
+	sync::{
+		atomic::{AtomicU64, Ordering},
+		Arc,
+	},
+};
+
+use parking_lot::Mutex;
+
+use crate::stats::InternerStats;
+
+/// A pool of the table names and keys held by a [`Starchart`], so pins, stats, loaders,
+/// and queued writes share a single allocation for each string.
+///
+/// A disabled interner allocates every string it's given, see [`StarchartBuilder::intern_strings`].
+///
+/// [`Starchart`]: crate::Starchart
+/// [`StarchartBuilder::intern_strings`]: crate::StarchartBuilder::intern_strings
+#[derive(Debug, Default)]
+pub struct Interner {
+	strings: Option<Mutex<HashSet<Arc<str>>>>,
+	hits: AtomicU64,
+	misses: AtomicU64,
+}
+
+impl Interner {
+	pub fn new(enabled: bool) -> Self {
+		Self {
+			strings: enabled.then(Mutex::default),
+			..Self::default()
+		}
+	}
+
+	pub fn intern(&self, string: &str) -> Arc<str> {
+		let Some(strings) = &self.strings else {
+			return Arc::from(string);
+		};
+
+		let mut strings = strings.lock();
+
+		if let Some(interned) = strings.get(string) {
+			self.hits.fetch_add(1, Ordering::Relaxed);
+			return interned.clone();
+		}
+
+		self.misses.fetch_add(1, Ordering::Relaxed);
+
+		let interned = Arc::<str>::from(string);
+		strings.insert(interned.clone());
+
+		interned
+	}
+
+	/// Removes every string that's only held by the interner, returning how many were removed.
+	pub fn shrink(&self) -> usize {
+		let Some(strings) = &self.strings else {
+			return 0;
+		};
+
+		let mut strings = strings.lock();
+		let before = strings.len();
+
+		strings.retain(|string| Arc::strong_count(string) > 1);
+
+		before - strings.len()
+	}
+
+	pub fn stats(&self) -> Option<InternerStats> {
+		let strings = self.strings.as_ref()?.lock();
+
+		Some(InternerStats {
+			strings: strings.len(),
+			bytes: strings.iter().map(|string| string.len()).sum(),
+			hits: self.hits.load(Ordering::Relaxed),
+			misses: self.misses.load(Ordering::Relaxed),
+		})
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use std::sync::Arc;
+
+	use super::Interner;
+	use crate::{
+		backend::{Backend, MockBackend},
+		stats::InternerStats,
+		testing::TestSettings,
+		Error, Starchart,
+	};
+
+	#[test]
+	fn intern() {
+		let interner = Interner::new(true);
+
+		let first = interner.intern("table");
+		let second = interner.intern("table");
+		assert!(Arc::ptr_eq(&first, &second));
+
+		let key = interner.intern("key");
+
+		let stats = interner.stats().unwrap_or_default();
+		assert_eq!(stats.strings(), 2);
+		assert_eq!(stats.bytes(), 8);
+		assert_eq!(stats.hits(), 1);
+		assert_eq!(stats.misses(), 2);
+
+		drop(key);
+		assert_eq!(interner.shrink(), 1);
+		assert_eq!(interner.stats().map(InternerStats::strings), Some(1));
+
+		let disabled = Interner::new(false);
+		assert!(!Arc::ptr_eq(
+			&disabled.intern("table"),
+			&disabled.intern("table")
+		));
+		assert_eq!(disabled.stats(), None);
+		assert_eq!(disabled.shrink(), 0);
+	}
+
+	#[tokio::test]
+	async fn intern_strings() -> Result<(), Error> {
+		let chart = Starchart::builder(MockBackend::new())
+			.intern_strings(true)
+			.payload_stats(true)
+			.build()
+			.await?;
+		chart.create_table("table").await?;
+
+		chart.write_behind("table", &1, TestSettings::default());
+		chart.pin("table", &1);
+		chart.pin("table", &2);
+
+		let stats = chart.interner_stats().unwrap_or_default();
+		assert_eq!(stats.strings(), 3);
+		assert_eq!(stats.hits(), 2);
+
+		chart.flush().await?;
+		assert_eq!(chart.shrink_interner(), 0);
+
+		chart.unpin("table", &1);
+		chart.unpin("table", &2);
+		assert_eq!(chart.shrink_interner(), 2);
+
+		assert!(Starchart::new(MockBackend::new())
+			.await?
+			.interner_stats()
+			.is_none());
+
+		Ok(())
+	}
+}
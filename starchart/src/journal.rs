@@ -0,0 +1,334 @@
+//! An ordered journal of changes, for external change-data-capture consumers.
+//!
+//! When enabled with [`StarchartBuilder::journal`], every write through an [`Action`], a [`Transaction`],
+//! or [`Starchart::flush`] appends a [`JournalRecord`] to a backend table, numbered with a sequence that
+//! increases by one for each record. Consumers read records with [`Starchart::journal_from`], and record how
+//! far they've read with [`Starchart::acknowledge_journal`]. Once every consumer has acknowledged a segment
+//! of records, the segment is deleted.
+//!
+//! Records only hold the key that changed, consumers read the entry itself from the table.
+//! Records are appended before the change is made, so a crash part way through can leave a record
+//! for a change that never happened, but never a change without a record.
+//!
+//! [`Action`]: crate::Action
+//! [`Transaction`]: crate::transaction::Transaction
+//! [`Starchart::flush`]: crate::Starchart::flush
+//! [`Starchart::journal_from`]: crate::Starchart::journal_from
+//! [`Starchart::acknowledge_journal`]: crate::Starchart::acknowledge_journal
+//! [`StarchartBuilder::journal`]: crate::StarchartBuilder::journal
+
+use std::{collections::BTreeMap, time::SystemTime};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{backend::Backend, expiry::to_millis};
+
+pub(crate) const JOURNAL_TABLE: &str = "__journal__";
+
+const STATE_KEY: &str = "__state__";
+
+/// The amount of records stored in each segment of the journal.
+pub const SEGMENT_LEN: u64 = 256;
+
+/// A single change recorded in the journal.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct JournalRecord {
+	sequence: u64,
+	table: String,
+	key: Option<String>,
+	kind: ChangeKind,
+	written_at: u64,
+}
+
+impl JournalRecord {
+	/// The position of the record in the journal.
+	#[must_use]
+	pub const fn sequence(&self) -> u64 {
+		self.sequence
+	}
+
+	/// The table that changed.
+	#[must_use]
+	pub fn table(&self) -> &str {
+		&self.table
+	}
+
+	/// The key of the entry that changed, the new name of a renamed table, or [`None`] if the whole table changed.
+	#[must_use]
+	pub fn key(&self) -> Option<&str> {
+		self.key.as_deref()
+	}
+
+	/// What kind of change was made.
+	#[must_use]
+	pub const fn kind(&self) -> ChangeKind {
+		self.kind
+	}
+
+	/// When the change was made, in milliseconds since the unix epoch.
+	#[must_use]
+	pub const fn written_at(&self) -> u64 {
+		self.written_at
+	}
+}
+
+/// The kind of change a [`JournalRecord`] describes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[non_exhaustive]
+pub enum ChangeKind {
+	/// The entry was created or updated.
+	Write,
+	/// The entry was deleted.
+	Delete,
+	/// The table was deleted, along with all of it's entries.
+	DeleteTable,
+	/// The table was renamed, the record's key is it's new name.
+	RenameTable,
+	/// Every entry in the table was deleted, keeping the table itself.
+	TruncateTable,
+}
+
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+struct JournalState {
+	next: u64,
+	first_segment: u64,
+	acknowledged: BTreeMap<String, u64>,
+}
+
+type Segment = Vec<JournalRecord>;
+
+fn segment_key(segment: u64) -> String {
+	format!("{segment:020}")
+}
+
+async fn state<B: Backend>(backend: &B) -> Result<JournalState, B::Error> {
+	backend.ensure_table(JOURNAL_TABLE).await?;
+
+	Ok(backend
+		.get::<JournalState>(JOURNAL_TABLE, STATE_KEY)
+		.await?
+		.unwrap_or_default())
+}
+
+/// Reads the segment holding the sequence.
+async fn segment<B: Backend>(backend: &B, sequence: u64) -> Result<Segment, B::Error> {
+	Ok(backend
+		.get::<Segment>(JOURNAL_TABLE, &segment_key(sequence / SEGMENT_LEN))
+		.await?
+		.unwrap_or_default())
+}
+
+/// Appends a record to the end of the journal, returning it's sequence.
+pub(crate) async fn append<B: Backend>(
+	backend: &B,
+	table: &str,
+	key: Option<&str>,
+	kind: ChangeKind,
+	now: SystemTime,
+) -> Result<u64, B::Error> {
+	let mut state = state(backend).await?;
+
+	let mut sequence = state.next;
+	let mut records = segment(backend, sequence).await?;
+
+	// the segment is written before the state, so a crash in between leaves the state behind the
+	// segment's last record, which the sequence continues after instead.
+	if let Some(last) = records.last().filter(|last| last.sequence >= sequence) {
+		sequence = last.sequence + 1;
+
+		if sequence % SEGMENT_LEN == 0 {
+			records = segment(backend, sequence).await?;
+		}
+	}
+
+	let segment = segment_key(sequence / SEGMENT_LEN);
+	records.push(JournalRecord {
+		sequence,
+		table: table.to_owned(),
+		key: key.map(ToOwned::to_owned),
+		kind,
+		written_at: to_millis(now),
+	});
+	backend.replace(JOURNAL_TABLE, &segment, &records).await?;
+
+	state.next = sequence + 1;
+	backend.replace(JOURNAL_TABLE, STATE_KEY, &state).await?;
+
+	Ok(sequence)
+}
+
+/// Reads every record from the given sequence onwards, or [`None`] if the sequence was already truncated.
+pub(crate) async fn read_from<B: Backend>(
+	backend: &B,
+	sequence: u64,
+) -> Result<Option<Vec<JournalRecord>>, B::Error> {
+	let state = state(backend).await?;
+
+	if sequence < state.first_segment * SEGMENT_LEN {
+		return Ok(None);
+	}
+
+	let mut records = Vec::new();
+	for segment in sequence / SEGMENT_LEN..=state.next / SEGMENT_LEN {
+		let Some(segment) = backend
+			.get::<Segment>(JOURNAL_TABLE, &segment_key(segment))
+			.await?
+		else {
+			continue;
+		};
+
+		records.extend(
+			segment
+				.into_iter()
+				.filter(|record| record.sequence >= sequence),
+		);
+	}
+
+	Ok(Some(records))
+}
+
+/// The last sequence acknowledged by the consumer.
+pub(crate) async fn acknowledged<B: Backend>(
+	backend: &B,
+	consumer: &str,
+) -> Result<Option<u64>, B::Error> {
+	Ok(state(backend).await?.acknowledged.get(consumer).copied())
+}
+
+/// Records that the consumer has read every record up to and including the sequence, then deletes
+/// every segment that all consumers have read.
+pub(crate) async fn acknowledge<B: Backend>(
+	backend: &B,
+	consumer: &str,
+	sequence: u64,
+) -> Result<(), B::Error> {
+	let mut state = state(backend).await?;
+
+	let acknowledged = state
+		.acknowledged
+		.entry(consumer.to_owned())
+		.or_insert(sequence);
+	*acknowledged = (*acknowledged).max(sequence);
+
+	// the lowest sequence every consumer has read, plus one, is the first record that must be kept.
+	let keep_from = state
+		.acknowledged
+		.values()
+		.min()
+		.map_or(0, |sequence| sequence.saturating_add(1))
+		.min(state.next);
+
+	while (state.first_segment + 1) * SEGMENT_LEN <= keep_from {
+		backend
+			.delete(JOURNAL_TABLE, &segment_key(state.first_segment))
+			.await?;
+		state.first_segment += 1;
+	}
+
+	backend.replace(JOURNAL_TABLE, STATE_KEY, &state).await
+}
+
+/// Removes a consumer, so it no longer holds back truncation.
+pub(crate) async fn remove_consumer<B: Backend>(
+	backend: &B,
+	consumer: &str,
+) -> Result<bool, B::Error> {
+	let mut state = state(backend).await?;
+
+	if state.acknowledged.remove(consumer).is_none() {
+		return Ok(false);
+	}
+
+	backend.replace(JOURNAL_TABLE, STATE_KEY, &state).await?;
+
+	Ok(true)
+}
+
+#[cfg(test)]
+mod tests {
+	use std::convert::TryFrom;
+
+	use super::{ChangeKind, SEGMENT_LEN};
+	use crate::{
+		action::{CreateEntryAction, DeleteEntryAction, DeleteTableAction},
+		backend::{Backend, MockBackend},
+		error::ErrorType,
+		testing::TestSettings,
+		Error, Starchart,
+	};
+
+	#[tokio::test]
+	async fn journal() -> Result<(), Error> {
+		let chart = Starchart::builder(MockBackend::new())
+			.journal(true)
+			.build()
+			.await?;
+		assert!(chart.is_journaling());
+		chart.create_table("table").await?;
+
+		CreateEntryAction::for_table("table")
+			.with_entry(&TestSettings::default())
+			.run_create_entry(&chart)
+			.await?;
+		DeleteEntryAction::<TestSettings>::for_table("table")
+			.with_key(&1_u32)
+			.run_delete_entry(&chart)
+			.await?;
+		DeleteTableAction::<TestSettings>::for_table("table")
+			.run_delete_table(&chart)
+			.await?;
+
+		let records = chart.journal_from(0).await?;
+		assert_eq!(
+			records
+				.iter()
+				.map(|record| (record.sequence(), record.key(), record.kind()))
+				.collect::<Vec<_>>(),
+			[
+				(0, Some("1"), ChangeKind::Write),
+				(1, Some("1"), ChangeKind::Delete),
+				(2, None, ChangeKind::DeleteTable),
+			]
+		);
+		assert!(records.iter().all(|record| record.table() == "table"));
+		assert_eq!(chart.journal_from(2).await?.len(), 1);
+		assert!(chart.journal_from(3).await?.is_empty());
+
+		// losing the journal's state, as a crash after writing a segment would, doesn't reuse sequences.
+		Backend::delete(&*chart, "__journal__", "__state__").await?;
+
+		chart.create_table("table").await?;
+		for id in 0..SEGMENT_LEN {
+			CreateEntryAction::for_table("table")
+				.with_entry(&TestSettings {
+					id: u32::try_from(id).unwrap(),
+					..TestSettings::default()
+				})
+				.run_create_entry(&chart)
+				.await?;
+		}
+
+		let last = SEGMENT_LEN + 2;
+		assert_eq!(chart.journal_from(0).await?.len() as u64, last + 1);
+
+		chart.acknowledge_journal("slow", 10).await?;
+		chart.acknowledge_journal("fast", last).await?;
+		assert_eq!(chart.journal_acknowledged("fast").await?, Some(last));
+		assert_eq!(chart.journal_acknowledged("other").await?, None);
+		assert_eq!(chart.journal_from(0).await?.len() as u64, last + 1);
+
+		chart.acknowledge_journal("slow", last - 1).await?;
+		let err = chart.journal_from(0).await.unwrap_err();
+		assert!(matches!(err.kind(), ErrorType::JournalTruncated));
+		assert_eq!(chart.journal_from(last).await?.len(), 1);
+
+		assert!(chart.remove_journal_consumer("slow").await?);
+		assert!(!chart.remove_journal_consumer("slow").await?);
+
+		let chart = Starchart::new(MockBackend::new()).await?;
+		let err = chart.journal_from(0).await.unwrap_err();
+		assert!(matches!(err.kind(), ErrorType::JournalDisabled));
+
+		Ok(())
+	}
+}
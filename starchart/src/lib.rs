@@ -17,7 +17,7 @@
 #![cfg_attr(not(test), warn(clippy::panic_in_result_fn))]
 //! A simple database system that allows the use of multiple different backends.
 
-#[cfg(feature = "metadata")]
+#[cfg(any(test, feature = "metadata", feature = "test-util"))]
 const METADATA_KEY: &str = "__metadata__";
 
 use std::result::Result as StdResult;
@@ -25,23 +25,89 @@ use std::result::Result as StdResult;
 pub mod action;
 mod atomics;
 pub mod backend;
+mod backup;
+pub mod clock;
+mod copy;
+pub mod describe;
 mod entry;
 pub mod error;
+mod expiry;
+pub mod extensions;
+pub mod hook;
+mod index;
+mod instrument;
+mod interner;
+pub mod journal;
+mod loader;
+pub mod metrics;
+pub mod migration;
+pub mod namespace;
+pub mod obfuscation;
+pub mod pagination;
+mod pins;
+pub mod prelude;
+pub mod query;
+pub mod repair;
+pub mod schema;
+pub mod spawner;
 mod starchart;
+pub mod stats;
+pub mod table;
+#[cfg(test)]
+mod testing;
+pub mod timer;
+pub mod transaction;
 #[cfg(not(tarpaulin_include))]
 mod util;
+pub mod version;
+mod write_behind;
 
 #[doc(inline)]
 pub use self::{
 	action::Action,
-	entry::{Entry, IndexEntry, Key},
+	atomics::LockFairness,
+	copy::copy,
+	entry::{Entry, FromKey, IndexEntry, Key},
 	error::Error,
-	starchart::Starchart,
+	starchart::{Starchart, StarchartBuilder},
 };
 
 /// A type alias for a [`Result`] that wraps around [`Error`].
 pub type Result<T, E = Error> = StdResult<T, E>;
 
 /// The helper derive macro for easily implementing [`IndexEntry`].
+///
+/// The key is the field marked with `#[key]`, or the field named `key` or `id`. Fields marked with
+/// `#[index]` are used as secondary indexes. Keys whose type doesn't implement [`Key`] the way they
+/// should be stored can use `#[key(with = "path::to_fn")]`, where the function takes a reference to
+/// the field and returns a [`String`], or `#[key(format = "user-{}")]`, which formats the field with [`format!`].
+///
+/// Keys in nested structs can be used by adding `#[key(path = "ids.primary")]` to the struct instead,
+/// which reads the key through the given fields, and can be combined with `with` or `format`.
+///
+/// Generic structs implement [`IndexEntry`] for any parameters that make them an [`Entry`], the
+/// generated code never uses `unsafe`, so it can be used in crates that forbid it.
+///
+/// Adding `#[entry(table = "users")]` to the struct also generates a `TABLE` constant holding the table
+/// name, and an `action()` function returning an [`Action`] already set to that table.
 #[cfg(feature = "derive")]
 pub use starchart_derive::IndexEntry;
+
+/// The helper derive macro for generating a partial version of an entry.
+///
+/// Deriving on `Settings` generates a `SettingsPatch` struct, with every field wrapped in an [`Option`],
+/// and an `apply` method that sets the fields that are [`Some`] on a `Settings`. Fields that are [`None`]
+/// aren't serialized, so the patch can also be passed to [`PatchAction::set_patch`] to merge it into a stored
+/// entry. Fields marked with `#[patch(skip)]`, such as the key, are left out of the patch, and `rename` and
+/// `rename_all` serde options are copied over so the field names match the entry.
+///
+/// [`PatchAction::set_patch`]: crate::action::PatchAction::set_patch
+#[cfg(feature = "derive")]
+pub use starchart_derive::EntryPatch;
+
+#[doc(hidden)]
+pub mod __private {
+	pub use serde;
+
+	pub use crate::entry::{EncodedKey, KeyEncoder};
+}
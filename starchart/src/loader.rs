@@ -0,0 +1,381 @@
+//! Read-through loaders, invoked when a read misses the backend.
+
+use std::{
+	any::{type_name, Any, TypeId},
+	collections::HashMap,
+	convert::Infallible,
+	error::Error,
+	fmt::{Debug, Display, Formatter, Result as FmtResult},
+	future::Future,
+	pin::Pin,
+	sync::Arc,
+	time::{Duration, SystemTime},
+};
+
+use futures_util::{future::Shared, FutureExt};
+use parking_lot::{Mutex, RwLock};
+
+use crate::{interner::Interner, Entry};
+
+/// The error produced by a loader, shared between every caller waiting on the same load.
+pub type LoadError = Arc<dyn Error + Send + Sync>;
+
+type Loaded = Arc<dyn Any + Send + Sync>;
+
+type LoadResult = Result<Option<Loaded>, LoadError>;
+
+type LoadFuture = Pin<Box<dyn Future<Output = LoadResult> + Send>>;
+
+type ErasedLoader = Arc<dyn Fn(String) -> LoadFuture + Send + Sync>;
+
+/// A loader along with the [`TypeId`] and name of the entry type it loads.
+#[derive(Clone)]
+struct Registered {
+	type_id: TypeId,
+	type_name: &'static str,
+	loader: ErasedLoader,
+}
+
+type LoadKey = (Arc<str>, Arc<str>);
+
+/// The loaders registered on a [`Starchart`], keyed by table.
+///
+/// Loaders are stored type-erased along with the [`TypeId`] of their entry type, and the loaded
+/// value is handed to the reading action as is, so only reads of that type can use the loader.
+///
+/// Concurrent loads of the same key share a single call to the loader, and failed loads
+/// are remembered for the configured error window, if any.
+///
+/// [`Starchart`]: crate::Starchart
+#[derive(Default)]
+pub struct Loaders {
+	registered: RwLock<HashMap<Arc<str>, Registered>>,
+	in_flight: Mutex<HashMap<LoadKey, Shared<LoadFuture>>>,
+	failures: Mutex<HashMap<LoadKey, (SystemTime, LoadError)>>,
+	error_ttl: Option<Duration>,
+	interner: Arc<Interner>,
+}
+
+impl Loaders {
+	pub fn new(error_ttl: Option<Duration>, interner: Arc<Interner>) -> Self {
+		Self {
+			error_ttl,
+			interner,
+			..Self::default()
+		}
+	}
+
+	pub const fn error_ttl(&self) -> Option<Duration> {
+		self.error_ttl
+	}
+
+	pub fn insert<S, F, Fut>(&self, table: &str, loader: F)
+	where
+		S: Entry + 'static,
+		F: Fn(String) -> Fut + Send + Sync + 'static,
+		Fut: Future<Output = Option<S>> + Send + 'static,
+	{
+		self.insert_fallible(table, move |key| loader(key).map(Ok::<_, Infallible>));
+	}
+
+	pub fn insert_fallible<S, F, Fut, E>(&self, table: &str, loader: F)
+	where
+		S: Entry + 'static,
+		F: Fn(String) -> Fut + Send + Sync + 'static,
+		Fut: Future<Output = Result<Option<S>, E>> + Send + 'static,
+		E: Error + Send + Sync + 'static,
+	{
+		let loader: ErasedLoader = Arc::new(move |key| {
+			loader(key)
+				.map(|loaded| match loaded {
+					Ok(value) => Ok(value.map(|value| Arc::new(value) as Loaded)),
+					Err(e) => Err(Arc::new(e) as LoadError),
+				})
+				.boxed()
+		});
+
+		let registered = Registered {
+			type_id: TypeId::of::<S>(),
+			type_name: type_name::<S>(),
+			loader,
+		};
+
+		self.registered
+			.write()
+			.insert(self.interner.intern(table), registered);
+		self.failures.lock().retain(|(t, _), _| &**t != table);
+	}
+
+	pub fn remove(&self, table: &str) -> bool {
+		self.failures.lock().retain(|(t, _), _| &**t != table);
+		self.registered.write().remove(table).is_some()
+	}
+
+	pub fn contains(&self, table: &str) -> bool {
+		self.registered.read().contains_key(table)
+	}
+
+	/// Runs the loader for the given table, if there is one.
+	///
+	/// If a load for the same key is already running, this waits for it's result instead.
+	/// Fails with a [`LoaderTypeError`] if the loader loads a different type than `S`.
+	pub async fn load<S: Entry + 'static>(
+		&self,
+		table: &str,
+		key: &str,
+		now: SystemTime,
+	) -> Result<Option<S>, LoadError> {
+		let Some(registered) = self.registered.read().get(table).cloned() else {
+			return Ok(None);
+		};
+
+		let mismatch = || {
+			Arc::new(LoaderTypeError {
+				table: table.to_owned(),
+				expected: type_name::<S>(),
+				loaded: registered.type_name,
+			}) as LoadError
+		};
+
+		if registered.type_id != TypeId::of::<S>() {
+			return Err(mismatch());
+		}
+
+		let load_key = (self.interner.intern(table), self.interner.intern(key));
+
+		if let Some(error) = self.cached_failure(&load_key, now) {
+			return Err(error);
+		}
+
+		let shared = {
+			let mut in_flight = self.in_flight.lock();
+
+			if let Some(shared) = in_flight.get(&load_key) {
+				shared.clone()
+			} else {
+				let shared = (registered.loader)(key.to_owned()).shared();
+				in_flight.insert(load_key.clone(), shared.clone());
+				shared
+			}
+		};
+
+		let result = shared.clone().await;
+
+		{
+			let mut in_flight = self.in_flight.lock();
+			if in_flight
+				.get(&load_key)
+				.is_some_and(|current| current.ptr_eq(&shared))
+			{
+				in_flight.remove(&load_key);
+			}
+		}
+
+		if let (Err(error), Some(_)) = (&result, self.error_ttl) {
+			self.failures.lock().insert(load_key, (now, error.clone()));
+		}
+
+		// a load started before the loader was replaced may still be of the previous loader's type.
+		result?
+			.map(|loaded| loaded.downcast_ref::<S>().cloned().ok_or_else(mismatch))
+			.transpose()
+	}
+
+	fn cached_failure(&self, load_key: &LoadKey, now: SystemTime) -> Option<LoadError> {
+		let ttl = self.error_ttl?;
+		let mut failures = self.failures.lock();
+
+		let (failed_at, error) = failures.get(load_key)?;
+
+		if now.duration_since(*failed_at).unwrap_or_default() < ttl {
+			Some(error.clone())
+		} else {
+			failures.remove(load_key);
+			None
+		}
+	}
+}
+
+impl Debug for Loaders {
+	fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+		f.debug_struct("Loaders")
+			.field("tables", &self.registered.read().keys().collect::<Vec<_>>())
+			.field("in_flight", &self.in_flight.lock().len())
+			.field("failures", &self.failures.lock().len())
+			.field("error_ttl", &self.error_ttl)
+			.finish_non_exhaustive()
+	}
+}
+
+/// The error a read fails with if the table's loader loads a different type than the entry being read.
+#[derive(Debug)]
+struct LoaderTypeError {
+	table: String,
+	expected: &'static str,
+	loaded: &'static str,
+}
+
+impl Display for LoaderTypeError {
+	fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+		f.write_str("the loader for table ")?;
+		Debug::fmt(&self.table, f)?;
+		f.write_str(" loads ")?;
+		f.write_str(self.loaded)?;
+		f.write_str(", not ")?;
+		f.write_str(self.expected)
+	}
+}
+
+impl Error for LoaderTypeError {}
+
+#[cfg(test)]
+mod tests {
+	use std::{
+		io::{Error as IoError, ErrorKind},
+		sync::{
+			atomic::{AtomicUsize, Ordering},
+			Arc,
+		},
+		time::{Duration, UNIX_EPOCH},
+	};
+
+	use futures_executor::block_on;
+	use futures_util::future::{join_all, ready};
+
+	use super::{LoadError, Loaders};
+	use crate::{
+		backend::{Backend, MockBackend},
+		error::ErrorType,
+		journal::ChangeKind,
+		testing::{Profile, TestSettings},
+		Error, Starchart,
+	};
+
+	#[test]
+	fn load() -> Result<(), LoadError> {
+		let loaders = Loaders::default();
+
+		loaders.insert("table", |key: String| ready(key.parse::<u32>().ok()));
+
+		assert!(loaders.contains("table"));
+		assert_eq!(
+			block_on(loaders.load::<u32>("table", "5", UNIX_EPOCH))?,
+			Some(5)
+		);
+		assert_eq!(
+			block_on(loaders.load::<u32>("table", "five", UNIX_EPOCH))?,
+			None
+		);
+		assert_eq!(
+			block_on(loaders.load::<u32>("other", "5", UNIX_EPOCH))?,
+			None
+		);
+
+		// the loaded value isn't converted to other types.
+		let error = block_on(loaders.load::<u64>("table", "5", UNIX_EPOCH)).unwrap_err();
+		assert_eq!(
+			error.to_string(),
+			"the loader for table \"table\" loads u32, not u64"
+		);
+
+		assert!(loaders.remove("table"));
+		assert!(!loaders.contains("table"));
+
+		Ok(())
+	}
+
+	#[tokio::test]
+	async fn single_flight() -> Result<(), LoadError> {
+		let loaders = Loaders::default();
+		let calls = Arc::new(AtomicUsize::new(0));
+
+		let counter = calls.clone();
+		loaders.insert("table", move |key: String| {
+			counter.fetch_add(1, Ordering::SeqCst);
+			async move {
+				tokio::time::sleep(Duration::from_millis(10)).await;
+				key.parse::<u32>().ok()
+			}
+		});
+
+		let results =
+			join_all((0..10).map(|_| loaders.load::<u32>("table", "5", UNIX_EPOCH))).await;
+
+		assert_eq!(calls.load(Ordering::SeqCst), 1);
+		for result in results {
+			assert_eq!(result?, Some(5));
+		}
+
+		loaders.load::<u32>("table", "5", UNIX_EPOCH).await?;
+
+		assert_eq!(calls.load(Ordering::SeqCst), 2);
+
+		Ok(())
+	}
+
+	#[test]
+	fn error_window() {
+		let loaders = Loaders::new(Some(Duration::from_secs(30)), Arc::default());
+		let calls = Arc::new(AtomicUsize::new(0));
+
+		let counter = calls.clone();
+		loaders.insert_fallible("table", move |_: String| {
+			counter.fetch_add(1, Ordering::SeqCst);
+			ready(Err::<Option<u32>, _>(IoError::from(ErrorKind::TimedOut)))
+		});
+
+		assert!(block_on(loaders.load::<u32>("table", "1", UNIX_EPOCH)).is_err());
+		assert!(
+			block_on(loaders.load::<u32>("table", "1", UNIX_EPOCH + Duration::from_secs(10)))
+				.is_err()
+		);
+		assert_eq!(calls.load(Ordering::SeqCst), 1);
+
+		assert!(
+			block_on(loaders.load::<u32>("table", "1", UNIX_EPOCH + Duration::from_secs(31)))
+				.is_err()
+		);
+		assert_eq!(calls.load(Ordering::SeqCst), 2);
+	}
+
+	#[tokio::test]
+	async fn loader() -> Result<(), Error> {
+		let chart = Starchart::builder(MockBackend::new())
+			.journal(true)
+			.build()
+			.await?;
+		chart.create_table("table").await?;
+		chart.register_loader("table", |key: String| {
+			ready(key.parse().ok().map(|id| TestSettings {
+				id,
+				..TestSettings::default()
+			}))
+		});
+
+		let settings = chart.table::<TestSettings>("table");
+		assert_eq!(settings.get(&2_u32).await?.map(|loaded| loaded.id), Some(2));
+		assert!(chart.has("table", "2").await?);
+		assert_eq!(settings.get(&"two".to_owned()).await?, None);
+
+		// loaded entries are persisted the same way as created ones.
+		let records = chart.journal_from(0).await?;
+		assert_eq!(
+			records
+				.iter()
+				.map(|record| (record.key(), record.kind()))
+				.collect::<Vec<_>>(),
+			[(Some("2"), ChangeKind::Write)]
+		);
+
+		// the loaded entry isn't converted for reads of a different type.
+		let error = chart
+			.table::<Profile>("table")
+			.get(&3_u32)
+			.await
+			.unwrap_err();
+		assert!(matches!(error.kind(), ErrorType::ActionRun));
+		assert!(!chart.has("table", "3").await?);
+
+		Ok(())
+	}
+}
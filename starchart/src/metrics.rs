@@ -0,0 +1,285 @@
+//! Hooks for recording the count, latency, and outcome of every action ran on a [`Starchart`].
+//!
+//! [`Starchart`]: crate::Starchart
+
+use std::{
+	fmt::Debug,
+	future::Future,
+	sync::Arc,
+	time::{Duration, Instant},
+};
+
+use futures_util::FutureExt;
+use serde_json::Value;
+
+use crate::{
+	action::{ActionError, ActionKind, TargetKind},
+	backend::Backend,
+	hook::{ContextParts, Hooks},
+	instrument::Span,
+	Entry, Error, Starchart,
+};
+
+/// A sink for the [`Operation`]s ran on a [`Starchart`], such as one exporting Prometheus counters.
+///
+/// Every action ran on the chart is recorded once it completes, whether it succeeded or not.
+/// Streamed table reads aren't recorded, as they don't complete at a single point.
+///
+/// [`Starchart`]: crate::Starchart
+pub trait Metrics: Debug + Send + Sync {
+	/// Records an operation that completed.
+	///
+	/// This is called on the task that ran the operation, so it shouldn't block.
+	fn record(&self, operation: &Operation<'_>);
+}
+
+impl<M: Metrics + ?Sized> Metrics for Arc<M> {
+	fn record(&self, operation: &Operation<'_>) {
+		(**self).record(operation);
+	}
+}
+
+/// A single operation that ran on a [`Starchart`].
+///
+/// [`Starchart`]: crate::Starchart
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[must_use = "an operation has no side effects"]
+pub struct Operation<'a> {
+	kind: ActionKind,
+	target: TargetKind,
+	table: Option<&'a str>,
+	duration: Duration,
+	succeeded: bool,
+}
+
+impl<'a> Operation<'a> {
+	/// The kind of action that ran.
+	pub const fn kind(&self) -> ActionKind {
+		self.kind
+	}
+
+	/// Whether the action ran on an entry or a whole table.
+	pub const fn target(&self) -> TargetKind {
+		self.target
+	}
+
+	/// The table the action ran on, this is only missing if the action failed because a table wasn't set.
+	#[must_use]
+	pub const fn table(&self) -> Option<&'a str> {
+		self.table
+	}
+
+	/// How long the action took to run, including waiting for the table's lock.
+	#[must_use]
+	pub const fn duration(&self) -> Duration {
+		self.duration
+	}
+
+	/// Whether the action completed without an error.
+	#[must_use]
+	pub const fn succeeded(&self) -> bool {
+		self.succeeded
+	}
+}
+
+/// Runs an action in it's [`Span`] and between the chart's [`Hook`]s, recording it to the chart's [`Metrics`]
+/// once it completes.
+///
+/// [`Hook`]: crate::hook::Hook
+pub(crate) struct Observer<'a> {
+	span: Span,
+	metrics: Option<Arc<dyn Metrics>>,
+	hooks: Hooks,
+	parts: ContextParts,
+	kind: ActionKind,
+	target: TargetKind,
+	table: Option<&'a str>,
+}
+
+impl<'a> Observer<'a> {
+	pub fn new<'e, B: Backend, S: Entry + 'e>(
+		chart: &Starchart<B>,
+		kind: ActionKind,
+		target: TargetKind,
+		table: Option<&'a str>,
+		key: Option<&str>,
+		entries: impl IntoIterator<Item = &'e S>,
+	) -> Self {
+		// entries that can't be serialized are left out, as writing them fails regardless.
+		Self::with_entries(chart, kind, target, table, key, || {
+			entries
+				.into_iter()
+				.filter_map(|entry| serde_json::to_value(entry).ok())
+				.collect()
+		})
+	}
+
+	/// Creates an observer with entries already encoded as JSON, which are only encoded if the chart has hooks.
+	pub fn with_entries<B: Backend>(
+		chart: &Starchart<B>,
+		kind: ActionKind,
+		target: TargetKind,
+		table: Option<&'a str>,
+		key: Option<&str>,
+		entries: impl FnOnce() -> Vec<Value>,
+	) -> Self {
+		let hooks = chart.hooks.clone();
+		let parts = if hooks.is_empty() {
+			ContextParts::default()
+		} else {
+			ContextParts::new(key, entries())
+		};
+
+		Self {
+			span: Span::action(kind, target, table, key),
+			metrics: chart.metrics.clone(),
+			hooks,
+			parts,
+			kind,
+			target,
+			table,
+		}
+	}
+
+	pub fn run<F, T>(self, action: F) -> impl Future<Output = Result<T, ActionError>> + 'a
+	where
+		F: Future<Output = Result<T, ActionError>> + 'a,
+		T: 'a,
+	{
+		let Self {
+			span,
+			metrics,
+			hooks,
+			parts,
+			kind,
+			target,
+			table,
+		} = self;
+
+		span.instrument(async move {
+			let context = parts.context(kind, target, table);
+			let started = Instant::now();
+			let result = match hooks.before(&context).await {
+				Ok(()) => action.await,
+				Err(e) => Err(e),
+			};
+
+			if let Some(metrics) = metrics {
+				metrics.record(&Operation {
+					kind,
+					target,
+					table,
+					duration: started.elapsed(),
+					succeeded: result.is_ok(),
+				});
+			}
+
+			hooks.after(&context, result.as_ref().err()).await;
+
+			result
+		})
+	}
+
+	/// Runs an operation of the chart itself that isn't an [`Action`], returning it's error as it was.
+	///
+	/// [`Action`]: crate::Action
+	pub fn run_chart<F, T>(self, operation: F) -> impl Future<Output = Result<T, Error>> + 'a
+	where
+		F: Future<Output = Result<T, Error>> + 'a,
+		T: 'a,
+	{
+		self.run(operation.map(|result| result.map_err(ActionError::chart)))
+			.map(|result| result.map_err(Error::from))
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use std::sync::{Arc, Mutex};
+
+	use super::{Metrics, Operation};
+	use crate::{
+		action::{
+			ActionKind, CreateEntryAction, CreateTableAction, ReadEntryAction, ReadTableAction,
+			TargetKind,
+		},
+		backend::MockBackend,
+		testing::TestSettings,
+		Error, Starchart,
+	};
+
+	type RecordedOperation = (ActionKind, TargetKind, Option<String>, bool);
+
+	#[derive(Debug, Default)]
+	struct RecordedMetrics(Mutex<Vec<RecordedOperation>>);
+
+	impl Metrics for RecordedMetrics {
+		fn record(&self, operation: &Operation<'_>) {
+			self.0.lock().unwrap().push((
+				operation.kind(),
+				operation.target(),
+				operation.table().map(ToOwned::to_owned),
+				operation.succeeded(),
+			));
+		}
+	}
+
+	#[tokio::test]
+	async fn metrics() -> Result<(), Error> {
+		let metrics = Arc::new(RecordedMetrics::default());
+		let chart = Starchart::builder(MockBackend::new())
+			.metrics(metrics.clone())
+			.build()
+			.await?;
+
+		let mut action = CreateTableAction::<TestSettings>::new();
+		action.set_table("table");
+		action.run_create_table(&chart).await?;
+
+		let settings = TestSettings::default();
+		let mut action = CreateEntryAction::new();
+		action.set_table("table").set_entry(&settings);
+		action.run_create_entry(&chart).await?;
+
+		let mut action = ReadEntryAction::<TestSettings>::new();
+		action.set_table("missing").set_key(&1_u32);
+		assert!(action.run_read_entry(&chart).await.is_err());
+
+		let mut action = ReadTableAction::<TestSettings>::new();
+		action.set_table("table");
+		action.run_read_table::<_, Vec<_>>(&chart).await?;
+
+		assert!(chart.metrics().is_some());
+		assert_eq!(
+			*metrics.0.lock().unwrap(),
+			[
+				(
+					ActionKind::Create,
+					TargetKind::Table,
+					Some("table".to_owned()),
+					true
+				),
+				(
+					ActionKind::Create,
+					TargetKind::Entry,
+					Some("table".to_owned()),
+					true
+				),
+				(
+					ActionKind::Read,
+					TargetKind::Entry,
+					Some("missing".to_owned()),
+					false
+				),
+				(
+					ActionKind::Read,
+					TargetKind::Table,
+					Some("table".to_owned()),
+					true
+				),
+			]
+		);
+
+		Ok(())
+	}
+}
@@ -0,0 +1,341 @@
+//! Ordered migrations, ran once per chart and recorded in the chart itself.
+//!
+//! A [`Migrator`] holds every [`Migration`] an application has ever needed, in the order they were
+//! written. Each time it's ran, usually upon startup, the migrations that haven't been applied yet are
+//! ran in order, and recorded as an [`AppliedMigration`] in a reserved table, so they're never ran twice.
+//!
+//! Migrations that were already applied are checked against the migrator first, so removing, reordering,
+//! or editing (see [`Migration::checksum`]) a migration that has already been applied fails fast,
+//! before any pending migrations are ran.
+
+use std::{
+	fmt::{Debug, Formatter, Result as FmtResult},
+	future::Future,
+	pin::Pin,
+};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{backend::Backend, expiry::to_millis, Error, Starchart};
+
+pub(crate) const MIGRATIONS_TABLE: &str = "__migrations__";
+
+/// The future returned from [`Migration::run`].
+pub type MigrationFuture<'a> = Pin<Box<dyn Future<Output = Result<(), Error>> + Send + 'a>>;
+
+/// A single step of a [`Migrator`].
+pub trait Migration<B: Backend>: Send + Sync {
+	/// The name the migration is recorded under, this should never change once it's been applied.
+	fn name(&self) -> &str;
+
+	/// A checksum of what the migration does, recorded along with it's name.
+	///
+	/// Override this with a hash of whatever defines the migration, or a number that's bumped every time
+	/// it's edited, so running a migrator whose migration no longer matches what was applied fails.
+	/// Defaults to 0, so only the name and order of migrations are checked.
+	fn checksum(&self) -> u64 {
+		0
+	}
+
+	/// Runs the migration.
+	///
+	/// Every table is locked while the migration runs, so it's given the backend directly, as actions
+	/// ran through the chart would wait on the lock forever. The migration is only recorded once it
+	/// succeeds, and is ran again the next time the migrator is ran if it fails part way through,
+	/// so migrations should be safe to run more than once.
+	fn run<'a>(&'a self, backend: &'a B) -> MigrationFuture<'a>;
+}
+
+/// A migration recorded as applied to a chart.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AppliedMigration {
+	position: usize,
+	name: String,
+	checksum: u64,
+	applied_at: u64,
+}
+
+impl AppliedMigration {
+	/// The position of the migration in the [`Migrator`], starting from 0.
+	#[must_use]
+	pub const fn position(&self) -> usize {
+		self.position
+	}
+
+	/// The name of the migration.
+	#[must_use]
+	pub fn name(&self) -> &str {
+		&self.name
+	}
+
+	/// The checksum of the migration when it was applied.
+	#[must_use]
+	pub const fn checksum(&self) -> u64 {
+		self.checksum
+	}
+
+	/// When the migration was applied, in milliseconds since the unix epoch.
+	#[must_use]
+	pub const fn applied_at(&self) -> u64 {
+		self.applied_at
+	}
+}
+
+/// Runs [`Migration`]s that haven't been applied to a chart yet, in order.
+#[must_use = "a migrator does nothing until it's ran"]
+pub struct Migrator<B: Backend> {
+	migrations: Vec<Box<dyn Migration<B>>>,
+}
+
+impl<B: Backend> Migrator<B> {
+	/// Creates a new [`Migrator`] without any migrations.
+	pub fn new() -> Self {
+		Self {
+			migrations: Vec::new(),
+		}
+	}
+
+	/// Adds a migration, to be ran after every migration added before it.
+	#[allow(clippy::should_implement_trait)]
+	pub fn add<M: Migration<B> + 'static>(mut self, migration: M) -> Self {
+		self.migrations.push(Box::new(migration));
+
+		self
+	}
+
+	/// The amount of migrations in the migrator.
+	#[must_use]
+	pub fn len(&self) -> usize {
+		self.migrations.len()
+	}
+
+	/// Whether the migrator has no migrations.
+	#[must_use]
+	pub fn is_empty(&self) -> bool {
+		self.migrations.is_empty()
+	}
+
+	/// Runs every pending migration in order, returning how many were ran.
+	///
+	/// Each migration is ran in it's own exclusive section, locking every table of the chart, so no
+	/// actions run while a migration is in progress. Migrations applied by a concurrent run of a
+	/// migrator are skipped.
+	///
+	/// # Errors
+	///
+	/// Returns an [`ErrorType::MigrationMismatch`] error if a migration that was already applied doesn't
+	/// match the one at it's position in the migrator, any error returned by a migration, which stops
+	/// the run, or any errors that [`Backend::get_keys`], [`Backend::get`], [`Backend::ensure_table`],
+	/// or [`Backend::create`] can raise.
+	///
+	/// [`ErrorType::MigrationMismatch`]: crate::error::ErrorType::MigrationMismatch
+	pub async fn run(&self, chart: &Starchart<B>) -> Result<usize, Error> {
+		let mut ran = 0;
+
+		for (position, migration) in self.migrations.iter().enumerate() {
+			let lock = chart.guard.exclusive_all();
+
+			let backend = &**chart;
+
+			let applied = read_applied(backend).await?;
+			self.verify(&applied)?;
+
+			if applied.iter().any(|record| record.position == position) {
+				continue;
+			}
+
+			migration.run(backend).await?;
+
+			let record = AppliedMigration {
+				position,
+				name: migration.name().to_owned(),
+				checksum: migration.checksum(),
+				applied_at: to_millis(chart.clock().now()),
+			};
+
+			let recorded = async {
+				backend.ensure_table(MIGRATIONS_TABLE).await?;
+				backend
+					.create(MIGRATIONS_TABLE, &record_key(position), &record)
+					.await
+			}
+			.await;
+			recorded.map_err(|e: B::Error| Error::backend(Some(Box::new(e))))?;
+
+			drop(lock);
+
+			ran += 1;
+		}
+
+		if self.migrations.is_empty() {
+			let lock = chart.guard.shared(MIGRATIONS_TABLE);
+
+			self.verify(&read_applied(&**chart).await?)?;
+
+			drop(lock);
+		}
+
+		Ok(ran)
+	}
+
+	/// Reads the migrations applied to the chart, in the order they were applied.
+	///
+	/// # Errors
+	///
+	/// Any errors that [`Backend::get_keys`] or [`Backend::get`] can raise.
+	pub async fn applied(chart: &Starchart<B>) -> Result<Vec<AppliedMigration>, Error> {
+		let lock = chart.guard.shared(MIGRATIONS_TABLE);
+
+		let applied = read_applied(&**chart).await;
+
+		drop(lock);
+
+		applied
+	}
+
+	fn verify(&self, applied: &[AppliedMigration]) -> Result<(), Error> {
+		for record in applied {
+			let matches = self
+				.migrations
+				.get(record.position)
+				.is_some_and(|migration| {
+					migration.name() == record.name && migration.checksum() == record.checksum
+				});
+
+			if !matches {
+				return Err(Error::migration_mismatch(record.name.clone()));
+			}
+		}
+
+		Ok(())
+	}
+}
+
+impl<B: Backend> Debug for Migrator<B> {
+	fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+		f.debug_list()
+			.entries(self.migrations.iter().map(|migration| migration.name()))
+			.finish()
+	}
+}
+
+impl<B: Backend> Default for Migrator<B> {
+	fn default() -> Self {
+		Self::new()
+	}
+}
+
+/// Keys are padded so they sort in the order the migrations were applied.
+fn record_key(position: usize) -> String {
+	format!("{position:020}")
+}
+
+async fn read_applied<B: Backend>(backend: &B) -> Result<Vec<AppliedMigration>, Error> {
+	let result = async {
+		if !backend.has_table(MIGRATIONS_TABLE).await? {
+			return Ok(Vec::new());
+		}
+
+		let mut keys = backend.get_keys::<Vec<_>>(MIGRATIONS_TABLE).await?;
+		keys.sort_unstable();
+
+		let mut applied = Vec::with_capacity(keys.len());
+		for key in keys {
+			if let Some(record) = backend.get(MIGRATIONS_TABLE, &key).await? {
+				applied.push(record);
+			}
+		}
+
+		Ok(applied)
+	}
+	.await;
+
+	result.map_err(|e: B::Error| Error::backend(Some(Box::new(e))))
+}
+
+#[cfg(test)]
+mod tests {
+	use super::{AppliedMigration, Migration, MigrationFuture, Migrator};
+	use crate::{
+		action::{CreateEntryAction, ReadEntryAction},
+		backend::{Backend, MockBackend, MockError},
+		error::ErrorType,
+		testing::TestSettings,
+		Error, Starchart,
+	};
+
+	struct Rename(&'static str, u64);
+
+	impl Migration<MockBackend> for Rename {
+		fn name(&self) -> &str {
+			self.0
+		}
+
+		fn checksum(&self) -> u64 {
+			self.1
+		}
+
+		fn run<'a>(&'a self, backend: &'a MockBackend) -> MigrationFuture<'a> {
+			Box::pin(async move {
+				let result = async {
+					for key in backend.get_keys::<Vec<_>>("table").await? {
+						let mut settings: TestSettings = backend.get("table", &key).await?.unwrap();
+						settings.value.push_str(self.0);
+						backend.update("table", &key, &settings).await?;
+					}
+
+					Ok(())
+				}
+				.await;
+
+				result.map_err(|e: MockError| Error::backend(Some(Box::new(e))))
+			})
+		}
+	}
+
+	#[tokio::test]
+	async fn migrations() -> Result<(), Error> {
+		let chart = Starchart::new(MockBackend::new()).await?;
+		chart.create_table("table").await?;
+		CreateEntryAction::for_table("table")
+			.with_entry(&TestSettings::default())
+			.run_create_entry(&chart)
+			.await?;
+
+		let migrator = Migrator::new().add(Rename("-a", 1)).add(Rename("-b", 1));
+		assert_eq!(migrator.run(&chart).await?, 2);
+		assert_eq!(migrator.run(&chart).await?, 0);
+
+		let migrator = migrator.add(Rename("-c", 1));
+		assert_eq!(migrator.run(&chart).await?, 1);
+
+		let settings = ReadEntryAction::<TestSettings>::for_table("table")
+			.with_key(&1_u32)
+			.run_read_entry(&chart)
+			.await?
+			.unwrap();
+		assert!(settings.value.ends_with("-a-b-c"));
+
+		let applied = Migrator::applied(&chart).await?;
+		assert_eq!(
+			applied
+				.iter()
+				.map(AppliedMigration::name)
+				.collect::<Vec<_>>(),
+			["-a", "-b", "-c"]
+		);
+
+		// editing, reordering, or removing applied migrations fails before anything is ran.
+		let edited = Migrator::new().add(Rename("-a", 2)).add(Rename("-b", 1));
+		let reordered = Migrator::new().add(Rename("-b", 1)).add(Rename("-a", 1));
+		let removed = Migrator::new();
+		for migrator in [edited, reordered, removed] {
+			let err = migrator.run(&chart).await.unwrap_err();
+			assert!(matches!(err.kind(), ErrorType::MigrationMismatch { name } if name == "-a"));
+		}
+
+		assert_eq!(Migrator::applied(&chart).await?.len(), 3);
+
+		Ok(())
+	}
+}
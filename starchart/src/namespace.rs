@@ -0,0 +1,106 @@
+//! Namespaces isolating the tables of several tenants sharing a [`Starchart`], see [`Starchart::namespace`].
+//!
+//! [`Starchart`]: crate::Starchart
+//! [`Starchart::namespace`]: crate::Starchart::namespace
+
+/// The separator between a namespace and the names of the tables in it.
+///
+/// The table `settings` in the namespace `tenant_a` is stored as `tenant_a.settings`.
+pub const SEPARATOR: char = '.';
+
+/// Checks whether the name can be used as a namespace.
+///
+/// Namespaces can't be empty, can't contain the [`SEPARATOR`], and can't start with `__`,
+/// as those names are reserved for the chart's own tables.
+#[must_use]
+pub fn is_valid(name: &str) -> bool {
+	!name.is_empty() && !name.contains(SEPARATOR) && !name.starts_with("__")
+}
+
+/// Returns the prefix prepended to the names of the tables in the namespace.
+pub(crate) fn prefix(name: &str) -> String {
+	let mut prefix = String::with_capacity(name.len() + SEPARATOR.len_utf8());
+	prefix.push_str(name);
+	prefix.push(SEPARATOR);
+	prefix
+}
+
+/// Splits a table name into it's namespace and the name of the table in it, if it's namespaced.
+#[must_use]
+pub fn split(table: &str) -> Option<(&str, &str)> {
+	table
+		.split_once(SEPARATOR)
+		.filter(|(namespace, _)| is_valid(namespace))
+}
+
+#[cfg(test)]
+mod tests {
+	use std::time::Duration;
+
+	use super::{is_valid, prefix, split};
+	use crate::{
+		action::CreateEntryAction,
+		backend::{Backend, MockBackend},
+		error::ErrorType,
+		testing::TestSettings,
+		Error, Starchart,
+	};
+
+	#[test]
+	fn names() {
+		assert!(is_valid("tenant_a"));
+		assert!(!is_valid(""));
+		assert!(!is_valid("tenant.a"));
+		assert!(!is_valid("__tenant"));
+
+		assert_eq!(prefix("tenant_a"), "tenant_a.");
+		assert_eq!(split("tenant_a.settings"), Some(("tenant_a", "settings")));
+		assert_eq!(
+			split("tenant_a.nested.settings"),
+			Some(("tenant_a", "nested.settings"))
+		);
+		assert_eq!(split("settings"), None);
+		assert_eq!(split("__expiry__tenant_a.settings"), None);
+	}
+
+	#[tokio::test]
+	async fn namespaces() -> Result<(), Error> {
+		let chart = Starchart::new(MockBackend::new()).await?;
+		let tenant_a = chart.namespace("tenant_a")?;
+		let tenant_b = chart.namespace("tenant_b")?;
+
+		tenant_a.create_table("table").await?;
+		tenant_b.create_table("table").await?;
+
+		let settings = TestSettings::default();
+		let mut action = CreateEntryAction::new();
+		action
+			.set_table("table")
+			.set_entry(&settings)
+			.set_ttl(Duration::from_secs(10));
+		action.run_create_entry(&tenant_a).await?;
+
+		assert!(tenant_a.has("table", "1").await?);
+		assert!(!tenant_b.has("table", "1").await?);
+
+		// the namespace's expiry table is namespaced too, and still isn't listed.
+		assert!(chart.has_table("tenant_a.__expiry__table").await?);
+
+		let mut tables: Vec<String> = chart.tables().await?;
+		tables.sort();
+		assert_eq!(tables, ["tenant_a.table", "tenant_b.table"]);
+		assert_eq!(tenant_a.tables::<Vec<_>>().await?, ["table"]);
+
+		assert_eq!(
+			chart.namespaces::<Vec<String>>().await?,
+			["tenant_a", "tenant_b"]
+		);
+
+		for name in ["", "tenant.a", "__tenant"] {
+			let err = chart.namespace(name).unwrap_err();
+			assert!(matches!(err.kind(), ErrorType::InvalidNamespace));
+		}
+
+		Ok(())
+	}
+}
@@ -0,0 +1,163 @@
+//! Reversible obfuscation of numeric keys, for exposing identifiers without revealing sequential values.
+
+use std::fmt::{Debug, Formatter, Result as FmtResult};
+
+const ALPHABET: &[u8; 62] = b"0123456789ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz";
+const ENCODED_LEN: usize = 11;
+const ROUNDS: usize = 4;
+
+/// A secret-keyed, reversible mapping between `u64` keys and fixed-width base62 tokens.
+///
+/// The mapping is a bijection, so every key has exactly one token and decoding a token
+/// made with a different secret will yield a different (but valid) key. This is obfuscation,
+/// not encryption, and shouldn't be relied upon to keep keys secret from a determined attacker.
+#[derive(Clone, Copy, PartialEq, Eq)]
+#[must_use = "an obfuscator does nothing on it's own"]
+pub struct KeyObfuscator {
+	round_keys: [u32; ROUNDS],
+}
+
+impl KeyObfuscator {
+	/// Creates a new [`KeyObfuscator`] from the given secret.
+	pub fn new<S: AsRef<[u8]>>(secret: S) -> Self {
+		let mut state = secret
+			.as_ref()
+			.iter()
+			.fold(0xcbf2_9ce4_8422_2325_u64, |hash, byte| {
+				(hash ^ u64::from(*byte)).wrapping_mul(0x0100_0000_01b3)
+			});
+
+		let mut round_keys = [0; ROUNDS];
+		for key in &mut round_keys {
+			*key = util::split(util::split_mix(&mut state)).1;
+		}
+
+		Self { round_keys }
+	}
+
+	/// Encodes a key into an opaque token.
+	#[must_use]
+	pub fn encode(&self, key: u64) -> String {
+		let (mut left, mut right) = util::split(key);
+
+		for round_key in &self.round_keys {
+			let next = left ^ util::round(right, *round_key);
+			left = right;
+			right = next;
+		}
+
+		util::to_base62(util::join(left, right))
+	}
+
+	/// Decodes a token created by [`Self::encode`] back into the original key.
+	///
+	/// Returns [`None`] if the token isn't a validly formatted token.
+	#[must_use]
+	pub fn decode(&self, token: &str) -> Option<u64> {
+		let value = util::from_base62(token)?;
+		let (mut left, mut right) = util::split(value);
+
+		for round_key in self.round_keys.iter().rev() {
+			let previous = right ^ util::round(left, *round_key);
+			right = left;
+			left = previous;
+		}
+
+		Some(util::join(left, right))
+	}
+}
+
+impl Debug for KeyObfuscator {
+	fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+		f.debug_struct("KeyObfuscator").finish_non_exhaustive()
+	}
+}
+
+mod util {
+	use super::{ALPHABET, ENCODED_LEN};
+
+	#[allow(clippy::cast_possible_truncation)]
+	pub const fn split(value: u64) -> (u32, u32) {
+		((value >> 32) as u32, value as u32)
+	}
+
+	pub const fn join(high: u32, low: u32) -> u64 {
+		((high as u64) << 32) | low as u64
+	}
+
+	pub fn split_mix(state: &mut u64) -> u64 {
+		*state = state.wrapping_add(0x9e37_79b9_7f4a_7c15);
+		let mut z = *state;
+		z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+		z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+		z ^ (z >> 31)
+	}
+
+	pub const fn round(value: u32, key: u32) -> u32 {
+		let mut hash = (value ^ key).wrapping_mul(0x9e37_79b1);
+		hash ^= hash >> 15;
+		hash = hash.wrapping_mul(0x85eb_ca77);
+		hash ^ (hash >> 13)
+	}
+
+	pub fn to_base62(mut value: u64) -> String {
+		let mut output = [ALPHABET[0]; ENCODED_LEN];
+		for slot in output.iter_mut().rev() {
+			*slot = ALPHABET[(value % 62) as usize];
+			value /= 62;
+		}
+
+		output.iter().map(|byte| char::from(*byte)).collect()
+	}
+
+	pub fn from_base62(token: &str) -> Option<u64> {
+		if token.len() != ENCODED_LEN {
+			return None;
+		}
+
+		token.bytes().try_fold(0_u64, |value, byte| {
+			let digit = ALPHABET.iter().position(|c| *c == byte)? as u64;
+			value.checked_mul(62)?.checked_add(digit)
+		})
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use std::fmt::Debug;
+
+	use static_assertions::assert_impl_all;
+
+	use super::KeyObfuscator;
+
+	assert_impl_all!(KeyObfuscator: Clone, Debug, Send, Sync);
+
+	#[test]
+	fn round_trip() {
+		let obfuscator = KeyObfuscator::new("secret");
+
+		for key in [0, 1, 2, 42, u64::from(u32::MAX), u64::MAX] {
+			let token = obfuscator.encode(key);
+			assert_eq!(token.len(), 11);
+			assert_eq!(obfuscator.decode(&token), Some(key));
+		}
+	}
+
+	#[test]
+	fn secrets_differ() {
+		let first = KeyObfuscator::new("first");
+		let second = KeyObfuscator::new("second");
+
+		assert_ne!(first.encode(1), second.encode(1));
+		assert_ne!(first.encode(1), first.encode(2));
+	}
+
+	#[test]
+	fn invalid_tokens() {
+		let obfuscator = KeyObfuscator::new("secret");
+
+		assert_eq!(obfuscator.decode(""), None);
+		assert_eq!(obfuscator.decode("not-base62!"), None);
+		assert_eq!(obfuscator.decode("zzzzzzzzzzz"), None);
+	}
+}
@@ -0,0 +1,443 @@
+//! Opaque, signed tokens for paging through a table with [`Starchart::read_page`].
+//!
+//! Every entry is given a sequence number the first time it's seen by a page read, and pages are
+//! ordered by that sequence. A [`PageToken`] is anchored to the last sequence number of the page it
+//! came from, so entries inserted or deleted between page reads never cause entries to be skipped
+//! or returned twice, new entries are simply returned at the end.
+//!
+//! Tables can also be processed in batches with a [`Scan`], which resumes from a [`ScanToken`].
+//!
+//! [`Starchart::read_page`]: crate::Starchart::read_page
+//! [`Scan`]: crate::action::Scan
+
+use std::{
+	collections::{hash_map::RandomState, HashMap, HashSet},
+	fmt::{Debug, Display, Formatter, Result as FmtResult, Write},
+	hash::{BuildHasher, Hasher},
+	str::FromStr,
+};
+
+use serde::{Deserialize, Serialize};
+use siphasher::{sip::SipHasher24, sip128::SipHasher24 as SipHasher128};
+
+use crate::{backend::Backend, util::is_metadata};
+
+pub(crate) const SEQUENCE_TABLE: &str = "__sequence__";
+const TOKEN_LEN: usize = 32;
+const PAGE_TAG: u8 = b'p';
+const KEY_TAG: u8 = b'k';
+
+/// An opaque token pointing to the next page of a table.
+///
+/// Tokens are signed with the secret of the [`Starchart`] that created them, and are only
+/// valid for the table they were created for.
+///
+/// [`Starchart`]: crate::Starchart
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct PageToken(String);
+
+impl PageToken {
+	/// Returns the token as a string, for handing to clients.
+	#[must_use]
+	pub fn as_str(&self) -> &str {
+		&self.0
+	}
+}
+
+impl Display for PageToken {
+	fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+		f.write_str(&self.0)
+	}
+}
+
+impl From<String> for PageToken {
+	fn from(token: String) -> Self {
+		Self(token)
+	}
+}
+
+impl FromStr for PageToken {
+	type Err = std::convert::Infallible;
+
+	fn from_str(s: &str) -> Result<Self, Self::Err> {
+		Ok(Self(s.to_owned()))
+	}
+}
+
+/// An opaque token pointing to where a [`Scan`] left off, so it can be resumed later, even by another process.
+///
+/// Like [`PageToken`]s, tokens are signed with the secret of the [`Starchart`] that created them,
+/// and are only valid for the table they were created for.
+///
+/// [`Scan`]: crate::action::Scan
+/// [`Starchart`]: crate::Starchart
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct ScanToken(pub(crate) String);
+
+impl ScanToken {
+	/// Returns the token as a string, for persisting it.
+	#[must_use]
+	pub fn as_str(&self) -> &str {
+		&self.0
+	}
+}
+
+impl Display for ScanToken {
+	fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+		f.write_str(&self.0)
+	}
+}
+
+impl From<String> for ScanToken {
+	fn from(token: String) -> Self {
+		Self(token)
+	}
+}
+
+impl FromStr for ScanToken {
+	type Err = std::convert::Infallible;
+
+	fn from_str(s: &str) -> Result<Self, Self::Err> {
+		Ok(Self(s.to_owned()))
+	}
+}
+
+/// A single page of entries, returned from [`Starchart::read_page`].
+///
+/// [`Starchart::read_page`]: crate::Starchart::read_page
+#[derive(Debug, Clone)]
+pub struct Page<S> {
+	pub(crate) entries: Vec<(String, S)>,
+	pub(crate) next: Option<PageToken>,
+}
+
+impl<S> Page<S> {
+	/// The entries in this page, along with their keys.
+	#[must_use]
+	pub fn entries(&self) -> &[(String, S)] {
+		&self.entries
+	}
+
+	/// Consumes the page, returning the entries along with their keys.
+	#[must_use]
+	pub fn into_entries(self) -> Vec<(String, S)> {
+		self.entries
+	}
+
+	/// The token for the next page, or [`None`] if this is the last page.
+	#[must_use]
+	pub const fn next(&self) -> Option<&PageToken> {
+		self.next.as_ref()
+	}
+}
+
+/// Signs and verifies [`PageToken`]s with the chart's secret.
+///
+/// Tokens are signed with SipHash-2-4, keyed with 128 bits derived from the secret, over the table
+/// and the token's contents.
+pub(crate) struct Paginator {
+	key: (u64, u64),
+}
+
+impl Paginator {
+	pub fn new<S: AsRef<[u8]>>(secret: S) -> Self {
+		let key = SipHasher128::new().hash(secret.as_ref());
+
+		Self {
+			key: (key.h1, key.h2),
+		}
+	}
+
+	/// Creates a [`Paginator`] with a secret unique to this process.
+	pub fn random() -> Self {
+		let random = || RandomState::new().build_hasher().finish();
+
+		Self {
+			key: (random(), random()),
+		}
+	}
+
+	pub fn sign(&self, table: &str, sequence: u64) -> PageToken {
+		PageToken(format!(
+			"{:016x}{:016x}",
+			sequence,
+			self.mac(table, PAGE_TAG, &sequence.to_le_bytes())
+		))
+	}
+
+	/// Returns the sequence the token is anchored to, or [`None`] if the token is malformed,
+	/// or was signed for a different table or with a different secret.
+	pub fn verify(&self, table: &str, token: &PageToken) -> Option<u64> {
+		let raw = token.as_str();
+
+		if raw.len() != TOKEN_LEN || !raw.is_ascii() {
+			return None;
+		}
+
+		let (sequence, mac) = raw.split_at(TOKEN_LEN / 2);
+		let sequence = u64::from_str_radix(sequence, 16).ok()?;
+		let mac = u64::from_str_radix(mac, 16).ok()?;
+
+		(mac == self.mac(table, PAGE_TAG, &sequence.to_le_bytes())).then_some(sequence)
+	}
+
+	/// Signs a key, for tokens that resume a scan after that key.
+	pub fn sign_key(&self, table: &str, key: &str) -> String {
+		let mac = format!("{:016x}", self.mac(table, KEY_TAG, key.as_bytes()));
+
+		key.bytes().fold(mac, |mut token, byte| {
+			let _ = write!(token, "{byte:02x}");
+			token
+		})
+	}
+
+	/// Returns the key signed with [`Self::sign_key`], or [`None`] if the token is malformed,
+	/// or was signed for a different table or with a different secret.
+	pub fn verify_key(&self, table: &str, token: &str) -> Option<String> {
+		if token.len() < TOKEN_LEN / 2 || token.len() % 2 != 0 || !token.is_ascii() {
+			return None;
+		}
+
+		let (mac, key) = token.split_at(TOKEN_LEN / 2);
+		let mac = u64::from_str_radix(mac, 16).ok()?;
+		let key = (0..key.len())
+			.step_by(2)
+			.map(|i| u8::from_str_radix(&key[i..i + 2], 16).ok())
+			.collect::<Option<Vec<_>>>()?;
+		let key = String::from_utf8(key).ok()?;
+
+		(mac == self.mac(table, KEY_TAG, key.as_bytes())).then_some(key)
+	}
+
+	/// Signs the payload of a token for the table, the tag keeping a token of one kind from being
+	/// valid as another.
+	fn mac(&self, table: &str, tag: u8, payload: &[u8]) -> u64 {
+		let mut hasher = SipHasher24::new_with_keys(self.key.0, self.key.1);
+
+		// the table is length prefixed, so no other table and payload sign the same bytes.
+		hasher.write(&(table.len() as u64).to_le_bytes());
+		hasher.write(table.as_bytes());
+		hasher.write_u8(tag);
+		hasher.write(payload);
+
+		hasher.finish()
+	}
+}
+
+impl Default for Paginator {
+	fn default() -> Self {
+		Self::random()
+	}
+}
+
+impl Debug for Paginator {
+	fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+		f.debug_struct("Paginator").finish_non_exhaustive()
+	}
+}
+
+/// The sequence numbers given to the entries of a table.
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+pub(crate) struct Sequences {
+	next: u64,
+	keys: HashMap<String, u64>,
+}
+
+impl Sequences {
+	/// Gives every key that doesn't have a sequence number one, and forgets the sequence numbers of
+	/// keys that are no longer present, returning whether anything changed.
+	fn assign(&mut self, keys: &[String]) -> bool {
+		let present = keys.iter().collect::<HashSet<_>>();
+
+		let before = self.keys.len();
+		self.keys.retain(|key, _| present.contains(key));
+		let mut changed = before != self.keys.len();
+
+		let mut missing = keys
+			.iter()
+			.filter(|key| !is_metadata(key) && !self.keys.contains_key(*key))
+			.collect::<Vec<_>>();
+		missing.sort();
+
+		for key in missing {
+			self.next += 1;
+			self.keys.insert(key.clone(), self.next);
+			changed = true;
+		}
+
+		changed
+	}
+
+	/// Returns up to `limit` keys after the given sequence, in sequence order,
+	/// and the sequence of the last key if there are more to come.
+	pub fn page(&self, after: u64, limit: usize) -> (Vec<&str>, Option<u64>) {
+		let mut remaining = self
+			.keys
+			.iter()
+			.filter(|(_, sequence)| **sequence > after)
+			.map(|(key, sequence)| (*sequence, key.as_str()))
+			.collect::<Vec<_>>();
+		remaining.sort_unstable();
+
+		let more = remaining.len() > limit;
+		remaining.truncate(limit);
+
+		let next = more.then(|| remaining.last().map_or(after, |(sequence, _)| *sequence));
+
+		(remaining.into_iter().map(|(_, key)| key).collect(), next)
+	}
+}
+
+/// Loads the sequence numbers for a table, assigning new ones for any keys without them, and returns
+/// whether any were assigned or forgotten, in which case they need to be saved with [`save`].
+///
+/// The sequence numbers of a table are only written when keys were added or removed since the last
+/// page read, so reads that don't change them can run alongside each other.
+pub(crate) async fn sequences<B: Backend>(
+	backend: &B,
+	table: &str,
+	keys: &[String],
+) -> Result<(Sequences, bool), B::Error> {
+	let mut sequences = if backend.has_table(SEQUENCE_TABLE).await? {
+		backend
+			.get::<Sequences>(SEQUENCE_TABLE, table)
+			.await?
+			.unwrap_or_default()
+	} else {
+		Sequences::default()
+	};
+
+	let changed = sequences.assign(keys);
+
+	Ok((sequences, changed))
+}
+
+/// Saves the sequence numbers for a table.
+pub(crate) async fn save<B: Backend>(
+	backend: &B,
+	table: &str,
+	sequences: &Sequences,
+) -> Result<(), B::Error> {
+	backend.ensure_table(SEQUENCE_TABLE).await?;
+	backend.replace(SEQUENCE_TABLE, table, sequences).await
+}
+
+/// Forgets the sequence numbers of a deleted table.
+pub(crate) async fn clear<B: Backend>(backend: &B, table: &str) -> Result<(), B::Error> {
+	if backend.has_table(SEQUENCE_TABLE).await? {
+		backend.delete(SEQUENCE_TABLE, table).await?;
+	}
+
+	Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+	use super::{PageToken, Paginator, Sequences};
+	use crate::{
+		backend::{Backend, MockBackend},
+		error::ErrorType,
+		testing::TestSettings,
+		Error, Starchart,
+	};
+
+	#[test]
+	fn tokens() {
+		let paginator = Paginator::new("secret");
+
+		let token = paginator.sign("table", 42);
+
+		assert_eq!(paginator.verify("table", &token), Some(42));
+		assert_eq!(paginator.verify("other", &token), None);
+		assert_eq!(Paginator::new("other").verify("table", &token), None);
+		assert_eq!(
+			paginator.verify("table", &PageToken::from("garbage".to_owned())),
+			None
+		);
+
+		// keys are signed separately from sequences, so one can't be passed off as the other.
+		assert_ne!(
+			paginator.sign_key("table", "").as_str(),
+			&paginator.sign("table", 0).as_str()[16..]
+		);
+
+		let token = paginator.sign_key("table", "käy");
+		assert_eq!(
+			paginator.verify_key("table", &token).as_deref(),
+			Some("käy")
+		);
+		assert_eq!(paginator.verify_key("other", &token), None);
+		assert_eq!(
+			paginator.verify_key("table", &token[..token.len() - 2]),
+			None
+		);
+	}
+
+	#[test]
+	fn stable_pages() {
+		let mut sequences = Sequences::default();
+		let keys = ["a", "b", "c"].map(ToOwned::to_owned);
+
+		assert!(sequences.assign(&keys));
+		assert!(!sequences.assign(&keys));
+
+		let (page, next) = sequences.page(0, 2);
+		assert_eq!(page, ["a", "b"]);
+		assert_eq!(next, Some(2));
+
+		// a key before the cursor is removed, and a new one is inserted
+		let keys = ["b", "c", "0"].map(ToOwned::to_owned);
+		assert!(sequences.assign(&keys));
+
+		let (page, next) = sequences.page(2, 2);
+		assert_eq!(page, ["c", "0"]);
+		assert_eq!(next, None);
+	}
+
+	#[tokio::test]
+	async fn read_page() -> Result<(), Error> {
+		let chart = Starchart::builder(MockBackend::new())
+			.pagination_secret("secret")
+			.build()
+			.await?;
+		chart.create_table("table").await?;
+
+		for id in 1..=3 {
+			chart
+				.create("table", &id.to_string(), &TestSettings::default())
+				.await?;
+		}
+
+		let first = chart.read_page::<TestSettings>("table", None, 2).await?;
+		assert_eq!(first.entries().len(), 2);
+		let token = first
+			.next()
+			.cloned()
+			.expect("there should be a second page");
+
+		chart.delete("table", &first.entries()[0].0).await?;
+		chart.create("table", "0", &TestSettings::default()).await?;
+
+		let second = chart
+			.read_page::<TestSettings>("table", Some(&token), 2)
+			.await?;
+		let keys = second
+			.entries()
+			.iter()
+			.map(|(key, _)| key.as_str())
+			.collect::<Vec<_>>();
+
+		assert_eq!(keys, ["3", "0"]);
+		assert!(second.next().is_none());
+
+		let wrong_table = chart
+			.read_page::<TestSettings>("other", Some(&token), 2)
+			.await;
+		assert!(matches!(
+			wrong_table.map_err(Error::into_parts),
+			Err((ErrorType::InvalidPageToken, None))
+		));
+
+		Ok(())
+	}
+}
@@ -0,0 +1,92 @@
+use std::{
+	collections::{HashMap, HashSet},
+	sync::Arc,
+};
+
+use parking_lot::RwLock;
+
+use crate::interner::Interner;
+
+/// The entries that automatic removal (eviction, expiry, archival) must never touch.
+#[derive(Debug, Default)]
+pub struct Pins {
+	tables: RwLock<HashMap<Arc<str>, HashSet<Arc<str>>>>,
+	interner: Arc<Interner>,
+}
+
+impl Pins {
+	pub fn new(interner: Arc<Interner>) -> Self {
+		Self {
+			tables: RwLock::default(),
+			interner,
+		}
+	}
+
+	/// Pins an entry, returning whether it wasn't already pinned.
+	pub fn pin(&self, table: &str, key: &str) -> bool {
+		let mut tables = self.tables.write();
+
+		if let Some(keys) = tables.get_mut(table) {
+			return !keys.contains(key) && keys.insert(self.interner.intern(key));
+		}
+
+		tables
+			.entry(self.interner.intern(table))
+			.or_default()
+			.insert(self.interner.intern(key))
+	}
+
+	/// Unpins an entry, returning whether it was pinned.
+	pub fn unpin(&self, table: &str, key: &str) -> bool {
+		let mut tables = self.tables.write();
+
+		let removed = tables.get_mut(table).is_some_and(|keys| keys.remove(key));
+
+		if tables.get(table).is_some_and(HashSet::is_empty) {
+			tables.remove(table);
+		}
+
+		removed
+	}
+
+	pub fn is_pinned(&self, table: &str, key: &str) -> bool {
+		self.tables
+			.read()
+			.get(table)
+			.is_some_and(|keys| keys.contains(key))
+	}
+
+	pub fn count(&self, table: &str) -> usize {
+		self.tables.read().get(table).map_or(0, HashSet::len)
+	}
+
+	pub fn clear_table(&self, table: &str) {
+		self.tables.write().remove(table);
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::Pins;
+
+	#[test]
+	fn pin_and_unpin() {
+		let pins = Pins::default();
+
+		assert!(pins.pin("table", "1"));
+		assert!(!pins.pin("table", "1"));
+		assert!(pins.pin("table", "2"));
+
+		assert!(pins.is_pinned("table", "1"));
+		assert!(!pins.is_pinned("other", "1"));
+		assert_eq!(pins.count("table"), 2);
+
+		assert!(pins.unpin("table", "1"));
+		assert!(!pins.unpin("table", "1"));
+		assert_eq!(pins.count("table"), 1);
+
+		pins.clear_table("table");
+
+		assert_eq!(pins.count("table"), 0);
+	}
+}
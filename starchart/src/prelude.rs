@@ -0,0 +1,23 @@
+//! The most commonly used items, to be glob imported.
+//!
+//! ```
+//! use starchart::prelude::*;
+//! ```
+//!
+//! The backends in `starchart-backends` have their own prelude, which includes this one.
+
+#[doc(no_inline)]
+pub use crate::{
+	action::{
+		CreateBatchAction, CreateEntryAction, CreateTableAction, DeleteEntryAction,
+		DeleteTableAction, PatchAction, ReadEntryAction, ReadTableAction, UpdateBatchAction,
+		UpdateEntryAction, UpdateTableAction,
+	},
+	backend::{Backend, BackendBuilder, BackendLayer},
+	table::Table,
+	Action, Entry, Error, FromKey, IndexEntry, Key, Starchart, StarchartBuilder,
+};
+
+#[cfg(feature = "derive")]
+#[doc(no_inline)]
+pub use crate::EntryPatch;
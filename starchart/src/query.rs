@@ -0,0 +1,442 @@
+//! Filters and page ranges for reading only part of a table.
+
+use std::{
+	cmp::Ordering,
+	fmt::{Debug, Formatter, Result as FmtResult},
+	ops::{Bound, RangeBounds},
+	sync::Arc,
+};
+
+use serde::Serialize;
+
+use crate::{
+	schema::{to_schema_value, SchemaValue},
+	util::is_metadata,
+};
+
+type EntryPredicate<'a, D> = Arc<dyn Fn(&D) -> bool + Send + Sync + 'a>;
+type ValuePredicate<'a> = Arc<dyn Fn(&SchemaValue) -> bool + Send + Sync + 'a>;
+
+/// A predicate used to select entries when reading a table.
+///
+/// The [`Backend::get_filtered`] default impl reads every entry and checks it against the filter,
+/// backends that can do better should override it.
+///
+/// [`Backend::get_filtered`]: crate::backend::Backend::get_filtered
+#[must_use = "a filter does nothing on it's own"]
+pub struct Filter<'a, D: ?Sized> {
+	kind: FilterKind<'a, D>,
+}
+
+impl<'a, D: ?Sized> Filter<'a, D> {
+	/// Creates a [`Filter`] over deserialized entries.
+	pub fn entry<F>(predicate: F) -> Self
+	where
+		F: Fn(&D) -> bool + Send + Sync + 'a,
+	{
+		Self {
+			kind: FilterKind::Entry(Arc::new(predicate)),
+		}
+	}
+
+	/// Creates a [`Filter`] over the [`SchemaValue`] representation of entries.
+	///
+	/// Entries that can't be represented as a [`SchemaValue`] never match.
+	pub fn value<F>(predicate: F) -> Self
+	where
+		F: Fn(&SchemaValue) -> bool + Send + Sync + 'a,
+	{
+		Self {
+			kind: FilterKind::Value(Arc::new(predicate)),
+		}
+	}
+
+	/// Returns whether the entry at the given key matches the filter.
+	///
+	/// The private metadata key never matches.
+	#[must_use]
+	pub fn matches(&self, key: &str, entry: &D) -> bool
+	where
+		D: Serialize,
+	{
+		if is_metadata(key) {
+			return false;
+		}
+
+		match &self.kind {
+			FilterKind::Entry(predicate) => predicate(entry),
+			FilterKind::Value(predicate) => {
+				to_schema_value(entry).is_ok_and(|value| predicate(&value))
+			}
+		}
+	}
+}
+
+impl<D: ?Sized> Clone for Filter<'_, D> {
+	fn clone(&self) -> Self {
+		Self {
+			kind: self.kind.clone(),
+		}
+	}
+}
+
+impl<D: ?Sized> Debug for Filter<'_, D> {
+	fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+		let kind = match self.kind {
+			FilterKind::Entry(_) => "Entry",
+			FilterKind::Value(_) => "Value",
+		};
+
+		f.debug_struct("Filter")
+			.field("kind", &kind)
+			.finish_non_exhaustive()
+	}
+}
+
+enum FilterKind<'a, D: ?Sized> {
+	Entry(EntryPredicate<'a, D>),
+	Value(ValuePredicate<'a>),
+}
+
+impl<D: ?Sized> Clone for FilterKind<'_, D> {
+	fn clone(&self) -> Self {
+		match self {
+			Self::Entry(predicate) => Self::Entry(Arc::clone(predicate)),
+			Self::Value(predicate) => Self::Value(Arc::clone(predicate)),
+		}
+	}
+}
+
+/// A range of entries to read from a table, ordered by key.
+///
+/// Keys are compared as strings, so numeric keys sort lexicographically (`"10"` before `"9"`).
+///
+/// The [`Backend::get_keys_in_range`] and [`Backend::get_page`] default impls read and sort every key in the
+/// table, backends that store keys in order should override them.
+///
+/// [`Backend::get_keys_in_range`]: crate::backend::Backend::get_keys_in_range
+/// [`Backend::get_page`]: crate::backend::Backend::get_page
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[must_use = "a page range does nothing on it's own"]
+pub struct PageRange {
+	/// Only keys that sort after this key are included.
+	pub after: Option<String>,
+	/// Only keys that start with this prefix are included.
+	pub prefix: Option<String>,
+	/// The lowest key included.
+	pub start: Bound<String>,
+	/// The highest key included.
+	pub end: Bound<String>,
+	/// The amount of keys to skip, after applying every bound.
+	pub offset: usize,
+	/// The maximum amount of entries to include.
+	pub limit: Option<usize>,
+}
+
+impl PageRange {
+	/// Creates a new [`PageRange`] that includes every entry.
+	pub const fn new() -> Self {
+		Self {
+			after: None,
+			prefix: None,
+			start: Bound::Unbounded,
+			end: Bound::Unbounded,
+			offset: 0,
+			limit: None,
+		}
+	}
+
+	/// Returns whether this range includes every entry.
+	#[must_use]
+	pub const fn is_unbounded(&self) -> bool {
+		self.is_unbounded_by_key() && self.offset == 0 && self.limit.is_none()
+	}
+
+	/// Returns a copy of this range without the offset and limit, including every key within it's bounds.
+	pub fn bounds(&self) -> Self {
+		Self {
+			offset: 0,
+			limit: None,
+			..self.clone()
+		}
+	}
+
+	/// Returns whether the key is within every bound of this range.
+	///
+	/// The private metadata key is never included.
+	#[must_use]
+	pub fn includes(&self, key: &str) -> bool {
+		!is_metadata(key)
+			&& self.after.as_deref().is_none_or(|after| key > after)
+			&& self
+				.prefix
+				.as_deref()
+				.is_none_or(|prefix| key.starts_with(prefix))
+			&& RangeBounds::<str>::contains(
+				&(
+					self.start.as_ref().map(String::as_str),
+					self.end.as_ref().map(String::as_str),
+				),
+				key,
+			)
+	}
+
+	/// Returns whether the key, and every key that sorts after it, is past the end of this range.
+	///
+	/// Backends that iterate keys in order can stop once this returns `true`.
+	#[must_use]
+	pub fn is_past(&self, key: &str) -> bool {
+		let past_end = match self.end.as_ref() {
+			Bound::Included(end) => key > end.as_str(),
+			Bound::Excluded(end) => key >= end.as_str(),
+			Bound::Unbounded => false,
+		};
+
+		past_end
+			|| self
+				.prefix
+				.as_deref()
+				.is_some_and(|prefix| key > prefix && !key.starts_with(prefix))
+	}
+
+	/// The tightest lower bound of this range, for backends that can start iterating keys at a given key.
+	#[must_use]
+	pub fn lower_bound(&self) -> Bound<&str> {
+		let bounds = [
+			self.after.as_deref().map(Bound::Excluded),
+			self.prefix.as_deref().map(Bound::Included),
+			Some(self.start.as_ref().map(String::as_str)),
+		];
+
+		bounds
+			.iter()
+			.flatten()
+			.copied()
+			.fold(Bound::Unbounded, |lowest, bound| {
+				match (lowest, bound) {
+					(Bound::Unbounded, bound) | (bound, Bound::Unbounded) => bound,
+					(
+						Bound::Included(a) | Bound::Excluded(a),
+						Bound::Included(b) | Bound::Excluded(b),
+					) => {
+						let tighter = match a.cmp(b) {
+							Ordering::Less => true,
+							// an excluded bound is tighter than an included one of the same key.
+							Ordering::Equal => matches!(bound, Bound::Excluded(_)),
+							Ordering::Greater => false,
+						};
+
+						if tighter {
+							bound
+						} else {
+							lowest
+						}
+					}
+				}
+			})
+	}
+
+	/// Selects the keys in this range from a list of keys, in order.
+	#[must_use]
+	pub fn select<'k>(&self, keys: &'k [String]) -> Vec<&'k str> {
+		let mut keys = keys
+			.iter()
+			.map(String::as_str)
+			.filter(|key| self.includes(key))
+			.collect::<Vec<_>>();
+		keys.sort_unstable();
+
+		keys.into_iter()
+			.skip(self.offset)
+			.take(self.limit.unwrap_or(usize::MAX))
+			.collect()
+	}
+
+	const fn is_unbounded_by_key(&self) -> bool {
+		self.after.is_none()
+			&& self.prefix.is_none()
+			&& matches!(self.start, Bound::Unbounded)
+			&& matches!(self.end, Bound::Unbounded)
+	}
+}
+
+impl Default for PageRange {
+	fn default() -> Self {
+		Self::new()
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use std::{collections::BTreeMap, convert::TryFrom, fmt::Debug, ops::Bound};
+
+	use static_assertions::assert_impl_all;
+
+	use super::{Filter, PageRange};
+	use crate::{
+		action::ReadTableAction,
+		backend::{Backend, MockBackend},
+		testing::TestSettings,
+		Error, Starchart,
+	};
+
+	assert_impl_all!(Filter<'static, u32>: Clone, Debug, Send, Sync);
+
+	#[test]
+	fn entry_filter() {
+		let filter = Filter::entry(|value: &u32| *value > 5);
+
+		assert!(filter.matches("1", &6));
+		assert!(!filter.matches("1", &5));
+	}
+
+	#[test]
+	fn value_filter() {
+		let filter = Filter::<u32>::value(|value| value.as_u64() == Some(5));
+
+		assert!(filter.matches("1", &5));
+		assert!(!filter.matches("1", &6));
+	}
+
+	#[test]
+	fn page_range() {
+		let keys = ["c", "a", "d", "b", "e"].map(ToOwned::to_owned);
+
+		assert!(PageRange::new().is_unbounded());
+		assert_eq!(PageRange::new().select(&keys), ["a", "b", "c", "d", "e"]);
+
+		let range = PageRange {
+			after: Some("a".to_owned()),
+			offset: 1,
+			limit: Some(2),
+			..PageRange::new()
+		};
+
+		assert!(!range.is_unbounded());
+		assert_eq!(range.select(&keys), ["c", "d"]);
+		assert!(range.bounds().limit.is_none());
+	}
+
+	#[test]
+	fn key_bounds() {
+		let keys = ["user:2", "post:1", "user:1", "user:10", "video:1"].map(ToOwned::to_owned);
+
+		let range = PageRange {
+			prefix: Some("user:".to_owned()),
+			..PageRange::new()
+		};
+
+		assert!(!range.is_unbounded());
+		assert_eq!(range.select(&keys), ["user:1", "user:10", "user:2"]);
+		assert_eq!(range.lower_bound(), Bound::Included("user:"));
+		assert!(!range.is_past("post:1"));
+		assert!(!range.is_past("user:3"));
+		assert!(range.is_past("video:1"));
+
+		let range = PageRange {
+			after: Some("user:1".to_owned()),
+			start: Bound::Included("post:".to_owned()),
+			end: Bound::Excluded("user:2".to_owned()),
+			..range
+		};
+
+		assert_eq!(range.select(&keys), ["user:10"]);
+		assert_eq!(range.lower_bound(), Bound::Excluded("user:1"));
+		assert!(range.is_past("user:2"));
+
+		let range = PageRange {
+			start: Bound::Excluded("b".to_owned()),
+			prefix: Some("b".to_owned()),
+			..PageRange::new()
+		};
+
+		assert_eq!(range.lower_bound(), Bound::Excluded("b"));
+	}
+
+	#[tokio::test]
+	async fn paged_read_table() -> Result<(), Error> {
+		let chart = Starchart::new(MockBackend::new()).await?;
+		chart.create_table("table").await?;
+
+		for id in 1..=5 {
+			let settings = TestSettings {
+				id,
+				..TestSettings::default()
+			};
+			chart.create("table", &id.to_string(), &settings).await?;
+		}
+
+		let mut action = ReadTableAction::<TestSettings>::new();
+		action.set_table("table").after_key(&1).limit(2);
+
+		let page: Vec<TestSettings> = action.run_read_table(&chart).await?;
+		assert_eq!(
+			page.iter().map(|settings| settings.id).collect::<Vec<_>>(),
+			[2, 3]
+		);
+
+		let mut action = ReadTableAction::<TestSettings>::new();
+		action
+			.set_table("table")
+			.filter(|settings| settings.id % 2 == 1)
+			.offset(1)
+			.limit(5);
+
+		let page: Vec<TestSettings> = action.run_read_table(&chart).await?;
+		assert_eq!(
+			page.iter().map(|settings| settings.id).collect::<Vec<_>>(),
+			[3, 5]
+		);
+
+		Ok(())
+	}
+
+	#[tokio::test]
+	async fn read_key_bounds() -> Result<(), Error> {
+		let chart = Starchart::new(MockBackend::new()).await?;
+		chart.create_table("table").await?;
+
+		for (id, key) in ["post:1", "user:1", "user:2", "user:3", "video:1"]
+			.iter()
+			.enumerate()
+		{
+			let settings = TestSettings {
+				id: u32::try_from(id).unwrap(),
+				..TestSettings::default()
+			};
+			chart.create("table", key, &settings).await?;
+		}
+
+		let ids = |entries: Vec<TestSettings>| {
+			entries
+				.iter()
+				.map(|settings| settings.id)
+				.collect::<Vec<_>>()
+		};
+
+		let mut action = ReadTableAction::<TestSettings>::for_table("table");
+		action.key_prefix("user:");
+		assert_eq!(ids(action.clone().run_read_table(&chart).await?), [1, 2, 3]);
+
+		action.key_range("user:2"..).limit(1);
+		assert_eq!(ids(action.run_read_table(&chart).await?), [2]);
+
+		let mut action = ReadTableAction::<TestSettings>::for_table("table");
+		action.key_range("post:1".."user:3");
+		let map: BTreeMap<String, TestSettings> = action.clone().run_read_map(&chart).await?;
+		assert_eq!(
+			map.keys().map(String::as_str).collect::<Vec<_>>(),
+			["post:1", "user:1", "user:2"]
+		);
+
+		action.key_prefix("user:");
+		let mut scan = action.run_scan(&chart, 1)?;
+		let mut scanned = Vec::new();
+		while let Some(batch) = scan.next_batch().await? {
+			scanned.extend(batch.into_iter().map(|(key, _)| key));
+		}
+		assert_eq!(scanned, ["user:1", "user:2"]);
+
+		Ok(())
+	}
+}
@@ -0,0 +1,355 @@
+//! Recovery of entries whose writes failed part way through.
+//!
+//! Writing an entry also updates it's secondary indexes, expiry time, and version, in separate
+//! backend calls. If one of those fails after the entry itself was written, or the write is cancelled
+//! in between, the entry is marked as needing repair, and the table is listed by [`Starchart::needs_repair`].
+//!
+//! Repairing an entry restores the index values of the stored entry and records the write again,
+//! bumping it's version and retaining it in the table's history. The write was already appended to the journal
+//! before it was made. Expiry times are only restored by writing the entry again with a TTL.
+//!
+//! Markers are kept in memory, so they're lost if the process exits before the entries are repaired.
+//!
+//! [`Starchart::needs_repair`]: crate::Starchart::needs_repair
+
+use std::{
+	collections::{HashMap, HashSet},
+	mem,
+	sync::Arc,
+};
+
+use parking_lot::Mutex;
+
+use crate::{
+	action::{ActionRunError, ActionRunErrorType},
+	backend::Backend,
+	index::{self, Indexes},
+	interner::Interner,
+	Entry, Starchart,
+};
+
+/// What actions do when the table they run on has entries that need repair, see [`StarchartBuilder::repair_policy`].
+///
+/// [`StarchartBuilder::repair_policy`]: crate::StarchartBuilder::repair_policy
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum RepairPolicy {
+	/// Actions that write to the table repair it's entries first, this is the default.
+	///
+	/// Actions that only read from the table don't wait for it to be repaired. Secondary indexes
+	/// are only restored by actions that maintain them, see [`Action::set_entry`],
+	/// otherwise the entries stay marked until [`Starchart::repair`] is called.
+	///
+	/// [`Action::set_entry`]: crate::Action::set_entry
+	/// [`Starchart::repair`]: crate::Starchart::repair
+	#[default]
+	Repair,
+	/// Every action on the table fails with an [`ActionRunErrorType::NeedsRepair`] error,
+	/// until the table is repaired with [`Starchart::repair`].
+	///
+	/// [`ActionRunErrorType::NeedsRepair`]: crate::action::ActionRunErrorType::NeedsRepair
+	/// [`Starchart::repair`]: crate::Starchart::repair
+	Fail,
+}
+
+/// The entries of a chart that need repair, keyed by table.
+#[derive(Debug, Default)]
+pub(crate) struct Repairs {
+	tables: Mutex<HashMap<Arc<str>, HashSet<Arc<str>>>>,
+	interner: Arc<Interner>,
+}
+
+impl Repairs {
+	pub(crate) fn new(interner: Arc<Interner>) -> Self {
+		Self {
+			tables: Mutex::default(),
+			interner,
+		}
+	}
+
+	/// Marks an entry as needing repair once the returned [`PendingRepair`] is dropped,
+	/// unless it's completed first.
+	pub(crate) const fn pending<'a>(&'a self, table: &'a str, key: &'a str) -> PendingRepair<'a> {
+		PendingRepair {
+			repairs: self,
+			table,
+			key,
+		}
+	}
+
+	pub(crate) fn mark(&self, table: &str, key: &str) {
+		self.tables
+			.lock()
+			.entry(self.interner.intern(table))
+			.or_default()
+			.insert(self.interner.intern(key));
+	}
+
+	pub(crate) fn contains(&self, table: &str) -> bool {
+		self.tables.lock().contains_key(table)
+	}
+
+	/// The tables with entries that need repair, sorted by name.
+	pub(crate) fn tables(&self) -> Vec<String> {
+		let mut tables = self
+			.tables
+			.lock()
+			.keys()
+			.map(ToString::to_string)
+			.collect::<Vec<_>>();
+		tables.sort_unstable();
+
+		tables
+	}
+
+	/// Removes and returns the keys that need repair in a table, sorted.
+	pub(crate) fn take(&self, table: &str) -> Vec<Arc<str>> {
+		let mut keys = self
+			.tables
+			.lock()
+			.remove(table)
+			.map(|keys| keys.into_iter().collect::<Vec<_>>())
+			.unwrap_or_default();
+		keys.sort_unstable();
+
+		keys
+	}
+
+	pub(crate) fn clear_table(&self, table: &str) {
+		self.tables.lock().remove(table);
+	}
+}
+
+/// Marks an entry as needing repair when dropped, unless [`Self::complete`] was called.
+///
+/// This is created once an entry is written, so a failed or cancelled update of it's indexes,
+/// expiry, or version is never silently ignored.
+#[must_use = "the entry is marked as needing repair as soon as this is dropped"]
+pub(crate) struct PendingRepair<'a> {
+	repairs: &'a Repairs,
+	table: &'a str,
+	key: &'a str,
+}
+
+impl PendingRepair<'_> {
+	pub(crate) const fn complete(self) {
+		mem::forget(self);
+	}
+}
+
+impl Drop for PendingRepair<'_> {
+	fn drop(&mut self) {
+		log::warn!(
+			"entry {:?} in table {:?} was only partially written, and needs repair",
+			self.key,
+			self.table
+		);
+
+		self.repairs.mark(self.table, self.key);
+	}
+}
+
+/// Repairs the marked entries of a table, returning how many were repaired.
+///
+/// Without an `indexer`, entries of tables that have secondary indexes are left marked, as their
+/// index values can't be restored. If repairing an entry fails, it and the entries after it stay marked.
+pub(crate) async fn run<B: Backend, S: Entry>(
+	chart: &Starchart<B>,
+	table: &str,
+	indexer: Option<fn(&S) -> Indexes>,
+) -> Result<usize, ActionRunError> {
+	let backend_error = |e: B::Error| ActionRunError {
+		source: Some(Box::new(e)),
+		kind: ActionRunErrorType::Backend,
+	};
+
+	let keys = chart.repairs.take(table);
+
+	if keys.is_empty() {
+		return Ok(0);
+	}
+
+	let remark = |keys: &[Arc<str>]| {
+		for key in keys {
+			chart.repairs.mark(table, key);
+		}
+	};
+
+	if indexer.is_none() {
+		match index::names(&**chart, table).await {
+			Ok(names) if names.is_empty() => {}
+			Ok(_) => {
+				remark(&keys);
+				return Ok(0);
+			}
+			Err(e) => {
+				remark(&keys);
+				return Err(backend_error(e));
+			}
+		}
+	}
+
+	for (i, key) in keys.iter().enumerate() {
+		if let Err(e) = entry(chart, table, key, indexer).await {
+			remark(&keys[i..]);
+			return Err(e);
+		}
+	}
+
+	Ok(keys.len())
+}
+
+async fn entry<B: Backend, S: Entry>(
+	chart: &Starchart<B>,
+	table: &str,
+	key: &str,
+	indexer: Option<fn(&S) -> Indexes>,
+) -> Result<(), ActionRunError> {
+	let backend = &**chart;
+	let backend_error = |e: B::Error| ActionRunError {
+		source: Some(Box::new(e)),
+		kind: ActionRunErrorType::Backend,
+	};
+
+	let stored = if indexer.is_some() || chart.history_limit(table).is_some() {
+		backend.get::<S>(table, key).await.map_err(backend_error)?
+	} else {
+		None
+	};
+
+	if let (Some(indexer), Some(stored)) = (indexer, &stored) {
+		index::update(chart, table, key, &[], &indexer(stored))
+			.await
+			.map_err(backend_error)?;
+	}
+
+	chart
+		.record_write(table, key)
+		.await
+		.map_err(backend_error)?;
+
+	chart.retain(table, key, stored.as_ref()).await
+}
+
+#[cfg(test)]
+mod tests {
+	use std::sync::Arc;
+
+	use serde::{Deserialize, Serialize};
+
+	use super::{RepairPolicy, Repairs};
+	use crate::{
+		action::{ActionRunError, ActionRunErrorType, CreateEntryAction, ReadEntryAction},
+		backend::{Backend, MockBackend},
+		Error, IndexEntry, Starchart,
+	};
+
+	#[test]
+	fn pending() {
+		let repairs = Repairs::default();
+
+		repairs.pending("table", "1").complete();
+		assert!(!repairs.contains("table"));
+
+		drop(repairs.pending("table", "2"));
+		drop(repairs.pending("other", "1"));
+		assert!(repairs.contains("table"));
+		assert_eq!(repairs.tables(), ["other", "table"]);
+
+		assert_eq!(repairs.take("table"), [Arc::<str>::from("2")]);
+		assert!(!repairs.contains("table"));
+	}
+
+	#[tokio::test]
+	async fn repair() -> Result<(), Error> {
+		#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+		struct User {
+			id: u32,
+			email: String,
+		}
+
+		impl IndexEntry for User {
+			type Key = u32;
+
+			fn key(&self) -> &Self::Key {
+				&self.id
+			}
+
+			fn indexes(&self) -> Vec<(&'static str, String)> {
+				vec![("email", self.email.clone())]
+			}
+		}
+
+		let user = |id: u32| User {
+			id,
+			email: format!("{id}@example.com"),
+		};
+
+		for policy in [RepairPolicy::Fail, RepairPolicy::Repair] {
+			let chart = Starchart::builder(MockBackend::new())
+				.repair_policy(policy)
+				.build()
+				.await?;
+			chart.create_table("users").await?;
+
+			// a corrupt index value makes the index update fail after the entry is written.
+			chart.create_table("__index__5_users__email").await?;
+			chart
+				.create(
+					"__index__5_users__email",
+					"1@example.com",
+					&"corrupt".to_owned(),
+				)
+				.await?;
+
+			let first = user(1);
+			assert!(CreateEntryAction::for_table("users")
+				.with_entry(&first)
+				.run_create_entry(&chart)
+				.await
+				.is_err());
+			assert!(chart.has("users", "1").await?);
+			assert_eq!(chart.needs_repair(), ["users"]);
+
+			chart
+				.delete("__index__5_users__email", "1@example.com")
+				.await?;
+
+			let read = ReadEntryAction::<User>::for_table("users")
+				.with_key(&1)
+				.run_read_entry(&chart)
+				.await;
+
+			if policy == RepairPolicy::Fail {
+				let error = read.unwrap_err().into_source().unwrap();
+				assert!(matches!(
+					error
+						.downcast_ref::<ActionRunError>()
+						.map(ActionRunError::kind),
+					Some(ActionRunErrorType::NeedsRepair { .. })
+				));
+
+				assert_eq!(chart.repair::<User>("users").await?, 1);
+			} else {
+				assert!(read.is_ok());
+
+				let second = user(2);
+				CreateEntryAction::for_table("users")
+					.with_entry(&second)
+					.run_create_entry(&chart)
+					.await?;
+			}
+
+			assert!(chart.needs_repair().is_empty());
+			assert_eq!(
+				chart
+					.find_by_index::<User, _, Vec<_>>("users", "email", &"1@example.com")
+					.await?
+					.len(),
+				1
+			);
+		}
+
+		Ok(())
+	}
+}
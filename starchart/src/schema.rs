@@ -0,0 +1,333 @@
+//! Untyped representations of entries, for working with tables without their concrete [`Entry`] types.
+//!
+//! With the `metadata` feature, entries are checked against the table's recorded schema with [`validate`] before being written,
+//! on backends that are [self-describing], as the schema is read back as a [`SchemaValue`].
+//!
+//! [`Entry`]: crate::Entry
+//! [self-describing]: crate::backend::Backend::is_self_describing
+
+use std::{
+	error::Error,
+	fmt::{Display, Formatter, Result as FmtResult},
+};
+
+use serde::Serialize;
+use serde_json::{Map, Value};
+
+/// An untyped value within an entry.
+pub type SchemaValue = Value;
+
+/// An untyped entry, mapping field names to their values.
+pub type SchemaMap = Map<String, SchemaValue>;
+
+/// What actions do when a table's metadata is missing or doesn't match the entry type, see [`StarchartBuilder::metadata_policy`].
+///
+/// Metadata is only recorded and checked with the `metadata` feature.
+///
+/// [`StarchartBuilder::metadata_policy`]: crate::StarchartBuilder::metadata_policy
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum MetadataPolicy {
+	/// Actions fail with an [`ActionRunErrorType::Metadata`] error if the metadata doesn't match
+	/// the entry type, this is the default.
+	///
+	/// Tables without metadata, such as ones created directly on the [`Backend`], aren't checked.
+	///
+	/// [`ActionRunErrorType::Metadata`]: crate::action::ActionRunErrorType::Metadata
+	/// [`Backend`]: crate::backend::Backend
+	#[default]
+	Strict,
+	/// Missing metadata is recorded with the metadata for the entry type by the next write to the table,
+	/// logging a warning, while mismatched metadata fails actions like [`Self::Strict`].
+	Repair,
+	/// Missing or mismatched metadata is replaced with the metadata for the entry type by the next write
+	/// to the table, logging a warning. Reads don't fail on mismatched metadata, but leave it in place.
+	///
+	/// This lets tables created by older versions of an entry type, or by external tools, be used again.
+	/// Metadata that can't be read at all still fails the action.
+	Replace,
+	/// Metadata is never checked, and entries aren't validated against the recorded schema.
+	Ignore,
+}
+
+/// Converts a value into a [`SchemaValue`].
+///
+/// # Errors
+///
+/// Returns an error if the value fails to serialize, or if it has a map with non-string keys.
+pub fn to_schema_value<T: Serialize + ?Sized>(value: &T) -> Result<SchemaValue, serde_json::Error> {
+	serde_json::to_value(value)
+}
+
+/// Checks that every field of the value has the same [`SchemaKind`] as the field in the schema.
+///
+/// Nested objects are checked recursively. Fields missing from either side, and fields that are
+/// [`SchemaKind::Null`] on either side (such as a [`None`] option), are allowed. Array elements aren't checked,
+/// as the schema is usually an empty default.
+///
+/// # Errors
+///
+/// Returns a [`SchemaError`] for the first field with a mismatched kind.
+pub fn validate(schema: &SchemaValue, value: &SchemaValue) -> Result<(), SchemaError> {
+	validate_at(String::new(), schema, value)
+}
+
+fn validate_at(path: String, schema: &SchemaValue, value: &SchemaValue) -> Result<(), SchemaError> {
+	let expected = SchemaKind::of(schema);
+	let found = SchemaKind::of(value);
+
+	if expected == SchemaKind::Null || found == SchemaKind::Null {
+		return Ok(());
+	}
+
+	if expected != found {
+		return Err(SchemaError {
+			field: path,
+			expected,
+			found,
+		});
+	}
+
+	if let (Value::Object(schema), Value::Object(value)) = (schema, value) {
+		for (name, field) in value {
+			if let Some(expected) = schema.get(name) {
+				let name = name.replace('~', "~0").replace('/', "~1");
+
+				validate_at(format!("{path}/{name}"), expected, field)?;
+			}
+		}
+	}
+
+	Ok(())
+}
+
+/// The kind of a [`SchemaValue`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum SchemaKind {
+	/// A missing value, such as a [`None`] option.
+	Null,
+	/// A boolean.
+	Bool,
+	/// Any kind of number.
+	Number,
+	/// A string.
+	String,
+	/// A sequence of values.
+	Array,
+	/// A map of field names to values.
+	Object,
+}
+
+impl SchemaKind {
+	/// Gets the kind of the given value.
+	#[must_use]
+	pub const fn of(value: &SchemaValue) -> Self {
+		match value {
+			Value::Null => Self::Null,
+			Value::Bool(_) => Self::Bool,
+			Value::Number(_) => Self::Number,
+			Value::String(_) => Self::String,
+			Value::Array(_) => Self::Array,
+			Value::Object(_) => Self::Object,
+		}
+	}
+}
+
+impl Display for SchemaKind {
+	fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+		f.write_str(match self {
+			Self::Null => "null",
+			Self::Bool => "a boolean",
+			Self::Number => "a number",
+			Self::String => "a string",
+			Self::Array => "an array",
+			Self::Object => "an object",
+		})
+	}
+}
+
+/// A field of an entry didn't match the kind recorded in the table's schema.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SchemaError {
+	field: String,
+	expected: SchemaKind,
+	found: SchemaKind,
+}
+
+impl SchemaError {
+	/// The [JSON pointer] to the mismatched field, which is empty if the entry itself didn't match.
+	///
+	/// [JSON pointer]: https://datatracker.ietf.org/doc/html/rfc6901
+	#[must_use]
+	pub fn field(&self) -> &str {
+		&self.field
+	}
+
+	/// The kind recorded in the schema.
+	#[must_use]
+	pub const fn expected(&self) -> SchemaKind {
+		self.expected
+	}
+
+	/// The kind that was found in the entry.
+	#[must_use]
+	pub const fn found(&self) -> SchemaKind {
+		self.found
+	}
+}
+
+impl Display for SchemaError {
+	fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+		if self.field.is_empty() {
+			f.write_str("the entry")?;
+		} else {
+			f.write_str("field ")?;
+			Display::fmt(&self.field, f)?;
+		}
+
+		f.write_str(" should be ")?;
+		Display::fmt(&self.expected, f)?;
+		f.write_str(", but was ")?;
+		Display::fmt(&self.found, f)
+	}
+}
+
+impl Error for SchemaError {}
+
+#[cfg(test)]
+mod tests {
+	use serde::Serialize;
+	use serde_json::json;
+
+	use super::{to_schema_value, validate, MetadataPolicy, SchemaKind};
+	use crate::{
+		action::{ReadEntryAction, UpdateEntryAction},
+		backend::{Backend, MockBackend},
+		testing::TestSettings,
+		Error, Starchart,
+	};
+
+	#[derive(Serialize)]
+	struct Settings {
+		id: u32,
+		name: &'static str,
+	}
+
+	#[test]
+	fn conversion() -> Result<(), serde_json::Error> {
+		let value = to_schema_value(&Settings { id: 1, name: "one" })?;
+
+		assert_eq!(value, json!({ "id": 1, "name": "one" }));
+
+		Ok(())
+	}
+
+	#[test]
+	fn validation() {
+		let schema = json!({ "id": 0, "name": "", "nested": { "flag": false }, "opt": null });
+
+		assert!(validate(
+			&schema,
+			&json!({ "id": 1, "name": "one", "opt": [1], "extra": 1 })
+		)
+		.is_ok());
+
+		let err = validate(&schema, &json!({ "id": 1, "nested": { "flag": "yes" } })).unwrap_err();
+		assert_eq!(err.field(), "/nested/flag");
+		assert_eq!(err.expected(), SchemaKind::Bool);
+		assert_eq!(err.found(), SchemaKind::String);
+
+		let err = validate(&schema, &json!([])).unwrap_err();
+		assert_eq!(err.field(), "");
+		assert_eq!(
+			err.to_string(),
+			"the entry should be an object, but was an array"
+		);
+	}
+
+	#[tokio::test]
+	async fn metadata_policy() -> Result<(), Error> {
+		let settings = TestSettings::default();
+
+		let mut read = ReadEntryAction::<TestSettings>::new();
+		read.set_table("table").set_key(&settings.id);
+
+		let mut charts = Vec::new();
+		for policy in [
+			MetadataPolicy::Strict,
+			MetadataPolicy::Repair,
+			MetadataPolicy::Replace,
+			MetadataPolicy::Ignore,
+		] {
+			let chart = Starchart::builder(MockBackend::new())
+				.metadata_policy(policy)
+				.build()
+				.await?;
+			chart.create_table("table").await?;
+			chart.create("table", "1", &settings).await?;
+			chart
+				.create("table", "__metadata__", &"corrupt".to_owned())
+				.await?;
+
+			let result = read.clone().run_read_entry(&chart).await;
+			charts.push((chart, result));
+		}
+
+		// metadata is only checked with the `metadata` feature.
+		let checked = charts[0].1.is_err();
+
+		for (chart, result) in charts {
+			let policy = chart.metadata_policy();
+
+			// only replacing mismatched metadata lets the table be used again.
+			if checked && matches!(policy, MetadataPolicy::Strict | MetadataPolicy::Repair) {
+				assert!(result.is_err());
+				continue;
+			}
+
+			assert_eq!(result?, Some(settings.clone()));
+
+			if policy == MetadataPolicy::Replace && checked {
+				// reads leave the mismatched metadata for the next write to replace.
+				assert!(chart
+					.get::<TestSettings>("table", "__metadata__")
+					.await
+					.is_err());
+
+				let mut update = UpdateEntryAction::new();
+				update.set_table("table").set_entry(&settings);
+				update.run_update_entry(&chart).await?;
+
+				assert!(chart
+					.get::<TestSettings>("table", "__metadata__")
+					.await?
+					.is_some());
+			}
+		}
+
+		let chart = Starchart::builder(MockBackend::new())
+			.metadata_policy(MetadataPolicy::Repair)
+			.build()
+			.await?;
+		chart.create_table("table").await?;
+		chart.create("table", "1", &settings).await?;
+
+		assert_eq!(read.run_read_entry(&chart).await?, Some(settings.clone()));
+		assert!(
+			!chart.has("table", "__metadata__").await?,
+			"reads don't write metadata"
+		);
+
+		let mut update = UpdateEntryAction::new();
+		update.set_table("table").set_entry(&settings);
+		update.run_update_entry(&chart).await?;
+
+		assert_eq!(
+			chart.has("table", "__metadata__").await?,
+			checked,
+			"missing metadata is written"
+		);
+
+		Ok(())
+	}
+}
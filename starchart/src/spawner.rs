@@ -0,0 +1,125 @@
+//! Executors used to run the background tasks of a [`Starchart`].
+//!
+//! [`Starchart`]: crate::Starchart
+
+use std::{
+	fmt::{Debug, Formatter, Result as FmtResult},
+	future::Future,
+	pin::Pin,
+	thread,
+};
+
+use futures_executor::block_on;
+
+/// A task to be ran in the background.
+pub type Task = Pin<Box<dyn Future<Output = ()> + Send + 'static>>;
+
+/// An executor for background tasks, such as purging expired entries.
+///
+/// The crate never assumes an async runtime, every background task is handed to the [`Spawner`]
+/// attached to the [`Starchart`], so tokio, async-std, or a custom executor can be plugged in.
+///
+/// [`Starchart`]: crate::Starchart
+pub trait Spawner: Debug + Send + Sync {
+	/// Spawns the task, running it to completion in the background.
+	fn spawn(&self, task: Task);
+}
+
+/// A [`Spawner`] that runs every task to completion on a thread of it's own, this is the default.
+///
+/// This works without an async runtime, but tasks that need one, such as tasks using a
+/// tokio-based [`Backend`], should use a [`FnSpawner`] calling into that runtime instead.
+///
+/// [`Backend`]: crate::backend::Backend
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct ThreadSpawner;
+
+impl ThreadSpawner {
+	/// Creates a new [`ThreadSpawner`].
+	#[must_use]
+	pub const fn new() -> Self {
+		Self
+	}
+}
+
+impl Spawner for ThreadSpawner {
+	fn spawn(&self, task: Task) {
+		thread::spawn(move || block_on(task));
+	}
+}
+
+/// A [`Spawner`] that hands every task to a function, for plugging in an executor without
+/// writing a type for it.
+///
+/// ```rust
+/// # use starchart::spawner::FnSpawner;
+/// let spawner = FnSpawner::new(|task| {
+///     // hand the task to the executor, such as with `tokio::spawn(task);`
+///     drop(task);
+/// });
+/// ```
+#[derive(Clone, Copy)]
+pub struct FnSpawner<F>(F);
+
+impl<F> FnSpawner<F>
+where
+	F: Fn(Task) + Send + Sync,
+{
+	/// Creates a new [`FnSpawner`] calling the given function.
+	pub const fn new(spawn: F) -> Self {
+		Self(spawn)
+	}
+}
+
+impl<F> Debug for FnSpawner<F> {
+	fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+		f.debug_struct("FnSpawner").finish_non_exhaustive()
+	}
+}
+
+impl<F> Spawner for FnSpawner<F>
+where
+	F: Fn(Task) + Send + Sync,
+{
+	fn spawn(&self, task: Task) {
+		(self.0)(task);
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use std::{
+		fmt::Debug,
+		sync::{mpsc, Arc},
+	};
+
+	use parking_lot::Mutex;
+	use static_assertions::assert_impl_all;
+
+	use super::{FnSpawner, Spawner, Task, ThreadSpawner};
+
+	assert_impl_all!(ThreadSpawner: Spawner, Clone, Copy, Debug, Default, Send, Sync);
+
+	#[test]
+	fn thread_spawner() {
+		let (sender, receiver) = mpsc::channel();
+
+		ThreadSpawner::new().spawn(Box::pin(async move {
+			sender.send(1_u8).unwrap();
+		}));
+
+		assert_eq!(receiver.recv(), Ok(1));
+	}
+
+	#[test]
+	fn fn_spawner() {
+		let tasks = Arc::new(Mutex::new(Vec::<Task>::new()));
+		let queue = tasks.clone();
+		let spawner = FnSpawner::new(move |task| queue.lock().push(task));
+
+		spawner.spawn(Box::pin(async {}));
+
+		assert_eq!(tasks.lock().len(), 1);
+	}
+}
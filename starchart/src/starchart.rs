@@ -1,19 +1,86 @@
 //! The base structure to use for starchart.
 
-use std::{ops::Deref, sync::Arc};
+use std::{
+	collections::{BTreeMap, BTreeSet, HashSet},
+	fmt::{Debug, Formatter, Result as FmtResult},
+	future::Future,
+	io::{BufRead, Write},
+	iter::FromIterator,
+	ops::Deref,
+	sync::Arc,
+	thread,
+	time::Duration,
+};
 
-use futures_executor::block_on;
+use futures_executor::{block_on, enter};
+use futures_util::{lock::Mutex as AsyncMutex, FutureExt};
+use serde::Serialize;
 
-use crate::{atomics::Guard, backend::Backend};
+use crate::{
+	action::{
+		ActionError, ActionKind, ActionRunError, ActionRunErrorType, CreateEntryAction, CreateMode,
+		InnerAction, ReadEntryAction, ReadTableAction, TargetKind,
+	},
+	atomics::{Guard, LockFairness},
+	backend::{Backend, PrefixedBackend},
+	backup,
+	clock::{Clock, SystemClock},
+	describe::{self, TableDescription},
+	expiry,
+	extensions::Extensions,
+	hook::{Hook, Hooks},
+	index::{self, IndexTables},
+	interner::Interner,
+	journal::{self, ChangeKind, JournalRecord},
+	loader::Loaders,
+	metrics::{Metrics, Observer},
+	namespace,
+	obfuscation::KeyObfuscator,
+	pagination::{self, Page, PageToken, Paginator, Sequences},
+	pins::Pins,
+	repair::{self, RepairPolicy, Repairs},
+	schema::{MetadataPolicy, SchemaMap, SchemaValue},
+	spawner::{Spawner, ThreadSpawner},
+	stats::{InternerStats, PayloadStats, Stats, WriteBehindStats},
+	table::Table,
+	transaction::{StagedOperation, StagedWrite, Transaction, TransactionalBackend},
+	util,
+	version::{self, HistoryLimit, PointInTime, Retained, Revision},
+	write_behind::WriteBehind,
+	Entry, Error, IndexEntry, Key,
+};
 
 /// The base structure for managing data.
 ///
 /// The inner data is wrapped in an [`Arc`], so cloning
 /// is cheap and will allow multiple accesses to the data.
-#[derive(Debug, Default)]
+#[derive(Debug)]
 pub struct Starchart<B: Backend> {
 	backend: Arc<B>,
+	// shared by every clone, shutting the backend down once the last of them is dropped.
+	shutdown: Arc<ShutdownGuard<B>>,
 	pub(crate) guard: Arc<Guard>,
+	clock: Arc<dyn Clock>,
+	spawner: Arc<dyn Spawner>,
+	obfuscator: Option<Arc<KeyObfuscator>>,
+	pub(crate) stats: Arc<Stats>,
+	pub(crate) pins: Arc<Pins>,
+	pub(crate) repairs: Arc<Repairs>,
+	pub(crate) index_tables: Arc<IndexTables>,
+	pub(crate) loaders: Arc<Loaders>,
+	pub(crate) paginator: Arc<Paginator>,
+	pub(crate) metrics: Option<Arc<dyn Metrics>>,
+	pub(crate) hooks: Hooks,
+	write_behind: Arc<WriteBehind<B>>,
+	extensions: Arc<Extensions>,
+	interner: Arc<Interner>,
+	auto_create_tables: bool,
+	repair_policy: RepairPolicy,
+	metadata_policy: MetadataPolicy,
+	retention: Option<Duration>,
+	history: Arc<BTreeMap<String, HistoryLimit>>,
+	// held while appending to or truncating the journal, `None` if journaling is disabled.
+	journal: Option<Arc<AsyncMutex<()>>>,
 }
 
 impl<B: Backend> Starchart<B> {
@@ -23,19 +90,2019 @@ impl<B: Backend> Starchart<B> {
 	///
 	/// Any errors that [`Backend::init`] can raise.
 	pub async fn new(backend: B) -> Result<Self, B::Error> {
-		backend.init().await?;
-		Ok(Self {
-			backend: Arc::new(backend),
-			guard: Arc::default(),
+		Self::builder(backend).build().await
+	}
+
+	/// Creates a [`StarchartBuilder`] for configuring a [`Starchart`] before it's initialized.
+	pub fn builder(backend: B) -> StarchartBuilder<B> {
+		StarchartBuilder::new(backend)
+	}
+
+	/// Creates a child [`Starchart`] whose table names are all prefixed with the given prefix.
+	///
+	/// The child shares the backend, lock, [`Metrics`], [`Hook`]s, and interned strings of this chart, so libraries can be handed
+	/// their own chart without their tables colliding with the application's. Pins, repairs, loaders,
+	/// write-behind queues, extensions, and the tables set with [`StarchartBuilder::keep_history`] are separate for each chart. The prefix is prepended as-is,
+	/// so it should end with a separator.
+	#[must_use]
+	pub fn child(&self, prefix: &str) -> Starchart<PrefixedBackend<B>> {
+		let backend = Arc::new(PrefixedBackend::new(self.backend.clone(), prefix));
+
+		Starchart {
+			shutdown: Arc::new(ShutdownGuard(backend.clone())),
+			backend,
+			guard: Arc::new(self.guard.child(prefix)),
+			clock: self.clock.clone(),
+			spawner: self.spawner.clone(),
+			obfuscator: self.obfuscator.clone(),
+			stats: Arc::new(Stats::new(
+				self.stats.is_enabled(),
+				self.stats.large_entry_warning_bytes(),
+				self.interner.clone(),
+			)),
+			pins: Arc::new(Pins::new(self.interner.clone())),
+			repairs: Arc::new(Repairs::new(self.interner.clone())),
+			index_tables: Arc::default(),
+			loaders: Arc::new(Loaders::new(
+				self.loaders.error_ttl(),
+				self.interner.clone(),
+			)),
+			paginator: self.paginator.clone(),
+			metrics: self.metrics.clone(),
+			hooks: self.hooks.clone(),
+			write_behind: Arc::default(),
+			extensions: Arc::default(),
+			interner: self.interner.clone(),
+			auto_create_tables: self.auto_create_tables,
+			repair_policy: self.repair_policy,
+			metadata_policy: self.metadata_policy,
+			retention: self.retention,
+			history: Arc::default(),
+			journal: self.journal.clone(),
+		}
+	}
+
+	/// Creates a child [`Starchart`] for the given namespace, isolating it's tables from every other namespace.
+	///
+	/// This is a [`Self::child`] prefixed with the namespace and the [`namespace::SEPARATOR`], so the table
+	/// `settings` in the namespace `tenant_a` is stored as `tenant_a.settings`. Backends mapping table names
+	/// to paths can store each namespace on it's own, such as an `FsBackend` nesting tables on the separator.
+	///
+	/// # Errors
+	///
+	/// Returns an [`ErrorType::InvalidNamespace`] error if the name isn't [`namespace::is_valid`].
+	///
+	/// [`ErrorType::InvalidNamespace`]: crate::error::ErrorType::InvalidNamespace
+	pub fn namespace(&self, name: &str) -> Result<Starchart<PrefixedBackend<B>>, Error> {
+		if !namespace::is_valid(name) {
+			return Err(Error::invalid_namespace());
+		}
+
+		Ok(self.child(&namespace::prefix(name)))
+	}
+
+	/// Lists the namespaces with at least one table, in order.
+	///
+	/// # Errors
+	///
+	/// Returns any errors that [`Backend::tables`] can raise.
+	pub async fn namespaces<I: FromIterator<String>>(&self) -> Result<I, Error> {
+		let tables = self.tables::<Vec<String>>().await?;
+
+		let namespaces = tables
+			.iter()
+			.filter_map(|table| namespace::split(table))
+			.map(|(namespace, _)| namespace.to_owned())
+			.collect::<BTreeSet<_>>();
+
+		Ok(namespaces.into_iter().collect())
+	}
+
+	/// Returns the [`Clock`] used for time-based features.
+	#[must_use]
+	pub fn clock(&self) -> &dyn Clock {
+		&*self.clock
+	}
+
+	/// Returns the [`Spawner`] background tasks are ran on.
+	#[must_use]
+	pub fn spawner(&self) -> &dyn Spawner {
+		&*self.spawner
+	}
+
+	/// Runs a task in the background on the chart's [`Spawner`].
+	pub fn spawn<F>(&self, task: F)
+	where
+		F: Future<Output = ()> + Send + 'static,
+	{
+		self.spawner.spawn(Box::pin(task));
+	}
+
+	/// Returns the [`Metrics`] every action is recorded to, if any.
+	#[must_use]
+	pub fn metrics(&self) -> Option<&dyn Metrics> {
+		self.metrics.as_deref()
+	}
+
+	/// Returns the [`Extensions`] attached to this chart.
+	///
+	/// Companion crates can store their own state here, keyed by type, so it's available
+	/// anywhere the chart is.
+	#[must_use]
+	pub fn extensions(&self) -> &Extensions {
+		&self.extensions
+	}
+
+	/// Returns a [`Table`] handle for reading and writing entries of type `S` in the given table.
+	///
+	/// The table isn't checked or created until the handle is used.
+	#[must_use]
+	pub fn table<S: Entry>(&self, name: &str) -> Table<'_, B, S> {
+		Table::new(self, name)
+	}
+
+	/// Returns the [`KeyObfuscator`] configured for this chart, if any.
+	#[must_use]
+	pub fn key_obfuscator(&self) -> Option<&KeyObfuscator> {
+		self.obfuscator.as_deref()
+	}
+
+	/// Encodes a key with the configured [`KeyObfuscator`].
+	///
+	/// Returns [`None`] if no obfuscator was configured.
+	#[must_use]
+	pub fn encode_key(&self, key: u64) -> Option<String> {
+		self.key_obfuscator()
+			.map(|obfuscator| obfuscator.encode(key))
+	}
+
+	/// Decodes a token created by [`Self::encode_key`] with the configured [`KeyObfuscator`].
+	///
+	/// Returns [`None`] if no obfuscator was configured, or if the token is invalid.
+	#[must_use]
+	pub fn decode_key(&self, token: &str) -> Option<u64> {
+		self.key_obfuscator()?.decode(token)
+	}
+
+	/// Returns the [`PayloadStats`] recorded for writes to the given table.
+	///
+	/// Returns [`None`] unless enabled with [`StarchartBuilder::payload_stats`].
+	#[must_use]
+	pub fn payload_stats(&self, table: &str) -> Option<PayloadStats> {
+		self.stats.payload(table)
+	}
+
+	// measures an entry being written, preferring the size the backend reports, but only if anything uses it.
+	pub(crate) fn record_payload<S: Entry>(&self, table: &str, key: &str, entry: &S) {
+		if !self.stats.measures() {
+			return;
+		}
+
+		let size = match self.backend.entry_size(table, key, entry) {
+			Some(size) => size,
+			None => match serde_json::to_vec(entry) {
+				Ok(serialized) => serialized.len(),
+				Err(e) => {
+					log::warn!("entry {key:?} in table {table:?} couldn't be measured: {e}");
+
+					return;
+				}
+			},
+		};
+
+		self.stats.record_size(table, key, size);
+	}
+
+	/// Returns the entry size, in bytes, over which writes are logged as warnings.
+	#[must_use]
+	pub fn large_entry_warning_bytes(&self) -> Option<usize> {
+		self.stats.large_entry_warning_bytes()
+	}
+
+	/// Queues a write to be flushed to the backend later with [`Self::flush`].
+	///
+	/// Only the latest queued write for each key is kept, so a key updated many times between
+	/// flushes is only written once. Queued writes aren't visible to reads until they're flushed.
+	pub fn write_behind<S, K>(&self, table: &str, key: &K, entry: S)
+	where
+		S: Entry + 'static,
+		K: Key,
+	{
+		self.write_behind.queue(
+			self.interner.intern(table),
+			self.interner.intern(&key.to_key()),
+			entry,
+			None,
+		);
+	}
+
+	/// Queues a write like [`Self::write_behind`], keeping the entry's secondary [`IndexEntry::indexes`]
+	/// up to date when it's flushed.
+	pub fn write_behind_indexed<S, K>(&self, table: &str, key: &K, entry: S)
+	where
+		S: IndexEntry + 'static,
+		K: Key,
+	{
+		self.write_behind.queue(
+			self.interner.intern(table),
+			self.interner.intern(&key.to_key()),
+			entry,
+			Some(S::indexes),
+		);
+	}
+
+	/// Flushes the writes queued with [`Self::write_behind`], returning how many were written.
+	///
+	/// Each write is ran as a [`CreateEntryAction`] that replaces any existing entry, so it's validated
+	/// against the table's schema, ran through the chart's hooks, and creates missing tables if
+	/// [`StarchartBuilder::auto_create_tables`] is set, like any other write.
+	///
+	/// # Errors
+	///
+	/// Any errors that [`CreateEntryAction::run_create_entry`] can raise, the failed write and any
+	/// that weren't reached are queued again.
+	///
+	/// [`CreateEntryAction`]: crate::action::CreateEntryAction
+	/// [`CreateEntryAction::run_create_entry`]: crate::action::CreateEntryAction::run_create_entry
+	pub async fn flush(&self) -> Result<usize, Error> {
+		Ok(self.write_behind.flush(self).await?)
+	}
+
+	/// Returns the counters for writes queued with [`Self::write_behind`].
+	pub fn write_behind_stats(&self) -> WriteBehindStats {
+		self.write_behind.stats()
+	}
+
+	/// Removes every expired entry from a table, returning how many were removed.
+	///
+	/// Pinned entries are never removed. This can be called periodically to clean up entries
+	/// that are never read again.
+	///
+	/// # Errors
+	///
+	/// Any errors that [`Backend::get_keys`], [`Backend::get`], or [`Backend::delete`] can raise.
+	pub async fn purge_expired(&self, table: &str) -> Result<usize, Error> {
+		let lock = self.guard.exclusive(table);
+
+		let backend = &*self.backend;
+
+		let expired = expiry::expired(backend, table, self.clock.now())
+			.await
+			.map_err(|e| Error::backend(Some(Box::new(e))))?;
+
+		let mut purged = 0;
+		for key in expired {
+			if self.pins.is_pinned(table, &key) {
+				continue;
+			}
+
+			self.remove_expired(table, &key).await?;
+
+			purged += 1;
+		}
+
+		drop(lock);
+
+		Ok(purged)
+	}
+
+	/// Removes every expired entry from a table in the background, on the chart's [`Spawner`].
+	///
+	/// Errors are logged rather than returned, use [`Self::purge_expired`] to wait on the result.
+	pub fn purge_expired_in_background(&self, table: &str)
+	where
+		B: 'static,
+	{
+		let chart = self.clone();
+		let table = table.to_owned();
+
+		self.spawn(async move {
+			if let Err(e) = chart.purge_expired(&table).await {
+				log::warn!("failed to purge the expired entries of table {table:?}: {e}");
+			}
+		});
+	}
+
+	/// Removes a single entry that was found to be expired while reading it.
+	pub(crate) async fn purge_expired_entry(
+		&self,
+		table: &str,
+		key: &str,
+	) -> Result<(), ActionRunError> {
+		let lock = self.guard.exclusive(table);
+
+		let backend = &*self.backend;
+
+		let result = async {
+			let expired = expiry::is_expired(backend, table, key, self.clock.now())
+				.await
+				.map_err(|e| ActionRunError {
+					source: Some(Box::new(e)),
+					kind: ActionRunErrorType::Backend,
+				})?;
+
+			if expired {
+				self.remove_expired(table, key).await?;
+			}
+
+			Ok(())
+		}
+		.await;
+
+		drop(lock);
+
+		result
+	}
+
+	/// Checks whether an entry has expired and isn't pinned, so reads should skip it.
+	pub(crate) async fn is_expired(&self, table: &str, key: &str) -> Result<bool, B::Error> {
+		if self.pins.is_pinned(table, key) {
+			return Ok(false);
+		}
+
+		expiry::is_expired(&*self.backend, table, key, self.clock.now()).await
+	}
+
+	/// Returns the keys of a table's expired entries that aren't pinned, so reads over the table can skip them.
+	pub(crate) async fn expired_keys(&self, table: &str) -> Result<HashSet<String>, B::Error> {
+		let expired = expiry::expired(&*self.backend, table, self.clock.now()).await?;
+
+		Ok(expired
+			.into_iter()
+			.filter(|key| !self.pins.is_pinned(table, key))
+			.collect())
+	}
+
+	/// Removes an expired entry along with it's expiry time, the table must already be locked.
+	pub(crate) async fn remove_expired(
+		&self,
+		table: &str,
+		key: &str,
+	) -> Result<(), ActionRunError> {
+		async {
+			self.record_change(table, Some(key), ChangeKind::Delete)
+				.await?;
+
+			self.backend.delete(table, key).await?;
+
+			index::remove_key(self, table, key).await?;
+			expiry::clear(&*self.backend, table, key).await?;
+
+			self.record_write(table, key).await
+		}
+		.await
+		.map_err(|e: B::Error| ActionRunError {
+			source: Some(Box::new(e)),
+			kind: ActionRunErrorType::Backend,
+		})?;
+
+		self.retain::<()>(table, key, None).await
+	}
+
+	/// Retains the entry just written to `key` if the table keeps a history, with [`None`] recording a delete.
+	///
+	/// This is called after [`Self::record_write`], so the revision is recorded at the entry's new version.
+	pub(crate) async fn retain<S: Serialize + Sync + ?Sized>(
+		&self,
+		table: &str,
+		key: &str,
+		entry: Option<&S>,
+	) -> Result<(), ActionRunError> {
+		if self.history_limit(table).is_none() {
+			return Ok(());
+		}
+
+		let entry = entry
+			.map(serde_json::to_string)
+			.transpose()
+			.map_err(|e| ActionRunError {
+				source: Some(Box::new(e)),
+				kind: ActionRunErrorType::Retention,
+			})?;
+
+		self.retain_encoded(table, key, entry).await
+	}
+
+	/// Retains an entry already encoded as JSON, see [`Self::retain`].
+	pub(crate) async fn retain_encoded(
+		&self,
+		table: &str,
+		key: &str,
+		entry: Option<String>,
+	) -> Result<(), ActionRunError> {
+		let Some(limit) = self.history_limit(table) else {
+			return Ok(());
+		};
+
+		version::retain(&*self.backend, table, key, entry, self.clock.now(), limit)
+			.await
+			.map_err(|e| ActionRunError {
+				source: Some(Box::new(e)),
+				kind: ActionRunErrorType::Retention,
+			})
+	}
+
+	/// Creates the [`Observer`] running an operation of the chart between it's hooks, as an action without entries.
+	fn observer<'a>(
+		&self,
+		kind: ActionKind,
+		target: TargetKind,
+		table: &'a str,
+		key: Option<&str>,
+	) -> Observer<'a> {
+		Observer::with_entries(self, kind, target, Some(table), key, Vec::new)
+	}
+
+	/// Fails with an [`ErrorType::Untyped`] error if the backend can't read entries without knowing their type.
+	///
+	/// [`ErrorType::Untyped`]: crate::error::ErrorType::Untyped
+	pub(crate) fn check_self_describing(&self) -> Result<(), Error> {
+		if self.backend.is_self_describing() {
+			Ok(())
+		} else {
+			Err(Error::untyped())
+		}
+	}
+
+	/// Bumps the version of a written entry.
+	///
+	/// Tables that keep a history start tracking versions here. The entry is retained separately with
+	/// [`Self::retain`], as that needs the entry that was written, and the write is recorded in the journal
+	/// with [`Self::record_change`] before it's made.
+	pub(crate) async fn record_write(&self, table: &str, key: &str) -> Result<(), B::Error> {
+		let track = self.history_limit(table).is_some();
+
+		version::bump(&*self.backend, table, key, track).await
+	}
+
+	/// Appends a record to the journal if journaling is enabled, this is called before the change is made.
+	pub(crate) async fn record_change(
+		&self,
+		table: &str,
+		key: Option<&str>,
+		kind: ChangeKind,
+	) -> Result<(), B::Error> {
+		let Some(journal) = &self.journal else {
+			return Ok(());
+		};
+
+		let lock = journal.lock().await;
+
+		journal::append(&*self.backend, table, key, kind, self.clock.now()).await?;
+
+		drop(lock);
+
+		Ok(())
+	}
+
+	/// Pins an entry, so eviction, expiry, and archival will never remove it.
+	///
+	/// Pinned entries can still be deleted explicitly, and entries can be pinned before they're created.
+	/// Returns whether the entry wasn't already pinned.
+	pub fn pin<K: Key>(&self, table: &str, key: &K) -> bool {
+		let key = key.to_key();
+
+		self.backend.set_pinned(table, &key, true);
+
+		self.pins.pin(table, &key)
+	}
+
+	/// Unpins an entry, returning whether it was pinned.
+	pub fn unpin<K: Key>(&self, table: &str, key: &K) -> bool {
+		let key = key.to_key();
+
+		self.backend.set_pinned(table, &key, false);
+
+		self.pins.unpin(table, &key)
+	}
+
+	/// Checks whether an entry is pinned.
+	#[must_use]
+	pub fn is_pinned<K: Key>(&self, table: &str, key: &K) -> bool {
+		self.pins.is_pinned(table, &key.to_key())
+	}
+
+	/// Finds all entries in a table whose secondary index matches the given value.
+	///
+	/// This reads the index table maintained by actions that set entries with [`Action::set_entry`],
+	/// rather than the whole table. Index entries that no longer match the stored entry are skipped.
+	///
+	/// # Errors
+	///
+	/// Returns an [`ErrorType::InvalidIndex`] error if the index name is empty or contains `__`,
+	/// an [`ErrorType::TableMissing`] error if the table doesn't exist,
+	/// or any errors that [`Backend::has_table`] or [`Backend::get`] can raise.
+	///
+	/// [`Action::set_entry`]: crate::Action::set_entry
+	/// [`ErrorType::InvalidIndex`]: crate::error::ErrorType::InvalidIndex
+	/// [`ErrorType::TableMissing`]: crate::error::ErrorType::TableMissing
+	pub async fn find_by_index<S, K, I>(
+		&self,
+		table: &str,
+		index: &str,
+		value: &K,
+	) -> Result<I, Error>
+	where
+		S: IndexEntry,
+		K: Key + Sync,
+		I: FromIterator<S>,
+	{
+		if !index::is_valid(index) {
+			return Err(Error::invalid_index());
+		}
+
+		let lock = self.guard.shared(table);
+
+		let value = value.to_key();
+		let index_table = index::table_name(table, index);
+
+		let backend = &*self.backend;
+
+		if !backend
+			.has_table(table)
+			.await
+			.map_err(|e| Error::backend(Some(Box::new(e))))?
+		{
+			drop(lock);
+			return Err(Error::table_missing());
+		}
+
+		if !backend
+			.has_table(&index_table)
+			.await
+			.map_err(|e| Error::backend(Some(Box::new(e))))?
+		{
+			drop(lock);
+			return Ok(I::from_iter(None));
+		}
+
+		let keys = backend
+			.get::<Vec<String>>(&index_table, &value)
+			.await
+			.map_err(|e| Error::backend(Some(Box::new(e))))?
+			.unwrap_or_default();
+
+		let mut entries = Vec::with_capacity(keys.len());
+		let mut expired = Vec::new();
+		for key in &keys {
+			let entry = backend
+				.get::<S>(table, key)
+				.await
+				.map_err(|e| Error::backend(Some(Box::new(e))))?;
+
+			let Some(entry) = entry else {
+				continue;
+			};
+
+			if self
+				.is_expired(table, key)
+				.await
+				.map_err(|e| Error::backend(Some(Box::new(e))))?
+			{
+				expired.push(key);
+			} else if entry
+				.indexes()
+				.iter()
+				.any(|(i, v)| *i == index && *v == value)
+			{
+				entries.push(entry);
+			}
+		}
+
+		drop(lock);
+
+		for key in expired {
+			self.purge_expired_entry(table, key).await?;
+		}
+
+		Ok(entries.into_iter().collect())
+	}
+
+	/// Reads a page of up to `limit` entries from a table, starting after the given [`PageToken`].
+	///
+	/// Pages are ordered by when entries were first seen, so entries inserted or deleted between
+	/// reads never cause other entries to be skipped or returned twice. Pass [`None`] to read the first page.
+	///
+	/// # Errors
+	///
+	/// Returns an [`ErrorType::InvalidPageToken`] error if the token wasn't created by this chart for this table,
+	/// or any errors that [`Backend::get_keys`] or [`Backend::get`] can raise.
+	///
+	/// [`ErrorType::InvalidPageToken`]: crate::error::ErrorType::InvalidPageToken
+	pub async fn read_page<S: Entry>(
+		&self,
+		table: &str,
+		after: Option<&PageToken>,
+		limit: usize,
+	) -> Result<Page<S>, Error> {
+		let after = match after {
+			Some(token) => self
+				.paginator
+				.verify(table, token)
+				.ok_or_else(Error::invalid_page_token)?,
+			None => 0,
+		};
+
+		let backend = &*self.backend;
+
+		// pages are read under a shared lock, which is only upgraded when keys were added or removed
+		// since the last page read and their sequence numbers have to be saved.
+		let mut shared = Some(self.guard.shared(table));
+		let mut exclusive = None;
+
+		let mut sequences = self.page_sequences(table).await?;
+		if sequences.1 {
+			shared = None;
+			exclusive = Some(self.guard.exclusive(table));
+
+			sequences = self.page_sequences(table).await?;
+			if sequences.1 {
+				pagination::save(backend, table, &sequences.0)
+					.await
+					.map_err(|e| Error::backend(Some(Box::new(e))))?;
+			}
+		}
+
+		let (keys, next) = sequences.0.page(after, limit);
+
+		let mut entries = Vec::with_capacity(keys.len());
+		let mut expired = Vec::new();
+		for key in keys {
+			let entry = backend
+				.get::<S>(table, key)
+				.await
+				.map_err(|e| Error::backend(Some(Box::new(e))))?;
+
+			let Some(entry) = entry else {
+				continue;
+			};
+
+			if self
+				.is_expired(table, key)
+				.await
+				.map_err(|e| Error::backend(Some(Box::new(e))))?
+			{
+				expired.push(key.to_owned());
+			} else {
+				entries.push((key.to_owned(), entry));
+			}
+		}
+
+		drop((shared, exclusive));
+
+		for key in &expired {
+			self.purge_expired_entry(table, key).await?;
+		}
+
+		Ok(Page {
+			entries,
+			next: next.map(|sequence| self.paginator.sign(table, sequence)),
 		})
 	}
+
+	/// Loads the sequence numbers of the table's entries for [`Self::read_page`], and whether they
+	/// changed and need to be saved.
+	async fn page_sequences(&self, table: &str) -> Result<(Sequences, bool), Error> {
+		let backend = &*self.backend;
+
+		if !backend
+			.has_table(table)
+			.await
+			.map_err(|e| Error::backend(Some(Box::new(e))))?
+		{
+			return Err(Error::from(ActionRunError {
+				source: None,
+				kind: ActionRunErrorType::MissingTable,
+			}));
+		}
+
+		let keys = backend
+			.get_keys::<Vec<_>>(table)
+			.await
+			.map_err(|e| Error::backend(Some(Box::new(e))))?;
+
+		pagination::sequences(backend, table, &keys)
+			.await
+			.map_err(|e| Error::backend(Some(Box::new(e))))
+	}
+
+	/// Reads an entry as it was at an earlier point, either a time or a [version].
+	///
+	/// Every write is retained for the window set with [`StarchartBuilder::retain_versions`], or the limit set
+	/// for the table with [`StarchartBuilder::keep_history`]. Entries that haven't been written since then are
+	/// returned as they are now, as they haven't changed.
+	///
+	/// # Errors
+	///
+	/// Returns an [`ErrorType::NotRetained`] error if the table doesn't keep a history, or the point is older than the
+	/// retained versions of the entry, an [`ErrorType::TableMissing`] error if the table doesn't exist,
+	/// or any errors that [`Backend::get`] can raise.
+	///
+	/// [version]: crate::version
+	/// [`ErrorType::NotRetained`]: crate::error::ErrorType::NotRetained
+	/// [`ErrorType::TableMissing`]: crate::error::ErrorType::TableMissing
+	pub async fn read_entry_at<S, K, P>(
+		&self,
+		table: &str,
+		key: &K,
+		at: P,
+	) -> Result<Option<S>, Error>
+	where
+		S: Entry,
+		K: Key + Sync,
+		P: Into<PointInTime> + Send,
+	{
+		if self.history_limit(table).is_none() {
+			return Err(Error::not_retained());
+		}
+
+		let at = at.into();
+		let key = key.to_key();
+
+		let lock = self.guard.shared(table);
+
+		let backend = &*self.backend;
+
+		if !backend
+			.has_table(table)
+			.await
+			.map_err(|e| Error::backend(Some(Box::new(e))))?
+		{
+			drop(lock);
+			return Err(Error::table_missing());
+		}
+
+		let retained = version::retained(backend, table, &key, at)
+			.await
+			.map_err(|e| Error::backend(Some(Box::new(e))))?;
+
+		let entry = match retained {
+			Retained::Found(entry) => entry
+				.as_deref()
+				.map(serde_json::from_str)
+				.transpose()
+				.map_err(Error::serialization),
+			Retained::Unchanged => {
+				let unchanged = match at {
+					PointInTime::Time(_) => true,
+					PointInTime::Version(version) => {
+						version::current(backend, table, &key)
+							.await
+							.map_err(|e| Error::backend(Some(Box::new(e))))?
+							== version
+					}
+				};
+
+				if unchanged {
+					backend
+						.get(table, &key)
+						.await
+						.map_err(|e| Error::backend(Some(Box::new(e))))
+				} else {
+					Err(Error::not_retained())
+				}
+			}
+			Retained::Pruned => Err(Error::not_retained()),
+		};
+
+		drop(lock);
+
+		entry
+	}
+
+	/// Lists the kept revisions of an entry, oldest first, with the newest being the entry as it is now.
+	///
+	/// Entries that haven't been written since the table started keeping a history have no revisions.
+	///
+	/// # Errors
+	///
+	/// Returns an [`ErrorType::NotRetained`] error if the table doesn't keep a history, an [`ErrorType::TableMissing`]
+	/// error if the table doesn't exist, an [`ErrorType::Serialization`] error if a revision doesn't match the entry type,
+	/// or any errors that [`Backend::get`] can raise.
+	///
+	/// [`ErrorType::NotRetained`]: crate::error::ErrorType::NotRetained
+	/// [`ErrorType::TableMissing`]: crate::error::ErrorType::TableMissing
+	/// [`ErrorType::Serialization`]: crate::error::ErrorType::Serialization
+	pub async fn history<S, K>(&self, table: &str, key: &K) -> Result<Vec<Revision<S>>, Error>
+	where
+		S: Entry,
+		K: Key + Sync,
+	{
+		if self.history_limit(table).is_none() {
+			return Err(Error::not_retained());
+		}
+
+		let key = key.to_key();
+
+		let lock = self.guard.shared(table);
+
+		let backend = &*self.backend;
+
+		if !backend
+			.has_table(table)
+			.await
+			.map_err(|e| Error::backend(Some(Box::new(e))))?
+		{
+			drop(lock);
+			return Err(Error::table_missing());
+		}
+
+		let revisions = version::history(backend, table, &key)
+			.await
+			.map_err(|e| Error::backend(Some(Box::new(e))))?;
+
+		drop(lock);
+
+		revisions.map_err(Error::serialization)
+	}
+
+	/// Checks that the [`Backend`] can serve requests, so services can wire it into readiness probes.
+	///
+	/// # Errors
+	///
+	/// Returns any errors that [`Backend::health_check`] can raise.
+	pub async fn health(&self) -> Result<(), Error> {
+		self.backend
+			.health_check()
+			.await
+			.map_err(|e| Error::backend(Some(Box::new(e))))
+	}
+
+	/// Lists the names of every table, in no particular order.
+	///
+	/// The tables the chart stores it's own metadata in, such as expiry times, revisions, and indexes,
+	/// are left out. These all start with `__`, so table names starting with `__` are reserved.
+	/// Tables of a [`Self::child`] chart are listed with their prefix.
+	///
+	/// # Errors
+	///
+	/// Returns any errors that [`Backend::tables`] can raise.
+	pub async fn tables<I: FromIterator<String>>(&self) -> Result<I, Error> {
+		let tables = self
+			.backend
+			.tables::<Vec<String>>()
+			.await
+			.map_err(|e| Error::backend(Some(Box::new(e))))?;
+
+		Ok(tables
+			.into_iter()
+			.filter(|table| !util::is_internal_table(table))
+			.collect())
+	}
+
+	/// Renames a table, moving it's entries along with their expiry times, versions, and indexes.
+	///
+	/// Settings registered by table name, such as history limits, loaders, and schemas, aren't moved,
+	/// and pins and page tokens for the old name are dropped.
+	///
+	/// # Errors
+	///
+	/// Returns an [`ErrorType::TableMissing`] error if the table doesn't exist, an [`ErrorType::TableExists`]
+	/// error if a table named `new` already exists, or any errors that [`Backend::rename_table`] can raise.
+	///
+	/// [`ErrorType::TableMissing`]: crate::error::ErrorType::TableMissing
+	/// [`ErrorType::TableExists`]: crate::error::ErrorType::TableExists
+	pub async fn rename_table(&self, table: &str, new: &str) -> Result<(), Error> {
+		self.observer(ActionKind::Update, TargetKind::Table, table, None)
+			.run_chart(async move {
+				let lock = self.guard.exclusive_many([table, new].iter().copied());
+
+				let backend = &*self.backend;
+
+				if !backend
+					.has_table(table)
+					.await
+					.map_err(|e| Error::backend(Some(Box::new(e))))?
+				{
+					return Err(Error::table_missing());
+				}
+
+				if backend
+					.has_table(new)
+					.await
+					.map_err(|e| Error::backend(Some(Box::new(e))))?
+				{
+					return Err(Error::table_exists());
+				}
+
+				let result = async {
+					self.record_change(table, Some(new), ChangeKind::RenameTable)
+						.await?;
+
+					backend.rename_table(table, new).await?;
+					expiry::rename_table(backend, table, new).await?;
+					version::rename_table(backend, table, new).await?;
+					index::rename_table(self, table, new).await?;
+					pagination::clear(backend, table).await?;
+
+					self.pins.clear_table(table);
+					self.repairs.clear_table(table);
+
+					Ok::<_, B::Error>(())
+				}
+				.await;
+
+				drop(lock);
+
+				result.map_err(|e| Error::backend(Some(Box::new(e))))
+			})
+			.await
+	}
+
+	/// Deletes every entry in a table, along with their expiry times, versions, and indexes, keeping the table itself.
+	///
+	/// The table's metadata is kept, so actions on it won't need to repair it.
+	///
+	/// # Errors
+	///
+	/// Returns an [`ErrorType::TableMissing`] error if the table doesn't exist,
+	/// or any errors that [`Backend::truncate_table`] can raise.
+	///
+	/// [`ErrorType::TableMissing`]: crate::error::ErrorType::TableMissing
+	pub async fn truncate_table(&self, table: &str) -> Result<(), Error> {
+		self.observer(ActionKind::Delete, TargetKind::Table, table, None)
+			.run_chart(async move {
+				let lock = self.guard.exclusive(table);
+
+				let backend = &*self.backend;
+
+				if !backend
+					.has_table(table)
+					.await
+					.map_err(|e| Error::backend(Some(Box::new(e))))?
+				{
+					return Err(Error::table_missing());
+				}
+
+				let result = async {
+					self.record_change(table, None, ChangeKind::TruncateTable)
+						.await?;
+
+					self.truncate_entries(table).await?;
+
+					expiry::clear_table(backend, table).await?;
+					version::clear_table(backend, table).await?;
+					index::clear_table(self, table).await?;
+					pagination::clear(backend, table).await?;
+
+					self.pins.clear_table(table);
+					self.repairs.clear_table(table);
+
+					Ok::<_, B::Error>(())
+				}
+				.await;
+
+				drop(lock);
+
+				result.map_err(|e| Error::backend(Some(Box::new(e))))
+			})
+			.await
+	}
+
+	/// Deletes every entry in a table but it's metadata.
+	async fn truncate_entries(&self, table: &str) -> Result<(), B::Error> {
+		let backend = &*self.backend;
+
+		// the metadata entry can't be read back without it's type, so every other entry is deleted around it.
+		if cfg!(feature = "metadata") && !backend.is_self_describing() {
+			for key in backend.get_keys::<Vec<String>>(table).await? {
+				if !util::is_metadata(&key) {
+					backend.delete(table, &key).await?;
+				}
+			}
+
+			return Ok(());
+		}
+
+		#[cfg(feature = "metadata")]
+		let metadata = backend
+			.get::<SchemaValue>(table, crate::METADATA_KEY)
+			.await?;
+
+		backend.truncate_table(table).await?;
+
+		#[cfg(feature = "metadata")]
+		if let Some(metadata) = metadata {
+			backend
+				.create(table, crate::METADATA_KEY, &metadata)
+				.await?;
+		}
+
+		Ok(())
+	}
+
+	/// Describes a table, sampling [`describe::DEFAULT_SAMPLES`] entries, see [`Self::describe_sampled`].
+	///
+	/// # Errors
+	///
+	/// See [`Self::describe_sampled`].
+	pub async fn describe(&self, table: &str) -> Result<TableDescription, Error> {
+		self.describe_sampled(table, describe::DEFAULT_SAMPLES)
+			.await
+	}
+
+	/// Describes a table with it's schema, entry count, payload stats, indexes, and up to `samples` entries.
+	///
+	/// Entries are read as untyped values, so this works without the table's entry type, as long as
+	/// the [`Backend`] stores entries in a self-describing format. Without a registered schema, the
+	/// schema is inferred from the sampled entries.
+	///
+	/// # Errors
+	///
+	/// Returns an [`ErrorType::TableMissing`] error if the table doesn't exist, an [`ErrorType::Untyped`] error
+	/// if the backend isn't self-describing, or any errors that [`Backend::get_keys`] or [`Backend::get`] can raise.
+	///
+	/// [`ErrorType::TableMissing`]: crate::error::ErrorType::TableMissing
+	/// [`ErrorType::Untyped`]: crate::error::ErrorType::Untyped
+	pub async fn describe_sampled(
+		&self,
+		table: &str,
+		samples: usize,
+	) -> Result<TableDescription, Error> {
+		self.check_self_describing()?;
+
+		let lock = self.guard.shared(table);
+
+		let backend = &*self.backend;
+
+		if !backend
+			.has_table(table)
+			.await
+			.map_err(|e| Error::backend(Some(Box::new(e))))?
+		{
+			drop(lock);
+			return Err(Error::table_missing());
+		}
+
+		let description = describe::read(backend, table, samples)
+			.await
+			.map_err(|e| Error::backend(Some(Box::new(e))));
+
+		drop(lock);
+
+		let mut description = description?;
+		description.payload = self.payload_stats(table);
+
+		Ok(description)
+	}
+
+	/// Writes every entry in a table to `writer` as pretty-printed JSON, keyed and sorted by entry key.
+	///
+	/// The output is the same for every [`Backend`], regardless of how it stores or orders entries,
+	/// so it can be used for golden-file tests and support bundles. The metadata entry is omitted.
+	///
+	/// # Errors
+	///
+	/// Returns an [`ErrorType::TableMissing`] error if the table doesn't exist, an [`ErrorType::Serialization`] error
+	/// if the entries couldn't be serialized, an [`ErrorType::Io`] error if they couldn't be written,
+	/// or any errors that [`Backend::get_keys`] or [`Backend::get`] can raise.
+	///
+	/// [`ErrorType::TableMissing`]: crate::error::ErrorType::TableMissing
+	/// [`ErrorType::Serialization`]: crate::error::ErrorType::Serialization
+	/// [`ErrorType::Io`]: crate::error::ErrorType::Io
+	pub async fn dump_pretty<S, W>(&self, table: &str, mut writer: W) -> Result<(), Error>
+	where
+		S: Entry,
+		W: Write + Send,
+	{
+		let lock = self.guard.shared(table);
+
+		let backend = &*self.backend;
+
+		if !backend
+			.has_table(table)
+			.await
+			.map_err(|e| Error::backend(Some(Box::new(e))))?
+		{
+			drop(lock);
+			return Err(Error::table_missing());
+		}
+
+		let keys = backend
+			.get_keys::<Vec<String>>(table)
+			.await
+			.map_err(|e| Error::backend(Some(Box::new(e))))?;
+
+		let mut entries = BTreeMap::new();
+		for key in keys {
+			if util::is_metadata(&key) {
+				continue;
+			}
+
+			let entry = backend
+				.get::<S>(table, &key)
+				.await
+				.map_err(|e| Error::backend(Some(Box::new(e))))?;
+
+			if let Some(entry) = entry {
+				entries.insert(
+					key,
+					serde_json::to_value(entry).map_err(Error::serialization)?,
+				);
+			}
+		}
+
+		drop(lock);
+
+		serde_json::to_writer_pretty(&mut writer, &entries).map_err(Error::json)?;
+		writeln!(writer).map_err(Error::io)
+	}
+
+	/// Backs up every entry of the given tables to `writer` as newline-delimited JSON, returning the amount of entries written.
+	///
+	/// Up to `parallelism` tables are read at once, and entries are written as they're read, so memory use
+	/// doesn't grow with the size of the tables. The tables are locked for reading for the whole backup, so it's
+	/// consistent across tables. Only entries are backed up, expiry times and versions are not.
+	///
+	/// # Errors
+	///
+	/// Returns an [`ErrorType::TableMissing`] error if any of the tables don't exist, an [`ErrorType::Io`] error
+	/// if the backup couldn't be written, an [`ErrorType::Untyped`] error if the backend isn't self-describing,
+	/// or any errors that [`Backend::get_keys`] or [`Backend::get`] can raise.
+	///
+	/// [`ErrorType::TableMissing`]: crate::error::ErrorType::TableMissing
+	/// [`ErrorType::Io`]: crate::error::ErrorType::Io
+	/// [`ErrorType::Untyped`]: crate::error::ErrorType::Untyped
+	pub async fn backup<W: Write + Send>(
+		&self,
+		tables: &[&str],
+		writer: W,
+		parallelism: usize,
+	) -> Result<usize, Error> {
+		self.check_self_describing()?;
+
+		let lock = self.guard.shared_many(tables.iter().copied());
+
+		let written = backup::write(&*self.backend, tables, writer, parallelism).await;
+
+		drop(lock);
+
+		written
+	}
+
+	/// Restores a backup written by [`Self::backup`], returning the amount of entries restored.
+	///
+	/// Up to `parallelism` entries are written at once. Missing tables are created, and entries
+	/// that already exist are replaced, while entries that aren't in the backup are left untouched.
+	///
+	/// # Errors
+	///
+	/// Returns an [`ErrorType::Io`] error if the backup couldn't be read, an [`ErrorType::Serialization`] error if
+	/// it's invalid, an [`ErrorType::Untyped`] error if the backend isn't self-describing, or any errors that
+	/// [`Backend::ensure_table`] or [`Backend::replace`] can raise.
+	///
+	/// [`ErrorType::Io`]: crate::error::ErrorType::Io
+	/// [`ErrorType::Serialization`]: crate::error::ErrorType::Serialization
+	/// [`ErrorType::Untyped`]: crate::error::ErrorType::Untyped
+	pub async fn restore<R: BufRead + Send>(
+		&self,
+		reader: R,
+		parallelism: usize,
+	) -> Result<usize, Error> {
+		self.check_self_describing()?;
+
+		let lock = self.guard.exclusive_all();
+
+		let restored = backup::read(&*self.backend, reader, parallelism, None).await;
+
+		drop(lock);
+
+		restored
+	}
+
+	/// Exports every entry of a table to `writer`, returning the amount of entries written.
+	///
+	/// Exports use the same newline-delimited JSON format as [`Self::backup`], which doesn't
+	/// depend on the backend, so they can be used to move a table to a different backend with [`Self::import`].
+	///
+	/// # Errors
+	///
+	/// Returns an [`ErrorType::TableMissing`] error if the table doesn't exist, an [`ErrorType::Io`] error
+	/// if the export couldn't be written, an [`ErrorType::Untyped`] error if the backend isn't self-describing,
+	/// or any errors that [`Backend::get_keys`] or [`Backend::get`] can raise.
+	///
+	/// [`ErrorType::TableMissing`]: crate::error::ErrorType::TableMissing
+	/// [`ErrorType::Io`]: crate::error::ErrorType::Io
+	/// [`ErrorType::Untyped`]: crate::error::ErrorType::Untyped
+	pub async fn export<W: Write + Send>(&self, table: &str, writer: W) -> Result<usize, Error> {
+		self.check_self_describing()?;
+
+		let lock = self.guard.shared(table);
+
+		let written = backup::write(&*self.backend, &[table], writer, 1).await;
+
+		drop(lock);
+
+		written
+	}
+
+	/// Imports an export written by [`Self::export`] into a table, returning the amount of entries imported.
+	///
+	/// The table doesn't need to have the same name as the exported one, and is created if it's missing.
+	/// Entries that already exist are replaced, while entries that aren't in the export are left untouched.
+	///
+	/// # Errors
+	///
+	/// Returns an [`ErrorType::Io`] error if the export couldn't be read, an [`ErrorType::Serialization`] error
+	/// if it's invalid or holds entries from more than one table, an [`ErrorType::Untyped`] error if the backend
+	/// isn't self-describing, or any errors that [`Backend::ensure_table`] or [`Backend::replace`] can raise.
+	///
+	/// [`ErrorType::Io`]: crate::error::ErrorType::Io
+	/// [`ErrorType::Serialization`]: crate::error::ErrorType::Serialization
+	/// [`ErrorType::Untyped`]: crate::error::ErrorType::Untyped
+	pub async fn import<R: BufRead + Send>(&self, table: &str, reader: R) -> Result<usize, Error> {
+		self.check_self_describing()?;
+
+		self.observer(ActionKind::Create, TargetKind::Table, table, None)
+			.run_chart(async move {
+				let lock = self.guard.exclusive(table);
+
+				let imported = backup::read(&*self.backend, reader, 1, Some(table)).await;
+
+				drop(lock);
+
+				imported
+			})
+			.await
+	}
+
+	/// Registers a loader for a table, replacing any existing one.
+	///
+	/// When reading an entry of type `S` misses the backend, the loader is called with the key,
+	/// and any value it returns is created in the table, as a [`CreateEntryAction`] would, before being returned.
+	/// Reads of other types fail instead of converting the loaded value.
+	///
+	/// Concurrent misses for the same key share a single call to the loader.
+	pub fn register_loader<S, F, Fut>(&self, table: &str, loader: F)
+	where
+		S: Entry + 'static,
+		F: Fn(String) -> Fut + Send + Sync + 'static,
+		Fut: Future<Output = Option<S>> + Send + 'static,
+	{
+		self.loaders.insert(table, loader);
+	}
+
+	/// Registers a loader that can fail for a table, replacing any existing one.
+	///
+	/// Errors are returned to every read waiting on the failed load, and are remembered
+	/// for the window set with [`StarchartBuilder::loader_error_ttl`].
+	pub fn register_fallible_loader<S, F, Fut, E>(&self, table: &str, loader: F)
+	where
+		S: Entry + 'static,
+		F: Fn(String) -> Fut + Send + Sync + 'static,
+		Fut: Future<Output = Result<Option<S>, E>> + Send + 'static,
+		E: std::error::Error + Send + Sync + 'static,
+	{
+		self.loaders.insert_fallible(table, loader);
+	}
+
+	/// Removes the loader for a table, returning whether there was one.
+	#[allow(clippy::must_use_candidate)]
+	pub fn unregister_loader(&self, table: &str) -> bool {
+		self.loaders.remove(table)
+	}
+
+	/// Checks whether a table has a loader registered.
+	#[must_use]
+	pub fn has_loader(&self, table: &str) -> bool {
+		self.loaders.contains(table)
+	}
+
+	/// Runs a closure that stages writes on a [`Transaction`], then commits them atomically.
+	///
+	/// Nothing is written if the closure returns an error, and if committing fails part way through,
+	/// the writes that were applied are rolled back. Staged writes keep expiry times and indexes up to date
+	/// the same way actions do, see [`Transaction`]. Every staged write is ran through the chart's
+	/// [`Hook`]s as an action on it's entry, so a hook rejecting any of them rejects the whole transaction.
+	///
+	/// # Errors
+	///
+	/// Any error returned from the closure, or an [`Error`] if a staged table doesn't exist, a staged entry
+	/// doesn't match it's table's schema, a hook rejected a staged write, or committing failed. An
+	/// [`ErrorType::RollbackFailed`] error is returned if committing failed and the writes couldn't be rolled back.
+	///
+	/// [`ErrorType::RollbackFailed`]: crate::error::ErrorType::RollbackFailed
+	pub async fn transaction<F, Fut, T, E>(&self, f: F) -> Result<T, E>
+	where
+		B: TransactionalBackend + 'static,
+		F: FnOnce(Transaction<B>) -> Fut,
+		Fut: Future<Output = Result<T, E>>,
+		E: From<Error>,
+	{
+		let transaction = Transaction::new();
+
+		let value = f(transaction.clone()).await?;
+
+		let operations = transaction.into_operations();
+
+		let staged = operations
+			.iter()
+			.map(|operation| {
+				(
+					operation.table().to_owned(),
+					operation.key().to_owned(),
+					operation.kind(),
+					operation.encode().and_then(Result::ok),
+				)
+			})
+			.collect::<Vec<_>>();
+
+		// the commit is ran within the observer of every staged write, the first of them outermost.
+		let mut commit = self
+			.commit_staged(operations)
+			.map(|result| result.map_err(ActionError::chart))
+			.boxed();
+		for (table, key, kind, entry) in staged.iter().rev() {
+			let observer = Observer::with_entries(
+				self,
+				*kind,
+				TargetKind::Entry,
+				Some(table),
+				Some(key),
+				|| entry.iter().cloned().collect(),
+			);
+
+			commit = observer.run(commit).boxed();
+		}
+
+		commit.await.map_err(Error::from)?;
+
+		Ok(value)
+	}
+
+	/// Commits the operations staged by a [`Transaction`], under the exclusive locks of their tables.
+	async fn commit_staged(&self, operations: Vec<StagedOperation<B>>) -> Result<(), Error>
+	where
+		B: TransactionalBackend + 'static,
+	{
+		let tables = operations
+			.iter()
+			.map(StagedOperation::table)
+			.collect::<HashSet<_>>();
+
+		let lock = self.guard.exclusive_many(tables.iter().copied());
+
+		let backend = &*self.backend;
+
+		for table in tables {
+			let exists = backend
+				.has_table(table)
+				.await
+				.map_err(|e| Error::backend(Some(Box::new(e))))?;
+
+			if !exists {
+				return Err(Error::table_missing());
+			}
+		}
+
+		// entries are encoded for the table's schema and history before they're handed to the backend.
+		let written = operations
+			.iter()
+			.map(StagedOperation::staged_write)
+			.collect::<Vec<_>>();
+
+		#[cfg(feature = "metadata")]
+		self.check_staged_schema(&written).await?;
+
+		// creates of entries that already exist don't write anything.
+		let mut applied = Vec::with_capacity(written.len());
+		for write in written {
+			let skipped = write.kind == ActionKind::Create
+				&& backend
+					.has(&write.table, &write.key)
+					.await
+					.map_err(|e| Error::backend(Some(Box::new(e))))?;
+
+			if !skipped {
+				applied.push(write);
+			}
+		}
+
+		for write in &applied {
+			let kind = if write.kind == ActionKind::Delete {
+				ChangeKind::Delete
+			} else {
+				ChangeKind::Write
+			};
+
+			self.record_change(&write.table, Some(&write.key), kind)
+				.await
+				.map_err(|e| Error::backend(Some(Box::new(e))))?;
+		}
+
+		backend.commit(operations).await.map_err(Error::commit)?;
+
+		for write in applied {
+			self.finish_staged(write).await?;
+		}
+
+		drop(lock);
+
+		Ok(())
+	}
+
+	/// Checks that the entries staged by a [`Transaction`] match the schemas recorded in their tables' metadata.
+	#[cfg(feature = "metadata")]
+	async fn check_staged_schema(&self, written: &[StagedWrite]) -> Result<(), Error> {
+		// the recorded schema can only be read as an untyped value from self-describing backends.
+		if self.metadata_policy() == MetadataPolicy::Ignore || !self.is_self_describing() {
+			return Ok(());
+		}
+
+		let mut schemas = std::collections::HashMap::new();
+		for write in written {
+			// entries that can't be encoded are left for the backend to reject.
+			let Some(Ok(entry)) = &write.entry else {
+				continue;
+			};
+
+			if !schemas.contains_key(&write.table) {
+				let recorded = self
+					.backend
+					.get::<SchemaValue>(&write.table, crate::METADATA_KEY)
+					.await
+					.map_err(|e| Error::backend(Some(Box::new(e))))?;
+
+				schemas.insert(write.table.clone(), recorded);
+			}
+
+			let Some(recorded) = &schemas[&write.table] else {
+				continue;
+			};
+
+			let value = crate::schema::to_schema_value(entry).map_err(Error::json)?;
+
+			crate::schema::validate(recorded, &value).map_err(|e| ActionRunError {
+				source: None,
+				kind: ActionRunErrorType::Schema(e),
+			})?;
+		}
+
+		Ok(())
+	}
+
+	/// Updates the expiry time, indexes, and history of an entry written by a committed [`Transaction`].
+	async fn finish_staged(&self, write: StagedWrite) -> Result<(), Error> {
+		let StagedWrite {
+			table,
+			key,
+			kind,
+			entry,
+			indexes,
+		} = write;
+
+		// deleted and newly created entries forget any expiry time and index rows (found by key)
+		// left over from an earlier entry.
+		let cleared = matches!(kind, ActionKind::Create | ActionKind::Delete);
+
+		async {
+			if cleared {
+				expiry::clear(&*self.backend, &table, &key).await?;
+			}
+
+			if cleared || indexes.is_some() {
+				index::update(self, &table, &key, &[], &indexes.unwrap_or_default()).await?;
+			}
+
+			self.record_write(&table, &key).await
+		}
+		.await
+		.map_err(|e| Error::backend(Some(Box::new(e))))?;
+
+		let revision = self
+			.history_limit(&table)
+			.and(entry)
+			.map(|entry| entry.and_then(|entry| serde_json::to_string(&entry)))
+			.transpose()
+			.map_err(|e| ActionRunError {
+				source: Some(Box::new(e)),
+				kind: ActionRunErrorType::Retention,
+			});
+
+		match revision {
+			Ok(revision) => self.retain_encoded(&table, &key, revision).await,
+			Err(e) => Err(e),
+		}
+		.map_err(Error::from)
+	}
+
+	/// How long previous versions of entries are retained, see [`StarchartBuilder::retain_versions`].
+	#[must_use]
+	pub const fn version_retention(&self) -> Option<Duration> {
+		self.retention
+	}
+
+	/// How many revisions of each entry the table keeps, if any.
+	///
+	/// This is the limit set with [`StarchartBuilder::keep_history`], or the window set with
+	/// [`StarchartBuilder::retain_versions`] for tables without one.
+	#[must_use]
+	pub fn history_limit(&self, table: &str) -> Option<HistoryLimit> {
+		self.history
+			.get(table)
+			.copied()
+			.or_else(|| self.retention.map(HistoryLimit::Age))
+	}
+
+	/// Whether changes are recorded in the [journal], see [`StarchartBuilder::journal`].
+	///
+	/// [journal]: crate::journal
+	#[must_use]
+	pub const fn is_journaling(&self) -> bool {
+		self.journal.is_some()
+	}
+
+	/// Reads every [`JournalRecord`] from the given sequence onwards, in order.
+	///
+	/// Consumers should pass one more than the last sequence they've read, starting from 0.
+	///
+	/// # Errors
+	///
+	/// Returns an [`ErrorType::JournalDisabled`] error if journaling isn't enabled, an [`ErrorType::JournalTruncated`]
+	/// error if the records from the sequence have already been acknowledged by every consumer and deleted,
+	/// or any errors that [`Backend::get`] can raise.
+	///
+	/// [`ErrorType::JournalDisabled`]: crate::error::ErrorType::JournalDisabled
+	/// [`ErrorType::JournalTruncated`]: crate::error::ErrorType::JournalTruncated
+	pub async fn journal_from(&self, sequence: u64) -> Result<Vec<JournalRecord>, Error> {
+		let Some(journal) = &self.journal else {
+			return Err(Error::journal_disabled());
+		};
+
+		let lock = journal.lock().await;
+
+		let records = journal::read_from(&*self.backend, sequence)
+			.await
+			.map_err(|e| Error::backend(Some(Box::new(e))))?;
+
+		drop(lock);
+
+		records.ok_or_else(Error::journal_truncated)
+	}
+
+	/// Records that the consumer has read every journal record up to and including the sequence.
+	///
+	/// Segments of the journal are deleted once every consumer has acknowledged them, consumers that
+	/// have never acknowledged a record don't hold back truncation.
+	///
+	/// # Errors
+	///
+	/// Returns an [`ErrorType::JournalDisabled`] error if journaling isn't enabled,
+	/// or any errors that [`Backend::replace`] or [`Backend::delete`] can raise.
+	///
+	/// [`ErrorType::JournalDisabled`]: crate::error::ErrorType::JournalDisabled
+	pub async fn acknowledge_journal(&self, consumer: &str, sequence: u64) -> Result<(), Error> {
+		let Some(journal) = &self.journal else {
+			return Err(Error::journal_disabled());
+		};
+
+		let lock = journal.lock().await;
+
+		journal::acknowledge(&*self.backend, consumer, sequence)
+			.await
+			.map_err(|e| Error::backend(Some(Box::new(e))))?;
+
+		drop(lock);
+
+		Ok(())
+	}
+
+	/// The last journal sequence acknowledged by the consumer, if it's acknowledged any.
+	///
+	/// # Errors
+	///
+	/// Returns an [`ErrorType::JournalDisabled`] error if journaling isn't enabled,
+	/// or any errors that [`Backend::get`] can raise.
+	///
+	/// [`ErrorType::JournalDisabled`]: crate::error::ErrorType::JournalDisabled
+	pub async fn journal_acknowledged(&self, consumer: &str) -> Result<Option<u64>, Error> {
+		if self.journal.is_none() {
+			return Err(Error::journal_disabled());
+		}
+
+		journal::acknowledged(&*self.backend, consumer)
+			.await
+			.map_err(|e| Error::backend(Some(Box::new(e))))
+	}
+
+	/// Forgets a journal consumer, so it no longer holds back truncation, returning whether it existed.
+	///
+	/// # Errors
+	///
+	/// Returns an [`ErrorType::JournalDisabled`] error if journaling isn't enabled,
+	/// or any errors that [`Backend::replace`] can raise.
+	///
+	/// [`ErrorType::JournalDisabled`]: crate::error::ErrorType::JournalDisabled
+	pub async fn remove_journal_consumer(&self, consumer: &str) -> Result<bool, Error> {
+		let Some(journal) = &self.journal else {
+			return Err(Error::journal_disabled());
+		};
+
+		let lock = journal.lock().await;
+
+		let removed = journal::remove_consumer(&*self.backend, consumer)
+			.await
+			.map_err(|e| Error::backend(Some(Box::new(e))))?;
+
+		drop(lock);
+
+		Ok(removed)
+	}
+
+	/// Whether create and update actions create missing tables, see [`StarchartBuilder::auto_create_tables`].
+	#[must_use]
+	pub const fn auto_creates_tables(&self) -> bool {
+		self.auto_create_tables
+	}
+
+	/// Reads an entry, passes it to the closure to be modified, and writes it back, all under a single
+	/// exclusive lock so no other write can happen in between.
+	///
+	/// Returns the modified entry, or [`None`] if the entry doesn't exist (or has expired), in which case
+	/// the closure isn't called. The entry's secondary [`IndexEntry::indexes`] are kept up to date.
+	///
+	/// # Errors
+	///
+	/// Returns an [`ErrorType::TableMissing`] error if the table doesn't exist, an
+	/// [`ActionRunErrorType::KeyChanged`] error if the closure changes the entry's [`IndexEntry::key`],
+	/// or any errors that [`Backend::get`] or [`Backend::update`] can raise.
+	///
+	/// [`ErrorType::TableMissing`]: crate::error::ErrorType::TableMissing
+	pub async fn modify<S, K, F>(&self, table: &str, key: &K, f: F) -> Result<Option<S>, Error>
+	where
+		S: IndexEntry,
+		K: Key + Sync,
+		F: FnOnce(&mut S) + Send,
+	{
+		let key = key.to_key();
+		let action = InnerAction::<S> {
+			table: Some(table),
+			key: Some(key.clone()),
+			indexer: Some(S::indexes),
+			..InnerAction::default()
+		};
+
+		self.observer(ActionKind::Update, TargetKind::Entry, table, Some(&key))
+			.run_chart(async move {
+				Ok(action
+					.modify_entry(self, |entry| {
+						f(entry);
+
+						Ok(())
+					})
+					.await?)
+			})
+			.await
+	}
+
+	/// Reads the entries at the given keys, in the same order, with [`None`] for the missing ones.
+	///
+	/// This runs a [`ReadTableAction`] with [`ReadTableAction::run_read_entries`], reading every entry
+	/// with a single [`Backend::get_multi`] call.
+	///
+	/// # Errors
+	///
+	/// Returns any errors [`ReadTableAction::run_read_entries`] can raise.
+	///
+	/// [`ReadTableAction`]: crate::action::ReadTableAction
+	/// [`ReadTableAction::run_read_entries`]: crate::action::ReadTableAction::run_read_entries
+	pub async fn get_many<S: Entry, K: Key + Sync>(
+		&self,
+		table: &str,
+		keys: &[K],
+	) -> Result<Vec<Option<S>>, Error> {
+		Ok(ReadTableAction::for_table(table)
+			.run_read_entries(self, keys)
+			.await?)
+	}
+
+	/// Reads an entry as an untyped map of it's fields, for tooling that doesn't have the entry's type compiled in,
+	/// such as admin dashboards.
+	///
+	/// This runs a [`ReadEntryAction`], so expired entries aren't returned, and the table's loader is ran for
+	/// missing entries.
+	///
+	/// # Errors
+	///
+	/// Returns an error if the entry isn't stored as a map, an [`ErrorType::Untyped`] error if the backend
+	/// isn't self-describing, or any errors a [`ReadEntryAction`] can raise.
+	///
+	/// [`ReadEntryAction`]: crate::action::ReadEntryAction
+	/// [`ErrorType::Untyped`]: crate::error::ErrorType::Untyped
+	pub async fn get_raw<K: Key + Sync>(
+		&self,
+		table: &str,
+		key: &K,
+	) -> Result<Option<SchemaMap>, Error> {
+		self.check_self_describing()?;
+
+		let mut action = ReadEntryAction::<SchemaMap>::for_table(table);
+		action.set_key(key);
+
+		Ok(action.run_read_entry(self).await?)
+	}
+
+	/// Writes an untyped map of fields as an entry, replacing the entry if it already exists.
+	///
+	/// This runs a [`CreateEntryAction`] with [`CreateMode::Replace`], so the fields are checked against the table's
+	/// schema like any other write. Secondary indexes aren't updated, as the entry's indexes aren't known.
+	///
+	/// # Errors
+	///
+	/// Returns an [`ErrorType::Untyped`] error if the backend isn't self-describing, as the fields wouldn't be
+	/// stored as the entry type, or any errors a [`CreateEntryAction`] can raise.
+	///
+	/// [`CreateEntryAction`]: crate::action::CreateEntryAction
+	/// [`CreateMode::Replace`]: crate::action::CreateMode::Replace
+	/// [`ErrorType::Untyped`]: crate::error::ErrorType::Untyped
+	pub async fn put_raw<K: Key + Sync>(
+		&self,
+		table: &str,
+		key: &K,
+		fields: &SchemaMap,
+	) -> Result<(), Error> {
+		self.check_self_describing()?;
+
+		let mut action = CreateEntryAction::for_table(table);
+		action
+			.set_key(key)
+			.set_data(fields)
+			.mode(CreateMode::Replace);
+
+		Ok(action.run_create_entry(self).await?)
+	}
+
+	/// Adds `delta` to an integer field of an entry, returning the new value.
+	///
+	/// The field is a path of field names separated by `.`, such as `stats.views`. The entry is read under a single
+	/// exclusive lock, then backends with native atomic increments (see [`Backend::increment`]) update the field in
+	/// place, otherwise the entry is written back. Like [`Self::modify`], the entry's secondary
+	/// [`IndexEntry::indexes`] are kept up to date.
+	///
+	/// # Errors
+	///
+	/// Returns an [`ErrorType::InvalidCounter`] error if the field doesn't exist or isn't an integer, or adding to it
+	/// would overflow, an [`ErrorType::EntryMissing`] error if the entry doesn't exist (or has expired),
+	/// an [`ErrorType::TableMissing`] error if the table doesn't exist, an [`ErrorType::Untyped`] error if the
+	/// backend isn't self-describing, as the field is found without knowing the entry's layout,
+	/// an [`ErrorType::Serialization`] error if the entry doesn't match `S`, an [`ActionRunErrorType::KeyChanged`]
+	/// error if the field is part of the entry's [`IndexEntry::key`],
+	/// or any errors that [`Backend::increment`], [`Backend::get`], or [`Backend::update`] can raise.
+	///
+	/// [`ErrorType::EntryMissing`]: crate::error::ErrorType::EntryMissing
+	/// [`ErrorType::InvalidCounter`]: crate::error::ErrorType::InvalidCounter
+	/// [`ErrorType::Serialization`]: crate::error::ErrorType::Serialization
+	/// [`ErrorType::TableMissing`]: crate::error::ErrorType::TableMissing
+	/// [`ErrorType::Untyped`]: crate::error::ErrorType::Untyped
+	pub async fn increment<S: IndexEntry, K: Key + Sync>(
+		&self,
+		table: &str,
+		key: &K,
+		field: &str,
+		delta: i64,
+	) -> Result<i64, Error> {
+		self.check_self_describing()?;
+
+		let key = key.to_key();
+		let path = field.split('.').collect::<Vec<_>>();
+
+		self.observer(ActionKind::Update, TargetKind::Entry, table, Some(&key))
+			.run_chart(async {
+				let lock = self.guard.exclusive(table);
+
+				let result = self
+					.increment_locked::<S>(table, &key, field, &path, delta)
+					.await;
+
+				drop(lock);
+
+				result
+			})
+			.await
+	}
+
+	async fn increment_locked<S: IndexEntry>(
+		&self,
+		table: &str,
+		key: &str,
+		field: &str,
+		path: &[&str],
+		delta: i64,
+	) -> Result<i64, Error> {
+		let backend = &*self.backend;
+		let backend_error = |e: B::Error| Error::backend(Some(Box::new(e)));
+
+		if !backend.has_table(table).await.map_err(backend_error)? {
+			return Err(Error::table_missing());
+		}
+
+		let expired = !self.pins.is_pinned(table, key)
+			&& expiry::is_expired(backend, table, key, self.clock.now())
+				.await
+				.map_err(backend_error)?;
+
+		if expired {
+			return Err(Error::entry_missing());
+		}
+
+		// the entry is read first even if the backend can increment natively, to check the key and find the
+		// entry's indexes before and after.
+		let mut entry = backend
+			.get::<SchemaValue>(table, key)
+			.await
+			.map_err(backend_error)?
+			.ok_or_else(Error::entry_missing)?;
+
+		let previous = serde_json::from_value::<S>(entry.clone()).map_err(Error::json)?;
+
+		let counter = path
+			.iter()
+			.try_fold(&mut entry, |value, name| value.get_mut(name))
+			.ok_or_else(|| Error::invalid_counter(field))?;
+
+		let value = counter
+			.as_i64()
+			.and_then(|counter| counter.checked_add(delta))
+			.ok_or_else(|| Error::invalid_counter(field))?;
+
+		*counter = value.into();
+
+		let modified = serde_json::from_value::<S>(entry.clone()).map_err(Error::json)?;
+
+		if modified.key().to_key() != previous.key().to_key() {
+			return Err(ActionRunError::key_changed(key.to_owned()).into());
+		}
+
+		self.record_change(table, Some(key), ChangeKind::Write)
+			.await
+			.map_err(backend_error)?;
+
+		let value = if let Some(value) = backend
+			.increment(table, key, path, delta)
+			.await
+			.map_err(backend_error)?
+		{
+			value
+		} else {
+			backend
+				.update(table, key, &entry)
+				.await
+				.map_err(backend_error)?;
+
+			value
+		};
+
+		let pending = self.repairs.pending(table, key);
+
+		index::update(self, table, key, &previous.indexes(), &modified.indexes())
+			.await
+			.map_err(backend_error)?;
+
+		self.record_write(table, key).await.map_err(backend_error)?;
+
+		pending.complete();
+
+		if self.history_limit(table).is_some() {
+			let entry = backend
+				.get::<SchemaValue>(table, key)
+				.await
+				.map_err(backend_error)?;
+
+			self.retain(table, key, entry.as_ref()).await?;
+		}
+
+		Ok(value)
+	}
+
+	/// What actions do when their table has entries that need repair, see [`StarchartBuilder::repair_policy`].
+	#[must_use]
+	pub const fn repair_policy(&self) -> RepairPolicy {
+		self.repair_policy
+	}
+
+	/// How the chart's table locks are handed to waiting operations, see [`StarchartBuilder::lock_fairness`].
+	#[must_use]
+	pub fn lock_fairness(&self) -> LockFairness {
+		self.guard.fairness()
+	}
+
+	/// What actions do when their table's metadata is missing or mismatched, see [`StarchartBuilder::metadata_policy`].
+	#[must_use]
+	pub const fn metadata_policy(&self) -> MetadataPolicy {
+		self.metadata_policy
+	}
+
+	/// Returns the tables with entries whose writes failed part way through, sorted by name.
+	///
+	/// See the [repair] module for how entries are marked and repaired.
+	///
+	/// [repair]: crate::repair
+	#[must_use]
+	pub fn needs_repair(&self) -> Vec<String> {
+		self.repairs.tables()
+	}
+
+	/// Repairs every entry of a table whose write failed part way through, returning how many were repaired.
+	///
+	/// The index values of the stored entries are restored, and the writes are recorded again.
+	/// Entries that fail to be repaired stay marked.
+	///
+	/// # Errors
+	///
+	/// Any errors that [`Backend::get`], [`Backend::create`], or [`Backend::update`] can raise.
+	pub async fn repair<S: IndexEntry>(&self, table: &str) -> Result<usize, Error> {
+		let lock = self.guard.exclusive(table);
+
+		let repaired = repair::run(self, table, Some(S::indexes as fn(&S) -> _))
+			.await
+			.map_err(Error::from);
+
+		drop(lock);
+
+		repaired
+	}
+
+	/// Returns the amount of pinned entries in the given table.
+	#[must_use]
+	pub fn pinned_count(&self, table: &str) -> usize {
+		self.pins.count(table)
+	}
+
+	/// Returns the [`InternerStats`] of the strings shared across this chart, or [`None`] if
+	/// [`StarchartBuilder::intern_strings`] isn't enabled.
+	#[must_use]
+	pub fn interner_stats(&self) -> Option<InternerStats> {
+		self.interner.stats()
+	}
+
+	/// Releases the interned strings that are no longer used anywhere in the chart, returning how many were released.
+	///
+	/// Keys stay interned after their pins, loads, and queued writes are gone, so charts that see many distinct
+	/// keys should call this periodically.
+	#[allow(clippy::must_use_candidate)]
+	pub fn shrink_interner(&self) -> usize {
+		self.interner.shrink()
+	}
 }
 
 impl<B: Backend> Clone for Starchart<B> {
 	fn clone(&self) -> Self {
 		Self {
 			backend: self.backend.clone(),
+			shutdown: self.shutdown.clone(),
 			guard: self.guard.clone(),
+			clock: self.clock.clone(),
+			spawner: self.spawner.clone(),
+			obfuscator: self.obfuscator.clone(),
+			stats: self.stats.clone(),
+			pins: self.pins.clone(),
+			repairs: self.repairs.clone(),
+			index_tables: self.index_tables.clone(),
+			loaders: self.loaders.clone(),
+			paginator: self.paginator.clone(),
+			metrics: self.metrics.clone(),
+			hooks: self.hooks.clone(),
+			write_behind: self.write_behind.clone(),
+			extensions: self.extensions.clone(),
+			interner: self.interner.clone(),
+			auto_create_tables: self.auto_create_tables,
+			repair_policy: self.repair_policy,
+			metadata_policy: self.metadata_policy,
+			retention: self.retention,
+			history: self.history.clone(),
+			journal: self.journal.clone(),
+		}
+	}
+}
+
+impl<B: Backend + Default> Default for Starchart<B> {
+	fn default() -> Self {
+		let backend = Arc::<B>::default();
+
+		Self {
+			shutdown: Arc::new(ShutdownGuard(backend.clone())),
+			backend,
+			guard: Arc::default(),
+			clock: Arc::new(SystemClock),
+			spawner: Arc::new(ThreadSpawner),
+			obfuscator: None,
+			stats: Arc::default(),
+			pins: Arc::default(),
+			repairs: Arc::default(),
+			index_tables: Arc::default(),
+			loaders: Arc::default(),
+			paginator: Arc::default(),
+			metrics: None,
+			hooks: Hooks::default(),
+			write_behind: Arc::default(),
+			extensions: Arc::default(),
+			interner: Arc::default(),
+			auto_create_tables: false,
+			repair_policy: RepairPolicy::default(),
+			metadata_policy: MetadataPolicy::default(),
+			retention: None,
+			history: Arc::default(),
+			journal: None,
 		}
 	}
 }
@@ -48,8 +2115,563 @@ impl<B: Backend> Deref for Starchart<B> {
 	}
 }
 
-impl<B: Backend> Drop for Starchart<B> {
+/// Runs [`Backend::shutdown`] when dropped, shared between the clones of a [`Starchart`] so the backend is only
+/// shut down once every clone is gone.
+///
+/// A child chart has a guard of it's own, but the [`PrefixedBackend`] it uses doesn't shut down the parent's backend.
+struct ShutdownGuard<B: Backend>(Arc<B>);
+
+impl<B: Backend> Debug for ShutdownGuard<B> {
+	fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+		f.debug_struct("ShutdownGuard").finish_non_exhaustive()
+	}
+}
+
+impl<B: Backend> Drop for ShutdownGuard<B> {
 	fn drop(&mut self) {
-		block_on(unsafe { self.backend.shutdown() });
+		let shutdown = unsafe { self.0.shutdown() };
+
+		// block_on panics when the chart is dropped from within another executor, so drive the shutdown
+		// from it's own thread there.
+		if enter().is_ok() {
+			block_on(shutdown);
+		} else {
+			thread::scope(|scope| {
+				scope.spawn(|| block_on(shutdown));
+			});
+		}
+	}
+}
+
+/// A builder for a [`Starchart`], used to configure the chart before the [`Backend`] is initialized.
+#[derive(Debug)]
+#[must_use = "a builder does nothing until it's built"]
+#[allow(clippy::struct_excessive_bools)]
+pub struct StarchartBuilder<B: Backend> {
+	backend: B,
+	clock: Arc<dyn Clock>,
+	spawner: Arc<dyn Spawner>,
+	obfuscator: Option<Arc<KeyObfuscator>>,
+	payload_stats: bool,
+	large_entry_warning_bytes: Option<usize>,
+	loader_error_ttl: Option<Duration>,
+	paginator: Option<Paginator>,
+	metrics: Option<Arc<dyn Metrics>>,
+	hooks: Vec<Arc<dyn Hook>>,
+	auto_create_tables: bool,
+	repair_policy: RepairPolicy,
+	metadata_policy: MetadataPolicy,
+	lock_fairness: LockFairness,
+	retention: Option<Duration>,
+	history: BTreeMap<String, HistoryLimit>,
+	journal: bool,
+	intern_strings: bool,
+}
+
+impl<B: Backend> StarchartBuilder<B> {
+	/// Creates a new [`StarchartBuilder`] for the given [`Backend`].
+	pub fn new(backend: B) -> Self {
+		Self {
+			backend,
+			clock: Arc::new(SystemClock),
+			spawner: Arc::new(ThreadSpawner),
+			obfuscator: None,
+			payload_stats: false,
+			large_entry_warning_bytes: None,
+			loader_error_ttl: None,
+			paginator: None,
+			metrics: None,
+			hooks: Vec::new(),
+			auto_create_tables: false,
+			repair_policy: RepairPolicy::Repair,
+			metadata_policy: MetadataPolicy::Strict,
+			lock_fairness: LockFairness::default(),
+			retention: None,
+			history: BTreeMap::new(),
+			journal: false,
+			intern_strings: false,
+		}
+	}
+
+	/// Sets the [`Clock`] used for time-based features, defaults to [`SystemClock`].
+	pub fn clock<C: Clock + 'static>(mut self, clock: C) -> Self {
+		self.clock = Arc::new(clock);
+
+		self
+	}
+
+	/// Sets the [`Spawner`] background tasks are ran on, defaults to [`ThreadSpawner`].
+	pub fn spawner<S: Spawner + 'static>(mut self, spawner: S) -> Self {
+		self.spawner = Arc::new(spawner);
+
+		self
+	}
+
+	/// Sets the [`KeyObfuscator`] used by [`Starchart::encode_key`] and [`Starchart::decode_key`].
+	pub fn key_obfuscator(mut self, obfuscator: KeyObfuscator) -> Self {
+		self.obfuscator = Some(Arc::new(obfuscator));
+
+		self
+	}
+
+	/// Whether the sizes of written entries should be recorded, to be retrieved with [`Starchart::payload_stats`].
+	///
+	/// Backends that don't report sizes with [`Backend::entry_size`] have every written entry serialized
+	/// again to measure it, so this is off by default.
+	pub const fn payload_stats(mut self, enabled: bool) -> Self {
+		self.payload_stats = enabled;
+
+		self
+	}
+
+	/// Sets the entry size, in bytes, over which writes will be logged as warnings.
+	///
+	/// Written entries are measured for the warning even if [`Self::payload_stats`] isn't enabled.
+	pub const fn large_entry_warning_bytes(mut self, bytes: usize) -> Self {
+		self.large_entry_warning_bytes = Some(bytes);
+
+		self
+	}
+
+	/// Sets how long a failed loader call is remembered.
+	///
+	/// Reads of the same key within the window return the cached error instead of calling
+	/// the loader again, by default errors aren't cached.
+	pub const fn loader_error_ttl(mut self, ttl: Duration) -> Self {
+		self.loader_error_ttl = Some(ttl);
+
+		self
+	}
+
+	/// Sets the secret used to sign [`PageToken`]s.
+	///
+	/// Without a secret, tokens are signed with a random one and won't be accepted after a restart.
+	pub fn pagination_secret<S: AsRef<[u8]>>(mut self, secret: S) -> Self {
+		self.paginator = Some(Paginator::new(secret));
+
+		self
+	}
+
+	/// Sets the [`Metrics`] every action is recorded to, by default actions aren't recorded.
+	pub fn metrics<M: Metrics + 'static>(mut self, metrics: M) -> Self {
+		self.metrics = Some(Arc::new(metrics));
+
+		self
+	}
+
+	/// Registers a [`Hook`] to run around every action, after any hooks already registered.
+	pub fn hook<H: Hook + 'static>(mut self, hook: H) -> Self {
+		self.hooks.push(Arc::new(hook));
+
+		self
+	}
+
+	/// Whether create and update actions should create missing tables, instead of failing.
+	///
+	/// Tables are created the same way as with a [`CreateTableAction`], including their metadata.
+	///
+	/// [`CreateTableAction`]: crate::action::CreateTableAction
+	pub const fn auto_create_tables(mut self, auto_create: bool) -> Self {
+		self.auto_create_tables = auto_create;
+
+		self
+	}
+
+	/// Sets what actions do when their table has entries whose writes failed part way through,
+	/// defaults to [`RepairPolicy::Repair`].
+	pub const fn repair_policy(mut self, policy: RepairPolicy) -> Self {
+		self.repair_policy = policy;
+
+		self
+	}
+
+	/// Sets what actions do when their table's metadata is missing or doesn't match the entry type,
+	/// defaults to [`MetadataPolicy::Strict`].
+	pub const fn metadata_policy(mut self, policy: MetadataPolicy) -> Self {
+		self.metadata_policy = policy;
+
+		self
+	}
+
+	/// Sets how the chart's table locks are handed to the operations waiting on them,
+	/// defaults to [`LockFairness::Eventual`].
+	pub const fn lock_fairness(mut self, fairness: LockFairness) -> Self {
+		self.lock_fairness = fairness;
+
+		self
+	}
+
+	/// Keeps every version of written entries for the given window, so they can be read with [`Starchart::read_entry_at`].
+	///
+	/// Each write also stores a copy of the entry in a backend table, so this roughly doubles the cost of writing.
+	pub const fn retain_versions(mut self, window: Duration) -> Self {
+		self.retention = Some(window);
+
+		self
+	}
+
+	/// Keeps previous revisions of every entry in the table, up to the given limit, so they can be listed with
+	/// [`Starchart::history`] and read with [`Starchart::read_entry_at`].
+	///
+	/// This overrides [`Self::retain_versions`] for the table, and costs the same on each write.
+	pub fn keep_history(mut self, table: &str, limit: HistoryLimit) -> Self {
+		self.history.insert(table.to_owned(), limit);
+
+		self
+	}
+
+	/// Whether every change should be recorded in the [journal], for change-data-capture consumers.
+	///
+	/// Each write also appends a record to a backend table, and checks whether the entry still exists.
+	///
+	/// [journal]: crate::journal
+	pub const fn journal(mut self, enabled: bool) -> Self {
+		self.journal = enabled;
+
+		self
+	}
+
+	/// Whether table names and keys should be interned, so the strings held by the chart are only allocated once.
+	///
+	/// Interning trades a lock on every lookup for fewer allocations, which helps charts that hold many
+	/// pins, loaders, or queued writes for the same tables and keys. See [`Starchart::interner_stats`] for tuning.
+	pub const fn intern_strings(mut self, enabled: bool) -> Self {
+		self.intern_strings = enabled;
+
+		self
+	}
+
+	/// Initializes the [`Backend`] and builds the [`Starchart`].
+	///
+	/// # Errors
+	///
+	/// Any errors that [`Backend::init`] can raise.
+	pub async fn build(self) -> Result<Starchart<B>, B::Error> {
+		self.backend.init().await?;
+
+		let interner = Arc::new(Interner::new(self.intern_strings));
+		let backend = Arc::new(self.backend);
+
+		Ok(Starchart {
+			shutdown: Arc::new(ShutdownGuard(backend.clone())),
+			backend,
+			guard: Arc::new(Guard::with_fairness(self.lock_fairness)),
+			clock: self.clock,
+			spawner: self.spawner,
+			obfuscator: self.obfuscator,
+			stats: Arc::new(Stats::new(
+				self.payload_stats,
+				self.large_entry_warning_bytes,
+				interner.clone(),
+			)),
+			pins: Arc::new(Pins::new(interner.clone())),
+			repairs: Arc::new(Repairs::new(interner.clone())),
+			index_tables: Arc::default(),
+			loaders: Arc::new(Loaders::new(self.loader_error_ttl, interner.clone())),
+			paginator: Arc::new(self.paginator.unwrap_or_default()),
+			metrics: self.metrics,
+			hooks: Hooks::new(self.hooks),
+			write_behind: Arc::default(),
+			extensions: Arc::default(),
+			interner,
+			auto_create_tables: self.auto_create_tables,
+			repair_policy: self.repair_policy,
+			metadata_policy: self.metadata_policy,
+			retention: self.retention,
+			history: Arc::new(self.history),
+			journal: self.journal.then(Arc::default),
+		})
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use std::time::Duration;
+
+	use crate::{
+		action::{CreateEntryAction, ReadEntryAction, ReadTableAction, UpdateBatchAction},
+		backend::{Backend, MockBackend, ReplicatedBackend},
+		clock::ManualClock,
+		error::ErrorType,
+		schema::SchemaValue,
+		testing::TestSettings,
+		Error, Starchart,
+	};
+
+	#[tokio::test]
+	async fn auto_create_tables() -> Result<(), Error> {
+		let chart = Starchart::builder(MockBackend::new())
+			.auto_create_tables(true)
+			.build()
+			.await?;
+
+		let settings = TestSettings::default();
+
+		let mut action = CreateEntryAction::new();
+		action.set_table("created").set_entry(&settings);
+		action.run_create_entry(&chart).await?;
+
+		let mut action = UpdateBatchAction::new();
+		action.set_table("updated").add_entry(&settings);
+		action.run_update_many(&chart).await?;
+
+		assert!(chart.has_table("created").await?);
+		assert!(chart.has_table("updated").await?);
+
+		let mut action = ReadEntryAction::<TestSettings>::new();
+		action.set_table("created").set_key(&settings.id);
+		assert_eq!(action.run_read_entry(&chart).await?, Some(settings));
+
+		// reads still fail on missing tables
+		let mut action = ReadEntryAction::<TestSettings>::new();
+		action.set_table("missing").set_key(&1_u32);
+		assert!(action.run_read_entry(&chart).await.is_err());
+
+		Ok(())
+	}
+
+	#[tokio::test]
+	async fn raw_entries() -> Result<(), Error> {
+		let chart = Starchart::new(MockBackend::new()).await?;
+		chart.create_table("table").await?;
+
+		CreateEntryAction::for_table("table")
+			.with_entry(&TestSettings::default())
+			.run_create_entry(&chart)
+			.await?;
+
+		let mut fields = chart.get_raw("table", &1_u32).await?.unwrap();
+		assert_eq!(
+			fields.get("value"),
+			Some(&SchemaValue::from("hello, world!"))
+		);
+		assert_eq!(chart.get_raw("table", &2_u32).await?, None);
+
+		fields.insert("value".to_owned(), "raw".into());
+		chart.put_raw("table", &1_u32, &fields).await?;
+		chart.put_raw("table", &2_u32, &fields).await?;
+
+		let settings = chart.get::<TestSettings>("table", "2").await?.unwrap();
+		assert_eq!(settings.value, "raw");
+		assert_eq!(
+			chart.get::<TestSettings>("table", "1").await?,
+			Some(settings)
+		);
+
+		chart.create("table", "3", &5_u32).await?;
+		assert!(chart.get_raw("table", &3_u32).await.is_err());
+
+		Ok(())
+	}
+
+	#[tokio::test]
+	async fn dump_pretty() -> Result<(), Error> {
+		let chart = Starchart::new(MockBackend::new()).await?;
+
+		let err = chart
+			.dump_pretty::<TestSettings, _>("table", Vec::new())
+			.await
+			.unwrap_err();
+		assert!(matches!(err.kind(), ErrorType::TableMissing));
+
+		chart.create_table("table").await?;
+
+		for id in [10, 2] {
+			let settings = TestSettings {
+				id,
+				..TestSettings::default()
+			};
+
+			let mut action = CreateEntryAction::new();
+			action.set_table("table").set_entry(&settings);
+			action.run_create_entry(&chart).await?;
+		}
+
+		let mut output = Vec::new();
+		chart
+			.dump_pretty::<TestSettings, _>("table", &mut output)
+			.await?;
+		let output = String::from_utf8(output).unwrap();
+
+		assert!(output.ends_with("}\n"));
+		assert!(output.contains("\n    \"id\": 10,"));
+		assert!(output.find("\"10\"") < output.find("\"2\""));
+		assert!(output.starts_with("{\n  \"10\""));
+
+		Ok(())
+	}
+
+	#[tokio::test]
+	async fn get_many() -> Result<(), Error> {
+		let clock = ManualClock::default();
+		let chart = Starchart::builder(MockBackend::new())
+			.clock(clock.clone())
+			.build()
+			.await?;
+		chart.create_table("table").await?;
+
+		for id in 1..=4 {
+			let settings = TestSettings {
+				id,
+				opt: (id != 4).then_some(1.0),
+				..TestSettings::default()
+			};
+
+			let mut action = CreateEntryAction::new();
+			action.set_table("table").set_entry(&settings);
+			if id == 2 {
+				action.set_ttl(Duration::from_secs(10));
+			}
+			action.run_create_entry(&chart).await?;
+		}
+
+		let ids = |entries: Vec<Option<TestSettings>>| {
+			entries
+				.into_iter()
+				.map(|entry| entry.map(|entry| entry.id))
+				.collect::<Vec<_>>()
+		};
+
+		let entries = chart.get_many("table", &[3_u32, 5, 1, 2]).await?;
+		assert_eq!(ids(entries), [Some(3), None, Some(1), Some(2)]);
+
+		clock.advance(Duration::from_secs(11));
+
+		let entries = chart.get_many("table", &[2_u32, 1]).await?;
+		assert_eq!(ids(entries), [None, Some(1)]);
+		assert!(!chart.has("table", "2").await?);
+
+		let mut action = ReadTableAction::<TestSettings>::for_table("table");
+		action.filter(|settings| settings.opt.is_some());
+		let entries = action.run_read_entries(&chart, &[4_u32, 3]).await?;
+		assert_eq!(ids(entries), [None, Some(3)]);
+
+		assert!(chart
+			.get_many::<TestSettings, _>("missing", &[1_u32])
+			.await
+			.is_err());
+
+		Ok(())
+	}
+
+	#[tokio::test]
+	async fn tables() -> Result<(), Error> {
+		let chart = Starchart::new(MockBackend::new()).await?;
+		let child = chart.child("library_");
+
+		chart.create_table("table").await?;
+		child.create_table("table").await?;
+
+		let settings = TestSettings::default();
+		let mut action = CreateEntryAction::new();
+		action
+			.set_table("table")
+			.set_entry(&settings)
+			.set_ttl(Duration::from_secs(10));
+		action.run_create_entry(&chart).await?;
+
+		// the expiry time is kept in an internal table, which isn't listed.
+		assert!(chart.has_table("__expiry__table").await?);
+
+		let mut tables: Vec<String> = chart.tables().await?;
+		tables.sort();
+		assert_eq!(tables, ["library_table", "table"]);
+
+		assert_eq!(child.tables::<Vec<_>>().await?, ["table"]);
+
+		Ok(())
+	}
+
+	#[tokio::test]
+	async fn health() -> Result<(), Error> {
+		let chart = Starchart::new(MockBackend::new()).await?;
+
+		// the memory backend has nothing to check, so it uses the default no-op.
+		chart.health().await?;
+		chart.namespace("tenant_a")?.health().await?;
+
+		let replicated = Starchart::new(ReplicatedBackend::new(vec![
+			MockBackend::new(),
+			MockBackend::new(),
+		]))
+		.await?;
+		replicated.health().await?;
+
+		Ok(())
+	}
+
+	#[tokio::test]
+	async fn rename_table() -> Result<(), Error> {
+		let clock = ManualClock::default();
+		let chart = Starchart::builder(MockBackend::new())
+			.clock(clock.clone())
+			.build()
+			.await?;
+		chart.create_table("table").await?;
+		chart.create_table("other").await?;
+
+		let settings = TestSettings::default();
+		let mut action = CreateEntryAction::new();
+		action
+			.set_table("table")
+			.set_entry(&settings)
+			.set_ttl(Duration::from_secs(10));
+		action.run_create_entry(&chart).await?;
+
+		chart.rename_table("table", "renamed").await?;
+
+		assert!(!chart.has_table("table").await?);
+		assert!(!chart.has_table("__expiry__table").await?);
+		assert!(chart.has_table("__expiry__renamed").await?);
+
+		let mut read = ReadEntryAction::new();
+		read.set_table("renamed").set_key(&settings.id);
+		assert_eq!(read.clone().run_read_entry(&chart).await?, Some(settings));
+
+		// the expiry time moved along with the entry.
+		clock.advance(Duration::from_secs(20));
+		assert_eq!(read.run_read_entry(&chart).await?, None);
+
+		let missing = chart.rename_table("table", "new").await.unwrap_err();
+		assert!(matches!(missing.kind(), ErrorType::TableMissing));
+
+		let exists = chart.rename_table("renamed", "other").await.unwrap_err();
+		assert!(matches!(exists.kind(), ErrorType::TableExists));
+
+		Ok(())
+	}
+
+	#[tokio::test]
+	async fn truncate_table() -> Result<(), Error> {
+		let chart = Starchart::new(MockBackend::new()).await?;
+		chart.create_table("table").await?;
+
+		let settings = TestSettings::default();
+		let mut action = CreateEntryAction::new();
+		action
+			.set_table("table")
+			.set_entry(&settings)
+			.set_ttl(Duration::from_secs(10));
+		action.run_create_entry(&chart).await?;
+
+		chart.truncate_table("table").await?;
+
+		assert!(chart.has_table("table").await?);
+		assert!(!chart.has_table("__expiry__table").await?);
+
+		let mut read = ReadEntryAction::<TestSettings>::new();
+		read.set_table("table").set_key(&settings.id);
+		assert_eq!(read.run_read_entry(&chart).await?, None);
+
+		// the metadata is kept, so the table can be written to right away.
+		CreateEntryAction::for_table("table")
+			.with_entry(&settings)
+			.run_create_entry(&chart)
+			.await?;
+
+		let missing = chart.truncate_table("missing").await.unwrap_err();
+		assert!(matches!(missing.kind(), ErrorType::TableMissing));
+
+		Ok(())
 	}
 }
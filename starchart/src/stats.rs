@@ -0,0 +1,392 @@
+//! Statistics collected by a [`Starchart`] while running actions.
+//!
+//! [`Starchart`]: crate::Starchart
+
+use std::{collections::HashMap, sync::Arc};
+
+use crate::interner::Interner;
+use parking_lot::Mutex;
+
+/// Statistics on the serialized size of entries written to a table, recorded if enabled with
+/// [`StarchartBuilder::payload_stats`].
+///
+/// Sizes are reported by the [`Backend::entry_size`], or measured as the length of the entry serialized as
+/// compact JSON for backends that don't report them, which may differ from the size they actually store.
+///
+/// [`Backend::entry_size`]: crate::backend::Backend::entry_size
+/// [`StarchartBuilder::payload_stats`]: crate::StarchartBuilder::payload_stats
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+#[must_use = "retrieving stats has no side effects"]
+pub struct PayloadStats {
+	writes: u64,
+	total_bytes: u64,
+	max_bytes: u64,
+}
+
+impl PayloadStats {
+	/// The amount of writes that have been recorded.
+	#[must_use]
+	pub const fn writes(self) -> u64 {
+		self.writes
+	}
+
+	/// The sum of the sizes of all recorded writes, in bytes.
+	#[must_use]
+	pub const fn total_bytes(self) -> u64 {
+		self.total_bytes
+	}
+
+	/// The size of the largest recorded write, in bytes.
+	#[must_use]
+	pub const fn max_bytes(self) -> u64 {
+		self.max_bytes
+	}
+
+	/// The average size of the recorded writes, in bytes.
+	#[must_use]
+	pub const fn average_bytes(self) -> u64 {
+		match self.total_bytes.checked_div(self.writes) {
+			Some(average) => average,
+			None => 0,
+		}
+	}
+
+	fn record(&mut self, size: u64) {
+		self.writes += 1;
+		self.total_bytes += size;
+		self.max_bytes = self.max_bytes.max(size);
+	}
+}
+
+/// Counters for writes queued with [`Starchart::write_behind`].
+///
+/// [`Starchart::write_behind`]: crate::Starchart::write_behind
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+#[must_use = "retrieving stats has no side effects"]
+pub struct WriteBehindStats {
+	pub(crate) pending: usize,
+	pub(crate) queued: u64,
+	pub(crate) coalesced: u64,
+	pub(crate) flushed: u64,
+}
+
+impl WriteBehindStats {
+	/// The amount of writes waiting to be flushed.
+	#[must_use]
+	pub const fn pending(self) -> usize {
+		self.pending
+	}
+
+	/// The amount of writes that have been queued.
+	#[must_use]
+	pub const fn queued(self) -> u64 {
+		self.queued
+	}
+
+	/// The amount of queued writes that replaced a pending write to the same key, and so never reached the backend.
+	#[must_use]
+	pub const fn coalesced(self) -> u64 {
+		self.coalesced
+	}
+
+	/// The amount of writes that have been flushed to the backend.
+	#[must_use]
+	pub const fn flushed(self) -> u64 {
+		self.flushed
+	}
+}
+
+/// Statistics on how often the replicas of a [`ReplicatedBackend`] disagreed, from [`ReplicatedBackend::stats`].
+///
+/// [`ReplicatedBackend`]: crate::backend::ReplicatedBackend
+/// [`ReplicatedBackend::stats`]: crate::backend::ReplicatedBackend::stats
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+#[must_use = "retrieving stats has no side effects"]
+pub struct ReplicationStats {
+	pub(crate) reads: u64,
+	pub(crate) divergent_reads: u64,
+	pub(crate) repairs: u64,
+	pub(crate) failed_writes: u64,
+}
+
+impl ReplicationStats {
+	/// The amount of entries that have been read.
+	#[must_use]
+	pub const fn reads(self) -> u64 {
+		self.reads
+	}
+
+	/// The amount of reads where the replicas didn't all return the same entry.
+	#[must_use]
+	pub const fn divergent_reads(self) -> u64 {
+		self.divergent_reads
+	}
+
+	/// The amount of replicas that were overwritten with the agreed entry after a divergent read.
+	#[must_use]
+	pub const fn repairs(self) -> u64 {
+		self.repairs
+	}
+
+	/// The amount of writes that failed on a single replica, whether or not the write quorum was met.
+	#[must_use]
+	pub const fn failed_writes(self) -> u64 {
+		self.failed_writes
+	}
+}
+
+/// Statistics on how often the backends of a [`TieredBackend`] failed, from [`TieredBackend::stats`].
+///
+/// [`TieredBackend`]: crate::backend::TieredBackend
+/// [`TieredBackend::stats`]: crate::backend::TieredBackend::stats
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+#[must_use = "retrieving stats has no side effects"]
+pub struct TieredStats {
+	pub(crate) fallback_reads: u64,
+	pub(crate) failed_primary_writes: u64,
+	pub(crate) failed_replica_writes: u64,
+}
+
+impl TieredStats {
+	/// The amount of reads that failed on the primary backend and were served by the replica.
+	#[must_use]
+	pub const fn fallback_reads(self) -> u64 {
+		self.fallback_reads
+	}
+
+	/// The amount of writes that failed on the primary backend, whether or not the write succeeded.
+	#[must_use]
+	pub const fn failed_primary_writes(self) -> u64 {
+		self.failed_primary_writes
+	}
+
+	/// The amount of writes that failed on the replica backend, whether or not the write succeeded.
+	#[must_use]
+	pub const fn failed_replica_writes(self) -> u64 {
+		self.failed_replica_writes
+	}
+}
+
+/// Statistics on how often the operations of a [`RetryBackend`] were retried, from [`RetryBackend::stats`].
+///
+/// [`RetryBackend`]: crate::backend::RetryBackend
+/// [`RetryBackend::stats`]: crate::backend::RetryBackend::stats
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+#[must_use = "retrieving stats has no side effects"]
+pub struct RetryStats {
+	pub(crate) retries: u64,
+	pub(crate) exhausted: u64,
+}
+
+impl RetryStats {
+	/// The amount of times an operation was attempted again after a retryable error.
+	#[must_use]
+	pub const fn retries(self) -> u64 {
+		self.retries
+	}
+
+	/// The amount of operations that still failed after using up every attempt.
+	#[must_use]
+	pub const fn exhausted(self) -> u64 {
+		self.exhausted
+	}
+}
+
+/// Statistics on the strings deduplicated by a chart, from [`Starchart::interner_stats`].
+///
+/// [`Starchart::interner_stats`]: crate::Starchart::interner_stats
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+#[must_use = "retrieving stats has no side effects"]
+pub struct InternerStats {
+	pub(crate) strings: usize,
+	pub(crate) bytes: usize,
+	pub(crate) hits: u64,
+	pub(crate) misses: u64,
+}
+
+impl InternerStats {
+	/// The amount of distinct strings held by the interner.
+	#[must_use]
+	pub const fn strings(self) -> usize {
+		self.strings
+	}
+
+	/// The sum of the lengths of the held strings, in bytes.
+	#[must_use]
+	pub const fn bytes(self) -> usize {
+		self.bytes
+	}
+
+	/// The amount of times a string was already held, and so wasn't allocated again.
+	#[must_use]
+	pub const fn hits(self) -> u64 {
+		self.hits
+	}
+
+	/// The amount of times a string wasn't held yet, and was allocated.
+	#[must_use]
+	pub const fn misses(self) -> u64 {
+		self.misses
+	}
+}
+
+#[derive(Debug, Default)]
+pub(crate) struct Stats {
+	payloads: Mutex<HashMap<Arc<str>, PayloadStats>>,
+	enabled: bool,
+	large_entry_warning_bytes: Option<usize>,
+	interner: Arc<Interner>,
+}
+
+impl Stats {
+	pub fn new(
+		enabled: bool,
+		large_entry_warning_bytes: Option<usize>,
+		interner: Arc<Interner>,
+	) -> Self {
+		Self {
+			payloads: Mutex::default(),
+			enabled,
+			large_entry_warning_bytes,
+			interner,
+		}
+	}
+
+	pub const fn is_enabled(&self) -> bool {
+		self.enabled
+	}
+
+	/// Whether entries being written need to be measured, either to record them or to warn about large ones.
+	pub const fn measures(&self) -> bool {
+		self.enabled || self.large_entry_warning_bytes.is_some()
+	}
+
+	pub const fn large_entry_warning_bytes(&self) -> Option<usize> {
+		self.large_entry_warning_bytes
+	}
+
+	pub fn payload(&self, table: &str) -> Option<PayloadStats> {
+		self.payloads.lock().get(table).copied()
+	}
+
+	/// Records the measured size of an entry being written, if enabled, and warns if it's over the threshold.
+	pub fn record_size(&self, table: &str, key: &str, size: usize) {
+		if self.enabled {
+			let mut payloads = self.payloads.lock();
+			if let Some(payload) = payloads.get_mut(table) {
+				payload.record(size as u64);
+			} else {
+				payloads
+					.entry(self.interner.intern(table))
+					.or_default()
+					.record(size as u64);
+			}
+		}
+
+		if let Some(threshold) = self.large_entry_warning_bytes {
+			if size > threshold {
+				log::warn!(
+					"entry {key:?} in table {table:?} is {size} bytes, over the {threshold} byte \
+					 warning threshold"
+				);
+			}
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use std::{fmt::Debug, sync::Arc};
+
+	use static_assertions::assert_impl_all;
+
+	use super::{
+		InternerStats, PayloadStats, ReplicationStats, RetryStats, Stats, TieredStats,
+		WriteBehindStats,
+	};
+	use crate::{
+		action::UpdateEntryAction,
+		backend::{Backend, MockBackend},
+		testing::TestSettings,
+		Error, Starchart,
+	};
+
+	assert_impl_all!(InternerStats: Clone, Copy, Debug, Default, Send, Sync);
+	assert_impl_all!(PayloadStats: Clone, Copy, Debug, Default, Send, Sync);
+	assert_impl_all!(ReplicationStats: Clone, Copy, Debug, Default, Send, Sync);
+	assert_impl_all!(RetryStats: Clone, Copy, Debug, Default, Send, Sync);
+	assert_impl_all!(TieredStats: Clone, Copy, Debug, Default, Send, Sync);
+	assert_impl_all!(WriteBehindStats: Clone, Copy, Debug, Default, Send, Sync);
+
+	#[test]
+	fn record_size() {
+		let stats = Stats::new(true, Some(4), Arc::default());
+
+		assert_eq!(stats.payload("table"), None);
+
+		stats.record_size("table", "1", 1);
+		stats.record_size("table", "2", 7);
+
+		let payload = stats.payload("table").unwrap_or_default();
+
+		assert_eq!(payload.writes(), 2);
+		assert_eq!(payload.total_bytes(), 8);
+		assert_eq!(payload.max_bytes(), 7);
+		assert_eq!(payload.average_bytes(), 4);
+
+		let stats = Stats::new(false, Some(4), Arc::default());
+		assert!(stats.measures());
+
+		stats.record_size("table", "1", 1);
+		assert_eq!(stats.payload("table"), None);
+	}
+
+	#[tokio::test]
+	async fn skip_unchanged() -> Result<(), Error> {
+		let chart = Starchart::builder(MockBackend::new())
+			.payload_stats(true)
+			.build()
+			.await?;
+		chart.create_table("table").await?;
+
+		let mut settings = TestSettings::default();
+
+		let mut action = UpdateEntryAction::new();
+		action
+			.set_table("table")
+			.set_entry(&settings)
+			.skip_unchanged(true);
+		action.clone().run_update_entry(&chart).await?;
+		action.run_update_entry(&chart).await?;
+
+		let writes = || chart.payload_stats("table").map(PayloadStats::writes);
+
+		assert_eq!(writes(), Some(1));
+
+		settings.opt = None;
+
+		let mut action = UpdateEntryAction::new();
+		action
+			.set_table("table")
+			.set_entry(&settings)
+			.skip_unchanged(true);
+		action.run_update_entry(&chart).await?;
+
+		assert_eq!(writes(), Some(2));
+
+		Ok(())
+	}
+
+	#[tokio::test]
+	async fn payload_stats() -> Result<(), Error> {
+		let chart = Starchart::new(MockBackend::new()).await?;
+		chart.create_table("table").await?;
+		chart
+			.table::<TestSettings>("table")
+			.insert(&TestSettings::default())
+			.await?;
+		assert_eq!(chart.payload_stats("table"), None);
+
+		Ok(())
+	}
+}
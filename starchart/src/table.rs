@@ -0,0 +1,208 @@
+//! Typed handles to a single table.
+
+use std::{
+	fmt::{Debug, Formatter, Result as FmtResult},
+	iter::FromIterator,
+	marker::PhantomData,
+};
+
+use futures_util::{Stream, TryStreamExt};
+
+use crate::{
+	action::{
+		CreateEntryAction, DeleteEntryAction, LossyTable, ReadEntryAction, ReadTableAction, Scan,
+	},
+	backend::Backend,
+	Entry, Error, FromKey, IndexEntry, Key, Starchart,
+};
+
+/// A handle to a table holding entries of type `S`, retrieved with [`Starchart::table`].
+///
+/// Each method runs the same [`Action`] that would be built by hand, so the table's metadata
+/// is still checked against `S` on every call.
+///
+/// [`Action`]: crate::Action
+pub struct Table<'a, B: Backend, S> {
+	chart: &'a Starchart<B>,
+	name: String,
+	kind: PhantomData<fn() -> S>,
+}
+
+impl<'a, B: Backend, S: Entry> Table<'a, B, S> {
+	pub(crate) fn new(chart: &'a Starchart<B>, name: &str) -> Self {
+		Self {
+			chart,
+			name: name.to_owned(),
+			kind: PhantomData,
+		}
+	}
+
+	/// The name of the table.
+	#[must_use]
+	pub fn name(&self) -> &str {
+		&self.name
+	}
+
+	/// Gets the entry at the given key.
+	///
+	/// # Errors
+	///
+	/// Returns any errors that [`ReadEntryAction::run_read_entry`] can raise.
+	pub async fn get<K: Key + Sync>(&self, key: &K) -> Result<Option<S>, Error>
+	where
+		S: 'static,
+	{
+		let mut action = ReadEntryAction::<S>::new();
+		action.set_table(&self.name).set_key(key);
+
+		Ok(action.run_read_entry(self.chart).await?)
+	}
+
+	/// Removes the entry at the given key, returning whether it existed.
+	///
+	/// # Errors
+	///
+	/// Returns any errors that [`DeleteEntryAction::run_delete_entry`] can raise.
+	pub async fn remove<K: Key + Sync>(&self, key: &K) -> Result<bool, Error> {
+		let mut action = DeleteEntryAction::<S>::new();
+		action.set_table(&self.name).set_key(key);
+
+		Ok(action.run_delete_entry(self.chart).await?)
+	}
+
+	/// Streams every entry in the table along with it's key.
+	///
+	/// # Errors
+	///
+	/// The stream yields any errors that [`ReadTableAction::run_stream_table`] can raise.
+	#[allow(clippy::iter_not_returning_iterator)]
+	pub fn iter(&self) -> impl Stream<Item = Result<(String, S), Error>> + '_ {
+		let mut action = ReadTableAction::<S>::new();
+		action.set_table(&self.name);
+
+		action.run_stream_table(self.chart).map_err(Error::from)
+	}
+
+	/// Reads every entry in the table into a map keyed by the parsed keys, such as a [`BTreeMap<u64, S>`].
+	///
+	/// # Errors
+	///
+	/// Returns any errors that [`ReadTableAction::run_read_map`] can raise.
+	///
+	/// [`BTreeMap<u64, S>`]: std::collections::BTreeMap
+	pub async fn read_map<K, M>(&self) -> Result<M, Error>
+	where
+		K: FromKey,
+		M: FromIterator<(K, S)>,
+	{
+		let mut action = ReadTableAction::<S>::new();
+		action.set_table(&self.name);
+
+		Ok(action.run_read_map(self.chart).await?)
+	}
+
+	/// Reads every entry in the table, skipping entries that can't be read, see [`LossyTable`].
+	///
+	/// # Errors
+	///
+	/// Returns any errors that [`ReadTableAction::run_read_table_lossy`] can raise.
+	pub async fn read_lossy(&self) -> Result<LossyTable<S>, Error> {
+		let mut action = ReadTableAction::<S>::new();
+		action.set_table(&self.name);
+
+		Ok(action.run_read_table_lossy(self.chart).await?)
+	}
+
+	/// Scans the table in batches of `batch_size` keys, see [`Scan`].
+	///
+	/// # Errors
+	///
+	/// Returns any errors that [`ReadTableAction::run_scan`] can raise.
+	pub fn scan(&self, batch_size: usize) -> Result<Scan<'_, B, S>, Error> {
+		let mut action = ReadTableAction::<S>::new();
+		action.set_table(&self.name);
+
+		Ok(action.run_scan(self.chart, batch_size)?)
+	}
+}
+
+impl<B: Backend, S: IndexEntry> Table<'_, B, S> {
+	/// Inserts an entry, replacing the existing entry with the same key.
+	///
+	/// # Errors
+	///
+	/// Returns any errors that [`CreateEntryAction::run_create_entry`] can raise.
+	pub async fn insert(&self, entry: &S) -> Result<(), Error> {
+		let mut action = CreateEntryAction::new();
+		action
+			.set_table(&self.name)
+			.set_entry(entry)
+			.overwrite(true);
+
+		Ok(action.run_create_entry(self.chart).await?)
+	}
+}
+
+impl<B: Backend, S> Debug for Table<'_, B, S> {
+	fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+		f.debug_struct("Table").field("name", &self.name).finish()
+	}
+}
+
+impl<B: Backend, S> Clone for Table<'_, B, S> {
+	fn clone(&self) -> Self {
+		Self {
+			chart: self.chart,
+			name: self.name.clone(),
+			kind: PhantomData,
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use futures_util::TryStreamExt;
+
+	use crate::{
+		backend::{Backend, MockBackend},
+		testing::TestSettings,
+		Error, Starchart,
+	};
+
+	#[tokio::test]
+	async fn table_handle() -> Result<(), Error> {
+		let chart = Starchart::new(MockBackend::new()).await?;
+		chart.create_table("table").await?;
+
+		let table = chart.table::<TestSettings>("table");
+		assert_eq!(table.name(), "table");
+
+		let mut settings = TestSettings::default();
+		table.insert(&settings).await?;
+
+		settings.value = "changed".to_owned();
+		table.insert(&settings).await?;
+		assert_eq!(table.get(&1_u32).await?, Some(settings.clone()));
+
+		table
+			.insert(&TestSettings {
+				id: 2,
+				..TestSettings::default()
+			})
+			.await?;
+
+		let mut keys = table
+			.iter()
+			.map_ok(|(key, _)| key)
+			.try_collect::<Vec<_>>()
+			.await?;
+		keys.sort();
+		assert_eq!(keys, ["1", "2"]);
+
+		assert!(table.remove(&2_u32).await?);
+		assert!(!table.remove(&2_u32).await?);
+		assert_eq!(table.get(&2_u32).await?, None);
+
+		Ok(())
+	}
+}
@@ -0,0 +1,55 @@
+use serde::{Deserialize, Serialize};
+
+use crate::IndexEntry;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TestSettings {
+	pub id: u32,
+	pub value: String,
+	pub array: Vec<u8>,
+	pub opt: Option<f64>,
+}
+
+impl Default for TestSettings {
+	fn default() -> Self {
+		Self {
+			id: 1,
+			value: "hello, world!".to_owned(),
+			array: vec![1, 2, 3, 4, 5],
+			opt: Some(4.2),
+		}
+	}
+}
+
+impl IndexEntry for TestSettings {
+	type Key = u32;
+
+	fn key(&self) -> &Self::Key {
+		&self.id
+	}
+}
+
+#[derive(Debug, Default, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Profile {
+	pub id: u32,
+	pub email: String,
+	pub settings: ProfileSettings,
+}
+
+#[derive(Debug, Default, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ProfileSettings {
+	pub theme: String,
+	pub notifications: bool,
+}
+
+impl IndexEntry for Profile {
+	type Key = u32;
+
+	fn key(&self) -> &Self::Key {
+		&self.id
+	}
+
+	fn indexes(&self) -> Vec<(&'static str, String)> {
+		vec![("email", self.email.clone())]
+	}
+}
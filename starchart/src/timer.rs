@@ -0,0 +1,288 @@
+//! Timers used to wait without depending on an async runtime, such as between retries.
+
+use std::{
+	collections::BTreeMap,
+	fmt::{Debug, Formatter, Result as FmtResult},
+	future::Future,
+	pin::Pin,
+	sync::{Arc, OnceLock},
+	task::{Context, Poll, Waker},
+	thread,
+	time::{Duration, Instant},
+};
+
+use parking_lot::{Condvar, Mutex};
+
+/// A future that completes once a [`Timer`] has waited long enough.
+pub type Sleep = Pin<Box<dyn Future<Output = ()> + Send + 'static>>;
+
+/// A source of futures that complete after a [`Duration`].
+///
+/// The crate never assumes an async runtime, so anything that waits asks a [`Timer`],
+/// which lets tokio, async-std, or a custom executor provide the sleeping.
+pub trait Timer: Debug + Send + Sync {
+	/// Returns a future that completes once the [`Duration`] has passed.
+	fn sleep(&self, duration: Duration) -> Sleep;
+}
+
+/// A [`Timer`] that waits on a background thread, this is the default.
+///
+/// This works without an async runtime. Every sleep is driven by a single thread shared by the whole
+/// process, which is started on the first sleep, and a sleep that's dropped before it completes is
+/// cancelled. A [`FnTimer`] calling into the runtime's timer should still be preferred when there is one.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct ThreadTimer;
+
+impl ThreadTimer {
+	/// Creates a new [`ThreadTimer`].
+	#[must_use]
+	pub const fn new() -> Self {
+		Self
+	}
+}
+
+impl Timer for ThreadTimer {
+	fn sleep(&self, duration: Duration) -> Sleep {
+		Box::pin(ThreadSleep::new(duration))
+	}
+}
+
+type TimerId = (Instant, u64);
+
+/// The thread driving every [`ThreadTimer`], with the pending sleeps ordered by their deadline.
+struct Driver {
+	timers: Mutex<Timers>,
+	changed: Condvar,
+}
+
+#[derive(Default)]
+struct Timers {
+	pending: BTreeMap<TimerId, Arc<Mutex<SleepState>>>,
+	next: u64,
+}
+
+impl Driver {
+	fn get() -> &'static Self {
+		static DRIVER: OnceLock<Driver> = OnceLock::new();
+
+		DRIVER.get_or_init(|| {
+			thread::Builder::new()
+				.name("starchart-timer".to_owned())
+				.spawn(|| Self::get().run())
+				.expect("failed to spawn the timer thread");
+
+			Self {
+				timers: Mutex::default(),
+				changed: Condvar::new(),
+			}
+		})
+	}
+
+	fn insert(&self, deadline: Instant, state: Arc<Mutex<SleepState>>) -> TimerId {
+		let mut timers = self.timers.lock();
+
+		let id = (deadline, timers.next);
+		timers.next += 1;
+
+		// the thread only needs waking if it's now waiting past the new deadline.
+		let earliest = timers.pending.keys().next().is_none_or(|first| id < *first);
+		timers.pending.insert(id, state);
+
+		drop(timers);
+
+		if earliest {
+			self.changed.notify_one();
+		}
+
+		id
+	}
+
+	fn remove(&self, id: &TimerId) {
+		self.timers.lock().pending.remove(id);
+	}
+
+	fn run(&self) {
+		let mut timers = self.timers.lock();
+
+		loop {
+			let now = Instant::now();
+
+			while let Some(entry) = timers.pending.first_entry() {
+				if entry.key().0 > now {
+					break;
+				}
+
+				let state = entry.remove();
+				let mut state = state.lock();
+				state.done = true;
+				if let Some(waker) = state.waker.take() {
+					waker.wake();
+				}
+			}
+
+			match timers.pending.keys().next() {
+				Some(&(deadline, _)) => {
+					self.changed.wait_until(&mut timers, deadline);
+				}
+				None => self.changed.wait(&mut timers),
+			}
+		}
+	}
+}
+
+#[derive(Default)]
+struct SleepState {
+	done: bool,
+	waker: Option<Waker>,
+}
+
+struct ThreadSleep {
+	state: Arc<Mutex<SleepState>>,
+	id: Option<TimerId>,
+}
+
+impl ThreadSleep {
+	fn new(duration: Duration) -> Self {
+		let state = Arc::new(Mutex::new(SleepState::default()));
+
+		if duration.is_zero() {
+			state.lock().done = true;
+
+			return Self { state, id: None };
+		}
+
+		let id = Driver::get().insert(Instant::now() + duration, state.clone());
+
+		Self {
+			state,
+			id: Some(id),
+		}
+	}
+}
+
+impl Future for ThreadSleep {
+	type Output = ();
+
+	fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+		let mut state = self.state.lock();
+
+		if state.done {
+			Poll::Ready(())
+		} else {
+			state.waker = Some(cx.waker().clone());
+			Poll::Pending
+		}
+	}
+}
+
+impl Drop for ThreadSleep {
+	fn drop(&mut self) {
+		if let Some(id) = self.id.take() {
+			if !self.state.lock().done {
+				Driver::get().remove(&id);
+			}
+		}
+	}
+}
+
+/// A [`Timer`] that gets it's futures from a function, for plugging in a runtime's timer without
+/// writing a type for it.
+///
+/// ```rust
+/// # use starchart::timer::FnTimer;
+/// let timer = FnTimer::new(|duration| {
+///     // return the runtime's timer, such as with `Box::pin(tokio::time::sleep(duration))`
+///     Box::pin(async move { drop(duration) })
+/// });
+/// ```
+#[derive(Clone, Copy)]
+pub struct FnTimer<F>(F);
+
+impl<F> FnTimer<F>
+where
+	F: Fn(Duration) -> Sleep + Send + Sync,
+{
+	/// Creates a new [`FnTimer`] calling the given function.
+	pub const fn new(sleep: F) -> Self {
+		Self(sleep)
+	}
+}
+
+impl<F> Debug for FnTimer<F> {
+	fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+		f.debug_struct("FnTimer").finish_non_exhaustive()
+	}
+}
+
+impl<F> Timer for FnTimer<F>
+where
+	F: Fn(Duration) -> Sleep + Send + Sync,
+{
+	fn sleep(&self, duration: Duration) -> Sleep {
+		(self.0)(duration)
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use std::{
+		fmt::Debug,
+		sync::Arc,
+		time::{Duration, Instant},
+	};
+
+	use futures_executor::block_on;
+	use parking_lot::Mutex;
+	use static_assertions::assert_impl_all;
+
+	use super::{Driver, FnTimer, ThreadSleep, ThreadTimer, Timer};
+
+	assert_impl_all!(ThreadTimer: Timer, Clone, Copy, Debug, Default, Send, Sync);
+
+	#[test]
+	fn thread_timer() {
+		let started = Instant::now();
+
+		block_on(ThreadTimer::new().sleep(Duration::from_millis(10)));
+		assert!(started.elapsed() >= Duration::from_millis(10));
+
+		block_on(ThreadTimer::new().sleep(Duration::ZERO));
+	}
+
+	#[test]
+	fn thread_timer_shared() {
+		let started = Instant::now();
+
+		let sleeps = (1..=20)
+			.rev()
+			.map(|millis| ThreadTimer::new().sleep(Duration::from_millis(millis)));
+		block_on(futures_util::future::join_all(sleeps));
+
+		assert!(started.elapsed() >= Duration::from_millis(20));
+	}
+
+	#[test]
+	fn thread_timer_cancelled() {
+		let sleep = ThreadSleep::new(Duration::from_secs(3600));
+		let id = sleep.id.expect("sleep wasn't scheduled");
+		assert!(Driver::get().timers.lock().pending.contains_key(&id));
+
+		drop(sleep);
+		assert!(!Driver::get().timers.lock().pending.contains_key(&id));
+	}
+
+	#[test]
+	fn fn_timer() {
+		let slept = Arc::new(Mutex::new(Vec::new()));
+		let record = slept.clone();
+		let timer = FnTimer::new(move |duration| {
+			record.lock().push(duration);
+			Box::pin(async {})
+		});
+
+		block_on(timer.sleep(Duration::from_secs(5)));
+
+		assert_eq!(*slept.lock(), [Duration::from_secs(5)]);
+	}
+}
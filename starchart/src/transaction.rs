@@ -0,0 +1,587 @@
+//! Staging multiple writes and committing them atomically.
+
+use std::{
+	error::Error as StdError,
+	fmt::{Debug, Display, Formatter, Result as FmtResult},
+	future::Future,
+	pin::Pin,
+	sync::Arc,
+};
+
+use futures_util::FutureExt;
+use parking_lot::Mutex;
+use serde_json::Value;
+
+use crate::{action::ActionKind, backend::Backend, index::Indexes, Entry, IndexEntry, Key};
+
+type BoxFuture<'a, T> = Pin<Box<dyn Future<Output = T> + Send + 'a>>;
+
+type Undo<B> =
+	Box<dyn for<'a> FnOnce(&'a B) -> BoxFuture<'a, Result<(), <B as Backend>::Error>> + Send>;
+
+type Apply<B> =
+	Box<dyn for<'a> FnOnce(&'a B) -> BoxFuture<'a, Result<Undo<B>, <B as Backend>::Error>> + Send>;
+
+type Encode = Box<dyn Fn() -> Result<Value, serde_json::Error> + Send + Sync>;
+
+/// The future returned from [`TransactionalBackend::commit`].
+pub type CommitFuture<'a, E> = BoxFuture<'a, Result<(), CommitError<E>>>;
+
+/// A [`Backend`] that can commit a set of [`StagedOperation`]s atomically.
+///
+/// The default impl emulates a transaction by applying each operation in order while recording
+/// the previous state of every entry it touches, and restoring that state in reverse if any operation fails.
+/// Backends with native transactions should override [`Self::commit`], the backends in this crate and in
+/// `starchart-backends`, including the Postgres and Sled backends, all use the emulated default.
+pub trait TransactionalBackend: Backend + Sized {
+	/// Applies all of the operations, or none of them.
+	///
+	/// # Errors
+	///
+	/// A [`CommitError::Failed`] error if an operation failed and the operations before it were rolled back,
+	/// or a [`CommitError::RollbackFailed`] error if rolling them back failed as well.
+	fn commit(&self, operations: Vec<StagedOperation<Self>>) -> CommitFuture<'_, Self::Error> {
+		async move {
+			let mut undos = Vec::with_capacity(operations.len());
+
+			for operation in operations {
+				match operation.apply(self).await {
+					Ok(undo) => undos.push(undo),
+					Err(source) => {
+						// every undo is still ran if one fails, to restore as much as possible.
+						let mut rollback = None;
+						for undo in undos.into_iter().rev() {
+							if let Err(e) = undo.revert(self).await {
+								rollback.get_or_insert(e);
+							}
+						}
+
+						return Err(match rollback {
+							Some(rollback) => CommitError::RollbackFailed { source, rollback },
+							None => CommitError::Failed(source),
+						});
+					}
+				}
+			}
+
+			Ok(())
+		}
+		.boxed()
+	}
+}
+
+/// An error committing the operations of a [`Transaction`].
+#[derive(Debug)]
+pub enum CommitError<E> {
+	/// An operation failed, and the operations applied before it were rolled back.
+	Failed(E),
+	/// An operation failed, and rolling back the operations applied before it failed as well,
+	/// so some of them may still be applied.
+	RollbackFailed {
+		/// The error the operation failed with.
+		source: E,
+		/// The first error that restoring an entry failed with.
+		rollback: E,
+	},
+}
+
+impl<E> CommitError<E> {
+	/// Consume the error, returning the error the failed operation raised.
+	pub fn into_source(self) -> E {
+		match self {
+			Self::Failed(source) | Self::RollbackFailed { source, .. } => source,
+		}
+	}
+}
+
+impl<E> Display for CommitError<E> {
+	fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+		match self {
+			Self::Failed(_) => {
+				f.write_str("a staged operation failed and the transaction was rolled back")
+			}
+			Self::RollbackFailed { .. } => f.write_str(
+				"a staged operation failed and the transaction could not be rolled back",
+			),
+		}
+	}
+}
+
+impl<E: StdError + 'static> StdError for CommitError<E> {
+	fn source(&self) -> Option<&(dyn StdError + 'static)> {
+		match self {
+			Self::Failed(source) | Self::RollbackFailed { source, .. } => Some(source),
+		}
+	}
+}
+
+/// A write staged within a [`Transaction`].
+#[must_use = "a staged operation does nothing until it's applied"]
+pub struct StagedOperation<B: Backend> {
+	table: String,
+	key: String,
+	kind: ActionKind,
+	apply: Apply<B>,
+	encode: Option<Encode>,
+	indexes: Option<Indexes>,
+}
+
+impl<B: Backend> StagedOperation<B> {
+	/// The table the operation writes to.
+	#[must_use]
+	pub fn table(&self) -> &str {
+		&self.table
+	}
+
+	/// The key of the entry the operation writes to.
+	#[must_use]
+	pub fn key(&self) -> &str {
+		&self.key
+	}
+
+	/// The kind of write the operation performs.
+	pub const fn kind(&self) -> ActionKind {
+		self.kind
+	}
+
+	/// Encodes the staged entry as JSON for the chart's hooks and the table's history, or [`None`] for deletes.
+	pub(crate) fn encode(&self) -> Option<Result<Value, serde_json::Error>> {
+		self.encode.as_ref().map(|encode| encode())
+	}
+
+	/// The parts of the operation that the chart needs once it's been committed.
+	pub(crate) fn staged_write(&self) -> StagedWrite {
+		StagedWrite {
+			table: self.table.clone(),
+			key: self.key.clone(),
+			kind: self.kind,
+			entry: self.encode(),
+			indexes: self.indexes.clone(),
+		}
+	}
+
+	/// Applies the operation, returning an [`UndoOperation`] that restores the previous state of the entry.
+	///
+	/// # Errors
+	///
+	/// Any errors that the [`Backend`] methods can raise.
+	pub async fn apply(self, backend: &B) -> Result<UndoOperation<B>, B::Error> {
+		(self.apply)(backend).await.map(UndoOperation)
+	}
+}
+
+impl<B: Backend> Debug for StagedOperation<B> {
+	fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+		f.debug_struct("StagedOperation")
+			.field("table", &self.table)
+			.field("key", &self.key)
+			.field("kind", &self.kind)
+			.finish_non_exhaustive()
+	}
+}
+
+/// A [`StagedOperation`] without the write itself, kept to maintain the entry's metadata after it's committed.
+pub(crate) struct StagedWrite {
+	pub table: String,
+	pub key: String,
+	pub kind: ActionKind,
+	/// The staged entry encoded as JSON, or [`None`] for deletes.
+	pub entry: Option<Result<Value, serde_json::Error>>,
+	/// The index values of the staged entry, or [`None`] if the write doesn't maintain indexes.
+	pub indexes: Option<Indexes>,
+}
+
+/// Restores an entry to it's state before a [`StagedOperation`] was applied.
+#[must_use = "an undo operation does nothing until it's reverted"]
+pub struct UndoOperation<B: Backend>(Undo<B>);
+
+impl<B: Backend> UndoOperation<B> {
+	/// Restores the entry to it's previous state.
+	///
+	/// # Errors
+	///
+	/// Any errors that the [`Backend`] methods can raise.
+	pub async fn revert(self, backend: &B) -> Result<(), B::Error> {
+		(self.0)(backend).await
+	}
+}
+
+impl<B: Backend> Debug for UndoOperation<B> {
+	fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+		f.debug_struct("UndoOperation").finish_non_exhaustive()
+	}
+}
+
+/// A handle for staging writes, created by [`Starchart::transaction`].
+///
+/// Nothing is written until the transaction's closure returns successfully. The handle is cheap to clone,
+/// and all clones stage to the same transaction. Staged writes only maintain secondary indexes if they were staged
+/// with [`Self::create_indexed`] or [`Self::update_indexed`], like an action given it's entry with [`Action::set_entry`].
+///
+/// [`Action::set_entry`]: crate::Action::set_entry
+///
+/// [`Starchart::transaction`]: crate::Starchart::transaction
+pub struct Transaction<B: Backend> {
+	operations: Arc<Mutex<Vec<StagedOperation<B>>>>,
+}
+
+impl<B: Backend + 'static> Transaction<B> {
+	pub(crate) fn new() -> Self {
+		Self {
+			operations: Arc::default(),
+		}
+	}
+
+	/// Stages creating an entry, this does nothing if the entry already exists.
+	pub fn create<S: Entry + 'static, K: Key>(&self, table: &str, key: &K, entry: S) -> &Self {
+		self.stage_create(table, key, entry, None)
+	}
+
+	/// Stages creating an entry like [`Self::create`], keeping it's secondary [`IndexEntry::indexes`] up to date.
+	pub fn create_indexed<S: IndexEntry + 'static, K: Key>(
+		&self,
+		table: &str,
+		key: &K,
+		entry: S,
+	) -> &Self {
+		let indexes = entry.indexes();
+
+		self.stage_create(table, key, entry, Some(indexes))
+	}
+
+	/// Stages updating an entry.
+	pub fn update<S: Entry + 'static, K: Key>(&self, table: &str, key: &K, entry: S) -> &Self {
+		self.stage_update(table, key, entry, None)
+	}
+
+	/// Stages updating an entry like [`Self::update`], keeping it's secondary [`IndexEntry::indexes`] up to date.
+	pub fn update_indexed<S: IndexEntry + 'static, K: Key>(
+		&self,
+		table: &str,
+		key: &K,
+		entry: S,
+	) -> &Self {
+		let indexes = entry.indexes();
+
+		self.stage_update(table, key, entry, Some(indexes))
+	}
+
+	/// Stages deleting an entry, the entry type is needed to restore it if the transaction fails.
+	///
+	/// The entry is removed from every index it was recorded in, whether or not it was written with indexes.
+	pub fn delete<S: Entry + 'static, K: Key>(&self, table: &str, key: &K) -> &Self {
+		self.stage(
+			table,
+			key,
+			ActionKind::Delete,
+			None,
+			None,
+			move |backend, table, key| {
+				async move {
+					let previous = backend.get::<S>(&table, &key).await?;
+
+					if previous.is_none() {
+						return Ok(noop());
+					}
+
+					backend.delete(&table, &key).await?;
+
+					Ok(restore::<B, S>(table, key, previous))
+				}
+				.boxed()
+			},
+		)
+	}
+
+	/// Returns the amount of staged operations.
+	#[must_use]
+	pub fn len(&self) -> usize {
+		self.operations.lock().len()
+	}
+
+	/// Checks whether no operations have been staged.
+	#[must_use]
+	pub fn is_empty(&self) -> bool {
+		self.operations.lock().is_empty()
+	}
+
+	pub(crate) fn into_operations(self) -> Vec<StagedOperation<B>> {
+		std::mem::take(&mut *self.operations.lock())
+	}
+
+	fn stage_create<S: Entry + 'static, K: Key>(
+		&self,
+		table: &str,
+		key: &K,
+		entry: S,
+		indexes: Option<Indexes>,
+	) -> &Self {
+		let entry = Arc::new(entry);
+
+		self.stage(
+			table,
+			key,
+			ActionKind::Create,
+			Some(encoder(&entry)),
+			indexes,
+			move |backend, table, key| {
+				async move {
+					if backend.has(&table, &key).await? {
+						return Ok(noop());
+					}
+
+					backend.create(&table, &key, &*entry).await?;
+
+					Ok(restore::<B, S>(table, key, None))
+				}
+				.boxed()
+			},
+		)
+	}
+
+	fn stage_update<S: Entry + 'static, K: Key>(
+		&self,
+		table: &str,
+		key: &K,
+		entry: S,
+		indexes: Option<Indexes>,
+	) -> &Self {
+		let entry = Arc::new(entry);
+
+		self.stage(
+			table,
+			key,
+			ActionKind::Update,
+			Some(encoder(&entry)),
+			indexes,
+			move |backend, table, key| {
+				async move {
+					let previous = backend.get::<S>(&table, &key).await?;
+
+					backend.update(&table, &key, &*entry).await?;
+
+					Ok(restore::<B, S>(table, key, previous))
+				}
+				.boxed()
+			},
+		)
+	}
+
+	fn stage<K, F>(
+		&self,
+		table: &str,
+		key: &K,
+		kind: ActionKind,
+		encode: Option<Encode>,
+		indexes: Option<Indexes>,
+		apply: F,
+	) -> &Self
+	where
+		K: Key,
+		F: for<'a> FnOnce(&'a B, String, String) -> BoxFuture<'a, Result<Undo<B>, B::Error>>
+			+ Send
+			+ 'static,
+	{
+		let table = table.to_owned();
+		let key = key.to_key();
+
+		let operation = StagedOperation {
+			table: table.clone(),
+			key: key.clone(),
+			kind,
+			apply: Box::new(move |backend| apply(backend, table, key)),
+			encode,
+			indexes,
+		};
+
+		self.operations.lock().push(operation);
+
+		self
+	}
+}
+
+impl<B: Backend> Clone for Transaction<B> {
+	fn clone(&self) -> Self {
+		Self {
+			operations: Arc::clone(&self.operations),
+		}
+	}
+}
+
+impl<B: Backend> Debug for Transaction<B> {
+	fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+		f.debug_struct("Transaction")
+			.field("operations", &*self.operations.lock())
+			.finish()
+	}
+}
+
+fn encoder<S: Entry + 'static>(entry: &Arc<S>) -> Encode {
+	let entry = Arc::clone(entry);
+
+	Box::new(move || serde_json::to_value(&*entry))
+}
+
+fn noop<B: Backend>() -> Undo<B> {
+	Box::new(|_| futures_util::future::ok(()).boxed())
+}
+
+fn restore<B: Backend + 'static, S: Entry + 'static>(
+	table: String,
+	key: String,
+	previous: Option<S>,
+) -> Undo<B> {
+	Box::new(move |backend| {
+		async move {
+			match previous {
+				Some(previous) => {
+					if backend.has(&table, &key).await? {
+						backend.update(&table, &key, &previous).await
+					} else {
+						backend.create(&table, &key, &previous).await
+					}
+				}
+				None => backend.delete(&table, &key).await,
+			}
+		}
+		.boxed()
+	})
+}
+
+#[cfg(test)]
+mod tests {
+	use crate::{
+		action::UpdateEntryAction,
+		backend::{Backend, MockBackend},
+		testing::{Profile, TestSettings},
+		Error, Starchart,
+	};
+
+	#[tokio::test]
+	async fn transaction() -> Result<(), Error> {
+		let chart = Starchart::new(MockBackend::new()).await?;
+		chart.create_table("table").await?;
+		chart.create("table", "1", &TestSettings::default()).await?;
+
+		let staged = chart
+			.transaction(|tx| async move {
+				tx.create("table", &2, TestSettings::default())
+					.delete::<TestSettings, _>("table", &1);
+
+				Ok::<_, Error>(tx.len())
+			})
+			.await?;
+
+		assert_eq!(staged, 2);
+		assert!(chart.has("table", "2").await?);
+		assert!(!chart.has("table", "1").await?);
+
+		let failed = chart
+			.transaction(|tx| async move {
+				tx.delete::<TestSettings, _>("table", &2);
+
+				Err::<(), _>(Error::backend(None))
+			})
+			.await;
+
+		assert!(failed.is_err());
+		assert!(chart.has("table", "2").await?);
+
+		let missing = chart
+			.transaction(|tx| async move {
+				tx.create("missing", &1, TestSettings::default());
+
+				Ok::<_, Error>(())
+			})
+			.await;
+
+		assert!(missing.is_err());
+
+		Ok(())
+	}
+
+	#[tokio::test]
+	async fn transaction_indexes() -> Result<(), Error> {
+		let chart = Starchart::new(MockBackend::new()).await?;
+		chart.create_table("profiles").await?;
+
+		let chart = &chart;
+		let find = |email: &'static str| async move {
+			chart
+				.find_by_index::<Profile, _, Vec<_>>("profiles", "email", &email)
+				.await
+				.map(|profiles| profiles.len())
+		};
+		let profile = Profile {
+			id: 1,
+			email: "old@example.com".to_owned(),
+			..Profile::default()
+		};
+
+		let created = profile.clone();
+		chart
+			.transaction(|tx| async move {
+				tx.create_indexed("profiles", &1, created);
+
+				Ok::<_, Error>(())
+			})
+			.await?;
+		assert_eq!(find("old@example.com").await?, 1);
+
+		chart
+			.transaction(|tx| async move {
+				tx.update_indexed(
+					"profiles",
+					&1,
+					Profile {
+						email: "new@example.com".to_owned(),
+						..profile
+					},
+				);
+
+				Ok::<_, Error>(())
+			})
+			.await?;
+		assert_eq!(find("old@example.com").await?, 0);
+		assert_eq!(find("new@example.com").await?, 1);
+
+		chart
+			.transaction(|tx| async move {
+				tx.delete::<Profile, _>("profiles", &1);
+
+				Ok::<_, Error>(())
+			})
+			.await?;
+		assert_eq!(find("new@example.com").await?, 0);
+
+		Ok(())
+	}
+
+	#[tokio::test]
+	async fn transaction_schema() -> Result<(), Error> {
+		let chart = Starchart::new(MockBackend::new()).await?;
+		chart.create_table("table").await?;
+		chart
+			.create("table", "__metadata__", &"corrupt".to_owned())
+			.await?;
+
+		// metadata is only checked with the `metadata` feature.
+		let settings = TestSettings::default();
+		let mut update = UpdateEntryAction::new();
+		update.set_table("table").set_entry(&settings);
+		let checked = update.run_update_entry(&chart).await.is_err();
+
+		let staged = chart
+			.transaction(|tx| async move {
+				tx.create("table", &1, settings);
+
+				Ok::<_, Error>(())
+			})
+			.await;
+
+		assert_eq!(staged.is_err(), checked);
+		assert_eq!(chart.has("table", "1").await?, !checked);
+
+		Ok(())
+	}
+}
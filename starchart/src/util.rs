@@ -1,7 +1,6 @@
-#![allow(clippy::missing_safety_doc)]
+use std::{collections::hash_map::DefaultHasher, hash::Hasher};
 
-#[cfg(not(has_unwrap_unchecked))]
-use std::hint::unreachable_unchecked;
+use serde::Serialize;
 
 #[cfg(feature = "metadata")]
 pub fn is_metadata(key: &str) -> bool {
@@ -13,50 +12,20 @@ pub fn is_metadata(_: &str) -> bool {
 	false
 }
 
-pub unsafe trait InnerUnwrap<T> {
-	unsafe fn inner_unwrap(self) -> T;
-}
-
-#[cfg(not(has_unwrap_unchecked))]
-unsafe impl<T> InnerUnwrap<T> for Option<T> {
-	#[inline]
-	#[track_caller]
-	unsafe fn inner_unwrap(self) -> T {
-		debug_assert!(self.is_some());
-		self.map_or_else(|| unreachable_unchecked(), |v| v)
-	}
-}
-
-#[cfg(has_unwrap_unchecked)]
-unsafe impl<T> InnerUnwrap<T> for Option<T> {
-	#[allow(clippy::inline_always)]
-	#[inline(always)]
-	#[track_caller]
-	unsafe fn inner_unwrap(self) -> T {
-		self.unwrap_unchecked()
+/// Whether the table is one the chart stores it's own metadata in, such as expiry times or revisions.
+///
+/// The internal tables of a namespace are namespaced themselves, so the namespace is skipped.
+pub fn is_internal_table(table: &str) -> bool {
+	match crate::namespace::split(table) {
+		Some((_, table)) => is_internal_table(table),
+		None => table.starts_with("__"),
 	}
 }
 
-#[cfg(not(has_unwrap_unchecked))]
-unsafe impl<T, E> InnerUnwrap<T> for Result<T, E> {
-	#[inline]
-	#[track_caller]
-	unsafe fn inner_unwrap(self) -> T {
-		debug_assert!(self.is_ok());
-		if let Ok(v) = self {
-			v
-		} else {
-			unreachable_unchecked()
-		}
-	}
-}
+/// Hashes the serialized form of a value, for cheaply comparing entries.
+pub fn fingerprint<S: Serialize + ?Sized>(value: &S) -> Option<u64> {
+	let mut hasher = DefaultHasher::new();
+	hasher.write(&serde_json::to_vec(value).ok()?);
 
-#[cfg(has_unwrap_unchecked)]
-unsafe impl<T, E> InnerUnwrap<T> for Result<T, E> {
-	#[allow(clippy::inline_always)]
-	#[inline(always)]
-	#[track_caller]
-	unsafe fn inner_unwrap(self) -> T {
-		self.unwrap_unchecked()
-	}
+	Some(hasher.finish())
 }
@@ -0,0 +1,610 @@
+//! Version counters for optimistic concurrency.
+//!
+//! Versions are tracked per table, starting the first time an entry in the table is read with
+//! [`ReadEntryAction::run_read_versioned`] or updated with [`UpdateEntryAction::expect_version`].
+//! From then on, every write through an [`Action`], a [`Transaction`], or [`Starchart::flush`]
+//! increments the version of the entry it wrote or deleted. Entries that haven't been written since versions
+//! started being tracked are at version 0.
+//!
+//! Tables that keep a history always track versions, starting with their first write, so every retained
+//! revision has the version it was written at. When [`StarchartBuilder::retain_versions`] is set, every
+//! version written is also kept for the retention window, so entries can be read as they were at an earlier point with [`Starchart::read_entry_at`].
+//! Tables can instead keep their own [`HistoryLimit`] with [`StarchartBuilder::keep_history`], and list every
+//! kept [`Revision`] with [`Starchart::history`]. Revisions are stored in a reserved table, so any backend works.
+//!
+//! [`Action`]: crate::Action
+//! [`ReadEntryAction::run_read_versioned`]: crate::action::ReadEntryAction::run_read_versioned
+//! [`UpdateEntryAction::expect_version`]: crate::action::UpdateEntryAction::expect_version
+//! [`Transaction`]: crate::transaction::Transaction
+//! [`Starchart::flush`]: crate::Starchart::flush
+//! [`Starchart::read_entry_at`]: crate::Starchart::read_entry_at
+//! [`StarchartBuilder::retain_versions`]: crate::StarchartBuilder::retain_versions
+//! [`StarchartBuilder::keep_history`]: crate::StarchartBuilder::keep_history
+//! [`Starchart::history`]: crate::Starchart::history
+
+use std::time::{Duration, SystemTime};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+	backend::Backend,
+	expiry::{from_millis, to_millis},
+	Entry,
+};
+
+const VERSION_PREFIX: &str = "__version__";
+
+const RETAINED_PREFIX: &str = "__retained__";
+
+/// An entry read along with it's version.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Versioned<S> {
+	pub(crate) version: u64,
+	pub(crate) entry: S,
+}
+
+impl<S> Versioned<S> {
+	/// The version of the entry when it was read.
+	///
+	/// Pass this to [`UpdateEntryAction::expect_version`] to only update the entry if it hasn't
+	/// been modified since.
+	///
+	/// [`UpdateEntryAction::expect_version`]: crate::action::UpdateEntryAction::expect_version
+	#[must_use]
+	pub const fn version(&self) -> u64 {
+		self.version
+	}
+
+	/// A reference to the entry.
+	#[must_use]
+	pub const fn entry(&self) -> &S {
+		&self.entry
+	}
+
+	/// Consumes the wrapper, returning the entry.
+	#[must_use]
+	pub fn into_entry(self) -> S {
+		self.entry
+	}
+}
+
+/// A point to read an entry at with [`Starchart::read_entry_at`].
+///
+/// [`Starchart::read_entry_at`]: crate::Starchart::read_entry_at
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PointInTime {
+	/// The entry as it was at the given time.
+	Time(SystemTime),
+	/// The entry as it was when it was at the given version.
+	Version(u64),
+}
+
+impl From<SystemTime> for PointInTime {
+	fn from(time: SystemTime) -> Self {
+		Self::Time(time)
+	}
+}
+
+impl From<u64> for PointInTime {
+	fn from(version: u64) -> Self {
+		Self::Version(version)
+	}
+}
+
+/// How many revisions of each entry a table keeps, see [`StarchartBuilder::keep_history`].
+///
+/// [`StarchartBuilder::keep_history`]: crate::StarchartBuilder::keep_history
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HistoryLimit {
+	/// Keeps the given number of the most recent revisions, including the current one.
+	///
+	/// At least one revision is always kept.
+	Count(usize),
+	/// Keeps revisions written within the given window, along with the newest revision written before it,
+	/// as it's what the entry was at the start of the window.
+	Age(Duration),
+}
+
+/// A kept version of an entry, returned from [`Starchart::history`].
+///
+/// [`Starchart::history`]: crate::Starchart::history
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Revision<S> {
+	written_at: SystemTime,
+	version: u64,
+	entry: Option<S>,
+}
+
+impl<S> Revision<S> {
+	/// When the revision was written, as reported by the chart's [`Clock`].
+	///
+	/// [`Clock`]: crate::clock::Clock
+	#[must_use]
+	pub const fn written_at(&self) -> SystemTime {
+		self.written_at
+	}
+
+	/// The version of the entry the revision was written at, which is 0 if versions weren't tracked.
+	#[must_use]
+	pub const fn version(&self) -> u64 {
+		self.version
+	}
+
+	/// A reference to the entry, which is [`None`] if the revision was a delete.
+	#[must_use]
+	pub const fn entry(&self) -> Option<&S> {
+		self.entry.as_ref()
+	}
+
+	/// Consumes the revision, returning the entry.
+	#[must_use]
+	pub fn into_entry(self) -> Option<S> {
+		self.entry
+	}
+}
+
+/// A version of an entry kept for time-travel reads, with [`None`] recording a delete.
+///
+/// The entry is stored as JSON text, so revisions of any entry type can be stored and read back
+/// by backends that aren't self-describing.
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+pub(crate) struct StoredRevision {
+	written_at: u64,
+	version: u64,
+	entry: Option<String>,
+}
+
+impl StoredRevision {
+	fn decode<S: Entry>(self) -> Result<Revision<S>, serde_json::Error> {
+		Ok(Revision {
+			written_at: from_millis(self.written_at),
+			version: self.version,
+			entry: self
+				.entry
+				.as_deref()
+				.map(serde_json::from_str)
+				.transpose()?,
+		})
+	}
+}
+
+/// What a retained read found.
+pub(crate) enum Retained {
+	/// The entry as it was, encoded as JSON, which is [`None`] if it didn't exist.
+	Found(Option<String>),
+	/// The entry hasn't been written since retention started, so it's unchanged.
+	Unchanged,
+	/// The point is older than the retained revisions.
+	Pruned,
+}
+
+/// Returns the name of the table recording versions for the given table.
+pub(crate) fn table_name(table: &str) -> String {
+	[VERSION_PREFIX, table].concat()
+}
+
+/// Returns the current version of the entry at `key`, starting to track versions for the table if needed.
+pub(crate) async fn get<B: Backend>(backend: &B, table: &str, key: &str) -> Result<u64, B::Error> {
+	let version_table = table_name(table);
+
+	backend.ensure_table(&version_table).await?;
+
+	Ok(backend
+		.get::<u64>(&version_table, key)
+		.await?
+		.unwrap_or_default())
+}
+
+/// Returns the current version of the entry at `key`, without starting to track versions for the table.
+pub(crate) async fn current<B: Backend>(
+	backend: &B,
+	table: &str,
+	key: &str,
+) -> Result<u64, B::Error> {
+	let version_table = table_name(table);
+
+	if !backend.has_table(&version_table).await? {
+		return Ok(0);
+	}
+
+	Ok(backend
+		.get::<u64>(&version_table, key)
+		.await?
+		.unwrap_or_default())
+}
+
+/// Increments the version of the entry at `key`, if versions are tracked for the table or `track` is set.
+pub(crate) async fn bump<B: Backend>(
+	backend: &B,
+	table: &str,
+	key: &str,
+	track: bool,
+) -> Result<(), B::Error> {
+	let version_table = table_name(table);
+
+	if track {
+		backend.ensure_table(&version_table).await?;
+	} else if !backend.has_table(&version_table).await? {
+		return Ok(());
+	}
+
+	match backend.get::<u64>(&version_table, key).await? {
+		Some(version) => backend.update(&version_table, key, &(version + 1)).await,
+		None => backend.create(&version_table, key, &1_u64).await,
+	}
+}
+
+/// Forgets the versions and retained revisions of every entry in a deleted table.
+pub(crate) async fn clear_table<B: Backend>(backend: &B, table: &str) -> Result<(), B::Error> {
+	for versions_table in [table_name(table), retained_table_name(table)] {
+		if backend.has_table(&versions_table).await? {
+			backend.delete_table(&versions_table).await?;
+		}
+	}
+
+	Ok(())
+}
+
+/// Moves the versions and retained revisions of a renamed table's entries to it's new name.
+pub(crate) async fn rename_table<B: Backend>(
+	backend: &B,
+	table: &str,
+	new: &str,
+) -> Result<(), B::Error> {
+	clear_table(backend, new).await?;
+
+	for (versions_table, renamed) in [
+		(table_name(table), table_name(new)),
+		(retained_table_name(table), retained_table_name(new)),
+	] {
+		if backend.has_table(&versions_table).await? {
+			backend.rename_table(&versions_table, &renamed).await?;
+		}
+	}
+
+	Ok(())
+}
+
+/// Returns the name of the table retaining previous versions for the given table.
+pub(crate) fn retained_table_name(table: &str) -> String {
+	[RETAINED_PREFIX, table].concat()
+}
+
+/// Retains the entry just written at `key`, encoded as JSON with [`None`] recording a delete,
+/// dropping revisions that fell outside of the limit.
+///
+/// The revision is recorded at the entry's current version, without starting to track versions for the table.
+pub(crate) async fn retain<B: Backend>(
+	backend: &B,
+	table: &str,
+	key: &str,
+	entry: Option<String>,
+	now: SystemTime,
+	limit: HistoryLimit,
+) -> Result<(), B::Error> {
+	let retained_table = retained_table_name(table);
+
+	backend.ensure_table(&retained_table).await?;
+
+	let existing = backend
+		.get::<Vec<StoredRevision>>(&retained_table, key)
+		.await?;
+	let mut revisions = existing.clone().unwrap_or_default();
+
+	revisions.push(StoredRevision {
+		written_at: to_millis(now),
+		version: current(backend, table, key).await?,
+		entry,
+	});
+
+	let outside = match limit {
+		HistoryLimit::Count(count) => revisions.len().saturating_sub(count.max(1)),
+		HistoryLimit::Age(window) => {
+			let cutoff = to_millis(now.checked_sub(window).unwrap_or(SystemTime::UNIX_EPOCH));
+
+			revisions
+				.iter()
+				.take_while(|revision| revision.written_at < cutoff)
+				.count()
+				.saturating_sub(1)
+		}
+	};
+	revisions.drain(..outside);
+
+	if existing.is_some() {
+		backend.update(&retained_table, key, &revisions).await
+	} else {
+		backend.create(&retained_table, key, &revisions).await
+	}
+}
+
+/// Finds the retained revision of the entry at `key` for the given point.
+pub(crate) async fn retained<B: Backend>(
+	backend: &B,
+	table: &str,
+	key: &str,
+	at: PointInTime,
+) -> Result<Retained, B::Error> {
+	let retained_table = retained_table_name(table);
+
+	if !backend.has_table(&retained_table).await? {
+		return Ok(Retained::Unchanged);
+	}
+
+	let revisions = match backend
+		.get::<Vec<StoredRevision>>(&retained_table, key)
+		.await?
+	{
+		Some(revisions) if !revisions.is_empty() => revisions,
+		_ => return Ok(Retained::Unchanged),
+	};
+
+	let found = match at {
+		PointInTime::Time(time) => {
+			let millis = to_millis(time);
+
+			revisions
+				.into_iter()
+				.take_while(|revision| revision.written_at <= millis)
+				.last()
+		}
+		PointInTime::Version(version) => revisions
+			.into_iter()
+			.rev()
+			.find(|revision| revision.version == version),
+	};
+
+	Ok(found.map_or(Retained::Pruned, |revision| Retained::Found(revision.entry)))
+}
+
+/// Returns every retained revision of the entry at `key`, oldest first.
+pub(crate) async fn history<B: Backend, S: Entry>(
+	backend: &B,
+	table: &str,
+	key: &str,
+) -> Result<Result<Vec<Revision<S>>, serde_json::Error>, B::Error> {
+	let retained_table = retained_table_name(table);
+
+	if !backend.has_table(&retained_table).await? {
+		return Ok(Ok(Vec::new()));
+	}
+
+	let revisions = backend
+		.get::<Vec<StoredRevision>>(&retained_table, key)
+		.await?
+		.unwrap_or_default();
+
+	Ok(revisions.into_iter().map(StoredRevision::decode).collect())
+}
+
+#[cfg(test)]
+mod tests {
+	use std::time::Duration;
+
+	use super::{HistoryLimit, PointInTime};
+	use crate::{
+		action::{
+			ActionRunError, ActionRunErrorType, CreateEntryAction, DeleteEntryAction,
+			ReadEntryAction, UpdateEntryAction,
+		},
+		backend::{Backend, MockBackend},
+		clock::ManualClock,
+		error::ErrorType,
+		testing::TestSettings,
+		Error, Starchart,
+	};
+
+	#[tokio::test]
+	async fn read_entry_at() -> Result<(), Error> {
+		let clock = ManualClock::default();
+		let chart = Starchart::builder(MockBackend::new())
+			.clock(clock.clone())
+			.retain_versions(Duration::from_secs(90))
+			.build()
+			.await?;
+		chart.create_table("table").await?;
+
+		let original = TestSettings::default();
+		let updated = TestSettings {
+			opt: Some(1.5),
+			..TestSettings::default()
+		};
+
+		clock.advance(Duration::from_secs(100));
+		let created_at = chart.clock().now();
+		CreateEntryAction::for_table("table")
+			.with_entry(&original)
+			.run_create_entry(&chart)
+			.await?;
+
+		clock.advance(Duration::from_secs(10));
+		let updated_at = chart.clock().now();
+		UpdateEntryAction::for_table("table")
+			.with_entry(&updated)
+			.run_update_entry(&chart)
+			.await?;
+
+		clock.advance(Duration::from_secs(10));
+		let deleted_at = chart.clock().now();
+		DeleteEntryAction::<TestSettings>::for_table("table")
+			.with_key(&1_u32)
+			.run_delete_entry(&chart)
+			.await?;
+
+		let read = |at: PointInTime| chart.read_entry_at::<TestSettings, _, _>("table", &1_u32, at);
+
+		assert_eq!(read(created_at.into()).await?, Some(original.clone()));
+		assert_eq!(
+			read((updated_at - Duration::from_secs(1)).into()).await?,
+			Some(original.clone())
+		);
+		assert_eq!(read(updated_at.into()).await?, Some(updated.clone()));
+		assert_eq!(read(deleted_at.into()).await?, None);
+		assert_eq!(read(1.into()).await?, Some(original.clone()));
+		assert_eq!(read(2.into()).await?, Some(updated));
+
+		let err = read((created_at - Duration::from_secs(1)).into())
+			.await
+			.unwrap_err();
+		assert!(matches!(err.kind(), ErrorType::NotRetained));
+
+		clock.advance(Duration::from_secs(150));
+		let recreated = TestSettings::default();
+		CreateEntryAction::for_table("table")
+			.with_entry(&recreated)
+			.run_create_entry(&chart)
+			.await?;
+
+		let err = read(created_at.into()).await.unwrap_err();
+		assert!(matches!(err.kind(), ErrorType::NotRetained));
+		assert_eq!(read(deleted_at.into()).await?, None);
+
+		let chart = Starchart::new(MockBackend::new()).await?;
+		chart.create_table("table").await?;
+		let err = chart
+			.read_entry_at::<TestSettings, _, _>("table", &1_u32, created_at)
+			.await
+			.unwrap_err();
+		assert!(matches!(err.kind(), ErrorType::NotRetained));
+
+		Ok(())
+	}
+
+	#[tokio::test]
+	async fn history() -> Result<(), Error> {
+		let clock = ManualClock::default();
+		let chart = Starchart::builder(MockBackend::new())
+			.clock(clock.clone())
+			.keep_history("table", HistoryLimit::Count(2))
+			.build()
+			.await?;
+		chart.create_table("table").await?;
+		chart.create_table("other").await?;
+
+		assert_eq!(chart.history_limit("table"), Some(HistoryLimit::Count(2)));
+		assert_eq!(chart.history_limit("other"), None);
+
+		assert!(chart
+			.history::<TestSettings, _>("table", &1_u32)
+			.await?
+			.is_empty());
+
+		let mut settings = TestSettings::default();
+		CreateEntryAction::for_table("table")
+			.with_entry(&settings)
+			.run_create_entry(&chart)
+			.await?;
+
+		for value in ["second", "third"] {
+			clock.advance(Duration::from_secs(10));
+			settings.value = value.to_owned();
+			UpdateEntryAction::for_table("table")
+				.with_entry(&settings)
+				.run_update_entry(&chart)
+				.await?;
+		}
+
+		let history = chart.history::<TestSettings, _>("table", &1_u32).await?;
+		assert_eq!(
+			history
+				.iter()
+				.map(|revision| revision.entry().map(|entry| entry.value.as_str()))
+				.collect::<Vec<_>>(),
+			[Some("second"), Some("third")]
+		);
+		assert_eq!(
+			history[0].written_at() + Duration::from_secs(10),
+			history[1].written_at()
+		);
+		assert_eq!(
+			chart
+				.read_entry_at::<TestSettings, _, _>("table", &1_u32, history[0].written_at())
+				.await?
+				.map(|entry| entry.value),
+			Some("second".to_owned())
+		);
+
+		DeleteEntryAction::<TestSettings>::for_table("table")
+			.with_key(&1_u32)
+			.run_delete_entry(&chart)
+			.await?;
+
+		let history = chart.history::<TestSettings, _>("table", &1_u32).await?;
+		assert_eq!(history.len(), 2);
+		assert_eq!(history[1].entry(), None);
+
+		let err = chart
+			.history::<TestSettings, _>("other", &1_u32)
+			.await
+			.unwrap_err();
+		assert!(matches!(err.kind(), ErrorType::NotRetained));
+
+		Ok(())
+	}
+
+	#[tokio::test]
+	async fn versions() -> Result<(), Error> {
+		let chart = Starchart::new(MockBackend::new()).await?;
+		chart.create_table("table").await?;
+
+		let mut settings = TestSettings::default();
+
+		let mut action = CreateEntryAction::new();
+		action.set_table("table").set_entry(&settings);
+		action.run_create_entry(&chart).await?;
+
+		let id = settings.id;
+		let read = || {
+			let mut action = ReadEntryAction::<TestSettings>::new();
+			action.set_table("table").set_key(&id);
+			action.run_read_versioned(&chart)
+		};
+
+		// versions start being tracked on the first versioned read
+		let versioned = read().await?.unwrap();
+		assert_eq!(versioned.version(), 0);
+
+		settings.opt = None;
+		let mut action = UpdateEntryAction::new();
+		action
+			.set_table("table")
+			.set_entry(&settings)
+			.expect_version(versioned.version());
+		action.run_update_entry(&chart).await?;
+
+		assert_eq!(read().await?.map(|versioned| versioned.version()), Some(1));
+
+		// a stale version is rejected
+		let mut action = UpdateEntryAction::new();
+		action
+			.set_table("table")
+			.set_entry(&settings)
+			.expect_version(versioned.version());
+		let err = action.run_update_entry(&chart).await.unwrap_err();
+		let source = err
+			.into_source()
+			.unwrap()
+			.downcast::<ActionRunError>()
+			.unwrap();
+		assert!(matches!(
+			source.kind(),
+			ActionRunErrorType::Conflict {
+				expected: 0,
+				found: 1
+			}
+		));
+
+		// deleting and recreating an entry doesn't reset it's version
+		let mut action = DeleteEntryAction::<TestSettings>::new();
+		action.set_table("table").set_key(&settings.id);
+		action.run_delete_entry(&chart).await?;
+
+		let mut action = CreateEntryAction::new();
+		action.set_table("table").set_entry(&settings);
+		action.run_create_entry(&chart).await?;
+
+		assert_eq!(read().await?.map(|versioned| versioned.version()), Some(3));
+
+		Ok(())
+	}
+}
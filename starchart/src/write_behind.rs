@@ -0,0 +1,236 @@
+//! Writes queued to be flushed to the backend later, coalescing repeated writes to the same key.
+
+use std::{
+	collections::HashMap,
+	fmt::{Debug, Formatter, Result as FmtResult},
+	future::Future,
+	iter, mem,
+	pin::Pin,
+	sync::Arc,
+};
+
+use futures_util::FutureExt;
+use parking_lot::Mutex;
+
+use crate::{
+	action::{ActionError, CreateEntryAction, CreateMode},
+	backend::Backend,
+	index::Indexes,
+	stats::WriteBehindStats,
+	Entry, Starchart,
+};
+
+type WriteFuture<'a> = Pin<Box<dyn Future<Output = Result<(), ActionError>> + Send + 'a>>;
+
+type PendingKey = (Arc<str>, Arc<str>);
+
+/// A type-erased entry waiting to be written.
+trait PendingWrite<B: Backend>: Send + Sync {
+	fn write<'a>(
+		&'a self,
+		chart: &'a Starchart<B>,
+		table: &'a str,
+		key: &'a str,
+	) -> WriteFuture<'a>;
+}
+
+/// A queued entry, along with the indexer to keep it's secondary indexes up to date, if any.
+struct Queued<S> {
+	entry: S,
+	indexer: Option<fn(&S) -> Indexes>,
+}
+
+impl<B: Backend, S: Entry> PendingWrite<B> for Queued<S> {
+	fn write<'a>(
+		&'a self,
+		chart: &'a Starchart<B>,
+		table: &'a str,
+		key: &'a str,
+	) -> WriteFuture<'a> {
+		// queued writes are ran as creates that replace the entry, so they're validated, indexed and
+		// hooked the same as any other write, and forget any earlier expiry.
+		let mut action = CreateEntryAction::<S>::new();
+		action
+			.set_table(table)
+			.set_key(&key)
+			.set_data(&self.entry)
+			.mode(CreateMode::Replace);
+		action.inner.indexer = self.indexer;
+
+		action.run_create_entry(chart).boxed()
+	}
+}
+
+/// The writes queued on a [`Starchart`], keyed by table and key.
+///
+/// Only the latest write for each key is kept.
+///
+/// [`Starchart`]: crate::Starchart
+pub struct WriteBehind<B: Backend> {
+	pending: Mutex<HashMap<PendingKey, Box<dyn PendingWrite<B>>>>,
+	stats: Mutex<WriteBehindStats>,
+}
+
+impl<B: Backend> WriteBehind<B> {
+	pub fn queue<S: Entry + 'static>(
+		&self,
+		table: Arc<str>,
+		key: Arc<str>,
+		entry: S,
+		indexer: Option<fn(&S) -> Indexes>,
+	) {
+		let replaced = self
+			.pending
+			.lock()
+			.insert((table, key), Box::new(Queued { entry, indexer }))
+			.is_some();
+
+		let mut stats = self.stats.lock();
+		stats.queued += 1;
+		stats.coalesced += u64::from(replaced);
+	}
+
+	pub fn stats(&self) -> WriteBehindStats {
+		WriteBehindStats {
+			pending: self.pending.lock().len(),
+			..*self.stats.lock()
+		}
+	}
+
+	/// Writes every pending entry to the backend.
+	///
+	/// If a write fails, it and every write after it are queued again, unless they've since been replaced.
+	pub async fn flush(&self, chart: &Starchart<B>) -> Result<usize, ActionError> {
+		let pending = mem::take(&mut *self.pending.lock());
+		let mut remaining = pending.into_iter();
+		let mut flushed = 0;
+
+		while let Some(((table, key), entry)) = remaining.next() {
+			if let Err(e) = entry.write(chart, &table, &key).await {
+				self.requeue(iter::once(((table, key), entry)).chain(remaining));
+
+				self.stats.lock().flushed += flushed as u64;
+
+				return Err(e);
+			}
+
+			flushed += 1;
+		}
+
+		self.stats.lock().flushed += flushed as u64;
+
+		Ok(flushed)
+	}
+
+	fn requeue<I>(&self, writes: I)
+	where
+		I: IntoIterator<Item = (PendingKey, Box<dyn PendingWrite<B>>)>,
+	{
+		let mut pending = self.pending.lock();
+
+		for (key, entry) in writes {
+			pending.entry(key).or_insert(entry);
+		}
+	}
+}
+
+impl<B: Backend> Default for WriteBehind<B> {
+	fn default() -> Self {
+		Self {
+			pending: Mutex::default(),
+			stats: Mutex::default(),
+		}
+	}
+}
+
+impl<B: Backend> Debug for WriteBehind<B> {
+	fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+		f.debug_struct("WriteBehind")
+			.field("stats", &self.stats())
+			.finish()
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use crate::{
+		backend::{Backend, MockBackend},
+		testing::{Profile, TestSettings},
+		Error, Starchart,
+	};
+
+	#[tokio::test]
+	async fn write_behind() -> Result<(), Error> {
+		let chart = Starchart::new(MockBackend::new()).await?;
+		chart.create_table("table").await?;
+
+		for value in 0..10 {
+			let settings = TestSettings {
+				value: value.to_string(),
+				..TestSettings::default()
+			};
+			chart.write_behind("table", &1, settings);
+		}
+		chart.write_behind("table", &2, TestSettings::default());
+
+		let stats = chart.write_behind_stats();
+		assert_eq!(stats.pending(), 2);
+		assert_eq!(stats.queued(), 11);
+		assert_eq!(stats.coalesced(), 9);
+		assert!(!chart.has("table", "1").await?);
+
+		assert_eq!(chart.flush().await?, 2);
+
+		assert_eq!(
+			chart
+				.get::<TestSettings>("table", "1")
+				.await?
+				.map(|settings| settings.value),
+			Some("9".to_owned())
+		);
+		assert_eq!(chart.write_behind_stats().pending(), 0);
+		assert_eq!(chart.write_behind_stats().flushed(), 2);
+
+		Ok(())
+	}
+
+	#[tokio::test]
+	async fn write_behind_indexed() -> Result<(), Error> {
+		let chart = Starchart::builder(MockBackend::new())
+			.auto_create_tables(true)
+			.build()
+			.await?;
+
+		let profile = Profile {
+			id: 1,
+			email: "old@example.com".to_owned(),
+			..Profile::default()
+		};
+		chart.write_behind_indexed("profiles", &1, profile.clone());
+		assert_eq!(chart.flush().await?, 1);
+
+		let found = chart
+			.find_by_index::<Profile, _, Vec<_>>("profiles", "email", &"old@example.com")
+			.await?;
+		assert_eq!(found, [profile]);
+
+		let profile = Profile {
+			id: 1,
+			email: "new@example.com".to_owned(),
+			..Profile::default()
+		};
+		chart.write_behind_indexed("profiles", &1, profile.clone());
+		assert_eq!(chart.flush().await?, 1);
+
+		assert!(chart
+			.find_by_index::<Profile, _, Vec<_>>("profiles", "email", &"old@example.com")
+			.await?
+			.is_empty());
+		let found = chart
+			.find_by_index::<Profile, _, Vec<_>>("profiles", "email", &"new@example.com")
+			.await?;
+		assert_eq!(found, [profile]);
+
+		Ok(())
+	}
+}